@@ -0,0 +1,32 @@
+use std::path::Path;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=proto");
+    println!("cargo:rerun-if-changed=migrations");
+
+    // Only the server is needed. Clients can generate their own code from the same file.
+    tonic_build::configure()
+        .build_client(false)
+        .compile(&["proto/wolfgang.proto"], &["proto"])?;
+
+    // The embedded migrations can't be listed, so their names are collected here. This has to be
+    // a complete item, because all generated files are formatted.
+    let mut migrations = Vec::new();
+    for entry in std::fs::read_dir("migrations")? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            migrations.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+
+    migrations.sort();
+
+    let out_dir = std::env::var("OUT_DIR")?;
+    std::fs::write(
+        Path::new(&out_dir).join("migrations.rs"),
+        format!("const MIGRATIONS: &[&str] = &{:?};", migrations),
+    )?;
+
+    Ok(())
+}
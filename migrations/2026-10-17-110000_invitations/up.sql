@@ -0,0 +1,5 @@
+CREATE TABLE invitations (
+    code TEXT NOT NULL PRIMARY KEY,
+    created_by TEXT NOT NULL REFERENCES users(username),
+    used_by TEXT REFERENCES users(username)
+);
@@ -0,0 +1,39 @@
+ALTER TABLE persons
+    DROP CONSTRAINT persons_created_by_fkey,
+    ADD CONSTRAINT persons_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(username);
+
+ALTER TABLE instruments
+    DROP CONSTRAINT instruments_created_by_fkey,
+    ADD CONSTRAINT instruments_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(username);
+
+ALTER TABLE works
+    DROP CONSTRAINT works_created_by_fkey,
+    ADD CONSTRAINT works_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(username);
+
+ALTER TABLE ensembles
+    DROP CONSTRAINT ensembles_created_by_fkey,
+    ADD CONSTRAINT ensembles_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(username);
+
+ALTER TABLE recordings
+    DROP CONSTRAINT recordings_created_by_fkey,
+    ADD CONSTRAINT recordings_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(username);
+
+ALTER TABLE mediums
+    DROP CONSTRAINT mediums_created_by_fkey,
+    ADD CONSTRAINT mediums_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(username);
+
+ALTER TABLE invitations
+    DROP CONSTRAINT invitations_created_by_fkey,
+    ADD CONSTRAINT invitations_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(username);
+
+ALTER TABLE invitations
+    DROP CONSTRAINT invitations_used_by_fkey,
+    ADD CONSTRAINT invitations_used_by_fkey FOREIGN KEY (used_by) REFERENCES users(username);
+
+ALTER TABLE api_keys
+    DROP CONSTRAINT api_keys_username_fkey,
+    ADD CONSTRAINT api_keys_username_fkey FOREIGN KEY (username) REFERENCES users(username) ON DELETE CASCADE;
+
+ALTER TABLE email_changes
+    DROP CONSTRAINT email_changes_username_fkey,
+    ADD CONSTRAINT email_changes_username_fkey FOREIGN KEY (username) REFERENCES users(username) ON DELETE CASCADE;
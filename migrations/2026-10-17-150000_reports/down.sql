@@ -0,0 +1,3 @@
+DROP TABLE report_comments;
+
+DROP TABLE reports;
@@ -0,0 +1,20 @@
+CREATE TABLE reports (
+    id TEXT NOT NULL PRIMARY KEY,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    resolved_by TEXT REFERENCES users(username) ON UPDATE CASCADE,
+    resolved_at TIMESTAMP,
+    resolution TEXT
+);
+
+CREATE TABLE report_comments (
+    id TEXT NOT NULL PRIMARY KEY,
+    report TEXT NOT NULL REFERENCES reports(id) ON DELETE CASCADE,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    text TEXT NOT NULL
+);
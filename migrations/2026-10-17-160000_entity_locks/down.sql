@@ -0,0 +1,5 @@
+ALTER TABLE persons DROP COLUMN locked;
+
+ALTER TABLE works DROP COLUMN locked;
+
+ALTER TABLE recordings DROP COLUMN locked;
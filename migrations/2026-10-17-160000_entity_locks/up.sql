@@ -0,0 +1,5 @@
+ALTER TABLE persons ADD COLUMN locked BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE works ADD COLUMN locked BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE recordings ADD COLUMN locked BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,21 @@
+CREATE TABLE events (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE webhooks (
+    id TEXT NOT NULL PRIMARY KEY,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    entity_types TEXT NOT NULL,
+    kinds TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_event BIGINT NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    next_attempt TIMESTAMP NOT NULL DEFAULT NOW()
+);
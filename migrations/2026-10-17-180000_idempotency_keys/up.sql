@@ -0,0 +1,8 @@
+CREATE TABLE idempotency_keys (
+    key TEXT NOT NULL PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    status SMALLINT,
+    content_type TEXT,
+    body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,17 @@
+ALTER TABLE instrumentations ALTER COLUMN id DROP DEFAULT;
+DROP SEQUENCE instrumentations_id_seq;
+
+ALTER TABLE performances ALTER COLUMN id DROP DEFAULT;
+DROP SEQUENCE performances_id_seq;
+
+ALTER TABLE track_sets ALTER COLUMN id DROP DEFAULT;
+DROP SEQUENCE track_sets_id_seq;
+
+ALTER TABLE tracks ALTER COLUMN id DROP DEFAULT;
+DROP SEQUENCE tracks_id_seq;
+
+ALTER TABLE work_parts ALTER COLUMN id DROP DEFAULT;
+DROP SEQUENCE work_parts_id_seq;
+
+ALTER TABLE work_sections ALTER COLUMN id DROP DEFAULT;
+DROP SEQUENCE work_sections_id_seq;
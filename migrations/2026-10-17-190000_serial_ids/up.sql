@@ -0,0 +1,25 @@
+-- These IDs used to be chosen randomly by the server. Continue after the largest existing ID.
+
+CREATE SEQUENCE instrumentations_id_seq OWNED BY instrumentations.id;
+SELECT setval('instrumentations_id_seq', GREATEST((SELECT MAX(id) FROM instrumentations), 0) + 1, false);
+ALTER TABLE instrumentations ALTER COLUMN id SET DEFAULT nextval('instrumentations_id_seq');
+
+CREATE SEQUENCE performances_id_seq OWNED BY performances.id;
+SELECT setval('performances_id_seq', GREATEST((SELECT MAX(id) FROM performances), 0) + 1, false);
+ALTER TABLE performances ALTER COLUMN id SET DEFAULT nextval('performances_id_seq');
+
+CREATE SEQUENCE track_sets_id_seq OWNED BY track_sets.id;
+SELECT setval('track_sets_id_seq', GREATEST((SELECT MAX(id) FROM track_sets), 0) + 1, false);
+ALTER TABLE track_sets ALTER COLUMN id SET DEFAULT nextval('track_sets_id_seq');
+
+CREATE SEQUENCE tracks_id_seq OWNED BY tracks.id;
+SELECT setval('tracks_id_seq', GREATEST((SELECT MAX(id) FROM tracks), 0) + 1, false);
+ALTER TABLE tracks ALTER COLUMN id SET DEFAULT nextval('tracks_id_seq');
+
+CREATE SEQUENCE work_parts_id_seq OWNED BY work_parts.id;
+SELECT setval('work_parts_id_seq', GREATEST((SELECT MAX(id) FROM work_parts), 0) + 1, false);
+ALTER TABLE work_parts ALTER COLUMN id SET DEFAULT nextval('work_parts_id_seq');
+
+CREATE SEQUENCE work_sections_id_seq OWNED BY work_sections.id;
+SELECT setval('work_sections_id_seq', GREATEST((SELECT MAX(id) FROM work_sections), 0) + 1, false);
+ALTER TABLE work_sections ALTER COLUMN id SET DEFAULT nextval('work_sections_id_seq');
@@ -0,0 +1,10 @@
+-- Fully resolved JSON representations of recordings and mediums. The data is removed whenever
+-- something it depends on changes and rebuilt on the next read. The version is increased on each
+-- change, so that data that was assembled concurrently isn't stored.
+CREATE TABLE read_models (
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    version BIGINT NOT NULL DEFAULT 0,
+    data TEXT,
+    PRIMARY KEY (entity_type, entity_id)
+);
@@ -0,0 +1 @@
+DROP INDEX events_created_by_idx;
@@ -0,0 +1,2 @@
+-- Used for counting the entities a user created recently, see database/quotas.rs.
+CREATE INDEX events_created_by_idx ON events (created_by, created_at);
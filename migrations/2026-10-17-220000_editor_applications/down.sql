@@ -0,0 +1 @@
+DROP TABLE editor_applications;
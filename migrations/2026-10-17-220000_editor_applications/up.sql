@@ -0,0 +1,9 @@
+CREATE TABLE editor_applications (
+    id TEXT NOT NULL PRIMARY KEY,
+    username TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    motivation TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    decided_by TEXT REFERENCES users(username) ON UPDATE CASCADE,
+    decided_at TIMESTAMP,
+    approved BOOLEAN
+);
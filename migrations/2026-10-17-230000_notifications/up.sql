@@ -0,0 +1,16 @@
+CREATE TABLE notifications (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    username TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    entity_type TEXT,
+    entity_id TEXT,
+    reference TEXT,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    read BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX notifications_username_idx ON notifications (username, id);
+
+-- Used for finding the creator of an entity when it is changed by somebody else.
+CREATE INDEX events_entity_idx ON events (entity_type, entity_id);
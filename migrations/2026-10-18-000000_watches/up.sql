@@ -0,0 +1,13 @@
+CREATE TABLE watches (
+    username TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE ON DELETE CASCADE,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    email BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (username, entity_type, entity_id)
+);
+
+CREATE INDEX watches_entity_idx ON watches (entity_type, entity_id);
+
+-- Notifications that should also be sent by mail. This is reset once the mail has been sent.
+ALTER TABLE notifications ADD COLUMN mail_pending BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,9 @@
+-- Mediums that users own physically.
+CREATE TABLE collection_items (
+    username TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE ON DELETE CASCADE,
+    medium TEXT NOT NULL REFERENCES mediums(id) ON DELETE CASCADE,
+    notes TEXT NOT NULL DEFAULT '',
+    condition TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (username, medium)
+);
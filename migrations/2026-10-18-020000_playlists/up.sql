@@ -0,0 +1,22 @@
+-- Playlists that users keep in sync between their devices.
+CREATE TABLE playlists (
+    id TEXT NOT NULL PRIMARY KEY,
+    username TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX playlists_username_idx ON playlists (username);
+
+-- Items of playlists. An item refers to a whole recording or, if a medium is set, to the track
+-- at the given position within that medium. If the medium is deleted, the item falls back to
+-- the whole recording.
+CREATE TABLE playlist_items (
+    playlist TEXT NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+    item_index INTEGER NOT NULL,
+    recording TEXT NOT NULL REFERENCES recordings(id) ON DELETE CASCADE,
+    medium TEXT REFERENCES mediums(id) ON DELETE SET NULL,
+    track_index INTEGER,
+    PRIMARY KEY (playlist, item_index)
+);
@@ -0,0 +1,2 @@
+DROP TABLE plays;
+ALTER TABLE users DROP COLUMN record_plays;
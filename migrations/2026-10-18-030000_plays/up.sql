@@ -0,0 +1,13 @@
+-- Whether plays are stored for a user. This is off by default.
+ALTER TABLE users ADD COLUMN record_plays BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Recordings or tracks users listened to on any of their devices.
+CREATE TABLE plays (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    username TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE ON DELETE CASCADE,
+    recording TEXT NOT NULL REFERENCES recordings(id) ON DELETE CASCADE,
+    medium TEXT REFERENCES mediums(id) ON DELETE SET NULL,
+    track_index INTEGER,
+    played_at TIMESTAMP NOT NULL,
+    UNIQUE (username, recording, played_at)
+);
@@ -0,0 +1,4 @@
+DROP TABLE ratings;
+
+UPDATE read_models SET version = version + 1, data = NULL
+    WHERE entity_type IN ('recording', 'medium');
@@ -0,0 +1,16 @@
+-- Star ratings of recordings with optional reviews. Editors can hide abusive reviews, but the
+-- rating itself still counts.
+CREATE TABLE ratings (
+    recording TEXT NOT NULL REFERENCES recordings(id) ON DELETE CASCADE,
+    username TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE ON DELETE CASCADE,
+    stars INTEGER NOT NULL CHECK (stars BETWEEN 1 AND 5),
+    review TEXT,
+    hidden BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (recording, username)
+);
+
+-- The stored representations of recordings and mediums don't include ratings yet.
+UPDATE read_models SET version = version + 1, data = NULL
+    WHERE entity_type IN ('recording', 'medium');
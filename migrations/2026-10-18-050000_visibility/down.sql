@@ -0,0 +1,6 @@
+ALTER TABLE mediums DROP COLUMN private;
+ALTER TABLE recordings DROP COLUMN private;
+ALTER TABLE works DROP COLUMN private;
+ALTER TABLE instruments DROP COLUMN private;
+ALTER TABLE ensembles DROP COLUMN private;
+ALTER TABLE persons DROP COLUMN private;
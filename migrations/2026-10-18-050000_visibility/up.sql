@@ -0,0 +1,7 @@
+-- Private entities are only visible to the user that created them.
+ALTER TABLE persons ADD COLUMN private BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE ensembles ADD COLUMN private BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE instruments ADD COLUMN private BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE works ADD COLUMN private BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE recordings ADD COLUMN private BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE mediums ADD COLUMN private BOOLEAN NOT NULL DEFAULT FALSE;
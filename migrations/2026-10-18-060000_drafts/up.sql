@@ -0,0 +1,13 @@
+-- Unfinished works and mediums that are only visible to the user that created them. The data is
+-- stored as submitted and only validated when the draft is published.
+CREATE TABLE drafts (
+    entity_type TEXT NOT NULL,
+    id TEXT NOT NULL,
+    username TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE ON DELETE CASCADE,
+    data TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (entity_type, id)
+);
+
+CREATE INDEX drafts_username_idx ON drafts (username);
@@ -0,0 +1,11 @@
+-- Discussions between users about entities. Comments are removed together with their entity.
+CREATE TABLE comments (
+    id TEXT NOT NULL PRIMARY KEY,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    text TEXT NOT NULL
+);
+
+CREATE INDEX comments_entity_idx ON comments (entity_type, entity_id);
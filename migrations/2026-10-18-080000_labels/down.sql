@@ -0,0 +1,2 @@
+ALTER TABLE mediums DROP COLUMN label;
+DROP TABLE labels;
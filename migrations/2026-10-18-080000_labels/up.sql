@@ -0,0 +1,12 @@
+-- Record labels that release mediums.
+CREATE TABLE labels (
+    id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    private BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- If a label is deleted together with everything referring to it, its mediums are kept.
+ALTER TABLE mediums ADD COLUMN label TEXT REFERENCES labels(id) ON DELETE SET NULL;
+
+CREATE INDEX mediums_label_idx ON mediums (label);
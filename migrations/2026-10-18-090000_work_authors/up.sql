@@ -0,0 +1,11 @@
+-- Authors of the texts of works, like librettists or poets. If the part index is NULL, the person
+-- wrote the text of the whole work, otherwise only the text of that part.
+CREATE TABLE work_authors (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    work TEXT NOT NULL REFERENCES works(id) ON DELETE CASCADE,
+    part_index BIGINT,
+    person TEXT NOT NULL REFERENCES persons(id) ON DELETE CASCADE
+);
+
+CREATE INDEX work_authors_work_idx ON work_authors (work);
+CREATE INDEX work_authors_person_idx ON work_authors (person);
@@ -0,0 +1,12 @@
+-- Sung texts of works and their translations. If the part index is NULL, the text belongs to the
+-- whole work, otherwise only to that part.
+CREATE TABLE work_texts (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    work TEXT NOT NULL REFERENCES works(id) ON DELETE CASCADE,
+    part_index BIGINT,
+    language TEXT NOT NULL,
+    translation BOOLEAN NOT NULL DEFAULT FALSE,
+    text TEXT NOT NULL
+);
+
+CREATE INDEX work_texts_work_idx ON work_texts (work);
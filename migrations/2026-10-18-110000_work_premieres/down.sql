@@ -0,0 +1,4 @@
+ALTER TABLE works DROP COLUMN dedication;
+ALTER TABLE works DROP COLUMN premiere_performers;
+ALTER TABLE works DROP COLUMN premiere_place;
+ALTER TABLE works DROP COLUMN premiere_date;
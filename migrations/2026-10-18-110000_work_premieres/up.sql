@@ -0,0 +1,6 @@
+-- The first performance of a work and to whom it is dedicated. Dates may be incomplete, so they
+-- are stored like "1808", "1808-12" or "1808-12-22".
+ALTER TABLE works ADD COLUMN premiere_date TEXT;
+ALTER TABLE works ADD COLUMN premiere_place TEXT;
+ALTER TABLE works ADD COLUMN premiere_performers TEXT;
+ALTER TABLE works ADD COLUMN dedication TEXT;
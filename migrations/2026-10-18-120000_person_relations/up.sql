@@ -0,0 +1,12 @@
+-- Relations between persons. For "teacher", the first person taught the related one. The other
+-- kinds are symmetric and stored with the smaller ID first.
+CREATE TABLE person_relations (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    person TEXT NOT NULL REFERENCES persons(id) ON DELETE CASCADE,
+    related TEXT NOT NULL REFERENCES persons(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    UNIQUE (person, related, kind)
+);
+
+CREATE INDEX person_relations_related_idx ON person_relations (related);
@@ -0,0 +1,3 @@
+ALTER TABLE works DROP COLUMN period;
+ALTER TABLE persons DROP COLUMN period;
+DROP TABLE periods;
@@ -0,0 +1,24 @@
+-- Musical periods like the Baroque that persons and works can be assigned to. These are managed
+-- by editors, so they start out with the most common ones.
+CREATE TABLE periods (
+    id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    start_year INTEGER,
+    end_year INTEGER
+);
+
+INSERT INTO periods (id, name, start_year, end_year) VALUES
+    ('medieval', 'Medieval', NULL, 1400),
+    ('renaissance', 'Renaissance', 1400, 1600),
+    ('baroque', 'Baroque', 1600, 1750),
+    ('classical', 'Classical', 1750, 1820),
+    ('romantic', 'Romantic', 1820, 1910),
+    ('modern', 'Modern', 1900, 1975),
+    ('contemporary', 'Contemporary', 1975, NULL);
+
+-- Periods that are still in use can't be deleted.
+ALTER TABLE persons ADD COLUMN period TEXT REFERENCES periods(id);
+ALTER TABLE works ADD COLUMN period TEXT REFERENCES periods(id);
+
+CREATE INDEX persons_period_idx ON persons (period);
+CREATE INDEX works_period_idx ON works (period);
@@ -0,0 +1,3 @@
+ALTER TABLE work_parts DROP COLUMN duration;
+ALTER TABLE work_parts DROP COLUMN tempo;
+ALTER TABLE work_parts DROP COLUMN key;
@@ -0,0 +1,5 @@
+-- Details on the parts of works like "C minor", "Allegro con brio" and how long they usually take
+-- in milliseconds.
+ALTER TABLE work_parts ADD COLUMN key TEXT;
+ALTER TABLE work_parts ADD COLUMN tempo TEXT;
+ALTER TABLE work_parts ADD COLUMN duration INTEGER;
@@ -0,0 +1,3 @@
+ALTER TABLE tracks DROP COLUMN work_index;
+
+DROP TABLE recording_works;
@@ -0,0 +1,14 @@
+-- Further works that are performed within a recording in addition to its main work, like encores.
+-- They are ordered by their ID.
+CREATE TABLE recording_works (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    recording TEXT NOT NULL REFERENCES recordings(id) ON DELETE CASCADE,
+    work TEXT NOT NULL REFERENCES works(id)
+);
+
+CREATE INDEX recording_works_recording_idx ON recording_works (recording);
+CREATE INDEX recording_works_work_idx ON recording_works (work);
+
+-- The index of the work of the recording that a track belongs to. 0 is the main work and 1 is the
+-- first additional work.
+ALTER TABLE tracks ADD COLUMN work_index INTEGER NOT NULL DEFAULT 0;
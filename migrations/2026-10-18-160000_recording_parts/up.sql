@@ -0,0 +1,3 @@
+-- The comma separated indices of the parts of the main work that a recording covers. NULL means
+-- that the complete work was recorded.
+ALTER TABLE recordings ADD COLUMN parts TEXT;
@@ -0,0 +1 @@
+DROP TABLE medium_relations;
@@ -0,0 +1,12 @@
+-- Relations between mediums, like "the medium is a reissue of the related medium". Each relation
+-- is stored once in this direction.
+CREATE TABLE medium_relations (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    medium TEXT NOT NULL REFERENCES mediums(id) ON DELETE CASCADE,
+    related TEXT NOT NULL REFERENCES mediums(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    UNIQUE (medium, related, kind)
+);
+
+CREATE INDEX medium_relations_related_idx ON medium_relations (related);
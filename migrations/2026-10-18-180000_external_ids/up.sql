@@ -0,0 +1,14 @@
+-- Identifiers of mediums and recordings within external services like streaming platforms. Each
+-- row belongs to exactly one medium or recording.
+CREATE TABLE external_ids (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    medium TEXT REFERENCES mediums(id) ON DELETE CASCADE,
+    recording TEXT REFERENCES recordings(id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    external_id TEXT NOT NULL,
+    UNIQUE (source, external_id),
+    CHECK ((medium IS NULL) <> (recording IS NULL))
+);
+
+CREATE INDEX external_ids_medium_idx ON external_ids (medium);
+CREATE INDEX external_ids_recording_idx ON external_ids (recording);
@@ -0,0 +1,3 @@
+ALTER TABLE tracks DROP COLUMN accuraterip_v2;
+ALTER TABLE tracks DROP COLUMN accuraterip_v1;
+ALTER TABLE tracks DROP COLUMN flac_md5;
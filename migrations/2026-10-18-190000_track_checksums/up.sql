@@ -0,0 +1,9 @@
+-- Checksums of the audio of tracks as lowercase hexadecimal strings. These can be used to check
+-- whether ripped files correspond to a medium.
+ALTER TABLE tracks ADD COLUMN flac_md5 TEXT;
+ALTER TABLE tracks ADD COLUMN accuraterip_v1 TEXT;
+ALTER TABLE tracks ADD COLUMN accuraterip_v2 TEXT;
+
+CREATE INDEX tracks_flac_md5_idx ON tracks (flac_md5);
+CREATE INDEX tracks_accuraterip_v1_idx ON tracks (accuraterip_v1);
+CREATE INDEX tracks_accuraterip_v2_idx ON tracks (accuraterip_v2);
@@ -0,0 +1,12 @@
+-- Snapshots of deleted entities, so that administrators can restore them. Items are removed after
+-- a retention period.
+CREATE TABLE trash (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    deleted_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    deleted_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX trash_deleted_at_idx ON trash (deleted_at);
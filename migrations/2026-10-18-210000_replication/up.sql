@@ -0,0 +1,6 @@
+-- The latest revision of the changefeed of each upstream server that was applied locally.
+CREATE TABLE replication_state (
+    upstream TEXT NOT NULL PRIMARY KEY,
+    revision BIGINT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
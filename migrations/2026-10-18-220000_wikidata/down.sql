@@ -0,0 +1,8 @@
+DROP TABLE wikidata_checks;
+DROP TABLE wikidata_candidates;
+
+DELETE FROM external_ids WHERE person IS NOT NULL;
+ALTER TABLE external_ids DROP CONSTRAINT external_ids_check;
+ALTER TABLE external_ids DROP COLUMN person;
+ALTER TABLE external_ids ADD CONSTRAINT external_ids_check
+    CHECK ((medium IS NULL) <> (recording IS NULL));
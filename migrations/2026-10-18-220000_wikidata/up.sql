@@ -0,0 +1,27 @@
+-- Persons can be identified by Wikidata items as well.
+ALTER TABLE external_ids ADD COLUMN person TEXT REFERENCES persons(id) ON DELETE CASCADE;
+ALTER TABLE external_ids DROP CONSTRAINT external_ids_check;
+ALTER TABLE external_ids ADD CONSTRAINT external_ids_check
+    CHECK (num_nonnulls(medium, recording, person) = 1);
+
+CREATE INDEX external_ids_person_idx ON external_ids (person);
+
+-- Wikidata items that might describe a person. They are found automatically and have to be
+-- confirmed or rejected by an editor.
+CREATE TABLE wikidata_candidates (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    person TEXT NOT NULL REFERENCES persons(id) ON DELETE CASCADE,
+    item TEXT NOT NULL,
+    label TEXT NOT NULL,
+    description TEXT,
+    birth_year INTEGER,
+    death_year INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (person, item)
+);
+
+-- Persons that were already searched on Wikidata, so that they aren't searched again.
+CREATE TABLE wikidata_checks (
+    person TEXT NOT NULL PRIMARY KEY REFERENCES persons(id) ON DELETE CASCADE,
+    checked_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+-- The title of the page of a work on IMSLP, where scores of the work can be found.
+ALTER TABLE works ADD COLUMN imslp TEXT;
@@ -0,0 +1,15 @@
+-- Photos of persons and ensembles. The files are stored within the image directory and named
+-- after the ID of the image. Each person or ensemble has at most one image.
+CREATE TABLE images (
+    id TEXT NOT NULL PRIMARY KEY,
+    person TEXT UNIQUE REFERENCES persons(id) ON DELETE CASCADE,
+    ensemble TEXT UNIQUE REFERENCES ensembles(id) ON DELETE CASCADE,
+    license TEXT NOT NULL,
+    attribution TEXT NOT NULL,
+    source TEXT,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL,
+    CHECK ((person IS NULL) <> (ensemble IS NULL))
+);
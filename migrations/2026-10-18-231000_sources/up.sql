@@ -0,0 +1,17 @@
+-- Where the data of an entity comes from and under which license it may be used, e.g. "Liner
+-- notes" or "Data derived from MusicBrainz". Each source is recorded as a change of the entity, so
+-- it belongs to the revision of that change. Sources are kept when their entity is deleted, so
+-- that they are still there if it is restored from the trash.
+CREATE TABLE sources (
+    id TEXT NOT NULL PRIMARY KEY,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    revision BIGINT NOT NULL REFERENCES events(id),
+    description TEXT NOT NULL,
+    license TEXT,
+    url TEXT,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX sources_entity_idx ON sources (entity_type, entity_id);
@@ -0,0 +1,12 @@
+-- Titles of works in different languages, e.g. "The Magic Flute" for "Die Zauberflöte". At most
+-- one title per work is marked as the original title.
+CREATE TABLE work_titles (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    work TEXT NOT NULL REFERENCES works(id) ON DELETE CASCADE,
+    language TEXT NOT NULL,
+    title TEXT NOT NULL,
+    original BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (work, language)
+);
+
+CREATE UNIQUE INDEX work_titles_original_idx ON work_titles (work) WHERE original;
@@ -0,0 +1,2 @@
+DROP TABLE work_section_titles;
+DROP TABLE work_part_titles;
@@ -0,0 +1,21 @@
+-- Titles of parts and sections of works in different languages. Like the parts themselves, they
+-- are identified by their position within the work.
+CREATE TABLE work_part_titles (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    work TEXT NOT NULL REFERENCES works(id) ON DELETE CASCADE,
+    part_index BIGINT NOT NULL,
+    language TEXT NOT NULL,
+    title TEXT NOT NULL,
+    original BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (work, part_index, language)
+);
+
+CREATE TABLE work_section_titles (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    work TEXT NOT NULL REFERENCES works(id) ON DELETE CASCADE,
+    section_index BIGINT NOT NULL,
+    language TEXT NOT NULL,
+    title TEXT NOT NULL,
+    original BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (work, section_index, language)
+);
@@ -0,0 +1,2 @@
+-- The name a work is popularly known by, e.g. "Moonlight Sonata" or "Eroica".
+ALTER TABLE works ADD COLUMN nickname TEXT;
@@ -0,0 +1,13 @@
+-- How complete and trustworthy the data of an entity is, as judged by an editor. Entities without
+-- a flag haven't been judged yet. Flags are kept when their entity is deleted, so that they are
+-- still there if it is restored from the trash.
+CREATE TABLE quality_flags (
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    level TEXT NOT NULL,
+    updated_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    updated_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (entity_type, entity_id)
+);
+
+CREATE INDEX quality_flags_level_idx ON quality_flags (entity_type, level);
@@ -0,0 +1,9 @@
+-- Field changes to many entities that an editor made at once. The affected entities get their
+-- usual events, while this is the single record of the whole change.
+CREATE TABLE bulk_edits (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    description TEXT NOT NULL,
+    changes TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,6 @@
+-- The state of an entity right after a change, so that earlier versions can be restored. Deletions
+-- don't have a revision. Changes from before this table was added don't have one either.
+CREATE TABLE revisions (
+    event BIGINT NOT NULL PRIMARY KEY REFERENCES events(id) ON DELETE CASCADE,
+    data TEXT NOT NULL
+);
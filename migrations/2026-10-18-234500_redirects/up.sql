@@ -0,0 +1,12 @@
+-- Entities that were merged into other ones. Requests for the old IDs are redirected to the
+-- entities that replaced them.
+CREATE TABLE redirects (
+    entity_type TEXT NOT NULL,
+    old_id TEXT NOT NULL,
+    new_id TEXT NOT NULL,
+    created_by TEXT NOT NULL REFERENCES users(username) ON UPDATE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (entity_type, old_id)
+);
+
+CREATE INDEX redirects_new_id_idx ON redirects (entity_type, new_id);
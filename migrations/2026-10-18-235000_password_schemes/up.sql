@@ -0,0 +1,3 @@
+-- How the password hash of a user was created. Existing hashes were created using sodiumoxide and
+-- are replaced on the next login.
+ALTER TABLE users ADD COLUMN password_scheme TEXT NOT NULL DEFAULT 'sodiumoxide';
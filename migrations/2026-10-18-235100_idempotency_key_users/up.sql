@@ -0,0 +1,5 @@
+-- Idempotency keys are chosen by clients, so they only have to be unique for each user. Requests
+-- without a valid token share the empty username.
+ALTER TABLE idempotency_keys DROP CONSTRAINT idempotency_keys_pkey;
+ALTER TABLE idempotency_keys ADD COLUMN username TEXT NOT NULL DEFAULT '';
+ALTER TABLE idempotency_keys ADD PRIMARY KEY (username, key);
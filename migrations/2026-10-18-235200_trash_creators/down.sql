@@ -0,0 +1 @@
+ALTER TABLE trash DROP COLUMN created_by;
@@ -0,0 +1,5 @@
+-- The user that created or last changed a deleted entity, so that it can be restored with its
+-- original owner. Existing items are attributed to the user that deleted them.
+ALTER TABLE trash ADD COLUMN created_by TEXT REFERENCES users(username) ON UPDATE CASCADE;
+UPDATE trash SET created_by = deleted_by;
+ALTER TABLE trash ALTER COLUMN created_by SET NOT NULL;
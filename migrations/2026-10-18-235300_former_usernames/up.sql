@@ -0,0 +1,6 @@
+-- Names that users had before they were renamed. They can't be taken again, so that login tokens
+-- that were issued for the old name don't become valid for another user.
+CREATE TABLE former_usernames (
+    username TEXT PRIMARY KEY,
+    renamed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,158 @@
+use crate::database;
+use crate::database::{ReadDbPool, Scope};
+use crate::error::ServerError;
+use crate::routes::authenticate;
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method};
+use actix_web::{web, Error};
+use anyhow::{anyhow, Result};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// Requests that are allowed without authentication even on private servers, so that clients
+/// can find out about the server, log in and confirm email addresses.
+const PUBLIC_PATHS: [&str; 3] = ["/info", "/captcha", "/account/email/confirm"];
+
+/// Who is allowed to read data from the server.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadAccess {
+    /// Everybody can read public data.
+    Public,
+
+    /// Only authenticated users can read anything.
+    Authenticated,
+}
+
+impl ReadAccess {
+    /// Get the read access policy from the environment variable "WOLFGANG_READ_ACCESS". Possible
+    /// values are "public" (the default) and "authenticated".
+    pub fn from_env() -> Result<Self> {
+        let access = match std::env::var("WOLFGANG_READ_ACCESS") {
+            Ok(access) => match access.as_str() {
+                "public" => ReadAccess::Public,
+                "authenticated" => ReadAccess::Authenticated,
+                _ => return Err(anyhow!("Unknown read access policy: {}", access)),
+            },
+            Err(_) => ReadAccess::Public,
+        };
+
+        Ok(access)
+    }
+}
+
+/// Query parameters carrying a token. This is used by the WebSocket endpoint, because browsers
+/// can't set headers for WebSocket connections.
+#[derive(Deserialize, Debug, Clone)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Get the token a request is authenticated with, if any.
+fn get_token(req: &ServiceRequest) -> Option<String> {
+    let authorization = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match authorization {
+        Some(token) => Some(token.to_string()),
+        None if req.path() == "/ws" => web::Query::<TokenQuery>::from_query(req.query_string())
+            .ok()
+            .and_then(|query| query.into_inner().token),
+        None => None,
+    }
+}
+
+/// Check whether a request has to be authenticated under the provided policy. Other requests
+/// authenticate themselves anyway.
+fn requires_authentication(access: ReadAccess, req: &ServiceRequest) -> bool {
+    access == ReadAccess::Authenticated
+        && matches!(req.method(), &Method::GET | &Method::HEAD)
+        && !PUBLIC_PATHS.contains(&req.path())
+}
+
+/// Middleware that rejects unauthenticated read requests with "401 Unauthorized", if the server
+/// is configured to require authentication for reads.
+pub struct RequireReadAccess;
+
+impl<S> Transform<S> for RequireReadAccess
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireReadAccessMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequireReadAccessMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+/// The service created by [`RequireReadAccess`].
+pub struct RequireReadAccessMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> Service for RequireReadAccessMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        let access = req
+            .app_data::<web::Data<ReadAccess>>()
+            .map(|access| *access.get_ref())
+            .unwrap_or(ReadAccess::Public);
+
+        if !requires_authentication(access, &req) {
+            return Box::pin(service.borrow_mut().call(req));
+        }
+
+        let token = get_token(&req);
+        let pool = req.app_data::<web::Data<ReadDbPool>>().cloned();
+
+        Box::pin(async move {
+            let (token, pool) = match (token, pool) {
+                (Some(token), Some(pool)) => (token, pool),
+                (None, _) => return Ok(req.error_response(ServerError::Unauthorized)),
+                (_, None) => return Ok(req.error_response(ServerError::Internal)),
+            };
+
+            let authenticated = database::block(move || {
+                let conn = pool.get()?;
+                let authenticated = authenticate(&conn, &token, Scope::Read).is_ok();
+                Ok::<_, ServerError>(authenticated)
+            })
+            .await;
+
+            match authenticated {
+                Ok(true) => (),
+                Ok(false) => return Ok(req.error_response(ServerError::Unauthorized)),
+                Err(error) => return Ok(req.error_response(ServerError::from(error))),
+            }
+
+            let future = service.borrow_mut().call(req);
+            future.await
+        })
+    }
+}
@@ -0,0 +1,10 @@
+use anyhow::Result;
+use wolfgang::cli;
+
+fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    sodiumoxide::init().expect("Failed to init crypto library!");
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    cli::run(&args)
+}
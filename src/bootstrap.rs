@@ -0,0 +1,138 @@
+use crate::database::{self, with_transaction, DbConn, DbPool, Role, UserInsertion};
+use crate::error::ServerError;
+use crate::routes::{hash_password, PasswordParams};
+use crate::validation::Validate;
+use anyhow::{anyhow, Error, Result};
+use rand::Rng;
+use std::sync::Mutex;
+
+/// Data for a new administrator created using the command line, the environment or the setup
+/// token.
+#[derive(Debug, Clone)]
+pub struct AdminCreation {
+    pub username: String,
+    pub password: String,
+}
+
+/// A token that allows creating the first administrator using the API. It is generated on
+/// startup, if there are no users yet, and can only be used once.
+pub struct SetupToken(Mutex<Option<String>>);
+
+impl SetupToken {
+    /// A setup token that doesn't accept anything.
+    pub fn none() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    /// Create the first administrator, if the token matches the expected one. The token is used
+    /// up, once this succeeded.
+    pub fn redeem<F>(&self, token: &str, create: F) -> Result<(), ServerError>
+    where
+        F: FnOnce() -> Result<(), ServerError>,
+    {
+        let mut expected = self.0.lock().unwrap();
+
+        let valid = match expected.as_deref() {
+            Some(expected) => sodiumoxide::utils::memcmp(expected.as_bytes(), token.as_bytes()),
+            None => false,
+        };
+
+        if !valid {
+            return Err(ServerError::Forbidden);
+        }
+
+        create()?;
+        *expected = None;
+
+        Ok(())
+    }
+}
+
+/// Make sure that a new server can be administrated. If there are no users yet, an administrator
+/// is created from the environment variables "WOLFGANG_ADMIN_USER" and "WOLFGANG_ADMIN_PASSWORD".
+/// If they are not set, a one-time setup token is generated and logged instead, which allows
+/// creating the administrator using the "/setup" route.
+pub fn run(pool: &DbPool, password_params: &PasswordParams) -> Result<SetupToken> {
+    let conn = pool.get()?;
+
+    if database::has_users(&conn)? {
+        return Ok(SetupToken::none());
+    }
+
+    match std::env::var("WOLFGANG_ADMIN_USER") {
+        Ok(username) => {
+            let password = std::env::var("WOLFGANG_ADMIN_PASSWORD").or(Err(anyhow!(
+                "WOLFGANG_ADMIN_PASSWORD is required for WOLFGANG_ADMIN_USER"
+            )))?;
+
+            let admin = AdminCreation { username, password };
+            create_first_admin(&conn, &admin, password_params)?;
+
+            Ok(SetupToken::none())
+        }
+        Err(_) => {
+            let secret: [u8; 32] = rand::thread_rng().gen();
+            let token = base64::encode_config(secret, base64::URL_SAFE_NO_PAD);
+
+            println!(
+                "There are no users yet. Create the first administrator using the setup token {}",
+                token
+            );
+
+            Ok(SetupToken(Mutex::new(Some(token))))
+        }
+    }
+}
+
+/// Create the first administrator of a new server. This fails with [`ServerError::Forbidden`], if
+/// there are users already. The check and the creation happen within one transaction that
+/// prevents other users from being added in the meantime, e.g. using open registration.
+pub fn create_first_admin(
+    conn: &DbConn,
+    data: &AdminCreation,
+    password_params: &PasswordParams,
+) -> Result<()> {
+    with_transaction(conn, |tx| {
+        database::lock_users(tx)?;
+
+        if database::has_users(tx.conn())? {
+            return Err(Error::new(ServerError::Forbidden));
+        }
+
+        create_admin(tx.conn(), data, password_params)
+    })
+}
+
+/// Create a new user with administrator rights. This is needed for setting up a new server,
+/// because the API only allows administrators to give rights to users.
+pub fn create_admin(
+    conn: &DbConn,
+    data: &AdminCreation,
+    password_params: &PasswordParams,
+) -> Result<()> {
+    if let Err(ServerError::Invalid(errors)) = data.validate() {
+        let messages: Vec<String> = errors
+            .errors
+            .iter()
+            .map(|error| format!("{}: {}", error.field, error.message))
+            .collect();
+
+        return Err(anyhow!("Invalid data!\n\n{}", messages.join("\n")));
+    }
+
+    if database::get_user(conn, &data.username)?.is_some() {
+        return Err(anyhow!("The username is already taken: {}", data.username));
+    }
+
+    let insertion = UserInsertion {
+        password_hash: hash_password(&data.password, password_params)?,
+        email: None,
+    };
+
+    database::insert_user(conn, &data.username, &insertion)?;
+    database::set_user_role(conn, &data.username, Role::Admin, true)?;
+
+    println!("Created administrator {}.", data.username);
+
+    Ok(())
+}
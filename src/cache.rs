@@ -0,0 +1,301 @@
+use crate::database;
+use crate::error::ServerError;
+use crate::shared::{RedisPool, SharedState};
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::{web, Error};
+use anyhow::Result;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// How long a cached response may be used. Changes that are not made through this server (e.g.
+/// using the command line) become visible after this time at the latest.
+const RESPONSE_TTL: Duration = Duration::from_secs(60);
+
+/// The maximum number of cached responses. Once this is reached, outdated responses are purged
+/// and, if that is not enough, the oldest response will be dropped.
+const MAX_RESPONSES: usize = 1000;
+
+/// The prefix for keys of responses stored in Redis.
+const REDIS_PREFIX: &str = "wolfgang:response:";
+
+/// The Redis key of the current revision.
+const REDIS_REVISION_KEY: &str = "wolfgang:revision";
+
+/// A cached response.
+struct Entry {
+    /// The response data before applying any query parameters.
+    value: Arc<Value>,
+
+    /// The revision of the cache at the time the data was read.
+    revision: u64,
+
+    /// When the response was cached.
+    created: Instant,
+}
+
+impl Entry {
+    /// Check whether the response may still be used at the provided revision.
+    fn is_valid(&self, revision: u64, now: Instant) -> bool {
+        self.revision == revision && now.duration_since(self.created) <= RESPONSE_TTL
+    }
+}
+
+/// The content of a [`ResponseCache`] that is kept locally.
+#[derive(Default)]
+struct State {
+    revision: u64,
+    entries: HashMap<String, Entry>,
+}
+
+/// Where the cached responses are kept.
+enum Backend {
+    Local(Mutex<State>),
+    Redis(RedisPool),
+}
+
+/// A small cache for responses of expensive read endpoints. The cache has a revision that is
+/// increased whenever data is changed. Responses are only used, if they were read at the current
+/// revision and haven't expired yet.
+pub struct ResponseCache {
+    backend: Backend,
+}
+
+impl ResponseCache {
+    /// Create a new empty cache using the provided backend for shared state.
+    pub fn new(state: &SharedState) -> Self {
+        let backend = match state {
+            SharedState::Local => Backend::Local(Mutex::new(State::default())),
+            SharedState::Redis(pool) => Backend::Redis(pool.clone()),
+        };
+
+        Self { backend }
+    }
+
+    /// Get the current revision.
+    pub fn revision(&self) -> Result<u64> {
+        let revision = match &self.backend {
+            Backend::Local(state) => state.lock().unwrap().revision,
+            Backend::Redis(pool) => {
+                let mut conn = pool.get()?;
+                redis::cmd("GET")
+                    .arg(REDIS_REVISION_KEY)
+                    .query::<Option<u64>>(&mut *conn)?
+                    .unwrap_or(0)
+            }
+        };
+
+        Ok(revision)
+    }
+
+    /// Get a cached response, if it is still valid.
+    pub fn get(&self, key: &str) -> Result<Option<Arc<Value>>> {
+        let value = match &self.backend {
+            Backend::Local(state) => {
+                let state = state.lock().unwrap();
+
+                match state.entries.get(key) {
+                    Some(entry) if entry.is_valid(state.revision, Instant::now()) => {
+                        Some(entry.value.clone())
+                    }
+                    _ => None,
+                }
+            }
+            Backend::Redis(pool) => {
+                let revision = self.revision()?;
+                let mut conn = pool.get()?;
+
+                let data: Option<String> = redis::cmd("GET")
+                    .arg(redis_key(revision, key))
+                    .query(&mut *conn)?;
+
+                match data {
+                    Some(data) => Some(Arc::new(serde_json::from_str(&data)?)),
+                    None => None,
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Store a response that was read at the provided revision. If the data has changed in the
+    /// meantime, the response will not be used.
+    pub fn insert(&self, key: String, revision: u64, value: Arc<Value>) -> Result<()> {
+        let state = match &self.backend {
+            Backend::Local(state) => state,
+            Backend::Redis(pool) => {
+                let mut conn = pool.get()?;
+
+                // Responses are stored per revision, so outdated ones are never read again.
+                redis::cmd("SET")
+                    .arg(redis_key(revision, &key))
+                    .arg(serde_json::to_string(&*value)?)
+                    .arg("EX")
+                    .arg(RESPONSE_TTL.as_secs())
+                    .query::<()>(&mut *conn)?;
+
+                return Ok(());
+            }
+        };
+
+        let mut state = state.lock().unwrap();
+
+        if revision != state.revision {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+
+        if state.entries.len() >= MAX_RESPONSES {
+            state
+                .entries
+                .retain(|_, entry| entry.is_valid(revision, now));
+        }
+
+        if state.entries.len() >= MAX_RESPONSES {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.created)
+                .map(|(key, _)| key.clone());
+
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+
+        state.entries.insert(
+            key,
+            Entry {
+                value,
+                revision,
+                created: now,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Invalidate all cached responses.
+    pub fn invalidate(&self) -> Result<()> {
+        match &self.backend {
+            Backend::Local(state) => {
+                let mut state = state.lock().unwrap();
+                state.revision += 1;
+                state.entries.clear();
+            }
+            Backend::Redis(pool) => {
+                let mut conn = pool.get()?;
+                redis::cmd("INCR")
+                    .arg(REDIS_REVISION_KEY)
+                    .query::<()>(&mut *conn)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the Redis key for a response that was read at the provided revision.
+fn redis_key(revision: u64, key: &str) -> String {
+    format!("{}{}:{}", REDIS_PREFIX, revision, key)
+}
+
+/// Get the response identified by `key` from the cache or compute it using `read` within a
+/// blocking context and remember it.
+pub async fn cached<T, F>(
+    cache: &web::Data<ResponseCache>,
+    key: String,
+    read: F,
+) -> Result<Arc<Value>, ServerError>
+where
+    T: Serialize + Send + 'static,
+    F: FnOnce() -> Result<T, ServerError> + Send + 'static,
+{
+    let cache = cache.clone();
+
+    let value = database::block(move || {
+        if let Some(value) = cache.get(&key)? {
+            return Ok(value);
+        }
+
+        let revision = cache.revision()?;
+        let data = read()?;
+        let value = Arc::new(serde_json::to_value(&data).or(Err(ServerError::Internal))?);
+
+        cache.insert(key, revision, value.clone())?;
+
+        Ok(value)
+    })
+    .await?;
+
+    Ok(value)
+}
+
+/// Middleware that invalidates the [`ResponseCache`] after each successful request that may have
+/// changed data, i.e. any request except for GET and HEAD requests.
+pub struct InvalidateCache;
+
+impl<S> Transform<S> for InvalidateCache
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = InvalidateCacheMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(InvalidateCacheMiddleware { service })
+    }
+}
+
+/// The service created by [`InvalidateCache`].
+pub struct InvalidateCacheMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service for InvalidateCacheMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let cache = match req.method() {
+            &Method::GET | &Method::HEAD => None,
+            _ => req.app_data::<web::Data<ResponseCache>>().cloned(),
+        };
+
+        let future = self.service.call(req);
+
+        Box::pin(async move {
+            let res = future.await?;
+
+            if let Some(cache) = cache {
+                if res.status().is_success() {
+                    if let Err(error) = web::block(move || cache.invalidate()).await {
+                        println!("{:?}", error);
+                    }
+                }
+            }
+
+            Ok(res)
+        })
+    }
+}
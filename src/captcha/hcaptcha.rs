@@ -0,0 +1,50 @@
+use super::{Captcha, CaptchaBackend};
+use anyhow::Result;
+use serde::Deserialize;
+
+/// The endpoint for verifying hCaptcha responses.
+const VERIFY_URL: &str = "https://hcaptcha.com/siteverify";
+
+/// The relevant parts of the response of the hCaptcha verification endpoint.
+#[derive(Deserialize, Debug, Clone)]
+struct VerifyResponse {
+    success: bool,
+}
+
+/// A captcha backend using the hCaptcha service. The client shows the hCaptcha widget using the
+/// provided site key and sends the resulting token as the answer. The captcha ID is ignored.
+pub struct HCaptcha {
+    site_key: String,
+    secret: String,
+}
+
+impl HCaptcha {
+    /// Create a new hCaptcha backend using the provided site key and secret.
+    pub fn new(site_key: String, secret: String) -> Self {
+        Self { site_key, secret }
+    }
+}
+
+impl CaptchaBackend for HCaptcha {
+    fn name(&self) -> &'static str {
+        "hcaptcha"
+    }
+
+    fn generate_captcha(&self) -> Result<Captcha> {
+        Ok(Captcha::Hcaptcha {
+            site_key: self.site_key.clone(),
+        })
+    }
+
+    fn check_captcha(&self, _: &str, answer: &str) -> Result<bool> {
+        let response: VerifyResponse = ureq::post(VERIFY_URL)
+            .send_form(&[
+                ("secret", &self.secret),
+                ("sitekey", &self.site_key),
+                ("response", answer),
+            ])?
+            .into_json()?;
+
+        Ok(response.success)
+    }
+}
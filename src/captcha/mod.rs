@@ -0,0 +1,83 @@
+use crate::shared::SharedState;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::sync::Arc;
+
+pub mod hcaptcha;
+pub use hcaptcha::*;
+
+pub mod pow;
+pub use pow::*;
+
+pub mod questions;
+pub use questions::*;
+
+mod store;
+use store::*;
+
+/// Response body data for captcha requests. The type of the captcha is indicated by the "type"
+/// field and determines what the client has to send as the answer.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Captcha {
+    /// A question that has to be answered.
+    #[serde(rename_all = "camelCase")]
+    Question { id: String, question: String },
+
+    /// An hCaptcha that has to be solved using the widget for the provided site key.
+    #[serde(rename_all = "camelCase")]
+    Hcaptcha { site_key: String },
+
+    /// A proof-of-work challenge, see [`ProofOfWorkCaptcha`].
+    #[serde(rename_all = "camelCase")]
+    ProofOfWork {
+        id: String,
+        challenge: String,
+        difficulty: u32,
+    },
+}
+
+/// A generator and checker for captchas.
+pub trait CaptchaBackend: Send + Sync {
+    /// The name of the backend as used for selecting it, e.g. "questions".
+    fn name(&self) -> &'static str;
+
+    /// Create a new captcha for a client.
+    fn generate_captcha(&self) -> Result<Captcha>;
+
+    /// Check whether the provided answer is correct. A captcha can only be answered once.
+    fn check_captcha(&self, id: &str, answer: &str) -> Result<bool>;
+
+    /// Delete captchas that have expired. This will be called regularly.
+    fn purge(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Create the captcha backend that is selected using the environment variable "WOLFGANG_CAPTCHA".
+/// Possible values are "questions" (the default), "hcaptcha" and "pow". The hCaptcha backend
+/// additionally requires "WOLFGANG_HCAPTCHA_SITE_KEY" and "WOLFGANG_HCAPTCHA_SECRET", while the
+/// proof-of-work difficulty can be set using "WOLFGANG_POW_DIFFICULTY". Pending challenges are
+/// kept using the provided backend for shared state.
+pub fn from_env(state: &SharedState) -> Result<Arc<dyn CaptchaBackend>> {
+    let backend = std::env::var("WOLFGANG_CAPTCHA").unwrap_or_else(|_| String::from("questions"));
+
+    let backend: Arc<dyn CaptchaBackend> = match backend.as_str() {
+        "questions" => Arc::new(QuestionCaptcha::new(state)),
+        "hcaptcha" => Arc::new(HCaptcha::new(
+            std::env::var("WOLFGANG_HCAPTCHA_SITE_KEY")?,
+            std::env::var("WOLFGANG_HCAPTCHA_SECRET")?,
+        )),
+        "pow" => {
+            let difficulty = match std::env::var("WOLFGANG_POW_DIFFICULTY") {
+                Ok(difficulty) => difficulty.parse()?,
+                Err(_) => 20,
+            };
+
+            Arc::new(ProofOfWorkCaptcha::new(state, difficulty))
+        }
+        _ => return Err(anyhow!("Unknown captcha backend: {}", backend)),
+    };
+
+    Ok(backend)
+}
@@ -0,0 +1,81 @@
+use super::{Captcha, CaptchaBackend, ChallengeStore};
+use crate::shared::SharedState;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+/// A captcha backend requiring the client to solve a proof-of-work challenge. The client has to
+/// find an answer, so that the SHA-256 hash of the challenge followed by the answer starts with
+/// the requested number of zero bits. This doesn't stop determined humans, but makes registering
+/// lots of accounts automatically expensive.
+pub struct ProofOfWorkCaptcha {
+    store: ChallengeStore,
+    difficulty: u32,
+}
+
+impl ProofOfWorkCaptcha {
+    /// Create a new proof-of-work captcha backend requiring the provided number of leading zero
+    /// bits.
+    pub fn new(state: &SharedState, difficulty: u32) -> Self {
+        Self {
+            store: ChallengeStore::new(state),
+            difficulty,
+        }
+    }
+}
+
+impl CaptchaBackend for ProofOfWorkCaptcha {
+    fn name(&self) -> &'static str {
+        "pow"
+    }
+
+    fn generate_captcha(&self) -> Result<Captcha> {
+        let mut buffer = uuid::Uuid::encode_buffer();
+        let challenge = uuid::Uuid::new_v4()
+            .to_simple()
+            .encode_lower(&mut buffer)
+            .to_owned();
+
+        let id = self.store.insert(challenge.clone())?;
+
+        Ok(Captcha::ProofOfWork {
+            id,
+            challenge,
+            difficulty: self.difficulty,
+        })
+    }
+
+    fn check_captcha(&self, id: &str, answer: &str) -> Result<bool> {
+        let result = match self.store.take(id)? {
+            Some(challenge) => {
+                let hash = Sha256::new()
+                    .chain(challenge.as_bytes())
+                    .chain(answer.as_bytes())
+                    .finalize();
+
+                leading_zero_bits(&hash) >= self.difficulty
+            }
+            None => false,
+        };
+
+        Ok(result)
+    }
+
+    fn purge(&self) -> Result<()> {
+        self.store.purge()
+    }
+}
+
+/// Count the number of zero bits at the start of a byte string.
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut count = 0;
+
+    for byte in bytes {
+        count += byte.leading_zeros();
+
+        if *byte != 0 {
+            break;
+        }
+    }
+
+    count
+}
@@ -0,0 +1,104 @@
+use super::{Captcha, CaptchaBackend, ChallengeStore};
+use crate::shared::SharedState;
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use rand::seq::SliceRandom;
+
+// TODO/INFO: These hardcoded questions are a placeholder for a future mechanism to autogenerate
+// questions from the database. This will require a easily accissible web interface for Musicus.
+// There may also be another, better solution. However, the current framework of question-answer
+// pairs with randomly generated identifiers will most likely stay in place.
+
+/// A question to identify users as human.
+#[derive(Clone, Debug)]
+struct Question {
+    /// The question that will be sent to the client.
+    pub question: &'static str,
+
+    /// The answer that the client has to provide.
+    pub answer: &'static str,
+}
+
+lazy_static! {
+    /// All available captcha questions.
+    static ref QUESTIONS: Vec<Question> = vec![
+        Question {
+            question: "In welchem Jahr wurde Johannes Brahms geboren?",
+            answer: "1833",
+        },
+        Question {
+            question: "In welchem Jahr ist Johannes Brahms gestorben?",
+            answer: "1897",
+        },
+        Question {
+            question: "In welchem Jahr wurde Ludwig van Beethoven geboren?",
+            answer: "1770",
+        },
+        Question {
+            question: "In welchem Jahr ist Ludwig van Beethoven gestorben?",
+            answer: "1827",
+        },
+        Question {
+            question: "In welchem Jahr wurde Claude Debussy geboren?",
+            answer: "1862",
+        },
+        Question {
+            question: "In welchem Jahr ist Claude Debussy gestorben?",
+            answer: "1918",
+        },
+        Question {
+            question: "In welchem Jahr wurde Sergei Rachmaninow geboren?",
+            answer: "1873",
+        },
+        Question {
+            question: "In welchem Jahr ist Sergei Rachmaninow gestorben?",
+            answer: "1943",
+        },
+    ];
+}
+
+/// A captcha backend asking simple questions on classical music.
+pub struct QuestionCaptcha {
+    store: ChallengeStore,
+}
+
+impl QuestionCaptcha {
+    /// Create a new question captcha backend.
+    pub fn new(state: &SharedState) -> Self {
+        Self {
+            store: ChallengeStore::new(state),
+        }
+    }
+}
+
+impl CaptchaBackend for QuestionCaptcha {
+    fn name(&self) -> &'static str {
+        "questions"
+    }
+
+    fn generate_captcha(&self) -> Result<Captcha> {
+        let question = QUESTIONS
+            .choose(&mut rand::thread_rng())
+            .ok_or_else(|| anyhow!("Failed to get random question!"))?;
+
+        let id = self.store.insert(question.answer.to_owned())?;
+
+        Ok(Captcha::Question {
+            id,
+            question: question.question.to_owned(),
+        })
+    }
+
+    fn check_captcha(&self, id: &str, answer: &str) -> Result<bool> {
+        let result = match self.store.take(id)? {
+            Some(expected) => answer == expected,
+            None => false,
+        };
+
+        Ok(result)
+    }
+
+    fn purge(&self) -> Result<()> {
+        self.store.purge()
+    }
+}
@@ -0,0 +1,160 @@
+use crate::shared::{RedisPool, SharedState};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a challenge stays valid after it has been created.
+const CHALLENGE_TTL: Duration = Duration::from_secs(600);
+
+/// The maximum number of challenges that are kept at the same time. Once this is reached, expired
+/// challenges are purged and, if that is not enough, the oldest challenge will be dropped.
+const MAX_CHALLENGES: usize = 10000;
+
+/// The prefix for keys of challenges stored in Redis.
+const REDIS_PREFIX: &str = "wolfgang:challenge:";
+
+/// A challenge that was handed out to a client and is waiting for an answer.
+struct Entry {
+    /// The data needed to check the answer.
+    value: String,
+
+    /// When the challenge was created.
+    created: Instant,
+}
+
+impl Entry {
+    /// Check whether the challenge is too old to be answered.
+    fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.created) > CHALLENGE_TTL
+    }
+}
+
+/// Where the challenges are kept.
+enum Backend {
+    Local(Mutex<HashMap<String, Entry>>),
+    Redis(RedisPool),
+}
+
+/// A bounded store for challenges that were handed out to clients. Each challenge is identified
+/// by a random ID and can only be taken out once.
+pub struct ChallengeStore {
+    backend: Backend,
+}
+
+impl ChallengeStore {
+    /// Create a new empty store using the provided backend for shared state.
+    pub fn new(state: &SharedState) -> Self {
+        let backend = match state {
+            SharedState::Local => Backend::Local(Mutex::new(HashMap::new())),
+            SharedState::Redis(pool) => Backend::Redis(pool.clone()),
+        };
+
+        Self { backend }
+    }
+
+    /// Add a new challenge and return its randomly generated ID.
+    pub fn insert(&self, value: String) -> Result<String> {
+        let mut buffer = uuid::Uuid::encode_buffer();
+        let id = uuid::Uuid::new_v4()
+            .to_simple()
+            .encode_lower(&mut buffer)
+            .to_owned();
+
+        let entries = match &self.backend {
+            Backend::Local(entries) => entries,
+            Backend::Redis(pool) => {
+                let mut conn = pool.get()?;
+                let key = format!("{}{}", REDIS_PREFIX, id);
+
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("EX")
+                    .arg(CHALLENGE_TTL.as_secs())
+                    .query::<()>(&mut *conn)?;
+
+                return Ok(id);
+            }
+        };
+
+        let entries = &mut entries
+            .lock()
+            .map_err(|_| anyhow!("Failed to aquire lock!"))?;
+
+        let now = Instant::now();
+
+        if entries.len() >= MAX_CHALLENGES {
+            entries.retain(|_, entry| !entry.is_expired(now));
+        }
+
+        if entries.len() >= MAX_CHALLENGES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.created)
+                .map(|(id, _)| id.clone());
+
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(
+            id.clone(),
+            Entry {
+                value,
+                created: now,
+            },
+        );
+
+        Ok(id)
+    }
+
+    /// Remove a challenge from the store and return it, if it exists and hasn't expired yet.
+    pub fn take(&self, id: &str) -> Result<Option<String>> {
+        let entries = match &self.backend {
+            Backend::Local(entries) => entries,
+            Backend::Redis(pool) => {
+                let mut conn = pool.get()?;
+                let key = format!("{}{}", REDIS_PREFIX, id);
+
+                let (value,): (Option<String>,) = redis::pipe()
+                    .atomic()
+                    .get(&key)
+                    .del(&key)
+                    .ignore()
+                    .query(&mut *conn)?;
+
+                return Ok(value);
+            }
+        };
+
+        let entries = &mut entries
+            .lock()
+            .map_err(|_| anyhow!("Failed to aquire lock!"))?;
+
+        let value = match entries.remove(id) {
+            Some(entry) if !entry.is_expired(Instant::now()) => Some(entry.value),
+            _ => None,
+        };
+
+        Ok(value)
+    }
+
+    /// Delete all challenges that have expired. Challenges stored in Redis expire automatically.
+    pub fn purge(&self) -> Result<()> {
+        let entries = match &self.backend {
+            Backend::Local(entries) => entries,
+            Backend::Redis(_) => return Ok(()),
+        };
+
+        let entries = &mut entries
+            .lock()
+            .map_err(|_| anyhow!("Failed to aquire lock!"))?;
+
+        let now = Instant::now();
+        entries.retain(|_, entry| !entry.is_expired(now));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use crate::bootstrap;
+use crate::bootstrap::AdminCreation;
+use crate::database;
+use crate::database::Role;
+use crate::publishing::{generate_signing_key, read_published_dump, DumpPublisher};
+use crate::replication::Replication;
+use crate::routes::PasswordParams;
+use crate::tasks::{read_backup, read_dump, write_backup, write_dump};
+use anyhow::{anyhow, Result};
+use std::io::BufRead;
+
+/// Usage information for the command line interface.
+const USAGE: &str = "Usage: wolfgang-admin COMMAND
+
+Commands:
+  create-admin USERNAME        Create a new administrator. The password is read from the
+                               standard input.
+  promote USERNAME ROLE        Make a user an \"admin\" or an \"editor\"
+  demote USERNAME ROLE         Take the role \"admin\" or \"editor\" away from a user
+  ban USERNAME                 Prevent a user from changing anything
+  unban USERNAME               Allow a banned user to make changes again
+  check [--repair]             Search for dangling references and optionally repair them
+  dump PATH                    Write a JSON dump of all public data to a file
+  backup PATH                  Write a complete backup of the database including users and
+                               private data to a file
+  restore-backup PATH          Replace all data of the database with a backup. The database
+                               must have the same migrations applied.
+  publish-dump                 Publish a signed dump now instead of waiting for the schedule
+  generate-dump-key            Generate a key pair for signing published dumps
+  restore PATH USERNAME        Load a dump into the database on behalf of a user. Entities
+                               are created or updated within a single transaction.
+  import-dump PATH KEY USERNAME
+                               Bootstrap a new instance from a published dump on behalf of a
+                               user. The signature is verified using the base64 encoded
+                               public key of the publishing server.
+  seed USERNAME                Load well-known composers, instruments and works on behalf of
+                               a user. Existing entities are left untouched.
+  migrate [--revert]           Run all pending migrations or revert the latest one. Reverting
+                               requires the \"migrations\" directory of the source code.";
+
+/// Run an administration command.
+pub fn run(args: &[String]) -> Result<()> {
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        ["create-admin", username] => create_admin(username),
+        ["promote", username, role] => set_role(username, parse_role(role)?, true),
+        ["demote", username, role] => set_role(username, parse_role(role)?, false),
+        ["ban", username] => set_role(username, Role::Banned, true),
+        ["unban", username] => set_role(username, Role::Banned, false),
+        ["check"] => check(false),
+        ["check", "--repair"] => check(true),
+        ["dump", path] => dump(path),
+        ["backup", path] => backup(path),
+        ["restore-backup", path] => restore_backup(path),
+        ["publish-dump"] => publish_dump(),
+        ["generate-dump-key"] => generate_dump_key(),
+        ["restore", path, username] => restore(path, username),
+        ["import-dump", path, key, username] => import_dump(path, key, username),
+        ["seed", username] => seed(username),
+        ["migrate"] => migrate(false),
+        ["migrate", "--revert"] => migrate(true),
+        ["help"] | ["--help"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(anyhow!("Invalid arguments!\n\n{}", USAGE)),
+    }
+}
+
+/// Parse a role that can be given to users using "promote" and "demote".
+fn parse_role(role: &str) -> Result<Role> {
+    match Role::parse(role) {
+        Some(role) if role != Role::Banned => Ok(role),
+        _ => Err(anyhow!("Unknown role: {}", role)),
+    }
+}
+
+/// Create a new user with administrator rights. The password is read from the standard input.
+fn create_admin(username: &str) -> Result<()> {
+    eprintln!("Password:");
+
+    let mut password = String::new();
+    std::io::stdin().lock().read_line(&mut password)?;
+
+    let data = AdminCreation {
+        username: username.to_string(),
+        password: password.trim_end_matches(&['\r', '\n'][..]).to_string(),
+    };
+
+    let password_params = PasswordParams::from_env()?;
+    let pool = database::connect()?;
+    let conn = pool.get()?;
+
+    bootstrap::create_admin(&conn, &data, &password_params)
+}
+
+/// Give a role to a user or take it away.
+fn set_role(username: &str, role: Role, value: bool) -> Result<()> {
+    let pool = database::connect()?;
+    let conn = pool.get()?;
+
+    database::set_user_role(&conn, username, role, value)
+        .map_err(|_| anyhow!("Failed to update user: {}", username))?;
+
+    println!("Updated user {}.", username);
+
+    Ok(())
+}
+
+/// Search for dangling references and print them.
+fn check(repair: bool) -> Result<()> {
+    let pool = database::connect()?;
+    let conn = pool.get()?;
+
+    let inconsistencies = database::check_consistency(&conn, repair)?;
+
+    for inconsistency in &inconsistencies {
+        println!("{} {}", inconsistency.id, inconsistency.description);
+    }
+
+    if repair {
+        println!("Repaired {} problems.", inconsistencies.len());
+    } else {
+        println!("Found {} problems.", inconsistencies.len());
+    }
+
+    Ok(())
+}
+
+/// Write a JSON dump of all public data.
+fn dump(path: &str) -> Result<()> {
+    let pool = database::connect()?;
+    write_dump(&pool, path)?;
+
+    println!("Wrote dump to {}.", path);
+
+    Ok(())
+}
+
+/// Write a complete backup of the database.
+fn backup(path: &str) -> Result<()> {
+    let pool = database::connect()?;
+    write_backup(&pool, path)?;
+
+    println!("Wrote backup to {}.", path);
+
+    Ok(())
+}
+
+/// Replace all data of the database with a backup.
+fn restore_backup(path: &str) -> Result<()> {
+    let backup = read_backup(path)?;
+
+    let pool = database::connect()?;
+    let conn = pool.get()?;
+
+    let restored = database::restore_backup(&conn, &backup)?;
+
+    println!("Restored {} rows from {}.", restored, path);
+
+    Ok(())
+}
+
+/// Publish a signed dump using the configured directory and key.
+fn publish_dump() -> Result<()> {
+    let publisher = DumpPublisher::from_env()?
+        .ok_or_else(|| anyhow!("WOLFGANG_PUBLIC_DUMP_PATH is not set!"))?;
+
+    let pool = database::connect()?;
+    let dump = publisher.publish(&pool)?;
+
+    println!("Published {} ({}).", dump.name, dump.sha256);
+
+    Ok(())
+}
+
+/// Print a new key pair for signing published dumps.
+fn generate_dump_key() -> Result<()> {
+    let (secret_key, public_key) = generate_signing_key();
+
+    println!("WOLFGANG_DUMP_SIGNING_KEY={}", secret_key);
+    println!("Public key: {}", public_key);
+
+    Ok(())
+}
+
+/// Load a JSON dump into the database.
+fn restore(path: &str, username: &str) -> Result<()> {
+    let dump = read_dump(path)?;
+
+    let pool = database::connect()?;
+    let conn = pool.get()?;
+
+    let user = database::get_user(&conn, username)?
+        .ok_or_else(|| anyhow!("User not found: {}", username))?;
+
+    let restored = database::restore_dump(&conn, &dump, &user)?;
+
+    println!("Restored {} entities from {}.", restored, path);
+
+    Ok(())
+}
+
+/// Load a published dump into an empty database after verifying its signature and checksum.
+/// Entity IDs are kept, so that the new instance can be used as a mirror.
+fn import_dump(path: &str, key: &str, username: &str) -> Result<()> {
+    let dump = read_published_dump(path, key)?;
+
+    // Nothing is imported, if replication couldn't continue afterwards.
+    let replication = Replication::from_env()?;
+    if let Some(replication) = &replication {
+        replication.check_dump_import()?;
+    }
+
+    let pool = database::connect()?;
+    let conn = pool.get()?;
+
+    if database::get_last_event_id(&conn)? > 0 {
+        return Err(anyhow!(
+            "The database already contains data. Use \"restore\" to load a dump anyway."
+        ));
+    }
+
+    let user = database::get_user(&conn, username)?
+        .ok_or_else(|| anyhow!("User not found: {}", username))?;
+
+    let restored = database::restore_dump(&conn, &dump, &user)?;
+
+    println!(
+        "Imported {} entities from {} up to revision {}.",
+        restored, path, dump.revision
+    );
+
+    // A mirror of the publishing server doesn't need to replicate what the dump already contains.
+    if let Some(replication) = replication {
+        replication.start_after(&conn, dump.revision)?;
+        println!(
+            "Replication will continue after revision {}.",
+            dump.revision
+        );
+    }
+
+    Ok(())
+}
+
+/// Load the seed dataset, so that new deployments aren't completely empty.
+fn seed(username: &str) -> Result<()> {
+    let pool = database::connect()?;
+    let conn = pool.get()?;
+
+    let user = database::get_user(&conn, username)?
+        .ok_or_else(|| anyhow!("User not found: {}", username))?;
+
+    let created = database::load_seed(&conn, &user)?;
+
+    println!("Created {} entities.", created);
+
+    Ok(())
+}
+
+/// Run all pending migrations or revert the latest one.
+fn migrate(revert: bool) -> Result<()> {
+    let pool = database::connect_without_migrations()?;
+    let conn = pool.get()?;
+
+    if revert {
+        let version = database::revert_migration(&conn)?;
+        println!("Reverted migration {}.", version);
+    } else {
+        database::run_migrations(&conn)?;
+        println!("Ran all pending migrations.");
+    }
+
+    Ok(())
+}
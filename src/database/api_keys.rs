@@ -0,0 +1,140 @@
+use super::schema::{api_keys, users};
+use super::{generate_id, DbConn, Scope, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use rand::Rng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// The prefix of all API keys. This is used to distinguish them from login tokens.
+pub const API_KEY_PREFIX: &str = "wolfgang_";
+
+/// A long-lived key allowing an automated client to act on behalf of a user.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Table data for an [`ApiKey`]. Only a hash of the actual key is stored.
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "api_keys"]
+struct ApiKeyRow {
+    pub id: String,
+    pub username: String,
+    pub name: String,
+    pub key_hash: String,
+    pub scopes: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(row: ApiKeyRow) -> ApiKey {
+        ApiKey {
+            id: row.id,
+            name: row.name,
+            scopes: row
+                .scopes
+                .split(',')
+                .map(|scope| scope.to_string())
+                .collect(),
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Create a new API key for a user. This returns the stored information on the key as well as
+/// the key itself, which can't be retrieved later. The user has to be allowed to grant all of the
+/// requested scopes and they have to be among the `granted` scopes of the token used for the
+/// request, so that a key can't have more permissions than the token creating it.
+pub fn insert_api_key(
+    conn: &DbConn,
+    user: &User,
+    granted: &[String],
+    name: &str,
+    scopes: &[String],
+) -> Result<(ApiKey, String)> {
+    let allowed = !scopes.is_empty()
+        && scopes.iter().all(|scope| match Scope::parse(scope) {
+            Some(parsed) => user.may_grant(parsed) && granted.contains(scope),
+            None => false,
+        });
+
+    if !allowed {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let id = generate_id();
+
+    let secret: [u8; 32] = rand::thread_rng().gen();
+    let key = format!(
+        "{}{}",
+        API_KEY_PREFIX,
+        base64::encode_config(secret, base64::URL_SAFE_NO_PAD)
+    );
+
+    let row = ApiKeyRow {
+        id,
+        username: user.username.clone(),
+        name: name.to_string(),
+        key_hash: hash_api_key(&key),
+        scopes: scopes.join(","),
+        created_at: chrono::Utc::now().naive_utc(),
+    };
+
+    diesel::insert_into(api_keys::table)
+        .values(&row)
+        .execute(conn)?;
+
+    Ok((row.into(), key))
+}
+
+/// Get all API keys of a user.
+pub fn get_api_keys(conn: &DbConn, username: &str) -> Result<Vec<ApiKey>> {
+    let keys = api_keys::table
+        .filter(api_keys::username.eq(username))
+        .order_by(api_keys::created_at)
+        .load::<ApiKeyRow>(conn)?
+        .into_iter()
+        .map(|row| row.into())
+        .collect();
+
+    Ok(keys)
+}
+
+/// Get the user an API key belongs to together with the key's scopes.
+pub fn get_api_key_user(conn: &DbConn, key: &str) -> Result<Option<(User, Vec<String>)>> {
+    let result = api_keys::table
+        .inner_join(users::table)
+        .filter(api_keys::key_hash.eq(hash_api_key(key)))
+        .select((users::table::all_columns(), api_keys::scopes))
+        .load::<(User, String)>(conn)?
+        .into_iter()
+        .next()
+        .map(|(user, scopes)| {
+            let scopes = scopes.split(',').map(|scope| scope.to_string()).collect();
+            (user, scopes)
+        });
+
+    Ok(result)
+}
+
+/// Revoke an API key of a user.
+pub fn delete_api_key(conn: &DbConn, id: &str, username: &str) -> Result<()> {
+    diesel::delete(api_keys::table)
+        .filter(api_keys::id.eq(id))
+        .filter(api_keys::username.eq(username))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Compute the hash of an API key that is stored in the database.
+fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
@@ -0,0 +1,203 @@
+use super::{with_transaction, DbConn};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel_migrations::MigrationConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A complete snapshot of the database. In contrast to dumps, backups contain all tables as they
+/// are, including users, API keys, private entities, drafts, playlists and revisions. Owners and
+/// IDs are kept, so restoring a backup results in exactly the same data.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    /// When the backup was created.
+    pub created_at: NaiveDateTime,
+
+    /// The versions of all migrations that were applied to the database. Backups can only be
+    /// restored into databases with exactly the same migrations.
+    pub migrations: BTreeSet<String>,
+
+    /// The rows of all tables as JSON objects by table name.
+    pub tables: BTreeMap<String, serde_json::Value>,
+}
+
+/// The name of a table.
+#[derive(QueryableByName, Debug, Clone)]
+struct TableName {
+    #[sql_type = "Text"]
+    table_name: String,
+}
+
+/// A foreign key from one table to another one.
+#[derive(QueryableByName, Debug, Clone)]
+struct Dependency {
+    #[sql_type = "Text"]
+    table_name: String,
+
+    #[sql_type = "Text"]
+    referenced_table: String,
+}
+
+/// A column getting its default values from a sequence.
+#[derive(QueryableByName, Debug, Clone)]
+struct SerialColumn {
+    #[sql_type = "Text"]
+    table_name: String,
+
+    #[sql_type = "Text"]
+    column_name: String,
+}
+
+/// All rows of a table as a JSON array.
+#[derive(QueryableByName, Debug, Clone)]
+struct Rows {
+    #[sql_type = "Text"]
+    data: String,
+}
+
+/// Collect all data from the database within a single snapshot.
+pub fn get_backup(conn: &DbConn) -> Result<Backup> {
+    conn.build_transaction()
+        .read_only()
+        .repeatable_read()
+        .run(|| {
+            let mut tables = BTreeMap::new();
+
+            for table in get_tables(conn)? {
+                let rows: Rows = diesel::sql_query(format!(
+                    "SELECT COALESCE(json_agg(t), '[]')::text AS data FROM \"{}\" t",
+                    table
+                ))
+                .get_result(conn)?;
+
+                tables.insert(table, serde_json::from_str(&rows.data)?);
+            }
+
+            Ok(Backup {
+                created_at: Utc::now().naive_utc(),
+                migrations: conn
+                    .previously_run_migration_versions()?
+                    .into_iter()
+                    .collect(),
+                tables,
+            })
+        })
+}
+
+/// Replace all data of the database with the contents of a backup. This happens within a single
+/// transaction, so either the whole backup is restored or nothing is changed. The database has to
+/// have the same migrations applied as the database the backup was created from. Returns the
+/// number of restored rows.
+pub fn restore_backup(conn: &DbConn, backup: &Backup) -> Result<usize> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let migrations: BTreeSet<String> = conn
+            .previously_run_migration_versions()?
+            .into_iter()
+            .collect();
+
+        if migrations != backup.migrations {
+            return Err(anyhow!(
+                "The backup was created using a different database schema. Migrate both databases \
+                to the same version first."
+            ));
+        }
+
+        let tables = get_tables(conn)?;
+
+        if let Some(table) = backup.tables.keys().find(|table| !tables.contains(table)) {
+            return Err(anyhow!("The backup contains an unknown table: {}", table));
+        }
+
+        let quoted: Vec<String> = tables
+            .iter()
+            .map(|table| format!("\"{}\"", table))
+            .collect();
+        diesel::sql_query(format!("TRUNCATE {}", quoted.join(", "))).execute(conn)?;
+
+        let mut restored = 0;
+
+        // The tables are ordered by their dependencies, so foreign keys are always satisfied.
+        for table in &tables {
+            if let Some(rows) = backup.tables.get(table) {
+                restored += diesel::sql_query(format!(
+                    "INSERT INTO \"{0}\" \
+                    SELECT * FROM json_populate_recordset(NULL::\"{0}\", $1::json)",
+                    table
+                ))
+                .bind::<Text, _>(rows.to_string())
+                .execute(conn)?;
+            }
+        }
+
+        // New rows have to get IDs after the restored ones.
+        let columns: Vec<SerialColumn> = diesel::sql_query(
+            "SELECT table_name::text AS table_name, column_name::text AS column_name \
+            FROM information_schema.columns \
+            WHERE table_schema = 'public' AND column_default LIKE 'nextval(%'",
+        )
+        .load(conn)?;
+
+        for column in columns {
+            diesel::sql_query(format!(
+                "SELECT setval(pg_get_serial_sequence('\"{0}\"', '{1}'), \
+                COALESCE(MAX(\"{1}\"), 0) + 1, false) FROM \"{0}\"",
+                column.table_name, column.column_name
+            ))
+            .execute(conn)?;
+        }
+
+        Ok(restored)
+    })
+}
+
+/// Get the names of all tables except for the one tracking migrations. Tables come after all
+/// tables they refer to.
+fn get_tables(conn: &DbConn) -> Result<Vec<String>> {
+    let mut remaining: Vec<String> = diesel::sql_query(
+        "SELECT tablename::text AS table_name FROM pg_tables \
+        WHERE schemaname = 'public' AND tablename <> '__diesel_schema_migrations' \
+        ORDER BY tablename",
+    )
+    .load::<TableName>(conn)?
+    .into_iter()
+    .map(|table| table.table_name)
+    .collect();
+
+    let dependencies: Vec<Dependency> = diesel::sql_query(
+        "SELECT a.relname::text AS table_name, b.relname::text AS referenced_table \
+        FROM pg_constraint \
+        JOIN pg_class a ON a.oid = conrelid \
+        JOIN pg_class b ON b.oid = confrelid \
+        WHERE contype = 'f' AND conrelid <> confrelid",
+    )
+    .load(conn)?;
+
+    let mut tables: Vec<String> = Vec::new();
+
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<String>, Vec<String>) =
+            remaining.into_iter().partition(|table| {
+                dependencies
+                    .iter()
+                    .filter(|dependency| &dependency.table_name == table)
+                    .all(|dependency| tables.contains(&dependency.referenced_table))
+            });
+
+        if ready.is_empty() {
+            return Err(anyhow!(
+                "The tables refer to each other: {}",
+                blocked.join(", ")
+            ));
+        }
+
+        tables.extend(ready);
+        remaining = blocked;
+    }
+
+    Ok(tables)
+}
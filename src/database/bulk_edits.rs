@@ -0,0 +1,227 @@
+use super::schema::bulk_edits;
+use super::{get_entity_data, update_ensemble_in, update_instrument_in, update_label_in};
+use super::{is_entity_visible, with_transaction, DbConn, DbTransaction, EntityType, User};
+use super::{update_medium_in, update_person_in, update_recording_in, update_work_in};
+use super::{Ensemble, Instrument, Label, Medium, Person, Recording, Work};
+use crate::error::ServerError;
+use crate::validation::{FieldError, Validate, ValidationErrors};
+use anyhow::{Error, Result};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A change of one field of an entity as part of a bulk edit.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkChange {
+    pub entity_type: EntityType,
+    pub id: String,
+
+    /// A JSON pointer to the field within the entity, e.g. "/composer" or "/parts/0/title".
+    pub field: String,
+
+    /// The new value of the field in the same format as for full updates. References to other
+    /// entities like the composer of a work have to be complete entities as well.
+    pub value: Value,
+}
+
+/// A record of field changes to many entities that were made at once.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkEdit {
+    pub id: i64,
+
+    /// Why the changes were made.
+    pub description: String,
+
+    pub changes: Vec<BulkChange>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Table data for a new bulk edit. The ID and time will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "bulk_edits"]
+struct NewBulkEditRow {
+    pub description: String,
+    pub changes: String,
+    pub created_by: String,
+}
+
+/// Table data for a bulk edit.
+#[derive(Queryable, Debug, Clone)]
+struct BulkEditRow {
+    pub id: i64,
+    pub description: String,
+    pub changes: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl BulkEditRow {
+    fn into_bulk_edit(self) -> Result<BulkEdit> {
+        Ok(BulkEdit {
+            id: self.id,
+            description: self.description,
+            changes: serde_json::from_str(&self.changes)?,
+            created_by: self.created_by,
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Apply field changes to many entities within a single transaction. Changes to the same entity
+/// are combined, so that each entity is updated once and gets one event as usual. Besides that,
+/// the whole bulk edit is recorded once. Either all changes are applied or none. Only editors may
+/// do that.
+pub fn apply_bulk_edit(
+    conn: &DbConn,
+    description: &str,
+    changes: &[BulkChange],
+    user: &User,
+) -> Result<BulkEdit> {
+    if !user.may_bulk_edit() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    // The changed entities in the order they were first mentioned together with the indices of
+    // their changes.
+    let mut entities: Vec<(EntityType, &str, Vec<usize>)> = Vec::new();
+
+    for (index, change) in changes.iter().enumerate() {
+        let entity = entities
+            .iter_mut()
+            .find(|(entity_type, id, _)| *entity_type == change.entity_type && *id == change.id);
+
+        match entity {
+            Some((_, _, indices)) => indices.push(index),
+            None => entities.push((change.entity_type, &change.id, vec![index])),
+        }
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        for (entity_type, id, indices) in &entities {
+            if !is_entity_visible(conn, *entity_type, id, Some(user))? {
+                return Err(Error::new(ServerError::NotFound));
+            }
+
+            let mut data = get_entity_data(conn, *entity_type, id)?
+                .ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+            for index in indices {
+                let change = &changes[*index];
+
+                match data.pointer_mut(&change.field) {
+                    Some(value) => *value = change.value.clone(),
+                    None => {
+                        return Err(invalid(*index, "field", "Must refer to an existing field."));
+                    }
+                }
+            }
+
+            // Errors are reported for the last change to the entity, because it is not known
+            // which of its changes caused them.
+            let index = indices[indices.len() - 1];
+
+            match entity_type {
+                EntityType::Person => {
+                    update_person_in(tx, &parse_entity::<Person>(index, data)?, user)?
+                }
+                EntityType::Ensemble => {
+                    update_ensemble_in(tx, &parse_entity::<Ensemble>(index, data)?, user)?
+                }
+                EntityType::Instrument => {
+                    update_instrument_in(tx, &parse_entity::<Instrument>(index, data)?, user)?
+                }
+                EntityType::Label => {
+                    update_label_in(tx, &parse_entity::<Label>(index, data)?, user)?
+                }
+                EntityType::Work => update_work_in(tx, &parse_entity::<Work>(index, data)?, user)?,
+                EntityType::Recording => {
+                    update_recording_in(tx, &parse_entity::<Recording>(index, data)?, user)?
+                }
+                EntityType::Medium => {
+                    update_medium_in(tx, &parse_entity::<Medium>(index, data)?, user)?
+                }
+            }
+        }
+
+        insert_bulk_edit(tx, description, changes, user)
+    })
+}
+
+/// Get a bulk edit by its ID.
+pub fn get_bulk_edit(conn: &DbConn, id: i64) -> Result<Option<BulkEdit>> {
+    bulk_edits::table
+        .filter(bulk_edits::id.eq(id))
+        .first::<BulkEditRow>(conn)
+        .optional()?
+        .map(BulkEditRow::into_bulk_edit)
+        .transpose()
+}
+
+/// Get up to `limit` bulk edits, most recent first.
+pub fn get_bulk_edits(conn: &DbConn, limit: i64) -> Result<Vec<BulkEdit>> {
+    bulk_edits::table
+        .order_by(bulk_edits::id.desc())
+        .limit(limit)
+        .load::<BulkEditRow>(conn)?
+        .into_iter()
+        .map(BulkEditRow::into_bulk_edit)
+        .collect()
+}
+
+/// Store the record of a bulk edit.
+fn insert_bulk_edit(
+    tx: &DbTransaction,
+    description: &str,
+    changes: &[BulkChange],
+    user: &User,
+) -> Result<BulkEdit> {
+    let row = diesel::insert_into(bulk_edits::table)
+        .values(NewBulkEditRow {
+            description: description.to_string(),
+            changes: serde_json::to_string(changes)?,
+            created_by: user.username.clone(),
+        })
+        .get_result::<BulkEditRow>(tx.conn())?;
+
+    row.into_bulk_edit()
+}
+
+/// Convert the changed data back to an entity and validate it. Problems are reported for the
+/// value of the change with the provided index and name the affected field of the entity.
+fn parse_entity<T: DeserializeOwned + Validate>(index: usize, data: Value) -> Result<T> {
+    let entity: T = serde_json::from_value(data)
+        .map_err(|error| invalid(index, "value", &error.to_string()))?;
+
+    entity.validate().map_err(|error| match error {
+        ServerError::Invalid(errors) => Error::new(ServerError::Invalid(ValidationErrors {
+            errors: errors
+                .errors
+                .into_iter()
+                .map(|error| FieldError {
+                    field: format!("changes[{}].value", index),
+                    message: format!("{}: {}", error.field, error.message),
+                })
+                .collect(),
+        })),
+        error => Error::new(error),
+    })?;
+
+    Ok(entity)
+}
+
+/// Create an error for one field of a change.
+fn invalid(index: usize, field: &str, message: &str) -> Error {
+    Error::new(ServerError::Invalid(ValidationErrors {
+        errors: vec![FieldError {
+            field: format!("changes[{}].{}", index, field),
+            message: message.to_string(),
+        }],
+    }))
+}
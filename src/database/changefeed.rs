@@ -0,0 +1,133 @@
+use super::{get_ensemble, get_events_after, get_instrument, get_label, get_medium, get_person};
+use super::{get_entity_sources, get_recording, get_work, is_entity_visible, DbConn, EntityType};
+use super::{EventKind, Source};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The change of one entity within the changefeed. Multiple events for the same entity are
+/// combined into one change.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeRecord {
+    /// The revision of the latest event for the entity within this part of the changefeed.
+    pub revision: i64,
+
+    pub entity_type: EntityType,
+    pub entity_id: String,
+    pub operation: EventKind,
+
+    /// The current public representation of the entity. This is missing for deleted entities.
+    pub payload: Option<Value>,
+
+    /// Where the data of the entity comes from. This is empty for deleted entities.
+    #[serde(default)]
+    pub sources: Vec<Source>,
+}
+
+/// A part of the changefeed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeFeed {
+    /// The revision to pass as `since` for getting the next part.
+    pub revision: i64,
+
+    /// Whether there are more changes after the revision.
+    pub more: bool,
+
+    pub changes: Vec<ChangeRecord>,
+}
+
+/// Get the changes to all entities after the provided revision, based on up to `limit` events.
+/// Entities that are private or don't exist anymore are reported as deleted, so that mirrors
+/// can drop them. The transaction itself isn't marked as read-only, because assembling mediums
+/// stores their read models on connections that allow writing. Read-only connections, like the
+/// ones of the read pool, skip that.
+pub fn get_changes_since(conn: &DbConn, since: i64, limit: i64) -> Result<ChangeFeed> {
+    conn.build_transaction().repeatable_read().run(|| {
+        let events = get_events_after(conn, since, limit + 1)?;
+        let more = events.len() as i64 > limit;
+
+        let mut changes: Vec<ChangeRecord> = Vec::new();
+        let mut positions: HashMap<(EntityType, String), usize> = HashMap::new();
+
+        for event in events.into_iter().take(limit as usize) {
+            let key = (event.entity_type, event.entity_id.clone());
+
+            match positions.get(&key) {
+                Some(&position) => {
+                    let change = &mut changes[position];
+                    change.revision = event.id;
+
+                    // An entity that was created within this part stays created.
+                    if change.operation != EventKind::Create || event.kind == EventKind::Delete {
+                        change.operation = event.kind;
+                    }
+                }
+                None => {
+                    positions.insert(key, changes.len());
+                    changes.push(ChangeRecord {
+                        revision: event.id,
+                        entity_type: event.entity_type,
+                        entity_id: event.entity_id,
+                        operation: event.kind,
+                        payload: None,
+                        sources: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        let revision = changes
+            .iter()
+            .map(|change| change.revision)
+            .max()
+            .unwrap_or(since);
+
+        for change in &mut changes {
+            let payload = if is_entity_visible(conn, change.entity_type, &change.entity_id, None)? {
+                get_payload(conn, change.entity_type, &change.entity_id)?
+            } else {
+                None
+            };
+
+            match payload {
+                Some(payload) => {
+                    // The entity was deleted and restored afterwards.
+                    if change.operation == EventKind::Delete {
+                        change.operation = EventKind::Update;
+                    }
+
+                    change.payload = Some(payload);
+                    change.sources =
+                        get_entity_sources(conn, change.entity_type, &change.entity_id)?;
+                }
+                None => change.operation = EventKind::Delete,
+            }
+        }
+
+        changes.sort_by_key(|change| change.revision);
+
+        Ok(ChangeFeed {
+            revision,
+            more,
+            changes,
+        })
+    })
+}
+
+/// Get the public representation of an entity as it is also used within dumps.
+fn get_payload(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<Option<Value>> {
+    let payload = match entity_type {
+        EntityType::Person => get_person(conn, id)?.map(serde_json::to_value),
+        EntityType::Ensemble => get_ensemble(conn, id)?.map(serde_json::to_value),
+        EntityType::Instrument => get_instrument(conn, id)?.map(serde_json::to_value),
+        EntityType::Work => get_work(conn, id)?.map(serde_json::to_value),
+        EntityType::Recording => get_recording(conn, id)?.map(serde_json::to_value),
+        EntityType::Medium => get_medium(conn, id)?.map(serde_json::to_value),
+        EntityType::Label => get_label(conn, id)?.map(serde_json::to_value),
+    };
+
+    Ok(payload.transpose()?)
+}
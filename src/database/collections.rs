@@ -0,0 +1,152 @@
+use super::schema::collection_items;
+use super::{get_medium, is_entity_visible, DbConn, EntityType, Medium, User};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The physical condition of a medium within a collection.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MediumCondition {
+    Mint,
+    NearMint,
+    VeryGood,
+    Good,
+    Fair,
+    Poor,
+}
+
+impl MediumCondition {
+    /// Get the string representation of the condition that is also used in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediumCondition::Mint => "mint",
+            MediumCondition::NearMint => "nearMint",
+            MediumCondition::VeryGood => "veryGood",
+            MediumCondition::Good => "good",
+            MediumCondition::Fair => "fair",
+            MediumCondition::Poor => "poor",
+        }
+    }
+
+    /// Get a condition from its string representation.
+    pub fn parse(condition: &str) -> Option<MediumCondition> {
+        match condition {
+            "mint" => Some(MediumCondition::Mint),
+            "nearMint" => Some(MediumCondition::NearMint),
+            "veryGood" => Some(MediumCondition::VeryGood),
+            "good" => Some(MediumCondition::Good),
+            "fair" => Some(MediumCondition::Fair),
+            "poor" => Some(MediumCondition::Poor),
+            _ => None,
+        }
+    }
+}
+
+/// A medium that a user owns.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItem {
+    pub medium: Medium,
+    pub notes: String,
+    pub condition: Option<MediumCondition>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Table data for a [`CollectionItem`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "collection_items"]
+struct CollectionItemRow {
+    pub username: String,
+    pub medium: String,
+    pub notes: String,
+    pub condition: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Add a medium to the collection of a user or update the notes and condition, if it is already
+/// contained.
+pub fn set_collection_item(
+    conn: &DbConn,
+    medium: &str,
+    notes: &str,
+    condition: Option<MediumCondition>,
+    user: &User,
+) -> Result<()> {
+    if !is_entity_visible(conn, EntityType::Medium, medium, Some(user))? {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    let row = CollectionItemRow {
+        username: user.username.clone(),
+        medium: medium.to_string(),
+        notes: notes.to_string(),
+        condition: condition.map(|condition| condition.as_str().to_string()),
+        created_at: Utc::now().naive_utc(),
+    };
+
+    diesel::insert_into(collection_items::table)
+        .values(&row)
+        .on_conflict((collection_items::username, collection_items::medium))
+        .do_update()
+        .set((
+            collection_items::notes.eq(&row.notes),
+            collection_items::condition.eq(&row.condition),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Remove a medium from the collection of a user.
+pub fn delete_collection_item(conn: &DbConn, medium: &str, user: &User) -> Result<()> {
+    let count = diesel::delete(collection_items::table)
+        .filter(collection_items::username.eq(&user.username))
+        .filter(collection_items::medium.eq(medium))
+        .execute(conn)?;
+
+    if count == 0 {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    Ok(())
+}
+
+/// Get all mediums within the collection of a user, most recently added first.
+pub fn get_collection(conn: &DbConn, user: &User) -> Result<Vec<CollectionItem>> {
+    let rows = collection_items::table
+        .filter(collection_items::username.eq(&user.username))
+        .order_by(collection_items::created_at.desc())
+        .load::<CollectionItemRow>(conn)?;
+
+    let mut items = Vec::new();
+
+    for row in rows {
+        // Mediums that were made private by another user in the meantime are left out.
+        if !is_entity_visible(conn, EntityType::Medium, &row.medium, Some(user))? {
+            continue;
+        }
+
+        let medium = get_medium(conn, &row.medium)?
+            .ok_or_else(|| anyhow!("Missing medium: {}", row.medium))?;
+
+        let condition = match &row.condition {
+            Some(condition) => Some(
+                MediumCondition::parse(condition)
+                    .ok_or_else(|| anyhow!("Unknown condition: {}", condition))?,
+            ),
+            None => None,
+        };
+
+        items.push(CollectionItem {
+            medium,
+            notes: row.notes,
+            condition,
+            created_at: row.created_at,
+        });
+    }
+
+    Ok(items)
+}
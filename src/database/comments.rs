@@ -0,0 +1,161 @@
+use super::schema::comments;
+use super::{generate_id, get_mentioned_users, get_visibility, get_watchers, insert_notification};
+use super::{is_entity_visible, with_transaction, DbConn, EntityType, NotificationKind, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// A comment within the discussion about an entity.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Comment {
+    pub id: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub text: String,
+}
+
+/// Table data for a [`Comment`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "comments"]
+struct CommentRow {
+    pub id: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub text: String,
+}
+
+impl From<CommentRow> for Comment {
+    fn from(row: CommentRow) -> Comment {
+        Comment {
+            id: row.id,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            text: row.text,
+        }
+    }
+}
+
+/// Comment on an entity. Every user that isn't banned may do that. All users watching the entity
+/// and all users that are mentioned within the text are notified. This returns the ID of the new
+/// comment.
+pub fn insert_comment(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    text: &str,
+    user: &User,
+) -> Result<String> {
+    if !user.may_create() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let visibility = match get_visibility(conn, entity_type, entity_id)? {
+            Some(visibility) if visibility.is_visible_to(Some(user)) => visibility,
+            _ => return Err(Error::new(ServerError::NotFound)),
+        };
+
+        let row = CommentRow {
+            id: generate_id(),
+            entity_type: entity_type.as_str().to_string(),
+            entity_id: entity_id.to_string(),
+            created_by: user.username.clone(),
+            created_at: Utc::now().naive_utc(),
+            text: text.to_string(),
+        };
+
+        diesel::insert_into(comments::table)
+            .values(&row)
+            .execute(conn)?;
+
+        // Only the owner can see private entities, so nobody else is notified about them.
+        if !visibility.private {
+            // Each user is notified only once, even if they are mentioned and watching the entity.
+            let mut recipients: BTreeSet<String> = BTreeSet::new();
+
+            for (username, _) in get_watchers(conn, entity_type, entity_id)? {
+                recipients.insert(username);
+            }
+
+            recipients.extend(get_mentioned_users(conn, text)?);
+
+            for username in recipients {
+                insert_notification(
+                    conn,
+                    &username,
+                    NotificationKind::EntityCommented,
+                    Some((entity_type, entity_id)),
+                    Some(&row.id),
+                    user,
+                )?;
+            }
+        }
+
+        Ok(row.id)
+    })
+}
+
+/// Get all comments on an entity, oldest first. The entity has to be visible to the user.
+pub fn get_comments(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    user: &User,
+) -> Result<Vec<Comment>> {
+    if !is_entity_visible(conn, entity_type, entity_id, Some(user))? {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    let rows = comments::table
+        .filter(comments::entity_type.eq(entity_type.as_str()))
+        .filter(comments::entity_id.eq(entity_id))
+        .order_by(comments::created_at)
+        .load::<CommentRow>(conn)?;
+
+    Ok(rows.into_iter().map(Comment::from).collect())
+}
+
+/// Delete a comment. This is possible for its author and for users that may moderate.
+pub fn delete_comment(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    id: &str,
+    user: &User,
+) -> Result<()> {
+    let row = comments::table
+        .filter(comments::id.eq(id))
+        .filter(comments::entity_type.eq(entity_type.as_str()))
+        .filter(comments::entity_id.eq(entity_id))
+        .first::<CommentRow>(conn)
+        .optional()?
+        .ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+    if row.created_by != user.username && !user.may_moderate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    diesel::delete(comments::table)
+        .filter(comments::id.eq(id))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Delete the whole discussion about an entity. This is called when the entity is deleted.
+pub fn delete_comments(conn: &DbConn, entity_type: EntityType, entity_id: &str) -> Result<()> {
+    diesel::delete(comments::table)
+        .filter(comments::entity_type.eq(entity_type.as_str()))
+        .filter(comments::entity_id.eq(entity_id))
+        .execute(conn)?;
+
+    Ok(())
+}
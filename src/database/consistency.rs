@@ -0,0 +1,157 @@
+use super::schema::{
+    instrumentations, performances, recording_works, recordings, track_sets, tracks, work_parts,
+    works,
+};
+use super::DbConn;
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The different kinds of problems that the consistency check looks for.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum InconsistencyKind {
+    /// A track references work parts that don't exist within the recorded work.
+    InvalidWorkParts,
+
+    /// A performance belongs to a recording that doesn't exist.
+    DanglingPerformance,
+
+    /// An instrumentation belongs to a work that doesn't exist.
+    DanglingInstrumentation,
+}
+
+/// A problem found by the consistency check.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Inconsistency {
+    pub kind: InconsistencyKind,
+
+    /// The ID of the affected row.
+    pub id: i64,
+
+    /// A human readable description of the problem.
+    pub description: String,
+}
+
+/// Search for dangling references within the database. If `repair` is true, the problems will be
+/// fixed by removing invalid work part indices from tracks and deleting dangling rows. This
+/// returns all problems that were found.
+pub fn check_consistency(conn: &DbConn, repair: bool) -> Result<Vec<Inconsistency>> {
+    conn.transaction::<_, Error, _>(|| {
+        let mut inconsistencies = Vec::new();
+
+        // Check the work part indices of all tracks.
+
+        let mut part_counts: HashMap<String, usize> = HashMap::new();
+        for work in work_parts::table
+            .select(work_parts::work)
+            .load::<String>(conn)?
+        {
+            *part_counts.entry(work).or_insert(0) += 1;
+        }
+
+        let mut additional_works: HashMap<String, Vec<String>> = HashMap::new();
+        for (recording, work) in recording_works::table
+            .order_by(recording_works::id)
+            .select((recording_works::recording, recording_works::work))
+            .load::<(String, String)>(conn)?
+        {
+            additional_works.entry(recording).or_default().push(work);
+        }
+
+        let track_rows = tracks::table
+            .inner_join(track_sets::table.inner_join(recordings::table))
+            .select((
+                tracks::id,
+                tracks::work_parts,
+                tracks::work_index,
+                recordings::id,
+                recordings::work,
+            ))
+            .load::<(i64, String, i32, String, String)>(conn)?;
+
+        for (id, parts, work_index, recording, work) in track_rows {
+            // Tracks with a work index out of range are treated like tracks of the main work.
+            let work = match work_index {
+                0 => work,
+                index => additional_works
+                    .get(&recording)
+                    .and_then(|works| works.get(index as usize - 1))
+                    .cloned()
+                    .unwrap_or(work),
+            };
+
+            let count = part_counts.get(&work).cloned().unwrap_or(0);
+
+            let indices: Vec<&str> = parts.split(',').filter(|part| !part.is_empty()).collect();
+            let valid: Vec<&str> = indices
+                .iter()
+                .filter(|index| matches!(index.parse::<usize>(), Ok(index) if index < count))
+                .cloned()
+                .collect();
+
+            if valid.len() != indices.len() {
+                inconsistencies.push(Inconsistency {
+                    kind: InconsistencyKind::InvalidWorkParts,
+                    id,
+                    description: format!(
+                        "Track references work parts {} of work {} with {} parts.",
+                        parts, work, count
+                    ),
+                });
+
+                if repair {
+                    diesel::update(tracks::table.filter(tracks::id.eq(id)))
+                        .set(tracks::work_parts.eq(valid.join(",")))
+                        .execute(conn)?;
+                }
+            }
+        }
+
+        // Check for performances without recordings.
+
+        let performance_rows = performances::table
+            .left_join(recordings::table)
+            .filter(recordings::id.nullable().is_null())
+            .select((performances::id, performances::recording))
+            .load::<(i64, String)>(conn)?;
+
+        for (id, recording) in performance_rows {
+            inconsistencies.push(Inconsistency {
+                kind: InconsistencyKind::DanglingPerformance,
+                id,
+                description: format!("Performance belongs to missing recording {}.", recording),
+            });
+
+            if repair {
+                diesel::delete(performances::table.filter(performances::id.eq(id)))
+                    .execute(conn)?;
+            }
+        }
+
+        // Check for instrumentations without works.
+
+        let instrumentation_rows = instrumentations::table
+            .left_join(works::table)
+            .filter(works::id.nullable().is_null())
+            .select((instrumentations::id, instrumentations::work))
+            .load::<(i64, String)>(conn)?;
+
+        for (id, work) in instrumentation_rows {
+            inconsistencies.push(Inconsistency {
+                kind: InconsistencyKind::DanglingInstrumentation,
+                id,
+                description: format!("Instrumentation belongs to missing work {}.", work),
+            });
+
+            if repair {
+                diesel::delete(instrumentations::table.filter(instrumentations::id.eq(id)))
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(inconsistencies)
+    })
+}
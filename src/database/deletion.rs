@@ -0,0 +1,321 @@
+use super::schema::{ensembles, instruments, labels, mediums, performances, persons, recordings};
+use super::schema::{recording_works, track_sets, work_authors, works};
+use super::{entity_exists, insert_event, move_to_trash, with_transaction, DbConn, DbTransaction};
+use super::{EntityReference, EntityType, EventKind, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// Everything that would be affected by deleting an entity together with everything that refers
+/// to it.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionPreview {
+    /// The entities that would be deleted, including the entity itself.
+    pub deleted: Vec<EntityReference>,
+
+    /// Works that would lose text authors, recordings that would lose performances and mediums
+    /// that would lose track sets or their label.
+    pub modified: Vec<EntityReference>,
+
+    /// The number of performances that would be removed.
+    pub performances: usize,
+
+    /// The number of track sets that would be removed.
+    pub track_sets: usize,
+}
+
+/// All rows that have to be removed for deleting an entity.
+struct Cascade {
+    works: Vec<String>,
+    recordings: Vec<String>,
+    performances: Vec<i64>,
+    track_sets: Vec<i64>,
+    modified_works: Vec<String>,
+    modified_recordings: Vec<String>,
+    modified_mediums: Vec<String>,
+}
+
+/// Find out what would be affected by deleting an entity. This returns [`None`], if the entity
+/// doesn't exist.
+pub fn get_deletion_preview(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+) -> Result<Option<DeletionPreview>> {
+    if !entity_exists(conn, entity_type, id)? {
+        return Ok(None);
+    }
+
+    let cascade = get_cascade(conn, entity_type, id)?;
+
+    let mut deleted = vec![EntityReference {
+        entity_type,
+        entity_id: id.to_string(),
+    }];
+
+    deleted.extend(references(EntityType::Work, &cascade.works));
+    deleted.extend(references(EntityType::Recording, &cascade.recordings));
+    deleted.sort();
+    deleted.dedup();
+
+    let mut modified = references(EntityType::Work, &cascade.modified_works);
+    modified.extend(references(
+        EntityType::Recording,
+        &cascade.modified_recordings,
+    ));
+    modified.extend(references(EntityType::Medium, &cascade.modified_mediums));
+
+    Ok(Some(DeletionPreview {
+        deleted,
+        modified,
+        performances: cascade.performances.len(),
+        track_sets: cascade.track_sets.len(),
+    }))
+}
+
+/// Delete an entity together with everything that refers to it within one transaction. Works and
+/// recordings depending on the entity are deleted, while recordings and mediums that only
+/// partially depend on it lose the corresponding performances and track sets. Only
+/// administrators are allowed to do this.
+pub fn delete_cascading(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+) -> Result<()> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| delete_cascading_in(tx, entity_type, id, user))
+}
+
+/// Delete an entity together with everything that refers to it as part of a larger change. See
+/// [`delete_cascading`].
+pub fn delete_cascading_in(
+    tx: &DbTransaction,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+) -> Result<()> {
+    let conn = tx.conn();
+
+    if !entity_exists(conn, entity_type, id)? {
+        return Ok(());
+    }
+
+    let cascade = get_cascade(conn, entity_type, id)?;
+
+    // Keep snapshots of everything that is deleted, so that it can be restored later.
+
+    for recording in &cascade.recordings {
+        move_to_trash(conn, EntityType::Recording, recording, user)?;
+    }
+
+    for work in &cascade.works {
+        move_to_trash(conn, EntityType::Work, work, user)?;
+    }
+
+    if entity_type != EntityType::Work && entity_type != EntityType::Recording {
+        move_to_trash(conn, entity_type, id, user)?;
+    }
+
+    // Tracks are deleted together with their track sets and the remaining rows of works and
+    // recordings are deleted together with them.
+
+    diesel::delete(track_sets::table.filter(track_sets::id.eq_any(&cascade.track_sets)))
+        .execute(conn)?;
+
+    diesel::delete(performances::table.filter(performances::id.eq_any(&cascade.performances)))
+        .execute(conn)?;
+
+    diesel::delete(recordings::table.filter(recordings::id.eq_any(&cascade.recordings)))
+        .execute(conn)?;
+
+    diesel::delete(works::table.filter(works::id.eq_any(&cascade.works))).execute(conn)?;
+
+    match entity_type {
+        EntityType::Person => {
+            diesel::delete(persons::table.filter(persons::id.eq(id))).execute(conn)?;
+        }
+        EntityType::Ensemble => {
+            diesel::delete(ensembles::table.filter(ensembles::id.eq(id))).execute(conn)?;
+        }
+        EntityType::Instrument => {
+            diesel::delete(instruments::table.filter(instruments::id.eq(id))).execute(conn)?;
+        }
+        EntityType::Medium => {
+            diesel::delete(mediums::table.filter(mediums::id.eq(id))).execute(conn)?;
+        }
+        EntityType::Label => {
+            // The mediums of the label lose their reference to it.
+            diesel::delete(labels::table.filter(labels::id.eq(id))).execute(conn)?;
+        }
+        EntityType::Work | EntityType::Recording => (),
+    }
+
+    for recording in &cascade.recordings {
+        insert_event(
+            conn,
+            EntityType::Recording,
+            recording,
+            EventKind::Delete,
+            user,
+        )?;
+    }
+
+    for work in &cascade.works {
+        insert_event(conn, EntityType::Work, work, EventKind::Delete, user)?;
+    }
+
+    if entity_type != EntityType::Work && entity_type != EntityType::Recording {
+        insert_event(conn, entity_type, id, EventKind::Delete, user)?;
+    }
+
+    for work in &cascade.modified_works {
+        insert_event(conn, EntityType::Work, work, EventKind::Update, user)?;
+    }
+
+    for recording in &cascade.modified_recordings {
+        insert_event(
+            conn,
+            EntityType::Recording,
+            recording,
+            EventKind::Update,
+            user,
+        )?;
+    }
+
+    for medium in &cascade.modified_mediums {
+        insert_event(conn, EntityType::Medium, medium, EventKind::Update, user)?;
+    }
+
+    Ok(())
+}
+
+/// Collect all rows that have to be removed for deleting an entity.
+fn get_cascade(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<Cascade> {
+    let works: Vec<String> = match entity_type {
+        EntityType::Person => works::table
+            .filter(works::composer.eq(id))
+            .select(works::id)
+            .load(conn)?,
+        EntityType::Work => vec![id.to_string()],
+        _ => Vec::new(),
+    };
+
+    // Text authors are removed together with the person, but the works themselves are kept.
+    let mut modified_works: Vec<String> = match entity_type {
+        EntityType::Person => work_authors::table
+            .filter(work_authors::person.eq(id))
+            .select(work_authors::work)
+            .load(conn)?,
+        _ => Vec::new(),
+    };
+
+    modified_works.retain(|work| !works.contains(work));
+    modified_works.sort();
+    modified_works.dedup();
+
+    let mut recordings: Vec<String> = recordings::table
+        .filter(recordings::work.eq_any(&works))
+        .select(recordings::id)
+        .load(conn)?;
+
+    // Recordings that contain a deleted work as an additional work are deleted as well, because
+    // their tracks refer to it.
+    recordings.extend(
+        recording_works::table
+            .filter(recording_works::work.eq_any(&works))
+            .select(recording_works::recording)
+            .load::<String>(conn)?,
+    );
+
+    recordings.sort();
+    recordings.dedup();
+
+    if entity_type == EntityType::Recording {
+        recordings.push(id.to_string());
+    }
+
+    // Performances of the entity itself within recordings that are not deleted.
+    let direct_performances: Vec<(i64, String)> = match entity_type {
+        EntityType::Person => performances::table
+            .filter(performances::person.eq(id))
+            .select((performances::id, performances::recording))
+            .load(conn)?,
+        EntityType::Ensemble => performances::table
+            .filter(performances::ensemble.eq(id))
+            .select((performances::id, performances::recording))
+            .load(conn)?,
+        EntityType::Instrument => performances::table
+            .filter(performances::role.eq(id))
+            .select((performances::id, performances::recording))
+            .load(conn)?,
+        _ => Vec::new(),
+    }
+    .into_iter()
+    .filter(|(_, recording)| !recordings.contains(recording))
+    .collect();
+
+    let mut performances: Vec<i64> = performances::table
+        .filter(performances::recording.eq_any(&recordings))
+        .select(performances::id)
+        .load(conn)?;
+
+    performances.extend(direct_performances.iter().map(|(id, _)| *id));
+
+    let mut modified_recordings: Vec<String> = direct_performances
+        .into_iter()
+        .map(|(_, recording)| recording)
+        .collect();
+
+    modified_recordings.sort();
+    modified_recordings.dedup();
+
+    let track_set_rows: Vec<(i64, String)> = track_sets::table
+        .filter(track_sets::recording.eq_any(&recordings))
+        .select((track_sets::id, track_sets::medium))
+        .load(conn)?;
+
+    let track_sets = track_set_rows.iter().map(|(id, _)| *id).collect();
+
+    let mut modified_mediums: Vec<String> = track_set_rows
+        .into_iter()
+        .map(|(_, medium)| medium)
+        .collect();
+
+    if entity_type == EntityType::Label {
+        modified_mediums.extend(
+            mediums::table
+                .filter(mediums::label.eq(id))
+                .select(mediums::id)
+                .load::<String>(conn)?,
+        );
+    }
+
+    modified_mediums.sort();
+    modified_mediums.dedup();
+
+    Ok(Cascade {
+        works,
+        recordings,
+        performances,
+        track_sets,
+        modified_works,
+        modified_recordings,
+        modified_mediums,
+    })
+}
+
+/// Create references to entities of one type.
+fn references(entity_type: EntityType, ids: &[String]) -> Vec<EntityReference> {
+    ids.iter()
+        .map(|id| EntityReference {
+            entity_type,
+            entity_id: id.clone(),
+        })
+        .collect()
+}
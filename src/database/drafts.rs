@@ -0,0 +1,149 @@
+use super::schema::drafts;
+use super::{with_transaction, DbConn, EntityType, User};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+
+/// An unfinished work or medium that is only visible to the user that created it.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    pub entity_type: EntityType,
+    pub id: String,
+
+    /// The data of the entity as it was submitted. This may be incomplete or invalid.
+    pub data: Value,
+
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Table data for a [`Draft`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "drafts"]
+struct DraftRow {
+    pub entity_type: String,
+    pub id: String,
+    pub username: String,
+    pub data: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl DraftRow {
+    /// Convert the table data into a [`Draft`].
+    fn into_draft(self) -> Result<Draft> {
+        Ok(Draft {
+            entity_type: EntityType::parse(&self.entity_type)
+                .ok_or_else(|| anyhow!("Unknown entity type: {}", self.entity_type))?,
+            id: self.id,
+            data: serde_json::from_str(&self.data)?,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// Save a new draft or replace the data of an existing one. Drafts can only be changed by the
+/// user that created them.
+pub fn update_draft(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    data: &Value,
+    user: &User,
+) -> Result<()> {
+    if !user.may_create() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let old_row = get_draft_row(conn, entity_type, id)?;
+        let data = serde_json::to_string(data)?;
+        let now = Utc::now().naive_utc();
+
+        match old_row {
+            Some(old_row) => {
+                if old_row.username != user.username {
+                    return Err(Error::new(ServerError::Conflict));
+                }
+
+                diesel::update(drafts::table)
+                    .filter(drafts::entity_type.eq(entity_type.as_str()))
+                    .filter(drafts::id.eq(id))
+                    .set((drafts::data.eq(data), drafts::updated_at.eq(now)))
+                    .execute(conn)?;
+            }
+            None => {
+                let row = DraftRow {
+                    entity_type: entity_type.as_str().to_string(),
+                    id: id.to_string(),
+                    username: user.username.clone(),
+                    data,
+                    created_at: now,
+                    updated_at: now,
+                };
+
+                diesel::insert_into(drafts::table)
+                    .values(row)
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Get a draft of a user. Drafts of other users are never returned.
+pub fn get_draft(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+) -> Result<Option<Draft>> {
+    let draft = match get_draft_row(conn, entity_type, id)? {
+        Some(row) if row.username == user.username => Some(row.into_draft()?),
+        _ => None,
+    };
+
+    Ok(draft)
+}
+
+/// Get all drafts of a user, most recently changed first.
+pub fn get_drafts(conn: &DbConn, user: &User) -> Result<Vec<Draft>> {
+    let rows = drafts::table
+        .filter(drafts::username.eq(&user.username))
+        .order_by(drafts::updated_at.desc())
+        .load::<DraftRow>(conn)?;
+
+    rows.into_iter().map(DraftRow::into_draft).collect()
+}
+
+/// Delete a draft of a user.
+pub fn delete_draft(conn: &DbConn, entity_type: EntityType, id: &str, user: &User) -> Result<()> {
+    let count = diesel::delete(drafts::table)
+        .filter(drafts::entity_type.eq(entity_type.as_str()))
+        .filter(drafts::id.eq(id))
+        .filter(drafts::username.eq(&user.username))
+        .execute(conn)?;
+
+    if count == 0 {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    Ok(())
+}
+
+/// Get the table data of a draft regardless of its owner.
+fn get_draft_row(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<Option<DraftRow>> {
+    Ok(drafts::table
+        .filter(drafts::entity_type.eq(entity_type.as_str()))
+        .filter(drafts::id.eq(id))
+        .first::<DraftRow>(conn)
+        .optional()?)
+}
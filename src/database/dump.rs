@@ -0,0 +1,132 @@
+use super::{get_all_mediums, get_all_recordings, get_all_works, get_ensembles, get_instruments};
+use super::{get_labels, get_last_event_id, get_persons, set_person_locked, set_recording_locked};
+use super::{get_public_sources, restore_source, with_transaction, DbConn, Ensemble, EntitySource};
+use super::{get_public_work_texts, replace_work_texts, Instrument, Label, Medium, Person};
+use super::{set_work_locked, update_ensemble_in, update_instrument_in, update_label_in};
+use super::{update_medium_in, update_person_in, update_recording_in, update_work_in};
+use super::{Recording, User, Work, WorkTexts};
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// All public data of the database.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Dump {
+    /// When the dump was created.
+    pub created_at: NaiveDateTime,
+
+    /// The revision of the latest change included in the dump. Mirrors can continue from here
+    /// using the changefeed. Older dumps don't contain it.
+    #[serde(default)]
+    pub revision: i64,
+
+    pub persons: Vec<Person>,
+    pub ensembles: Vec<Ensemble>,
+    pub instruments: Vec<Instrument>,
+    pub works: Vec<Work>,
+
+    /// The texts of the works. They were added later, so older dumps don't contain them.
+    #[serde(default)]
+    pub work_texts: Vec<WorkTexts>,
+
+    pub recordings: Vec<Recording>,
+
+    /// Labels were added later, so older dumps don't contain them.
+    #[serde(default)]
+    pub labels: Vec<Label>,
+
+    pub mediums: Vec<Medium>,
+
+    /// Where the data of the entities comes from. Sources were added later, so older dumps don't
+    /// contain them.
+    #[serde(default)]
+    pub sources: Vec<EntitySource>,
+}
+
+/// Collect all public data from the database.
+pub fn get_dump(conn: &DbConn) -> Result<Dump> {
+    conn.build_transaction()
+        .read_only()
+        .repeatable_read()
+        .run(|| {
+            Ok(Dump {
+                created_at: Utc::now().naive_utc(),
+                revision: get_last_event_id(conn)?,
+                persons: get_persons(conn, None)?,
+                ensembles: get_ensembles(conn, None)?,
+                instruments: get_instruments(conn, None)?,
+                works: get_all_works(conn, None)?,
+                work_texts: get_public_work_texts(conn)?,
+                recordings: get_all_recordings(conn, None)?,
+                labels: get_labels(conn, None)?,
+                mediums: get_all_mediums(conn, None)?,
+                sources: get_public_sources(conn)?,
+            })
+        })
+}
+
+/// Load a dump into the database on behalf of the provided user. Entities are inserted or
+/// updated in the order of their dependencies within a single transaction, so either the whole
+/// dump is restored or nothing at all. Returns the number of restored entities.
+pub fn restore_dump(conn: &DbConn, dump: &Dump, user: &User) -> Result<usize> {
+    with_transaction(conn, |tx| {
+        for person in &dump.persons {
+            update_person_in(tx, person, user)?;
+        }
+
+        for ensemble in &dump.ensembles {
+            update_ensemble_in(tx, ensemble, user)?;
+        }
+
+        for instrument in &dump.instruments {
+            update_instrument_in(tx, instrument, user)?;
+        }
+
+        for work in &dump.works {
+            update_work_in(tx, work, user)?;
+        }
+
+        for texts in &dump.work_texts {
+            replace_work_texts(tx.conn(), &texts.work, &texts.texts)?;
+        }
+
+        for recording in &dump.recordings {
+            update_recording_in(tx, recording, user)?;
+        }
+
+        for label in &dump.labels {
+            update_label_in(tx, label, user)?;
+        }
+
+        for medium in &dump.mediums {
+            update_medium_in(tx, medium, user)?;
+        }
+
+        // Updates ignore whether an entity is locked, so that is restored separately.
+        for person in dump.persons.iter().filter(|person| person.locked) {
+            set_person_locked(tx.conn(), &person.id, true, user)?;
+        }
+
+        for work in dump.works.iter().filter(|work| work.locked) {
+            set_work_locked(tx.conn(), &work.id, true, user)?;
+        }
+
+        for recording in dump.recordings.iter().filter(|recording| recording.locked) {
+            set_recording_locked(tx.conn(), &recording.id, true, user)?;
+        }
+
+        // Sources are restored as new changes of their entities, so they come last.
+        for source in &dump.sources {
+            restore_source(tx.conn(), source, user)?;
+        }
+
+        Ok(dump.persons.len()
+            + dump.ensembles.len()
+            + dump.instruments.len()
+            + dump.works.len()
+            + dump.recordings.len()
+            + dump.labels.len()
+            + dump.mediums.len())
+    })
+}
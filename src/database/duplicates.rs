@@ -0,0 +1,61 @@
+use super::{get_persons, get_works, normalize_text, DbConn, Person, Work};
+use anyhow::Result;
+
+/// Find existing public persons with names that are nearly identical to the name of the provided
+/// person. The person itself is excluded.
+pub fn find_similar_persons(conn: &DbConn, person: &Person) -> Result<Vec<Person>> {
+    let name = normalize(&format!("{} {}", person.first_name, person.last_name));
+
+    let candidates = get_persons(conn, None)?
+        .into_iter()
+        .filter(|candidate| {
+            candidate.id != person.id
+                && is_similar(
+                    &name,
+                    &normalize(&format!("{} {}", candidate.first_name, candidate.last_name)),
+                )
+        })
+        .collect();
+
+    Ok(candidates)
+}
+
+/// Find existing public works by the same composer with titles that are nearly identical to the
+/// title of the provided work. The work itself is excluded.
+pub fn find_similar_works(conn: &DbConn, work: &Work) -> Result<Vec<Work>> {
+    let title = normalize(&work.title);
+
+    let candidates = get_works(conn, &work.composer.id, None)?
+        .into_iter()
+        .filter(|candidate| {
+            candidate.id != work.id && is_similar(&title, &normalize(&candidate.title))
+        })
+        .collect();
+
+    Ok(candidates)
+}
+
+/// Reduce a name to lowercase letters and digits separated by single spaces.
+fn normalize(name: &str) -> String {
+    normalize_text(name)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Check whether two normalized names are nearly identical. The number of allowed typos depends
+/// on the length of the names.
+fn is_similar(a: &str, b: &str) -> bool {
+    let length = a.chars().count().min(b.chars().count());
+    let max_distance = match length {
+        0..=3 => 0,
+        4..=11 => 1,
+        _ => 2,
+    };
+
+    strsim::levenshtein(a, b) <= max_distance
+}
@@ -0,0 +1,141 @@
+use super::schema::editor_applications;
+use super::{generate_id, insert_notification, set_user_role, with_transaction};
+use super::{DbConn, NotificationKind, Role, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// An application of a user for becoming an editor.
+#[derive(Insertable, Queryable, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorApplication {
+    pub id: String,
+    pub username: String,
+
+    /// Why the user wants to become an editor.
+    pub motivation: String,
+
+    pub created_at: NaiveDateTime,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<NaiveDateTime>,
+
+    /// Whether the application was approved. This is empty, until an administrator decides.
+    pub approved: Option<bool>,
+}
+
+/// Apply for becoming an editor. Every user that isn't banned and isn't already an editor may do
+/// that, but only one application can be pending at a time. This returns the ID of the new
+/// application.
+pub fn insert_editor_application(conn: &DbConn, motivation: &str, user: &User) -> Result<String> {
+    if !user.may_create() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    if user.is_editor {
+        return Err(Error::new(ServerError::Conflict));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let pending: i64 = editor_applications::table
+            .filter(editor_applications::username.eq(&user.username))
+            .filter(editor_applications::decided_at.is_null())
+            .count()
+            .get_result(conn)?;
+
+        if pending > 0 {
+            return Err(Error::new(ServerError::Conflict));
+        }
+
+        let application = EditorApplication {
+            id: generate_id(),
+            username: user.username.clone(),
+            motivation: motivation.to_string(),
+            created_at: Utc::now().naive_utc(),
+            decided_by: None,
+            decided_at: None,
+            approved: None,
+        };
+
+        diesel::insert_into(editor_applications::table)
+            .values(&application)
+            .execute(conn)?;
+
+        Ok(application.id)
+    })
+}
+
+/// Get all applications for becoming an editor, optionally filtered by whether they are still
+/// pending. The user has to be an administrator.
+pub fn get_editor_applications(
+    conn: &DbConn,
+    pending: Option<bool>,
+    user: &User,
+) -> Result<Vec<EditorApplication>> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let mut query = editor_applications::table
+        .order_by(editor_applications::created_at)
+        .into_boxed();
+
+    query = match pending {
+        Some(true) => query.filter(editor_applications::decided_at.is_null()),
+        Some(false) => query.filter(editor_applications::decided_at.is_not_null()),
+        None => query,
+    };
+
+    Ok(query.load::<EditorApplication>(conn)?)
+}
+
+/// Approve or reject a pending application. Approving it makes the applicant an editor. The
+/// applicant is notified either way. The user has to be an administrator.
+pub fn decide_editor_application(
+    conn: &DbConn,
+    id: &str,
+    approved: bool,
+    user: &User,
+) -> Result<()> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let application = editor_applications::table
+            .filter(editor_applications::id.eq(id))
+            .load::<EditorApplication>(conn)?
+            .into_iter()
+            .next()
+            .ok_or(ServerError::NotFound)?;
+
+        if application.decided_at.is_some() {
+            return Err(Error::new(ServerError::Conflict));
+        }
+
+        diesel::update(editor_applications::table)
+            .filter(editor_applications::id.eq(id))
+            .set((
+                editor_applications::decided_by.eq(&user.username),
+                editor_applications::decided_at.eq(Utc::now().naive_utc()),
+                editor_applications::approved.eq(approved),
+            ))
+            .execute(conn)?;
+
+        let kind = if approved {
+            set_user_role(conn, &application.username, Role::Editor, true)?;
+            NotificationKind::EditorApplicationApproved
+        } else {
+            NotificationKind::EditorApplicationRejected
+        };
+
+        insert_notification(conn, &application.username, kind, None, Some(id), user)?;
+
+        Ok(())
+    })
+}
@@ -1,16 +1,27 @@
 use super::schema::ensembles;
-use super::{DbConn, User};
+use super::{
+    check_may_become_private, check_quota, check_unreferenced, insert_event, may_delete_entity,
+    move_to_trash, normalize_text, viewer_name, with_transaction, DbConn, DbTransaction,
+    EntityType, EventKind, User,
+};
 use crate::error::ServerError;
 use anyhow::{Error, Result};
 use diesel::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// A ensemble as represented within the API.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Ensemble {
+    /// Generated by the server, if a new ensemble is added without it.
+    #[serde(default)]
     pub id: String,
     pub name: String,
+
+    /// Whether the ensemble is only visible to the user that created it.
+    #[serde(default)]
+    pub private: bool,
 }
 
 /// A ensemble as represented in the database.
@@ -20,6 +31,7 @@ struct EnsembleRow {
     pub id: String,
     pub name: String,
     pub created_by: String,
+    pub private: bool,
 }
 
 impl From<EnsembleRow> for Ensemble {
@@ -27,6 +39,7 @@ impl From<EnsembleRow> for Ensemble {
         Ensemble {
             id: row.id,
             name: row.name,
+            private: row.private,
         }
     }
 }
@@ -34,18 +47,41 @@ impl From<EnsembleRow> for Ensemble {
 /// Update an existing ensemble or insert a new one. This will only work, if the provided user is
 /// allowed to do that.
 pub fn update_ensemble(conn: &DbConn, ensemble: &Ensemble, user: &User) -> Result<()> {
+    with_transaction(conn, |tx| update_ensemble_in(tx, ensemble, user))
+}
+
+/// Update an existing ensemble or insert a new one as part of a larger change. See
+/// [`update_ensemble`].
+pub fn update_ensemble_in(tx: &DbTransaction, ensemble: &Ensemble, user: &User) -> Result<()> {
+    let conn = tx.conn();
+
     let old_row = get_ensemble_row(conn, &ensemble.id)?;
+    let kind = if old_row.is_some() {
+        EventKind::Update
+    } else {
+        EventKind::Create
+    };
 
-    let allowed = match old_row {
+    if old_row.is_none() {
+        check_quota(conn, user)?;
+    }
+
+    let allowed = match &old_row {
+        Some(row) if row.private => user.may_edit_private(&row.created_by),
         Some(row) => user.may_edit(&row.created_by),
         None => user.may_create(),
     };
 
     if allowed {
+        if ensemble.private && matches!(&old_row, Some(row) if !row.private) {
+            check_may_become_private(conn, EntityType::Ensemble, &ensemble.id, user)?;
+        }
+
         let new_row = EnsembleRow {
             id: ensemble.id.clone(),
-            name: ensemble.name.clone(),
+            name: normalize_text(&ensemble.name),
             created_by: user.username.clone(),
+            private: ensemble.private,
         };
 
         diesel::insert_into(ensembles::table)
@@ -55,6 +91,8 @@ pub fn update_ensemble(conn: &DbConn, ensemble: &Ensemble, user: &User) -> Resul
             .set(&new_row)
             .execute(conn)?;
 
+        insert_event(conn, EntityType::Ensemble, &ensemble.id, kind, user)?;
+
         Ok(())
     } else {
         Err(Error::new(ServerError::Forbidden))
@@ -69,19 +107,49 @@ pub fn get_ensemble(conn: &DbConn, id: &str) -> Result<Option<Ensemble>> {
     Ok(ensemble)
 }
 
+/// Check whether a user may edit an existing ensemble. This fails, if the ensemble doesn't exist.
+pub fn may_edit_ensemble(conn: &DbConn, id: &str, user: &User) -> Result<bool> {
+    let row = get_ensemble_row(conn, id)?.ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+    let allowed = if row.private {
+        user.may_edit_private(&row.created_by)
+    } else {
+        user.may_edit(&row.created_by)
+    };
+
+    Ok(allowed)
+}
+
 /// Delete an existing ensemble. This will only work if the provided user is allowed to do that.
 pub fn delete_ensemble(conn: &DbConn, id: &str, user: &User) -> Result<()> {
-    if user.may_delete() {
-        diesel::delete(ensembles::table.filter(ensembles::id.eq(id))).execute(conn)?;
-        Ok(())
+    if may_delete_entity(conn, EntityType::Ensemble, id, user)? {
+        conn.transaction::<(), Error, _>(|| {
+            check_unreferenced(conn, EntityType::Ensemble, id)?;
+            move_to_trash(conn, EntityType::Ensemble, id, user)?;
+
+            let count =
+                diesel::delete(ensembles::table.filter(ensembles::id.eq(id))).execute(conn)?;
+
+            if count > 0 {
+                insert_event(conn, EntityType::Ensemble, id, EventKind::Delete, user)?;
+            }
+
+            Ok(())
+        })
     } else {
         Err(Error::new(ServerError::Forbidden))
     }
 }
 
-/// Get all existing ensembles.
-pub fn get_ensembles(conn: &DbConn) -> Result<Vec<Ensemble>> {
-    let rows = ensembles::table.load::<EnsembleRow>(conn)?;
+/// Get all existing ensembles that are public or private ensembles of the viewer.
+pub fn get_ensembles(conn: &DbConn, viewer: Option<&User>) -> Result<Vec<Ensemble>> {
+    let rows = ensembles::table
+        .filter(
+            ensembles::private
+                .eq(false)
+                .or(ensembles::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<EnsembleRow>(conn)?;
     let ensembles: Vec<Ensemble> = rows.into_iter().map(|row| row.into()).collect();
 
     Ok(ensembles)
@@ -0,0 +1,266 @@
+use super::schema::{ensembles, instruments, labels, mediums, performances, persons, recordings};
+use super::schema::{recording_works, track_sets, work_authors, works};
+use super::{get_ensemble, get_instrument, get_label, get_medium, get_person, get_recording};
+use super::{get_work, DbConn, DbTransaction, Scope};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The different kinds of entities that are stored in the database.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityType {
+    Person,
+    Ensemble,
+    Instrument,
+    Work,
+    Recording,
+    Medium,
+    Label,
+}
+
+impl EntityType {
+    /// All entity types.
+    pub const ALL: [EntityType; 7] = [
+        EntityType::Person,
+        EntityType::Ensemble,
+        EntityType::Instrument,
+        EntityType::Work,
+        EntityType::Recording,
+        EntityType::Medium,
+        EntityType::Label,
+    ];
+
+    /// Get the string representation of the entity type that is also used in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityType::Person => "person",
+            EntityType::Ensemble => "ensemble",
+            EntityType::Instrument => "instrument",
+            EntityType::Work => "work",
+            EntityType::Recording => "recording",
+            EntityType::Medium => "medium",
+            EntityType::Label => "label",
+        }
+    }
+
+    /// Get an entity type from its string representation.
+    pub fn parse(entity_type: &str) -> Option<EntityType> {
+        EntityType::ALL
+            .iter()
+            .find(|t| t.as_str() == entity_type)
+            .cloned()
+    }
+
+    /// Get the path segment that is used for entities of this type within the API, e.g.
+    /// "persons" for "/persons/{id}".
+    pub fn path(&self) -> &'static str {
+        match self {
+            EntityType::Person => "persons",
+            EntityType::Ensemble => "ensembles",
+            EntityType::Instrument => "instruments",
+            EntityType::Work => "works",
+            EntityType::Recording => "recordings",
+            EntityType::Medium => "mediums",
+            EntityType::Label => "labels",
+        }
+    }
+
+    /// Get an entity type from the path segment used within the API.
+    pub fn from_path(path: &str) -> Option<EntityType> {
+        EntityType::ALL.iter().find(|t| t.path() == path).cloned()
+    }
+
+    /// Get the scope that API keys need for changing entities of this type.
+    pub fn write_scope(&self) -> Scope {
+        match self {
+            EntityType::Person => Scope::WritePersons,
+            EntityType::Ensemble => Scope::WriteEnsembles,
+            EntityType::Instrument => Scope::WriteInstruments,
+            EntityType::Work => Scope::WriteWorks,
+            EntityType::Recording => Scope::WriteRecordings,
+            EntityType::Medium | EntityType::Label => Scope::WriteMediums,
+        }
+    }
+}
+
+/// Check whether an entity exists.
+pub fn entity_exists(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<bool> {
+    let result =
+        match entity_type {
+            EntityType::Person => diesel::select(exists(persons::table.filter(persons::id.eq(id))))
+                .get_result(conn)?,
+            EntityType::Ensemble => {
+                diesel::select(exists(ensembles::table.filter(ensembles::id.eq(id))))
+                    .get_result(conn)?
+            }
+            EntityType::Instrument => {
+                diesel::select(exists(instruments::table.filter(instruments::id.eq(id))))
+                    .get_result(conn)?
+            }
+            EntityType::Work => {
+                diesel::select(exists(works::table.filter(works::id.eq(id)))).get_result(conn)?
+            }
+            EntityType::Recording => {
+                diesel::select(exists(recordings::table.filter(recordings::id.eq(id))))
+                    .get_result(conn)?
+            }
+            EntityType::Medium => diesel::select(exists(mediums::table.filter(mediums::id.eq(id))))
+                .get_result(conn)?,
+            EntityType::Label => {
+                diesel::select(exists(labels::table.filter(labels::id.eq(id)))).get_result(conn)?
+            }
+        };
+
+    Ok(result)
+}
+
+/// The first part of the keys of advisory locks for entity IDs. The second part is a hash of the
+/// entity type and ID.
+const ENTITY_ID_LOCK: i32 = 0x776f6c67;
+
+/// Lock the ID of an entity until the transaction ends. Other transactions trying to lock the same
+/// ID wait until then, so that they see the entity, if it was added in the meantime.
+pub fn lock_entity_id(tx: &DbTransaction, entity_type: EntityType, id: &str) -> Result<()> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock($1, hashtext($2))")
+        .bind::<Integer, _>(ENTITY_ID_LOCK)
+        .bind::<Text, _>(format!("{}/{}", entity_type.as_str(), id))
+        .execute(tx.conn())?;
+
+    Ok(())
+}
+
+/// Get the current state of an entity in the format used for updates.
+pub fn get_entity_data(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<Option<Value>> {
+    let data = match entity_type {
+        EntityType::Person => get_person(conn, id)?.map(serde_json::to_value),
+        EntityType::Ensemble => get_ensemble(conn, id)?.map(serde_json::to_value),
+        EntityType::Instrument => get_instrument(conn, id)?.map(serde_json::to_value),
+        EntityType::Work => get_work(conn, id)?.map(serde_json::to_value),
+        EntityType::Recording => get_recording(conn, id)?.map(serde_json::to_value),
+        EntityType::Medium => get_medium(conn, id)?.map(serde_json::to_value),
+        EntityType::Label => get_label(conn, id)?.map(serde_json::to_value),
+    };
+
+    Ok(data.transpose()?)
+}
+
+/// An entity that refers to another one.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityReference {
+    pub entity_type: EntityType,
+    pub entity_id: String,
+}
+
+/// All entities that refer to an entity that should be deleted.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityReferences {
+    pub references: Vec<EntityReference>,
+}
+
+/// Get all entities that refer to an entity and prevent it from being deleted. Rows that are
+/// removed together with the entity, like the instrumentation of a work, are not included.
+pub fn get_referencing_entities(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+) -> Result<Vec<EntityReference>> {
+    let mut works = Vec::new();
+    let mut recordings = Vec::new();
+    let mut mediums = Vec::new();
+
+    match entity_type {
+        EntityType::Person => {
+            works = works::table
+                .filter(works::composer.eq(id))
+                .select(works::id)
+                .load(conn)?;
+
+            works.extend(
+                work_authors::table
+                    .filter(work_authors::person.eq(id))
+                    .select(work_authors::work)
+                    .load::<String>(conn)?,
+            );
+
+            recordings = performances::table
+                .filter(performances::person.eq(id))
+                .select(performances::recording)
+                .load(conn)?;
+        }
+        EntityType::Ensemble => {
+            recordings = performances::table
+                .filter(performances::ensemble.eq(id))
+                .select(performances::recording)
+                .load(conn)?;
+        }
+        EntityType::Instrument => {
+            recordings = performances::table
+                .filter(performances::role.eq(id))
+                .select(performances::recording)
+                .load(conn)?;
+        }
+        EntityType::Work => {
+            recordings = recordings::table
+                .filter(recordings::work.eq(id))
+                .select(recordings::id)
+                .load(conn)?;
+
+            recordings.extend(
+                recording_works::table
+                    .filter(recording_works::work.eq(id))
+                    .select(recording_works::recording)
+                    .load::<String>(conn)?,
+            );
+        }
+        EntityType::Recording => {
+            mediums = track_sets::table
+                .filter(track_sets::recording.eq(id))
+                .select(track_sets::medium)
+                .load(conn)?;
+        }
+        EntityType::Medium => (),
+        EntityType::Label => {
+            mediums = mediums::table
+                .filter(mediums::label.eq(id))
+                .select(mediums::id)
+                .load(conn)?;
+        }
+    }
+
+    let mut references: Vec<EntityReference> = works
+        .into_iter()
+        .map(|id| (EntityType::Work, id))
+        .chain(recordings.into_iter().map(|id| (EntityType::Recording, id)))
+        .chain(mediums.into_iter().map(|id| (EntityType::Medium, id)))
+        .map(|(entity_type, entity_id)| EntityReference {
+            entity_type,
+            entity_id,
+        })
+        .collect();
+
+    references.sort();
+    references.dedup();
+
+    Ok(references)
+}
+
+/// Fail with a conflict listing the referencing entities, if an entity can't be deleted because
+/// other entities still refer to it.
+pub fn check_unreferenced(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<()> {
+    let references = get_referencing_entities(conn, entity_type, id)?;
+
+    if references.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(ServerError::Referenced(EntityReferences {
+            references,
+        })))
+    }
+}
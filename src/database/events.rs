@@ -0,0 +1,222 @@
+use super::schema::events;
+use super::{delete_comments, insert_revision, invalidate_read_models, notify_change};
+use super::{DbConn, EntityType, User};
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use serde::{Deserialize, Serialize};
+
+/// The kind of change that happened to an entity.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum EventKind {
+    Create,
+    Update,
+    Delete,
+}
+
+impl EventKind {
+    /// All event kinds.
+    pub const ALL: [EventKind; 3] = [EventKind::Create, EventKind::Update, EventKind::Delete];
+
+    /// Get the string representation of the event kind that is also used in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Create => "create",
+            EventKind::Update => "update",
+            EventKind::Delete => "delete",
+        }
+    }
+
+    /// Get an event kind from its string representation.
+    pub fn parse(kind: &str) -> Option<EventKind> {
+        EventKind::ALL.iter().find(|k| k.as_str() == kind).cloned()
+    }
+}
+
+/// A change to an entity. The IDs of events are increasing in the order in which the changes were
+/// committed, so they can be used to find out about all changes that happened after a known event.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: i64,
+    pub entity_type: EntityType,
+    pub entity_id: String,
+    pub kind: EventKind,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A public notification on an [`Event`]. The event's ID is used as the revision.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub revision: i64,
+    pub entity_type: EntityType,
+    pub entity_id: String,
+    pub kind: EventKind,
+}
+
+impl From<Event> for Change {
+    fn from(event: Event) -> Change {
+        Change {
+            revision: event.id,
+            entity_type: event.entity_type,
+            entity_id: event.entity_id,
+            kind: event.kind,
+        }
+    }
+}
+
+/// The key of the advisory lock that is held while recording events.
+const EVENTS_LOCK: i64 = 0x776f6c66;
+
+/// Table data for a new [`Event`]. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "events"]
+struct NewEventRow {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub kind: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Table data for an [`Event`].
+#[derive(Queryable, Debug, Clone)]
+struct EventRow {
+    pub id: i64,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub kind: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl EventRow {
+    /// Convert the row to an [`Event`]. This fails, if the stored entity type or kind is unknown.
+    fn into_event(self) -> Result<Event> {
+        let entity_type = EntityType::parse(&self.entity_type)
+            .ok_or_else(|| anyhow!("Unknown entity type: {}", self.entity_type))?;
+        let kind = EventKind::parse(&self.kind)
+            .ok_or_else(|| anyhow!("Unknown event kind: {}", self.kind))?;
+
+        Ok(Event {
+            id: self.id,
+            entity_type,
+            entity_id: self.entity_id,
+            kind,
+            created_by: self.created_by,
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Record a change to an entity that was made by the provided user. This also invalidates the
+/// read models that include the entity, stores its new state as a revision and notifies its
+/// creator and watchers. This should be called within the same transaction as the change itself.
+/// Returns the ID of the event, which is the revision of the change.
+///
+/// Only one transaction at a time may record events, so that their IDs are increasing in the
+/// order in which they are committed. Otherwise, readers asking for the events after the last one
+/// they know would miss events of transactions that commit late.
+pub fn insert_event(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    kind: EventKind,
+    user: &User,
+) -> Result<i64> {
+    conn.transaction::<i64, Error, _>(|| {
+        diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+            .bind::<BigInt, _>(EVENTS_LOCK)
+            .execute(conn)?;
+
+        insert_event_locked(conn, entity_type, entity_id, kind, user)
+    })
+}
+
+/// Record a change to an entity while holding the lock for events. See [`insert_event`].
+fn insert_event_locked(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    kind: EventKind,
+    user: &User,
+) -> Result<i64> {
+    let row = NewEventRow {
+        entity_type: entity_type.as_str().to_string(),
+        entity_id: entity_id.to_string(),
+        kind: kind.as_str().to_string(),
+        created_by: user.username.clone(),
+        created_at: Utc::now().naive_utc(),
+    };
+
+    let id = diesel::insert_into(events::table)
+        .values(row)
+        .returning(events::id)
+        .get_result::<i64>(conn)?;
+
+    invalidate_read_models(conn, entity_type, entity_id)?;
+
+    if kind != EventKind::Delete {
+        insert_revision(conn, id, entity_type, entity_id)?;
+    }
+
+    notify_change(conn, entity_type, entity_id, kind, user)?;
+
+    if kind == EventKind::Delete {
+        delete_comments(conn, entity_type, entity_id)?;
+    }
+
+    Ok(id)
+}
+
+/// Get an event by its ID.
+pub fn get_event(conn: &DbConn, id: i64) -> Result<Option<Event>> {
+    events::table
+        .filter(events::id.eq(id))
+        .first::<EventRow>(conn)
+        .optional()?
+        .map(EventRow::into_event)
+        .transpose()
+}
+
+/// Get up to `limit` events that happened after the event with the ID `after`, oldest first.
+pub fn get_events_after(conn: &DbConn, after: i64, limit: i64) -> Result<Vec<Event>> {
+    let rows = events::table
+        .filter(events::id.gt(after))
+        .order(events::id.asc())
+        .limit(limit)
+        .load::<EventRow>(conn)?;
+
+    rows.into_iter().map(|row| row.into_event()).collect()
+}
+
+/// Get the user that made the latest change to an entity, if there is any recorded change.
+pub fn get_last_event_author(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+) -> Result<Option<String>> {
+    let author = events::table
+        .filter(events::entity_type.eq(entity_type.as_str()))
+        .filter(events::entity_id.eq(entity_id))
+        .order(events::id.desc())
+        .select(events::created_by)
+        .first::<String>(conn)
+        .optional()?;
+
+    Ok(author)
+}
+
+/// Get the ID of the latest event or zero, if there are no events yet.
+pub fn get_last_event_id(conn: &DbConn) -> Result<i64> {
+    let id = events::table
+        .select(diesel::dsl::max(events::id))
+        .first::<Option<i64>>(conn)?
+        .unwrap_or(0);
+
+    Ok(id)
+}
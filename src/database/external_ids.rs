@@ -0,0 +1,223 @@
+use super::schema::external_ids;
+use super::{DbConn, EntityReference, EntityType};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use diesel::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// An external service that identifies mediums, recordings or persons.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExternalSource {
+    /// Spotify URIs like "spotify:album:4uLU6hMCjMI75M1A2tKUQC".
+    Spotify,
+
+    /// Numeric Apple Music IDs.
+    AppleMusic,
+
+    /// Qobuz album or track IDs.
+    Qobuz,
+
+    /// Wikidata item IDs like "Q254".
+    Wikidata,
+}
+
+impl ExternalSource {
+    /// All external sources.
+    pub const ALL: [ExternalSource; 4] = [
+        ExternalSource::Spotify,
+        ExternalSource::AppleMusic,
+        ExternalSource::Qobuz,
+        ExternalSource::Wikidata,
+    ];
+
+    /// Get the string representation of the source as used within the API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExternalSource::Spotify => "spotify",
+            ExternalSource::AppleMusic => "appleMusic",
+            ExternalSource::Qobuz => "qobuz",
+            ExternalSource::Wikidata => "wikidata",
+        }
+    }
+
+    /// Get a source from its string representation.
+    pub fn parse(source: &str) -> Option<ExternalSource> {
+        ExternalSource::ALL
+            .iter()
+            .find(|s| s.as_str() == source)
+            .cloned()
+    }
+}
+
+/// The identifier of a medium, recording or person within an external service.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalId {
+    pub source: ExternalSource,
+    pub id: String,
+}
+
+/// Table data for a new external ID. The ID of the row will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "external_ids"]
+struct NewExternalIdRow {
+    pub medium: Option<String>,
+    pub recording: Option<String>,
+    pub source: String,
+    pub external_id: String,
+    pub person: Option<String>,
+}
+
+/// Table data for an external ID.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name = "external_ids"]
+struct ExternalIdRow {
+    pub id: i64,
+    pub medium: Option<String>,
+    pub recording: Option<String>,
+    pub source: String,
+    pub external_id: String,
+    pub person: Option<String>,
+}
+
+impl ExternalIdRow {
+    /// Get the entity this row belongs to.
+    fn entity(&self) -> Result<EntityReference> {
+        match (&self.medium, &self.recording, &self.person) {
+            (Some(medium), None, None) => Ok(EntityReference {
+                entity_type: EntityType::Medium,
+                entity_id: medium.clone(),
+            }),
+            (None, Some(recording), None) => Ok(EntityReference {
+                entity_type: EntityType::Recording,
+                entity_id: recording.clone(),
+            }),
+            (None, None, Some(person)) => Ok(EntityReference {
+                entity_type: EntityType::Person,
+                entity_id: person.clone(),
+            }),
+            _ => Err(anyhow!("Invalid external ID: {}", self.id)),
+        }
+    }
+}
+
+/// Load the rows of the external IDs of a medium, recording or person.
+fn get_external_id_rows(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+) -> Result<Vec<ExternalIdRow>> {
+    let query = external_ids::table.order_by(external_ids::id).into_boxed();
+
+    let query = match entity_type {
+        EntityType::Medium => query.filter(external_ids::medium.eq(id)),
+        EntityType::Recording => query.filter(external_ids::recording.eq(id)),
+        EntityType::Person => query.filter(external_ids::person.eq(id)),
+        _ => return Ok(Vec::new()),
+    };
+
+    Ok(query.load::<ExternalIdRow>(conn)?)
+}
+
+/// Get the external IDs of a medium, recording or person. Other entities don't have external
+/// IDs.
+pub fn get_external_ids(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+) -> Result<Vec<ExternalId>> {
+    let mut external_ids = Vec::new();
+
+    for row in get_external_id_rows(conn, entity_type, id)? {
+        let source = ExternalSource::parse(&row.source)
+            .ok_or_else(|| anyhow!("Unknown external source: {}", row.source))?;
+
+        external_ids.push(ExternalId {
+            source,
+            id: row.external_id,
+        });
+    }
+
+    Ok(external_ids)
+}
+
+/// Replace the external IDs of a medium, recording or person. This should be called within the
+/// same transaction as the update of the entity itself. If one of the IDs already belongs to
+/// another entity, this fails with a conflict.
+pub fn update_external_ids(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    ids: &[ExternalId],
+) -> Result<()> {
+    let (medium, recording, person) = match entity_type {
+        EntityType::Medium => (Some(id.to_string()), None, None),
+        EntityType::Recording => (None, Some(id.to_string()), None),
+        EntityType::Person => (None, None, Some(id.to_string())),
+        _ => return Err(Error::new(ServerError::BadRequest)),
+    };
+
+    for external_id in ids {
+        if let Some(entity) = lookup_external_id(conn, external_id.source, &external_id.id)? {
+            if entity.entity_type != entity_type || entity.entity_id != id {
+                return Err(Error::new(ServerError::Conflict));
+            }
+        }
+    }
+
+    // Rows of IDs that are kept stay untouched, so only changed IDs are deleted or inserted.
+
+    let old_rows = get_external_id_rows(conn, entity_type, id)?;
+
+    for old in &old_rows {
+        let kept = ids
+            .iter()
+            .any(|new| new.source.as_str() == old.source && new.id == old.external_id);
+
+        if !kept {
+            diesel::delete(old).execute(conn)?;
+        }
+    }
+
+    for external_id in ids {
+        let exists = old_rows.iter().any(|old| {
+            old.source == external_id.source.as_str() && old.external_id == external_id.id
+        });
+
+        if !exists {
+            diesel::insert_into(external_ids::table)
+                .values(NewExternalIdRow {
+                    medium: medium.clone(),
+                    recording: recording.clone(),
+                    source: external_id.source.as_str().to_string(),
+                    external_id: external_id.id.clone(),
+                    person: person.clone(),
+                })
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Find the medium, recording or person with an ID within an external service. This also returns private
+/// entities, so the caller has to check whether the viewer may see them.
+pub fn lookup_external_id(
+    conn: &DbConn,
+    source: ExternalSource,
+    id: &str,
+) -> Result<Option<EntityReference>> {
+    let row = external_ids::table
+        .filter(external_ids::source.eq(source.as_str()))
+        .filter(external_ids::external_id.eq(id))
+        .first::<ExternalIdRow>(conn)
+        .optional()?;
+
+    match row {
+        Some(row) => Ok(Some(row.entity()?)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,160 @@
+use super::schema::idempotency_keys;
+use super::DbConn;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+/// How long idempotency keys are remembered in hours.
+const IDEMPOTENCY_KEY_VALIDITY: i64 = 24;
+
+/// How long a request may be processed in minutes. After that, the request is assumed to have
+/// been interrupted and its key may be used again.
+const IDEMPOTENCY_KEY_TIMEOUT: i64 = 10;
+
+/// A response that was stored for replaying it to retried requests.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// The state of a request with an idempotency key.
+#[derive(Debug, Clone)]
+pub enum IdempotentRequest {
+    /// The key wasn't used before and has been reserved for this request.
+    New,
+
+    /// Another request with the same key is still being processed and hasn't timed out yet.
+    Pending,
+
+    /// The key was already used for a different request.
+    Mismatch,
+
+    /// The same request was already handled and resulted in this response.
+    Done(StoredResponse),
+}
+
+/// Table data for an idempotency key.
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "idempotency_keys"]
+struct IdempotencyKeyRow {
+    pub username: String,
+    pub key: String,
+    pub request_hash: String,
+    pub status: Option<i16>,
+    pub content_type: Option<String>,
+    pub body: Option<Vec<u8>>,
+}
+
+/// Reserve an idempotency key of a user for a request, unless it was already used. Requests without
+/// a user use an empty username. The request hash should identify the request including its body
+/// and credentials.
+pub fn begin_idempotent_request(
+    conn: &DbConn,
+    username: &str,
+    key: &str,
+    request_hash: &str,
+) -> Result<IdempotentRequest> {
+    let row = IdempotencyKeyRow {
+        username: username.to_string(),
+        key: key.to_string(),
+        request_hash: request_hash.to_string(),
+        status: None,
+        content_type: None,
+        body: None,
+    };
+
+    let count = diesel::insert_into(idempotency_keys::table)
+        .values(row)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    if count > 0 {
+        return Ok(IdempotentRequest::New);
+    }
+
+    // Take over the key, if the same request was started before but didn't finish in time.
+    let timeout = Utc::now().naive_utc() - Duration::minutes(IDEMPOTENCY_KEY_TIMEOUT);
+
+    let count = diesel::update(idempotency_keys::table)
+        .filter(idempotency_keys::username.eq(username))
+        .filter(idempotency_keys::key.eq(key))
+        .filter(idempotency_keys::request_hash.eq(request_hash))
+        .filter(idempotency_keys::status.is_null())
+        .filter(idempotency_keys::created_at.lt(timeout))
+        .set(idempotency_keys::created_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?;
+
+    if count > 0 {
+        return Ok(IdempotentRequest::New);
+    }
+
+    let row: IdempotencyKeyRow = idempotency_keys::table
+        .select((
+            idempotency_keys::username,
+            idempotency_keys::key,
+            idempotency_keys::request_hash,
+            idempotency_keys::status,
+            idempotency_keys::content_type,
+            idempotency_keys::body,
+        ))
+        .filter(idempotency_keys::username.eq(username))
+        .filter(idempotency_keys::key.eq(key))
+        .first(conn)?;
+
+    let state = if row.request_hash != request_hash {
+        IdempotentRequest::Mismatch
+    } else if let Some(status) = row.status {
+        IdempotentRequest::Done(StoredResponse {
+            status: status as u16,
+            content_type: row.content_type,
+            body: row.body.unwrap_or_default(),
+        })
+    } else {
+        IdempotentRequest::Pending
+    };
+
+    Ok(state)
+}
+
+/// Store the response to a request with a reserved idempotency key.
+pub fn finish_idempotent_request(
+    conn: &DbConn,
+    username: &str,
+    key: &str,
+    response: &StoredResponse,
+) -> Result<()> {
+    diesel::update(idempotency_keys::table)
+        .filter(idempotency_keys::username.eq(username))
+        .filter(idempotency_keys::key.eq(key))
+        .set((
+            idempotency_keys::status.eq(response.status as i16),
+            idempotency_keys::content_type.eq(&response.content_type),
+            idempotency_keys::body.eq(&response.body),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Release a reserved idempotency key, so that the request can be retried.
+pub fn abort_idempotent_request(conn: &DbConn, username: &str, key: &str) -> Result<()> {
+    diesel::delete(idempotency_keys::table)
+        .filter(idempotency_keys::username.eq(username))
+        .filter(idempotency_keys::key.eq(key))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Delete all idempotency keys that have expired. This returns the number of deleted keys.
+pub fn delete_expired_idempotency_keys(conn: &DbConn) -> Result<usize> {
+    let expired = Utc::now().naive_utc() - Duration::hours(IDEMPOTENCY_KEY_VALIDITY);
+
+    let count = diesel::delete(idempotency_keys::table)
+        .filter(idempotency_keys::created_at.lt(expired))
+        .execute(conn)?;
+
+    Ok(count)
+}
@@ -0,0 +1,165 @@
+use super::schema::images;
+use super::{may_edit_ensemble, may_edit_person, with_transaction, DbConn, EntityType, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// A photo of a person or an ensemble together with the information needed for showing it.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Image {
+    pub id: String,
+
+    /// The license under which the image may be used, e.g. "CC BY-SA 4.0".
+    pub license: String,
+
+    /// Who has to be credited when showing the image.
+    pub attribution: String,
+
+    /// Where the image was published before, if anywhere.
+    pub source: Option<String>,
+
+    /// The width of the stored image in pixels.
+    pub width: i32,
+
+    /// The height of the stored image in pixels.
+    pub height: i32,
+
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Table data for an [`Image`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "images"]
+struct ImageRow {
+    pub id: String,
+    pub person: Option<String>,
+    pub ensemble: Option<String>,
+    pub license: String,
+    pub attribution: String,
+    pub source: Option<String>,
+    pub width: i32,
+    pub height: i32,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<ImageRow> for Image {
+    fn from(row: ImageRow) -> Image {
+        Image {
+            id: row.id,
+            license: row.license,
+            attribution: row.attribution,
+            source: row.source,
+            width: row.width,
+            height: row.height,
+            created_by: row.created_by,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Get the row of the image of a person or an ensemble. Other entities don't have images.
+fn get_image_row(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<Option<ImageRow>> {
+    let query = images::table.into_boxed();
+
+    let query = match entity_type {
+        EntityType::Person => query.filter(images::person.eq(id)),
+        EntityType::Ensemble => query.filter(images::ensemble.eq(id)),
+        _ => return Ok(None),
+    };
+
+    Ok(query.first::<ImageRow>(conn).optional()?)
+}
+
+/// Get the image of a person or an ensemble.
+pub fn get_image(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<Option<Image>> {
+    Ok(get_image_row(conn, entity_type, id)?.map(|row| row.into()))
+}
+
+/// Check whether a user may change the image of a person or an ensemble. This fails, if the
+/// entity doesn't exist or can't have an image.
+pub fn may_edit_image(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+) -> Result<bool> {
+    match entity_type {
+        EntityType::Person => may_edit_person(conn, id, user),
+        EntityType::Ensemble => may_edit_ensemble(conn, id, user),
+        _ => Err(Error::new(ServerError::NotFound)),
+    }
+}
+
+/// Set the image of a person or an ensemble. This returns the ID of the replaced image, if there
+/// was one, so that its files can be removed. The user has to be allowed to edit the entity.
+pub fn update_image(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    image: &Image,
+    user: &User,
+) -> Result<Option<String>> {
+    if !may_edit_image(conn, entity_type, id, user)? {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let (person, ensemble) = match entity_type {
+        EntityType::Person => (Some(id.to_string()), None),
+        _ => (None, Some(id.to_string())),
+    };
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+        let old_row = get_image_row(conn, entity_type, id)?;
+
+        if let Some(old_row) = &old_row {
+            diesel::delete(images::table.filter(images::id.eq(&old_row.id))).execute(conn)?;
+        }
+
+        diesel::insert_into(images::table)
+            .values(ImageRow {
+                id: image.id.clone(),
+                person,
+                ensemble,
+                license: image.license.clone(),
+                attribution: image.attribution.clone(),
+                source: image.source.clone(),
+                width: image.width,
+                height: image.height,
+                created_by: image.created_by.clone(),
+                created_at: image.created_at,
+            })
+            .execute(conn)?;
+
+        Ok(old_row.map(|row| row.id))
+    })
+}
+
+/// Remove the image of a person or an ensemble. This returns the ID of the removed image, so
+/// that its files can be removed. The user has to be allowed to edit the entity.
+pub fn delete_image(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+) -> Result<String> {
+    if !may_edit_image(conn, entity_type, id, user)? {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let row = get_image_row(conn, entity_type, id)?.ok_or(ServerError::NotFound)?;
+    diesel::delete(images::table.filter(images::id.eq(&row.id))).execute(conn)?;
+
+    Ok(row.id)
+}
+
+/// Get the IDs of all images. This is used for removing files of images that don't exist anymore,
+/// e.g. because their person was deleted.
+pub fn get_image_ids(conn: &DbConn) -> Result<Vec<String>> {
+    Ok(images::table.select(images::id).load::<String>(conn)?)
+}
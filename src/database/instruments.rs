@@ -1,16 +1,27 @@
 use super::schema::instruments;
-use super::{DbConn, User};
+use super::{
+    check_may_become_private, check_quota, check_unreferenced, insert_event, may_delete_entity,
+    move_to_trash, normalize_text, viewer_name, with_transaction, DbConn, DbTransaction,
+    EntityType, EventKind, User,
+};
 use crate::error::ServerError;
 use anyhow::{Error, Result};
 use diesel::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// A instrument as represented within the API.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Instrument {
+    /// Generated by the server, if a new instrument is added without it.
+    #[serde(default)]
     pub id: String,
     pub name: String,
+
+    /// Whether the instrument is only visible to the user that created it.
+    #[serde(default)]
+    pub private: bool,
 }
 
 /// A instrument as represented in the database.
@@ -20,6 +31,7 @@ struct InstrumentRow {
     pub id: String,
     pub name: String,
     pub created_by: String,
+    pub private: bool,
 }
 
 impl From<InstrumentRow> for Instrument {
@@ -27,6 +39,7 @@ impl From<InstrumentRow> for Instrument {
         Instrument {
             id: row.id,
             name: row.name,
+            private: row.private,
         }
     }
 }
@@ -34,18 +47,45 @@ impl From<InstrumentRow> for Instrument {
 /// Update an existing instrument or insert a new one. This will only work, if the provided user is
 /// allowed to do that.
 pub fn update_instrument(conn: &DbConn, instrument: &Instrument, user: &User) -> Result<()> {
+    with_transaction(conn, |tx| update_instrument_in(tx, instrument, user))
+}
+
+/// Update an existing instrument or insert a new one as part of a larger change. See
+/// [`update_instrument`].
+pub fn update_instrument_in(
+    tx: &DbTransaction,
+    instrument: &Instrument,
+    user: &User,
+) -> Result<()> {
+    let conn = tx.conn();
+
     let old_row = get_instrument_row(conn, &instrument.id)?;
+    let kind = if old_row.is_some() {
+        EventKind::Update
+    } else {
+        EventKind::Create
+    };
+
+    if old_row.is_none() {
+        check_quota(conn, user)?;
+    }
 
-    let allowed = match old_row {
+    let allowed = match &old_row {
+        Some(row) if row.private => user.may_edit_private(&row.created_by),
         Some(row) => user.may_edit(&row.created_by),
         None => user.may_create(),
     };
 
     if allowed {
+        if instrument.private && matches!(&old_row, Some(row) if !row.private) {
+            check_may_become_private(conn, EntityType::Instrument, &instrument.id, user)?;
+        }
+
         let new_row = InstrumentRow {
             id: instrument.id.clone(),
-            name: instrument.name.clone(),
+            name: normalize_text(&instrument.name),
             created_by: user.username.clone(),
+            private: instrument.private,
         };
 
         diesel::insert_into(instruments::table)
@@ -55,6 +95,8 @@ pub fn update_instrument(conn: &DbConn, instrument: &Instrument, user: &User) ->
             .set(&new_row)
             .execute(conn)?;
 
+        insert_event(conn, EntityType::Instrument, &instrument.id, kind, user)?;
+
         Ok(())
     } else {
         Err(Error::new(ServerError::Forbidden))
@@ -71,17 +113,34 @@ pub fn get_instrument(conn: &DbConn, id: &str) -> Result<Option<Instrument>> {
 
 /// Delete an existing instrument. This will only work if the provided user is allowed to do that.
 pub fn delete_instrument(conn: &DbConn, id: &str, user: &User) -> Result<()> {
-    if user.may_delete() {
-        diesel::delete(instruments::table.filter(instruments::id.eq(id))).execute(conn)?;
-        Ok(())
+    if may_delete_entity(conn, EntityType::Instrument, id, user)? {
+        conn.transaction::<(), Error, _>(|| {
+            check_unreferenced(conn, EntityType::Instrument, id)?;
+            move_to_trash(conn, EntityType::Instrument, id, user)?;
+
+            let count =
+                diesel::delete(instruments::table.filter(instruments::id.eq(id))).execute(conn)?;
+
+            if count > 0 {
+                insert_event(conn, EntityType::Instrument, id, EventKind::Delete, user)?;
+            }
+
+            Ok(())
+        })
     } else {
         Err(Error::new(ServerError::Forbidden))
     }
 }
 
-/// Get all existing instruments.
-pub fn get_instruments(conn: &DbConn) -> Result<Vec<Instrument>> {
-    let rows = instruments::table.load::<InstrumentRow>(conn)?;
+/// Get all existing instruments that are public or private instruments of the viewer.
+pub fn get_instruments(conn: &DbConn, viewer: Option<&User>) -> Result<Vec<Instrument>> {
+    let rows = instruments::table
+        .filter(
+            instruments::private
+                .eq(false)
+                .or(instruments::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<InstrumentRow>(conn)?;
     let instruments: Vec<Instrument> = rows.into_iter().map(|row| row.into()).collect();
 
     Ok(instruments)
@@ -0,0 +1,75 @@
+use super::schema::invitations;
+use super::{generate_id, DbConn, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// An invitation code allowing to register a new user.
+#[derive(Insertable, Queryable, Serialize, Debug, Clone)]
+#[table_name = "invitations"]
+#[serde(rename_all = "camelCase")]
+pub struct Invitation {
+    /// The secret code that has to be provided on registration.
+    pub code: String,
+
+    /// The user that created the invitation.
+    pub created_by: String,
+
+    /// The user that registered using this invitation, if it was already used.
+    pub used_by: Option<String>,
+}
+
+/// Create a new invitation with a random code. The user has to be an administrator.
+pub fn insert_invitation(conn: &DbConn, user: &User) -> Result<Invitation> {
+    if user.may_administrate() {
+        let invitation = Invitation {
+            code: generate_id(),
+            created_by: user.username.clone(),
+            used_by: None,
+        };
+
+        diesel::insert_into(invitations::table)
+            .values(&invitation)
+            .execute(conn)?;
+
+        Ok(invitation)
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Get all existing invitations. The user has to be an administrator.
+pub fn get_invitations(conn: &DbConn, user: &User) -> Result<Vec<Invitation>> {
+    if user.may_administrate() {
+        Ok(invitations::table.load::<Invitation>(conn)?)
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Mark an unused invitation as used by a newly registered user. This will fail, if the
+/// invitation doesn't exist or was already used.
+pub fn use_invitation(conn: &DbConn, code: &str, username: &str) -> Result<()> {
+    let count = diesel::update(invitations::table)
+        .filter(invitations::code.eq(code))
+        .filter(invitations::used_by.is_null())
+        .set(invitations::used_by.eq(username))
+        .execute(conn)?;
+
+    if count == 1 {
+        Ok(())
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Delete an existing invitation. The user has to be an administrator.
+pub fn delete_invitation(conn: &DbConn, code: &str, user: &User) -> Result<()> {
+    if user.may_administrate() {
+        diesel::delete(invitations::table.filter(invitations::code.eq(code))).execute(conn)?;
+        Ok(())
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
@@ -0,0 +1,153 @@
+use super::schema::labels;
+use super::{
+    check_may_become_private, check_quota, check_unreferenced, insert_event, may_delete_entity,
+    move_to_trash, normalize_text, viewer_name, with_transaction, DbConn, DbTransaction,
+    EntityType, EventKind, User,
+};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A record label that releases mediums as represented within the API.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Label {
+    /// Left out for new labels, so that the server generates it.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+
+    /// Whether the label is only visible to the user that created it.
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// A label as represented in the database.
+#[derive(Insertable, Queryable, AsChangeset, Debug, Clone)]
+#[table_name = "labels"]
+struct LabelRow {
+    pub id: String,
+    pub name: String,
+    pub created_by: String,
+    pub private: bool,
+}
+
+impl From<LabelRow> for Label {
+    fn from(row: LabelRow) -> Label {
+        Label {
+            id: row.id,
+            name: row.name,
+            private: row.private,
+        }
+    }
+}
+
+/// Update an existing label or insert a new one. This will only work, if the provided user is
+/// allowed to do that.
+pub fn update_label(conn: &DbConn, label: &Label, user: &User) -> Result<()> {
+    with_transaction(conn, |tx| update_label_in(tx, label, user))
+}
+
+/// Update an existing label or insert a new one as part of a larger change. See
+/// [`update_label`].
+pub fn update_label_in(tx: &DbTransaction, label: &Label, user: &User) -> Result<()> {
+    let conn = tx.conn();
+
+    let old_row = get_label_row(conn, &label.id)?;
+    let kind = if old_row.is_some() {
+        EventKind::Update
+    } else {
+        EventKind::Create
+    };
+
+    if old_row.is_none() {
+        check_quota(conn, user)?;
+    }
+
+    let allowed = match &old_row {
+        Some(row) if row.private => user.may_edit_private(&row.created_by),
+        Some(row) => user.may_edit(&row.created_by),
+        None => user.may_create(),
+    };
+
+    if allowed {
+        if label.private && matches!(&old_row, Some(row) if !row.private) {
+            check_may_become_private(conn, EntityType::Label, &label.id, user)?;
+        }
+
+        let new_row = LabelRow {
+            id: label.id.clone(),
+            name: normalize_text(&label.name),
+            created_by: user.username.clone(),
+            private: label.private,
+        };
+
+        diesel::insert_into(labels::table)
+            .values(&new_row)
+            .on_conflict(labels::id)
+            .do_update()
+            .set(&new_row)
+            .execute(conn)?;
+
+        insert_event(conn, EntityType::Label, &label.id, kind, user)?;
+
+        Ok(())
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Get an existing label.
+pub fn get_label(conn: &DbConn, id: &str) -> Result<Option<Label>> {
+    let row = get_label_row(conn, id)?;
+    let label = row.map(|row| row.into());
+
+    Ok(label)
+}
+
+/// Delete an existing label. This will only work if the provided user is allowed to do that.
+pub fn delete_label(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+    if may_delete_entity(conn, EntityType::Label, id, user)? {
+        conn.transaction::<(), Error, _>(|| {
+            check_unreferenced(conn, EntityType::Label, id)?;
+            move_to_trash(conn, EntityType::Label, id, user)?;
+
+            let count = diesel::delete(labels::table.filter(labels::id.eq(id))).execute(conn)?;
+
+            if count > 0 {
+                insert_event(conn, EntityType::Label, id, EventKind::Delete, user)?;
+            }
+
+            Ok(())
+        })
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Get all existing labels that are public or private labels of the viewer.
+pub fn get_labels(conn: &DbConn, viewer: Option<&User>) -> Result<Vec<Label>> {
+    let rows = labels::table
+        .filter(
+            labels::private
+                .eq(false)
+                .or(labels::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<LabelRow>(conn)?;
+    let labels: Vec<Label> = rows.into_iter().map(|row| row.into()).collect();
+
+    Ok(labels)
+}
+
+/// Get a label row if it exists.
+fn get_label_row(conn: &DbConn, id: &str) -> Result<Option<LabelRow>> {
+    let row = labels::table
+        .filter(labels::id.eq(id))
+        .load::<LabelRow>(conn)?
+        .into_iter()
+        .next();
+
+    Ok(row)
+}
@@ -0,0 +1,226 @@
+use actix_web::error::BlockingError;
+use actix_web::web;
+use diesel::connection::{AnsiTransactionManager, SimpleConnection};
+use diesel::deserialize::{Queryable, QueryableByName};
+use diesel::pg::{Pg, TransactionBuilder};
+use diesel::query_builder::{AsQuery, QueryFragment, QueryId};
+use diesel::sql_types::HasSqlType;
+use diesel::{debug_query, Connection, ConnectionResult, PgConnection, QueryResult};
+use futures::Future;
+use lazy_static::lazy_static;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    /// Queries taking longer than this are logged. This is read from the environment variable
+    /// "WOLFGANG_SLOW_QUERY_MS". If it is not set, no queries are logged.
+    static ref SLOW_QUERY_THRESHOLD: Option<Duration> = std::env::var("WOLFGANG_SLOW_QUERY_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .map(Duration::from_millis);
+}
+
+thread_local! {
+    /// The context of the queries that are run on this thread.
+    static CONTEXT: RefCell<Option<Arc<QueryContext>>> = const { RefCell::new(None) };
+}
+
+/// Information on the request that queries are run for. This is used for logging slow queries
+/// and collects the time spent within the database.
+#[derive(Debug)]
+pub struct QueryContext {
+    /// A description of the request, e.g. its route and the entity ID.
+    pub label: String,
+
+    /// The number of queries that were run.
+    queries: AtomicU64,
+
+    /// The total time spent running queries in microseconds.
+    duration: AtomicU64,
+}
+
+impl QueryContext {
+    /// Create a new context for a request.
+    pub fn new(label: String) -> Self {
+        Self {
+            label,
+            queries: AtomicU64::new(0),
+            duration: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the number of queries that were run so far.
+    pub fn queries(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    /// Get the total time spent running queries so far.
+    pub fn duration(&self) -> Duration {
+        Duration::from_micros(self.duration.load(Ordering::Relaxed))
+    }
+
+    /// Record a query that took the provided time.
+    fn add(&self, duration: Duration) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        self.duration
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Run a function with the provided context for all queries on the current thread.
+pub fn with_query_context<T, F>(context: Option<Arc<QueryContext>>, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let previous = CONTEXT.with(|current| current.replace(context));
+    let result = f();
+    CONTEXT.with(|current| current.replace(previous));
+    result
+}
+
+/// Get the context for queries on the current thread.
+pub fn get_query_context() -> Option<Arc<QueryContext>> {
+    CONTEXT.with(|current| current.borrow().clone())
+}
+
+/// Run a blocking function on the thread pool like [`web::block`]. The current context for
+/// queries is kept, so that queries run by the function are attributed to the right request.
+pub fn block<F, I, E>(f: F) -> impl Future<Output = Result<I, BlockingError<E>>>
+where
+    F: FnOnce() -> Result<I, E> + Send + 'static,
+    I: Send + 'static,
+    E: Send + Debug + 'static,
+{
+    let context = get_query_context();
+    web::block(move || with_query_context(context, f))
+}
+
+/// A PostgreSQL connection that measures the time of each query. Queries that exceed the
+/// configured threshold are logged together with the current [`QueryContext`].
+pub struct LoggingConnection {
+    inner: PgConnection,
+    read_only: bool,
+}
+
+impl LoggingConnection {
+    /// Make the connection read-only. Afterwards, statements that try to change anything will
+    /// fail.
+    pub fn set_read_only(&mut self) -> QueryResult<()> {
+        self.batch_execute("SET default_transaction_read_only = on")?;
+        self.read_only = true;
+        Ok(())
+    }
+
+    /// Check whether the connection was made read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Create a builder for a transaction with custom settings, see
+    /// [`PgConnection::build_transaction`].
+    pub fn build_transaction(&self) -> TransactionBuilder<'_> {
+        self.inner.build_transaction()
+    }
+
+    /// Run a query and record how long it took. The query is only described, if it was slow.
+    fn run<T, D, F>(&self, describe: D, f: F) -> QueryResult<T>
+    where
+        D: FnOnce() -> String,
+        F: FnOnce(&PgConnection) -> QueryResult<T>,
+    {
+        let start = Instant::now();
+        let result = f(&self.inner);
+        let duration = start.elapsed();
+
+        let context = get_query_context();
+
+        if let Some(context) = &context {
+            context.add(duration);
+        }
+
+        if let Some(threshold) = *SLOW_QUERY_THRESHOLD {
+            if duration >= threshold {
+                let label = match &context {
+                    Some(context) => context.label.as_str(),
+                    None => "no request",
+                };
+
+                println!(
+                    "Slow query ({} ms, {}): {}",
+                    duration.as_millis(),
+                    label,
+                    describe()
+                );
+            }
+        }
+
+        result
+    }
+}
+
+impl SimpleConnection for LoggingConnection {
+    fn batch_execute(&self, query: &str) -> QueryResult<()> {
+        self.run(|| query.to_string(), |conn| conn.batch_execute(query))
+    }
+}
+
+impl Connection for LoggingConnection {
+    type Backend = Pg;
+    type TransactionManager = AnsiTransactionManager;
+
+    fn establish(database_url: &str) -> ConnectionResult<Self> {
+        Ok(Self {
+            inner: PgConnection::establish(database_url)?,
+            read_only: false,
+        })
+    }
+
+    fn execute(&self, query: &str) -> QueryResult<usize> {
+        self.run(|| query.to_string(), |conn| conn.execute(query))
+    }
+
+    fn query_by_index<T, U>(&self, source: T) -> QueryResult<Vec<U>>
+    where
+        T: AsQuery,
+        T::Query: QueryFragment<Pg> + QueryId,
+        Pg: HasSqlType<T::SqlType>,
+        U: Queryable<T::SqlType, Pg>,
+    {
+        // The query is consumed when running it, so it has to be described beforehand.
+        let query = source.as_query();
+        let description = SLOW_QUERY_THRESHOLD.map(|_| debug_query::<Pg, _>(&query).to_string());
+
+        self.run(
+            || description.unwrap_or_default(),
+            |conn| conn.query_by_index(query),
+        )
+    }
+
+    fn query_by_name<T, U>(&self, source: &T) -> QueryResult<Vec<U>>
+    where
+        T: QueryFragment<Pg> + QueryId,
+        U: QueryableByName<Pg>,
+    {
+        self.run(
+            || debug_query::<Pg, _>(source).to_string(),
+            |conn| conn.query_by_name(source),
+        )
+    }
+
+    fn execute_returning_count<T>(&self, source: &T) -> QueryResult<usize>
+    where
+        T: QueryFragment<Pg> + QueryId,
+    {
+        self.run(
+            || debug_query::<Pg, _>(source).to_string(),
+            |conn| conn.execute_returning_count(source),
+        )
+    }
+
+    fn transaction_manager(&self) -> &Self::TransactionManager {
+        self.inner.transaction_manager()
+    }
+}
@@ -0,0 +1,242 @@
+use super::person_relations::{delete_relation, get_relation_rows, insert_relation, RelationTable};
+use super::{get_medium, is_entity_visible, may_edit_medium, DbConn, EntityType, Medium, User};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+/// How a medium is related to another one from the perspective of the medium.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MediumRelationKind {
+    /// The medium is a reissue of the related medium.
+    ReissueOf,
+
+    /// The related medium is a reissue of the medium.
+    ReissuedAs,
+
+    /// The medium contains a remastered version of the related medium.
+    RemasterOf,
+
+    /// The related medium contains a remastered version of the medium.
+    RemasteredAs,
+
+    /// The medium is part of the related box set.
+    PartOf,
+
+    /// The medium is a box set containing the related medium.
+    Contains,
+}
+
+impl MediumRelationKind {
+    /// All relation kinds.
+    pub const ALL: [MediumRelationKind; 6] = [
+        MediumRelationKind::ReissueOf,
+        MediumRelationKind::ReissuedAs,
+        MediumRelationKind::RemasterOf,
+        MediumRelationKind::RemasteredAs,
+        MediumRelationKind::PartOf,
+        MediumRelationKind::Contains,
+    ];
+
+    /// Get the string representation of the relation kind as used within the API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediumRelationKind::ReissueOf => "reissueOf",
+            MediumRelationKind::ReissuedAs => "reissuedAs",
+            MediumRelationKind::RemasterOf => "remasterOf",
+            MediumRelationKind::RemasteredAs => "remasteredAs",
+            MediumRelationKind::PartOf => "partOf",
+            MediumRelationKind::Contains => "contains",
+        }
+    }
+
+    /// Get a relation kind from its string representation.
+    pub fn parse(kind: &str) -> Option<MediumRelationKind> {
+        MediumRelationKind::ALL
+            .iter()
+            .find(|k| k.as_str() == kind)
+            .cloned()
+    }
+
+    /// Get the kind of the same relation from the perspective of the related medium.
+    pub fn inverse(&self) -> MediumRelationKind {
+        match self {
+            MediumRelationKind::ReissueOf => MediumRelationKind::ReissuedAs,
+            MediumRelationKind::ReissuedAs => MediumRelationKind::ReissueOf,
+            MediumRelationKind::RemasterOf => MediumRelationKind::RemasteredAs,
+            MediumRelationKind::RemasteredAs => MediumRelationKind::RemasterOf,
+            MediumRelationKind::PartOf => MediumRelationKind::Contains,
+            MediumRelationKind::Contains => MediumRelationKind::PartOf,
+        }
+    }
+
+    /// Whether relations of this kind are stored with the medium first.
+    fn is_stored(&self) -> bool {
+        matches!(
+            self,
+            MediumRelationKind::ReissueOf
+                | MediumRelationKind::RemasterOf
+                | MediumRelationKind::PartOf
+        )
+    }
+}
+
+/// Another medium and how a medium is related to it.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MediumRelation {
+    pub kind: MediumRelationKind,
+    pub medium: Medium,
+}
+
+/// The table storing relations between mediums.
+const MEDIUM_RELATIONS: RelationTable = RelationTable {
+    entity_type: EntityType::Medium,
+    table: "medium_relations",
+    column: "medium",
+    may_edit: may_edit_medium,
+};
+
+/// The maximum number of relations that are followed from a medium to find related mediums.
+const MAX_RELATED_DEPTH: usize = 3;
+
+/// The maximum number of related mediums that are returned.
+const MAX_RELATED_MEDIUMS: usize = 100;
+
+/// Get the row values for a relation of a medium. Only one direction of each relation is stored,
+/// e.g. the reissue is stored first with the kind "reissueOf".
+fn get_row_key(
+    medium_id: &str,
+    kind: MediumRelationKind,
+    related_id: &str,
+) -> (String, String, String) {
+    let (first, second, stored_kind) = if kind.is_stored() {
+        (medium_id, related_id, kind)
+    } else {
+        (related_id, medium_id, kind.inverse())
+    };
+
+    (
+        first.to_string(),
+        second.to_string(),
+        stored_kind.as_str().to_string(),
+    )
+}
+
+/// Relate another medium to a medium. The user has to be allowed to edit the medium and both
+/// mediums have to be visible to them. Public mediums can't be related to private ones.
+pub fn insert_medium_relation(
+    conn: &DbConn,
+    medium_id: &str,
+    kind: MediumRelationKind,
+    related_id: &str,
+    user: &User,
+) -> Result<()> {
+    let key = get_row_key(medium_id, kind, related_id);
+    insert_relation(conn, &MEDIUM_RELATIONS, medium_id, related_id, key, user)
+}
+
+/// Remove a relation between two mediums. The user has to be allowed to edit the medium.
+pub fn delete_medium_relation(
+    conn: &DbConn,
+    medium_id: &str,
+    kind: MediumRelationKind,
+    related_id: &str,
+    user: &User,
+) -> Result<()> {
+    let key = get_row_key(medium_id, kind, related_id);
+    delete_relation(conn, &MEDIUM_RELATIONS, medium_id, key, user)
+}
+
+/// Get the IDs of all mediums directly related to a medium together with the kind of relation.
+/// This includes mediums that are not visible to the viewer.
+fn get_relation_ids(conn: &DbConn, medium_id: &str) -> Result<Vec<(String, MediumRelationKind)>> {
+    let mut relations = Vec::new();
+
+    for row in get_relation_rows(conn, &MEDIUM_RELATIONS, medium_id)? {
+        let kind = MediumRelationKind::parse(&row.kind)
+            .ok_or_else(|| anyhow!("Unknown relation kind: {}", row.kind))?;
+
+        if row.entity == medium_id {
+            relations.push((row.related, kind));
+        } else {
+            relations.push((row.entity, kind.inverse()));
+        }
+    }
+
+    Ok(relations)
+}
+
+/// Get all mediums directly related to a medium that are visible to the viewer.
+pub fn get_medium_relations(
+    conn: &DbConn,
+    medium_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<MediumRelation>> {
+    let mut relations = Vec::new();
+
+    for (other_id, kind) in get_relation_ids(conn, medium_id)? {
+        if !is_entity_visible(conn, EntityType::Medium, &other_id, viewer)? {
+            continue;
+        }
+
+        let other = get_medium(conn, &other_id)?
+            .ok_or_else(|| anyhow!("No medium with ID: {}", other_id))?;
+
+        relations.push(MediumRelation {
+            kind,
+            medium: other,
+        });
+    }
+
+    Ok(relations)
+}
+
+/// Get all mediums that are connected to a medium through a chain of relations, e.g. all reissues
+/// and remasters of the same release. Mediums that are not visible to the viewer are neither
+/// included nor followed. The medium itself is not included. At most [`MAX_RELATED_MEDIUMS`]
+/// mediums up to [`MAX_RELATED_DEPTH`] relations away are returned, nearest first.
+pub fn get_related_mediums(
+    conn: &DbConn,
+    medium_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Medium>> {
+    let mut visited = HashSet::new();
+    visited.insert(medium_id.to_string());
+
+    let mut queue = VecDeque::new();
+    queue.push_back((medium_id.to_string(), 0));
+
+    let mut mediums = Vec::new();
+
+    while let Some((current, depth)) = queue.pop_front() {
+        if depth >= MAX_RELATED_DEPTH {
+            continue;
+        }
+
+        for (other_id, _) in get_relation_ids(conn, &current)? {
+            if visited.contains(&other_id) {
+                continue;
+            }
+
+            visited.insert(other_id.clone());
+
+            if !is_entity_visible(conn, EntityType::Medium, &other_id, viewer)? {
+                continue;
+            }
+
+            let other = get_medium(conn, &other_id)?
+                .ok_or_else(|| anyhow!("No medium with ID: {}", other_id))?;
+
+            mediums.push(other);
+
+            if mediums.len() >= MAX_RELATED_MEDIUMS {
+                return Ok(mediums);
+            }
+
+            queue.push_back((other_id, depth + 1));
+        }
+    }
+
+    Ok(mediums)
+}
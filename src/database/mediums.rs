@@ -1,16 +1,24 @@
 use super::schema::{mediums, track_sets, tracks};
-use super::{get_recording, update_recording};
-use super::{DbConn, Recording, User};
+use super::{
+    check_may_become_private, check_quota, check_reference, check_unreferenced, get_read_model,
+    insert_event, may_delete_entity, move_to_trash, normalize_text, viewer_name, with_transaction,
+    DbConn, DbTransaction, EntityType, EventKind, Recording, User, Work,
+};
+use super::{get_external_ids, update_external_ids, ExternalId};
+use super::{get_label, get_recording, update_label_in, update_recording_in, Label};
 use crate::error::ServerError;
 use anyhow::{anyhow, Error, Result};
 use diesel::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// A medium containing multiple recordings.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Medium {
-    /// An unique ID for the medium.
+    /// An unique ID for the medium. The server generates one, if it is left out for a new
+    /// medium.
+    #[serde(default)]
     pub id: String,
 
     /// The human identifier for the medium.
@@ -19,12 +27,25 @@ pub struct Medium {
     /// If applicable, the MusicBrainz DiscID.
     pub discid: Option<String>,
 
+    /// The record label that released the medium, if known.
+    #[serde(default)]
+    pub label: Option<Label>,
+
+    /// Identifiers of the medium within streaming services and digital stores.
+    #[serde(default)]
+    pub external_ids: Vec<ExternalId>,
+
     /// The tracks of the medium, grouped by recording.
     pub tracks: Vec<TrackSet>,
+
+    /// Whether the medium is only visible to the user that created it. Only private mediums may
+    /// contain private recordings of the same user.
+    #[serde(default)]
+    pub private: bool,
 }
 
 /// A set of tracks of one recording within a medium.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TrackSet {
     /// The recording to which the tracks belong.
@@ -35,26 +56,149 @@ pub struct TrackSet {
 }
 
 /// A track within a recording on a medium.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Track {
+    /// The index of the work of the recording that is played on this track. 0 is the main work
+    /// of the recording and all following indices refer to its additional works.
+    #[serde(default)]
+    pub work_index: usize,
+
     /// The work parts that are played on this track. They are indices to the
-    /// work parts of the work that is associated with the recording.
+    /// work parts of the work that is selected by the work index.
     pub work_parts: Vec<usize>,
+
+    /// The duration of the track in milliseconds, if known.
+    #[serde(default)]
+    pub duration: Option<i32>,
+
+    /// The MD5 checksum of the decoded audio as stored within FLAC files, if known.
+    #[serde(default)]
+    pub flac_md5: Option<String>,
+
+    /// The AccurateRip CRC of the track using the first version of the algorithm, if known.
+    #[serde(default)]
+    pub accuraterip_v1: Option<String>,
+
+    /// The AccurateRip CRC of the track using the second version of the algorithm, if known.
+    #[serde(default)]
+    pub accuraterip_v2: Option<String>,
+}
+
+/// A kind of audio checksum of tracks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ChecksumKind {
+    /// See [`Track::flac_md5`].
+    FlacMd5,
+
+    /// See [`Track::accuraterip_v1`].
+    AccurateripV1,
+
+    /// See [`Track::accuraterip_v2`].
+    AccurateripV2,
+}
+
+impl ChecksumKind {
+    /// All checksum kinds.
+    pub const ALL: [ChecksumKind; 3] = [
+        ChecksumKind::FlacMd5,
+        ChecksumKind::AccurateripV1,
+        ChecksumKind::AccurateripV2,
+    ];
+
+    /// Get the string representation of the checksum kind as used within the API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecksumKind::FlacMd5 => "flacMd5",
+            ChecksumKind::AccurateripV1 => "accurateripV1",
+            ChecksumKind::AccurateripV2 => "accurateripV2",
+        }
+    }
+
+    /// Get a checksum kind from its string representation.
+    pub fn parse(kind: &str) -> Option<ChecksumKind> {
+        ChecksumKind::ALL
+            .iter()
+            .find(|k| k.as_str() == kind)
+            .cloned()
+    }
+}
+
+/// A track on a medium that has a specific checksum.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackMatch {
+    pub medium: Medium,
+
+    /// The index of the track set within the medium.
+    pub track_set: usize,
+
+    /// The index of the track within the track set.
+    pub track: usize,
+}
+
+impl Track {
+    /// Get the work of the recording that is played on this track. This falls back to the main
+    /// work, if the work index is out of range.
+    pub fn work<'a>(&self, recording: &'a Recording) -> &'a Work {
+        recording
+            .work_at(self.work_index)
+            .unwrap_or(&recording.work)
+    }
+
+    /// Get a title for this track. This consists of the title of the work followed by the titles
+    /// of the work parts that are played on the track, if any.
+    pub fn title(&self, work: &Work) -> String {
+        match self.movement(work) {
+            Some(movement) => format!("{}: {}", work.title, movement),
+            None => work.title.clone(),
+        }
+    }
+
+    /// Get the titles of the work parts that are played on this track joined into one string.
+    /// This will return [`None`], if the track doesn't reference any work parts.
+    pub fn movement(&self, work: &Work) -> Option<String> {
+        let parts = self
+            .work_parts
+            .iter()
+            .filter_map(|index| work.parts.get(*index))
+            .map(|part| part.title.clone())
+            .collect::<Vec<String>>();
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" / "))
+        }
+    }
 }
 
 /// Table data for a [`Medium`].
-#[derive(Insertable, Queryable, Debug, Clone)]
+#[derive(Insertable, Queryable, Identifiable, AsChangeset, Debug, Clone)]
 #[table_name = "mediums"]
+#[changeset_options(treat_none_as_null = "true")]
 struct MediumRow {
     pub id: String,
     pub name: String,
     pub discid: Option<String>,
     pub created_by: String,
+    pub private: bool,
+    pub label: Option<String>,
+}
+
+/// Table data for a new [`TrackSet`]. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "track_sets"]
+struct NewTrackSetRow {
+    pub medium: String,
+    pub index: i32,
+    pub recording: String,
 }
 
 /// Table data for a [`TrackSet`].
-#[derive(Insertable, Queryable, Debug, Clone)]
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(MediumRow, foreign_key = "medium")]
 #[table_name = "track_sets"]
 struct TrackSetRow {
     pub id: i64,
@@ -63,149 +207,389 @@ struct TrackSetRow {
     pub recording: String,
 }
 
+/// Table data for a new [`Track`]. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "tracks"]
+struct NewTrackRow {
+    pub track_set: i64,
+    pub index: i32,
+    pub work_parts: String,
+    pub duration: Option<i32>,
+    pub work_index: i32,
+    pub flac_md5: Option<String>,
+    pub accuraterip_v1: Option<String>,
+    pub accuraterip_v2: Option<String>,
+}
+
 /// Table data for a [`Track`].
-#[derive(Insertable, Queryable, Debug, Clone)]
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(TrackSetRow, foreign_key = "track_set")]
 #[table_name = "tracks"]
 struct TrackRow {
     pub id: i64,
     pub track_set: i64,
     pub index: i32,
     pub work_parts: String,
+    pub duration: Option<i32>,
+    pub work_index: i32,
+    pub flac_md5: Option<String>,
+    pub accuraterip_v1: Option<String>,
+    pub accuraterip_v2: Option<String>,
 }
 
 /// Update an existing medium or insert a new one. This will only work, if the provided user is
 /// allowed to do that.
 pub fn update_medium(conn: &DbConn, medium: &Medium, user: &User) -> Result<()> {
-    conn.transaction::<(), Error, _>(|| {
-        let old_row = get_medium_row(conn, &medium.id)?;
+    with_transaction(conn, |tx| update_medium_in(tx, medium, user))
+}
 
-        let allowed = match old_row {
-            Some(row) => user.may_edit(&row.created_by),
-            None => user.may_create(),
-        };
+/// Update an existing medium or insert a new one as part of a larger change. See
+/// [`update_medium`].
+pub fn update_medium_in(tx: &DbTransaction, medium: &Medium, user: &User) -> Result<()> {
+    let conn = tx.conn();
 
-        if allowed {
-            let id = &medium.id;
+    let old_row = get_medium_row(conn, &medium.id)?;
+    let kind = if old_row.is_some() {
+        EventKind::Update
+    } else {
+        EventKind::Create
+    };
 
-            // This will also delete the track sets and tracks.
+    if old_row.is_none() {
+        check_quota(conn, user)?;
+    }
 
-            diesel::delete(mediums::table)
-                .filter(mediums::id.eq(id))
-                .execute(conn)?;
+    let allowed = match &old_row {
+        Some(row) if row.private => user.may_edit_private(&row.created_by),
+        Some(row) => user.may_edit(&row.created_by),
+        None => user.may_create(),
+    };
 
-            // Add the actual medium first.
+    if allowed {
+        let id = &medium.id;
 
-            let row = MediumRow {
-                id: id.clone(),
-                name: medium.name.clone(),
-                discid: medium.discid.clone(),
-                created_by: user.username.clone(),
-            };
+        if medium.private && matches!(&old_row, Some(row) if !row.private) {
+            check_may_become_private(conn, EntityType::Medium, id, user)?;
+        }
 
-            diesel::insert_into(mediums::table)
-                .values(row)
-                .execute(conn)?;
+        // Add the label, if it doesn't exist.
 
-            // Add the track sets.
+        if let Some(label) = &medium.label {
+            if get_label(conn, &label.id)?.is_none() {
+                update_label_in(tx, label, user)?;
+            }
 
-            for (index, track_set) in medium.tracks.iter().enumerate() {
-                // Add the associated recording, if it doesn't exist.
+            check_reference(conn, EntityType::Label, &label.id, medium.private, user)?;
+        }
 
-                if get_recording(conn, &track_set.recording.id)?.is_none() {
-                    update_recording(conn, &track_set.recording, user)?;
-                }
+        // Add or update the actual medium.
 
-                // Add the track set itself.
+        let row = MediumRow {
+            id: id.clone(),
+            name: normalize_text(&medium.name),
+            discid: medium.discid.clone(),
+            created_by: user.username.clone(),
+            private: medium.private,
+            label: medium.label.as_ref().map(|label| label.id.clone()),
+        };
+
+        diesel::insert_into(mediums::table)
+            .values(&row)
+            .on_conflict(mediums::id)
+            .do_update()
+            .set(&row)
+            .execute(conn)?;
 
-                let track_set_id = rand::random();
+        // Update the track sets and tracks. Existing rows are matched by their position, so that
+        // only changed rows are touched and unchanged ones keep their IDs.
 
-                let track_set_row = TrackSetRow {
-                    id: track_set_id,
-                    medium: id.clone(),
-                    index: index as i32,
-                    recording: track_set.recording.id.clone(),
-                };
+        let old_track_sets = TrackSetRow::belonging_to(&row)
+            .order_by(track_sets::index)
+            .load::<TrackSetRow>(conn)?;
 
-                diesel::insert_into(track_sets::table)
-                    .values(track_set_row)
-                    .execute(conn)?;
+        for (index, track_set) in medium.tracks.iter().enumerate() {
+            // Add the associated recording, if it doesn't exist.
+
+            if get_recording(conn, &track_set.recording.id)?.is_none() {
+                update_recording_in(tx, &track_set.recording, user)?;
+            }
 
-                // Add the tracks within the track set.
+            // Add or update the track set itself.
 
-                for (index, track) in track_set.tracks.iter().enumerate() {
-                    let work_parts = track
-                        .work_parts
-                        .iter()
-                        .map(|part_index| part_index.to_string())
-                        .collect::<Vec<String>>()
-                        .join(",");
+            let recording = &track_set.recording.id;
+            check_reference(conn, EntityType::Recording, recording, medium.private, user)?;
 
-                    let track_row = TrackRow {
-                        id: rand::random(),
-                        track_set: track_set_id,
+            let track_set_row = match old_track_sets.get(index) {
+                Some(old) if old.index == index as i32 && &old.recording == recording => {
+                    old.clone()
+                }
+                Some(old) => diesel::update(old)
+                    .set((
+                        track_sets::index.eq(index as i32),
+                        track_sets::recording.eq(recording),
+                    ))
+                    .get_result::<TrackSetRow>(conn)?,
+                None => diesel::insert_into(track_sets::table)
+                    .values(NewTrackSetRow {
+                        medium: id.clone(),
                         index: index as i32,
-                        work_parts,
-                    };
+                        recording: recording.clone(),
+                    })
+                    .get_result::<TrackSetRow>(conn)?,
+            };
 
-                    diesel::insert_into(tracks::table)
-                        .values(track_row)
-                        .execute(conn)?;
+            // Add or update the tracks within the track set.
+
+            let old_tracks = TrackRow::belonging_to(&track_set_row)
+                .order_by(tracks::index)
+                .load::<TrackRow>(conn)?;
+
+            for (index, track) in track_set.tracks.iter().enumerate() {
+                let work_parts = track
+                    .work_parts
+                    .iter()
+                    .map(|part_index| part_index.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",");
+
+                match old_tracks.get(index) {
+                    Some(old)
+                        if old.index == index as i32
+                            && old.work_parts == work_parts
+                            && old.duration == track.duration
+                            && old.work_index == track.work_index as i32
+                            && old.flac_md5 == track.flac_md5
+                            && old.accuraterip_v1 == track.accuraterip_v1
+                            && old.accuraterip_v2 == track.accuraterip_v2 => {}
+                    Some(old) => {
+                        diesel::update(old)
+                            .set((
+                                tracks::index.eq(index as i32),
+                                tracks::work_parts.eq(work_parts),
+                                tracks::duration.eq(track.duration),
+                                tracks::work_index.eq(track.work_index as i32),
+                                tracks::flac_md5.eq(&track.flac_md5),
+                                tracks::accuraterip_v1.eq(&track.accuraterip_v1),
+                                tracks::accuraterip_v2.eq(&track.accuraterip_v2),
+                            ))
+                            .execute(conn)?;
+                    }
+                    None => {
+                        diesel::insert_into(tracks::table)
+                            .values(NewTrackRow {
+                                track_set: track_set_row.id,
+                                index: index as i32,
+                                work_parts,
+                                duration: track.duration,
+                                work_index: track.work_index as i32,
+                                flac_md5: track.flac_md5.clone(),
+                                accuraterip_v1: track.accuraterip_v1.clone(),
+                                accuraterip_v2: track.accuraterip_v2.clone(),
+                            })
+                            .execute(conn)?;
+                    }
                 }
             }
 
-            Ok(())
-        } else {
-            Err(Error::new(ServerError::Forbidden))
+            for old in old_tracks.iter().skip(track_set.tracks.len()) {
+                diesel::delete(old).execute(conn)?;
+            }
         }
-    })?;
 
-    Ok(())
+        // This will also delete the tracks within the removed track sets.
+        for old in old_track_sets.iter().skip(medium.tracks.len()) {
+            diesel::delete(old).execute(conn)?;
+        }
+
+        update_external_ids(conn, EntityType::Medium, id, &medium.external_ids)?;
+
+        insert_event(conn, EntityType::Medium, id, kind, user)?;
+
+        Ok(())
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
 }
 
 /// Get an existing medium and all available information from related tables.
 pub fn get_medium(conn: &DbConn, id: &str) -> Result<Option<Medium>> {
-    let medium = match get_medium_row(conn, id)? {
-        Some(row) => Some(get_medium_data(conn, row)?),
-        None => None,
+    get_read_model(conn, EntityType::Medium, id, || {
+        let medium = match get_medium_row(conn, id)? {
+            Some(row) => Some(get_medium_data(conn, row)?),
+            None => None,
+        };
+
+        Ok(medium)
+    })
+}
+
+/// Check whether a user may edit an existing medium. This fails, if the medium doesn't exist.
+pub fn may_edit_medium(conn: &DbConn, id: &str, user: &User) -> Result<bool> {
+    let row = get_medium_row(conn, id)?.ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+    let allowed = if row.private {
+        user.may_edit_private(&row.created_by)
+    } else {
+        user.may_edit(&row.created_by)
     };
 
-    Ok(medium)
+    Ok(allowed)
 }
 
-/// Get mediums that contain a specific recording.
-pub fn get_mediums_for_recording(conn: &DbConn, recording_id: &str) -> Result<Vec<Medium>> {
+/// Get mediums that contain a specific recording. Only public mediums and private mediums of the
+/// viewer are included.
+pub fn get_mediums_for_recording(
+    conn: &DbConn,
+    recording_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Medium>> {
     let mut mediums: Vec<Medium> = Vec::new();
 
     let rows = mediums::table
         .inner_join(track_sets::table.on(track_sets::medium.eq(mediums::id)))
         .filter(track_sets::recording.eq(recording_id))
+        .filter(
+            mediums::private
+                .eq(false)
+                .or(mediums::created_by.eq(viewer_name(viewer))),
+        )
         .select(mediums::table::all_columns())
         .load::<MediumRow>(conn)?;
 
     for row in rows {
-        let medium = get_medium_data(conn, row)?;
+        let medium = get_medium_from_row(conn, row)?;
         mediums.push(medium);
     }
 
     Ok(mediums)
 }
 
-/// Get mediums that have a specific DiscID.
-pub fn get_mediums_by_discid(conn: &DbConn, discid: &str) -> Result<Vec<Medium>> {
+/// Get mediums that have a specific DiscID. Only public mediums and private mediums of the viewer
+/// are included.
+pub fn get_mediums_by_discid(
+    conn: &DbConn,
+    discid: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Medium>> {
     let mut mediums: Vec<Medium> = Vec::new();
 
     let rows = mediums::table
         .filter(mediums::discid.nullable().eq(discid))
+        .filter(
+            mediums::private
+                .eq(false)
+                .or(mediums::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<MediumRow>(conn)?;
+
+    for row in rows {
+        let medium = get_medium_from_row(conn, row)?;
+        mediums.push(medium);
+    }
+
+    Ok(mediums)
+}
+
+/// Get all tracks with a specific checksum. Only tracks of public mediums and private mediums of
+/// the viewer are included.
+pub fn get_tracks_by_checksum(
+    conn: &DbConn,
+    kind: ChecksumKind,
+    checksum: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<TrackMatch>> {
+    let checksum = checksum.to_lowercase();
+
+    let query = tracks::table
+        .inner_join(track_sets::table.inner_join(mediums::table))
+        .filter(
+            mediums::private
+                .eq(false)
+                .or(mediums::created_by.eq(viewer_name(viewer))),
+        )
+        .select((mediums::id, track_sets::index, tracks::index))
+        .into_boxed();
+
+    let query = match kind {
+        ChecksumKind::FlacMd5 => query.filter(tracks::flac_md5.eq(checksum)),
+        ChecksumKind::AccurateripV1 => query.filter(tracks::accuraterip_v1.eq(checksum)),
+        ChecksumKind::AccurateripV2 => query.filter(tracks::accuraterip_v2.eq(checksum)),
+    };
+
+    let rows = query.load::<(String, i32, i32)>(conn)?;
+
+    let mut matches = Vec::new();
+
+    for (medium_id, track_set, track) in rows {
+        let medium = get_medium(conn, &medium_id)?
+            .ok_or_else(|| anyhow!("No medium with ID: {}", medium_id))?;
+
+        matches.push(TrackMatch {
+            medium,
+            track_set: track_set as usize,
+            track: track as usize,
+        });
+    }
+
+    Ok(matches)
+}
+
+/// Get mediums that were released by a label. Only public mediums and private mediums of the
+/// viewer are included.
+pub fn get_mediums_for_label(
+    conn: &DbConn,
+    label_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Medium>> {
+    let mut mediums: Vec<Medium> = Vec::new();
+
+    let rows = mediums::table
+        .filter(mediums::label.eq(label_id))
+        .filter(
+            mediums::private
+                .eq(false)
+                .or(mediums::created_by.eq(viewer_name(viewer))),
+        )
         .load::<MediumRow>(conn)?;
 
     for row in rows {
-        let medium = get_medium_data(conn, row)?;
+        let medium = get_medium_from_row(conn, row)?;
         mediums.push(medium);
     }
 
     Ok(mediums)
 }
 
+/// Get all existing mediums that are public or private mediums of the viewer.
+pub fn get_all_mediums(conn: &DbConn, viewer: Option<&User>) -> Result<Vec<Medium>> {
+    let mut mediums: Vec<Medium> = Vec::new();
+
+    let rows = mediums::table
+        .filter(
+            mediums::private
+                .eq(false)
+                .or(mediums::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<MediumRow>(conn)?;
+
+    for row in rows {
+        let medium = get_medium_from_row(conn, row)?;
+        mediums.push(medium);
+    }
+
+    Ok(mediums)
+}
+
+/// Get all available information on a medium using its read model, if possible.
+fn get_medium_from_row(conn: &DbConn, row: MediumRow) -> Result<Medium> {
+    let id = row.id.clone();
+    let medium = get_read_model(conn, EntityType::Medium, &id, || {
+        Ok(Some(get_medium_data(conn, row)?))
+    })?;
+
+    medium.ok_or_else(|| anyhow!("No medium with ID: {}", id))
+}
+
 /// Get an existing medium row.
 fn get_medium_row(conn: &DbConn, id: &str) -> Result<Option<MediumRow>> {
     Ok(mediums::table
@@ -217,8 +601,7 @@ fn get_medium_row(conn: &DbConn, id: &str) -> Result<Option<MediumRow>> {
 
 /// Retrieve all available information on a medium from related tables.
 fn get_medium_data(conn: &DbConn, row: MediumRow) -> Result<Medium> {
-    let track_set_rows = track_sets::table
-        .filter(track_sets::medium.eq(&row.id))
+    let track_set_rows = TrackSetRow::belonging_to(&row)
         .order_by(track_sets::index)
         .load::<TrackSetRow>(conn)?;
 
@@ -229,11 +612,23 @@ fn get_medium_data(conn: &DbConn, row: MediumRow) -> Result<Medium> {
         track_sets.push(track_set);
     }
 
+    let label = match &row.label {
+        Some(label_id) => Some(
+            get_label(conn, label_id)?.ok_or_else(|| anyhow!("No label with ID: {}", label_id))?,
+        ),
+        None => None,
+    };
+
+    let external_ids = get_external_ids(conn, EntityType::Medium, &row.id)?;
+
     let medium = Medium {
         id: row.id,
         name: row.name,
         discid: row.discid,
+        label,
+        external_ids,
         tracks: track_sets,
+        private: row.private,
     };
 
     Ok(medium)
@@ -241,13 +636,12 @@ fn get_medium_data(conn: &DbConn, row: MediumRow) -> Result<Medium> {
 
 /// Convert a track set row from the database to an actual track set.
 fn get_track_set_from_row(conn: &DbConn, row: TrackSetRow) -> Result<TrackSet> {
-    let recording_id = row.recording;
+    let recording_id = &row.recording;
 
-    let recording = get_recording(conn, &recording_id)?
+    let recording = get_recording(conn, recording_id)?
         .ok_or_else(|| anyhow!("No recording with ID: {}", recording_id))?;
 
-    let track_rows = tracks::table
-        .filter(tracks::track_set.eq(row.id))
+    let track_rows = TrackRow::belonging_to(&row)
         .order_by(tracks::index)
         .load::<TrackRow>(conn)?;
 
@@ -261,7 +655,12 @@ fn get_track_set_from_row(conn: &DbConn, row: TrackSetRow) -> Result<TrackSet> {
             .collect::<Result<Vec<usize>>>()?;
 
         let track = Track {
+            work_index: track_row.work_index as usize,
             work_parts,
+            duration: track_row.duration,
+            flac_md5: track_row.flac_md5,
+            accuraterip_v1: track_row.accuraterip_v1,
+            accuraterip_v2: track_row.accuraterip_v2,
         };
 
         tracks.push(track);
@@ -276,11 +675,20 @@ fn get_track_set_from_row(conn: &DbConn, row: TrackSetRow) -> Result<TrackSet> {
 /// medium from other tables that are not directly part of the recording data. Also, the
 /// provided user has to be allowed to delete the recording.
 pub fn delete_medium(conn: &DbConn, id: &str, user: &User) -> Result<()> {
-    if user.may_delete() {
-        diesel::delete(mediums::table.filter(mediums::id.eq(id))).execute(conn)?;
-        Ok(())
+    if may_delete_entity(conn, EntityType::Medium, id, user)? {
+        conn.transaction::<(), Error, _>(|| {
+            check_unreferenced(conn, EntityType::Medium, id)?;
+            move_to_trash(conn, EntityType::Medium, id, user)?;
+
+            let count = diesel::delete(mediums::table.filter(mediums::id.eq(id))).execute(conn)?;
+
+            if count > 0 {
+                insert_event(conn, EntityType::Medium, id, EventKind::Delete, user)?;
+            }
+
+            Ok(())
+        })
     } else {
         Err(Error::new(ServerError::Forbidden))
     }
 }
-
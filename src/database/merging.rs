@@ -0,0 +1,317 @@
+use super::schema::{external_ids, images, instrumentations, mediums, performances};
+use super::schema::{person_relations, wikidata_candidates, wikidata_checks, work_authors, works};
+use super::{delete_cascading_in, get_referencing_entities, get_visibility, insert_event};
+use super::{insert_redirect, with_transaction, DbConn, EntityReference, EntityType};
+use super::{EventKind, ExternalSource, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+
+/// Merge an entity into another one of the same type, e.g. a person that was added twice. All
+/// references to the old entity are changed to the new one, the old entity is moved to the trash
+/// and requests for its ID are redirected afterwards. Data that belongs to the old entity, like
+/// its image, is moved to the new one, unless that already has an equivalent. Only persons, ensembles, instruments and
+/// labels can be merged and both entities have to be public. The user must be an editor.
+pub fn merge_entities(
+    conn: &DbConn,
+    entity_type: EntityType,
+    old_id: &str,
+    new_id: &str,
+    user: &User,
+) -> Result<()> {
+    if !user.may_merge() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    if old_id == new_id {
+        return Err(Error::new(ServerError::BadRequest));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        for id in &[old_id, new_id] {
+            let visibility = get_visibility(conn, entity_type, id)?
+                .ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+            if visibility.private {
+                return Err(Error::new(ServerError::Forbidden));
+            }
+        }
+
+        let mut references = get_referencing_entities(conn, entity_type, old_id)?;
+
+        match entity_type {
+            EntityType::Person => {
+                diesel::update(works::table.filter(works::composer.eq(old_id)))
+                    .set(works::composer.eq(new_id))
+                    .execute(conn)?;
+
+                merge_work_authors(conn, old_id, new_id)?;
+
+                diesel::update(performances::table.filter(performances::person.eq(old_id)))
+                    .set(performances::person.eq(new_id))
+                    .execute(conn)?;
+
+                for related in merge_person_relations(conn, old_id, new_id)? {
+                    references.push(EntityReference {
+                        entity_type: EntityType::Person,
+                        entity_id: related,
+                    });
+                }
+
+                merge_external_ids(conn, old_id, new_id)?;
+                merge_wikidata(conn, old_id, new_id)?;
+                merge_images(conn, EntityType::Person, old_id, new_id)?;
+            }
+            EntityType::Ensemble => {
+                diesel::update(performances::table.filter(performances::ensemble.eq(old_id)))
+                    .set(performances::ensemble.eq(new_id))
+                    .execute(conn)?;
+
+                merge_images(conn, EntityType::Ensemble, old_id, new_id)?;
+            }
+            EntityType::Instrument => {
+                diesel::update(performances::table.filter(performances::role.eq(old_id)))
+                    .set(performances::role.eq(new_id))
+                    .execute(conn)?;
+
+                // Instrumentations would be deleted together with the instrument, so the works
+                // using it are changed as well.
+                let instrumented: Vec<String> = instrumentations::table
+                    .filter(instrumentations::instrument.eq(old_id))
+                    .select(instrumentations::work)
+                    .load(conn)?;
+
+                diesel::update(
+                    instrumentations::table.filter(instrumentations::instrument.eq(old_id)),
+                )
+                .set(instrumentations::instrument.eq(new_id))
+                .execute(conn)?;
+
+                for work in instrumented {
+                    references.push(EntityReference {
+                        entity_type: EntityType::Work,
+                        entity_id: work,
+                    });
+                }
+            }
+            EntityType::Label => {
+                diesel::update(mediums::table.filter(mediums::label.eq(old_id)))
+                    .set(mediums::label.eq(new_id))
+                    .execute(conn)?;
+            }
+            _ => return Err(Error::new(ServerError::BadRequest)),
+        }
+
+        references.sort();
+        references.dedup();
+
+        // The redirect has to exist before the old entity is deleted, so that its creator and
+        // watchers are notified about the merge instead of the deletion.
+        insert_redirect(conn, entity_type, old_id, new_id, user)?;
+        delete_cascading_in(tx, entity_type, old_id, user)?;
+
+        for reference in references {
+            insert_event(
+                conn,
+                reference.entity_type,
+                &reference.entity_id,
+                EventKind::Update,
+                user,
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Move the relations of a person to another one. Relations between both persons are dropped, as
+/// well as relations the other person already has. Returns the IDs of the related persons.
+fn merge_person_relations(conn: &DbConn, old_id: &str, new_id: &str) -> Result<Vec<String>> {
+    let rows: Vec<(i64, String, String, String, String)> = person_relations::table
+        .filter(
+            person_relations::person
+                .eq(old_id)
+                .or(person_relations::related.eq(old_id)),
+        )
+        .select((
+            person_relations::id,
+            person_relations::person,
+            person_relations::related,
+            person_relations::kind,
+            person_relations::created_by,
+        ))
+        .load(conn)?;
+
+    let mut related_ids = Vec::new();
+
+    for (id, person, related, kind, created_by) in rows {
+        diesel::delete(person_relations::table.filter(person_relations::id.eq(id)))
+            .execute(conn)?;
+
+        let replace = |id: String| if id == old_id { new_id.to_string() } else { id };
+        let (mut person, mut related) = (replace(person), replace(related));
+
+        if person == related {
+            continue;
+        }
+
+        // Symmetric relations are stored with the smaller ID first.
+        if kind != "teacher" && person > related {
+            std::mem::swap(&mut person, &mut related);
+        }
+
+        related_ids.push(if person == new_id {
+            related.clone()
+        } else {
+            person.clone()
+        });
+
+        diesel::insert_into(person_relations::table)
+            .values((
+                person_relations::person.eq(person),
+                person_relations::related.eq(related),
+                person_relations::kind.eq(kind),
+                person_relations::created_by.eq(created_by),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+
+    Ok(related_ids)
+}
+
+/// Move the external IDs of a person to another one. Each person has at most one ID within each
+/// service, so IDs from services the other person already has an ID for are dropped.
+fn merge_external_ids(conn: &DbConn, old_id: &str, new_id: &str) -> Result<()> {
+    let sources: Vec<String> = external_ids::table
+        .filter(external_ids::person.eq(new_id))
+        .select(external_ids::source)
+        .load(conn)?;
+
+    diesel::delete(
+        external_ids::table
+            .filter(external_ids::person.eq(old_id))
+            .filter(external_ids::source.eq_any(&sources)),
+    )
+    .execute(conn)?;
+
+    diesel::update(external_ids::table.filter(external_ids::person.eq(old_id)))
+        .set(external_ids::person.eq(new_id))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Move the Wikidata candidates of a person to another one, unless the other person already has a
+/// confirmed item. This has to happen after merging the external IDs.
+fn merge_wikidata(conn: &DbConn, old_id: &str, new_id: &str) -> Result<()> {
+    let confirmed: bool = diesel::select(diesel::dsl::exists(
+        external_ids::table
+            .filter(external_ids::person.eq(new_id))
+            .filter(external_ids::source.eq(ExternalSource::Wikidata.as_str())),
+    ))
+    .get_result(conn)?;
+
+    if confirmed {
+        diesel::delete(wikidata_candidates::table.filter(wikidata_candidates::person.eq(old_id)))
+            .execute(conn)?;
+    } else {
+        let items: Vec<String> = wikidata_candidates::table
+            .filter(wikidata_candidates::person.eq(new_id))
+            .select(wikidata_candidates::item)
+            .load(conn)?;
+
+        diesel::delete(
+            wikidata_candidates::table
+                .filter(wikidata_candidates::person.eq(old_id))
+                .filter(wikidata_candidates::item.eq_any(&items)),
+        )
+        .execute(conn)?;
+
+        diesel::update(wikidata_candidates::table.filter(wikidata_candidates::person.eq(old_id)))
+            .set(wikidata_candidates::person.eq(new_id))
+            .execute(conn)?;
+    }
+
+    let checked: bool = diesel::select(diesel::dsl::exists(
+        wikidata_checks::table.filter(wikidata_checks::person.eq(new_id)),
+    ))
+    .get_result(conn)?;
+
+    if !checked {
+        diesel::update(wikidata_checks::table.filter(wikidata_checks::person.eq(old_id)))
+            .set(wikidata_checks::person.eq(new_id))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Move the image of a person or an ensemble to another one, if that doesn't have an image yet.
+/// Otherwise, the image is deleted together with the entity.
+fn merge_images(conn: &DbConn, entity_type: EntityType, old_id: &str, new_id: &str) -> Result<()> {
+    match entity_type {
+        EntityType::Person => {
+            let exists: bool = diesel::select(diesel::dsl::exists(
+                images::table.filter(images::person.eq(new_id)),
+            ))
+            .get_result(conn)?;
+
+            if !exists {
+                diesel::update(images::table.filter(images::person.eq(old_id)))
+                    .set(images::person.eq(new_id))
+                    .execute(conn)?;
+            }
+        }
+        EntityType::Ensemble => {
+            let exists: bool = diesel::select(diesel::dsl::exists(
+                images::table.filter(images::ensemble.eq(new_id)),
+            ))
+            .get_result(conn)?;
+
+            if !exists {
+                diesel::update(images::table.filter(images::ensemble.eq(old_id)))
+                    .set(images::ensemble.eq(new_id))
+                    .execute(conn)?;
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Replace a person with another one as author of works. If both were authors of the same work or
+/// part, the old person is just removed.
+fn merge_work_authors(conn: &DbConn, old_id: &str, new_id: &str) -> Result<()> {
+    let rows: Vec<(i64, String, Option<i64>)> = work_authors::table
+        .filter(work_authors::person.eq(old_id))
+        .select((
+            work_authors::id,
+            work_authors::work,
+            work_authors::part_index,
+        ))
+        .load(conn)?;
+
+    for (id, work, part_index) in rows {
+        let duplicate: bool = diesel::select(diesel::dsl::exists(
+            work_authors::table
+                .filter(work_authors::work.eq(&work))
+                .filter(work_authors::part_index.is_not_distinct_from(part_index))
+                .filter(work_authors::person.eq(new_id)),
+        ))
+        .get_result(conn)?;
+
+        if duplicate {
+            diesel::delete(work_authors::table.filter(work_authors::id.eq(id))).execute(conn)?;
+        } else {
+            diesel::update(work_authors::table.filter(work_authors::id.eq(id)))
+                .set(work_authors::person.eq(new_id))
+                .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
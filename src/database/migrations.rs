@@ -0,0 +1,104 @@
+use super::DbConn;
+use anyhow::{anyhow, Result};
+use diesel_migrations::MigrationConnection;
+use serde::Serialize;
+
+// This defines MIGRATIONS, the names of all migrations that are embedded into the server, e.g.
+// "2020-11-09-153819_initial_schema". They are collected by the build script.
+include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+/// What to do with pending migrations on startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationPolicy {
+    /// Run all pending migrations.
+    Run,
+
+    /// Refuse to start, if there are pending migrations. They have to be run using the command
+    /// line interface instead.
+    Check,
+}
+
+impl MigrationPolicy {
+    /// Get the migration policy from the environment variable "WOLFGANG_MIGRATIONS". Possible
+    /// values are "run" (the default) and "check".
+    pub fn from_env() -> Result<Self> {
+        let policy = match std::env::var("WOLFGANG_MIGRATIONS") {
+            Ok(policy) => match policy.as_str() {
+                "run" => MigrationPolicy::Run,
+                "check" => MigrationPolicy::Check,
+                _ => return Err(anyhow!("Unknown migration policy: {}", policy)),
+            },
+            Err(_) => MigrationPolicy::Run,
+        };
+
+        Ok(policy)
+    }
+}
+
+/// The state of one database migration.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    /// The version as stored in the database, e.g. "20201109153819".
+    pub version: String,
+
+    /// The full name of the migration. This is unknown for migrations that were run by a newer
+    /// version of the server.
+    pub name: Option<String>,
+
+    pub applied: bool,
+}
+
+/// Get the state of all migrations that are either embedded into the server or were applied to
+/// the database, ordered by their version.
+pub fn get_migrations(conn: &DbConn) -> Result<Vec<MigrationStatus>> {
+    diesel_migrations::setup_database(&**conn)?;
+    let mut applied = conn.previously_run_migration_versions()?;
+
+    let mut migrations: Vec<MigrationStatus> = MIGRATIONS
+        .iter()
+        .map(|name| {
+            let version = migration_version(name);
+
+            MigrationStatus {
+                applied: applied.remove(&version),
+                version,
+                name: Some(name.to_string()),
+            }
+        })
+        .collect();
+
+    migrations.extend(applied.into_iter().map(|version| MigrationStatus {
+        version,
+        name: None,
+        applied: true,
+    }));
+
+    migrations.sort_by(|a, b| a.version.cmp(&b.version));
+
+    Ok(migrations)
+}
+
+/// Fail, if there are migrations that haven't been applied to the database yet.
+pub fn check_migrations(conn: &DbConn) -> Result<()> {
+    let pending: Vec<String> = get_migrations(conn)?
+        .into_iter()
+        .filter(|migration| !migration.applied)
+        .filter_map(|migration| migration.name)
+        .collect();
+
+    if !pending.is_empty() {
+        return Err(anyhow!(
+            "The database schema is behind. Run \"wolfgang-admin migrate\" to apply the pending \
+            migrations:\n\n{}",
+            pending.join("\n")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Get the version of a migration from its name like Diesel does.
+fn migration_version(name: &str) -> String {
+    name.split('_').next().unwrap_or_default().replace('-', "")
+}
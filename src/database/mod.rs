@@ -1,25 +1,178 @@
 use anyhow::Result;
 use diesel::r2d2;
-use diesel::PgConnection;
+use unicode_normalization::UnicodeNormalization;
+
+pub mod api_keys;
+pub use api_keys::*;
+
+pub mod backup;
+pub use backup::*;
+
+pub mod bulk_edits;
+pub use bulk_edits::*;
+
+pub mod changefeed;
+pub use changefeed::*;
+
+pub mod collections;
+pub use collections::*;
+
+pub mod comments;
+pub use comments::*;
+
+pub mod consistency;
+pub use consistency::*;
+
+pub mod deletion;
+pub use deletion::*;
+
+pub mod drafts;
+pub use drafts::*;
+
+pub mod dump;
+pub use dump::*;
+
+pub mod duplicates;
+pub use duplicates::*;
+
+pub mod editor_applications;
+pub use editor_applications::*;
 
 pub mod ensembles;
 pub use ensembles::*;
 
+pub mod entities;
+pub use entities::*;
+
+pub mod events;
+pub use events::*;
+
+pub mod external_ids;
+pub use external_ids::*;
+
+pub mod idempotency;
+pub use idempotency::*;
+
+pub mod images;
+pub use images::*;
+
 pub mod instruments;
 pub use instruments::*;
 
+pub mod invitations;
+pub use invitations::*;
+
+pub mod labels;
+pub use labels::*;
+
+pub mod logging;
+pub use logging::*;
+
+pub mod medium_relations;
+pub use medium_relations::*;
+
 pub mod mediums;
 pub use mediums::*;
 
+pub mod merging;
+pub use merging::*;
+
+pub mod migrations;
+pub use migrations::*;
+
+pub mod nested;
+pub use nested::*;
+
+pub mod normalization;
+pub use normalization::*;
+
+pub mod notifications;
+pub use notifications::*;
+
+pub mod orphans;
+pub use orphans::*;
+
+pub mod periods;
+pub use periods::*;
+
+pub mod person_overviews;
+pub use person_overviews::*;
+
+pub mod person_relations;
+pub use person_relations::*;
+
 pub mod persons;
 pub use persons::*;
 
+pub mod playlists;
+pub use playlists::*;
+
+pub mod plays;
+pub use plays::*;
+
+pub mod quality;
+pub use quality::*;
+
+pub mod quotas;
+pub use quotas::*;
+
+pub mod ratings;
+pub use ratings::*;
+
+pub mod read_models;
+pub use read_models::*;
+
 pub mod recordings;
 pub use recordings::*;
 
+pub mod redirects;
+pub use redirects::*;
+
+pub mod replication;
+pub use replication::*;
+
+pub mod reports;
+pub use reports::*;
+
+pub mod revisions;
+pub use revisions::*;
+
+pub mod search;
+pub use search::*;
+
+pub mod seed;
+pub use seed::*;
+
+pub mod sources;
+pub use sources::*;
+
+pub mod statistics;
+pub use statistics::*;
+
+pub mod transactions;
+pub use transactions::*;
+
+pub mod trash;
+pub use trash::*;
+
 pub mod users;
 pub use users::*;
 
+pub mod visibility;
+pub use visibility::*;
+
+pub mod watches;
+pub use watches::*;
+
+pub mod webhooks;
+pub use webhooks::*;
+
+pub mod wikidata;
+pub use wikidata::*;
+
+pub mod work_texts;
+pub use work_texts::*;
+
 pub mod works;
 pub use works::*;
 
@@ -29,21 +182,95 @@ mod schema;
 embed_migrations!();
 
 /// A pool of connections to the database.
-pub type DbPool = r2d2::Pool<r2d2::ConnectionManager<PgConnection>>;
+pub type DbPool = r2d2::Pool<r2d2::ConnectionManager<LoggingConnection>>;
+
+/// A pool of read-only connections. They may be connected to a read replica, so changes may
+/// become visible with a short delay.
+#[derive(Clone)]
+pub struct ReadDbPool(DbPool);
+
+impl ReadDbPool {
+    /// Get a connection from the pool.
+    pub fn get(&self) -> Result<DbConn, r2d2::PoolError> {
+        self.0.get()
+    }
+}
 
 /// One database connection from the connection pool.
-pub type DbConn = r2d2::PooledConnection<r2d2::ConnectionManager<PgConnection>>;
+pub type DbConn = r2d2::PooledConnection<r2d2::ConnectionManager<LoggingConnection>>;
+
+/// Generate a new random ID that can be used for identifying rows.
+pub fn generate_id() -> String {
+    let mut buffer = uuid::Uuid::encode_buffer();
+    uuid::Uuid::new_v4()
+        .to_simple()
+        .encode_lower(&mut buffer)
+        .to_owned()
+}
+
+/// Normalize a name or title to the Unicode normalization form C, so that texts that look equal
+/// are also stored equally.
+pub fn normalize_text(text: &str) -> String {
+    text.nfc().collect()
+}
 
-/// Create a connection pool for a database. This will look for the database URL in the
-/// "WOLFGANG_DATABASE_URL" environment variable and fail, if that is not set.
+/// Create a connection pool for a database and run all pending migrations. This will look for
+/// the database URL in the "WOLFGANG_DATABASE_URL" environment variable and fail, if that is not
+/// set. Depending on the [`MigrationPolicy`], pending migrations are not run but cause an error.
 pub fn connect() -> Result<DbPool> {
-    let url = std::env::var("WOLFGANG_DATABASE_URL")?;
-    let manager = r2d2::ConnectionManager::<PgConnection>::new(url);
-    let pool = r2d2::Pool::new(manager)?;
+    let pool = connect_without_migrations()?;
 
-    // Run embedded migrations.
     let conn = pool.get()?;
-    embedded_migrations::run(&conn)?;
+    match MigrationPolicy::from_env()? {
+        MigrationPolicy::Run => run_migrations(&conn)?,
+        MigrationPolicy::Check => check_migrations(&conn)?,
+    }
+
+    Ok(pool)
+}
+
+/// Create a connection pool like [`connect`] without touching the database schema.
+pub fn connect_without_migrations() -> Result<DbPool> {
+    let url = std::env::var("WOLFGANG_DATABASE_URL")?;
+    let manager = r2d2::ConnectionManager::<LoggingConnection>::new(url);
+    let pool = r2d2::Pool::new(manager)?;
 
     Ok(pool)
 }
+
+/// Run all embedded migrations that haven't been run yet.
+pub fn run_migrations(conn: &DbConn) -> Result<()> {
+    embedded_migrations::run(&**conn)?;
+    Ok(())
+}
+
+/// Revert the latest migration that was run and return its version. This requires the
+/// "migrations" directory of the source code repository to be found from the current directory.
+pub fn revert_migration(conn: &DbConn) -> Result<String> {
+    Ok(diesel_migrations::revert_latest_migration(&**conn)?)
+}
+
+/// Create a separate connection pool for reading, so that heavy read requests don't compete with
+/// write transactions. If the "WOLFGANG_DATABASE_READ_URL" environment variable is set, the pool
+/// will be connected to that read replica. Otherwise, the primary database is used.
+pub fn connect_read() -> Result<ReadDbPool> {
+    let url = std::env::var("WOLFGANG_DATABASE_READ_URL")
+        .or_else(|_| std::env::var("WOLFGANG_DATABASE_URL"))?;
+
+    let manager = r2d2::ConnectionManager::<LoggingConnection>::new(url);
+    let pool = r2d2::Pool::builder()
+        .connection_customizer(Box::new(ReadOnly))
+        .build(manager)?;
+
+    Ok(ReadDbPool(pool))
+}
+
+/// Makes all connections of a pool read-only.
+#[derive(Debug)]
+struct ReadOnly;
+
+impl r2d2::CustomizeConnection<LoggingConnection, r2d2::Error> for ReadOnly {
+    fn on_acquire(&self, conn: &mut LoggingConnection) -> Result<(), r2d2::Error> {
+        conn.set_read_only().map_err(r2d2::Error::QueryError)
+    }
+}
@@ -0,0 +1,127 @@
+use super::{get_ensemble, get_instrument, get_label, get_person, get_recording, get_work};
+use super::{DbConn, Ensemble, EntityType, Instrument, Label, Medium, Person, Recording, Work};
+use anyhow::Result;
+
+/// Entities that may contain other entities inline. Those are created along with the entity, if
+/// they don't exist yet.
+pub trait NestedEntities {
+    /// Add the types of all contained entities that don't exist yet. This includes entities that
+    /// are contained within them in turn.
+    fn add_new_types(&self, conn: &DbConn, types: &mut Vec<EntityType>) -> Result<()>;
+}
+
+/// Get the types of all entities that would be created along with an entity, because it contains
+/// them and they don't exist yet, e.g. the composer of a new work.
+pub fn get_new_nested_types<T: NestedEntities>(
+    conn: &DbConn,
+    entity: &T,
+) -> Result<Vec<EntityType>> {
+    let mut types = Vec::new();
+    entity.add_new_types(conn, &mut types)?;
+
+    types.sort();
+    types.dedup();
+
+    Ok(types)
+}
+
+impl NestedEntities for Person {
+    fn add_new_types(&self, _: &DbConn, _: &mut Vec<EntityType>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl NestedEntities for Ensemble {
+    fn add_new_types(&self, _: &DbConn, _: &mut Vec<EntityType>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl NestedEntities for Instrument {
+    fn add_new_types(&self, _: &DbConn, _: &mut Vec<EntityType>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl NestedEntities for Label {
+    fn add_new_types(&self, _: &DbConn, _: &mut Vec<EntityType>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl NestedEntities for Work {
+    fn add_new_types(&self, conn: &DbConn, types: &mut Vec<EntityType>) -> Result<()> {
+        let authors = self
+            .authors
+            .iter()
+            .chain(self.parts.iter().flat_map(|part| part.authors.iter()));
+
+        for person in std::iter::once(&self.composer).chain(authors) {
+            if get_person(conn, &person.id)?.is_none() {
+                types.push(EntityType::Person);
+            }
+        }
+
+        for instrument in &self.instruments {
+            if get_instrument(conn, &instrument.id)?.is_none() {
+                types.push(EntityType::Instrument);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl NestedEntities for Recording {
+    fn add_new_types(&self, conn: &DbConn, types: &mut Vec<EntityType>) -> Result<()> {
+        for work in self.works() {
+            if get_work(conn, &work.id)?.is_none() {
+                types.push(EntityType::Work);
+                work.add_new_types(conn, types)?;
+            }
+        }
+
+        for performance in &self.performances {
+            if let Some(person) = &performance.person {
+                if get_person(conn, &person.id)?.is_none() {
+                    types.push(EntityType::Person);
+                }
+            }
+
+            if let Some(ensemble) = &performance.ensemble {
+                if get_ensemble(conn, &ensemble.id)?.is_none() {
+                    types.push(EntityType::Ensemble);
+                }
+            }
+
+            if let Some(role) = &performance.role {
+                if get_instrument(conn, &role.id)?.is_none() {
+                    types.push(EntityType::Instrument);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl NestedEntities for Medium {
+    fn add_new_types(&self, conn: &DbConn, types: &mut Vec<EntityType>) -> Result<()> {
+        if let Some(label) = &self.label {
+            if get_label(conn, &label.id)?.is_none() {
+                types.push(EntityType::Label);
+            }
+        }
+
+        for track_set in &self.tracks {
+            let recording = &track_set.recording;
+
+            if get_recording(conn, &recording.id)?.is_none() {
+                types.push(EntityType::Recording);
+                recording.add_new_types(conn, types)?;
+            }
+        }
+
+        Ok(())
+    }
+}
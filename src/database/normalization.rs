@@ -0,0 +1,132 @@
+use super::{insert_event, normalize_text, with_transaction, DbConn, EntityType, EventKind, User};
+use anyhow::Result;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+
+/// A column containing normalized names, titles or comments. Rows of the tables of entities are
+/// identified by the entity ID. Other tables have numeric IDs and a column referring to the entity
+/// instead.
+struct Pass {
+    entity_type: EntityType,
+    table: &'static str,
+    column: &'static str,
+
+    /// The column containing the entity ID, if the table doesn't belong to the entity itself.
+    entity: Option<&'static str>,
+}
+
+/// Shorthand for a column of the table of an entity.
+const fn entity(entity_type: EntityType, table: &'static str, column: &'static str) -> Pass {
+    Pass {
+        entity_type,
+        table,
+        column,
+        entity: None,
+    }
+}
+
+/// Shorthand for a column of a table containing parts of works.
+const fn work(table: &'static str) -> Pass {
+    Pass {
+        entity_type: EntityType::Work,
+        table,
+        column: "title",
+        entity: Some("work"),
+    }
+}
+
+/// All columns that contain normalized names, titles or comments.
+const PASSES: &[Pass] = &[
+    entity(EntityType::Person, "persons", "first_name"),
+    entity(EntityType::Person, "persons", "last_name"),
+    entity(EntityType::Ensemble, "ensembles", "name"),
+    entity(EntityType::Instrument, "instruments", "name"),
+    entity(EntityType::Label, "labels", "name"),
+    entity(EntityType::Medium, "mediums", "name"),
+    entity(EntityType::Recording, "recordings", "comment"),
+    entity(EntityType::Work, "works", "title"),
+    entity(EntityType::Work, "works", "nickname"),
+    work("work_titles"),
+    work("work_parts"),
+    work("work_part_titles"),
+    work("work_sections"),
+    work("work_section_titles"),
+];
+
+/// One value of a normalized column.
+#[derive(QueryableByName, Debug, Clone)]
+struct Row {
+    #[sql_type = "Text"]
+    key: String,
+
+    #[sql_type = "Text"]
+    entity_id: String,
+
+    #[sql_type = "Text"]
+    value: String,
+}
+
+/// Normalize all stored names and titles again using [`normalize_text`]. This is needed, if the
+/// normalization changed or data was inserted without it. Each change is recorded as an update of
+/// the affected entity on behalf of the user. `progress` is called with the number of finished and
+/// of all columns after each column. Returns the number of changed values.
+pub fn normalize_names(
+    conn: &DbConn,
+    user: &User,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<usize> {
+    let mut changed = 0;
+
+    for (index, pass) in PASSES.iter().enumerate() {
+        changed += normalize_column(conn, pass, user)?;
+        progress(index + 1, PASSES.len());
+    }
+
+    Ok(changed)
+}
+
+/// Update the values of one column that are not normalized yet.
+fn normalize_column(conn: &DbConn, pass: &Pass, user: &User) -> Result<usize> {
+    let (key_type, entity) = match pass.entity {
+        Some(entity) => ("bigint", entity),
+        None => ("text", "id"),
+    };
+
+    let rows: Vec<Row> = diesel::sql_query(format!(
+        "SELECT id::text AS key, {} AS entity_id, {} AS value FROM {} WHERE {} IS NOT NULL",
+        entity, pass.column, pass.table, pass.column,
+    ))
+    .load(conn)?;
+
+    let update = format!(
+        "UPDATE {} SET {} = $1 WHERE id = CAST($2 AS {})",
+        pass.table, pass.column, key_type,
+    );
+
+    let mut changed = 0;
+
+    for row in rows {
+        let normalized = normalize_text(&row.value);
+
+        if normalized != row.value {
+            with_transaction(conn, |tx| {
+                diesel::sql_query(&update)
+                    .bind::<Text, _>(normalized)
+                    .bind::<Text, _>(&row.key)
+                    .execute(tx.conn())?;
+
+                insert_event(
+                    tx.conn(),
+                    pass.entity_type,
+                    &row.entity_id,
+                    EventKind::Update,
+                    user,
+                )
+            })?;
+
+            changed += 1;
+        }
+    }
+
+    Ok(changed)
+}
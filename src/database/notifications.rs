@@ -0,0 +1,406 @@
+use super::schema::{events, notifications, users};
+use super::{delete_watches, get_last_visibility, get_redirect, get_watchers, DbConn, EntityType};
+use super::{EventKind, User};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The maximum number of notifications that are returned at once.
+const MAX_NOTIFICATIONS: i64 = 100;
+
+/// What a notification is about.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationKind {
+    /// An entity created or watched by the user was changed by somebody else.
+    EntityUpdated,
+
+    /// An entity created or watched by the user was deleted by somebody else.
+    EntityDeleted,
+
+    /// A report by the user was resolved. The reference is the ID of the report.
+    ReportResolved,
+
+    /// The user was mentioned in the resolution of a report. The reference is the ID of the
+    /// report.
+    Mentioned,
+
+    /// The application of the user for becoming an editor was approved. The reference is the ID
+    /// of the application.
+    EditorApplicationApproved,
+
+    /// The application of the user for becoming an editor was rejected. The reference is the ID
+    /// of the application.
+    EditorApplicationRejected,
+
+    /// Somebody commented on an entity watched by the user or mentioned the user in a comment.
+    /// The reference is the ID of the comment.
+    EntityCommented,
+
+    /// An entity created or watched by the user was merged into another one by somebody else.
+    /// The reference is the ID of the entity that replaced it.
+    EntityMerged,
+}
+
+impl NotificationKind {
+    /// All notification kinds.
+    pub const ALL: [NotificationKind; 8] = [
+        NotificationKind::EntityUpdated,
+        NotificationKind::EntityDeleted,
+        NotificationKind::ReportResolved,
+        NotificationKind::Mentioned,
+        NotificationKind::EditorApplicationApproved,
+        NotificationKind::EditorApplicationRejected,
+        NotificationKind::EntityCommented,
+        NotificationKind::EntityMerged,
+    ];
+
+    /// Get the string representation of the notification kind that is also used in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::EntityUpdated => "entityUpdated",
+            NotificationKind::EntityDeleted => "entityDeleted",
+            NotificationKind::ReportResolved => "reportResolved",
+            NotificationKind::Mentioned => "mentioned",
+            NotificationKind::EditorApplicationApproved => "editorApplicationApproved",
+            NotificationKind::EditorApplicationRejected => "editorApplicationRejected",
+            NotificationKind::EntityCommented => "entityCommented",
+            NotificationKind::EntityMerged => "entityMerged",
+        }
+    }
+
+    /// Get a notification kind from its string representation.
+    pub fn parse(kind: &str) -> Option<NotificationKind> {
+        NotificationKind::ALL
+            .iter()
+            .find(|k| k.as_str() == kind)
+            .cloned()
+    }
+}
+
+/// A notification for a user about something that concerns them.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub id: i64,
+    pub kind: NotificationKind,
+
+    /// The entity the notification is about, if any.
+    pub entity_type: Option<EntityType>,
+    pub entity_id: Option<String>,
+
+    /// The ID of a report or an application depending on the kind of notification.
+    pub reference: Option<String>,
+
+    /// The user that caused the notification.
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub read: bool,
+}
+
+/// Table data for a new [`Notification`]. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "notifications"]
+struct NewNotificationRow {
+    pub username: String,
+    pub kind: String,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub reference: Option<String>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub mail_pending: bool,
+}
+
+/// Table data for a [`Notification`] without the recipient.
+#[derive(Queryable, Debug, Clone)]
+struct NotificationRow {
+    pub id: i64,
+    pub kind: String,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub reference: Option<String>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub read: bool,
+}
+
+impl NotificationRow {
+    /// Convert the row to a [`Notification`]. This fails, if the stored kind or entity type is
+    /// unknown.
+    fn into_notification(self) -> Result<Notification> {
+        let kind = NotificationKind::parse(&self.kind)
+            .ok_or_else(|| anyhow!("Unknown notification kind: {}", self.kind))?;
+
+        let entity_type = match &self.entity_type {
+            Some(entity_type) => Some(
+                EntityType::parse(entity_type)
+                    .ok_or_else(|| anyhow!("Unknown entity type: {}", entity_type))?,
+            ),
+            None => None,
+        };
+
+        Ok(Notification {
+            id: self.id,
+            kind,
+            entity_type,
+            entity_id: self.entity_id,
+            reference: self.reference,
+            created_by: self.created_by,
+            created_at: self.created_at,
+            read: self.read,
+        })
+    }
+}
+
+/// Notify a user about something another user did. Nothing happens, if the users are the same.
+pub fn insert_notification(
+    conn: &DbConn,
+    username: &str,
+    kind: NotificationKind,
+    entity: Option<(EntityType, &str)>,
+    reference: Option<&str>,
+    user: &User,
+) -> Result<()> {
+    insert_notification_row(conn, username, kind, entity, reference, false, user)
+}
+
+/// Insert a notification and optionally mark it for being sent by mail. See
+/// [`insert_notification`].
+fn insert_notification_row(
+    conn: &DbConn,
+    username: &str,
+    kind: NotificationKind,
+    entity: Option<(EntityType, &str)>,
+    reference: Option<&str>,
+    mail: bool,
+    user: &User,
+) -> Result<()> {
+    if username == user.username {
+        return Ok(());
+    }
+
+    let row = NewNotificationRow {
+        username: username.to_string(),
+        kind: kind.as_str().to_string(),
+        entity_type: entity.map(|(entity_type, _)| entity_type.as_str().to_string()),
+        entity_id: entity.map(|(_, id)| id.to_string()),
+        reference: reference.map(str::to_string),
+        created_by: user.username.clone(),
+        created_at: Utc::now().naive_utc(),
+        mail_pending: mail,
+    };
+
+    diesel::insert_into(notifications::table)
+        .values(row)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Notify the creator of an entity and all users watching it that somebody else changed or
+/// deleted it. Watchers may additionally get a mail. Once an entity is deleted, it can't be
+/// watched anymore. Deleted entities that have a redirect were merged into another one, which is
+/// notified about instead. Users that may not see the entity aren't notified. This is called for
+/// each new event.
+pub fn notify_change(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    kind: EventKind,
+    user: &User,
+) -> Result<()> {
+    let mut reference = None;
+
+    let kind = match kind {
+        EventKind::Create => return Ok(()),
+        EventKind::Update => NotificationKind::EntityUpdated,
+        EventKind::Delete => match get_redirect(conn, entity_type, entity_id)? {
+            Some(new_id) => {
+                reference = Some(new_id);
+                NotificationKind::EntityMerged
+            }
+            None => NotificationKind::EntityDeleted,
+        },
+    };
+
+    // Each user is notified only once and gets a mail, if any of the reasons asks for it.
+    let mut recipients: BTreeMap<String, bool> = BTreeMap::new();
+
+    // The creator is stored with the entity, but that is replaced on each change.
+    let creator = events::table
+        .filter(events::entity_type.eq(entity_type.as_str()))
+        .filter(events::entity_id.eq(entity_id))
+        .filter(events::kind.eq(EventKind::Create.as_str()))
+        .order_by(events::id.desc())
+        .select(events::created_by)
+        .first::<String>(conn)
+        .optional()?;
+
+    if let Some(creator) = creator {
+        recipients.insert(creator, false);
+    }
+
+    for (username, email) in get_watchers(conn, entity_type, entity_id)? {
+        *recipients.entry(username).or_insert(false) |= email;
+    }
+
+    let visibility = get_last_visibility(conn, entity_type, entity_id)?;
+    recipients.retain(|username, _| {
+        visibility
+            .as_ref()
+            .is_some_and(|visibility| visibility.is_visible_to_name(username))
+    });
+
+    for (username, mail) in recipients {
+        insert_notification_row(
+            conn,
+            &username,
+            kind,
+            Some((entity_type, entity_id)),
+            reference.as_deref(),
+            mail,
+            user,
+        )?;
+    }
+
+    if kind != NotificationKind::EntityUpdated {
+        delete_watches(conn, entity_type, entity_id)?;
+    }
+
+    Ok(())
+}
+
+/// Notify all users that are mentioned using "@username" within a text.
+pub fn notify_mentions(
+    conn: &DbConn,
+    text: &str,
+    kind: NotificationKind,
+    reference: Option<&str>,
+    user: &User,
+) -> Result<()> {
+    for username in get_mentioned_users(conn, text)? {
+        insert_notification(conn, &username, kind, None, reference, user)?;
+    }
+
+    Ok(())
+}
+
+/// Get the names of all existing users that are mentioned using "@username" within a text.
+pub fn get_mentioned_users(conn: &DbConn, text: &str) -> Result<Vec<String>> {
+    let mut mentioned: Vec<&str> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '@' || c == '_' || c == '-' || c == '.'))
+        .filter_map(|word| word.strip_prefix('@'))
+        .map(|username| username.trim_end_matches('.'))
+        .filter(|username| !username.is_empty())
+        .collect();
+
+    mentioned.sort_unstable();
+    mentioned.dedup();
+
+    Ok(users::table
+        .filter(users::username.eq_any(&mentioned))
+        .select(users::username)
+        .load(conn)?)
+}
+
+/// Get the latest notifications of a user, newest first. If `unread` is set, only unread
+/// notifications are returned.
+pub fn get_notifications(conn: &DbConn, user: &User, unread: bool) -> Result<Vec<Notification>> {
+    let mut query = notifications::table
+        .filter(notifications::username.eq(&user.username))
+        .order_by(notifications::id.desc())
+        .limit(MAX_NOTIFICATIONS)
+        .select((
+            notifications::id,
+            notifications::kind,
+            notifications::entity_type,
+            notifications::entity_id,
+            notifications::reference,
+            notifications::created_by,
+            notifications::created_at,
+            notifications::read,
+        ))
+        .into_boxed();
+
+    if unread {
+        query = query.filter(notifications::read.eq(false));
+    }
+
+    query
+        .load::<NotificationRow>(conn)?
+        .into_iter()
+        .map(NotificationRow::into_notification)
+        .collect()
+}
+
+/// Mark notifications of a user as read. If no IDs are provided, all notifications are marked.
+pub fn mark_notifications_read(conn: &DbConn, user: &User, ids: Option<&[i64]>) -> Result<()> {
+    let query = notifications::table.filter(notifications::username.eq(&user.username));
+
+    match ids {
+        Some(ids) => diesel::update(query.filter(notifications::id.eq_any(ids)))
+            .set(notifications::read.eq(true))
+            .execute(conn)?,
+        None => diesel::update(query)
+            .set(notifications::read.eq(true))
+            .execute(conn)?,
+    };
+
+    Ok(())
+}
+
+/// A notification that should be sent by mail.
+#[derive(Debug, Clone)]
+pub struct PendingMail {
+    pub username: String,
+
+    /// The address of the user. If this is missing, the mail can't be sent.
+    pub email: Option<String>,
+
+    pub notification: Notification,
+}
+
+/// Get all notifications that should be sent by mail, oldest first.
+pub fn get_pending_mails(conn: &DbConn) -> Result<Vec<PendingMail>> {
+    let rows = notifications::table
+        .inner_join(users::table.on(users::username.eq(notifications::username)))
+        .filter(notifications::mail_pending.eq(true))
+        .order_by(notifications::id)
+        .select((
+            users::username,
+            users::email,
+            (
+                notifications::id,
+                notifications::kind,
+                notifications::entity_type,
+                notifications::entity_id,
+                notifications::reference,
+                notifications::created_by,
+                notifications::created_at,
+                notifications::read,
+            ),
+        ))
+        .load::<(String, Option<String>, NotificationRow)>(conn)?;
+
+    rows.into_iter()
+        .map(|(username, email, row)| {
+            Ok(PendingMail {
+                username,
+                email,
+                notification: row.into_notification()?,
+            })
+        })
+        .collect()
+}
+
+/// Mark notifications as not needing to be sent by mail anymore.
+pub fn clear_pending_mails(conn: &DbConn, ids: &[i64]) -> Result<()> {
+    diesel::update(notifications::table.filter(notifications::id.eq_any(ids)))
+        .set(notifications::mail_pending.eq(false))
+        .execute(conn)?;
+
+    Ok(())
+}
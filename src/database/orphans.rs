@@ -0,0 +1,115 @@
+use super::schema::{drafts, events, mediums, quality_flags, read_models, recordings, trash};
+use super::{entity_exists, DbConn, EntityType, EventKind};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel::dsl::{exists, not};
+use diesel::prelude::*;
+
+/// Delete all rows that refer to entities that don't exist anymore. Those are left behind,
+/// because they refer to entities using their type and ID instead of a foreign key. Returns the
+/// number of deleted rows.
+pub fn delete_orphans(conn: &DbConn) -> Result<usize> {
+    Ok(delete_orphaned_read_models(conn)?
+        + delete_orphaned_quality_flags(conn)?
+        + delete_orphaned_drafts(conn)?)
+}
+
+/// Delete the read models of deleted recordings and mediums. They are only invalidated when the
+/// entity is deleted and will be recreated if it is restored from the trash.
+fn delete_orphaned_read_models(conn: &DbConn) -> Result<usize> {
+    let recordings = diesel::delete(
+        read_models::table
+            .filter(read_models::entity_type.eq(EntityType::Recording.as_str()))
+            .filter(not(
+                read_models::entity_id.eq_any(recordings::table.select(recordings::id))
+            )),
+    )
+    .execute(conn)?;
+
+    let mediums = diesel::delete(
+        read_models::table
+            .filter(read_models::entity_type.eq(EntityType::Medium.as_str()))
+            .filter(not(
+                read_models::entity_id.eq_any(mediums::table.select(mediums::id))
+            )),
+    )
+    .execute(conn)?;
+
+    Ok(recordings + mediums)
+}
+
+/// Delete the quality flags of deleted entities. Flags of entities within the trash are kept, so
+/// that they are still there if the entity is restored.
+fn delete_orphaned_quality_flags(conn: &DbConn) -> Result<usize> {
+    let flags = quality_flags::table
+        .select((quality_flags::entity_type, quality_flags::entity_id))
+        .load::<(String, String)>(conn)?;
+
+    let mut count = 0;
+
+    for (entity_type, entity_id) in flags {
+        if let Some(entity_type) = EntityType::parse(&entity_type) {
+            if !entity_exists(conn, entity_type, &entity_id)?
+                && !is_in_trash(conn, entity_type, &entity_id)?
+            {
+                count += diesel::delete(
+                    quality_flags::table
+                        .filter(quality_flags::entity_type.eq(entity_type.as_str()))
+                        .filter(quality_flags::entity_id.eq(&entity_id)),
+                )
+                .execute(conn)?;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Delete drafts for changing entities that were deleted after the draft was saved. Drafts of new
+/// entities are kept, even though their entity doesn't exist.
+fn delete_orphaned_drafts(conn: &DbConn) -> Result<usize> {
+    let drafts = drafts::table
+        .select((drafts::entity_type, drafts::id, drafts::updated_at))
+        .load::<(String, String, NaiveDateTime)>(conn)?;
+
+    let mut count = 0;
+
+    for (entity_type, id, updated_at) in drafts {
+        if let Some(entity_type) = EntityType::parse(&entity_type) {
+            let deleted: bool = diesel::select(exists(
+                events::table
+                    .filter(events::entity_type.eq(entity_type.as_str()))
+                    .filter(events::entity_id.eq(&id))
+                    .filter(events::kind.eq(EventKind::Delete.as_str()))
+                    .filter(events::created_at.gt(updated_at)),
+            ))
+            .get_result(conn)?;
+
+            if deleted
+                && !entity_exists(conn, entity_type, &id)?
+                && !is_in_trash(conn, entity_type, &id)?
+            {
+                count += diesel::delete(
+                    drafts::table
+                        .filter(drafts::entity_type.eq(entity_type.as_str()))
+                        .filter(drafts::id.eq(&id)),
+                )
+                .execute(conn)?;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Check whether a deleted entity is still within the trash.
+fn is_in_trash(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<bool> {
+    let result = diesel::select(exists(
+        trash::table
+            .filter(trash::entity_type.eq(entity_type.as_str()))
+            .filter(trash::entity_id.eq(id)),
+    ))
+    .get_result(conn)?;
+
+    Ok(result)
+}
@@ -0,0 +1,91 @@
+use super::schema::{periods, persons, works};
+use super::{DbConn, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A musical period like the Baroque that persons and works can be assigned to.
+#[derive(Insertable, Queryable, AsChangeset, Serialize, Deserialize, Debug, Clone)]
+#[table_name = "periods"]
+#[changeset_options(treat_none_as_null = "true")]
+#[serde(rename_all = "camelCase")]
+pub struct Period {
+    pub id: String,
+    pub name: String,
+
+    /// The approximate year in which the period began, if it has a beginning.
+    #[serde(default)]
+    pub start_year: Option<i32>,
+
+    /// The approximate year in which the period ended, if it is over.
+    #[serde(default)]
+    pub end_year: Option<i32>,
+}
+
+/// Add a new period or update an existing one. Only editors are allowed to do this.
+pub fn update_period(conn: &DbConn, period: &Period, user: &User) -> Result<()> {
+    if !user.may_manage_periods() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    diesel::insert_into(periods::table)
+        .values(period)
+        .on_conflict(periods::id)
+        .do_update()
+        .set(period)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Get an existing period.
+pub fn get_period(conn: &DbConn, id: &str) -> Result<Option<Period>> {
+    Ok(periods::table
+        .filter(periods::id.eq(id))
+        .first::<Period>(conn)
+        .optional()?)
+}
+
+/// Get all periods in chronological order.
+pub fn get_periods(conn: &DbConn) -> Result<Vec<Period>> {
+    Ok(periods::table
+        .order_by((periods::start_year.asc().nulls_first(), periods::id))
+        .load::<Period>(conn)?)
+}
+
+/// Delete a period. This fails with a conflict, if persons or works are still assigned to it.
+/// Only editors are allowed to do this.
+pub fn delete_period(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+    if !user.may_manage_periods() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    conn.transaction::<(), Error, _>(|| {
+        let used: bool = diesel::select(exists(persons::table.filter(persons::period.eq(id))))
+            .get_result::<bool>(conn)?
+            || diesel::select(exists(works::table.filter(works::period.eq(id))))
+                .get_result::<bool>(conn)?;
+
+        if used {
+            return Err(Error::new(ServerError::Conflict));
+        }
+
+        diesel::delete(periods::table.filter(periods::id.eq(id))).execute(conn)?;
+
+        Ok(())
+    })
+}
+
+/// Check that a period exists before assigning it to an entity. Unknown periods result in a bad
+/// request.
+pub fn check_period(conn: &DbConn, period: Option<&str>) -> Result<()> {
+    if let Some(id) = period {
+        if get_period(conn, id)?.is_none() {
+            return Err(Error::new(ServerError::BadRequest));
+        }
+    }
+
+    Ok(())
+}
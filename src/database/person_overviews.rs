@@ -0,0 +1,181 @@
+use super::schema::{ensembles, instruments, performances, recordings, work_authors, works};
+use super::{get_person, viewer_name, DbConn, Ensemble, Person, User};
+use anyhow::Result;
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// Everything a person was involved in, as shown on an overview page.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonOverview {
+    pub person: Person,
+
+    /// The works composed by the person ordered by title.
+    pub works: Vec<WorkOverview>,
+
+    /// The recordings the person performed in ordered by the title of their work.
+    pub recordings: Vec<RecordingOverview>,
+
+    /// The ensembles that performed within the same recordings as the person ordered by name.
+    /// Wolfgang doesn't know about members of ensembles, so this is the closest match.
+    pub ensembles_performed_with: Vec<Ensemble>,
+
+    pub statistics: PersonStatistics,
+}
+
+/// The basic information on a work within a [`PersonOverview`].
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkOverview {
+    pub id: String,
+    pub title: String,
+    pub nickname: Option<String>,
+}
+
+/// The basic information on a recording within a [`PersonOverview`].
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingOverview {
+    pub id: String,
+    pub work: WorkOverview,
+
+    /// The names of the instruments or roles the person performed in the recording.
+    pub roles: Vec<String>,
+}
+
+/// How many entities a person was involved in.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonStatistics {
+    pub works: i64,
+
+    /// The number of works with texts written by the person.
+    pub authored_works: i64,
+
+    pub recordings: i64,
+    pub ensembles_performed_with: i64,
+}
+
+/// Get an overview of the works and recordings of a person. Only public entities and private
+/// entities of the viewer are included. This uses a fixed number of queries regardless of how
+/// many works and recordings there are. Returns [`None`], if the person doesn't exist.
+pub fn get_person_overview(
+    conn: &DbConn,
+    id: &str,
+    viewer: Option<&User>,
+) -> Result<Option<PersonOverview>> {
+    let person = match get_person(conn, id)? {
+        Some(person) => person,
+        None => return Ok(None),
+    };
+
+    let works: Vec<WorkOverview> = works::table
+        .filter(works::composer.eq(id))
+        .filter(
+            works::private
+                .eq(false)
+                .or(works::created_by.eq(viewer_name(viewer))),
+        )
+        .order_by(works::title)
+        .select((works::id, works::title, works::nickname))
+        .load::<(String, String, Option<String>)>(conn)?
+        .into_iter()
+        .map(|(id, title, nickname)| WorkOverview {
+            id,
+            title,
+            nickname,
+        })
+        .collect();
+
+    let authored_works = work_authors::table
+        .inner_join(works::table)
+        .filter(work_authors::person.eq(id))
+        .filter(
+            works::private
+                .eq(false)
+                .or(works::created_by.eq(viewer_name(viewer))),
+        )
+        .select(work_authors::work)
+        .distinct()
+        .load::<String>(conn)?
+        .len();
+
+    let rows = performances::table
+        .inner_join(recordings::table.inner_join(works::table))
+        .left_join(instruments::table)
+        .filter(performances::person.eq(id))
+        .filter(
+            recordings::private
+                .eq(false)
+                .or(recordings::created_by.eq(viewer_name(viewer))),
+        )
+        .order_by((works::title, recordings::id, performances::id))
+        .select((
+            recordings::id,
+            works::id,
+            works::title,
+            works::nickname,
+            instruments::name.nullable(),
+        ))
+        .load::<(String, String, String, Option<String>, Option<String>)>(conn)?;
+
+    // A person may have multiple roles within the same recording, which are adjacent rows.
+    let mut recordings: Vec<RecordingOverview> = Vec::new();
+    for (recording_id, work_id, title, nickname, role) in rows {
+        if recordings.last().map(|recording| &recording.id) != Some(&recording_id) {
+            recordings.push(RecordingOverview {
+                id: recording_id,
+                work: WorkOverview {
+                    id: work_id,
+                    title,
+                    nickname,
+                },
+                roles: Vec::new(),
+            });
+        }
+
+        if let (Some(recording), Some(role)) = (recordings.last_mut(), role) {
+            if !recording.roles.contains(&role) {
+                recording.roles.push(role);
+            }
+        }
+    }
+
+    let recording_ids: Vec<&str> = recordings
+        .iter()
+        .map(|recording| recording.id.as_str())
+        .collect();
+
+    let performing_ensembles = performances::table
+        .filter(performances::recording.eq_any(recording_ids))
+        .select(performances::ensemble);
+
+    let ensembles_performed_with: Vec<Ensemble> = ensembles::table
+        .filter(ensembles::id.nullable().eq_any(performing_ensembles))
+        .filter(
+            ensembles::private
+                .eq(false)
+                .or(ensembles::created_by.eq(viewer_name(viewer))),
+        )
+        .order_by(ensembles::name)
+        .select((ensembles::id, ensembles::name, ensembles::private))
+        .load::<(String, String, bool)>(conn)?
+        .into_iter()
+        .map(|(id, name, private)| Ensemble { id, name, private })
+        .collect();
+
+    let statistics = PersonStatistics {
+        works: works.len() as i64,
+        authored_works: authored_works as i64,
+        recordings: recordings.len() as i64,
+        ensembles_performed_with: ensembles_performed_with.len() as i64,
+    };
+
+    Ok(Some(PersonOverview {
+        person,
+        works,
+        recordings,
+        ensembles_performed_with,
+        statistics,
+    }))
+}
@@ -0,0 +1,299 @@
+use super::{check_reference, get_person, get_visibility, insert_event, is_entity_visible};
+use super::{may_edit_person, with_transaction, DbConn, EntityType, EventKind, Person, User};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+
+/// How a person is related to another one from the perspective of that other person.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RelationKind {
+    /// The related person taught the person.
+    Teacher,
+
+    /// The person taught the related person.
+    Student,
+
+    /// The persons are members of the same family.
+    Family,
+
+    /// The persons are married to each other.
+    Spouse,
+}
+
+impl RelationKind {
+    /// All relation kinds.
+    pub const ALL: [RelationKind; 4] = [
+        RelationKind::Teacher,
+        RelationKind::Student,
+        RelationKind::Family,
+        RelationKind::Spouse,
+    ];
+
+    /// Get the string representation of the relation kind as used within the API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RelationKind::Teacher => "teacher",
+            RelationKind::Student => "student",
+            RelationKind::Family => "family",
+            RelationKind::Spouse => "spouse",
+        }
+    }
+
+    /// Get a relation kind from its string representation.
+    pub fn parse(kind: &str) -> Option<RelationKind> {
+        RelationKind::ALL
+            .iter()
+            .find(|k| k.as_str() == kind)
+            .cloned()
+    }
+}
+
+/// Another person and how they are related to a person.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonRelation {
+    pub kind: RelationKind,
+    pub person: Person,
+}
+
+/// A table storing relations between entities of one type. Each relation is stored only once
+/// with the kind as seen from the entity in the first column.
+pub(super) struct RelationTable {
+    pub entity_type: EntityType,
+    pub table: &'static str,
+
+    /// The column containing the first entity. The second one is always stored in `related`.
+    pub column: &'static str,
+
+    /// Check whether a user may edit an entity and thereby its relations.
+    pub may_edit: fn(&DbConn, &str, &User) -> Result<bool>,
+}
+
+/// The table storing relations between persons.
+const PERSON_RELATIONS: RelationTable = RelationTable {
+    entity_type: EntityType::Person,
+    table: "person_relations",
+    column: "person",
+    may_edit: may_edit_person,
+};
+
+/// A stored relation as it is found in the table.
+#[derive(QueryableByName, Debug, Clone)]
+pub(super) struct RelationRow {
+    #[sql_type = "Text"]
+    pub entity: String,
+
+    #[sql_type = "Text"]
+    pub related: String,
+
+    #[sql_type = "Text"]
+    pub kind: String,
+}
+
+/// Get the row values for a relation of a person. Teachers are stored first and symmetric
+/// relations are stored with the smaller ID first, so that each relation is stored only once.
+fn get_row_key(person_id: &str, kind: RelationKind, related_id: &str) -> (String, String, String) {
+    let (first, second, stored_kind) = match kind {
+        RelationKind::Teacher => (related_id, person_id, "teacher"),
+        RelationKind::Student => (person_id, related_id, "teacher"),
+        RelationKind::Family | RelationKind::Spouse if person_id <= related_id => {
+            (person_id, related_id, kind.as_str())
+        }
+        RelationKind::Family | RelationKind::Spouse => (related_id, person_id, kind.as_str()),
+    };
+
+    (
+        first.to_string(),
+        second.to_string(),
+        stored_kind.to_string(),
+    )
+}
+
+/// Store a relation between an entity and another one of the same type using the row values
+/// `key`. The user has to be allowed to edit the entity and both entities have to be visible to
+/// them. Public entities can't be related to private ones.
+pub(super) fn insert_relation(
+    conn: &DbConn,
+    table: &RelationTable,
+    id: &str,
+    related_id: &str,
+    key: (String, String, String),
+    user: &User,
+) -> Result<()> {
+    if id == related_id {
+        return Err(Error::new(ServerError::BadRequest));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let visibility = match get_visibility(conn, table.entity_type, id)? {
+            Some(visibility) if visibility.is_visible_to(Some(user)) => visibility,
+            _ => return Err(Error::new(ServerError::NotFound)),
+        };
+
+        if !is_entity_visible(conn, table.entity_type, related_id, Some(user))? {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        if !(table.may_edit)(conn, id, user)? {
+            return Err(Error::new(ServerError::Forbidden));
+        }
+
+        check_reference(
+            conn,
+            table.entity_type,
+            related_id,
+            visibility.private,
+            user,
+        )?;
+
+        let (first, second, kind) = key;
+
+        diesel::sql_query(format!(
+            "INSERT INTO {} ({}, related, kind, created_by) VALUES ($1, $2, $3, $4) \
+            ON CONFLICT DO NOTHING",
+            table.table, table.column,
+        ))
+        .bind::<Text, _>(first)
+        .bind::<Text, _>(second)
+        .bind::<Text, _>(kind)
+        .bind::<Text, _>(&user.username)
+        .execute(conn)?;
+
+        insert_event(conn, table.entity_type, id, EventKind::Update, user)?;
+
+        Ok(())
+    })
+}
+
+/// Remove the relation with the row values `key` from an entity. The user has to be allowed to
+/// edit the entity.
+pub(super) fn delete_relation(
+    conn: &DbConn,
+    table: &RelationTable,
+    id: &str,
+    key: (String, String, String),
+    user: &User,
+) -> Result<()> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        if !is_entity_visible(conn, table.entity_type, id, Some(user))? {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        if !(table.may_edit)(conn, id, user)? {
+            return Err(Error::new(ServerError::Forbidden));
+        }
+
+        let (first, second, kind) = key;
+
+        let count = diesel::sql_query(format!(
+            "DELETE FROM {} WHERE {} = $1 AND related = $2 AND kind = $3",
+            table.table, table.column,
+        ))
+        .bind::<Text, _>(first)
+        .bind::<Text, _>(second)
+        .bind::<Text, _>(kind)
+        .execute(conn)?;
+
+        if count == 0 {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        insert_event(conn, table.entity_type, id, EventKind::Update, user)?;
+
+        Ok(())
+    })
+}
+
+/// Get all stored relations an entity is part of on either side in the order they were added.
+/// This includes relations to entities that are not visible to the viewer.
+pub(super) fn get_relation_rows(
+    conn: &DbConn,
+    table: &RelationTable,
+    id: &str,
+) -> Result<Vec<RelationRow>> {
+    let rows = diesel::sql_query(format!(
+        "SELECT {0} AS entity, related, kind FROM {1} WHERE {0} = $1 OR related = $1 ORDER BY id",
+        table.column, table.table,
+    ))
+    .bind::<Text, _>(id)
+    .load(conn)?;
+
+    Ok(rows)
+}
+
+/// Relate another person to a person. The user has to be allowed to edit the person and both
+/// persons have to be visible to them. Public persons can't be related to private ones.
+pub fn insert_person_relation(
+    conn: &DbConn,
+    person_id: &str,
+    kind: RelationKind,
+    related_id: &str,
+    user: &User,
+) -> Result<()> {
+    let key = get_row_key(person_id, kind, related_id);
+    insert_relation(conn, &PERSON_RELATIONS, person_id, related_id, key, user)
+}
+
+/// Remove a relation between two persons. The user has to be allowed to edit the person.
+pub fn delete_person_relation(
+    conn: &DbConn,
+    person_id: &str,
+    kind: RelationKind,
+    related_id: &str,
+    user: &User,
+) -> Result<()> {
+    let key = get_row_key(person_id, kind, related_id);
+    delete_relation(conn, &PERSON_RELATIONS, person_id, key, user)
+}
+
+/// Get all persons related to a person that are visible to the viewer.
+pub fn get_person_relations(
+    conn: &DbConn,
+    person_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<PersonRelation>> {
+    let mut relations = Vec::new();
+
+    for RelationRow {
+        entity: person,
+        related,
+        kind,
+    } in get_relation_rows(conn, &PERSON_RELATIONS, person_id)?
+    {
+        let (other_id, kind) = match kind.as_str() {
+            "teacher" if person == person_id => (related, RelationKind::Student),
+            "teacher" => (person, RelationKind::Teacher),
+            _ => {
+                let kind = RelationKind::parse(&kind)
+                    .ok_or_else(|| anyhow!("Unknown relation kind: {}", kind))?;
+
+                if person == person_id {
+                    (related, kind)
+                } else {
+                    (person, kind)
+                }
+            }
+        };
+
+        if !is_entity_visible(conn, EntityType::Person, &other_id, viewer)? {
+            continue;
+        }
+
+        let other = get_person(conn, &other_id)?
+            .ok_or_else(|| anyhow!("No person with ID: {}", other_id))?;
+
+        relations.push(PersonRelation {
+            kind,
+            person: other,
+        });
+    }
+
+    Ok(relations)
+}
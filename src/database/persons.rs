@@ -1,27 +1,57 @@
 use super::schema::persons;
-use super::{DbConn, User};
+use super::{
+    check_may_become_private, check_period, check_quota, check_unreferenced, insert_event,
+    may_delete_entity, move_to_trash, normalize_text, viewer_name, with_transaction, DbConn,
+    DbTransaction, EntityType, EventKind, User,
+};
 use crate::error::ServerError;
 use anyhow::{Error, Result};
 use diesel::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// A person as represented within the API.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Person {
+    /// Left out for new persons, so that the server generates it.
+    #[serde(default)]
     pub id: String,
     pub first_name: String,
     pub last_name: String,
+
+    /// The ID of the musical period the person belongs to, if any.
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Whether the person can only be edited by editors. This is ignored on updates.
+    #[serde(default)]
+    pub locked: bool,
+
+    /// Whether the person is only visible to the user that created it.
+    #[serde(default)]
+    pub private: bool,
 }
 
 /// A person as represented in the database.
 #[derive(Insertable, Queryable, AsChangeset, Debug, Clone)]
 #[table_name = "persons"]
+#[changeset_options(treat_none_as_null = "true")]
 struct PersonRow {
     pub id: String,
     pub first_name: String,
     pub last_name: String,
     pub created_by: String,
+    pub locked: bool,
+    pub private: bool,
+    pub period: Option<String>,
+}
+
+impl Person {
+    /// Get the full name of the person with the first name in front.
+    pub fn name_fl(&self) -> String {
+        format!("{} {}", self.first_name, self.last_name)
+    }
 }
 
 impl From<PersonRow> for Person {
@@ -30,6 +60,9 @@ impl From<PersonRow> for Person {
             id: row.id,
             first_name: row.first_name,
             last_name: row.last_name,
+            period: row.period,
+            locked: row.locked,
+            private: row.private,
         }
     }
 }
@@ -37,19 +70,46 @@ impl From<PersonRow> for Person {
 /// Update an existing person or insert a new one. This will only work, if the provided user is
 /// allowed to do that.
 pub fn update_person(conn: &DbConn, person: &Person, user: &User) -> Result<()> {
+    with_transaction(conn, |tx| update_person_in(tx, person, user))
+}
+
+/// Update an existing person or insert a new one as part of a larger change. See
+/// [`update_person`].
+pub fn update_person_in(tx: &DbTransaction, person: &Person, user: &User) -> Result<()> {
+    let conn = tx.conn();
+
     let old_row = get_person_row(conn, &person.id)?;
+    let kind = if old_row.is_some() {
+        EventKind::Update
+    } else {
+        EventKind::Create
+    };
 
-    let allowed = match old_row {
-        Some(row) => user.may_edit(&row.created_by),
+    if old_row.is_none() {
+        check_quota(conn, user)?;
+    }
+
+    let allowed = match &old_row {
+        Some(row) if row.private => user.may_edit_private(&row.created_by),
+        Some(row) => user.may_edit_item(&row.created_by, row.locked),
         None => user.may_create(),
     };
 
     if allowed {
+        if person.private && matches!(&old_row, Some(row) if !row.private) {
+            check_may_become_private(conn, EntityType::Person, &person.id, user)?;
+        }
+
+        check_period(conn, person.period.as_deref())?;
+
         let new_row = PersonRow {
             id: person.id.clone(),
-            first_name: person.first_name.clone(),
-            last_name: person.last_name.clone(),
+            first_name: normalize_text(&person.first_name),
+            last_name: normalize_text(&person.last_name),
             created_by: user.username.clone(),
+            locked: old_row.map(|row| row.locked).unwrap_or(false),
+            private: person.private,
+            period: person.period.clone(),
         };
 
         diesel::insert_into(persons::table)
@@ -59,6 +119,8 @@ pub fn update_person(conn: &DbConn, person: &Person, user: &User) -> Result<()>
             .set(&new_row)
             .execute(conn)?;
 
+        insert_event(conn, EntityType::Person, &person.id, kind, user)?;
+
         Ok(())
     } else {
         Err(Error::new(ServerError::Forbidden))
@@ -73,19 +135,70 @@ pub fn get_person(conn: &DbConn, id: &str) -> Result<Option<Person>> {
     Ok(person)
 }
 
+/// Check whether a user may edit an existing person. This fails, if the person doesn't exist.
+pub fn may_edit_person(conn: &DbConn, id: &str, user: &User) -> Result<bool> {
+    let row = get_person_row(conn, id)?.ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+    let allowed = if row.private {
+        user.may_edit_private(&row.created_by)
+    } else {
+        user.may_edit_item(&row.created_by, row.locked)
+    };
+
+    Ok(allowed)
+}
+
 /// Delete an existing person. This will only work if the provided user is allowed to do that.
 pub fn delete_person(conn: &DbConn, id: &str, user: &User) -> Result<()> {
-    if user.may_delete() {
-        diesel::delete(persons::table.filter(persons::id.eq(id))).execute(conn)?;
-        Ok(())
+    if may_delete_entity(conn, EntityType::Person, id, user)? {
+        conn.transaction::<(), Error, _>(|| {
+            check_unreferenced(conn, EntityType::Person, id)?;
+            move_to_trash(conn, EntityType::Person, id, user)?;
+
+            let count = diesel::delete(persons::table.filter(persons::id.eq(id))).execute(conn)?;
+
+            if count > 0 {
+                insert_event(conn, EntityType::Person, id, EventKind::Delete, user)?;
+            }
+
+            Ok(())
+        })
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Lock or unlock an existing person. This will only work if the provided user is allowed to do
+/// that.
+pub fn set_person_locked(conn: &DbConn, id: &str, locked: bool, user: &User) -> Result<()> {
+    if user.may_lock() {
+        conn.transaction::<(), Error, _>(|| {
+            let count = diesel::update(persons::table.filter(persons::id.eq(id)))
+                .set(persons::locked.eq(locked))
+                .execute(conn)?;
+
+            if count == 0 {
+                return Err(Error::new(ServerError::NotFound));
+            }
+
+            insert_event(conn, EntityType::Person, id, EventKind::Update, user)?;
+
+            Ok(())
+        })
     } else {
         Err(Error::new(ServerError::Forbidden))
     }
 }
 
-/// Get all existing persons.
-pub fn get_persons(conn: &DbConn) -> Result<Vec<Person>> {
-    let rows = persons::table.load::<PersonRow>(conn)?;
+/// Get all existing persons that are public or private persons of the viewer.
+pub fn get_persons(conn: &DbConn, viewer: Option<&User>) -> Result<Vec<Person>> {
+    let rows = persons::table
+        .filter(
+            persons::private
+                .eq(false)
+                .or(persons::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<PersonRow>(conn)?;
     let persons: Vec<Person> = rows.into_iter().map(|row| row.into()).collect();
 
     Ok(persons)
@@ -0,0 +1,239 @@
+use super::schema::{playlist_items, playlists, track_sets, tracks};
+use super::{is_entity_visible, with_transaction, DbConn, EntityType, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A list of recordings and tracks that a user wants to listen to in order.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Playlist {
+    pub id: String,
+    pub name: String,
+    pub items: Vec<PlaylistItem>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// An entry within a playlist.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistItem {
+    /// The recording to play.
+    pub recording: String,
+
+    /// If set, only this track of the recording is played instead of the whole recording.
+    pub track: Option<TrackReference>,
+}
+
+/// A track identified by its position within a medium.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackReference {
+    pub medium: String,
+
+    /// The index of the track counting across all track sets of the medium, starting at zero.
+    pub index: usize,
+}
+
+/// Table data for a [`Playlist`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "playlists"]
+struct PlaylistRow {
+    pub id: String,
+    pub username: String,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Table data for a [`PlaylistItem`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "playlist_items"]
+struct PlaylistItemRow {
+    pub playlist: String,
+    pub item_index: i32,
+    pub recording: String,
+    pub medium: Option<String>,
+    pub track_index: Option<i32>,
+}
+
+/// Create a new playlist or replace the name and items of an existing one. Playlists can only be
+/// changed by their owner. All referenced recordings have to be visible to the user and referenced
+/// tracks have to belong to the recording of their item.
+pub fn update_playlist(
+    conn: &DbConn,
+    id: &str,
+    name: &str,
+    items: &[PlaylistItem],
+    user: &User,
+) -> Result<()> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let old_row = get_playlist_row(conn, id)?;
+        let now = Utc::now().naive_utc();
+
+        match old_row {
+            Some(old_row) => {
+                if old_row.username != user.username {
+                    return Err(Error::new(ServerError::Conflict));
+                }
+
+                diesel::update(playlists::table)
+                    .filter(playlists::id.eq(id))
+                    .set((playlists::name.eq(name), playlists::updated_at.eq(now)))
+                    .execute(conn)?;
+
+                diesel::delete(playlist_items::table)
+                    .filter(playlist_items::playlist.eq(id))
+                    .execute(conn)?;
+            }
+            None => {
+                let row = PlaylistRow {
+                    id: id.to_string(),
+                    username: user.username.clone(),
+                    name: name.to_string(),
+                    created_at: now,
+                    updated_at: now,
+                };
+
+                diesel::insert_into(playlists::table)
+                    .values(row)
+                    .execute(conn)?;
+            }
+        }
+
+        let mut rows = Vec::new();
+
+        for (index, item) in items.iter().enumerate() {
+            check_recording_reference(conn, &item.recording, item.track.as_ref(), user)?;
+
+            rows.push(PlaylistItemRow {
+                playlist: id.to_string(),
+                item_index: index as i32,
+                recording: item.recording.clone(),
+                medium: item.track.as_ref().map(|track| track.medium.clone()),
+                track_index: item.track.as_ref().map(|track| track.index as i32),
+            });
+        }
+
+        if !rows.is_empty() {
+            diesel::insert_into(playlist_items::table)
+                .values(rows)
+                .execute(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Get a playlist of a user. Playlists of other users are never returned.
+pub fn get_playlist(conn: &DbConn, id: &str, user: &User) -> Result<Option<Playlist>> {
+    let playlist = match get_playlist_row(conn, id)? {
+        Some(row) if row.username == user.username => Some(get_playlist_data(conn, row)?),
+        _ => None,
+    };
+
+    Ok(playlist)
+}
+
+/// Get all playlists of a user ordered by their name.
+pub fn get_playlists(conn: &DbConn, user: &User) -> Result<Vec<Playlist>> {
+    let rows = playlists::table
+        .filter(playlists::username.eq(&user.username))
+        .order_by(playlists::name)
+        .load::<PlaylistRow>(conn)?;
+
+    rows.into_iter()
+        .map(|row| get_playlist_data(conn, row))
+        .collect()
+}
+
+/// Delete a playlist of a user.
+pub fn delete_playlist(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+    let count = diesel::delete(playlists::table)
+        .filter(playlists::id.eq(id))
+        .filter(playlists::username.eq(&user.username))
+        .execute(conn)?;
+
+    if count == 0 {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    Ok(())
+}
+
+/// Get the table data of a playlist regardless of its owner.
+fn get_playlist_row(conn: &DbConn, id: &str) -> Result<Option<PlaylistRow>> {
+    Ok(playlists::table
+        .filter(playlists::id.eq(id))
+        .load::<PlaylistRow>(conn)?
+        .into_iter()
+        .next())
+}
+
+/// Retrieve the items of a playlist.
+fn get_playlist_data(conn: &DbConn, row: PlaylistRow) -> Result<Playlist> {
+    let item_rows = playlist_items::table
+        .filter(playlist_items::playlist.eq(&row.id))
+        .order_by(playlist_items::item_index)
+        .load::<PlaylistItemRow>(conn)?;
+
+    let items = item_rows
+        .into_iter()
+        .map(|item_row| PlaylistItem {
+            recording: item_row.recording,
+            // The medium is unset, if it was deleted in the meantime.
+            track: match (item_row.medium, item_row.track_index) {
+                (Some(medium), Some(index)) => Some(TrackReference {
+                    medium,
+                    index: index as usize,
+                }),
+                _ => None,
+            },
+        })
+        .collect();
+
+    Ok(Playlist {
+        id: row.id,
+        name: row.name,
+        items,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    })
+}
+
+/// Check that a recording exists and is visible to the user and that the track, if any, is part of
+/// that recording. This fails with a bad request error otherwise.
+pub fn check_recording_reference(
+    conn: &DbConn,
+    recording: &str,
+    track: Option<&TrackReference>,
+    user: &User,
+) -> Result<()> {
+    if !is_entity_visible(conn, EntityType::Recording, recording, Some(user))? {
+        return Err(Error::new(ServerError::BadRequest));
+    }
+
+    if let Some(track) = track {
+        if !is_entity_visible(conn, EntityType::Medium, &track.medium, Some(user))? {
+            return Err(Error::new(ServerError::BadRequest));
+        }
+
+        let track_recordings: Vec<String> = tracks::table
+            .inner_join(track_sets::table)
+            .filter(track_sets::medium.eq(&track.medium))
+            .order_by((track_sets::index, tracks::index))
+            .select(track_sets::recording)
+            .load(conn)?;
+
+        if track_recordings.get(track.index).map(String::as_str) != Some(recording) {
+            return Err(Error::new(ServerError::BadRequest));
+        }
+    }
+
+    Ok(())
+}
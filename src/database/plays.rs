@@ -0,0 +1,265 @@
+use super::schema::{plays, recordings, users, works};
+use super::{check_recording_reference, get_person, get_work, is_entity_visible};
+use super::{with_transaction, DbConn, EntityType, Person, TrackReference, User, Work};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The maximum number of recent plays that are returned.
+const MAX_PLAYS: usize = 100;
+
+/// The number of composers and works in the listening statistics.
+const MAX_TOP_ENTRIES: usize = 10;
+
+/// A recording or track that a user listened to.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Play {
+    pub recording: String,
+
+    /// If set, only this track of the recording was played.
+    pub track: Option<TrackReference>,
+
+    /// When the user started listening according to the client.
+    pub played_at: NaiveDateTime,
+}
+
+/// The listening history of a user with statistics on what they listened to most.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayHistory {
+    /// The total number of plays considered.
+    pub total: i64,
+
+    /// The latest plays, newest first.
+    pub recent: Vec<Play>,
+
+    /// The most played composers, most plays first.
+    pub composers: Vec<ComposerPlays>,
+
+    /// The most played works, most plays first.
+    pub works: Vec<WorkPlays>,
+}
+
+/// The number of plays of recordings of works by a composer.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposerPlays {
+    pub composer: Person,
+    pub plays: i64,
+}
+
+/// The number of plays of recordings of a work.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkPlays {
+    pub work: Work,
+    pub plays: i64,
+}
+
+/// Table data for a new [`Play`]. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "plays"]
+struct NewPlayRow {
+    pub username: String,
+    pub recording: String,
+    pub medium: Option<String>,
+    pub track_index: Option<i32>,
+    pub played_at: NaiveDateTime,
+}
+
+/// Table data for a [`Play`] without the ID and the user.
+#[derive(Queryable, Debug, Clone)]
+struct PlayRow {
+    pub recording: String,
+    pub medium: Option<String>,
+    pub track_index: Option<i32>,
+    pub played_at: NaiveDateTime,
+}
+
+impl From<PlayRow> for Play {
+    fn from(row: PlayRow) -> Play {
+        Play {
+            recording: row.recording,
+            // The medium is unset, if it was deleted in the meantime.
+            track: match (row.medium, row.track_index) {
+                (Some(medium), Some(index)) => Some(TrackReference {
+                    medium,
+                    index: index as usize,
+                }),
+                _ => None,
+            },
+            played_at: row.played_at,
+        }
+    }
+}
+
+/// Enable or disable storing plays for a user. Disabling it also deletes the existing history.
+pub fn set_record_plays(conn: &DbConn, enabled: bool, user: &User) -> Result<()> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        diesel::update(users::table)
+            .filter(users::username.eq(&user.username))
+            .set(users::record_plays.eq(enabled))
+            .execute(conn)?;
+
+        if !enabled {
+            diesel::delete(plays::table)
+                .filter(plays::username.eq(&user.username))
+                .execute(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Store plays of a user. Plays that were already submitted, e.g. by another device, are ignored.
+/// The user has to have opted in to storing plays.
+pub fn insert_plays(conn: &DbConn, plays: &[Play], user: &User) -> Result<()> {
+    if !user.record_plays {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+        let mut rows = Vec::new();
+
+        for play in plays {
+            check_recording_reference(conn, &play.recording, play.track.as_ref(), user)?;
+
+            rows.push(NewPlayRow {
+                username: user.username.clone(),
+                recording: play.recording.clone(),
+                medium: play.track.as_ref().map(|track| track.medium.clone()),
+                track_index: play.track.as_ref().map(|track| track.index as i32),
+                played_at: play.played_at,
+            });
+        }
+
+        if !rows.is_empty() {
+            diesel::insert_into(plays::table)
+                .values(rows)
+                .on_conflict((plays::username, plays::recording, plays::played_at))
+                .do_nothing()
+                .execute(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Get the listening history of a user together with the most played composers and works. If
+/// `since` is provided, only plays after that time are considered.
+pub fn get_play_history(
+    conn: &DbConn,
+    since: Option<NaiveDateTime>,
+    user: &User,
+) -> Result<PlayHistory> {
+    let mut query = plays::table
+        .filter(plays::username.eq(&user.username))
+        .into_boxed();
+
+    if let Some(since) = since {
+        query = query.filter(plays::played_at.gt(since));
+    }
+
+    let recent = query
+        .order_by(plays::played_at.desc())
+        .limit(MAX_PLAYS as i64)
+        .select((
+            plays::recording,
+            plays::medium,
+            plays::track_index,
+            plays::played_at,
+        ))
+        .load::<PlayRow>(conn)?
+        .into_iter()
+        .map(Play::from)
+        .collect();
+
+    let mut query = plays::table
+        .inner_join(recordings::table.inner_join(works::table))
+        .filter(plays::username.eq(&user.username))
+        .into_boxed();
+
+    if let Some(since) = since {
+        query = query.filter(plays::played_at.gt(since));
+    }
+
+    let played_works: Vec<(String, String)> =
+        query.select((works::id, works::composer)).load(conn)?;
+
+    let mut work_counts: HashMap<&str, i64> = HashMap::new();
+    let mut composer_counts: HashMap<&str, i64> = HashMap::new();
+
+    for (work, composer) in &played_works {
+        *work_counts.entry(work).or_insert(0) += 1;
+        *composer_counts.entry(composer).or_insert(0) += 1;
+    }
+
+    // Entities that were made private after they were played are left out of the statistics.
+    retain_visible(conn, EntityType::Person, &mut composer_counts, user)?;
+    retain_visible(conn, EntityType::Work, &mut work_counts, user)?;
+
+    let mut composers = Vec::new();
+    for (id, plays) in top_entries(composer_counts) {
+        let composer = get_person(conn, id)?.ok_or_else(|| anyhow!("Missing person: {}", id))?;
+        composers.push(ComposerPlays { composer, plays });
+    }
+
+    let mut works = Vec::new();
+    for (id, plays) in top_entries(work_counts) {
+        let work = get_work(conn, id)?.ok_or_else(|| anyhow!("Missing work: {}", id))?;
+        works.push(WorkPlays { work, plays });
+    }
+
+    Ok(PlayHistory {
+        total: played_works.len() as i64,
+        recent,
+        composers,
+        works,
+    })
+}
+
+/// Delete the whole listening history of a user.
+pub fn delete_plays(conn: &DbConn, user: &User) -> Result<()> {
+    diesel::delete(plays::table)
+        .filter(plays::username.eq(&user.username))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Remove the counts of entities that the user isn't allowed to see.
+fn retain_visible(
+    conn: &DbConn,
+    entity_type: EntityType,
+    counts: &mut HashMap<&str, i64>,
+    user: &User,
+) -> Result<()> {
+    let mut hidden = Vec::new();
+
+    for id in counts.keys() {
+        if !is_entity_visible(conn, entity_type, id, Some(user))? {
+            hidden.push(*id);
+        }
+    }
+
+    for id in hidden {
+        counts.remove(id);
+    }
+
+    Ok(())
+}
+
+/// Get the entries with the highest counts, ordered by count and then by ID.
+fn top_entries(counts: HashMap<&str, i64>) -> Vec<(&str, i64)> {
+    let mut entries: Vec<(&str, i64)> = counts.into_iter().collect();
+    entries.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    entries.truncate(MAX_TOP_ENTRIES);
+    entries
+}
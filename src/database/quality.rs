@@ -0,0 +1,188 @@
+use super::schema::quality_flags;
+use super::User;
+use super::{insert_event, is_entity_visible, with_transaction, DbConn, EntityType, EventKind};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How complete and trustworthy the data of an entity is.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum QualityLevel {
+    /// Only the most basic information is there.
+    Stub,
+
+    /// The data might be wrong and should be checked by somebody else.
+    NeedsReview,
+
+    /// The data was checked against a reliable source.
+    Verified,
+}
+
+impl QualityLevel {
+    /// All quality levels.
+    pub const ALL: [QualityLevel; 3] = [
+        QualityLevel::Stub,
+        QualityLevel::NeedsReview,
+        QualityLevel::Verified,
+    ];
+
+    /// Get the string representation of the quality level that is also used in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QualityLevel::Stub => "stub",
+            QualityLevel::NeedsReview => "needsReview",
+            QualityLevel::Verified => "verified",
+        }
+    }
+
+    /// Get a quality level from its string representation.
+    pub fn parse(level: &str) -> Option<QualityLevel> {
+        QualityLevel::ALL
+            .iter()
+            .find(|l| l.as_str() == level)
+            .cloned()
+    }
+}
+
+/// The quality level of an entity as set by an editor.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityFlag {
+    pub level: QualityLevel,
+    pub updated_by: String,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Table data for a [`QualityFlag`].
+#[derive(Insertable, Queryable, AsChangeset, Debug, Clone)]
+#[table_name = "quality_flags"]
+struct QualityFlagRow {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub level: String,
+    pub updated_by: String,
+    pub updated_at: NaiveDateTime,
+}
+
+impl QualityFlagRow {
+    fn into_flag(self) -> Result<QualityFlag> {
+        Ok(QualityFlag {
+            level: QualityLevel::parse(&self.level)
+                .ok_or_else(|| anyhow!("Unknown quality level: {}", self.level))?,
+            updated_by: self.updated_by,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// Set the quality level of an entity. Only editors may do that. This is recorded as a change of
+/// the entity, so that watchers learn about it.
+pub fn set_quality(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    level: QualityLevel,
+    user: &User,
+) -> Result<()> {
+    if !user.may_set_quality() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        if !is_entity_visible(conn, entity_type, entity_id, Some(user))? {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        let row = QualityFlagRow {
+            entity_type: entity_type.as_str().to_string(),
+            entity_id: entity_id.to_string(),
+            level: level.as_str().to_string(),
+            updated_by: user.username.clone(),
+            updated_at: Utc::now().naive_utc(),
+        };
+
+        diesel::insert_into(quality_flags::table)
+            .values(&row)
+            .on_conflict((quality_flags::entity_type, quality_flags::entity_id))
+            .do_update()
+            .set(&row)
+            .execute(conn)?;
+
+        insert_event(conn, entity_type, entity_id, EventKind::Update, user)?;
+
+        Ok(())
+    })
+}
+
+/// Remove the quality level of an entity, so that it counts as not judged again. Only editors
+/// may do that.
+pub fn delete_quality(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    user: &User,
+) -> Result<()> {
+    if !user.may_set_quality() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let count = diesel::delete(quality_flags::table)
+            .filter(quality_flags::entity_type.eq(entity_type.as_str()))
+            .filter(quality_flags::entity_id.eq(entity_id))
+            .execute(conn)?;
+
+        if count == 0 {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        insert_event(conn, entity_type, entity_id, EventKind::Update, user)?;
+
+        Ok(())
+    })
+}
+
+/// Get the quality level of an entity, if it was set. The entity has to be visible to the user,
+/// if any.
+pub fn get_quality(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    viewer: Option<&User>,
+) -> Result<Option<QualityFlag>> {
+    if !is_entity_visible(conn, entity_type, entity_id, viewer)? {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    quality_flags::table
+        .filter(quality_flags::entity_type.eq(entity_type.as_str()))
+        .filter(quality_flags::entity_id.eq(entity_id))
+        .first::<QualityFlagRow>(conn)
+        .optional()?
+        .map(QualityFlagRow::into_flag)
+        .transpose()
+}
+
+/// Get the IDs of all entities of one type that have one of the provided quality levels.
+pub fn get_ids_with_quality(
+    conn: &DbConn,
+    entity_type: EntityType,
+    levels: &[QualityLevel],
+) -> Result<Vec<String>> {
+    let levels: Vec<&str> = levels.iter().map(QualityLevel::as_str).collect();
+
+    let ids = quality_flags::table
+        .filter(quality_flags::entity_type.eq(entity_type.as_str()))
+        .filter(quality_flags::level.eq_any(levels))
+        .select(quality_flags::entity_id)
+        .load(conn)?;
+
+    Ok(ids)
+}
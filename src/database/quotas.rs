@@ -0,0 +1,40 @@
+use super::schema::events;
+use super::{DbConn, EventKind, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// The number of entities a user that is not an editor may create within 24 hours. This is
+    /// read from the environment variable "WOLFGANG_DAILY_QUOTA". If it is not set, there is no
+    /// limit.
+    static ref DAILY_QUOTA: Option<i64> = std::env::var("WOLFGANG_DAILY_QUOTA")
+        .ok()
+        .and_then(|quota| quota.parse().ok());
+}
+
+/// Check whether the user may create another entity. Editors and administrators are not
+/// limited. The created entities are counted using the events of the last 24 hours.
+pub fn check_quota(conn: &DbConn, user: &User) -> Result<()> {
+    let quota = match *DAILY_QUOTA {
+        Some(quota) if !user.is_editor && !user.is_admin => quota,
+        _ => return Ok(()),
+    };
+
+    let since = Utc::now().naive_utc() - Duration::hours(24);
+
+    let created: i64 = events::table
+        .filter(events::created_by.eq(&user.username))
+        .filter(events::kind.eq(EventKind::Create.as_str()))
+        .filter(events::created_at.gt(since))
+        .count()
+        .get_result(conn)?;
+
+    if created >= quota {
+        Err(Error::new(ServerError::TooManyRequests))
+    } else {
+        Ok(())
+    }
+}
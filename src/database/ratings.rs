@@ -0,0 +1,194 @@
+use super::schema::ratings;
+use super::{invalidate_read_models, is_entity_visible, with_transaction};
+use super::{DbConn, EntityType, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The combined ratings of a recording.
+#[derive(Serialize, Deserialize, JsonSchema, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingSummary {
+    /// The average number of stars. This is empty, if there are no ratings yet.
+    pub average: Option<f64>,
+
+    /// The number of ratings.
+    pub count: i64,
+}
+
+/// The rating of a recording by one user.
+#[derive(Queryable, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Rating {
+    pub username: String,
+
+    /// The number of stars from 1 to 5.
+    pub stars: i32,
+
+    pub review: Option<String>,
+
+    /// Whether the review was hidden by an editor.
+    pub hidden: bool,
+
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Table data for a new [`Rating`].
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "ratings"]
+struct NewRatingRow {
+    pub recording: String,
+    pub username: String,
+    pub stars: i32,
+    pub review: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Rate a recording or change an existing rating of the user. If a review was hidden before, it
+/// stays hidden.
+pub fn set_rating(
+    conn: &DbConn,
+    recording: &str,
+    stars: i32,
+    review: Option<&str>,
+    user: &User,
+) -> Result<()> {
+    if !user.may_create() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        if !is_entity_visible(conn, EntityType::Recording, recording, Some(user))? {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        let now = Utc::now().naive_utc();
+
+        let row = NewRatingRow {
+            recording: recording.to_string(),
+            username: user.username.clone(),
+            stars,
+            review: review.map(str::to_string),
+            created_at: now,
+            updated_at: now,
+        };
+
+        diesel::insert_into(ratings::table)
+            .values(&row)
+            .on_conflict((ratings::recording, ratings::username))
+            .do_update()
+            .set((
+                ratings::stars.eq(stars),
+                ratings::review.eq(&row.review),
+                ratings::updated_at.eq(now),
+            ))
+            .execute(conn)?;
+
+        invalidate_read_models(conn, EntityType::Recording, recording)?;
+
+        Ok(())
+    })
+}
+
+/// Remove the rating of a recording by the user.
+pub fn delete_rating(conn: &DbConn, recording: &str, user: &User) -> Result<()> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let count = diesel::delete(ratings::table)
+            .filter(ratings::recording.eq(recording))
+            .filter(ratings::username.eq(&user.username))
+            .execute(conn)?;
+
+        if count == 0 {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        invalidate_read_models(conn, EntityType::Recording, recording)?;
+
+        Ok(())
+    })
+}
+
+/// Get the rating of a recording by a specific user.
+pub fn get_rating(conn: &DbConn, recording: &str, username: &str) -> Result<Option<Rating>> {
+    Ok(ratings::table
+        .filter(ratings::recording.eq(recording))
+        .filter(ratings::username.eq(username))
+        .select((
+            ratings::username,
+            ratings::stars,
+            ratings::review,
+            ratings::hidden,
+            ratings::created_at,
+            ratings::updated_at,
+        ))
+        .first::<Rating>(conn)
+        .optional()?)
+}
+
+/// Get all ratings of a recording that include a review, newest first. Hidden reviews are left
+/// out.
+pub fn get_reviews(conn: &DbConn, recording: &str) -> Result<Vec<Rating>> {
+    Ok(ratings::table
+        .filter(ratings::recording.eq(recording))
+        .filter(ratings::review.is_not_null())
+        .filter(ratings::hidden.eq(false))
+        .order_by(ratings::updated_at.desc())
+        .select((
+            ratings::username,
+            ratings::stars,
+            ratings::review,
+            ratings::hidden,
+            ratings::created_at,
+            ratings::updated_at,
+        ))
+        .load::<Rating>(conn)?)
+}
+
+/// Combine all ratings of a recording.
+pub fn get_rating_summary(conn: &DbConn, recording: &str) -> Result<RatingSummary> {
+    let query = ratings::table.filter(ratings::recording.eq(recording));
+
+    let count: i64 = query.count().get_result(conn)?;
+    let total: Option<i64> = query.select(diesel::dsl::sum(ratings::stars)).first(conn)?;
+
+    Ok(RatingSummary {
+        average: total
+            .filter(|_| count > 0)
+            .map(|total| total as f64 / count as f64),
+        count,
+    })
+}
+
+/// Hide or show the review of another user. The user has to be allowed to moderate reviews.
+pub fn set_review_hidden(
+    conn: &DbConn,
+    recording: &str,
+    username: &str,
+    hidden: bool,
+    user: &User,
+) -> Result<()> {
+    if !user.may_moderate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let count = diesel::update(ratings::table)
+        .filter(ratings::recording.eq(recording))
+        .filter(ratings::username.eq(username))
+        .set(ratings::hidden.eq(hidden))
+        .execute(conn)?;
+
+    if count == 0 {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use super::schema::{instrumentations, mediums, performances, read_models, recordings, track_sets};
+use super::schema::{recording_works, work_authors, works};
+use super::{get_medium, get_recording, DbConn, EntityType};
+use anyhow::Result;
+use diesel::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Table data for an invalidated read model.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "read_models"]
+struct NewReadModelRow {
+    pub entity_type: String,
+    pub entity_id: String,
+}
+
+/// Get the fully resolved representation of an entity from its read model. If there is none,
+/// `assemble` is used to create it from the actual tables and the result is stored for the next
+/// time, unless the connection is read-only. This returns [`None`], if the entity doesn't exist.
+pub fn get_read_model<T, F>(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    assemble: F,
+) -> Result<Option<T>>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Result<Option<T>>,
+{
+    let row = read_models::table
+        .filter(read_models::entity_type.eq(entity_type.as_str()))
+        .filter(read_models::entity_id.eq(id))
+        .select((read_models::version, read_models::data))
+        .first::<(i64, Option<String>)>(conn)
+        .optional()?;
+
+    if let Some((_, Some(data))) = &row {
+        return Ok(Some(serde_json::from_str(data)?));
+    }
+
+    let value = assemble()?;
+
+    if conn.is_read_only() {
+        return Ok(value);
+    }
+
+    if let Some(value) = &value {
+        let data = serde_json::to_string(value)?;
+
+        // If something changed while assembling the value, the version will be different or the
+        // row will already exist. In both cases, the possibly outdated data is not stored.
+        match row {
+            Some((version, _)) => {
+                diesel::update(read_models::table)
+                    .filter(read_models::entity_type.eq(entity_type.as_str()))
+                    .filter(read_models::entity_id.eq(id))
+                    .filter(read_models::version.eq(version))
+                    .set(read_models::data.eq(data))
+                    .execute(conn)?;
+            }
+            None => {
+                diesel::insert_into(read_models::table)
+                    .values((
+                        read_models::entity_type.eq(entity_type.as_str()),
+                        read_models::entity_id.eq(id),
+                        read_models::data.eq(data),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+/// Invalidate the read models of all recordings and mediums that include an entity and assemble
+/// them again. Reads may be served by read-only connections that can't store read models, so they
+/// have to be available afterwards. This should be called within the same transaction as the
+/// change to the entity.
+pub fn invalidate_read_models(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<()> {
+    let recordings = get_dependent_recordings(conn, entity_type, id)?;
+
+    let mut mediums: Vec<String> = track_sets::table
+        .filter(track_sets::recording.eq_any(&recordings))
+        .select(track_sets::medium)
+        .load(conn)?;
+
+    if entity_type == EntityType::Medium {
+        mediums.push(id.to_string());
+    }
+
+    if entity_type == EntityType::Label {
+        mediums.extend(
+            mediums::table
+                .filter(mediums::label.eq(id))
+                .select(mediums::id)
+                .load::<String>(conn)?,
+        );
+    }
+
+    mediums.sort();
+    mediums.dedup();
+
+    let rows: Vec<NewReadModelRow> = recordings
+        .iter()
+        .map(|id| (EntityType::Recording, id))
+        .chain(mediums.iter().map(|id| (EntityType::Medium, id)))
+        .map(|(entity_type, entity_id)| NewReadModelRow {
+            entity_type: entity_type.as_str().to_string(),
+            entity_id: entity_id.clone(),
+        })
+        .collect();
+
+    if !rows.is_empty() {
+        diesel::insert_into(read_models::table)
+            .values(&rows)
+            .on_conflict((read_models::entity_type, read_models::entity_id))
+            .do_update()
+            .set((
+                read_models::version.eq(read_models::version + 1),
+                read_models::data.eq(None::<String>),
+            ))
+            .execute(conn)?;
+    }
+
+    for id in &recordings {
+        get_recording(conn, id)?;
+    }
+
+    for id in &mediums {
+        get_medium(conn, id)?;
+    }
+
+    Ok(())
+}
+
+/// Drop all read models and assemble them again for every recording and medium. `progress` is
+/// called with the number of finished and of all entities after each one. Returns the number of
+/// assembled read models.
+pub fn rebuild_read_models(conn: &DbConn, progress: &mut dyn FnMut(usize, usize)) -> Result<usize> {
+    diesel::delete(read_models::table).execute(conn)?;
+
+    let recordings: Vec<String> = recordings::table.select(recordings::id).load(conn)?;
+    let mediums: Vec<String> = mediums::table.select(mediums::id).load(conn)?;
+    let total = recordings.len() + mediums.len();
+
+    for (index, id) in recordings.iter().enumerate() {
+        get_recording(conn, id)?;
+        progress(index + 1, total);
+    }
+
+    for (index, id) in mediums.iter().enumerate() {
+        get_medium(conn, id)?;
+        progress(recordings.len() + index + 1, total);
+    }
+
+    Ok(total)
+}
+
+/// Get the IDs of all recordings whose fully resolved representation includes an entity.
+pub fn get_dependent_recordings(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+) -> Result<Vec<String>> {
+    let mut recordings: Vec<String> = match entity_type {
+        EntityType::Person => {
+            let mut ids: Vec<String> = recordings::table
+                .inner_join(works::table)
+                .filter(works::composer.eq(id))
+                .select(recordings::id)
+                .load(conn)?;
+
+            let authored = work_authors::table
+                .filter(work_authors::person.eq(id))
+                .select(work_authors::work);
+
+            ids.extend(
+                recordings::table
+                    .filter(recordings::work.eq_any(authored))
+                    .select(recordings::id)
+                    .load::<String>(conn)?,
+            );
+
+            let composed = works::table
+                .filter(works::composer.eq(id))
+                .select(works::id);
+
+            ids.extend(
+                recording_works::table
+                    .filter(recording_works::work.eq_any(composed))
+                    .select(recording_works::recording)
+                    .load::<String>(conn)?,
+            );
+
+            let authored = work_authors::table
+                .filter(work_authors::person.eq(id))
+                .select(work_authors::work);
+
+            ids.extend(
+                recording_works::table
+                    .filter(recording_works::work.eq_any(authored))
+                    .select(recording_works::recording)
+                    .load::<String>(conn)?,
+            );
+
+            ids.extend(
+                performances::table
+                    .filter(performances::person.eq(id))
+                    .select(performances::recording)
+                    .load::<String>(conn)?,
+            );
+
+            ids
+        }
+        EntityType::Ensemble => performances::table
+            .filter(performances::ensemble.eq(id))
+            .select(performances::recording)
+            .load(conn)?,
+        EntityType::Instrument => {
+            let mut ids: Vec<String> = recordings::table
+                .inner_join(instrumentations::table.on(instrumentations::work.eq(recordings::work)))
+                .filter(instrumentations::instrument.eq(id))
+                .select(recordings::id)
+                .load(conn)?;
+
+            ids.extend(
+                recording_works::table
+                    .inner_join(
+                        instrumentations::table
+                            .on(instrumentations::work.eq(recording_works::work)),
+                    )
+                    .filter(instrumentations::instrument.eq(id))
+                    .select(recording_works::recording)
+                    .load::<String>(conn)?,
+            );
+
+            ids.extend(
+                performances::table
+                    .filter(performances::role.eq(id))
+                    .select(performances::recording)
+                    .load::<String>(conn)?,
+            );
+
+            ids
+        }
+        EntityType::Work => {
+            let mut ids: Vec<String> = recordings::table
+                .filter(recordings::work.eq(id))
+                .select(recordings::id)
+                .load(conn)?;
+
+            ids.extend(
+                recording_works::table
+                    .filter(recording_works::work.eq(id))
+                    .select(recording_works::recording)
+                    .load::<String>(conn)?,
+            );
+
+            ids
+        }
+        EntityType::Recording => vec![id.to_string()],
+        EntityType::Medium | EntityType::Label => Vec::new(),
+    };
+
+    recordings.sort();
+    recordings.dedup();
+
+    Ok(recordings)
+}
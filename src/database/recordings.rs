@@ -1,24 +1,59 @@
-use super::schema::{ensembles, performances, persons, recordings};
+use super::schema::{ensembles, performances, persons, recording_works, recordings};
+use super::{check_may_become_private, check_reference, may_delete_entity, move_to_trash};
+use super::{check_quota, check_unreferenced, get_rating_summary, get_read_model, insert_event};
 use super::{get_ensemble, get_instrument, get_person, get_work};
-use super::{update_ensemble, update_instrument, update_person, update_work};
-use super::{DbConn, Ensemble, Instrument, Person, User, Work};
+use super::{get_external_ids, normalize_text, update_external_ids, ExternalId, RatingSummary};
+use super::{update_ensemble_in, update_instrument_in, update_person_in, update_work_in};
+use super::{viewer_name, with_transaction, DbConn, DbTransaction, EntityType, EventKind};
+use super::{Ensemble, Instrument, Person, User, Work};
 use crate::error::ServerError;
 use anyhow::{anyhow, Error, Result};
 use diesel::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// A specific recording of a work.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Recording {
+    /// Clients may leave this out for new recordings to get a generated ID.
+    #[serde(default)]
     pub id: String,
     pub work: Work,
+
+    /// Further works that were performed within the same recording, like encores. Tracks refer to
+    /// them using their work index, where 0 is the main work and 1 the first additional work.
+    #[serde(default)]
+    pub additional_works: Vec<Work>,
+
+    /// The indices of the parts of the main work that this recording covers, if it is only an
+    /// excerpt of the work. [`None`] means that the complete work was recorded.
+    #[serde(default)]
+    pub parts: Option<Vec<usize>>,
+
+    /// Identifiers of the recording within streaming services and digital stores.
+    #[serde(default)]
+    pub external_ids: Vec<ExternalId>,
+
     pub comment: String,
     pub performances: Vec<Performance>,
+
+    /// Whether the recording can only be edited by editors. This is ignored on updates.
+    #[serde(default)]
+    pub locked: bool,
+
+    /// The ratings of users combined. This is ignored on updates.
+    #[serde(default)]
+    pub rating: RatingSummary,
+
+    /// Whether the recording is only visible to the user that created it. Private recordings may
+    /// only refer to public entities or to private ones of the same user.
+    #[serde(default)]
+    pub private: bool,
 }
 
 /// How a person or ensemble was involved in a recording.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Performance {
     pub person: Option<Person>,
@@ -26,18 +61,79 @@ pub struct Performance {
     pub role: Option<Instrument>,
 }
 
+impl Recording {
+    /// Get a comma separated list of all performers including their roles.
+    pub fn performers(&self) -> String {
+        self.performances
+            .iter()
+            .map(|performance| performance.label())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Get the work with the provided index. 0 is the main work and all following indices refer
+    /// to the additional works.
+    pub fn work_at(&self, index: usize) -> Option<&Work> {
+        match index {
+            0 => Some(&self.work),
+            index => self.additional_works.get(index - 1),
+        }
+    }
+
+    /// Check whether the recording only covers selected parts of its main work.
+    pub fn is_excerpt(&self) -> bool {
+        self.parts.is_some()
+    }
+
+    /// Get all works of the recording starting with the main work.
+    pub fn works(&self) -> impl Iterator<Item = &Work> {
+        std::iter::once(&self.work).chain(self.additional_works.iter())
+    }
+}
+
+impl Performance {
+    /// Get the name of the performer followed by the role, if there is one.
+    pub fn label(&self) -> String {
+        let name = match (&self.person, &self.ensemble) {
+            (Some(person), _) => person.name_fl(),
+            (None, Some(ensemble)) => ensemble.name.clone(),
+            (None, None) => String::new(),
+        };
+
+        match &self.role {
+            Some(role) => format!("{} ({})", name, role.name),
+            None => name,
+        }
+    }
+}
+
 /// Row data for a recording.
-#[derive(Insertable, Queryable, Debug, Clone)]
+#[derive(Insertable, Queryable, Identifiable, AsChangeset, Debug, Clone)]
 #[table_name = "recordings"]
+#[changeset_options(treat_none_as_null = "true")]
 struct RecordingRow {
     pub id: String,
     pub work: String,
     pub comment: String,
     pub created_by: String,
+    pub locked: bool,
+    pub private: bool,
+    pub parts: Option<String>,
+}
+
+/// Row data for a new performance. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "performances"]
+struct NewPerformanceRow {
+    pub recording: String,
+    pub person: Option<String>,
+    pub ensemble: Option<String>,
+    pub role: Option<String>,
 }
 
 /// Row data for a performance.
-#[derive(Insertable, Queryable, Debug, Clone)]
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(RecordingRow, foreign_key = "recording")]
 #[table_name = "performances"]
 struct PerformanceRow {
     pub id: i64,
@@ -47,144 +143,330 @@ struct PerformanceRow {
     pub role: Option<String>,
 }
 
+/// Row data for a new additional work of a recording. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "recording_works"]
+struct NewRecordingWorkRow {
+    pub recording: String,
+    pub work: String,
+}
+
+/// Row data for an additional work of a recording.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(RecordingRow, foreign_key = "recording")]
+#[table_name = "recording_works"]
+struct RecordingWorkRow {
+    pub id: i64,
+    pub recording: String,
+    pub work: String,
+}
+
 /// Update an existing recording or insert a new one. This will only work, if the provided user is
 /// allowed to do that.
 pub fn update_recording(conn: &DbConn, recording: &Recording, user: &User) -> Result<()> {
-    conn.transaction::<(), Error, _>(|| {
-        let old_row = get_recording_row(conn, &recording.id)?;
+    with_transaction(conn, |tx| update_recording_in(tx, recording, user))
+}
 
-        let allowed = match old_row {
-            Some(row) => user.may_edit(&row.created_by),
-            None => user.may_create(),
-        };
+/// Update an existing recording or insert a new one as part of a larger change. See
+/// [`update_recording`].
+pub fn update_recording_in(tx: &DbTransaction, recording: &Recording, user: &User) -> Result<()> {
+    let conn = tx.conn();
 
-        if allowed {
-            let id = &recording.id;
+    let old_row = get_recording_row(conn, &recording.id)?;
+    let kind = if old_row.is_some() {
+        EventKind::Update
+    } else {
+        EventKind::Create
+    };
 
-            // This will also delete the old performances.
-            diesel::delete(recordings::table)
-                .filter(recordings::id.eq(id))
-                .execute(conn)?;
+    if old_row.is_none() {
+        check_quota(conn, user)?;
+    }
+
+    let allowed = match &old_row {
+        Some(row) if row.private => user.may_edit_private(&row.created_by),
+        Some(row) => user.may_edit_item(&row.created_by, row.locked),
+        None => user.may_create(),
+    };
+
+    if allowed {
+        let id = &recording.id;
 
-            // Add associated items, if they don't already exist.
+        if recording.private && matches!(&old_row, Some(row) if !row.private) {
+            check_may_become_private(conn, EntityType::Recording, id, user)?;
+        }
+
+        // Add associated items, if they don't already exist.
 
-            if get_work(conn, &recording.work.id)?.is_none() {
-                update_work(conn, &recording.work, &user)?;
+        for work in recording.works() {
+            if get_work(conn, &work.id)?.is_none() {
+                update_work_in(tx, work, user)?;
             }
+        }
 
-            for performance in &recording.performances {
-                if let Some(person) = &performance.person {
-                    if get_person(conn, &person.id)?.is_none() {
-                        update_person(conn, person, &user)?;
-                    }
+        for performance in &recording.performances {
+            if let Some(person) = &performance.person {
+                if get_person(conn, &person.id)?.is_none() {
+                    update_person_in(tx, person, user)?;
                 }
+            }
 
-                if let Some(ensemble) = &performance.ensemble {
-                    if get_ensemble(conn, &ensemble.id)?.is_none() {
-                        update_ensemble(conn, ensemble, &user)?;
-                    }
+            if let Some(ensemble) = &performance.ensemble {
+                if get_ensemble(conn, &ensemble.id)?.is_none() {
+                    update_ensemble_in(tx, ensemble, user)?;
                 }
+            }
 
-                if let Some(role) = &performance.role {
-                    if get_instrument(conn, &role.id)?.is_none() {
-                        update_instrument(conn, role, &user)?;
-                    }
+            if let Some(role) = &performance.role {
+                if get_instrument(conn, &role.id)?.is_none() {
+                    update_instrument_in(tx, role, user)?;
                 }
             }
+        }
 
-            // Add the actual recording.
+        let private = recording.private;
+        for work in recording.works() {
+            check_reference(conn, EntityType::Work, &work.id, private, user)?;
+        }
 
-            let row = RecordingRow {
-                id: id.clone(),
-                work: recording.work.id.clone(),
-                comment: recording.comment.clone(),
-                created_by: user.username.clone(),
-            };
+        for performance in &recording.performances {
+            if let Some(person) = &performance.person {
+                check_reference(conn, EntityType::Person, &person.id, private, user)?;
+            }
 
-            diesel::insert_into(recordings::table)
-                .values(row)
-                .execute(conn)?;
+            if let Some(ensemble) = &performance.ensemble {
+                check_reference(conn, EntityType::Ensemble, &ensemble.id, private, user)?;
+            }
 
-            for performance in &recording.performances {
-                diesel::insert_into(performances::table)
-                    .values(PerformanceRow {
-                        id: rand::random(),
-                        recording: id.clone(),
-                        person: performance.person.as_ref().map(|person| person.id.clone()),
-                        ensemble: performance
-                            .ensemble
-                            .as_ref()
-                            .map(|ensemble| ensemble.id.clone()),
-                        role: performance.role.as_ref().map(|role| role.id.clone()),
-                    })
-                    .execute(conn)?;
+            if let Some(role) = &performance.role {
+                check_reference(conn, EntityType::Instrument, &role.id, private, user)?;
             }
+        }
 
-            Ok(())
-        } else {
-            Err(Error::new(ServerError::Forbidden))
+        // Add or update the actual recording.
+
+        let row = RecordingRow {
+            id: id.clone(),
+            work: recording.work.id.clone(),
+            comment: normalize_text(&recording.comment),
+            created_by: user.username.clone(),
+            locked: old_row.map(|row| row.locked).unwrap_or(false),
+            private: recording.private,
+            parts: recording.parts.as_ref().map(|parts| {
+                parts
+                    .iter()
+                    .map(|part_index| part_index.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            }),
+        };
+
+        diesel::insert_into(recordings::table)
+            .values(&row)
+            .on_conflict(recordings::id)
+            .do_update()
+            .set(&row)
+            .execute(conn)?;
+
+        // Update the performances. Existing rows are matched by their position, so that only
+        // changed rows are touched and unchanged ones keep their IDs.
+
+        let old_performances = PerformanceRow::belonging_to(&row)
+            .order_by(performances::id)
+            .load::<PerformanceRow>(conn)?;
+
+        for (index, performance) in recording.performances.iter().enumerate() {
+            let person = performance.person.as_ref().map(|person| person.id.clone());
+            let ensemble = performance
+                .ensemble
+                .as_ref()
+                .map(|ensemble| ensemble.id.clone());
+            let role = performance.role.as_ref().map(|role| role.id.clone());
+
+            match old_performances.get(index) {
+                Some(old)
+                    if old.person == person && old.ensemble == ensemble && old.role == role => {}
+                Some(old) => {
+                    diesel::update(old)
+                        .set((
+                            performances::person.eq(person),
+                            performances::ensemble.eq(ensemble),
+                            performances::role.eq(role),
+                        ))
+                        .execute(conn)?;
+                }
+                None => {
+                    diesel::insert_into(performances::table)
+                        .values(NewPerformanceRow {
+                            recording: id.clone(),
+                            person,
+                            ensemble,
+                            role,
+                        })
+                        .execute(conn)?;
+                }
+            }
         }
-    })?;
 
-    Ok(())
+        for old in old_performances.iter().skip(recording.performances.len()) {
+            diesel::delete(old).execute(conn)?;
+        }
+
+        // Update the additional works in the same way.
+
+        let old_works = RecordingWorkRow::belonging_to(&row)
+            .order_by(recording_works::id)
+            .load::<RecordingWorkRow>(conn)?;
+
+        for (index, work) in recording.additional_works.iter().enumerate() {
+            match old_works.get(index) {
+                Some(old) if old.work == work.id => {}
+                Some(old) => {
+                    diesel::update(old)
+                        .set(recording_works::work.eq(&work.id))
+                        .execute(conn)?;
+                }
+                None => {
+                    diesel::insert_into(recording_works::table)
+                        .values(NewRecordingWorkRow {
+                            recording: id.clone(),
+                            work: work.id.clone(),
+                        })
+                        .execute(conn)?;
+                }
+            }
+        }
+
+        for old in old_works.iter().skip(recording.additional_works.len()) {
+            diesel::delete(old).execute(conn)?;
+        }
+
+        update_external_ids(conn, EntityType::Recording, id, &recording.external_ids)?;
+
+        insert_event(conn, EntityType::Recording, id, kind, user)?;
+
+        Ok(())
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
 }
 
 /// Get an existing recording and all available information from related tables.
 pub fn get_recording(conn: &DbConn, id: &str) -> Result<Option<Recording>> {
-    let recording = match get_recording_row(conn, id)? {
-        Some(row) => Some(get_description_for_recording_row(conn, &row)?),
-        None => None,
-    };
+    get_read_model(conn, EntityType::Recording, id, || {
+        let recording = match get_recording_row(conn, id)? {
+            Some(row) => Some(get_description_for_recording_row(conn, &row)?),
+            None => None,
+        };
 
-    Ok(recording)
+        Ok(recording)
+    })
 }
 
-/// Get all available information on all recordings where a person is performing.
-pub fn get_recordings_for_person(conn: &DbConn, person_id: &str) -> Result<Vec<Recording>> {
+/// Get all available information on all recordings where a person is performing. Only public
+/// recordings and private recordings of the viewer are included.
+pub fn get_recordings_for_person(
+    conn: &DbConn,
+    person_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Recording>> {
     let mut recordings: Vec<Recording> = Vec::new();
 
     let rows = recordings::table
         .inner_join(performances::table.on(performances::recording.eq(recordings::id)))
         .inner_join(persons::table.on(persons::id.nullable().eq(performances::person)))
         .filter(persons::id.eq(person_id))
+        .filter(
+            recordings::private
+                .eq(false)
+                .or(recordings::created_by.eq(viewer_name(viewer))),
+        )
         .select(recordings::table::all_columns())
         .load::<RecordingRow>(conn)?;
 
     for row in rows {
-        recordings.push(get_description_for_recording_row(conn, &row)?);
+        recordings.push(get_recording_from_row(conn, row)?);
     }
 
     Ok(recordings)
 }
 
-/// Get all available information on all recordings where an ensemble is performing.
-pub fn get_recordings_for_ensemble(conn: &DbConn, ensemble_id: &str) -> Result<Vec<Recording>> {
+/// Get all available information on all recordings where an ensemble is performing. Only public
+/// recordings and private recordings of the viewer are included.
+pub fn get_recordings_for_ensemble(
+    conn: &DbConn,
+    ensemble_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Recording>> {
     let mut recordings: Vec<Recording> = Vec::new();
 
     let rows = recordings::table
         .inner_join(performances::table.on(performances::recording.eq(recordings::id)))
         .inner_join(ensembles::table.on(ensembles::id.nullable().eq(performances::ensemble)))
         .filter(ensembles::id.eq(ensemble_id))
+        .filter(
+            recordings::private
+                .eq(false)
+                .or(recordings::created_by.eq(viewer_name(viewer))),
+        )
         .select(recordings::table::all_columns())
         .load::<RecordingRow>(conn)?;
 
     for row in rows {
-        recordings.push(get_description_for_recording_row(conn, &row)?);
+        recordings.push(get_recording_from_row(conn, row)?);
     }
 
     Ok(recordings)
 }
 
-/// Get allavailable information on all recordings of a work.
-pub fn get_recordings_for_work(conn: &DbConn, work_id: &str) -> Result<Vec<Recording>> {
+/// Get all available information on all recordings of a work, including those that contain it as an
+/// additional work. Only public recordings and private recordings of the viewer are included.
+pub fn get_recordings_for_work(
+    conn: &DbConn,
+    work_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Recording>> {
     let mut recordings: Vec<Recording> = Vec::new();
 
+    let additional = recording_works::table
+        .filter(recording_works::work.eq(work_id))
+        .select(recording_works::recording);
+
     let rows = recordings::table
-        .filter(recordings::work.eq(work_id))
+        .filter(
+            recordings::work
+                .eq(work_id)
+                .or(recordings::id.eq_any(additional)),
+        )
+        .filter(
+            recordings::private
+                .eq(false)
+                .or(recordings::created_by.eq(viewer_name(viewer))),
+        )
         .load::<RecordingRow>(conn)?;
 
     for row in rows {
-        recordings.push(get_description_for_recording_row(conn, &row)?);
+        recordings.push(get_recording_from_row(conn, row)?);
+    }
+
+    Ok(recordings)
+}
+
+/// Get all available information on all recordings that are public or private recordings of the
+/// viewer.
+pub fn get_all_recordings(conn: &DbConn, viewer: Option<&User>) -> Result<Vec<Recording>> {
+    let mut recordings: Vec<Recording> = Vec::new();
+
+    let rows = recordings::table
+        .filter(
+            recordings::private
+                .eq(false)
+                .or(recordings::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<RecordingRow>(conn)?;
+
+    for row in rows {
+        recordings.push(get_recording_from_row(conn, row)?);
     }
 
     Ok(recordings)
@@ -194,14 +476,56 @@ pub fn get_recordings_for_work(conn: &DbConn, work_id: &str) -> Result<Vec<Recor
 /// recording from other tables that are not directly part of the recording data. Also, the
 /// provided user has to be allowed to delete the recording.
 pub fn delete_recording(conn: &DbConn, id: &str, user: &User) -> Result<()> {
-    if user.may_delete() {
-        diesel::delete(recordings::table.filter(recordings::id.eq(id))).execute(conn)?;
-        Ok(())
+    if may_delete_entity(conn, EntityType::Recording, id, user)? {
+        conn.transaction::<(), Error, _>(|| {
+            check_unreferenced(conn, EntityType::Recording, id)?;
+            move_to_trash(conn, EntityType::Recording, id, user)?;
+
+            let count =
+                diesel::delete(recordings::table.filter(recordings::id.eq(id))).execute(conn)?;
+
+            if count > 0 {
+                insert_event(conn, EntityType::Recording, id, EventKind::Delete, user)?;
+            }
+
+            Ok(())
+        })
     } else {
         Err(Error::new(ServerError::Forbidden))
     }
 }
 
+/// Lock or unlock an existing recording. This will only work if the provided user is allowed to
+/// do that.
+pub fn set_recording_locked(conn: &DbConn, id: &str, locked: bool, user: &User) -> Result<()> {
+    if user.may_lock() {
+        conn.transaction::<(), Error, _>(|| {
+            let count = diesel::update(recordings::table.filter(recordings::id.eq(id)))
+                .set(recordings::locked.eq(locked))
+                .execute(conn)?;
+
+            if count == 0 {
+                return Err(Error::new(ServerError::NotFound));
+            }
+
+            insert_event(conn, EntityType::Recording, id, EventKind::Update, user)?;
+
+            Ok(())
+        })
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Get all available information on a recording using its read model, if possible.
+fn get_recording_from_row(conn: &DbConn, row: RecordingRow) -> Result<Recording> {
+    let recording = get_read_model(conn, EntityType::Recording, &row.id, || {
+        Ok(Some(get_description_for_recording_row(conn, &row)?))
+    })?;
+
+    recording.ok_or_else(|| anyhow!("No recording with ID: {}", row.id))
+}
+
 /// Get an existing recording row.
 fn get_recording_row(conn: &DbConn, id: &str) -> Result<Option<RecordingRow>> {
     Ok(recordings::table
@@ -215,8 +539,8 @@ fn get_recording_row(conn: &DbConn, id: &str) -> Result<Option<RecordingRow>> {
 fn get_description_for_recording_row(conn: &DbConn, row: &RecordingRow) -> Result<Recording> {
     let mut performances: Vec<Performance> = Vec::new();
 
-    let performance_rows = performances::table
-        .filter(performances::recording.eq(&row.id))
+    let performance_rows = PerformanceRow::belonging_to(row)
+        .order_by(performances::id)
         .load::<PerformanceRow>(conn)?;
 
     for row in performance_rows {
@@ -244,11 +568,39 @@ fn get_description_for_recording_row(conn: &DbConn, row: &RecordingRow) -> Resul
 
     let work = get_work(conn, &row.work)?.ok_or(anyhow!("No work with ID: {}", &row.work))?;
 
+    let mut additional_works: Vec<Work> = Vec::new();
+
+    let work_rows = RecordingWorkRow::belonging_to(row)
+        .order_by(recording_works::id)
+        .load::<RecordingWorkRow>(conn)?;
+
+    for row in work_rows {
+        additional_works
+            .push(get_work(conn, &row.work)?.ok_or(anyhow!("No work with ID: {}", row.work))?);
+    }
+
+    let parts = match &row.parts {
+        Some(parts) => Some(
+            parts
+                .split(',')
+                .filter(|part_index| !part_index.is_empty())
+                .map(|part_index| Ok(str::parse(part_index)?))
+                .collect::<Result<Vec<usize>>>()?,
+        ),
+        None => None,
+    };
+
     let recording = Recording {
         id: row.id.clone(),
         work,
+        additional_works,
+        parts,
+        external_ids: get_external_ids(conn, EntityType::Recording, &row.id)?,
         comment: row.comment.clone(),
         performances,
+        locked: row.locked,
+        rating: get_rating_summary(conn, &row.id)?,
+        private: row.private,
     };
 
     Ok(recording)
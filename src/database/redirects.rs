@@ -0,0 +1,62 @@
+use super::schema::redirects;
+use super::{DbConn, EntityType, User};
+use anyhow::Result;
+use diesel::prelude::*;
+
+/// Remember that an entity was merged into another one, so that requests for the old ID can be
+/// redirected. Redirects to the old entity are updated to point to the new one as well. This
+/// should be called within the same transaction as the merge itself.
+pub fn insert_redirect(
+    conn: &DbConn,
+    entity_type: EntityType,
+    old_id: &str,
+    new_id: &str,
+    user: &User,
+) -> Result<()> {
+    diesel::update(
+        redirects::table
+            .filter(redirects::entity_type.eq(entity_type.as_str()))
+            .filter(redirects::new_id.eq(old_id)),
+    )
+    .set(redirects::new_id.eq(new_id))
+    .execute(conn)?;
+
+    diesel::insert_into(redirects::table)
+        .values((
+            redirects::entity_type.eq(entity_type.as_str()),
+            redirects::old_id.eq(old_id),
+            redirects::new_id.eq(new_id),
+            redirects::created_by.eq(&user.username),
+        ))
+        .on_conflict((redirects::entity_type, redirects::old_id))
+        .do_update()
+        .set(redirects::new_id.eq(new_id))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Get the ID of the entity that replaced a merged one, if there is any.
+pub fn get_redirect(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<Option<String>> {
+    let new_id = redirects::table
+        .filter(redirects::entity_type.eq(entity_type.as_str()))
+        .filter(redirects::old_id.eq(id))
+        .select(redirects::new_id)
+        .first::<String>(conn)
+        .optional()?;
+
+    Ok(new_id)
+}
+
+/// Remove the redirect of an entity, e.g. because it was restored from the trash after it was
+/// merged into another one.
+pub fn delete_redirect(conn: &DbConn, entity_type: EntityType, id: &str) -> Result<()> {
+    diesel::delete(
+        redirects::table
+            .filter(redirects::entity_type.eq(entity_type.as_str()))
+            .filter(redirects::old_id.eq(id)),
+    )
+    .execute(conn)?;
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use super::schema::replication_state;
+use super::DbConn;
+use anyhow::Result;
+use chrono::Utc;
+use diesel::prelude::*;
+
+/// Get the latest revision of the changefeed of an upstream server that was applied locally or
+/// zero, if nothing was replicated from it yet.
+pub fn get_replication_revision(conn: &DbConn, upstream: &str) -> Result<i64> {
+    let revision = replication_state::table
+        .filter(replication_state::upstream.eq(upstream))
+        .select(replication_state::revision)
+        .first::<i64>(conn)
+        .optional()?
+        .unwrap_or(0);
+
+    Ok(revision)
+}
+
+/// Remember the latest revision of the changefeed of an upstream server that was applied locally.
+pub fn set_replication_revision(conn: &DbConn, upstream: &str, revision: i64) -> Result<()> {
+    let updated_at = Utc::now().naive_utc();
+
+    diesel::insert_into(replication_state::table)
+        .values((
+            replication_state::upstream.eq(upstream),
+            replication_state::revision.eq(revision),
+            replication_state::updated_at.eq(updated_at),
+        ))
+        .on_conflict(replication_state::upstream)
+        .do_update()
+        .set((
+            replication_state::revision.eq(revision),
+            replication_state::updated_at.eq(updated_at),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
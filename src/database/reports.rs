@@ -0,0 +1,279 @@
+use super::schema::{report_comments, reports};
+use super::{entity_exists, generate_id, insert_notification, notify_mentions, with_transaction};
+use super::{DbConn, EntityType, NotificationKind, User};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The reason for reporting an entity.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportKind {
+    WrongData,
+    Spam,
+    Duplicate,
+    Other,
+}
+
+impl ReportKind {
+    /// Get the string representation of the report kind that is also used in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportKind::WrongData => "wrongData",
+            ReportKind::Spam => "spam",
+            ReportKind::Duplicate => "duplicate",
+            ReportKind::Other => "other",
+        }
+    }
+
+    /// Get a report kind from its string representation.
+    pub fn parse(kind: &str) -> Option<ReportKind> {
+        match kind {
+            "wrongData" => Some(ReportKind::WrongData),
+            "spam" => Some(ReportKind::Spam),
+            "duplicate" => Some(ReportKind::Duplicate),
+            "other" => Some(ReportKind::Other),
+            _ => None,
+        }
+    }
+}
+
+/// A report on a problem with an entity submitted by a user.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub id: String,
+    pub entity_type: EntityType,
+    pub entity_id: String,
+    pub kind: ReportKind,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<NaiveDateTime>,
+    pub resolution: Option<String>,
+    pub comments: Vec<ReportComment>,
+}
+
+/// A comment on a report by an administrator.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportComment {
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub text: String,
+}
+
+/// Table data for a [`Report`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "reports"]
+struct ReportRow {
+    pub id: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub kind: String,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<NaiveDateTime>,
+    pub resolution: Option<String>,
+}
+
+/// Table data for a [`ReportComment`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "report_comments"]
+struct ReportCommentRow {
+    pub id: String,
+    pub report: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub text: String,
+}
+
+/// Report a problem with an existing entity. Every user that isn't banned may do that. This
+/// returns the ID of the new report.
+pub fn insert_report(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    kind: ReportKind,
+    reason: &str,
+    user: &User,
+) -> Result<String> {
+    if !user.may_create() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    if !entity_exists(conn, entity_type, entity_id)? {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    let row = ReportRow {
+        id: generate_id(),
+        entity_type: entity_type.as_str().to_string(),
+        entity_id: entity_id.to_string(),
+        kind: kind.as_str().to_string(),
+        reason: reason.to_string(),
+        created_by: user.username.clone(),
+        created_at: Utc::now().naive_utc(),
+        resolved_by: None,
+        resolved_at: None,
+        resolution: None,
+    };
+
+    diesel::insert_into(reports::table)
+        .values(&row)
+        .execute(conn)?;
+
+    Ok(row.id)
+}
+
+/// Get all reports, optionally filtered by whether they are resolved. The user has to be an
+/// administrator.
+pub fn get_reports(conn: &DbConn, resolved: Option<bool>, user: &User) -> Result<Vec<Report>> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let mut query = reports::table.order_by(reports::created_at).into_boxed();
+
+    query = match resolved {
+        Some(true) => query.filter(reports::resolved_at.is_not_null()),
+        Some(false) => query.filter(reports::resolved_at.is_null()),
+        None => query,
+    };
+
+    let mut reports = Vec::new();
+
+    for row in query.load::<ReportRow>(conn)? {
+        reports.push(get_report_data(conn, row)?);
+    }
+
+    Ok(reports)
+}
+
+/// Get an existing report. The user has to be an administrator.
+pub fn get_report(conn: &DbConn, id: &str, user: &User) -> Result<Option<Report>> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let report = match get_report_row(conn, id)? {
+        Some(row) => Some(get_report_data(conn, row)?),
+        None => None,
+    };
+
+    Ok(report)
+}
+
+/// Add a comment to an existing report. The user has to be an administrator.
+pub fn insert_report_comment(conn: &DbConn, id: &str, text: &str, user: &User) -> Result<()> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    if get_report_row(conn, id)?.is_none() {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    let row = ReportCommentRow {
+        id: generate_id(),
+        report: id.to_string(),
+        created_by: user.username.clone(),
+        created_at: Utc::now().naive_utc(),
+        text: text.to_string(),
+    };
+
+    diesel::insert_into(report_comments::table)
+        .values(row)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Mark a report as resolved. The reporter and all users that are mentioned in the resolution
+/// are notified. The user has to be an administrator.
+pub fn resolve_report(conn: &DbConn, id: &str, resolution: &str, user: &User) -> Result<()> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let row = get_report_row(conn, id)?.ok_or(ServerError::NotFound)?;
+
+        diesel::update(reports::table)
+            .filter(reports::id.eq(id))
+            .set((
+                reports::resolved_by.eq(&user.username),
+                reports::resolved_at.eq(Utc::now().naive_utc()),
+                reports::resolution.eq(resolution),
+            ))
+            .execute(conn)?;
+
+        let entity_type = EntityType::parse(&row.entity_type)
+            .ok_or_else(|| anyhow!("Unknown entity type: {}", row.entity_type))?;
+
+        insert_notification(
+            conn,
+            &row.created_by,
+            NotificationKind::ReportResolved,
+            Some((entity_type, &row.entity_id)),
+            Some(id),
+            user,
+        )?;
+
+        notify_mentions(
+            conn,
+            resolution,
+            NotificationKind::Mentioned,
+            Some(id),
+            user,
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Get an existing report row.
+fn get_report_row(conn: &DbConn, id: &str) -> Result<Option<ReportRow>> {
+    Ok(reports::table
+        .filter(reports::id.eq(id))
+        .load::<ReportRow>(conn)?
+        .into_iter()
+        .next())
+}
+
+/// Retrieve the comments on a report and convert it to its API representation.
+fn get_report_data(conn: &DbConn, row: ReportRow) -> Result<Report> {
+    let comments = report_comments::table
+        .filter(report_comments::report.eq(&row.id))
+        .order_by(report_comments::created_at)
+        .load::<ReportCommentRow>(conn)?
+        .into_iter()
+        .map(|comment| ReportComment {
+            created_by: comment.created_by,
+            created_at: comment.created_at,
+            text: comment.text,
+        })
+        .collect();
+
+    Ok(Report {
+        entity_type: EntityType::parse(&row.entity_type)
+            .ok_or_else(|| anyhow!("Unknown entity type: {}", row.entity_type))?,
+        kind: ReportKind::parse(&row.kind).ok_or_else(|| anyhow!("Unknown kind: {}", row.kind))?,
+        id: row.id,
+        entity_id: row.entity_id,
+        reason: row.reason,
+        created_by: row.created_by,
+        created_at: row.created_at,
+        resolved_by: row.resolved_by,
+        resolved_at: row.resolved_at,
+        resolution: row.resolution,
+        comments,
+    })
+}
@@ -0,0 +1,155 @@
+use super::schema::{events, revisions};
+use super::{entity_exists, get_entity_data, get_event, get_last_visibility, set_created_by};
+use super::{set_person_locked, set_recording_locked, set_work_locked, update_ensemble_in};
+use super::{update_instrument_in, update_label_in, update_medium_in, update_person_in};
+use super::{update_recording_in, update_work_in, with_transaction, DbConn, EntityType, Person};
+use super::{Recording, User, Visibility, Work};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+use serde_json::Value;
+
+/// Store the current state of an entity as the revision of an event. Nothing happens, if the
+/// entity doesn't exist.
+pub fn insert_revision(
+    conn: &DbConn,
+    event: i64,
+    entity_type: EntityType,
+    entity_id: &str,
+) -> Result<()> {
+    if let Some(data) = get_entity_data(conn, entity_type, entity_id)? {
+        diesel::insert_into(revisions::table)
+            .values((
+                revisions::event.eq(event),
+                revisions::data.eq(serde_json::to_string(&data)?),
+            ))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Undo a change by restoring the version of the entity from before the change. Deleted entities
+/// are recreated on behalf of their previous owner. If the entity was changed again afterwards,
+/// this fails with "409 Conflict", unless `force` is set. Changes without a previous version, like
+/// the creation of an entity, can't be reverted. Only editors may do that and only if they may see
+/// both the current or deleted entity and the restored version. Returns the revision of the new
+/// change.
+pub fn revert_revision(conn: &DbConn, revision: i64, force: bool, user: &User) -> Result<i64> {
+    if !user.may_revert() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let event = get_event(conn, revision)?.ok_or_else(|| Error::new(ServerError::NotFound))?;
+        let entity_type = event.entity_type;
+        let id = &event.entity_id;
+
+        let exists = entity_exists(conn, entity_type, id)?;
+
+        // Deleted entities are checked against their snapshot within the trash.
+        let last_visibility = get_last_visibility(conn, entity_type, id)?;
+
+        if let Some(visibility) = &last_visibility {
+            if !visibility.is_visible_to(Some(user)) {
+                return Err(Error::new(ServerError::NotFound));
+            }
+        }
+
+        let later_revision = events::table
+            .filter(events::entity_type.eq(entity_type.as_str()))
+            .filter(events::entity_id.eq(id))
+            .filter(events::id.gt(revision))
+            .select(events::id)
+            .first::<i64>(conn)
+            .optional()?;
+
+        if later_revision.is_some() && !force {
+            return Err(Error::new(ServerError::Conflict));
+        }
+
+        // The user that made the change leading to the restored version owned the entity then.
+        let (data, owner) = revisions::table
+            .inner_join(events::table)
+            .filter(events::entity_type.eq(entity_type.as_str()))
+            .filter(events::entity_id.eq(id))
+            .filter(events::id.lt(revision))
+            .order(events::id.desc())
+            .select((revisions::data, events::created_by))
+            .first::<(String, String)>(conn)
+            .optional()?
+            .ok_or_else(|| Error::new(ServerError::BadRequest))?;
+
+        let data: Value = serde_json::from_str(&data)?;
+
+        let restored_visibility = Visibility {
+            private: data["private"].as_bool().unwrap_or(false),
+            owner,
+        };
+
+        if !restored_visibility.is_visible_to(Some(user)) {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        // Updates keep the locked state of existing entities. Recreated ones are locked again.
+        match entity_type {
+            EntityType::Person => {
+                let person: Person = serde_json::from_value(data)?;
+                update_person_in(tx, &person, user)?;
+
+                if person.locked && !exists {
+                    set_person_locked(conn, id, true, user)?;
+                }
+            }
+            EntityType::Ensemble => {
+                update_ensemble_in(tx, &serde_json::from_value(data)?, user)?;
+            }
+            EntityType::Instrument => {
+                update_instrument_in(tx, &serde_json::from_value(data)?, user)?;
+            }
+            EntityType::Label => {
+                update_label_in(tx, &serde_json::from_value(data)?, user)?;
+            }
+            EntityType::Work => {
+                let work: Work = serde_json::from_value(data)?;
+                update_work_in(tx, &work, user)?;
+
+                if work.locked && !exists {
+                    set_work_locked(conn, id, true, user)?;
+                }
+            }
+            EntityType::Recording => {
+                let recording: Recording = serde_json::from_value(data)?;
+                update_recording_in(tx, &recording, user)?;
+
+                if recording.locked && !exists {
+                    set_recording_locked(conn, id, true, user)?;
+                }
+            }
+            EntityType::Medium => {
+                update_medium_in(tx, &serde_json::from_value(data)?, user)?;
+            }
+        }
+
+        // Recreated entities keep their owner instead of belonging to the reverting user.
+        if !exists {
+            let owner = match last_visibility {
+                Some(visibility) => visibility.owner,
+                None => restored_visibility.owner,
+            };
+
+            set_created_by(conn, entity_type, id, &owner)?;
+        }
+
+        let revision = events::table
+            .filter(events::entity_type.eq(entity_type.as_str()))
+            .filter(events::entity_id.eq(id))
+            .select(diesel::dsl::max(events::id))
+            .first::<Option<i64>>(conn)?
+            .unwrap_or_default();
+
+        Ok(revision)
+    })
+}
@@ -1,8 +1,139 @@
+table! {
+    api_keys (id) {
+        id -> Text,
+        username -> Text,
+        name -> Text,
+        key_hash -> Text,
+        scopes -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    bulk_edits (id) {
+        id -> Int8,
+        description -> Text,
+        changes -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    collection_items (username, medium) {
+        username -> Text,
+        medium -> Text,
+        notes -> Text,
+        condition -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    comments (id) {
+        id -> Text,
+        entity_type -> Text,
+        entity_id -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+        text -> Text,
+    }
+}
+
+table! {
+    drafts (entity_type, id) {
+        entity_type -> Text,
+        id -> Text,
+        username -> Text,
+        data -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    editor_applications (id) {
+        id -> Text,
+        username -> Text,
+        motivation -> Text,
+        created_at -> Timestamp,
+        decided_by -> Nullable<Text>,
+        decided_at -> Nullable<Timestamp>,
+        approved -> Nullable<Bool>,
+    }
+}
+
+table! {
+    email_changes (token) {
+        token -> Text,
+        username -> Text,
+        email -> Text,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     ensembles (id) {
         id -> Text,
         name -> Text,
         created_by -> Text,
+        private -> Bool,
+    }
+}
+
+table! {
+    events (id) {
+        id -> Int8,
+        entity_type -> Text,
+        entity_id -> Text,
+        kind -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    external_ids (id) {
+        id -> Int8,
+        medium -> Nullable<Text>,
+        recording -> Nullable<Text>,
+        source -> Text,
+        external_id -> Text,
+        person -> Nullable<Text>,
+    }
+}
+
+table! {
+    former_usernames (username) {
+        username -> Text,
+        renamed_at -> Timestamp,
+    }
+}
+
+table! {
+    idempotency_keys (username, key) {
+        key -> Text,
+        request_hash -> Text,
+        status -> Nullable<Int2>,
+        content_type -> Nullable<Text>,
+        body -> Nullable<Bytea>,
+        created_at -> Timestamp,
+        username -> Text,
+    }
+}
+
+table! {
+    images (id) {
+        id -> Text,
+        person -> Nullable<Text>,
+        ensemble -> Nullable<Text>,
+        license -> Text,
+        attribution -> Text,
+        source -> Nullable<Text>,
+        width -> Int4,
+        height -> Int4,
+        created_by -> Text,
+        created_at -> Timestamp,
     }
 }
 
@@ -19,6 +150,34 @@ table! {
         id -> Text,
         name -> Text,
         created_by -> Text,
+        private -> Bool,
+    }
+}
+
+table! {
+    invitations (code) {
+        code -> Text,
+        created_by -> Text,
+        used_by -> Nullable<Text>,
+    }
+}
+
+table! {
+    labels (id) {
+        id -> Text,
+        name -> Text,
+        created_by -> Text,
+        private -> Bool,
+    }
+}
+
+table! {
+    medium_relations (id) {
+        id -> Int8,
+        medium -> Text,
+        related -> Text,
+        kind -> Text,
+        created_by -> Text,
     }
 }
 
@@ -28,6 +187,23 @@ table! {
         name -> Text,
         discid -> Nullable<Text>,
         created_by -> Text,
+        private -> Bool,
+        label -> Nullable<Text>,
+    }
+}
+
+table! {
+    notifications (id) {
+        id -> Int8,
+        username -> Text,
+        kind -> Text,
+        entity_type -> Nullable<Text>,
+        entity_id -> Nullable<Text>,
+        reference -> Nullable<Text>,
+        created_by -> Text,
+        created_at -> Timestamp,
+        read -> Bool,
+        mail_pending -> Bool,
     }
 }
 
@@ -41,12 +217,104 @@ table! {
     }
 }
 
+table! {
+    periods (id) {
+        id -> Text,
+        name -> Text,
+        start_year -> Nullable<Int4>,
+        end_year -> Nullable<Int4>,
+    }
+}
+
+table! {
+    person_relations (id) {
+        id -> Int8,
+        person -> Text,
+        related -> Text,
+        kind -> Text,
+        created_by -> Text,
+    }
+}
+
 table! {
     persons (id) {
         id -> Text,
         first_name -> Text,
         last_name -> Text,
         created_by -> Text,
+        locked -> Bool,
+        private -> Bool,
+        period -> Nullable<Text>,
+    }
+}
+
+table! {
+    plays (id) {
+        id -> Int8,
+        username -> Text,
+        recording -> Text,
+        medium -> Nullable<Text>,
+        track_index -> Nullable<Int4>,
+        played_at -> Timestamp,
+    }
+}
+
+table! {
+    playlist_items (playlist, item_index) {
+        playlist -> Text,
+        item_index -> Int4,
+        recording -> Text,
+        medium -> Nullable<Text>,
+        track_index -> Nullable<Int4>,
+    }
+}
+
+table! {
+    playlists (id) {
+        id -> Text,
+        username -> Text,
+        name -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    quality_flags (entity_type, entity_id) {
+        entity_type -> Text,
+        entity_id -> Text,
+        level -> Text,
+        updated_by -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    ratings (recording, username) {
+        recording -> Text,
+        username -> Text,
+        stars -> Int4,
+        review -> Nullable<Text>,
+        hidden -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    read_models (entity_type, entity_id) {
+        entity_type -> Text,
+        entity_id -> Text,
+        version -> Int8,
+        data -> Nullable<Text>,
+    }
+}
+
+table! {
+    recording_works (id) {
+        id -> Int8,
+        recording -> Text,
+        work -> Text,
     }
 }
 
@@ -56,6 +324,73 @@ table! {
         work -> Text,
         comment -> Text,
         created_by -> Text,
+        locked -> Bool,
+        private -> Bool,
+        parts -> Nullable<Text>,
+    }
+}
+
+table! {
+    redirects (entity_type, old_id) {
+        entity_type -> Text,
+        old_id -> Text,
+        new_id -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    replication_state (upstream) {
+        upstream -> Text,
+        revision -> Int8,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    report_comments (id) {
+        id -> Text,
+        report -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+        text -> Text,
+    }
+}
+
+table! {
+    reports (id) {
+        id -> Text,
+        entity_type -> Text,
+        entity_id -> Text,
+        kind -> Text,
+        reason -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+        resolved_by -> Nullable<Text>,
+        resolved_at -> Nullable<Timestamp>,
+        resolution -> Nullable<Text>,
+    }
+}
+
+table! {
+    revisions (event) {
+        event -> Int8,
+        data -> Text,
+    }
+}
+
+table! {
+    sources (id) {
+        id -> Text,
+        entity_type -> Text,
+        entity_id -> Text,
+        revision -> Int8,
+        description -> Text,
+        license -> Nullable<Text>,
+        url -> Nullable<Text>,
+        created_by -> Text,
+        created_at -> Timestamp,
     }
 }
 
@@ -74,6 +409,23 @@ table! {
         track_set -> Int8,
         index -> Int4,
         work_parts -> Text,
+        duration -> Nullable<Int4>,
+        work_index -> Int4,
+        flac_md5 -> Nullable<Text>,
+        accuraterip_v1 -> Nullable<Text>,
+        accuraterip_v2 -> Nullable<Text>,
+    }
+}
+
+table! {
+    trash (id) {
+        id -> Int8,
+        entity_type -> Text,
+        entity_id -> Text,
+        data -> Text,
+        deleted_by -> Text,
+        deleted_at -> Timestamp,
+        created_by -> Text,
     }
 }
 
@@ -85,6 +437,73 @@ table! {
         is_admin -> Bool,
         is_editor -> Bool,
         is_banned -> Bool,
+        record_plays -> Bool,
+        password_scheme -> Text,
+    }
+}
+
+table! {
+    watches (username, entity_type, entity_id) {
+        username -> Text,
+        entity_type -> Text,
+        entity_id -> Text,
+        email -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    webhooks (id) {
+        id -> Text,
+        url -> Text,
+        secret -> Text,
+        entity_types -> Text,
+        kinds -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+        last_event -> Int8,
+        failures -> Int4,
+        next_attempt -> Timestamp,
+    }
+}
+
+table! {
+    wikidata_candidates (id) {
+        id -> Int8,
+        person -> Text,
+        item -> Text,
+        label -> Text,
+        description -> Nullable<Text>,
+        birth_year -> Nullable<Int4>,
+        death_year -> Nullable<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    wikidata_checks (person) {
+        person -> Text,
+        checked_at -> Timestamp,
+    }
+}
+
+table! {
+    work_authors (id) {
+        id -> Int8,
+        work -> Text,
+        part_index -> Nullable<Int8>,
+        person -> Text,
+    }
+}
+
+table! {
+    work_part_titles (id) {
+        id -> Int8,
+        work -> Text,
+        part_index -> Int8,
+        language -> Text,
+        title -> Text,
+        original -> Bool,
     }
 }
 
@@ -94,6 +513,20 @@ table! {
         work -> Text,
         part_index -> Int8,
         title -> Text,
+        key -> Nullable<Text>,
+        tempo -> Nullable<Text>,
+        duration -> Nullable<Int4>,
+    }
+}
+
+table! {
+    work_section_titles (id) {
+        id -> Int8,
+        work -> Text,
+        section_index -> Int8,
+        language -> Text,
+        title -> Text,
+        original -> Bool,
     }
 }
 
@@ -106,47 +539,167 @@ table! {
     }
 }
 
+table! {
+    work_texts (id) {
+        id -> Int8,
+        work -> Text,
+        part_index -> Nullable<Int8>,
+        language -> Text,
+        translation -> Bool,
+        text -> Text,
+    }
+}
+
+table! {
+    work_titles (id) {
+        id -> Int8,
+        work -> Text,
+        language -> Text,
+        title -> Text,
+        original -> Bool,
+    }
+}
+
 table! {
     works (id) {
         id -> Text,
         composer -> Text,
         title -> Text,
         created_by -> Text,
+        locked -> Bool,
+        private -> Bool,
+        premiere_date -> Nullable<Text>,
+        premiere_place -> Nullable<Text>,
+        premiere_performers -> Nullable<Text>,
+        dedication -> Nullable<Text>,
+        period -> Nullable<Text>,
+        imslp -> Nullable<Text>,
+        nickname -> Nullable<Text>,
     }
 }
 
+joinable!(api_keys -> users (username));
+joinable!(bulk_edits -> users (created_by));
+joinable!(collection_items -> mediums (medium));
+joinable!(collection_items -> users (username));
+joinable!(comments -> users (created_by));
+joinable!(drafts -> users (username));
+joinable!(email_changes -> users (username));
 joinable!(ensembles -> users (created_by));
+joinable!(events -> users (created_by));
+joinable!(external_ids -> mediums (medium));
+joinable!(external_ids -> persons (person));
+joinable!(external_ids -> recordings (recording));
+joinable!(images -> ensembles (ensemble));
+joinable!(images -> persons (person));
+joinable!(images -> users (created_by));
 joinable!(instrumentations -> instruments (instrument));
 joinable!(instrumentations -> works (work));
 joinable!(instruments -> users (created_by));
+joinable!(labels -> users (created_by));
+joinable!(medium_relations -> users (created_by));
+joinable!(mediums -> labels (label));
 joinable!(mediums -> users (created_by));
 joinable!(performances -> ensembles (ensemble));
 joinable!(performances -> instruments (role));
 joinable!(performances -> persons (person));
 joinable!(performances -> recordings (recording));
+joinable!(person_relations -> users (created_by));
+joinable!(persons -> periods (period));
 joinable!(persons -> users (created_by));
+joinable!(playlist_items -> mediums (medium));
+joinable!(playlist_items -> playlists (playlist));
+joinable!(playlist_items -> recordings (recording));
+joinable!(playlists -> users (username));
+joinable!(plays -> mediums (medium));
+joinable!(plays -> recordings (recording));
+joinable!(plays -> users (username));
+joinable!(quality_flags -> users (updated_by));
+joinable!(ratings -> recordings (recording));
+joinable!(ratings -> users (username));
+joinable!(recording_works -> recordings (recording));
+joinable!(recording_works -> works (work));
 joinable!(recordings -> users (created_by));
 joinable!(recordings -> works (work));
+joinable!(redirects -> users (created_by));
+joinable!(report_comments -> reports (report));
+joinable!(report_comments -> users (created_by));
+joinable!(revisions -> events (event));
+joinable!(sources -> events (revision));
+joinable!(sources -> users (created_by));
 joinable!(track_sets -> mediums (medium));
 joinable!(track_sets -> recordings (recording));
 joinable!(tracks -> track_sets (track_set));
+joinable!(trash -> users (deleted_by));
+joinable!(watches -> users (username));
+joinable!(webhooks -> users (created_by));
+joinable!(wikidata_candidates -> persons (person));
+joinable!(wikidata_checks -> persons (person));
+joinable!(work_authors -> persons (person));
+joinable!(work_authors -> works (work));
+joinable!(work_part_titles -> works (work));
 joinable!(work_parts -> works (work));
+joinable!(work_section_titles -> works (work));
 joinable!(work_sections -> works (work));
+joinable!(work_texts -> works (work));
+joinable!(work_titles -> works (work));
+joinable!(works -> periods (period));
 joinable!(works -> persons (composer));
 joinable!(works -> users (created_by));
 
 allow_tables_to_appear_in_same_query!(
+    api_keys,
+    bulk_edits,
+    collection_items,
+    comments,
+    drafts,
+    editor_applications,
+    email_changes,
     ensembles,
+    events,
+    external_ids,
+    former_usernames,
+    idempotency_keys,
+    images,
     instrumentations,
     instruments,
+    invitations,
+    labels,
+    medium_relations,
     mediums,
+    notifications,
     performances,
+    periods,
+    person_relations,
     persons,
+    playlist_items,
+    playlists,
+    plays,
+    quality_flags,
+    ratings,
+    read_models,
+    recording_works,
     recordings,
+    redirects,
+    replication_state,
+    report_comments,
+    reports,
+    revisions,
+    sources,
     track_sets,
     tracks,
+    trash,
     users,
+    watches,
+    webhooks,
+    wikidata_candidates,
+    wikidata_checks,
+    work_authors,
+    work_part_titles,
     work_parts,
+    work_section_titles,
     work_sections,
+    work_texts,
+    work_titles,
     works,
 );
@@ -0,0 +1,204 @@
+use super::schema::{ensembles, performances, persons, recordings};
+use super::schema::{work_authors, work_parts, work_titles, works};
+use super::{get_recording, get_work, normalize_text, DbConn, Recording, Work};
+use anyhow::Result;
+use diesel::prelude::*;
+use diesel::PgTextExpressionMethods;
+
+/// Find public works where each word of the query is contained in the title or one of its
+/// translations, the popular name, the composer's name, the name of one of the text authors or
+/// the title of one of the parts. If a period is provided, only works of that period are
+/// included. Works without a period of their own belong to the period of their composer.
+pub fn search_works(
+    conn: &DbConn,
+    query: &str,
+    period: Option<&str>,
+    limit: i64,
+) -> Result<Vec<Work>> {
+    let words = get_patterns(query);
+    if words.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let phrase = get_phrase_pattern(query);
+
+    let mut select = works::table
+        .inner_join(persons::table)
+        .filter(works::private.eq(false))
+        .select(works::id)
+        .into_boxed();
+
+    if let Some(period) = period {
+        select = select.filter(
+            works::period
+                .eq(period)
+                .or(works::period.is_null().and(persons::period.eq(period))),
+        );
+    }
+
+    for pattern in words {
+        let parts = work_parts::table
+            .filter(work_parts::title.ilike(pattern.clone()))
+            .select(work_parts::work);
+
+        let titles = work_titles::table
+            .filter(work_titles::title.ilike(pattern.clone()))
+            .select(work_titles::work);
+
+        // Persons can't appear twice within the same query, so the authors are matched separately.
+        let authored: Vec<String> = work_authors::table
+            .inner_join(persons::table)
+            .filter(
+                persons::first_name
+                    .ilike(pattern.clone())
+                    .or(persons::last_name.ilike(pattern.clone())),
+            )
+            .select(work_authors::work)
+            .load(conn)?;
+
+        select = select.filter(
+            works::title
+                .ilike(pattern.clone())
+                .or(works::nickname.ilike(pattern.clone()))
+                .or(persons::first_name.ilike(pattern.clone()))
+                .or(persons::last_name.ilike(pattern.clone()))
+                .or(works::id.eq_any(parts))
+                .or(works::id.eq_any(titles))
+                .or(works::id.eq_any(authored)),
+        );
+    }
+
+    // Works whose popular name contains the whole query are the most likely ones to be meant.
+    let ids: Vec<String> = select
+        .order((
+            works::nickname.ilike(phrase).desc().nulls_last(),
+            works::title,
+        ))
+        .limit(limit)
+        .load(conn)?;
+
+    let mut works = Vec::new();
+    for id in ids {
+        if let Some(work) = get_work(conn, &id)? {
+            works.push(work);
+        }
+    }
+
+    Ok(works)
+}
+
+/// Find public recordings where each word of the query is contained in the work's title, the
+/// composer's name, the comment or the name of one of the performers. If a period is provided,
+/// only recordings of works of that period are included.
+pub fn search_recordings(
+    conn: &DbConn,
+    query: &str,
+    period: Option<&str>,
+    limit: i64,
+) -> Result<Vec<Recording>> {
+    let words = get_patterns(query);
+    if words.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let phrase = get_phrase_pattern(query);
+
+    let mut select = recordings::table
+        .inner_join(works::table)
+        .filter(recordings::private.eq(false))
+        .select(recordings::id)
+        .into_boxed();
+
+    if let Some(period) = period {
+        let composers = persons::table
+            .filter(persons::period.eq(period))
+            .select(persons::id);
+
+        select = select.filter(
+            works::period.eq(period).or(works::period
+                .is_null()
+                .and(works::composer.eq_any(composers))),
+        );
+    }
+
+    for pattern in words {
+        let composers = persons::table
+            .filter(
+                persons::first_name
+                    .ilike(pattern.clone())
+                    .or(persons::last_name.ilike(pattern.clone())),
+            )
+            .select(persons::id);
+
+        let performers = persons::table
+            .filter(
+                persons::first_name
+                    .ilike(pattern.clone())
+                    .or(persons::last_name.ilike(pattern.clone())),
+            )
+            .select(persons::id.nullable());
+
+        let ensembles = ensembles::table
+            .filter(ensembles::name.ilike(pattern.clone()))
+            .select(ensembles::id.nullable());
+
+        let performances = performances::table
+            .filter(
+                performances::person
+                    .eq_any(performers)
+                    .or(performances::ensemble.eq_any(ensembles)),
+            )
+            .select(performances::recording);
+
+        select = select.filter(
+            works::title
+                .ilike(pattern.clone())
+                .or(works::nickname.ilike(pattern.clone()))
+                .or(works::composer.eq_any(composers))
+                .or(recordings::comment.ilike(pattern.clone()))
+                .or(recordings::id.eq_any(performances)),
+        );
+    }
+
+    let ids: Vec<String> = select
+        .order((
+            works::nickname.ilike(phrase).desc().nulls_last(),
+            works::title,
+        ))
+        .limit(limit)
+        .load(conn)?;
+
+    let mut recordings = Vec::new();
+    for id in ids {
+        if let Some(recording) = get_recording(conn, &id)? {
+            recordings.push(recording);
+        }
+    }
+
+    Ok(recordings)
+}
+
+/// Split a search query into words and create a pattern for matching each of them. The query is
+/// normalized like the stored texts, so that it matches them regardless of its encoding.
+fn get_patterns(query: &str) -> Vec<String> {
+    normalize_text(query)
+        .split_whitespace()
+        .map(|word| format!("%{}%", escape_pattern(word)))
+        .collect()
+}
+
+/// Get a pattern that matches texts containing all words of the query in their order.
+fn get_phrase_pattern(query: &str) -> String {
+    let words: Vec<String> = normalize_text(query)
+        .split_whitespace()
+        .map(escape_pattern)
+        .collect();
+    format!("%{}%", words.join("%"))
+}
+
+/// Escape the characters that have a special meaning within patterns.
+fn escape_pattern(word: &str) -> String {
+    word.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
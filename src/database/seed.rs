@@ -0,0 +1,111 @@
+use super::{
+    get_instrument, get_person, get_work, update_instrument_in, update_person_in, update_work_in,
+    with_transaction, DbConn, Instrument, Person, User, Work, WorkPart,
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+/// The curated seed dataset that is compiled into the binary.
+const SEED: &str = include_str!("seed.json");
+
+/// A set of well-known entities for populating an empty database.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Seed {
+    persons: Vec<Person>,
+    instruments: Vec<Instrument>,
+    works: Vec<SeedWork>,
+}
+
+/// A work within the seed dataset. The composer and the instruments are referenced by their ID.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SeedWork {
+    id: String,
+    title: String,
+    composer: String,
+    instruments: Vec<String>,
+    parts: Vec<String>,
+}
+
+/// Load the seed dataset of well-known composers, instruments and works. Entities that already
+/// exist are left untouched, so this can safely be run more than once. Everything is created on
+/// behalf of the provided user. Returns the number of newly created entities.
+pub fn load_seed(conn: &DbConn, user: &User) -> Result<usize> {
+    let seed: Seed = serde_json::from_str(SEED)?;
+
+    with_transaction(conn, |tx| {
+        let mut created = 0;
+
+        for person in &seed.persons {
+            if get_person(tx.conn(), &person.id)?.is_none() {
+                update_person_in(tx, person, user)?;
+                created += 1;
+            }
+        }
+
+        for instrument in &seed.instruments {
+            if get_instrument(tx.conn(), &instrument.id)?.is_none() {
+                update_instrument_in(tx, instrument, user)?;
+                created += 1;
+            }
+        }
+
+        for seed_work in &seed.works {
+            if get_work(tx.conn(), &seed_work.id)?.is_some() {
+                continue;
+            }
+
+            let composer = seed
+                .persons
+                .iter()
+                .find(|person| person.id == seed_work.composer)
+                .ok_or_else(|| anyhow!("Unknown composer in seed: {}", seed_work.composer))?;
+
+            let mut instruments = Vec::new();
+            for id in &seed_work.instruments {
+                let instrument = seed
+                    .instruments
+                    .iter()
+                    .find(|instrument| &instrument.id == id)
+                    .ok_or_else(|| anyhow!("Unknown instrument in seed: {}", id))?;
+
+                instruments.push(instrument.clone());
+            }
+
+            let work = Work {
+                id: seed_work.id.clone(),
+                title: seed_work.title.clone(),
+                nickname: None,
+                titles: Vec::new(),
+                composer: composer.clone(),
+                authors: Vec::new(),
+                instruments,
+                parts: seed_work
+                    .parts
+                    .iter()
+                    .map(|title| WorkPart {
+                        title: title.clone(),
+                        titles: Vec::new(),
+                        key: None,
+                        tempo: None,
+                        duration: None,
+                        authors: Vec::new(),
+                    })
+                    .collect(),
+                sections: Vec::new(),
+                premiere: None,
+                dedication: None,
+                period: None,
+                imslp: None,
+                locked: false,
+                private: false,
+            };
+
+            update_work_in(tx, &work, user)?;
+            created += 1;
+        }
+
+        Ok(created)
+    })
+}
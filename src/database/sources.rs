@@ -0,0 +1,253 @@
+use super::schema::sources;
+use super::{generate_id, insert_event, is_entity_visible, may_edit_entity, with_transaction};
+use super::{DbConn, EntityType, EventKind, User};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Where the data of an entity comes from and under which license it may be used.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+    pub id: String,
+
+    /// The revision of the change that added the source.
+    pub revision: i64,
+
+    /// What the data is based on, e.g. "Liner notes" or "Data derived from MusicBrainz".
+    pub description: String,
+
+    /// The license of the source, e.g. "CC0", if it is known.
+    pub license: Option<String>,
+
+    /// A link to the source, if it is available online.
+    pub url: Option<String>,
+
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A [`Source`] together with the entity it belongs to, as it is included within dumps.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EntitySource {
+    pub entity_type: EntityType,
+    pub entity_id: String,
+
+    #[serde(flatten)]
+    pub source: Source,
+}
+
+/// Table data for a [`Source`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "sources"]
+struct SourceRow {
+    pub id: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub revision: i64,
+    pub description: String,
+    pub license: Option<String>,
+    pub url: Option<String>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<SourceRow> for Source {
+    fn from(row: SourceRow) -> Source {
+        Source {
+            id: row.id,
+            revision: row.revision,
+            description: row.description,
+            license: row.license,
+            url: row.url,
+            created_by: row.created_by,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Add a source to an entity. The user has to be allowed to edit the entity. This is recorded as
+/// a change of the entity, so that mirrors and watchers learn about it. Returns the new source.
+pub fn insert_source(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    description: &str,
+    license: Option<&str>,
+    url: Option<&str>,
+    user: &User,
+) -> Result<Source> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        if !may_edit_entity(conn, entity_type, entity_id, user)? {
+            return Err(Error::new(ServerError::Forbidden));
+        }
+
+        let revision = insert_event(conn, entity_type, entity_id, EventKind::Update, user)?;
+
+        let row = SourceRow {
+            id: generate_id(),
+            entity_type: entity_type.as_str().to_string(),
+            entity_id: entity_id.to_string(),
+            revision,
+            description: description.to_string(),
+            license: license.map(str::to_string),
+            url: url.map(str::to_string),
+            created_by: user.username.clone(),
+            created_at: Utc::now().naive_utc(),
+        };
+
+        diesel::insert_into(sources::table)
+            .values(&row)
+            .execute(conn)?;
+
+        Ok(row.into())
+    })
+}
+
+/// Get all sources of an entity, oldest first. The entity has to be visible to the user, if any.
+pub fn get_sources(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Source>> {
+    if !is_entity_visible(conn, entity_type, entity_id, viewer)? {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    get_entity_sources(conn, entity_type, entity_id)
+}
+
+/// Get all sources of an entity, oldest first, without checking whether it is visible.
+pub fn get_entity_sources(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+) -> Result<Vec<Source>> {
+    let rows = sources::table
+        .filter(sources::entity_type.eq(entity_type.as_str()))
+        .filter(sources::entity_id.eq(entity_id))
+        .order_by(sources::revision)
+        .load::<SourceRow>(conn)?;
+
+    Ok(rows.into_iter().map(Source::from).collect())
+}
+
+/// Get the sources of all public entities ordered by entity and revision.
+pub fn get_public_sources(conn: &DbConn) -> Result<Vec<EntitySource>> {
+    let rows = sources::table
+        .order_by((sources::entity_type, sources::entity_id, sources::revision))
+        .load::<SourceRow>(conn)?;
+
+    let mut visible: HashMap<(String, String), bool> = HashMap::new();
+    let mut result = Vec::new();
+
+    for row in rows {
+        let entity_type = EntityType::parse(&row.entity_type)
+            .ok_or_else(|| anyhow!("Unknown entity type: {}", row.entity_type))?;
+
+        let key = (row.entity_type.clone(), row.entity_id.clone());
+        let is_visible = match visible.get(&key) {
+            Some(&is_visible) => is_visible,
+            None => {
+                let is_visible = is_entity_visible(conn, entity_type, &row.entity_id, None)?;
+                visible.insert(key, is_visible);
+                is_visible
+            }
+        };
+
+        if is_visible {
+            result.push(EntitySource {
+                entity_type,
+                entity_id: row.entity_id.clone(),
+                source: row.into(),
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Restore a source from a dump on behalf of the provided user. Sources that already exist are
+/// kept. Otherwise, this is recorded as a new change of the entity.
+pub fn restore_source(conn: &DbConn, source: &EntitySource, user: &User) -> Result<()> {
+    let exists = sources::table
+        .filter(sources::id.eq(&source.source.id))
+        .count()
+        .get_result::<i64>(conn)?
+        > 0;
+
+    if exists {
+        return Ok(());
+    }
+
+    let revision = insert_event(
+        conn,
+        source.entity_type,
+        &source.entity_id,
+        EventKind::Update,
+        user,
+    )?;
+
+    let row = SourceRow {
+        id: source.source.id.clone(),
+        entity_type: source.entity_type.as_str().to_string(),
+        entity_id: source.entity_id.clone(),
+        revision,
+        description: source.source.description.clone(),
+        license: source.source.license.clone(),
+        url: source.source.url.clone(),
+        created_by: user.username.clone(),
+        created_at: source.source.created_at,
+    };
+
+    diesel::insert_into(sources::table)
+        .values(&row)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Remove a source from an entity. This is possible for its author and for users that may
+/// moderate. Like adding a source, this is recorded as a change of the entity.
+pub fn delete_source(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    id: &str,
+    user: &User,
+) -> Result<()> {
+    if !is_entity_visible(conn, entity_type, entity_id, Some(user))? {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let row = sources::table
+            .filter(sources::id.eq(id))
+            .filter(sources::entity_type.eq(entity_type.as_str()))
+            .filter(sources::entity_id.eq(entity_id))
+            .first::<SourceRow>(conn)
+            .optional()?
+            .ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+        if row.created_by != user.username && !user.may_moderate() {
+            return Err(Error::new(ServerError::Forbidden));
+        }
+
+        diesel::delete(sources::table)
+            .filter(sources::id.eq(id))
+            .execute(conn)?;
+
+        insert_event(conn, entity_type, entity_id, EventKind::Update, user)?;
+
+        Ok(())
+    })
+}
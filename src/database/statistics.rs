@@ -0,0 +1,185 @@
+use super::schema::{ensembles, instruments, labels, mediums, persons, recordings, works};
+use super::schema::{instrumentations, performances, recording_works, track_sets, tracks};
+use super::DbConn;
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// The number of stored entities of each type.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Statistics {
+    pub persons: i64,
+    pub ensembles: i64,
+    pub instruments: i64,
+    pub works: i64,
+    pub recordings: i64,
+    pub labels: i64,
+    pub mediums: i64,
+
+    /// When the statistics were computed.
+    pub computed_at: NaiveDateTime,
+}
+
+/// Count the stored entities. This is rather expensive, so the result should be cached.
+pub fn compute_statistics(conn: &DbConn) -> Result<Statistics> {
+    Ok(Statistics {
+        persons: persons::table.count().get_result(conn)?,
+        ensembles: ensembles::table.count().get_result(conn)?,
+        instruments: instruments::table.count().get_result(conn)?,
+        works: works::table.count().get_result(conn)?,
+        recordings: recordings::table.count().get_result(conn)?,
+        labels: labels::table.count().get_result(conn)?,
+        mediums: mediums::table.count().get_result(conn)?,
+        computed_at: Utc::now().naive_utc(),
+    })
+}
+
+/// How many public entities lack information that is usually available. Editors can use this to
+/// find out where to focus.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Completeness {
+    pub works: WorkCompleteness,
+    pub recordings: RecordingCompleteness,
+    pub mediums: MediumCompleteness,
+}
+
+/// The number of works and how many of them lack information.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkCompleteness {
+    pub total: i64,
+    pub without_instrumentation: i64,
+    pub without_period: i64,
+    pub without_recordings: i64,
+}
+
+/// The number of recordings and how many of them lack information.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingCompleteness {
+    pub total: i64,
+    pub without_performances: i64,
+    pub without_mediums: i64,
+}
+
+/// The number of mediums and how many of them lack information.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MediumCompleteness {
+    pub total: i64,
+    pub without_discid: i64,
+    pub without_label: i64,
+
+    /// Mediums with at least one track without a duration.
+    pub without_durations: i64,
+}
+
+/// Count public entities with missing information. If a composer is provided, only their works,
+/// recordings of them and mediums containing any of these recordings are counted.
+pub fn compute_completeness(conn: &DbConn, composer: Option<&str>) -> Result<Completeness> {
+    let works = WorkCompleteness {
+        total: public_works(composer).count().get_result(conn)?,
+        without_instrumentation: public_works(composer)
+            .filter(works::id.ne_all(instrumentations::table.select(instrumentations::work)))
+            .count()
+            .get_result(conn)?,
+        without_period: public_works(composer)
+            .filter(works::period.is_null())
+            .count()
+            .get_result(conn)?,
+        without_recordings: public_works(composer)
+            .filter(works::id.ne_all(recordings::table.select(recordings::work)))
+            .filter(works::id.ne_all(recording_works::table.select(recording_works::work)))
+            .count()
+            .get_result(conn)?,
+    };
+
+    let recordings = RecordingCompleteness {
+        total: public_recordings(composer).count().get_result(conn)?,
+        without_performances: public_recordings(composer)
+            .filter(recordings::id.ne_all(performances::table.select(performances::recording)))
+            .count()
+            .get_result(conn)?,
+        without_mediums: public_recordings(composer)
+            .filter(recordings::id.ne_all(track_sets::table.select(track_sets::recording)))
+            .count()
+            .get_result(conn)?,
+    };
+
+    let incomplete = track_sets::table
+        .inner_join(tracks::table)
+        .filter(tracks::duration.is_null())
+        .select(track_sets::medium);
+
+    let mediums = MediumCompleteness {
+        total: public_mediums(composer).count().get_result(conn)?,
+        without_discid: public_mediums(composer)
+            .filter(mediums::discid.is_null())
+            .count()
+            .get_result(conn)?,
+        without_label: public_mediums(composer)
+            .filter(mediums::label.is_null())
+            .count()
+            .get_result(conn)?,
+        without_durations: public_mediums(composer)
+            .filter(mediums::id.eq_any(incomplete))
+            .count()
+            .get_result(conn)?,
+    };
+
+    Ok(Completeness {
+        works,
+        recordings,
+        mediums,
+    })
+}
+
+/// Query public works, optionally only those of one composer.
+fn public_works(composer: Option<&str>) -> works::BoxedQuery<'_, Pg> {
+    let mut query = works::table.filter(works::private.eq(false)).into_boxed();
+
+    if let Some(composer) = composer {
+        query = query.filter(works::composer.eq(composer));
+    }
+
+    query
+}
+
+/// Query public recordings, optionally only those of works by one composer.
+fn public_recordings(composer: Option<&str>) -> recordings::BoxedQuery<'_, Pg> {
+    let mut query = recordings::table
+        .filter(recordings::private.eq(false))
+        .into_boxed();
+
+    if let Some(composer) = composer {
+        let works = works::table
+            .filter(works::composer.eq(composer))
+            .select(works::id);
+
+        query = query.filter(recordings::work.eq_any(works));
+    }
+
+    query
+}
+
+/// Query public mediums, optionally only those containing recordings of works by one composer.
+fn public_mediums(composer: Option<&str>) -> mediums::BoxedQuery<'_, Pg> {
+    let mut query = mediums::table
+        .filter(mediums::private.eq(false))
+        .into_boxed();
+
+    if let Some(composer) = composer {
+        let mediums = track_sets::table
+            .inner_join(recordings::table.inner_join(works::table))
+            .filter(works::composer.eq(composer))
+            .select(track_sets::medium);
+
+        query = query.filter(mediums::id.eq_any(mediums));
+    }
+
+    query
+}
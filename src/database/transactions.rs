@@ -0,0 +1,25 @@
+use super::DbConn;
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+
+/// A database connection with an open transaction. Functions that take this instead of a
+/// [`DbConn`] are meant to be part of a larger change and can't commit anything on their own.
+pub struct DbTransaction<'a> {
+    conn: &'a DbConn,
+}
+
+impl<'a> DbTransaction<'a> {
+    /// Get the connection for running queries within the transaction.
+    pub fn conn(&self) -> &DbConn {
+        self.conn
+    }
+}
+
+/// Run a function within a new transaction. The transaction is committed if the function
+/// succeeds and rolled back as a whole otherwise.
+pub fn with_transaction<T, F>(conn: &DbConn, f: F) -> Result<T>
+where
+    F: FnOnce(&DbTransaction) -> Result<T>,
+{
+    conn.transaction::<T, Error, _>(|| f(&DbTransaction { conn }))
+}
@@ -0,0 +1,280 @@
+use super::schema::{ensembles, instruments, labels, mediums, persons, recordings, trash, works};
+use super::{delete_redirect, get_ensemble, get_instrument, get_label, get_medium, get_person};
+use super::{get_recording, get_visibility, get_work, get_work_texts, replace_work_texts};
+use super::{set_person_locked, set_recording_locked, set_work_locked, update_ensemble_in};
+use super::{update_instrument_in, update_label_in, update_medium_in, update_person_in};
+use super::{update_recording_in, update_work_in, with_transaction, DbConn, EntityType, Person};
+use super::{Recording, User, Work, WorkText};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::TryFrom;
+
+lazy_static! {
+    /// The number of days deleted entities are kept within the trash. This is read from the
+    /// environment variable "WOLFGANG_TRASH_RETENTION_DAYS" and defaults to 30 days.
+    static ref RETENTION_DAYS: i64 = std::env::var("WOLFGANG_TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(30);
+}
+
+/// A deleted entity that can be restored.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub id: i64,
+    pub entity_type: EntityType,
+    pub entity_id: String,
+
+    /// The entity as it was before it was deleted.
+    pub data: Value,
+
+    /// The user that created or last changed the entity. It will be restored with this owner.
+    pub created_by: String,
+
+    pub deleted_by: String,
+    pub deleted_at: NaiveDateTime,
+}
+
+/// Table data for a new trash item. The ID and time will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "trash"]
+struct NewTrashRow {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub data: String,
+    pub deleted_by: String,
+    pub created_by: String,
+}
+
+/// Table data for a trash item.
+#[derive(Queryable, Debug, Clone)]
+struct TrashRow {
+    pub id: i64,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub data: String,
+    pub deleted_by: String,
+    pub deleted_at: NaiveDateTime,
+    pub created_by: String,
+}
+
+impl TryFrom<TrashRow> for TrashItem {
+    type Error = Error;
+
+    fn try_from(row: TrashRow) -> Result<Self> {
+        Ok(TrashItem {
+            id: row.id,
+            entity_type: EntityType::parse(&row.entity_type)
+                .ok_or_else(|| anyhow!("Unknown entity type: {}", row.entity_type))?,
+            entity_id: row.entity_id,
+            data: serde_json::from_str(&row.data)?,
+            created_by: row.created_by,
+            deleted_by: row.deleted_by,
+            deleted_at: row.deleted_at,
+        })
+    }
+}
+
+/// Store a snapshot of an entity within the trash. This has to be called within the same
+/// transaction right before the entity is deleted. Nothing happens, if the entity doesn't exist.
+pub fn move_to_trash(conn: &DbConn, entity_type: EntityType, id: &str, user: &User) -> Result<()> {
+    let data = match entity_type {
+        EntityType::Person => get_person(conn, id)?.map(|e| serde_json::to_string(&e)),
+        EntityType::Ensemble => get_ensemble(conn, id)?.map(|e| serde_json::to_string(&e)),
+        EntityType::Instrument => get_instrument(conn, id)?.map(|e| serde_json::to_string(&e)),
+        EntityType::Work => match get_work(conn, id)? {
+            Some(work) => {
+                // Texts aren't part of works, so they are stored alongside.
+                let mut data = serde_json::to_value(&work)?;
+                data["texts"] = serde_json::to_value(get_work_texts(conn, id)?)?;
+                Some(serde_json::to_string(&data))
+            }
+            None => None,
+        },
+        EntityType::Recording => get_recording(conn, id)?.map(|e| serde_json::to_string(&e)),
+        EntityType::Medium => get_medium(conn, id)?.map(|e| serde_json::to_string(&e)),
+        EntityType::Label => get_label(conn, id)?.map(|e| serde_json::to_string(&e)),
+    };
+
+    let visibility = get_visibility(conn, entity_type, id)?;
+
+    if let (Some(data), Some(visibility)) = (data, visibility) {
+        diesel::insert_into(trash::table)
+            .values(NewTrashRow {
+                entity_type: entity_type.as_str().to_string(),
+                entity_id: id.to_string(),
+                data: data?,
+                deleted_by: user.username.clone(),
+                created_by: visibility.owner,
+            })
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Get all items within the trash, most recently deleted first.
+pub fn get_trash(conn: &DbConn) -> Result<Vec<TrashItem>> {
+    trash::table
+        .order_by(trash::deleted_at.desc())
+        .load::<TrashRow>(conn)?
+        .into_iter()
+        .map(TrashItem::try_from)
+        .collect()
+}
+
+/// Restore items from the trash on behalf of the provided user. Entities are restored in the
+/// order of their dependencies within a single transaction, so either all of them are restored
+/// or none. Restored entities belong to the user that created or last changed them before, while
+/// the events are attributed to the restoring user. The restored items are removed from the
+/// trash. Returns the number of restored items.
+pub fn restore_from_trash(conn: &DbConn, ids: &[i64], user: &User) -> Result<usize> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let mut items = trash::table
+            .filter(trash::id.eq_any(ids))
+            .load::<TrashRow>(conn)?
+            .into_iter()
+            .map(TrashItem::try_from)
+            .collect::<Result<Vec<TrashItem>>>()?;
+
+        let mut unique = ids.to_vec();
+        unique.sort_unstable();
+        unique.dedup();
+
+        if items.len() != unique.len() {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        // Referenced entities have to be restored first. Entities that were deleted together
+        // are restored in the order they were deleted in.
+        items.sort_by_key(|item| (get_restore_rank(item.entity_type), item.id));
+
+        for item in &items {
+            let data = item.data.clone();
+
+            // A restored entity doesn't replace a merged one anymore.
+            delete_redirect(conn, item.entity_type, &item.entity_id)?;
+
+            match item.entity_type {
+                EntityType::Person => {
+                    let person: Person = serde_json::from_value(data)?;
+                    update_person_in(tx, &person, user)?;
+
+                    if person.locked {
+                        set_person_locked(conn, &person.id, true, user)?;
+                    }
+                }
+                EntityType::Ensemble => {
+                    update_ensemble_in(tx, &serde_json::from_value(data)?, user)?;
+                }
+                EntityType::Instrument => {
+                    update_instrument_in(tx, &serde_json::from_value(data)?, user)?;
+                }
+                EntityType::Label => {
+                    update_label_in(tx, &serde_json::from_value(data)?, user)?;
+                }
+                EntityType::Work => {
+                    let work: Work = serde_json::from_value(data)?;
+                    update_work_in(tx, &work, user)?;
+
+                    if work.locked {
+                        set_work_locked(conn, &work.id, true, user)?;
+                    }
+
+                    // Older snapshots don't contain texts.
+                    if let Some(texts) = item.data.get("texts") {
+                        let texts: Vec<WorkText> = serde_json::from_value(texts.clone())?;
+                        replace_work_texts(conn, &work.id, &texts)?;
+                    }
+                }
+                EntityType::Recording => {
+                    let recording: Recording = serde_json::from_value(data)?;
+                    update_recording_in(tx, &recording, user)?;
+
+                    if recording.locked {
+                        set_recording_locked(conn, &recording.id, true, user)?;
+                    }
+                }
+                EntityType::Medium => {
+                    update_medium_in(tx, &serde_json::from_value(data)?, user)?;
+                }
+            }
+
+            set_created_by(conn, item.entity_type, &item.entity_id, &item.created_by)?;
+        }
+
+        diesel::delete(trash::table.filter(trash::id.eq_any(ids))).execute(conn)?;
+
+        Ok(items.len())
+    })
+}
+
+/// Permanently remove all items from the trash that were deleted before the provided time.
+/// Returns the number of removed items.
+pub fn purge_trash(conn: &DbConn, before: NaiveDateTime) -> Result<usize> {
+    Ok(diesel::delete(trash::table.filter(trash::deleted_at.lt(before))).execute(conn)?)
+}
+
+/// Permanently remove all items from the trash that are older than the configured retention
+/// period. Returns the number of removed items.
+pub fn purge_expired_trash(conn: &DbConn) -> Result<usize> {
+    purge_trash(
+        conn,
+        Utc::now().naive_utc() - Duration::days(*RETENTION_DAYS),
+    )
+}
+
+/// Change the user that created or last changed an entity without recording an event.
+pub fn set_created_by(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    username: &str,
+) -> Result<()> {
+    match entity_type {
+        EntityType::Person => diesel::update(persons::table.filter(persons::id.eq(id)))
+            .set(persons::created_by.eq(username))
+            .execute(conn)?,
+        EntityType::Ensemble => diesel::update(ensembles::table.filter(ensembles::id.eq(id)))
+            .set(ensembles::created_by.eq(username))
+            .execute(conn)?,
+        EntityType::Instrument => diesel::update(instruments::table.filter(instruments::id.eq(id)))
+            .set(instruments::created_by.eq(username))
+            .execute(conn)?,
+        EntityType::Work => diesel::update(works::table.filter(works::id.eq(id)))
+            .set(works::created_by.eq(username))
+            .execute(conn)?,
+        EntityType::Recording => diesel::update(recordings::table.filter(recordings::id.eq(id)))
+            .set(recordings::created_by.eq(username))
+            .execute(conn)?,
+        EntityType::Medium => diesel::update(mediums::table.filter(mediums::id.eq(id)))
+            .set(mediums::created_by.eq(username))
+            .execute(conn)?,
+        EntityType::Label => diesel::update(labels::table.filter(labels::id.eq(id)))
+            .set(labels::created_by.eq(username))
+            .execute(conn)?,
+    };
+
+    Ok(())
+}
+
+/// Get the position of an entity type within the order in which deleted entities are restored.
+fn get_restore_rank(entity_type: EntityType) -> usize {
+    match entity_type {
+        EntityType::Person => 0,
+        EntityType::Ensemble => 1,
+        EntityType::Instrument => 2,
+        EntityType::Label => 3,
+        EntityType::Work => 4,
+        EntityType::Recording => 5,
+        EntityType::Medium => 6,
+    }
+}
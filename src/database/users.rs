@@ -1,6 +1,9 @@
-use super::schema::users;
-use super::DbConn;
-use anyhow::Result;
+use super::schema::{email_changes, former_usernames, users};
+use super::{generate_id, DbConn, DbTransaction};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::exists;
 use diesel::prelude::*;
 use serde::Deserialize;
 
@@ -13,6 +16,12 @@ pub struct User {
     pub is_admin: bool,
     pub is_editor: bool,
     pub is_banned: bool,
+
+    /// Whether the user opted in to storing what they listen to.
+    pub record_plays: bool,
+
+    /// How the password hash was created, see [`PasswordScheme`].
+    pub password_scheme: String,
 }
 
 impl User {
@@ -26,23 +35,168 @@ impl User {
         !self.is_banned && (self.username == creator || self.is_editor)
     }
 
+    /// Check whether the user is allowed to edit an item that may have been locked. Locked items
+    /// can only be edited by editors.
+    pub fn may_edit_item(&self, creator: &str, locked: bool) -> bool {
+        self.may_edit(creator) && (!locked || self.is_editor)
+    }
+
+    /// Check whether the user is allowed to edit a private item. Only its owner may do that.
+    pub fn may_edit_private(&self, owner: &str) -> bool {
+        !self.is_banned && self.username == owner
+    }
+
+    /// Check whether the user is allowed to lock and unlock items.
+    pub fn may_lock(&self) -> bool {
+        !self.is_banned && self.is_editor
+    }
+
+    /// Check whether the user is allowed to judge the quality of the data of entities.
+    pub fn may_set_quality(&self) -> bool {
+        !self.is_banned && self.is_editor
+    }
+
+    /// Check whether the user is allowed to change fields of many entities at once.
+    pub fn may_bulk_edit(&self) -> bool {
+        !self.is_banned && self.is_editor
+    }
+
+    /// Check whether the user is allowed to merge duplicate entities.
+    pub fn may_merge(&self) -> bool {
+        !self.is_banned && self.is_editor
+    }
+
+    /// Check whether the user is allowed to undo changes by restoring earlier revisions.
+    pub fn may_revert(&self) -> bool {
+        !self.is_banned && self.is_editor
+    }
+
+    /// Check whether the user is allowed to hide reviews and delete comments of other users.
+    pub fn may_moderate(&self) -> bool {
+        !self.is_banned && self.is_editor
+    }
+
+    /// Check whether the user is allowed to add, change and remove musical periods.
+    pub fn may_manage_periods(&self) -> bool {
+        !self.is_banned && self.is_editor
+    }
+
+    /// Check whether the user is allowed to confirm or reject matches with external databases
+    /// that were found automatically.
+    pub fn may_review_candidates(&self) -> bool {
+        !self.is_banned && self.is_editor
+    }
+
     /// Check whether the user is allowed to delete an item.
     pub fn may_delete(&self) -> bool {
         !self.is_banned && self.is_editor
     }
+
+    /// Check whether the user is allowed to administrate the server.
+    pub fn may_administrate(&self) -> bool {
+        !self.is_banned && self.is_admin
+    }
+
+    /// Check whether a token for this user may be granted a scope.
+    pub fn may_grant(&self, scope: Scope) -> bool {
+        match scope {
+            Scope::Admin => self.may_administrate(),
+            _ => !self.is_banned,
+        }
+    }
+
+    /// Get all scopes that a token for this user may be granted.
+    pub fn grantable_scopes(&self) -> Vec<Scope> {
+        Scope::ALL
+            .iter()
+            .cloned()
+            .filter(|scope| self.may_grant(*scope))
+            .collect()
+    }
+}
+
+/// A permission that can be granted to a login token or an API key. Independent of the scopes,
+/// the role of the user still limits what the token can be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Read data that is not public, e.g. the user's own account details.
+    Read,
+
+    /// Create, change and delete persons.
+    WritePersons,
+
+    /// Create, change and delete ensembles.
+    WriteEnsembles,
+
+    /// Create, change and delete instruments.
+    WriteInstruments,
+
+    /// Create, change and delete works.
+    WriteWorks,
+
+    /// Create, change and delete recordings.
+    WriteRecordings,
+
+    /// Create, change and delete mediums.
+    WriteMediums,
+
+    /// Create, change and delete musical periods.
+    WritePeriods,
+
+    /// Use administrative functions.
+    Admin,
+}
+
+impl Scope {
+    /// All available scopes.
+    pub const ALL: [Scope; 9] = [
+        Scope::Read,
+        Scope::WritePersons,
+        Scope::WriteEnsembles,
+        Scope::WriteInstruments,
+        Scope::WriteWorks,
+        Scope::WriteRecordings,
+        Scope::WriteMediums,
+        Scope::WritePeriods,
+        Scope::Admin,
+    ];
+
+    /// Get the string representation of the scope.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::WritePersons => "write:persons",
+            Scope::WriteEnsembles => "write:ensembles",
+            Scope::WriteInstruments => "write:instruments",
+            Scope::WriteWorks => "write:works",
+            Scope::WriteRecordings => "write:recordings",
+            Scope::WriteMediums => "write:mediums",
+            Scope::WritePeriods => "write:periods",
+            Scope::Admin => "admin",
+        }
+    }
+
+    /// Get a scope from its string representation.
+    pub fn parse(scope: &str) -> Option<Scope> {
+        Scope::ALL.iter().find(|s| s.as_str() == scope).cloned()
+    }
 }
 
 /// A structure representing data on a user.
-#[derive(AsChangeset, Deserialize, Debug, Clone)]
-#[table_name = "users"]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UserInsertion {
     pub password_hash: String,
     pub email: Option<String>,
 }
 
-/// Insert a new user.
+/// Insert a new user. Names that other users had before being renamed can't be taken again and
+/// result in [`ServerError::Conflict`].
 pub fn insert_user(conn: &DbConn, username: &str, data: &UserInsertion) -> Result<()> {
+    if is_former_username(conn, username)? {
+        return Err(Error::new(ServerError::Conflict));
+    }
+
     let user = User {
         username: username.to_string(),
         password_hash: data.password_hash.clone(),
@@ -50,6 +204,8 @@ pub fn insert_user(conn: &DbConn, username: &str, data: &UserInsertion) -> Resul
         is_admin: false,
         is_editor: false,
         is_banned: false,
+        record_plays: false,
+        password_scheme: PasswordScheme::Argon2id.as_str().to_string(),
     };
     diesel::insert_into(users::table)
         .values(user)
@@ -58,14 +214,44 @@ pub fn insert_user(conn: &DbConn, username: &str, data: &UserInsertion) -> Resul
     Ok(())
 }
 
-/// Update an existing user.
-pub fn update_user(conn: &DbConn, username: &str, data: &UserInsertion) -> Result<()> {
-    diesel::update(users::table)
-        .filter(users::username.eq(username))
-        .set(data)
-        .execute(conn)?;
+/// Change the name of an existing user. All references to the user are updated by the database.
+/// The old name can't be used by anyone afterwards. This will fail, if the new name is already
+/// taken or was used by a user before, and only succeeds, if the provided user is an
+/// administrator.
+pub fn rename_user(conn: &DbConn, old: &str, new: &str, user: &User) -> Result<()> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
 
-    Ok(())
+    conn.transaction(|| {
+        if get_user(conn, new)?.is_some() || is_former_username(conn, new)? {
+            return Err(Error::new(ServerError::Conflict));
+        }
+
+        let count = diesel::update(users::table)
+            .filter(users::username.eq(old))
+            .set(users::username.eq(new))
+            .execute(conn)?;
+
+        if count == 0 {
+            return Err(Error::new(ServerError::NotFound));
+        }
+
+        // Login tokens only contain the name, so it must never refer to another user.
+        diesel::insert_into(former_usernames::table)
+            .values(former_usernames::username.eq(old))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+/// Check whether a user was called like this before being renamed.
+fn is_former_username(conn: &DbConn, username: &str) -> Result<bool> {
+    Ok(diesel::select(exists(
+        former_usernames::table.filter(former_usernames::username.eq(username)),
+    ))
+    .get_result(conn)?)
 }
 
 /// Get an existing user.
@@ -76,3 +262,184 @@ pub fn get_user(conn: &DbConn, username: &str) -> Result<Option<User>> {
         .first()
         .cloned())
 }
+
+/// Check whether there are any users at all. This is not the case on a newly set up server.
+pub fn has_users(conn: &DbConn) -> Result<bool> {
+    Ok(diesel::select(exists(users::table.select(users::username))).get_result(conn)?)
+}
+
+/// Prevent other transactions from adding users until the transaction ends. They wait until then
+/// instead.
+pub fn lock_users(tx: &DbTransaction) -> Result<()> {
+    diesel::sql_query("LOCK TABLE users IN SHARE MODE").execute(tx.conn())?;
+
+    Ok(())
+}
+
+/// A role that an administrator can assign to a user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// The user may administrate the server.
+    Admin,
+
+    /// The user may edit, lock and delete items created by others.
+    Editor,
+
+    /// The user may not change anything.
+    Banned,
+}
+
+impl Role {
+    /// Get a role from its string representation.
+    pub fn parse(role: &str) -> Option<Role> {
+        match role {
+            "admin" => Some(Role::Admin),
+            "editor" => Some(Role::Editor),
+            "banned" => Some(Role::Banned),
+            _ => None,
+        }
+    }
+}
+
+/// Give a role to an existing user or take it away. This fails, if the user doesn't exist.
+pub fn set_user_role(conn: &DbConn, username: &str, role: Role, value: bool) -> Result<()> {
+    let query = diesel::update(users::table).filter(users::username.eq(username));
+
+    let count = match role {
+        Role::Admin => query.set(users::is_admin.eq(value)).execute(conn)?,
+        Role::Editor => query.set(users::is_editor.eq(value)).execute(conn)?,
+        Role::Banned => query.set(users::is_banned.eq(value)).execute(conn)?,
+    };
+
+    if count == 0 {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    Ok(())
+}
+
+/// How the password hash of a user was created. New hashes are always created using the current
+/// scheme, so both [`insert_user`] and [`set_password_hash`] store that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordScheme {
+    /// Argon2id using sodiumoxide with its fixed interactive limits. These hashes are replaced on
+    /// the next login.
+    Sodiumoxide,
+
+    /// Argon2id with configurable parameters.
+    Argon2id,
+}
+
+impl PasswordScheme {
+    /// Get a password scheme from its string representation.
+    pub fn parse(scheme: &str) -> Option<PasswordScheme> {
+        match scheme {
+            "sodiumoxide" => Some(PasswordScheme::Sodiumoxide),
+            "argon2id" => Some(PasswordScheme::Argon2id),
+            _ => None,
+        }
+    }
+
+    /// Get the string representation of the password scheme.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PasswordScheme::Sodiumoxide => "sodiumoxide",
+            PasswordScheme::Argon2id => "argon2id",
+        }
+    }
+}
+
+/// Set a new password hash for an existing user. The hash has to be created using the current
+/// password scheme.
+pub fn set_password_hash(conn: &DbConn, username: &str, password_hash: &str) -> Result<()> {
+    diesel::update(users::table)
+        .filter(users::username.eq(username))
+        .set((
+            users::password_hash.eq(password_hash),
+            users::password_scheme.eq(PasswordScheme::Argon2id.as_str()),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Set or remove the email address of an existing user.
+pub fn set_email(conn: &DbConn, username: &str, email: Option<&str>) -> Result<()> {
+    diesel::update(users::table)
+        .filter(users::username.eq(username))
+        .set(users::email.eq(email))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// How long a requested email change can be confirmed.
+const EMAIL_CHANGE_VALIDITY: i64 = 24;
+
+/// Table data for a requested email change that has not been confirmed yet.
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "email_changes"]
+struct EmailChangeRow {
+    pub token: String,
+    pub username: String,
+    pub email: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Store a requested email change and return the token that is needed to confirm it.
+pub fn insert_email_change(conn: &DbConn, username: &str, email: &str) -> Result<String> {
+    let row = EmailChangeRow {
+        token: generate_id(),
+        username: username.to_string(),
+        email: email.to_string(),
+        created_at: Utc::now().naive_utc(),
+    };
+
+    diesel::insert_into(email_changes::table)
+        .values(&row)
+        .execute(conn)?;
+
+    Ok(row.token)
+}
+
+/// Apply a requested email change. This will return the name of the user whose email address
+/// was changed or [`None`], if the token is unknown or has expired. Other pending changes for
+/// the same user will be discarded.
+pub fn confirm_email_change(conn: &DbConn, token: &str) -> Result<Option<String>> {
+    conn.transaction(|| {
+        let row = email_changes::table
+            .filter(email_changes::token.eq(token))
+            .load::<EmailChangeRow>(conn)?
+            .into_iter()
+            .next();
+
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        diesel::delete(email_changes::table)
+            .filter(email_changes::username.eq(&row.username))
+            .execute(conn)?;
+
+        if row.created_at + Duration::hours(EMAIL_CHANGE_VALIDITY) < Utc::now().naive_utc() {
+            return Ok(None);
+        }
+
+        set_email(conn, &row.username, Some(&row.email))?;
+
+        Ok(Some(row.username))
+    })
+}
+
+/// Delete all requested email changes that have expired. This returns the number of deleted
+/// requests.
+pub fn delete_expired_email_changes(conn: &DbConn) -> Result<usize> {
+    let expired = Utc::now().naive_utc() - Duration::hours(EMAIL_CHANGE_VALIDITY);
+
+    let count = diesel::delete(email_changes::table)
+        .filter(email_changes::created_at.lt(expired))
+        .execute(conn)?;
+
+    Ok(count)
+}
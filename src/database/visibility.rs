@@ -0,0 +1,321 @@
+use super::schema::{
+    ensembles, instrumentations, instruments, labels, mediums, persons, recordings, trash, works,
+};
+use super::User;
+use super::{get_referencing_entities, DbConn, EntityReference, EntityReferences, EntityType};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+
+/// Who is allowed to see an entity.
+#[derive(Debug, Clone)]
+pub struct Visibility {
+    /// Whether only the owner can see the entity.
+    pub private: bool,
+
+    /// The user that last changed the entity. Private entities can only be changed by their
+    /// owner, so this doesn't change for them.
+    pub owner: String,
+}
+
+impl Visibility {
+    /// Check whether a user, if any, may see the entity.
+    pub fn is_visible_to(&self, viewer: Option<&User>) -> bool {
+        self.is_visible_to_name(viewer_name(viewer))
+    }
+
+    /// Check whether the user with the provided name may see the entity. Anonymous viewers are
+    /// represented by an empty name.
+    pub fn is_visible_to_name(&self, username: &str) -> bool {
+        !self.private || username == self.owner
+    }
+}
+
+/// Get the name of the user that is used to include their private entities within queries. For
+/// anonymous requests, this is empty, which doesn't match any user.
+pub fn viewer_name(viewer: Option<&User>) -> &str {
+    viewer.map_or("", |user| user.username.as_str())
+}
+
+/// Get who is allowed to see an entity. This returns [`None`], if the entity doesn't exist.
+pub fn get_visibility(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+) -> Result<Option<Visibility>> {
+    let row: Option<(bool, String)> = match entity_type {
+        EntityType::Person => persons::table
+            .filter(persons::id.eq(id))
+            .select((persons::private, persons::created_by))
+            .first(conn)
+            .optional()?,
+        EntityType::Ensemble => ensembles::table
+            .filter(ensembles::id.eq(id))
+            .select((ensembles::private, ensembles::created_by))
+            .first(conn)
+            .optional()?,
+        EntityType::Instrument => instruments::table
+            .filter(instruments::id.eq(id))
+            .select((instruments::private, instruments::created_by))
+            .first(conn)
+            .optional()?,
+        EntityType::Work => works::table
+            .filter(works::id.eq(id))
+            .select((works::private, works::created_by))
+            .first(conn)
+            .optional()?,
+        EntityType::Recording => recordings::table
+            .filter(recordings::id.eq(id))
+            .select((recordings::private, recordings::created_by))
+            .first(conn)
+            .optional()?,
+        EntityType::Medium => mediums::table
+            .filter(mediums::id.eq(id))
+            .select((mediums::private, mediums::created_by))
+            .first(conn)
+            .optional()?,
+        EntityType::Label => labels::table
+            .filter(labels::id.eq(id))
+            .select((labels::private, labels::created_by))
+            .first(conn)
+            .optional()?,
+    };
+
+    Ok(row.map(|(private, owner)| Visibility { private, owner }))
+}
+
+/// Get who was allowed to see an entity like [`get_visibility`]. For deleted entities, this uses
+/// their latest snapshot within the trash. This returns [`None`], if the entity neither exists
+/// nor is within the trash.
+pub fn get_last_visibility(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+) -> Result<Option<Visibility>> {
+    if let Some(visibility) = get_visibility(conn, entity_type, id)? {
+        return Ok(Some(visibility));
+    }
+
+    let row: Option<(String, String)> = trash::table
+        .filter(trash::entity_type.eq(entity_type.as_str()))
+        .filter(trash::entity_id.eq(id))
+        .order_by(trash::id.desc())
+        .select((trash::data, trash::created_by))
+        .first(conn)
+        .optional()?;
+
+    match row {
+        Some((data, owner)) => {
+            let data: serde_json::Value = serde_json::from_str(&data)?;
+            let private = data["private"].as_bool().unwrap_or(false);
+
+            Ok(Some(Visibility { private, owner }))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Check whether a user, if any, may learn about changes to an entity, e.g. through
+/// notifications or webhooks. Unlike [`is_entity_visible`], this also works for deleted entities
+/// that are still within the trash. Changes to entities that are gone entirely are not visible.
+pub fn is_change_visible(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    viewer: Option<&User>,
+) -> Result<bool> {
+    Ok(get_last_visibility(conn, entity_type, id)?
+        .is_some_and(|visibility| visibility.is_visible_to(viewer)))
+}
+
+/// Get those of the provided IDs that belong to existing entities a user, if any, may see. This
+/// uses a single query regardless of the number of IDs.
+pub fn get_visible_ids(
+    conn: &DbConn,
+    entity_type: EntityType,
+    ids: &[String],
+    viewer: Option<&User>,
+) -> Result<Vec<String>> {
+    let viewer = viewer_name(viewer);
+
+    let visible = match entity_type {
+        EntityType::Person => persons::table
+            .filter(persons::id.eq_any(ids))
+            .filter(
+                persons::private
+                    .eq(false)
+                    .or(persons::created_by.eq(viewer)),
+            )
+            .select(persons::id)
+            .load(conn)?,
+        EntityType::Ensemble => ensembles::table
+            .filter(ensembles::id.eq_any(ids))
+            .filter(
+                ensembles::private
+                    .eq(false)
+                    .or(ensembles::created_by.eq(viewer)),
+            )
+            .select(ensembles::id)
+            .load(conn)?,
+        EntityType::Instrument => instruments::table
+            .filter(instruments::id.eq_any(ids))
+            .filter(
+                instruments::private
+                    .eq(false)
+                    .or(instruments::created_by.eq(viewer)),
+            )
+            .select(instruments::id)
+            .load(conn)?,
+        EntityType::Work => works::table
+            .filter(works::id.eq_any(ids))
+            .filter(works::private.eq(false).or(works::created_by.eq(viewer)))
+            .select(works::id)
+            .load(conn)?,
+        EntityType::Recording => recordings::table
+            .filter(recordings::id.eq_any(ids))
+            .filter(
+                recordings::private
+                    .eq(false)
+                    .or(recordings::created_by.eq(viewer)),
+            )
+            .select(recordings::id)
+            .load(conn)?,
+        EntityType::Medium => mediums::table
+            .filter(mediums::id.eq_any(ids))
+            .filter(
+                mediums::private
+                    .eq(false)
+                    .or(mediums::created_by.eq(viewer)),
+            )
+            .select(mediums::id)
+            .load(conn)?,
+        EntityType::Label => labels::table
+            .filter(labels::id.eq_any(ids))
+            .filter(labels::private.eq(false).or(labels::created_by.eq(viewer)))
+            .select(labels::id)
+            .load(conn)?,
+    };
+
+    Ok(visible)
+}
+
+/// Check whether an entity exists and a user, if any, may see it.
+pub fn is_entity_visible(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    viewer: Option<&User>,
+) -> Result<bool> {
+    Ok(get_visibility(conn, entity_type, id)?
+        .is_some_and(|visibility| visibility.is_visible_to(viewer)))
+}
+
+/// Check whether a user may delete an entity. Editors may delete all entities they can see and
+/// other users may delete their own private entities.
+pub fn may_delete_entity(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+) -> Result<bool> {
+    let allowed = match get_visibility(conn, entity_type, id)? {
+        Some(visibility) if visibility.private => {
+            !user.is_banned && visibility.owner == user.username
+        }
+        _ => user.may_delete(),
+    };
+
+    Ok(allowed)
+}
+
+/// Check whether a user may edit an entity. Private entities may only be edited by their owner.
+/// This fails with not found, if the entity doesn't exist.
+pub fn may_edit_entity(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+) -> Result<bool> {
+    let visibility =
+        get_visibility(conn, entity_type, id)?.ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+    let allowed = if visibility.private {
+        user.may_edit_private(&visibility.owner)
+    } else {
+        user.may_edit(&visibility.owner)
+    };
+
+    Ok(allowed)
+}
+
+/// Check that an entity may refer to another one. Private entities may only be referred to by
+/// private entities of the same user, so that they never become part of public entities.
+pub fn check_reference(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    private: bool,
+    user: &User,
+) -> Result<()> {
+    if let Some(visibility) = get_visibility(conn, entity_type, id)? {
+        if visibility.private && (!private || visibility.owner != user.username) {
+            return Err(Error::new(ServerError::BadRequest));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that an existing public entity may become private. This is only possible for the user
+/// that last changed it and only if all entities referring to it are private entities of the same
+/// user. Otherwise, this fails with a conflict listing the entities that refer to it.
+pub fn check_may_become_private(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+) -> Result<()> {
+    match get_visibility(conn, entity_type, id)? {
+        Some(visibility) if visibility.owner != user.username => {
+            return Err(Error::new(ServerError::Forbidden));
+        }
+        _ => (),
+    }
+
+    let mut referencing = get_referencing_entities(conn, entity_type, id)?;
+
+    // The instrumentation of a work is not a reason to keep an instrument from being deleted, but
+    // it is part of the public work.
+    if entity_type == EntityType::Instrument {
+        let works: Vec<String> = instrumentations::table
+            .filter(instrumentations::instrument.eq(id))
+            .select(instrumentations::work)
+            .load(conn)?;
+
+        referencing.extend(works.into_iter().map(|entity_id| EntityReference {
+            entity_type: EntityType::Work,
+            entity_id,
+        }));
+
+        referencing.sort();
+        referencing.dedup();
+    }
+
+    let mut references = Vec::new();
+
+    for reference in referencing {
+        let visibility = get_visibility(conn, reference.entity_type, &reference.entity_id)?;
+
+        if !matches!(visibility, Some(v) if v.private && v.owner == user.username) {
+            references.push(reference);
+        }
+    }
+
+    if references.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(ServerError::Referenced(EntityReferences {
+            references,
+        })))
+    }
+}
@@ -0,0 +1,123 @@
+use super::schema::watches;
+use super::{is_entity_visible, DbConn, EntityType, User};
+use crate::error::ServerError;
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// An entity a user wants to be notified about when it changes.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Watch {
+    pub entity_type: EntityType,
+    pub entity_id: String,
+
+    /// Whether notifications are also sent by mail.
+    pub email: bool,
+
+    pub created_at: NaiveDateTime,
+}
+
+/// Table data for a [`Watch`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "watches"]
+struct WatchRow {
+    pub username: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub email: bool,
+    pub created_at: NaiveDateTime,
+}
+
+/// Start watching an entity or change whether notifications are sent by mail.
+pub fn set_watch(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    email: bool,
+    user: &User,
+) -> Result<()> {
+    if !is_entity_visible(conn, entity_type, entity_id, Some(user))? {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    let row = WatchRow {
+        username: user.username.clone(),
+        entity_type: entity_type.as_str().to_string(),
+        entity_id: entity_id.to_string(),
+        email,
+        created_at: Utc::now().naive_utc(),
+    };
+
+    diesel::insert_into(watches::table)
+        .values(&row)
+        .on_conflict((watches::username, watches::entity_type, watches::entity_id))
+        .do_update()
+        .set(watches::email.eq(email))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Stop watching an entity.
+pub fn delete_watch(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+    user: &User,
+) -> Result<()> {
+    let count = diesel::delete(watches::table)
+        .filter(watches::username.eq(&user.username))
+        .filter(watches::entity_type.eq(entity_type.as_str()))
+        .filter(watches::entity_id.eq(entity_id))
+        .execute(conn)?;
+
+    if count == 0 {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    Ok(())
+}
+
+/// Get all entities a user is watching, newest first.
+pub fn get_watches(conn: &DbConn, user: &User) -> Result<Vec<Watch>> {
+    watches::table
+        .filter(watches::username.eq(&user.username))
+        .order_by(watches::created_at.desc())
+        .load::<WatchRow>(conn)?
+        .into_iter()
+        .map(|row| {
+            Ok(Watch {
+                entity_type: EntityType::parse(&row.entity_type)
+                    .ok_or_else(|| anyhow!("Unknown entity type: {}", row.entity_type))?,
+                entity_id: row.entity_id,
+                email: row.email,
+                created_at: row.created_at,
+            })
+        })
+        .collect()
+}
+
+/// Get the users watching an entity together with whether they want to receive mails.
+pub fn get_watchers(
+    conn: &DbConn,
+    entity_type: EntityType,
+    entity_id: &str,
+) -> Result<Vec<(String, bool)>> {
+    Ok(watches::table
+        .filter(watches::entity_type.eq(entity_type.as_str()))
+        .filter(watches::entity_id.eq(entity_id))
+        .select((watches::username, watches::email))
+        .load(conn)?)
+}
+
+/// Remove all watches of an entity, e.g. because it was deleted.
+pub fn delete_watches(conn: &DbConn, entity_type: EntityType, entity_id: &str) -> Result<()> {
+    diesel::delete(watches::table)
+        .filter(watches::entity_type.eq(entity_type.as_str()))
+        .filter(watches::entity_id.eq(entity_id))
+        .execute(conn)?;
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use super::schema::webhooks;
+use super::{generate_id, get_last_event_id, DbConn, EntityType, Event, EventKind, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use rand::Rng;
+use serde::Serialize;
+
+/// A URL that gets notified about changes to entities. Empty filters match everything.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub entity_types: Vec<EntityType>,
+    pub kinds: Vec<EventKind>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+
+    /// The ID of the last event that was delivered or skipped.
+    pub last_event: i64,
+
+    /// The number of failed deliveries since the last successful one.
+    pub failures: i32,
+}
+
+/// Table data for a [`Webhook`].
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[table_name = "webhooks"]
+struct WebhookRow {
+    pub id: String,
+    pub url: String,
+    pub secret: String,
+    pub entity_types: String,
+    pub kinds: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub last_event: i64,
+    pub failures: i32,
+    pub next_attempt: NaiveDateTime,
+}
+
+impl From<WebhookRow> for Webhook {
+    fn from(row: WebhookRow) -> Webhook {
+        Webhook {
+            id: row.id,
+            url: row.url,
+            entity_types: row
+                .entity_types
+                .split(',')
+                .filter_map(EntityType::parse)
+                .collect(),
+            kinds: row.kinds.split(',').filter_map(EventKind::parse).collect(),
+            created_by: row.created_by,
+            created_at: row.created_at,
+            last_event: row.last_event,
+            failures: row.failures,
+        }
+    }
+}
+
+impl Webhook {
+    /// Check whether the webhook should be notified about an event.
+    pub fn matches(&self, event: &Event) -> bool {
+        (self.entity_types.is_empty() || self.entity_types.contains(&event.entity_type))
+            && (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+    }
+}
+
+/// Register a new webhook. It will be notified about all matching events that happen after its
+/// creation. This returns the webhook as well as the secret that is used to sign the payloads,
+/// which can't be retrieved later. The user has to be an administrator.
+pub fn insert_webhook(
+    conn: &DbConn,
+    url: &str,
+    entity_types: &[EntityType],
+    kinds: &[EventKind],
+    user: &User,
+) -> Result<(Webhook, String)> {
+    if !user.may_administrate() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(Error::new(ServerError::BadRequest));
+    }
+
+    let secret: [u8; 32] = rand::thread_rng().gen();
+    let secret = base64::encode_config(secret, base64::URL_SAFE_NO_PAD);
+    let now = Utc::now().naive_utc();
+
+    let row = WebhookRow {
+        id: generate_id(),
+        url: url.to_string(),
+        secret: secret.clone(),
+        entity_types: entity_types
+            .iter()
+            .map(|t| t.as_str())
+            .collect::<Vec<&str>>()
+            .join(","),
+        kinds: kinds
+            .iter()
+            .map(|k| k.as_str())
+            .collect::<Vec<&str>>()
+            .join(","),
+        created_by: user.username.clone(),
+        created_at: now,
+        last_event: get_last_event_id(conn)?,
+        failures: 0,
+        next_attempt: now,
+    };
+
+    diesel::insert_into(webhooks::table)
+        .values(&row)
+        .execute(conn)?;
+
+    Ok((row.into(), secret))
+}
+
+/// Get all registered webhooks. The user has to be an administrator.
+pub fn get_webhooks(conn: &DbConn, user: &User) -> Result<Vec<Webhook>> {
+    if user.may_administrate() {
+        let rows = webhooks::table
+            .order(webhooks::created_at.asc())
+            .load::<WebhookRow>(conn)?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Delete a webhook. The user has to be an administrator.
+pub fn delete_webhook(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+    if user.may_administrate() {
+        let count = diesel::delete(webhooks::table.filter(webhooks::id.eq(id))).execute(conn)?;
+
+        if count == 1 {
+            Ok(())
+        } else {
+            Err(Error::new(ServerError::NotFound))
+        }
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Get all webhooks that are due for a delivery attempt together with their secrets.
+pub fn get_due_webhooks(conn: &DbConn) -> Result<Vec<(Webhook, String)>> {
+    let rows = webhooks::table
+        .filter(webhooks::next_attempt.le(Utc::now().naive_utc()))
+        .load::<WebhookRow>(conn)?;
+
+    let webhooks = rows
+        .into_iter()
+        .map(|row| {
+            let secret = row.secret.clone();
+            (row.into(), secret)
+        })
+        .collect();
+
+    Ok(webhooks)
+}
+
+/// Remember that all events up to and including `last_event` were handled for a webhook.
+pub fn set_webhook_delivered(conn: &DbConn, id: &str, last_event: i64) -> Result<()> {
+    diesel::update(webhooks::table.filter(webhooks::id.eq(id)))
+        .set((
+            webhooks::last_event.eq(last_event),
+            webhooks::failures.eq(0),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Remember a failed delivery for a webhook and postpone the next attempt. The delay doubles with
+/// each consecutive failure up to a maximum of one day.
+pub fn set_webhook_failed(conn: &DbConn, id: &str, failures: i32) -> Result<()> {
+    let delay =
+        Duration::seconds(2i64.pow(failures.clamp(0, 16) as u32) * 10).min(Duration::days(1));
+
+    diesel::update(webhooks::table.filter(webhooks::id.eq(id)))
+        .set((
+            webhooks::failures.eq(failures),
+            webhooks::next_attempt.eq(Utc::now().naive_utc() + delay),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use super::schema::{external_ids, persons, wikidata_candidates, wikidata_checks};
+use super::{get_external_ids, get_person, update_external_ids, with_transaction};
+use super::{DbConn, EntityType, ExternalId, ExternalSource, Person, User};
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::{exists, not};
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// An item on Wikidata describing a human.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WikidataItem {
+    /// The ID of the item like "Q254".
+    pub id: String,
+
+    pub label: String,
+    pub description: Option<String>,
+    pub birth_year: Option<i32>,
+    pub death_year: Option<i32>,
+}
+
+/// A Wikidata item that might describe a person, waiting for confirmation by an editor.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WikidataCandidate {
+    pub id: i64,
+    pub person: Person,
+    pub item: WikidataItem,
+    pub created_at: NaiveDateTime,
+}
+
+/// Table data for a [`WikidataCandidate`].
+#[derive(Queryable, Debug, Clone)]
+struct WikidataCandidateRow {
+    pub id: i64,
+    pub person: String,
+    pub item: String,
+    pub label: String,
+    pub description: Option<String>,
+    pub birth_year: Option<i32>,
+    pub death_year: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Table data for a new [`WikidataCandidate`]. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "wikidata_candidates"]
+struct NewWikidataCandidateRow {
+    pub person: String,
+    pub item: String,
+    pub label: String,
+    pub description: Option<String>,
+    pub birth_year: Option<i32>,
+    pub death_year: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Get up to `limit` public persons that have neither been searched on Wikidata yet nor have any
+/// external IDs.
+pub fn get_unreconciled_persons(conn: &DbConn, limit: i64) -> Result<Vec<Person>> {
+    let ids = persons::table
+        .filter(persons::private.eq(false))
+        .filter(not(exists(
+            wikidata_checks::table.filter(wikidata_checks::person.eq(persons::id)),
+        )))
+        .filter(not(exists(
+            external_ids::table.filter(external_ids::person.eq(persons::id.nullable())),
+        )))
+        .order_by(persons::id)
+        .limit(limit)
+        .select(persons::id)
+        .load::<String>(conn)?;
+
+    let mut persons = Vec::new();
+
+    for id in ids {
+        if let Some(person) = get_person(conn, &id)? {
+            persons.push(person);
+        }
+    }
+
+    Ok(persons)
+}
+
+/// Remember that a person was searched on Wikidata and store the items that were found as
+/// candidates. Candidates that already exist are kept.
+pub fn save_wikidata_candidates(conn: &DbConn, person: &str, items: &[WikidataItem]) -> Result<()> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+        let now = Utc::now().naive_utc();
+
+        diesel::insert_into(wikidata_checks::table)
+            .values((
+                wikidata_checks::person.eq(person),
+                wikidata_checks::checked_at.eq(now),
+            ))
+            .on_conflict(wikidata_checks::person)
+            .do_update()
+            .set(wikidata_checks::checked_at.eq(now))
+            .execute(conn)?;
+
+        let rows: Vec<NewWikidataCandidateRow> = items
+            .iter()
+            .map(|item| NewWikidataCandidateRow {
+                person: person.to_string(),
+                item: item.id.clone(),
+                label: item.label.clone(),
+                description: item.description.clone(),
+                birth_year: item.birth_year,
+                death_year: item.death_year,
+                created_at: now,
+            })
+            .collect();
+
+        diesel::insert_into(wikidata_candidates::table)
+            .values(&rows)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+/// Get all candidates waiting for confirmation grouped by person. The user has to be allowed to
+/// review them.
+pub fn get_wikidata_candidates(conn: &DbConn, user: &User) -> Result<Vec<WikidataCandidate>> {
+    if !user.may_review_candidates() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let rows = wikidata_candidates::table
+        .order_by((wikidata_candidates::person, wikidata_candidates::id))
+        .load::<WikidataCandidateRow>(conn)?;
+
+    let mut candidates = Vec::new();
+
+    for row in rows {
+        if let Some(person) = get_person(conn, &row.person)? {
+            candidates.push(WikidataCandidate {
+                id: row.id,
+                person,
+                item: WikidataItem {
+                    id: row.item,
+                    label: row.label,
+                    description: row.description,
+                    birth_year: row.birth_year,
+                    death_year: row.death_year,
+                },
+                created_at: row.created_at,
+            });
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Link a person to the item of a candidate. All other candidates for the person are discarded.
+/// This fails with a conflict, if the item already belongs to another entity.
+pub fn confirm_wikidata_candidate(conn: &DbConn, id: i64, user: &User) -> Result<()> {
+    if !user.may_review_candidates() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        let row = wikidata_candidates::table
+            .filter(wikidata_candidates::id.eq(id))
+            .first::<WikidataCandidateRow>(conn)
+            .optional()?
+            .ok_or(ServerError::NotFound)?;
+
+        let mut ids = get_external_ids(conn, EntityType::Person, &row.person)?;
+        ids.retain(|external_id| external_id.source != ExternalSource::Wikidata);
+        ids.push(ExternalId {
+            source: ExternalSource::Wikidata,
+            id: row.item,
+        });
+
+        update_external_ids(conn, EntityType::Person, &row.person, &ids)?;
+
+        diesel::delete(wikidata_candidates::table)
+            .filter(wikidata_candidates::person.eq(&row.person))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+/// Discard a candidate, because it describes somebody else.
+pub fn reject_wikidata_candidate(conn: &DbConn, id: i64, user: &User) -> Result<()> {
+    if !user.may_review_candidates() {
+        return Err(Error::new(ServerError::Forbidden));
+    }
+
+    let deleted = diesel::delete(wikidata_candidates::table)
+        .filter(wikidata_candidates::id.eq(id))
+        .execute(conn)?;
+
+    if deleted == 0 {
+        return Err(Error::new(ServerError::NotFound));
+    }
+
+    Ok(())
+}
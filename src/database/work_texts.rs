@@ -0,0 +1,178 @@
+use super::schema::{work_parts, work_texts, works};
+use super::{insert_event, may_edit_work, with_transaction, DbConn, EntityType, EventKind, User};
+use crate::error::ServerError;
+use crate::validation::{FieldError, ValidationErrors};
+use anyhow::{Error, Result};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The sung text of a work or of one of its parts in one language.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkText {
+    /// The index of the part the text belongs to. If this is missing, the text belongs to the
+    /// whole work.
+    #[serde(default)]
+    pub part_index: Option<i64>,
+
+    /// The language of the text as a code like "de" or "en-GB".
+    pub language: String,
+
+    /// Whether this is a translation of the original text.
+    #[serde(default)]
+    pub translation: bool,
+
+    pub text: String,
+}
+
+/// All texts of one work, e.g. within dumps.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkTexts {
+    pub work: String,
+    pub texts: Vec<WorkText>,
+}
+
+/// Table data for a new [`WorkText`]. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "work_texts"]
+struct NewWorkTextRow {
+    pub work: String,
+    pub part_index: Option<i64>,
+    pub language: String,
+    pub translation: bool,
+    pub text: String,
+}
+
+/// Get all texts of a work in the order they were provided.
+pub fn get_work_texts(conn: &DbConn, work_id: &str) -> Result<Vec<WorkText>> {
+    let rows = work_texts::table
+        .filter(work_texts::work.eq(work_id))
+        .order_by(work_texts::id)
+        .select((
+            work_texts::part_index,
+            work_texts::language,
+            work_texts::translation,
+            work_texts::text,
+        ))
+        .load::<(Option<i64>, String, bool, String)>(conn)?;
+
+    let texts = rows
+        .into_iter()
+        .map(|(part_index, language, translation, text)| WorkText {
+            part_index,
+            language,
+            translation,
+            text,
+        })
+        .collect();
+
+    Ok(texts)
+}
+
+/// Get the texts of all public works that have any, ordered by work.
+pub fn get_public_work_texts(conn: &DbConn) -> Result<Vec<WorkTexts>> {
+    let rows = work_texts::table
+        .inner_join(works::table)
+        .filter(works::private.eq(false))
+        .order_by((work_texts::work, work_texts::id))
+        .select((
+            work_texts::work,
+            work_texts::part_index,
+            work_texts::language,
+            work_texts::translation,
+            work_texts::text,
+        ))
+        .load::<(String, Option<i64>, String, bool, String)>(conn)?;
+
+    let mut all_texts: Vec<WorkTexts> = Vec::new();
+
+    for (work, part_index, language, translation, text) in rows {
+        if all_texts.last().map(|texts| &texts.work) != Some(&work) {
+            all_texts.push(WorkTexts {
+                work,
+                texts: Vec::new(),
+            });
+        }
+
+        if let Some(texts) = all_texts.last_mut() {
+            texts.texts.push(WorkText {
+                part_index,
+                language,
+                translation,
+                text,
+            });
+        }
+    }
+
+    Ok(all_texts)
+}
+
+/// Replace all texts of a work. This will only work, if the user is allowed to edit the work and
+/// all texts refer to existing parts.
+pub fn update_work_texts(
+    conn: &DbConn,
+    work_id: &str,
+    texts: &[WorkText],
+    user: &User,
+) -> Result<()> {
+    with_transaction(conn, |tx| {
+        let conn = tx.conn();
+
+        if !may_edit_work(conn, work_id, user)? {
+            return Err(Error::new(ServerError::Forbidden));
+        }
+
+        replace_work_texts(conn, work_id, texts)?;
+        insert_event(conn, EntityType::Work, work_id, EventKind::Update, user)?;
+
+        Ok(())
+    })
+}
+
+/// Replace all texts of an existing work without checking permissions or recording an event, e.g.
+/// when restoring the work. Texts referring to parts that don't exist result in
+/// [`ServerError::Invalid`].
+pub fn replace_work_texts(conn: &DbConn, work_id: &str, texts: &[WorkText]) -> Result<()> {
+    let parts: i64 = work_parts::table
+        .filter(work_parts::work.eq(work_id))
+        .count()
+        .get_result(conn)?;
+
+    let errors: Vec<FieldError> = texts
+        .iter()
+        .enumerate()
+        .filter(|(_, text)| matches!(text.part_index, Some(index) if index < 0 || index >= parts))
+        .map(|(index, _)| FieldError {
+            field: format!("texts[{}].partIndex", index),
+            message: "Must refer to an existing part of the work.".to_string(),
+        })
+        .collect();
+
+    if !errors.is_empty() {
+        return Err(Error::new(ServerError::Invalid(ValidationErrors {
+            errors,
+        })));
+    }
+
+    diesel::delete(work_texts::table.filter(work_texts::work.eq(work_id))).execute(conn)?;
+
+    let rows: Vec<NewWorkTextRow> = texts
+        .iter()
+        .map(|text| NewWorkTextRow {
+            work: work_id.to_string(),
+            part_index: text.part_index,
+            language: text.language.clone(),
+            translation: text.translation,
+            text: text.text.clone(),
+        })
+        .collect();
+
+    if !rows.is_empty() {
+        diesel::insert_into(work_texts::table)
+            .values(&rows)
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
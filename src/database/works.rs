@@ -1,51 +1,221 @@
-use super::schema::{instrumentations, work_parts, work_sections, works};
-use super::{get_instrument, get_person, update_instrument, update_person};
-use super::{DbConn, Instrument, Person, User};
+use super::schema::{instrumentations, work_authors, work_part_titles, work_parts};
+use super::schema::{work_section_titles, work_sections, work_texts, work_titles, works};
+use super::{
+    check_may_become_private, check_period, check_quota, check_reference, check_unreferenced,
+    insert_event, may_delete_entity, move_to_trash, normalize_text, viewer_name, with_transaction,
+    DbConn, DbTransaction, EntityType, EventKind, Instrument, Person, User,
+};
+use super::{get_instrument, get_person, update_instrument_in, update_person_in};
+use super::{get_mediums_for_recording, get_recordings_for_work, Medium, Recording};
 use crate::error::ServerError;
 use anyhow::{anyhow, Error, Result};
 use diesel::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+
+/// The base URL of pages on IMSLP.
+const IMSLP_URL: &str = "https://imslp.org/wiki/";
+
+/// A work together with all of its recordings and the mediums containing them.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FullWork {
+    #[serde(flatten)]
+    pub work: Work,
+
+    pub recordings: Vec<FullRecording>,
+}
+
+/// A recording within a [`FullWork`] together with the mediums containing it.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FullRecording {
+    #[serde(flatten)]
+    pub recording: Recording,
+
+    pub mediums: Vec<Medium>,
+}
 
 /// A specific work by a composer.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Work {
+    /// Clients may leave this out for new works to get a generated ID.
+    #[serde(default)]
     pub id: String,
     pub title: String,
+
+    /// The name the work is popularly known by, e.g. "Moonlight Sonata" or "Eroica". Searches
+    /// rank works with a matching nickname first.
+    #[serde(default)]
+    pub nickname: Option<String>,
+
+    /// Titles of the work in different languages. The title above is the canonical one, which is
+    /// used if there is no title in the requested language.
+    #[serde(default)]
+    pub titles: Vec<WorkTitle>,
+
     pub composer: Person,
+
+    /// The authors of the text of the whole work, e.g. the librettist of an opera.
+    #[serde(default)]
+    pub authors: Vec<Person>,
+
     pub instruments: Vec<Instrument>,
     pub parts: Vec<WorkPart>,
     pub sections: Vec<WorkSection>,
+
+    /// The first performance of the work, if anything is known about it.
+    #[serde(default)]
+    pub premiere: Option<Premiere>,
+
+    /// To whom the work is dedicated.
+    #[serde(default)]
+    pub dedication: Option<String>,
+
+    /// The ID of the musical period the work belongs to, if it differs from the composer's or
+    /// should be stated explicitly.
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// The title of the page of the work on IMSLP, where scores of the work can be found, e.g.
+    /// "Symphony_No.5,_Op.67_(Beethoven,_Ludwig_van)".
+    #[serde(default)]
+    pub imslp: Option<String>,
+
+    /// Whether the work can only be edited by editors. This is ignored on updates.
+    #[serde(default)]
+    pub locked: bool,
+
+    /// Whether the work is only visible to the user that created it. Private works may only refer
+    /// to public persons and instruments or to private ones of the same user.
+    #[serde(default)]
+    pub private: bool,
+}
+
+impl Work {
+    /// Get the URL of the page of the work on IMSLP, if it is known.
+    pub fn imslp_url(&self) -> Option<String> {
+        let page = self.imslp.as_ref()?;
+        let mut url = String::from(IMSLP_URL);
+
+        for byte in page.bytes() {
+            if byte.is_ascii_alphanumeric() || b"-_.~,()':/!".contains(&byte) {
+                url.push(byte as char);
+            } else {
+                url.push_str(&format!("%{:02X}", byte));
+            }
+        }
+
+        Some(url)
+    }
+}
+
+/// The title of a work or of one of its parts or sections in a specific language.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkTitle {
+    /// The language code of the title like "de" or "en-GB".
+    pub language: String,
+
+    pub title: String,
+
+    /// Whether this is the title the composer used.
+    #[serde(default)]
+    pub original: bool,
+}
+
+/// Information on the first performance of a work. All fields are optional, because often only
+/// some of them are known.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Premiere {
+    /// The date of the premiere like "1808-12-22". The month and day may be left out, if they are
+    /// unknown.
+    #[serde(default)]
+    pub date: Option<String>,
+
+    /// Where the premiere took place.
+    #[serde(default)]
+    pub place: Option<String>,
+
+    /// Who performed the work at its premiere.
+    #[serde(default)]
+    pub performers: Option<String>,
 }
 
 /// A playable part of a work.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WorkPart {
     pub title: String,
+
+    /// Titles of the part in different languages.
+    #[serde(default)]
+    pub titles: Vec<WorkTitle>,
+
+    /// The key of the part, e.g. "C minor".
+    #[serde(default)]
+    pub key: Option<String>,
+
+    /// The tempo marking of the part, e.g. "Allegro con brio".
+    #[serde(default)]
+    pub tempo: Option<String>,
+
+    /// How long the part usually takes in milliseconds.
+    #[serde(default)]
+    pub duration: Option<i32>,
+
+    /// The authors of the text of this part only, e.g. the poet of a song within a cycle.
+    #[serde(default)]
+    pub authors: Vec<Person>,
 }
 
 /// A heading within the work structure.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WorkSection {
     pub title: String,
+
+    /// Titles of the section in different languages.
+    #[serde(default)]
+    pub titles: Vec<WorkTitle>,
+
     pub before_index: i64,
 }
 
 /// Table data for a work.
-#[derive(Insertable, Queryable, Debug, Clone)]
+#[derive(Insertable, Queryable, Identifiable, AsChangeset, Debug, Clone)]
 #[table_name = "works"]
+#[changeset_options(treat_none_as_null = "true")]
 struct WorkRow {
     pub id: String,
     pub composer: String,
     pub title: String,
     pub created_by: String,
+    pub locked: bool,
+    pub private: bool,
+    pub premiere_date: Option<String>,
+    pub premiere_place: Option<String>,
+    pub premiere_performers: Option<String>,
+    pub dedication: Option<String>,
+    pub period: Option<String>,
+    pub imslp: Option<String>,
+    pub nickname: Option<String>,
+}
+
+/// Table data for a new instrumentation. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "instrumentations"]
+struct NewInstrumentationRow {
+    pub work: String,
+    pub instrument: String,
 }
 
 /// Table data for an instrumentation.
-#[derive(Insertable, Queryable, Debug, Clone)]
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(WorkRow, foreign_key = "work")]
 #[table_name = "instrumentations"]
 struct InstrumentationRow {
     pub id: i64,
@@ -53,19 +223,136 @@ struct InstrumentationRow {
     pub instrument: String,
 }
 
+/// Table data for a new work part. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "work_parts"]
+struct NewWorkPartRow {
+    pub work: String,
+    pub part_index: i64,
+    pub title: String,
+    pub key: Option<String>,
+    pub tempo: Option<String>,
+    pub duration: Option<i32>,
+}
+
 /// Table data for a work part.
-#[derive(Insertable, Queryable, Debug, Clone)]
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(WorkRow, foreign_key = "work")]
 #[table_name = "work_parts"]
 struct WorkPartRow {
     pub id: i64,
     pub work: String,
     pub part_index: i64,
     pub title: String,
+    pub key: Option<String>,
+    pub tempo: Option<String>,
+    pub duration: Option<i32>,
+}
+
+/// Table data for a new text author. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "work_authors"]
+struct NewWorkAuthorRow {
+    pub work: String,
+    pub part_index: Option<i64>,
+    pub person: String,
+}
+
+/// Table data for a text author. If there is no part index, the person is an author of the whole
+/// work.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(WorkRow, foreign_key = "work")]
+#[table_name = "work_authors"]
+struct WorkAuthorRow {
+    pub id: i64,
+    pub work: String,
+    pub part_index: Option<i64>,
+    pub person: String,
+}
+
+/// Table data for a new work title. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "work_titles"]
+struct NewWorkTitleRow {
+    pub work: String,
+    pub language: String,
+    pub title: String,
+    pub original: bool,
+}
+
+/// Table data for a work title.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(WorkRow, foreign_key = "work")]
+#[table_name = "work_titles"]
+struct WorkTitleRow {
+    pub id: i64,
+    pub work: String,
+    pub language: String,
+    pub title: String,
+    pub original: bool,
+}
+
+/// Table data for a new title of a work part. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "work_part_titles"]
+struct NewWorkPartTitleRow {
+    pub work: String,
+    pub part_index: i64,
+    pub language: String,
+    pub title: String,
+    pub original: bool,
+}
+
+/// Table data for a title of a work part.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(WorkRow, foreign_key = "work")]
+#[table_name = "work_part_titles"]
+struct WorkPartTitleRow {
+    pub id: i64,
+    pub work: String,
+    pub part_index: i64,
+    pub language: String,
+    pub title: String,
+    pub original: bool,
+}
+
+/// Table data for a new title of a work section. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "work_section_titles"]
+struct NewWorkSectionTitleRow {
+    pub work: String,
+    pub section_index: i64,
+    pub language: String,
+    pub title: String,
+    pub original: bool,
+}
+
+/// Table data for a title of a work section.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(WorkRow, foreign_key = "work")]
+#[table_name = "work_section_titles"]
+struct WorkSectionTitleRow {
+    pub id: i64,
+    pub work: String,
+    pub section_index: i64,
+    pub language: String,
+    pub title: String,
+    pub original: bool,
+}
+
+/// Table data for a new work section. The ID will be assigned by the database.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "work_sections"]
+struct NewWorkSectionRow {
+    pub work: String,
+    pub title: String,
+    pub before_index: i64,
 }
 
 /// Table data for a work section.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(WorkRow, foreign_key = "work")]
 #[table_name = "work_sections"]
-#[derive(Insertable, Queryable, Debug, Clone)]
 struct WorkSectionRow {
     pub id: i64,
     pub work: String,
@@ -76,90 +363,387 @@ struct WorkSectionRow {
 /// Update an existing work or insert a new one. This will only succeed, if the user is allowed to
 /// do that.
 pub fn update_work(conn: &DbConn, work: &Work, user: &User) -> Result<()> {
-    conn.transaction::<(), Error, _>(|| {
-        let old_row = get_work_row(conn, &work.id)?;
+    with_transaction(conn, |tx| update_work_in(tx, work, user))
+}
+
+/// Update an existing work or insert a new one as part of a larger change. See
+/// [`update_work`].
+pub fn update_work_in(tx: &DbTransaction, work: &Work, user: &User) -> Result<()> {
+    let conn = tx.conn();
+
+    let old_row = get_work_row(conn, &work.id)?;
+    let kind = if old_row.is_some() {
+        EventKind::Update
+    } else {
+        EventKind::Create
+    };
+
+    if old_row.is_none() {
+        check_quota(conn, user)?;
+    }
+
+    let allowed = match &old_row {
+        Some(row) if row.private => user.may_edit_private(&row.created_by),
+        Some(row) => user.may_edit_item(&row.created_by, row.locked),
+        None => user.may_create(),
+    };
+
+    if allowed {
+        let id = &work.id;
+
+        if work.private && matches!(&old_row, Some(row) if !row.private) {
+            check_may_become_private(conn, EntityType::Work, id, user)?;
+        }
+
+        // Add associated items, if they don't already exist.
+
+        if get_person(conn, &work.composer.id)?.is_none() {
+            update_person_in(tx, &work.composer, user)?;
+        }
+
+        // Authors of the whole work come first, followed by the authors of each part.
+        let mut authors: Vec<(Option<i64>, &Person)> =
+            work.authors.iter().map(|author| (None, author)).collect();
+
+        for (index, part) in work.parts.iter().enumerate() {
+            let part_index: i64 = index.try_into()?;
+            authors.extend(part.authors.iter().map(|author| (Some(part_index), author)));
+        }
+
+        for (_, author) in &authors {
+            if get_person(conn, &author.id)?.is_none() {
+                update_person_in(tx, author, user)?;
+            }
+        }
+
+        for instrument in &work.instruments {
+            if get_instrument(conn, &instrument.id)?.is_none() {
+                update_instrument_in(tx, instrument, user)?;
+            }
+        }
+
+        check_reference(
+            conn,
+            EntityType::Person,
+            &work.composer.id,
+            work.private,
+            user,
+        )?;
+
+        for (_, author) in &authors {
+            check_reference(conn, EntityType::Person, &author.id, work.private, user)?;
+        }
+
+        for instrument in &work.instruments {
+            check_reference(
+                conn,
+                EntityType::Instrument,
+                &instrument.id,
+                work.private,
+                user,
+            )?;
+        }
+
+        check_period(conn, work.period.as_deref())?;
+
+        // Add or update the actual work.
+
+        let premiere = work.premiere.clone().unwrap_or_default();
 
-        let allowed = match old_row {
-            Some(row) => user.may_edit(&row.created_by),
-            None => user.may_create(),
+        let row = WorkRow {
+            id: id.clone(),
+            composer: work.composer.id.clone(),
+            title: normalize_text(&work.title),
+            created_by: user.username.clone(),
+            locked: old_row.map(|row| row.locked).unwrap_or(false),
+            private: work.private,
+            premiere_date: premiere.date.clone(),
+            premiere_place: premiere.place.as_deref().map(normalize_text),
+            premiere_performers: premiere.performers.as_deref().map(normalize_text),
+            dedication: work.dedication.as_deref().map(normalize_text),
+            period: work.period.clone(),
+            imslp: work.imslp.as_deref().map(normalize_imslp_page),
+            nickname: work.nickname.as_deref().map(normalize_text),
         };
 
-        if allowed {
-            let id = &work.id;
+        diesel::insert_into(works::table)
+            .values(&row)
+            .on_conflict(works::id)
+            .do_update()
+            .set(&row)
+            .execute(conn)?;
 
-            // This will also delete rows from associated tables.
-            diesel::delete(works::table)
-                .filter(works::id.eq(id))
+        // Update the rows from associated tables. Existing rows are matched by their position,
+        // so that only changed rows are touched and unchanged ones keep their IDs.
+
+        // Titles are unique per language, so all changed ones are removed before inserting
+        // them again.
+        let old_titles = WorkTitleRow::belonging_to(&row)
+            .order_by(work_titles::id)
+            .load::<WorkTitleRow>(conn)?;
+
+        let titles: Vec<NewWorkTitleRow> = work
+            .titles
+            .iter()
+            .map(|title| NewWorkTitleRow {
+                work: id.clone(),
+                language: title.language.clone(),
+                title: normalize_text(&title.title),
+                original: title.original,
+            })
+            .collect();
+
+        let unchanged = old_titles.len() == titles.len()
+            && old_titles.iter().zip(&titles).all(|(old, new)| {
+                old.language == new.language
+                    && old.title == new.title
+                    && old.original == new.original
+            });
+
+        if !unchanged {
+            diesel::delete(WorkTitleRow::belonging_to(&row)).execute(conn)?;
+            diesel::insert_into(work_titles::table)
+                .values(&titles)
                 .execute(conn)?;
+        }
 
-            // Add associated items, if they don't already exist.
+        let old_instrumentations = InstrumentationRow::belonging_to(&row)
+            .order_by(instrumentations::id)
+            .load::<InstrumentationRow>(conn)?;
 
-            if get_person(conn, &work.composer.id)?.is_none() {
-                update_person(conn, &work.composer, &user)?;
+        for (index, instrument) in work.instruments.iter().enumerate() {
+            match old_instrumentations.get(index) {
+                Some(old) if old.instrument == instrument.id => (),
+                Some(old) => {
+                    diesel::update(old)
+                        .set(instrumentations::instrument.eq(&instrument.id))
+                        .execute(conn)?;
+                }
+                None => {
+                    diesel::insert_into(instrumentations::table)
+                        .values(NewInstrumentationRow {
+                            work: id.clone(),
+                            instrument: instrument.id.clone(),
+                        })
+                        .execute(conn)?;
+                }
             }
+        }
 
-            for instrument in &work.instruments {
-                if get_instrument(conn, &instrument.id)?.is_none() {
-                    update_instrument(conn, instrument, &user)?;
+        for old in old_instrumentations.iter().skip(work.instruments.len()) {
+            diesel::delete(old).execute(conn)?;
+        }
+
+        let old_parts = WorkPartRow::belonging_to(&row)
+            .order_by(work_parts::part_index)
+            .load::<WorkPartRow>(conn)?;
+
+        for (index, part) in work.parts.iter().enumerate() {
+            let part_index: i64 = index.try_into()?;
+            let title = normalize_text(&part.title);
+            let key = part.key.as_deref().map(normalize_text);
+            let tempo = part.tempo.as_deref().map(normalize_text);
+
+            match old_parts.get(index) {
+                Some(old)
+                    if old.part_index == part_index
+                        && old.title == title
+                        && old.key == key
+                        && old.tempo == tempo
+                        && old.duration == part.duration => {}
+                Some(old) => {
+                    diesel::update(old)
+                        .set((
+                            work_parts::part_index.eq(part_index),
+                            work_parts::title.eq(title),
+                            work_parts::key.eq(key),
+                            work_parts::tempo.eq(tempo),
+                            work_parts::duration.eq(part.duration),
+                        ))
+                        .execute(conn)?;
+                }
+                None => {
+                    diesel::insert_into(work_parts::table)
+                        .values(NewWorkPartRow {
+                            work: id.clone(),
+                            part_index,
+                            title,
+                            key,
+                            tempo,
+                            duration: part.duration,
+                        })
+                        .execute(conn)?;
                 }
             }
+        }
 
-            // Add the actual work.
+        for old in old_parts.iter().skip(work.parts.len()) {
+            diesel::delete(old).execute(conn)?;
+        }
 
-            let row = WorkRow {
-                id: id.clone(),
-                composer: work.composer.id.clone(),
-                title: work.title.clone(),
-                created_by: user.username.clone(),
-            };
+        // Texts of removed parts are removed as well.
+        let part_count: i64 = work.parts.len().try_into()?;
+        diesel::delete(
+            work_texts::table
+                .filter(work_texts::work.eq(id))
+                .filter(work_texts::part_index.ge(part_count)),
+        )
+        .execute(conn)?;
 
-            diesel::insert_into(works::table)
-                .values(row)
-                .execute(conn)?;
+        let old_authors = WorkAuthorRow::belonging_to(&row)
+            .order_by(work_authors::id)
+            .load::<WorkAuthorRow>(conn)?;
 
-            for instrument in &work.instruments {
-                diesel::insert_into(instrumentations::table)
-                    .values(InstrumentationRow {
-                        id: rand::random(),
-                        work: id.clone(),
-                        instrument: instrument.id.clone(),
-                    })
-                    .execute(conn)?;
+        for (index, (part_index, author)) in authors.iter().enumerate() {
+            match old_authors.get(index) {
+                Some(old) if old.part_index == *part_index && old.person == author.id => (),
+                Some(old) => {
+                    diesel::update(old)
+                        .set((
+                            work_authors::part_index.eq(part_index),
+                            work_authors::person.eq(&author.id),
+                        ))
+                        .execute(conn)?;
+                }
+                None => {
+                    diesel::insert_into(work_authors::table)
+                        .values(NewWorkAuthorRow {
+                            work: id.clone(),
+                            part_index: *part_index,
+                            person: author.id.clone(),
+                        })
+                        .execute(conn)?;
+                }
             }
+        }
 
-            for (index, part) in work.parts.iter().enumerate() {
-                let row = WorkPartRow {
-                    id: rand::random(),
-                    work: id.clone(),
-                    part_index: index.try_into()?,
-                    title: part.title.clone(),
-                };
-
-                diesel::insert_into(work_parts::table)
-                    .values(row)
-                    .execute(conn)?;
-            }
+        for old in old_authors.iter().skip(authors.len()) {
+            diesel::delete(old).execute(conn)?;
+        }
 
-            for section in &work.sections {
-                let row = WorkSectionRow {
-                    id: rand::random(),
-                    work: id.clone(),
-                    title: section.title.clone(),
-                    before_index: section.before_index,
-                };
-
-                diesel::insert_into(work_sections::table)
-                    .values(row)
-                    .execute(conn)?;
+        let old_sections = WorkSectionRow::belonging_to(&row)
+            .order_by(work_sections::id)
+            .load::<WorkSectionRow>(conn)?;
+
+        for (index, section) in work.sections.iter().enumerate() {
+            let title = normalize_text(&section.title);
+
+            match old_sections.get(index) {
+                Some(old) if old.title == title && old.before_index == section.before_index => (),
+                Some(old) => {
+                    diesel::update(old)
+                        .set((
+                            work_sections::title.eq(title),
+                            work_sections::before_index.eq(section.before_index),
+                        ))
+                        .execute(conn)?;
+                }
+                None => {
+                    diesel::insert_into(work_sections::table)
+                        .values(NewWorkSectionRow {
+                            work: id.clone(),
+                            title,
+                            before_index: section.before_index,
+                        })
+                        .execute(conn)?;
+                }
             }
+        }
 
-            Ok(())
-        } else {
-            Err(Error::new(ServerError::Forbidden))
+        for old in old_sections.iter().skip(work.sections.len()) {
+            diesel::delete(old).execute(conn)?;
+        }
+
+        // Titles of parts and sections are replaced as a whole like the titles of the work.
+
+        let mut part_titles: Vec<NewWorkPartTitleRow> = Vec::new();
+
+        for (index, part) in work.parts.iter().enumerate() {
+            let part_index: i64 = index.try_into()?;
+
+            part_titles.extend(part.titles.iter().map(|title| NewWorkPartTitleRow {
+                work: id.clone(),
+                part_index,
+                language: title.language.clone(),
+                title: normalize_text(&title.title),
+                original: title.original,
+            }));
         }
-    })?;
 
-    Ok(())
+        let old_part_titles = WorkPartTitleRow::belonging_to(&row)
+            .order_by((work_part_titles::part_index, work_part_titles::id))
+            .load::<WorkPartTitleRow>(conn)?;
+
+        let unchanged = old_part_titles.len() == part_titles.len()
+            && old_part_titles.iter().zip(&part_titles).all(|(old, new)| {
+                old.part_index == new.part_index
+                    && old.language == new.language
+                    && old.title == new.title
+                    && old.original == new.original
+            });
+
+        if !unchanged {
+            diesel::delete(WorkPartTitleRow::belonging_to(&row)).execute(conn)?;
+            diesel::insert_into(work_part_titles::table)
+                .values(&part_titles)
+                .execute(conn)?;
+        }
+
+        let mut section_titles: Vec<NewWorkSectionTitleRow> = Vec::new();
+
+        for (index, section) in work.sections.iter().enumerate() {
+            let section_index: i64 = index.try_into()?;
+
+            section_titles.extend(section.titles.iter().map(|title| NewWorkSectionTitleRow {
+                work: id.clone(),
+                section_index,
+                language: title.language.clone(),
+                title: normalize_text(&title.title),
+                original: title.original,
+            }));
+        }
+
+        let old_section_titles = WorkSectionTitleRow::belonging_to(&row)
+            .order_by((work_section_titles::section_index, work_section_titles::id))
+            .load::<WorkSectionTitleRow>(conn)?;
+
+        let unchanged = old_section_titles.len() == section_titles.len()
+            && old_section_titles
+                .iter()
+                .zip(&section_titles)
+                .all(|(old, new)| {
+                    old.section_index == new.section_index
+                        && old.language == new.language
+                        && old.title == new.title
+                        && old.original == new.original
+                });
+
+        if !unchanged {
+            diesel::delete(WorkSectionTitleRow::belonging_to(&row)).execute(conn)?;
+            diesel::insert_into(work_section_titles::table)
+                .values(&section_titles)
+                .execute(conn)?;
+        }
+
+        insert_event(conn, EntityType::Work, id, kind, user)?;
+
+        Ok(())
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Check whether a user may edit an existing work. This fails, if the work doesn't exist.
+pub fn may_edit_work(conn: &DbConn, id: &str, user: &User) -> Result<bool> {
+    let row = get_work_row(conn, id)?.ok_or_else(|| Error::new(ServerError::NotFound))?;
+
+    let allowed = if row.private {
+        user.may_edit_private(&row.created_by)
+    } else {
+        user.may_edit_item(&row.created_by, row.locked)
+    };
+
+    Ok(allowed)
 }
 
 /// Get an existing work and all available information from related tables.
@@ -176,20 +760,124 @@ pub fn get_work(conn: &DbConn, id: &str) -> Result<Option<Work>> {
 /// this work except for the things that are part of the information on the work itself. Also,
 /// this will only succeed, if the provided user is allowed to delete the work.
 pub fn delete_work(conn: &DbConn, id: &str, user: &User) -> Result<()> {
-    if user.may_delete() {
-        diesel::delete(works::table.filter(works::id.eq(id))).execute(conn)?;
-        Ok(())
+    if may_delete_entity(conn, EntityType::Work, id, user)? {
+        conn.transaction::<(), Error, _>(|| {
+            check_unreferenced(conn, EntityType::Work, id)?;
+            move_to_trash(conn, EntityType::Work, id, user)?;
+
+            let count = diesel::delete(works::table.filter(works::id.eq(id))).execute(conn)?;
+
+            if count > 0 {
+                insert_event(conn, EntityType::Work, id, EventKind::Delete, user)?;
+            }
+
+            Ok(())
+        })
+    } else {
+        Err(Error::new(ServerError::Forbidden))
+    }
+}
+
+/// Lock or unlock an existing work. This will only work if the provided user is allowed to do
+/// that.
+pub fn set_work_locked(conn: &DbConn, id: &str, locked: bool, user: &User) -> Result<()> {
+    if user.may_lock() {
+        conn.transaction::<(), Error, _>(|| {
+            let count = diesel::update(works::table.filter(works::id.eq(id)))
+                .set(works::locked.eq(locked))
+                .execute(conn)?;
+
+            if count == 0 {
+                return Err(Error::new(ServerError::NotFound));
+            }
+
+            insert_event(conn, EntityType::Work, id, EventKind::Update, user)?;
+
+            Ok(())
+        })
     } else {
         Err(Error::new(ServerError::Forbidden))
     }
 }
 
-/// Get all existing works by a composer and related information from other tables.
-pub fn get_works(conn: &DbConn, composer_id: &str) -> Result<Vec<Work>> {
+/// Get a work including its recordings, their performers and the mediums containing them. Only
+/// public recordings and mediums and private ones of the viewer are included.
+pub fn get_full_work(conn: &DbConn, id: &str, viewer: Option<&User>) -> Result<Option<FullWork>> {
+    let work = match get_work(conn, id)? {
+        Some(work) => work,
+        None => return Ok(None),
+    };
+
+    let mut recordings = Vec::new();
+    for recording in get_recordings_for_work(conn, id, viewer)? {
+        let mediums = get_mediums_for_recording(conn, &recording.id, viewer)?;
+        recordings.push(FullRecording { recording, mediums });
+    }
+
+    Ok(Some(FullWork { work, recordings }))
+}
+
+/// Get all existing works by a composer and related information from other tables. Only public
+/// works and private works of the viewer are included.
+pub fn get_works(conn: &DbConn, composer_id: &str, viewer: Option<&User>) -> Result<Vec<Work>> {
     let mut works: Vec<Work> = Vec::new();
 
     let rows = works::table
         .filter(works::composer.eq(composer_id))
+        .filter(
+            works::private
+                .eq(false)
+                .or(works::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<WorkRow>(conn)?;
+
+    for row in rows {
+        works.push(get_description_for_work_row(conn, &row)?);
+    }
+
+    Ok(works)
+}
+
+/// Get all existing works with texts written by a person, either for the whole work or for some
+/// of its parts. Only public works and private works of the viewer are included.
+pub fn get_works_by_author(
+    conn: &DbConn,
+    author_id: &str,
+    viewer: Option<&User>,
+) -> Result<Vec<Work>> {
+    let mut works: Vec<Work> = Vec::new();
+
+    let authored = work_authors::table
+        .filter(work_authors::person.eq(author_id))
+        .select(work_authors::work);
+
+    let rows = works::table
+        .filter(works::id.eq_any(authored))
+        .filter(
+            works::private
+                .eq(false)
+                .or(works::created_by.eq(viewer_name(viewer))),
+        )
+        .load::<WorkRow>(conn)?;
+
+    for row in rows {
+        works.push(get_description_for_work_row(conn, &row)?);
+    }
+
+    Ok(works)
+}
+
+/// Get all existing works and related information from other tables. Only public works and
+/// private works of the viewer are included.
+pub fn get_all_works(conn: &DbConn, viewer: Option<&User>) -> Result<Vec<Work>> {
+    let mut works: Vec<Work> = Vec::new();
+
+    let rows = works::table
+        .filter(
+            works::private
+                .eq(false)
+                .or(works::created_by.eq(viewer_name(viewer))),
+        )
         .load::<WorkRow>(conn)?;
 
     for row in rows {
@@ -212,8 +900,8 @@ fn get_work_row(conn: &DbConn, id: &str) -> Result<Option<WorkRow>> {
 fn get_description_for_work_row(conn: &DbConn, row: &WorkRow) -> Result<Work> {
     let mut instruments: Vec<Instrument> = Vec::new();
 
-    let instrumentations = instrumentations::table
-        .filter(instrumentations::work.eq(&row.id))
+    let instrumentations = InstrumentationRow::belonging_to(row)
+        .order_by(instrumentations::id)
         .load::<InstrumentationRow>(conn)?;
 
     for instrumentation in instrumentations {
@@ -224,38 +912,144 @@ fn get_description_for_work_row(conn: &DbConn, row: &WorkRow) -> Result<Work> {
 
     let mut parts: Vec<WorkPart> = Vec::new();
 
-    let part_rows = work_parts::table
-        .filter(work_parts::work.eq(&row.id))
+    let part_rows = WorkPartRow::belonging_to(row)
+        .order_by(work_parts::part_index)
         .load::<WorkPartRow>(conn)?;
 
     for part_row in part_rows {
         parts.push(WorkPart {
             title: part_row.title,
+            titles: Vec::new(),
+            key: part_row.key,
+            tempo: part_row.tempo,
+            duration: part_row.duration,
+            authors: Vec::new(),
+        });
+    }
+
+    let part_title_rows = WorkPartTitleRow::belonging_to(row)
+        .order_by((work_part_titles::part_index, work_part_titles::id))
+        .load::<WorkPartTitleRow>(conn)?;
+
+    for title in part_title_rows {
+        let part = usize::try_from(title.part_index)
+            .ok()
+            .and_then(|index| parts.get_mut(index))
+            .ok_or(anyhow!("No part {} of work: {}", title.part_index, row.id))?;
+
+        part.titles.push(WorkTitle {
+            language: title.language,
+            title: title.title,
+            original: title.original,
         });
     }
 
+    let mut authors: Vec<Person> = Vec::new();
+
+    let author_rows = WorkAuthorRow::belonging_to(row)
+        .order_by(work_authors::id)
+        .load::<WorkAuthorRow>(conn)?;
+
+    for author_row in author_rows {
+        let id = &author_row.person;
+        let author = get_person(conn, id)?.ok_or(anyhow!("No person with ID: {}", id))?;
+
+        match author_row.part_index {
+            Some(part_index) => {
+                let part = usize::try_from(part_index)
+                    .ok()
+                    .and_then(|index| parts.get_mut(index))
+                    .ok_or(anyhow!("No part {} of work: {}", part_index, row.id))?;
+
+                part.authors.push(author);
+            }
+            None => authors.push(author),
+        }
+    }
+
+    let titles = WorkTitleRow::belonging_to(row)
+        .order_by(work_titles::id)
+        .load::<WorkTitleRow>(conn)?
+        .into_iter()
+        .map(|title| WorkTitle {
+            language: title.language,
+            title: title.title,
+            original: title.original,
+        })
+        .collect();
+
     let mut sections: Vec<WorkSection> = Vec::new();
 
-    let section_rows = work_sections::table
-        .filter(work_sections::work.eq(&row.id))
+    let section_rows = WorkSectionRow::belonging_to(row)
+        .order_by(work_sections::id)
         .load::<WorkSectionRow>(conn)?;
 
     for section in section_rows {
         sections.push(WorkSection {
             title: section.title,
+            titles: Vec::new(),
             before_index: section.before_index,
         });
     }
 
+    let section_title_rows = WorkSectionTitleRow::belonging_to(row)
+        .order_by((work_section_titles::section_index, work_section_titles::id))
+        .load::<WorkSectionTitleRow>(conn)?;
+
+    for title in section_title_rows {
+        let section = usize::try_from(title.section_index)
+            .ok()
+            .and_then(|index| sections.get_mut(index))
+            .ok_or(anyhow!(
+                "No section {} of work: {}",
+                title.section_index,
+                row.id
+            ))?;
+
+        section.titles.push(WorkTitle {
+            language: title.language,
+            title: title.title,
+            original: title.original,
+        });
+    }
+
     let id = &row.composer;
     let composer = get_person(conn, id)?.ok_or(anyhow!("No person with ID: {}", id))?;
 
+    let premiere = if row.premiere_date.is_some()
+        || row.premiere_place.is_some()
+        || row.premiere_performers.is_some()
+    {
+        Some(Premiere {
+            date: row.premiere_date.clone(),
+            place: row.premiere_place.clone(),
+            performers: row.premiere_performers.clone(),
+        })
+    } else {
+        None
+    };
+
     Ok(Work {
         id: row.id.clone(),
         composer,
+        authors,
         title: row.title.clone(),
+        nickname: row.nickname.clone(),
+        titles,
         instruments,
         parts,
         sections,
+        premiere,
+        dedication: row.dedication.clone(),
+        period: row.period.clone(),
+        imslp: row.imslp.clone(),
+        locked: row.locked,
+        private: row.private,
     })
 }
+
+/// Normalize the title of a page on IMSLP to the form used within its URL, which contains
+/// underscores instead of spaces.
+fn normalize_imslp_page(page: &str) -> String {
+    normalize_text(page.trim()).replace(' ', "_")
+}
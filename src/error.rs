@@ -1,26 +1,72 @@
-use actix_web::{dev::HttpResponseBuilder, error, http::StatusCode, HttpResponse};
+use crate::database::EntityReferences;
+use crate::validation::ValidationErrors;
+use actix_web::http::{header, StatusCode};
+use actix_web::{dev::HttpResponseBuilder, error, HttpResponse};
 use derive_more::{Display, Error};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 
 /// An error intended for the public interface.
 #[derive(Display, Error, Debug)]
 pub enum ServerError {
+    BadRequest,
     NotFound,
     Unauthorized,
     Forbidden,
+    Conflict,
+    PayloadTooLarge,
+    TooManyRequests,
     Internal,
+
+    /// The request body contains invalid data. The response will list the problems.
+    #[display(fmt = "Invalid")]
+    Invalid(#[error(not(source))] ValidationErrors),
+
+    /// The request body doesn't match the expected data, e.g. because of a wrong type or an
+    /// unknown field. The response will list the problems.
+    #[display(fmt = "Malformed")]
+    Malformed(#[error(not(source))] ValidationErrors),
+
+    /// The entity can't be deleted, because other entities still refer to it. The response will
+    /// list them.
+    #[display(fmt = "Referenced")]
+    Referenced(#[error(not(source))] EntityReferences),
+
+    /// The entity was merged into another one. The response will redirect to the location of
+    /// that entity.
+    #[display(fmt = "Moved")]
+    Moved(#[error(not(source))] String),
 }
 
 impl error::ResponseError for ServerError {
     fn error_response(&self) -> HttpResponse {
-        HttpResponseBuilder::new(self.status_code()).finish()
+        match self {
+            ServerError::Invalid(errors) | ServerError::Malformed(errors) => {
+                HttpResponseBuilder::new(self.status_code()).json(errors)
+            }
+            ServerError::Referenced(references) => {
+                HttpResponseBuilder::new(self.status_code()).json(references)
+            }
+            ServerError::Moved(location) => HttpResponseBuilder::new(self.status_code())
+                .header(header::LOCATION, location.as_str())
+                .finish(),
+            _ => HttpResponseBuilder::new(self.status_code()).finish(),
+        }
     }
 
     fn status_code(&self) -> StatusCode {
         match self {
+            ServerError::BadRequest => StatusCode::BAD_REQUEST,
             ServerError::NotFound => StatusCode::NOT_FOUND,
             ServerError::Unauthorized => StatusCode::UNAUTHORIZED,
             ServerError::Forbidden => StatusCode::FORBIDDEN,
+            ServerError::Conflict => StatusCode::CONFLICT,
+            ServerError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ServerError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
             ServerError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            ServerError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ServerError::Malformed(_) => StatusCode::BAD_REQUEST,
+            ServerError::Referenced(_) => StatusCode::CONFLICT,
+            ServerError::Moved(_) => StatusCode::PERMANENT_REDIRECT,
         }
     }
 }
@@ -35,9 +81,15 @@ impl From<anyhow::Error> for ServerError {
     fn from(error: anyhow::Error) -> Self {
         match error.downcast() {
             Ok(error) => error,
-            Err(error) => {
-                println!("{:?}", error);
-                ServerError::Internal
+            Err(error) => match error.downcast_ref() {
+                // Another request may have added a reference in the meantime.
+                Some(DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => {
+                    ServerError::Conflict
+                }
+                _ => {
+                    println!("{:?}", error);
+                    ServerError::Internal
+                }
             },
         }
     }
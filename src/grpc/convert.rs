@@ -0,0 +1,458 @@
+// Conversions fail with the status that is returned to the client, just like the generated
+// service methods.
+#![allow(clippy::result_large_err)]
+
+use super::proto;
+use crate::database;
+use crate::database::ExternalSource;
+use std::convert::{TryFrom, TryInto};
+use tonic::Status;
+
+/// Get a message that is required, but optional within protobuf.
+fn required<T>(value: Option<T>, field: &str) -> Result<T, Status> {
+    value.ok_or_else(|| Status::invalid_argument(format!("Missing field: {}", field)))
+}
+
+/// Convert all items of a list using a fallible conversion.
+fn try_all<T, U>(values: Vec<T>) -> Result<Vec<U>, Status>
+where
+    U: TryFrom<T, Error = Status>,
+{
+    values.into_iter().map(U::try_from).collect()
+}
+
+/// Convert all items of a list.
+fn all<T, U: From<T>>(values: Vec<T>) -> Vec<U> {
+    values.into_iter().map(U::from).collect()
+}
+
+/// Convert indices to their protobuf representation.
+fn to_indices(values: Vec<usize>) -> Vec<u64> {
+    values.into_iter().map(|value| value as u64).collect()
+}
+
+/// Convert indices from their protobuf representation.
+fn from_indices(values: Vec<u64>) -> Result<Vec<usize>, Status> {
+    values
+        .into_iter()
+        .map(|value| {
+            value
+                .try_into()
+                .map_err(|_| Status::invalid_argument("Invalid index"))
+        })
+        .collect()
+}
+
+impl From<database::Person> for proto::Person {
+    fn from(person: database::Person) -> Self {
+        Self {
+            id: person.id,
+            first_name: person.first_name,
+            last_name: person.last_name,
+            period: person.period,
+            locked: person.locked,
+            private: person.private,
+        }
+    }
+}
+
+impl TryFrom<proto::Person> for database::Person {
+    type Error = Status;
+
+    fn try_from(person: proto::Person) -> Result<Self, Status> {
+        Ok(Self {
+            id: person.id,
+            first_name: person.first_name,
+            last_name: person.last_name,
+            period: person.period,
+            locked: person.locked,
+            private: person.private,
+        })
+    }
+}
+
+impl From<database::Ensemble> for proto::Ensemble {
+    fn from(ensemble: database::Ensemble) -> Self {
+        Self {
+            id: ensemble.id,
+            name: ensemble.name,
+            private: ensemble.private,
+        }
+    }
+}
+
+impl TryFrom<proto::Ensemble> for database::Ensemble {
+    type Error = Status;
+
+    fn try_from(ensemble: proto::Ensemble) -> Result<Self, Status> {
+        Ok(Self {
+            id: ensemble.id,
+            name: ensemble.name,
+            private: ensemble.private,
+        })
+    }
+}
+
+impl From<database::Instrument> for proto::Instrument {
+    fn from(instrument: database::Instrument) -> Self {
+        Self {
+            id: instrument.id,
+            name: instrument.name,
+            private: instrument.private,
+        }
+    }
+}
+
+impl TryFrom<proto::Instrument> for database::Instrument {
+    type Error = Status;
+
+    fn try_from(instrument: proto::Instrument) -> Result<Self, Status> {
+        Ok(Self {
+            id: instrument.id,
+            name: instrument.name,
+            private: instrument.private,
+        })
+    }
+}
+
+impl From<database::Label> for proto::Label {
+    fn from(label: database::Label) -> Self {
+        Self {
+            id: label.id,
+            name: label.name,
+            private: label.private,
+        }
+    }
+}
+
+impl TryFrom<proto::Label> for database::Label {
+    type Error = Status;
+
+    fn try_from(label: proto::Label) -> Result<Self, Status> {
+        Ok(Self {
+            id: label.id,
+            name: label.name,
+            private: label.private,
+        })
+    }
+}
+
+impl From<database::WorkTitle> for proto::WorkTitle {
+    fn from(title: database::WorkTitle) -> Self {
+        Self {
+            language: title.language,
+            title: title.title,
+            original: title.original,
+        }
+    }
+}
+
+impl From<proto::WorkTitle> for database::WorkTitle {
+    fn from(title: proto::WorkTitle) -> Self {
+        Self {
+            language: title.language,
+            title: title.title,
+            original: title.original,
+        }
+    }
+}
+
+impl From<database::Premiere> for proto::Premiere {
+    fn from(premiere: database::Premiere) -> Self {
+        Self {
+            date: premiere.date,
+            place: premiere.place,
+            performers: premiere.performers,
+        }
+    }
+}
+
+impl From<proto::Premiere> for database::Premiere {
+    fn from(premiere: proto::Premiere) -> Self {
+        Self {
+            date: premiere.date,
+            place: premiere.place,
+            performers: premiere.performers,
+        }
+    }
+}
+
+impl From<database::WorkPart> for proto::WorkPart {
+    fn from(part: database::WorkPart) -> Self {
+        Self {
+            title: part.title,
+            titles: all(part.titles),
+            key: part.key,
+            tempo: part.tempo,
+            duration: part.duration,
+            authors: all(part.authors),
+        }
+    }
+}
+
+impl TryFrom<proto::WorkPart> for database::WorkPart {
+    type Error = Status;
+
+    fn try_from(part: proto::WorkPart) -> Result<Self, Status> {
+        Ok(Self {
+            title: part.title,
+            titles: all(part.titles),
+            key: part.key,
+            tempo: part.tempo,
+            duration: part.duration,
+            authors: try_all(part.authors)?,
+        })
+    }
+}
+
+impl From<database::WorkSection> for proto::WorkSection {
+    fn from(section: database::WorkSection) -> Self {
+        Self {
+            title: section.title,
+            titles: all(section.titles),
+            before_index: section.before_index,
+        }
+    }
+}
+
+impl From<proto::WorkSection> for database::WorkSection {
+    fn from(section: proto::WorkSection) -> Self {
+        Self {
+            title: section.title,
+            titles: all(section.titles),
+            before_index: section.before_index,
+        }
+    }
+}
+
+impl From<database::Work> for proto::Work {
+    fn from(work: database::Work) -> Self {
+        Self {
+            id: work.id,
+            title: work.title,
+            nickname: work.nickname,
+            titles: all(work.titles),
+            composer: Some(work.composer.into()),
+            authors: all(work.authors),
+            instruments: all(work.instruments),
+            parts: all(work.parts),
+            sections: all(work.sections),
+            premiere: work.premiere.map(Into::into),
+            dedication: work.dedication,
+            period: work.period,
+            imslp: work.imslp,
+            locked: work.locked,
+            private: work.private,
+        }
+    }
+}
+
+impl TryFrom<proto::Work> for database::Work {
+    type Error = Status;
+
+    fn try_from(work: proto::Work) -> Result<Self, Status> {
+        Ok(Self {
+            id: work.id,
+            title: work.title,
+            nickname: work.nickname,
+            titles: all(work.titles),
+            composer: required(work.composer, "composer")?.try_into()?,
+            authors: try_all(work.authors)?,
+            instruments: try_all(work.instruments)?,
+            parts: try_all(work.parts)?,
+            sections: all(work.sections),
+            premiere: work.premiere.map(Into::into),
+            dedication: work.dedication,
+            period: work.period,
+            imslp: work.imslp,
+            locked: work.locked,
+            private: work.private,
+        })
+    }
+}
+
+impl From<database::ExternalId> for proto::ExternalId {
+    fn from(external_id: database::ExternalId) -> Self {
+        Self {
+            source: external_id.source.as_str().to_string(),
+            id: external_id.id,
+        }
+    }
+}
+
+impl TryFrom<proto::ExternalId> for database::ExternalId {
+    type Error = Status;
+
+    fn try_from(external_id: proto::ExternalId) -> Result<Self, Status> {
+        let source = ExternalSource::parse(&external_id.source).ok_or_else(|| {
+            Status::invalid_argument(format!("Unknown source: {}", external_id.source))
+        })?;
+
+        Ok(Self {
+            source,
+            id: external_id.id,
+        })
+    }
+}
+
+impl From<database::RatingSummary> for proto::RatingSummary {
+    fn from(rating: database::RatingSummary) -> Self {
+        Self {
+            average: rating.average,
+            count: rating.count,
+        }
+    }
+}
+
+impl From<proto::RatingSummary> for database::RatingSummary {
+    fn from(rating: proto::RatingSummary) -> Self {
+        Self {
+            average: rating.average,
+            count: rating.count,
+        }
+    }
+}
+
+impl From<database::Performance> for proto::Performance {
+    fn from(performance: database::Performance) -> Self {
+        Self {
+            person: performance.person.map(Into::into),
+            ensemble: performance.ensemble.map(Into::into),
+            role: performance.role.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<proto::Performance> for database::Performance {
+    type Error = Status;
+
+    fn try_from(performance: proto::Performance) -> Result<Self, Status> {
+        Ok(Self {
+            person: performance.person.map(TryInto::try_into).transpose()?,
+            ensemble: performance.ensemble.map(TryInto::try_into).transpose()?,
+            role: performance.role.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<database::Recording> for proto::Recording {
+    fn from(recording: database::Recording) -> Self {
+        Self {
+            id: recording.id,
+            work: Some(recording.work.into()),
+            additional_works: all(recording.additional_works),
+            parts: recording.parts.map(|parts| proto::PartSelection {
+                parts: to_indices(parts),
+            }),
+            external_ids: all(recording.external_ids),
+            comment: recording.comment,
+            performances: all(recording.performances),
+            locked: recording.locked,
+            rating: Some(recording.rating.into()),
+            private: recording.private,
+        }
+    }
+}
+
+impl TryFrom<proto::Recording> for database::Recording {
+    type Error = Status;
+
+    fn try_from(recording: proto::Recording) -> Result<Self, Status> {
+        Ok(Self {
+            id: recording.id,
+            work: required(recording.work, "work")?.try_into()?,
+            additional_works: try_all(recording.additional_works)?,
+            parts: recording
+                .parts
+                .map(|parts| from_indices(parts.parts))
+                .transpose()?,
+            external_ids: try_all(recording.external_ids)?,
+            comment: recording.comment,
+            performances: try_all(recording.performances)?,
+            locked: recording.locked,
+            rating: recording.rating.map(Into::into).unwrap_or_default(),
+            private: recording.private,
+        })
+    }
+}
+
+impl From<database::Track> for proto::Track {
+    fn from(track: database::Track) -> Self {
+        Self {
+            work_index: track.work_index as u64,
+            work_parts: to_indices(track.work_parts),
+            duration: track.duration,
+            flac_md5: track.flac_md5,
+            accuraterip_v1: track.accuraterip_v1,
+            accuraterip_v2: track.accuraterip_v2,
+        }
+    }
+}
+
+impl TryFrom<proto::Track> for database::Track {
+    type Error = Status;
+
+    fn try_from(track: proto::Track) -> Result<Self, Status> {
+        Ok(Self {
+            work_index: track
+                .work_index
+                .try_into()
+                .map_err(|_| Status::invalid_argument("Invalid work index"))?,
+            work_parts: from_indices(track.work_parts)?,
+            duration: track.duration,
+            flac_md5: track.flac_md5,
+            accuraterip_v1: track.accuraterip_v1,
+            accuraterip_v2: track.accuraterip_v2,
+        })
+    }
+}
+
+impl From<database::TrackSet> for proto::TrackSet {
+    fn from(track_set: database::TrackSet) -> Self {
+        Self {
+            recording: Some(track_set.recording.into()),
+            tracks: all(track_set.tracks),
+        }
+    }
+}
+
+impl TryFrom<proto::TrackSet> for database::TrackSet {
+    type Error = Status;
+
+    fn try_from(track_set: proto::TrackSet) -> Result<Self, Status> {
+        Ok(Self {
+            recording: required(track_set.recording, "recording")?.try_into()?,
+            tracks: try_all(track_set.tracks)?,
+        })
+    }
+}
+
+impl From<database::Medium> for proto::Medium {
+    fn from(medium: database::Medium) -> Self {
+        Self {
+            id: medium.id,
+            name: medium.name,
+            discid: medium.discid,
+            label: medium.label.map(Into::into),
+            external_ids: all(medium.external_ids),
+            tracks: all(medium.tracks),
+            private: medium.private,
+        }
+    }
+}
+
+impl TryFrom<proto::Medium> for database::Medium {
+    type Error = Status;
+
+    fn try_from(medium: proto::Medium) -> Result<Self, Status> {
+        Ok(Self {
+            id: medium.id,
+            name: medium.name,
+            discid: medium.discid,
+            label: medium.label.map(TryInto::try_into).transpose()?,
+            external_ids: try_all(medium.external_ids)?,
+            tracks: try_all(medium.tracks)?,
+            private: medium.private,
+        })
+    }
+}
@@ -0,0 +1,624 @@
+use crate::access::ReadAccess;
+use crate::cache::ResponseCache;
+use crate::database;
+use crate::database::{DbConn, DbPool, EntityType, NestedEntities, ReadDbPool, Scope, User};
+use crate::database::{Ensemble, Instrument, Label, Medium, Person, Recording, Work};
+use crate::error::ServerError;
+use crate::maintenance::MaintenanceMode;
+use crate::routes::{assign_id, authenticate, authorize_nested, check_redirect, check_visible};
+use crate::routes::CreateQuery;
+use crate::shutdown::Shutdown;
+use crate::validation::Validate;
+use anyhow::Result;
+use futures::channel::oneshot;
+use proto::wolfgang_server::{Wolfgang, WolfgangServer};
+use proto::{DeleteRequest, DeleteResponse, GetRequest, UpdateResponse};
+use std::convert::TryInto;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::transport::Server;
+use tonic::{Code, Request, Response, Status};
+
+pub mod convert;
+
+/// The code generated from "proto/wolfgang.proto".
+pub mod proto {
+    tonic::include_proto!("wolfgang");
+}
+
+/// Read the address for the gRPC server from the environment variable "WOLFGANG_GRPC_ADDRESS",
+/// e.g. "127.0.0.1:50051". If it is not set, there is no gRPC server.
+pub fn address_from_env() -> Result<Option<SocketAddr>> {
+    match std::env::var("WOLFGANG_GRPC_ADDRESS") {
+        Ok(address) => Ok(Some(address.parse()?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Serve the gRPC interface until `stop` is triggered.
+pub async fn serve(
+    address: SocketAddr,
+    service: GrpcService,
+    stop: oneshot::Receiver<()>,
+) -> Result<()> {
+    Server::builder()
+        .add_service(WolfgangServer::new(service))
+        .serve_with_shutdown(address, async {
+            stop.await.ok();
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// The gRPC interface for the core entities. It follows the same rules as the REST API:
+/// requests are authenticated using a bearer token within the "authorization" metadata, changes
+/// need the write scope of the entity type and are rejected in maintenance mode.
+pub struct GrpcService {
+    db: DbPool,
+    read_db: ReadDbPool,
+    read_access: ReadAccess,
+    maintenance: Arc<MaintenanceMode>,
+    cache: Arc<ResponseCache>,
+    shutdown: Shutdown,
+}
+
+impl GrpcService {
+    pub fn new(
+        db: DbPool,
+        read_db: ReadDbPool,
+        read_access: ReadAccess,
+        maintenance: Arc<MaintenanceMode>,
+        cache: Arc<ResponseCache>,
+        shutdown: Shutdown,
+    ) -> Self {
+        Self {
+            db,
+            read_db,
+            read_access,
+            maintenance,
+            cache,
+            shutdown,
+        }
+    }
+
+    /// Get an entity like `GET /{type}/{id}`.
+    async fn get<T: Entity>(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<T::Message>, Status> {
+        let token = get_token(&request);
+        let id = request.into_inner().id;
+        let db = self.read_db.clone();
+        let read_access = self.read_access;
+
+        let entity = database::block(move || {
+            let conn = db.get()?;
+
+            let viewer = match token {
+                Some(token) => Some(authenticate(&conn, &token, Scope::Read)?),
+                None if read_access == ReadAccess::Authenticated => {
+                    return Err(ServerError::Unauthorized)
+                }
+                None => None,
+            };
+
+            check_redirect(&conn, T::TYPE, &id, viewer.as_ref())?;
+            check_visible(&conn, T::TYPE, &id, viewer.as_ref())?;
+            T::get(&conn, &id)?.ok_or(ServerError::NotFound)
+        })
+        .await
+        .map_err(ServerError::from)?;
+
+        Ok(Response::new(entity.into()))
+    }
+
+    /// Add or update an entity like `POST /{type}`.
+    async fn update<T: Entity>(
+        &self,
+        token: Option<String>,
+        entity: Option<T::Message>,
+        force: bool,
+        if_absent: bool,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let entity = entity.ok_or_else(|| Status::invalid_argument("Missing entity"))?;
+        let mut entity: T = entity.try_into()?;
+        let generated = assign_id(entity.id());
+        entity.validate()?;
+
+        let id = entity.id().clone();
+        let create = CreateQuery { if_absent };
+        let nested_token = token.clone().unwrap_or_default();
+
+        let candidates = self
+            .write(token, T::TYPE, move |conn, user| {
+                authorize_nested(conn, &nested_token, &entity)?;
+
+                let candidates = database::with_transaction(conn, |tx| {
+                    let conn = tx.conn();
+                    create.check(tx, T::TYPE, entity.id())?;
+
+                    if !force && T::get(conn, entity.id())?.is_none() {
+                        let candidates = entity.find_similar(conn)?;
+                        if !candidates.is_empty() {
+                            return Ok(candidates);
+                        }
+                    }
+
+                    entity.update(conn, user)?;
+
+                    Ok(Vec::new())
+                })?;
+
+                Ok(candidates)
+            })
+            .await?;
+
+        if !candidates.is_empty() {
+            return Err(Status::already_exists(format!(
+                "Similar entities exist: {}",
+                candidates.join(", ")
+            )));
+        }
+
+        Ok(Response::new(UpdateResponse { id, generated }))
+    }
+
+    /// Delete an entity like `DELETE /{type}/{id}`.
+    async fn delete<T: Entity>(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        let token = get_token(&request);
+        let request = request.into_inner();
+
+        self.write(token, T::TYPE, move |conn, user| {
+            if request.cascade {
+                database::delete_cascading(conn, T::TYPE, &request.id, user)?;
+            } else {
+                T::delete(conn, &request.id, user)?;
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        Ok(Response::new(DeleteResponse {}))
+    }
+
+    /// Make a change to entities of the provided type. The job is registered with the shutdown
+    /// handle, so that stopping the server waits for it.
+    async fn write<T, F>(
+        &self,
+        token: Option<String>,
+        entity_type: EntityType,
+        write: F,
+    ) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&DbConn, &User) -> Result<T, ServerError> + Send + 'static,
+    {
+        let token = token.ok_or(ServerError::Unauthorized)?;
+
+        let job = self
+            .shutdown
+            .start_job()
+            .ok_or_else(|| Status::unavailable("The server is shutting down"))?;
+
+        let db = self.db.clone();
+        let maintenance = self.maintenance.clone();
+        let cache = self.cache.clone();
+
+        let result = database::block(move || {
+            let _job = job;
+
+            if maintenance.is_enabled()? {
+                return Ok(Err(Status::unavailable(
+                    "The server is in maintenance mode",
+                )));
+            }
+
+            let conn = db.get()?;
+            let user = authenticate(&conn, &token, entity_type.write_scope())?;
+
+            let result = write(&conn, &user)?;
+
+            if let Err(error) = cache.invalidate() {
+                println!("{:?}", error);
+            }
+
+            Ok(Ok(result))
+        })
+        .await
+        .map_err(ServerError::from)?;
+
+        result
+    }
+}
+
+#[tonic::async_trait]
+impl Wolfgang for GrpcService {
+    async fn get_person(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<proto::Person>, Status> {
+        self.get::<Person>(request).await
+    }
+
+    async fn update_person(
+        &self,
+        request: Request<proto::UpdatePersonRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let token = get_token(&request);
+        let request = request.into_inner();
+        self.update::<Person>(token, request.person, request.force, request.if_absent)
+            .await
+    }
+
+    async fn delete_person(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        self.delete::<Person>(request).await
+    }
+
+    async fn get_ensemble(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<proto::Ensemble>, Status> {
+        self.get::<Ensemble>(request).await
+    }
+
+    async fn update_ensemble(
+        &self,
+        request: Request<proto::UpdateEnsembleRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let token = get_token(&request);
+        let request = request.into_inner();
+        self.update::<Ensemble>(token, request.ensemble, true, request.if_absent)
+            .await
+    }
+
+    async fn delete_ensemble(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        self.delete::<Ensemble>(request).await
+    }
+
+    async fn get_instrument(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<proto::Instrument>, Status> {
+        self.get::<Instrument>(request).await
+    }
+
+    async fn update_instrument(
+        &self,
+        request: Request<proto::UpdateInstrumentRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let token = get_token(&request);
+        let request = request.into_inner();
+        self.update::<Instrument>(token, request.instrument, true, request.if_absent)
+            .await
+    }
+
+    async fn delete_instrument(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        self.delete::<Instrument>(request).await
+    }
+
+    async fn get_label(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<proto::Label>, Status> {
+        self.get::<Label>(request).await
+    }
+
+    async fn update_label(
+        &self,
+        request: Request<proto::UpdateLabelRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let token = get_token(&request);
+        let request = request.into_inner();
+        self.update::<Label>(token, request.label, true, request.if_absent)
+            .await
+    }
+
+    async fn delete_label(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        self.delete::<Label>(request).await
+    }
+
+    async fn get_work(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<proto::Work>, Status> {
+        self.get::<Work>(request).await
+    }
+
+    async fn update_work(
+        &self,
+        request: Request<proto::UpdateWorkRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let token = get_token(&request);
+        let request = request.into_inner();
+        self.update::<Work>(token, request.work, request.force, request.if_absent)
+            .await
+    }
+
+    async fn delete_work(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        self.delete::<Work>(request).await
+    }
+
+    async fn get_recording(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<proto::Recording>, Status> {
+        self.get::<Recording>(request).await
+    }
+
+    async fn update_recording(
+        &self,
+        request: Request<proto::UpdateRecordingRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let token = get_token(&request);
+        let request = request.into_inner();
+        self.update::<Recording>(token, request.recording, true, request.if_absent)
+            .await
+    }
+
+    async fn delete_recording(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        self.delete::<Recording>(request).await
+    }
+
+    async fn get_medium(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<proto::Medium>, Status> {
+        self.get::<Medium>(request).await
+    }
+
+    async fn update_medium(
+        &self,
+        request: Request<proto::UpdateMediumRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let token = get_token(&request);
+        let request = request.into_inner();
+        self.update::<Medium>(token, request.medium, true, request.if_absent)
+            .await
+    }
+
+    async fn delete_medium(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        self.delete::<Medium>(request).await
+    }
+}
+
+/// An entity that is available through the gRPC interface.
+trait Entity: Validate + NestedEntities + Sized + Send + 'static {
+    /// The protobuf message for the entity.
+    type Message: From<Self> + TryInto<Self, Error = Status> + Send;
+
+    const TYPE: EntityType;
+
+    fn id(&mut self) -> &mut String;
+    fn get(conn: &DbConn, id: &str) -> Result<Option<Self>>;
+    fn update(&self, conn: &DbConn, user: &User) -> Result<()>;
+    fn delete(conn: &DbConn, id: &str, user: &User) -> Result<()>;
+
+    /// Get the IDs of existing entities that look like duplicates of this one.
+    fn find_similar(&self, _conn: &DbConn) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
+
+impl Entity for Person {
+    type Message = proto::Person;
+    const TYPE: EntityType = EntityType::Person;
+
+    fn id(&mut self) -> &mut String {
+        &mut self.id
+    }
+
+    fn get(conn: &DbConn, id: &str) -> Result<Option<Self>> {
+        database::get_person(conn, id)
+    }
+
+    fn update(&self, conn: &DbConn, user: &User) -> Result<()> {
+        database::update_person(conn, self, user)
+    }
+
+    fn delete(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+        database::delete_person(conn, id, user)
+    }
+
+    fn find_similar(&self, conn: &DbConn) -> Result<Vec<String>> {
+        let candidates = database::find_similar_persons(conn, self)?;
+        Ok(candidates.into_iter().map(|person| person.id).collect())
+    }
+}
+
+impl Entity for Ensemble {
+    type Message = proto::Ensemble;
+    const TYPE: EntityType = EntityType::Ensemble;
+
+    fn id(&mut self) -> &mut String {
+        &mut self.id
+    }
+
+    fn get(conn: &DbConn, id: &str) -> Result<Option<Self>> {
+        database::get_ensemble(conn, id)
+    }
+
+    fn update(&self, conn: &DbConn, user: &User) -> Result<()> {
+        database::update_ensemble(conn, self, user)
+    }
+
+    fn delete(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+        database::delete_ensemble(conn, id, user)
+    }
+}
+
+impl Entity for Instrument {
+    type Message = proto::Instrument;
+    const TYPE: EntityType = EntityType::Instrument;
+
+    fn id(&mut self) -> &mut String {
+        &mut self.id
+    }
+
+    fn get(conn: &DbConn, id: &str) -> Result<Option<Self>> {
+        database::get_instrument(conn, id)
+    }
+
+    fn update(&self, conn: &DbConn, user: &User) -> Result<()> {
+        database::update_instrument(conn, self, user)
+    }
+
+    fn delete(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+        database::delete_instrument(conn, id, user)
+    }
+}
+
+impl Entity for Label {
+    type Message = proto::Label;
+    const TYPE: EntityType = EntityType::Label;
+
+    fn id(&mut self) -> &mut String {
+        &mut self.id
+    }
+
+    fn get(conn: &DbConn, id: &str) -> Result<Option<Self>> {
+        database::get_label(conn, id)
+    }
+
+    fn update(&self, conn: &DbConn, user: &User) -> Result<()> {
+        database::update_label(conn, self, user)
+    }
+
+    fn delete(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+        database::delete_label(conn, id, user)
+    }
+}
+
+impl Entity for Work {
+    type Message = proto::Work;
+    const TYPE: EntityType = EntityType::Work;
+
+    fn id(&mut self) -> &mut String {
+        &mut self.id
+    }
+
+    fn get(conn: &DbConn, id: &str) -> Result<Option<Self>> {
+        database::get_work(conn, id)
+    }
+
+    fn update(&self, conn: &DbConn, user: &User) -> Result<()> {
+        database::update_work(conn, self, user)
+    }
+
+    fn delete(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+        database::delete_work(conn, id, user)
+    }
+
+    fn find_similar(&self, conn: &DbConn) -> Result<Vec<String>> {
+        let candidates = database::find_similar_works(conn, self)?;
+        Ok(candidates.into_iter().map(|work| work.id).collect())
+    }
+}
+
+impl Entity for Recording {
+    type Message = proto::Recording;
+    const TYPE: EntityType = EntityType::Recording;
+
+    fn id(&mut self) -> &mut String {
+        &mut self.id
+    }
+
+    fn get(conn: &DbConn, id: &str) -> Result<Option<Self>> {
+        database::get_recording(conn, id)
+    }
+
+    fn update(&self, conn: &DbConn, user: &User) -> Result<()> {
+        database::update_recording(conn, self, user)
+    }
+
+    fn delete(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+        database::delete_recording(conn, id, user)
+    }
+}
+
+impl Entity for Medium {
+    type Message = proto::Medium;
+    const TYPE: EntityType = EntityType::Medium;
+
+    fn id(&mut self) -> &mut String {
+        &mut self.id
+    }
+
+    fn get(conn: &DbConn, id: &str) -> Result<Option<Self>> {
+        database::get_medium(conn, id)
+    }
+
+    fn update(&self, conn: &DbConn, user: &User) -> Result<()> {
+        database::update_medium(conn, self, user)
+    }
+
+    fn delete(conn: &DbConn, id: &str, user: &User) -> Result<()> {
+        database::delete_medium(conn, id, user)
+    }
+}
+
+/// Get the bearer token from the "authorization" metadata of a request.
+fn get_token<T>(request: &Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+}
+
+impl From<ServerError> for Status {
+    fn from(error: ServerError) -> Self {
+        let code = match &error {
+            ServerError::BadRequest | ServerError::Invalid(_) | ServerError::Malformed(_) => {
+                Code::InvalidArgument
+            }
+            ServerError::NotFound | ServerError::Moved(_) => Code::NotFound,
+            ServerError::Unauthorized => Code::Unauthenticated,
+            ServerError::Forbidden => Code::PermissionDenied,
+            ServerError::Conflict => Code::Aborted,
+            ServerError::Referenced(_) => Code::FailedPrecondition,
+            ServerError::PayloadTooLarge | ServerError::TooManyRequests => Code::ResourceExhausted,
+            ServerError::Internal => Code::Internal,
+        };
+
+        // Details are passed on as JSON like in the responses of the REST API.
+        let message = match &error {
+            ServerError::Invalid(errors) | ServerError::Malformed(errors) => {
+                serde_json::to_string(errors).unwrap_or_default()
+            }
+            ServerError::Referenced(references) => {
+                serde_json::to_string(references).unwrap_or_default()
+            }
+            ServerError::Moved(location) => format!("Moved to {}", location),
+            _ => error.to_string(),
+        };
+
+        Status::new(code, message)
+    }
+}
@@ -0,0 +1,54 @@
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::Error;
+use futures::future::{ok, Ready};
+use std::task::{Context, Poll};
+
+/// Middleware that answers HEAD requests using the handler for GET requests to the same route.
+/// Clients can use this to check whether an entity exists or whether it has changed without
+/// transferring its data. The body is left out by the HTTP layer, which still knows that the
+/// request was a HEAD request, while the headers are kept as they are.
+pub struct HeadAsGet;
+
+impl<S> Transform<S> for HeadAsGet
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = HeadAsGetMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(HeadAsGetMiddleware { service })
+    }
+}
+
+/// The service created by [`HeadAsGet`].
+pub struct HeadAsGetMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service for HeadAsGetMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        if req.method() == Method::HEAD {
+            req.head_mut().method = Method::GET;
+        }
+
+        self.service.call(req)
+    }
+}
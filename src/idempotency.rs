@@ -0,0 +1,242 @@
+use crate::database;
+use crate::database::{DbPool, IdempotentRequest, StoredResponse};
+use crate::error::ServerError;
+use crate::routes::{identify, MEDIUM_JSON_LIMIT};
+use actix_http::h1;
+use actix_web::dev::{Body, ResponseBody, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// The header containing the idempotency key chosen by the client.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// The header that is added to responses that were replayed.
+const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// The maximum length of idempotency keys.
+const MAX_KEY_LENGTH: usize = 256;
+
+/// Routes whose responses contain secrets, e.g. tokens or API keys. Their responses must not be
+/// stored, so idempotency keys are ignored for them.
+const SECRET_PATHS: &[&str] = &["/login", "/account/api-keys", "/webhooks"];
+
+/// Middleware that remembers responses to POST requests with an "Idempotency-Key" header. If a
+/// client retries such a request, the stored response is returned instead of handling the
+/// request again. Reusing a key for a different request results in a bad request response. Keys
+/// belong to the user of the request, so different users may use the same keys. Requests to the
+/// [`SECRET_PATHS`] are handled as if they had no key.
+pub struct Idempotency;
+
+impl<S> Transform<S> for Idempotency
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IdempotencyMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(IdempotencyMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+/// The service created by [`Idempotency`].
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> Service for IdempotencyMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+            Some(_) if SECRET_PATHS.contains(&req.path()) => {
+                return Box::pin(service.borrow_mut().call(req))
+            }
+            Some(key) if req.method() == Method::POST => key.to_str().ok().map(str::to_string),
+            _ => return Box::pin(service.borrow_mut().call(req)),
+        };
+
+        Box::pin(async move {
+            match key {
+                Some(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => {
+                    handle(service, req, key).await
+                }
+                _ => Ok(req.error_response(ServerError::BadRequest)),
+            }
+        })
+    }
+}
+
+/// Handle a request with an idempotency key.
+async fn handle<S>(
+    service: Rc<RefCell<S>>,
+    mut req: ServiceRequest,
+    key: String,
+) -> Result<ServiceResponse<Body>, Error>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error>,
+{
+    let pool = match req.app_data::<web::Data<DbPool>>() {
+        Some(pool) => pool.get_ref().clone(),
+        None => return Ok(req.error_response(ServerError::Internal)),
+    };
+
+    // Read the whole body to include it in the hash and put it back afterwards.
+    let mut payload = req.take_payload();
+    let mut body = web::BytesMut::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+
+        if body.len() + chunk.len() > MEDIUM_JSON_LIMIT {
+            return Ok(req.error_response(ServerError::PayloadTooLarge));
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    let request_hash = hash_request(&req, &body);
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+
+    let (_, mut new_payload) = h1::Payload::create(true);
+    new_payload.unread_data(body.freeze());
+    req.set_payload(new_payload.into());
+
+    let (username, state) = {
+        let pool = pool.clone();
+        let key = key.clone();
+
+        web::block(move || begin_request(&pool, token.as_deref(), &key, &request_hash))
+            .await
+            .map_err(ServerError::from)?
+    };
+
+    match state {
+        IdempotentRequest::New => (),
+        IdempotentRequest::Pending => return Ok(req.error_response(ServerError::Conflict)),
+        IdempotentRequest::Mismatch => return Ok(req.error_response(ServerError::BadRequest)),
+        IdempotentRequest::Done(stored) => {
+            let status = StatusCode::from_u16(stored.status).map_err(|_| ServerError::Internal)?;
+            let mut response = HttpResponse::build(status);
+
+            response.header(IDEMPOTENT_REPLAYED_HEADER, "true");
+            if let Some(content_type) = stored.content_type {
+                response.content_type(content_type);
+            }
+
+            return Ok(req.into_response(response.body(stored.body)));
+        }
+    }
+
+    let future = service.borrow_mut().call(req);
+    let res = future.await?;
+
+    // Only complete responses are stored. Server errors are not stored, so that the client may
+    // try again.
+    let body = match res.response().body() {
+        ResponseBody::Body(Body::Bytes(bytes)) => Some(bytes.to_vec()),
+        ResponseBody::Body(Body::Empty) | ResponseBody::Body(Body::None) => Some(Vec::new()),
+        _ => None,
+    };
+
+    let stored = body
+        .filter(|_| !res.status().is_server_error())
+        .map(|body| StoredResponse {
+            status: res.status().as_u16(),
+            content_type: res
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            body,
+        });
+
+    let result = web::block(move || -> Result<(), ServerError> {
+        let conn = pool.get()?;
+
+        match stored {
+            Some(stored) => database::finish_idempotent_request(&conn, &username, &key, &stored)?,
+            None => database::abort_idempotent_request(&conn, &username, &key)?,
+        }
+
+        Ok(())
+    })
+    .await;
+
+    if let Err(error) = result {
+        println!("{:?}", error);
+    }
+
+    Ok(res)
+}
+
+/// Reserve the idempotency key for a request by the user that a token belongs to. Requests with
+/// invalid tokens will fail anyway, so they are treated like anonymous ones, which use an empty
+/// username. This returns the username along with the state of the request.
+fn begin_request(
+    pool: &DbPool,
+    token: Option<&str>,
+    key: &str,
+    request_hash: &str,
+) -> Result<(String, IdempotentRequest), ServerError> {
+    let conn = pool.get()?;
+
+    let username = match token.map(|token| identify(&conn, token)) {
+        Some(Ok((user, _))) => user.username,
+        Some(Err(ServerError::Unauthorized)) | None => String::new(),
+        Some(Err(error)) => return Err(error),
+    };
+
+    let state = database::begin_idempotent_request(&conn, &username, key, request_hash)?;
+
+    Ok((username, state))
+}
+
+/// Compute a hash identifying a request by its method, path, credentials and body.
+fn hash_request(req: &ServiceRequest, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+
+    hasher.update(req.method().as_str().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(req.uri().to_string().as_bytes());
+    hasher.update(b"\n");
+
+    if let Some(authorization) = req.headers().get(header::AUTHORIZATION) {
+        hasher.update(authorization.as_bytes());
+    }
+
+    hasher.update(b"\n");
+    hasher.update(body);
+
+    format!("{:x}", hasher.finalize())
+}
@@ -0,0 +1,170 @@
+use crate::error::ServerError;
+use anyhow::{Error, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::io::{Limits, Reader};
+use image::{ColorType, DynamicImage};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The maximum width and height of stored images in pixels. Larger images are scaled down.
+const MAX_SIZE: u32 = 2048;
+
+/// The maximum width and height of uploaded images in pixels. Larger images are rejected before
+/// they are decoded completely.
+const MAX_UPLOAD_SIZE: u32 = 16384;
+
+/// The quality of the stored JPEG files.
+const QUALITY: u8 = 85;
+
+/// How old files have to be before they are purged. Files of new images are written before the
+/// image is added to the database, so they would otherwise be purged while being uploaded.
+const PURGE_MIN_AGE: Duration = Duration::from_secs(3600);
+
+/// The sizes images can be requested in. Images are scaled down to fit into a square of this
+/// many pixels.
+pub const IMAGE_SIZES: [u32; 5] = [64, 128, 256, 512, 1024];
+
+/// The directory containing the files of images. Each image is stored as JPEG file named after
+/// its ID. Scaled down versions are created on demand and kept next to it.
+#[derive(Debug, Clone)]
+pub struct ImageStore {
+    directory: PathBuf,
+}
+
+impl ImageStore {
+    /// Read the directory from the environment variable "WOLFGANG_IMAGE_PATH". If it is not set,
+    /// there is no store and images can't be uploaded.
+    pub fn from_env() -> Option<Self> {
+        std::env::var("WOLFGANG_IMAGE_PATH")
+            .ok()
+            .map(|directory| Self {
+                directory: PathBuf::from(directory),
+            })
+    }
+
+    /// Decode an uploaded JPEG or PNG image, scale it down, if necessary, and store it. This
+    /// returns the width and height of the stored image. Images that can't be decoded are
+    /// rejected as bad requests.
+    pub fn save(&self, id: &str, data: &[u8]) -> Result<(u32, u32)> {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_UPLOAD_SIZE);
+        limits.max_image_height = Some(MAX_UPLOAD_SIZE);
+
+        let mut reader = Reader::new(Cursor::new(data)).with_guessed_format()?;
+        reader.limits(limits);
+
+        let mut image = reader
+            .decode()
+            .map_err(|_| Error::new(ServerError::BadRequest))?;
+
+        if image.width() > MAX_SIZE || image.height() > MAX_SIZE {
+            image = image.resize(MAX_SIZE, MAX_SIZE, FilterType::Lanczos3);
+        }
+
+        write_jpeg(&self.path(id, None), &image)?;
+
+        Ok((image.width(), image.height()))
+    }
+
+    /// Load the JPEG file of an image, optionally scaled down to one of the [`IMAGE_SIZES`].
+    pub fn load(&self, id: &str, size: Option<u32>) -> Result<Vec<u8>> {
+        let original = self.path(id, None);
+
+        let size = match size {
+            Some(size) => size,
+            None => return Ok(std::fs::read(original)?),
+        };
+
+        let path = self.path(id, Some(size));
+        if path.exists() {
+            return Ok(std::fs::read(path)?);
+        }
+
+        let image = image::open(&original)?;
+
+        if image.width() <= size && image.height() <= size {
+            return Ok(std::fs::read(original)?);
+        }
+
+        write_jpeg(&path, &image.resize(size, size, FilterType::Lanczos3))?;
+
+        Ok(std::fs::read(path)?)
+    }
+
+    /// Remove all files of an image.
+    pub fn remove(&self, id: &str) -> Result<()> {
+        self.remove_where(|file_id| file_id == id, None)
+    }
+
+    /// Remove the files of all images that aren't listed. Recently written files are kept.
+    pub fn purge(&self, ids: &[String]) -> Result<()> {
+        self.remove_where(
+            |file_id| !ids.iter().any(|id| id == file_id),
+            Some(PURGE_MIN_AGE),
+        )
+    }
+
+    /// Remove all files belonging to images with IDs matching a predicate, optionally only if
+    /// they weren't modified for some time.
+    fn remove_where<F: Fn(&str) -> bool>(
+        &self,
+        predicate: F,
+        min_age: Option<Duration>,
+    ) -> Result<()> {
+        for entry in std::fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if let Some(min_age) = min_age {
+                let age = entry.metadata()?.modified()?.elapsed().unwrap_or_default();
+
+                if age < min_age {
+                    continue;
+                }
+            }
+
+            let id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.split('-').next());
+
+            if matches!(id, Some(id) if predicate(id)) {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the path of the file of an image in its original size or scaled down.
+    fn path(&self, id: &str, size: Option<u32>) -> PathBuf {
+        let name = match size {
+            Some(size) => format!("{}-{}.jpg", id, size),
+            None => format!("{}.jpg", id),
+        };
+
+        self.directory.join(name)
+    }
+}
+
+/// Encode an image as JPEG and write it to a file. The file is written to a temporary file first,
+/// so that requests never see incomplete files.
+fn write_jpeg(path: &Path, image: &DynamicImage) -> Result<()> {
+    let rgb = image.to_rgb8();
+    let mut data = Vec::new();
+
+    JpegEncoder::new_with_quality(&mut data, QUALITY).encode(
+        rgb.as_raw(),
+        rgb.width(),
+        rgb.height(),
+        ColorType::Rgb8,
+    )?;
+
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, data)?;
+    std::fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
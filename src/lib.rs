@@ -0,0 +1,36 @@
+// Required for database/schema.rs
+#[macro_use]
+extern crate diesel;
+
+// Required for embed_migrations macro in database/mod.rs
+#[macro_use]
+extern crate diesel_migrations;
+
+pub mod access;
+pub mod bootstrap;
+pub mod cache;
+pub mod captcha;
+pub mod cli;
+pub mod database;
+pub mod error;
+pub mod grpc;
+pub mod head;
+pub mod idempotency;
+pub mod images;
+pub mod mail;
+pub mod msgpack;
+pub mod maintenance;
+pub mod presence;
+pub mod publishing;
+pub mod rebuild;
+pub mod replication;
+pub mod routes;
+pub mod scheduler;
+pub mod search;
+pub mod shared;
+pub mod shutdown;
+pub mod tasks;
+pub mod timing;
+pub mod validation;
+pub mod webhooks;
+pub mod wikidata;
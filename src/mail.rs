@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Send a plain text mail. This uses the sendmail compatible program specified by the environment
+/// variable "WOLFGANG_SENDMAIL" (defaults to "/usr/sbin/sendmail"). The sender address is taken
+/// from "WOLFGANG_MAIL_FROM", which has to be set.
+pub fn send_mail(to: &str, subject: &str, body: &str) -> Result<()> {
+    let sendmail =
+        std::env::var("WOLFGANG_SENDMAIL").unwrap_or_else(|_| String::from("/usr/sbin/sendmail"));
+    let from = std::env::var("WOLFGANG_MAIL_FROM")?;
+
+    // Prevent header injection through the provided values.
+    if [to, subject, &from]
+        .iter()
+        .any(|value| value.contains('\n') || value.contains('\r'))
+    {
+        return Err(anyhow!("Invalid mail header value!"));
+    }
+
+    let mut child = Command::new(sendmail)
+        .arg("-i")
+        .arg("--")
+        .arg(to)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    let message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}",
+        from, to, subject, body
+    );
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to open sendmail input!"))?
+        .write_all(message.as_bytes())?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("Sendmail failed: {}", status));
+    }
+
+    Ok(())
+}
@@ -1,19 +1,12 @@
-// Required for database/schema.rs
-#[macro_use]
-extern crate diesel;
-
-// Required for embed_migrations macro in database/mod.rs
-#[macro_use]
-extern crate diesel_migrations;
-
 use actix_web::{web, App, HttpServer};
 use anyhow::Result;
-
-mod database;
-mod error;
-
-mod routes;
-use routes::*;
+use std::sync::{Arc, RwLock};
+use wolfgang::routes::*;
+use wolfgang::{
+    access, bootstrap, cache, captcha, database, grpc, head, idempotency, images, maintenance,
+    msgpack, presence, publishing, rebuild, replication, search, shared, shutdown, tasks, timing,
+    webhooks,
+};
 
 #[actix_web::main]
 async fn main() -> Result<()> {
@@ -22,24 +15,163 @@ async fn main() -> Result<()> {
     sodiumoxide::init().expect("Failed to init crypto library!");
 
     let db_pool = web::Data::new(database::connect()?);
-    let captcha_manager = web::Data::new(CaptchaManager::new());
+    let read_pool = web::Data::new(database::connect_read()?);
+    let shared = shared::SharedState::from_env()?;
+    let registration_policy = web::Data::new(RegistrationPolicy::from_env()?);
+    let password_params = web::Data::new(PasswordParams::from_env()?);
+    let read_access = web::Data::new(access::ReadAccess::from_env()?);
+    let unknown_fields = web::Data::new(UnknownFields::from_env()?);
+    let backup_location = web::Data::new(BackupLocation::from_env());
+    let image_store = web::Data::new(images::ImageStore::from_env());
+    let dump_publisher = web::Data::new(publishing::DumpPublisher::from_env()?);
+    let maintenance_mode = web::Data::new(maintenance::MaintenanceMode::from_env(&shared)?);
+    let shutdown = shutdown::Shutdown::new();
+    let shutdown_timeout = shutdown::timeout_from_env()?;
+    let setup_token = bootstrap::run(db_pool.get_ref(), password_params.get_ref())?;
+    let setup_token = web::Data::new(setup_token);
+    let captchas: web::Data<dyn captcha::CaptchaBackend> =
+        web::Data::from(captcha::from_env(&shared)?);
+
+    // Deliver events to registered webhooks in the background.
+    webhooks::spawn(db_pool.get_ref().clone(), shutdown.clone());
+
+    // Keep the search index up to date, if there is one.
+    let search_index = search::SearchIndex::from_env();
+    if let Some(index) = &search_index {
+        search::spawn(index.clone(), db_pool.get_ref().clone(), shutdown.clone());
+    }
+    let search_index = web::Data::new(search_index);
+
+    let info = web::Data::new(ServerInfo::from_env(
+        *registration_policy.get_ref(),
+        *read_access.get_ref(),
+        &**captchas,
+        search_index.is_some(),
+    ));
+
+    // Notify WebSocket clients about changes.
+    let hub = Arc::new(presence::Hub::new());
+    presence::spawn(hub.clone(), db_pool.get_ref().clone(), shutdown.clone());
+    let hub = web::Data::from(hub);
+
+    // Cache responses of expensive read endpoints until the next change.
+    let cache = web::Data::new(cache::ResponseCache::new(&shared));
+
+    // Pull changes from an upstream server, if this is a mirror.
+    if let Some(replication) = replication::Replication::from_env()? {
+        replication::spawn(
+            replication,
+            db_pool.get_ref().clone(),
+            cache.clone(),
+            shutdown.clone(),
+        );
+    }
+
+    // Run periodic maintenance tasks.
+    let statistics: web::Data<StatisticsCache> = web::Data::new(RwLock::new(None));
+    tasks::schedule(
+        db_pool.get_ref().clone(),
+        captchas.clone(),
+        statistics.clone(),
+    )?
+    .spawn(shutdown.clone());
+
+    // Rebuild derived data on request.
+    let rebuilds = web::Data::new(rebuild::Rebuilds::new(
+        db_pool.get_ref().clone(),
+        search_index.get_ref().clone(),
+        statistics.clone(),
+        cache.clone(),
+        shutdown.clone(),
+    ));
+
+    // Serve the gRPC interface next to the REST API, if it is enabled.
+    let grpc_stop = match grpc::address_from_env()? {
+        Some(address) => {
+            let service = grpc::GrpcService::new(
+                db_pool.get_ref().clone(),
+                read_pool.get_ref().clone(),
+                *read_access.get_ref(),
+                maintenance_mode.clone().into_inner(),
+                cache.clone().into_inner(),
+                shutdown.clone(),
+            );
+
+            let (stop, stopped) = futures::channel::oneshot::channel();
+
+            actix_web::rt::spawn(async move {
+                if let Err(error) = grpc::serve(address, service, stopped).await {
+                    println!("{:?}", error);
+                }
+            });
+
+            Some(stop)
+        }
+        None => None,
+    };
 
     let server = HttpServer::new(move || {
         App::new()
             .app_data(db_pool.clone())
-            .app_data(captcha_manager.clone())
+            .app_data(read_pool.clone())
+            .app_data(captchas.clone())
+            .app_data(registration_policy.clone())
+            .app_data(password_params.clone())
+            .app_data(read_access.clone())
+            .app_data(backup_location.clone())
+            .app_data(image_store.clone())
+            .app_data(dump_publisher.clone())
+            .app_data(maintenance_mode.clone())
+            .app_data(info.clone())
+            .app_data(hub.clone())
+            .app_data(statistics.clone())
+            .app_data(cache.clone())
+            .app_data(search_index.clone())
+            .app_data(rebuilds.clone())
+            .app_data(unknown_fields.clone())
+            .app_data(setup_token.clone())
+            .wrap(head::HeadAsGet)
+            .wrap(cache::InvalidateCache)
+            .wrap(idempotency::Idempotency)
+            .wrap(msgpack::MessagePack)
+            .wrap(maintenance::Maintenance)
+            .wrap(access::RequireReadAccess)
+            .wrap(timing::Timing)
             .wrap(actix_web::middleware::Logger::new(
                 "%t: %r -> %s; %b B; %D ms",
             ))
+            .service(get_info)
+            .service(get_existence)
             .service(get_captcha)
             .service(register_user)
             .service(login_user)
             .service(put_user)
             .service(get_user)
+            .service(rename_user)
+            .service(change_password)
+            .service(change_email)
+            .service(confirm_email)
+            .service(create_api_key)
+            .service(get_api_keys)
+            .service(delete_api_key)
+            .service(create_invitation)
+            .service(get_invitations)
+            .service(delete_invitation)
             .service(get_person)
+            .service(get_person_overview)
             .service(update_person)
             .service(get_persons)
+            .service(get_periods)
+            .service(get_period)
+            .service(update_period)
+            .service(delete_period)
+            .service(get_person_relations)
+            .service(add_person_relation)
+            .service(delete_person_relation)
+            .service(get_person_deletion_preview)
             .service(delete_person)
+            .service(lock_person)
+            .service(unlock_person)
             .service(get_ensemble)
             .service(update_ensemble)
             .service(delete_ensemble)
@@ -48,22 +180,159 @@ async fn main() -> Result<()> {
             .service(update_instrument)
             .service(delete_instrument)
             .service(get_instruments)
+            .service(get_label)
+            .service(update_label)
+            .service(delete_label)
+            .service(get_label_deletion_preview)
+            .service(get_labels)
             .service(get_work)
+            .service(get_work_score)
+            .service(get_full_work)
             .service(update_work)
+            .service(get_work_deletion_preview)
             .service(delete_work)
+            .service(lock_work)
+            .service(unlock_work)
             .service(get_works)
+            .service(get_works_by_author)
+            .service(get_work_texts)
+            .service(update_work_texts)
             .service(get_recording)
             .service(update_recording)
+            .service(get_recording_deletion_preview)
             .service(delete_recording)
+            .service(lock_recording)
+            .service(unlock_recording)
             .service(get_recordings_for_work)
+            .service(rate_recording)
+            .service(get_rating)
+            .service(delete_rating)
+            .service(get_reviews)
+            .service(hide_review)
+            .service(unhide_review)
             .service(get_medium)
+            .service(get_medium_cue)
+            .service(get_medium_m3u)
+            .service(get_medium_export)
+            .service(get_medium_tags)
+            .service(get_medium_diff)
             .service(get_mediums_for_recording)
+            .service(get_mediums_for_label)
             .service(get_mediums_by_discid)
+            .service(get_tracks_by_checksum)
             .service(update_medium)
+            .service(create_medium_from_rip)
             .service(delete_medium)
+            .service(get_medium_relations)
+            .service(get_related_mediums)
+            .service(add_medium_relation)
+            .service(delete_medium_relation)
+            .service(get_drafts)
+            .service(get_draft)
+            .service(update_draft)
+            .service(delete_draft)
+            .service(publish_draft)
+            .service(lookup_toc)
+            .service(get_search_results)
+            .service(lookup_external_id)
+            .service(get_person_external_ids)
+            .service(get_statistics)
+            .service(get_completeness)
+            .service(check_consistency)
+            .service(repair_consistency)
+            .service(reconcile_wikidata)
+            .service(get_trash)
+            .service(restore_trash)
+            .service(purge_trash)
+            .service(create_backup)
+            .service(start_rebuild)
+            .service(get_rebuilds)
+            .service(get_rebuild)
+            .service(get_maintenance)
+            .service(set_maintenance)
+            .service(get_events)
+            .service(get_changes)
+            .service(import_csv)
+            .service(connect_ws)
+            .service(create_report)
+            .service(get_reports)
+            .service(get_report)
+            .service(comment_report)
+            .service(resolve_report)
+            .service(get_notifications)
+            .service(read_notifications)
+            .service(put_collection_item)
+            .service(delete_collection_item)
+            .service(get_collection)
+            .service(get_playlists)
+            .service(get_playlist)
+            .service(update_playlist)
+            .service(delete_playlist)
+            .service(submit_plays)
+            .service(get_plays)
+            .service(delete_plays)
+            .service(get_play_settings)
+            .service(set_play_settings)
+            .service(put_watch)
+            .service(delete_watch)
+            .service(get_watchlist)
+            .service(get_comments)
+            .service(create_comment)
+            .service(delete_comment)
+            .service(apply_editor)
+            .service(get_editor_applications)
+            .service(approve_editor_application)
+            .service(reject_editor_application)
+            .service(create_webhook)
+            .service(get_webhooks)
+            .service(delete_webhook)
+            .service(get_wikidata_candidates)
+            .service(confirm_wikidata_candidate)
+            .service(reject_wikidata_candidate)
+            .service(get_image)
+            .service(get_image_metadata)
+            .service(update_image)
+            .service(delete_image)
+            .service(merge_entity)
+            .service(get_sources)
+            .service(create_source)
+            .service(delete_source)
+            .service(get_quality)
+            .service(set_quality)
+            .service(delete_quality)
+            .service(create_bulk_edit)
+            .service(get_bulk_edits)
+            .service(get_bulk_edit)
+            .service(revert_revision)
+            .service(get_dumps)
+            .service(get_dump_file)
+            .service(get_schemas)
+            .service(get_schema)
+            .service(setup_admin)
+            .service(get_migrations)
     });
 
-    server.bind("127.0.0.1:8087")?.run().await?;
+    // On SIGTERM or SIGINT, the server stops accepting connections and waits for running
+    // requests, including their database transactions, to finish.
+    server
+        .shutdown_timeout(shutdown_timeout.as_secs())
+        .bind("127.0.0.1:8087")?
+        .run()
+        .await?;
+
+    if let Some(stop) = grpc_stop {
+        stop.send(()).ok();
+    }
+
+    // Afterwards, background jobs are allowed to finish as well. The connection pools are closed
+    // once the last thread using them has stopped.
+    let remaining = shutdown.stop(shutdown_timeout);
+    if remaining > 0 {
+        println!(
+            "Stopping while {} background jobs are still running.",
+            remaining
+        );
+    }
 
     Ok(())
 }
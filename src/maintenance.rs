@@ -0,0 +1,183 @@
+use crate::shared::{RedisPool, SharedState};
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method};
+use actix_web::{web, Error, HttpResponse};
+use anyhow::Result;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+
+/// The Redis key that is set while the server is in maintenance mode.
+const REDIS_KEY: &str = "wolfgang:maintenance";
+
+/// The default number of seconds clients are asked to wait before retrying a change.
+const DEFAULT_RETRY_AFTER: u64 = 300;
+
+/// Where the maintenance mode flag is kept.
+enum Backend {
+    Local(AtomicBool),
+    Redis(RedisPool),
+}
+
+/// Whether the server is in read-only maintenance mode. While it is, all requests that could
+/// change anything are rejected, except for logging in and administration requests.
+pub struct MaintenanceMode {
+    backend: Backend,
+
+    /// The value of the "Retry-After" header for rejected requests in seconds.
+    retry_after: u64,
+}
+
+impl MaintenanceMode {
+    /// Set up the maintenance mode flag using the provided backend for shared state. If the
+    /// environment variable "WOLFGANG_MAINTENANCE" is set to "on", the server starts in
+    /// maintenance mode. "WOLFGANG_MAINTENANCE_RETRY_AFTER" may contain the number of seconds
+    /// clients should wait before retrying.
+    pub fn from_env(state: &SharedState) -> Result<Self> {
+        let backend = match state {
+            SharedState::Local => Backend::Local(AtomicBool::new(false)),
+            SharedState::Redis(pool) => Backend::Redis(pool.clone()),
+        };
+
+        let retry_after = match std::env::var("WOLFGANG_MAINTENANCE_RETRY_AFTER") {
+            Ok(seconds) => seconds.parse()?,
+            Err(_) => DEFAULT_RETRY_AFTER,
+        };
+
+        let maintenance = Self {
+            backend,
+            retry_after,
+        };
+
+        // With Redis, the flag is kept as is unless requested, because other instances may have
+        // set it.
+        if std::env::var("WOLFGANG_MAINTENANCE").as_deref() == Ok("on") {
+            maintenance.set_enabled(true)?;
+        }
+
+        Ok(maintenance)
+    }
+
+    /// Check whether the server is in maintenance mode.
+    pub fn is_enabled(&self) -> Result<bool> {
+        let enabled = match &self.backend {
+            Backend::Local(flag) => flag.load(Ordering::Relaxed),
+            Backend::Redis(pool) => {
+                let mut conn = pool.get()?;
+                redis::cmd("EXISTS")
+                    .arg(REDIS_KEY)
+                    .query::<bool>(&mut *conn)?
+            }
+        };
+
+        Ok(enabled)
+    }
+
+    /// Enter or leave maintenance mode.
+    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+        match &self.backend {
+            Backend::Local(flag) => flag.store(enabled, Ordering::Relaxed),
+            Backend::Redis(pool) => {
+                let mut conn = pool.get()?;
+
+                if enabled {
+                    redis::cmd("SET")
+                        .arg(REDIS_KEY)
+                        .arg("on")
+                        .query::<()>(&mut *conn)?;
+                } else {
+                    redis::cmd("DEL").arg(REDIS_KEY).query::<()>(&mut *conn)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check whether a request may be handled in maintenance mode.
+fn is_allowed(req: &ServiceRequest) -> bool {
+    match req.method() {
+        &Method::GET | &Method::HEAD | &Method::OPTIONS => true,
+        _ => req.path() == "/login" || req.path().starts_with("/admin/"),
+    }
+}
+
+/// Middleware that rejects requests that could change anything with "503 Service Unavailable"
+/// while the server is in maintenance mode.
+pub struct Maintenance;
+
+impl<S> Transform<S> for Maintenance
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MaintenanceMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MaintenanceMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+/// The service created by [`Maintenance`].
+pub struct MaintenanceMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> Service for MaintenanceMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        let maintenance = if is_allowed(&req) {
+            None
+        } else {
+            req.app_data::<web::Data<MaintenanceMode>>().cloned()
+        };
+
+        Box::pin(async move {
+            if let Some(maintenance) = maintenance {
+                let retry_after = maintenance.retry_after;
+
+                // If the flag can't be read, changes are allowed rather than rejecting them all.
+                let enabled = match web::block(move || maintenance.is_enabled()).await {
+                    Ok(enabled) => enabled,
+                    Err(error) => {
+                        println!("{:?}", error);
+                        false
+                    }
+                };
+
+                if enabled {
+                    let response = HttpResponse::ServiceUnavailable()
+                        .header(header::RETRY_AFTER, retry_after.to_string())
+                        .finish();
+
+                    return Ok(req.into_response(response));
+                }
+            }
+
+            let future = service.borrow_mut().call(req);
+            future.await
+        })
+    }
+}
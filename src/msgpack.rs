@@ -0,0 +1,191 @@
+use crate::error::ServerError;
+use crate::routes::MEDIUM_JSON_LIMIT;
+use actix_http::h1;
+use actix_web::dev::{Body, ResponseBody, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, HeaderMap, HeaderValue};
+use actix_web::{web, Error, HttpMessage};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use futures::StreamExt;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// The media type of MessagePack.
+const MSGPACK: &str = "application/msgpack";
+
+/// Another media type for MessagePack that is used by some clients.
+const X_MSGPACK: &str = "application/x-msgpack";
+
+/// Middleware that allows clients to use MessagePack instead of JSON. Request bodies with the
+/// content type "application/msgpack" are converted to JSON before they reach the handlers.
+/// JSON responses are converted to MessagePack, if the client accepts "application/msgpack".
+/// Streamed responses are always sent as they are.
+pub struct MessagePack;
+
+impl<S> Transform<S> for MessagePack
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MessagePackMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MessagePackMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+/// The service created by [`MessagePack`].
+pub struct MessagePackMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> Service for MessagePackMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let msgpack_request = has_media_type(req.headers(), header::CONTENT_TYPE);
+        let msgpack_response = has_media_type(req.headers(), header::ACCEPT);
+
+        if !msgpack_request && !msgpack_response {
+            return Box::pin(service.borrow_mut().call(req));
+        }
+
+        Box::pin(async move {
+            let req = if msgpack_request {
+                match convert_request(req).await {
+                    Ok(req) => req,
+                    Err((req, error)) => return Ok(req.error_response(error)),
+                }
+            } else {
+                req
+            };
+
+            let future = service.borrow_mut().call(req);
+            let mut res = future.await?;
+
+            if msgpack_response {
+                res.headers_mut()
+                    .append(header::VARY, HeaderValue::from_static("Accept"));
+
+                if has_json_body(&res) {
+                    res = convert_response(res);
+                }
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+/// Replace a MessagePack request body with the equivalent JSON.
+async fn convert_request(
+    mut req: ServiceRequest,
+) -> Result<ServiceRequest, (ServiceRequest, ServerError)> {
+    let mut payload = req.take_payload();
+    let mut body = web::BytesMut::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(_) => return Err((req, ServerError::BadRequest)),
+        };
+
+        if body.len() + chunk.len() > MEDIUM_JSON_LIMIT {
+            return Err((req, ServerError::PayloadTooLarge));
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    let json = match rmp_serde::from_slice::<Value>(&body) {
+        Ok(value) => match serde_json::to_vec(&value) {
+            Ok(json) => json,
+            Err(_) => return Err((req, ServerError::Internal)),
+        },
+        Err(_) => return Err((req, ServerError::BadRequest)),
+    };
+
+    let (_, mut new_payload) = h1::Payload::create(true);
+    new_payload.unread_data(json.into());
+    req.set_payload(new_payload.into());
+
+    // The length is different now and handlers only accept JSON.
+    let headers = req.headers_mut();
+    headers.remove(header::CONTENT_LENGTH);
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    Ok(req)
+}
+
+/// Replace a JSON response body with the equivalent MessagePack. The response is left as it is,
+/// if the body can't be converted.
+fn convert_response(mut res: ServiceResponse<Body>) -> ServiceResponse<Body> {
+    let msgpack = match res.response().body() {
+        ResponseBody::Body(Body::Bytes(bytes)) => serde_json::from_slice::<Value>(bytes)
+            .ok()
+            .and_then(|value| rmp_serde::to_vec_named(&value).ok()),
+        _ => None,
+    };
+
+    match msgpack {
+        Some(msgpack) => {
+            res.headers_mut()
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static(MSGPACK));
+            res.map_body(|_, _| ResponseBody::Body(Body::from(msgpack)))
+        }
+        None => res,
+    }
+}
+
+/// Check whether a response contains a complete JSON body.
+fn has_json_body(res: &ServiceResponse<Body>) -> bool {
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| media_type(value) == "application/json")
+        .unwrap_or(false);
+
+    is_json && matches!(res.response().body(), ResponseBody::Body(Body::Bytes(_)))
+}
+
+/// Check whether a header lists MessagePack as one of its media types.
+fn has_media_type(headers: &HeaderMap, name: header::HeaderName) -> bool {
+    headers
+        .get_all(name)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(media_type)
+        .any(|media_type| media_type == MSGPACK || media_type == X_MSGPACK)
+}
+
+/// Get the media type from a header value, leaving out any parameters.
+fn media_type(value: &str) -> String {
+    value
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
@@ -0,0 +1,248 @@
+use crate::database;
+use crate::database::{Change, DbPool, EntityType, Visibility};
+use crate::shutdown::Shutdown;
+use actix_http::ws::Message;
+use anyhow::Result;
+use futures::channel::mpsc::UnboundedSender;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// The time to wait before checking for new events again.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The maximum number of events that are broadcasted at once.
+const BATCH_SIZE: i64 = 100;
+
+/// An entity that is being edited by a user.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EditedEntity {
+    pub entity_type: EntityType,
+    pub entity_id: String,
+}
+
+/// A message sent by a WebSocket client.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ClientMessage {
+    /// The user started editing an entity.
+    Editing(EditedEntity),
+
+    /// The user stopped editing.
+    Stopped,
+}
+
+/// A message sent to WebSocket clients.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ServerMessage {
+    /// A user started editing an entity.
+    Editing {
+        username: String,
+        #[serde(flatten)]
+        entity: EditedEntity,
+    },
+
+    /// A user stopped editing an entity.
+    Stopped {
+        username: String,
+        #[serde(flatten)]
+        entity: EditedEntity,
+    },
+
+    /// An entity was changed.
+    Change(Change),
+}
+
+/// A connected WebSocket client.
+struct Client {
+    /// The authenticated user, if any. Anonymous clients only receive messages.
+    username: Option<String>,
+
+    /// The entity that the user is currently editing.
+    editing: Option<EditedEntity>,
+
+    /// The channel for sending messages to the client.
+    sender: UnboundedSender<Message>,
+}
+
+/// Keeps track of all connected WebSocket clients and broadcasts presence information and change
+/// notifications to them.
+#[derive(Default)]
+pub struct Hub {
+    clients: Mutex<HashMap<usize, Client>>,
+    next_id: AtomicUsize,
+}
+
+impl Hub {
+    /// Create a new hub without any clients.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get access to the connected clients. The map of clients is valid even if another thread
+    /// panicked while holding the lock, so a poisoned lock is just taken over.
+    fn clients(&self) -> MutexGuard<'_, HashMap<usize, Client>> {
+        self.clients.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Register a new client and send it the current presence information. This returns an ID
+    /// that identifies the client later.
+    pub fn connect(&self, username: Option<String>, sender: UnboundedSender<Message>) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut clients = self.clients();
+
+        for client in clients.values() {
+            if let (Some(username), Some(entity)) = (&client.username, &client.editing) {
+                let message = ServerMessage::Editing {
+                    username: username.clone(),
+                    entity: entity.clone(),
+                };
+
+                send(&sender, &message);
+            }
+        }
+
+        clients.insert(
+            id,
+            Client {
+                username,
+                editing: None,
+                sender,
+            },
+        );
+
+        id
+    }
+
+    /// Remove a client. If it was editing an entity, the other clients will be notified.
+    pub fn disconnect(&self, id: usize) {
+        self.set_editing(id, None);
+        self.clients().remove(&id);
+    }
+
+    /// Handle a message from a client. Messages from anonymous clients are ignored.
+    pub fn handle(&self, id: usize, message: ClientMessage) {
+        match message {
+            ClientMessage::Editing(entity) => self.set_editing(id, Some(entity)),
+            ClientMessage::Stopped => self.set_editing(id, None),
+        }
+    }
+
+    /// Update the entity that a client is editing and notify the other clients.
+    fn set_editing(&self, id: usize, editing: Option<EditedEntity>) {
+        let mut clients = self.clients();
+
+        let (username, previous) = match clients.get_mut(&id) {
+            Some(Client {
+                username: Some(username),
+                editing: current,
+                ..
+            }) => {
+                if *current == editing {
+                    return;
+                }
+
+                let previous = std::mem::replace(current, editing.clone());
+                (username.clone(), previous)
+            }
+            _ => return,
+        };
+
+        if let Some(entity) = previous {
+            let message = ServerMessage::Stopped {
+                username: username.clone(),
+                entity,
+            };
+
+            broadcast(&mut clients, Some(id), &message);
+        }
+
+        if let Some(entity) = editing {
+            let message = ServerMessage::Editing { username, entity };
+            broadcast(&mut clients, Some(id), &message);
+        }
+    }
+
+    /// Notify all clients that may see the changed entity about the change.
+    pub fn notify(&self, change: Change, visibility: &Visibility) {
+        let message = ServerMessage::Change(change);
+
+        self.clients().retain(|_, client| {
+            let username = client.username.as_deref().unwrap_or_default();
+            !visibility.is_visible_to_name(username) || send(&client.sender, &message)
+        });
+    }
+
+    /// Check whether there are any connected clients.
+    fn is_empty(&self) -> bool {
+        self.clients().is_empty()
+    }
+}
+
+/// Send a message to all clients except for the one with the ID `except`. Clients that can't be
+/// reached anymore are removed.
+fn broadcast(clients: &mut HashMap<usize, Client>, except: Option<usize>, message: &ServerMessage) {
+    clients.retain(|id, client| Some(*id) == except || send(&client.sender, message));
+}
+
+/// Send a message to one client. This returns false, if the client is not connected anymore.
+fn send(sender: &UnboundedSender<Message>, message: &ServerMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => sender.unbounded_send(Message::Text(text)).is_ok(),
+        Err(_) => true,
+    }
+}
+
+/// Start broadcasting notifications on new events to the clients of a hub in a background thread.
+/// The thread stops once the server shuts down.
+pub fn spawn(hub: Arc<Hub>, pool: DbPool, shutdown: Shutdown) {
+    std::thread::spawn(move || {
+        let mut last = None;
+
+        while let Some(job) = shutdown.start_job() {
+            if let Err(error) = notify_changes(&hub, &pool, &mut last) {
+                println!("{:?}", error);
+            }
+
+            drop(job);
+            shutdown.sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Broadcast all events after the last known one. If there is no known event yet, this will just
+/// remember the latest one.
+fn notify_changes(hub: &Hub, pool: &DbPool, last: &mut Option<i64>) -> Result<()> {
+    let conn = pool.get()?;
+
+    let after = match last {
+        Some(after) => *after,
+        None => {
+            *last = Some(database::get_last_event_id(&conn)?);
+            return Ok(());
+        }
+    };
+
+    let events = database::get_events_after(&conn, after, BATCH_SIZE)?;
+
+    if let Some(event) = events.last() {
+        *last = Some(event.id);
+    }
+
+    if !hub.is_empty() {
+        for event in events {
+            let visibility =
+                database::get_last_visibility(&conn, event.entity_type, &event.entity_id)?;
+
+            if let Some(visibility) = visibility {
+                hub.notify(event.into(), &visibility);
+            }
+        }
+    }
+
+    Ok(())
+}
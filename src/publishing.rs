@@ -0,0 +1,277 @@
+use crate::database;
+use crate::database::{DbPool, Dump};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sodiumoxide::crypto::sign;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+/// The number of published dumps that are kept by default.
+const DEFAULT_KEEP: usize = 7;
+
+/// The beginning of the names of published dumps. The rest of the name is the time of creation.
+const PREFIX: &str = "wolfgang-dump-";
+
+/// The format of the time within the names of published dumps.
+const TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// A dump that was published together with its checksum and signature.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedDump {
+    /// The name of the JSON file containing the dump.
+    pub name: String,
+
+    pub created_at: NaiveDateTime,
+
+    /// The size of the dump in bytes.
+    pub size: u64,
+
+    /// The hexadecimal SHA-256 checksum of the dump.
+    pub sha256: String,
+
+    /// The name of the file containing the checksum in the format of "sha256sum".
+    pub checksum_file: String,
+
+    /// The name of the file containing the base64 encoded Ed25519 signature of the checksum file.
+    pub signature_file: String,
+}
+
+/// All dumps that are currently published.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedDumps {
+    /// The base64 encoded Ed25519 public key for verifying the signatures.
+    pub public_key: String,
+
+    /// The published dumps, most recent first.
+    pub dumps: Vec<PublishedDump>,
+}
+
+/// Regularly writes signed dumps of all public data into a directory, so that the data can be
+/// mirrored and reused by others.
+pub struct DumpPublisher {
+    directory: PathBuf,
+    secret_key: sign::SecretKey,
+    keep: usize,
+}
+
+impl DumpPublisher {
+    /// Read the directory from the environment variable "WOLFGANG_PUBLIC_DUMP_PATH". If it is not
+    /// set, no dumps are published. The signing key is read from "WOLFGANG_DUMP_SIGNING_KEY" and
+    /// is required in that case. "WOLFGANG_PUBLIC_DUMP_KEEP" sets the number of kept dumps.
+    pub fn from_env() -> Result<Option<Self>> {
+        let directory = match std::env::var("WOLFGANG_PUBLIC_DUMP_PATH") {
+            Ok(directory) => PathBuf::from(directory),
+            Err(_) => return Ok(None),
+        };
+
+        let key = std::env::var("WOLFGANG_DUMP_SIGNING_KEY")
+            .map_err(|_| anyhow!("WOLFGANG_DUMP_SIGNING_KEY is required for publishing dumps!"))?;
+
+        let secret_key = sign::SecretKey::from_slice(&base64::decode(key.trim())?)
+            .ok_or_else(|| anyhow!("Invalid WOLFGANG_DUMP_SIGNING_KEY!"))?;
+
+        let keep = match std::env::var("WOLFGANG_PUBLIC_DUMP_KEEP") {
+            Ok(keep) => keep
+                .parse()
+                .map_err(|_| anyhow!("Invalid WOLFGANG_PUBLIC_DUMP_KEEP: {}", keep))?,
+            Err(_) => DEFAULT_KEEP,
+        };
+
+        Ok(Some(Self {
+            directory,
+            secret_key,
+            keep: keep.max(1),
+        }))
+    }
+
+    /// Write a new dump together with its checksum and signature and remove dumps that exceed
+    /// the number of kept ones. All files are written to temporary files first, so that
+    /// incomplete dumps are never listed.
+    pub fn publish(&self, pool: &DbPool) -> Result<PublishedDump> {
+        let conn = pool.get()?;
+        let dump = database::get_dump(&conn)?;
+        let data = serde_json::to_vec(&dump)?;
+
+        let name = format!("{}{}.json", PREFIX, Utc::now().format(TIME_FORMAT));
+        let checksum = format!("{:x}  {}\n", Sha256::digest(&data), name);
+        let signature = sign::sign_detached(checksum.as_bytes(), &self.secret_key);
+
+        // The dump itself is renamed last, because it is what makes the others visible.
+        self.write(&checksum_name(&name), checksum.as_bytes())?;
+        self.write(&signature_name(&name), base64::encode(signature).as_bytes())?;
+        self.write(&name, &data)?;
+
+        self.prune()?;
+
+        self.get(&name)?
+            .ok_or_else(|| anyhow!("Published dump is missing: {}", name))
+    }
+
+    /// List all published dumps, most recent first.
+    pub fn list(&self) -> Result<PublishedDumps> {
+        let mut dumps = Vec::new();
+
+        for name in self.names()? {
+            if let Some(dump) = self.get(&name)? {
+                dumps.push(dump);
+            }
+        }
+
+        Ok(PublishedDumps {
+            public_key: base64::encode(self.secret_key.public_key()),
+            dumps,
+        })
+    }
+
+    /// Load one of the files of a published dump. Returns [`None`], if the name doesn't refer to
+    /// a dump, its checksum or its signature.
+    pub fn load(&self, file: &str) -> Result<Option<Vec<u8>>> {
+        let published = self.names()?.iter().any(|name| {
+            file == name || file == checksum_name(name) || file == signature_name(name)
+        });
+
+        if published {
+            Ok(Some(std::fs::read(self.directory.join(file))?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get the names of all published dumps, most recent first.
+    fn names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+
+        for entry in std::fs::read_dir(&self.directory)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+
+            if parse_time(&name).is_some() {
+                names.push(name);
+            }
+        }
+
+        // The time format sorts chronologically.
+        names.sort();
+        names.reverse();
+
+        Ok(names)
+    }
+
+    /// Get information on a published dump, if it is complete.
+    fn get(&self, name: &str) -> Result<Option<PublishedDump>> {
+        let created_at = match parse_time(name) {
+            Some(created_at) => created_at,
+            None => return Ok(None),
+        };
+
+        let checksum = match std::fs::read_to_string(self.directory.join(checksum_name(name))) {
+            Ok(checksum) => checksum,
+            Err(_) => return Ok(None),
+        };
+
+        let size = std::fs::metadata(self.directory.join(name))?.len();
+
+        Ok(Some(PublishedDump {
+            name: name.to_string(),
+            created_at,
+            size,
+            sha256: checksum
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            checksum_file: checksum_name(name),
+            signature_file: signature_name(name),
+        }))
+    }
+
+    /// Remove the oldest dumps, so that only the configured number of them is left.
+    fn prune(&self) -> Result<()> {
+        for name in self.names()?.iter().skip(self.keep) {
+            std::fs::remove_file(self.directory.join(name))?;
+
+            for file in &[checksum_name(name), signature_name(name)] {
+                let path = self.directory.join(file);
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write a file using a temporary file first.
+    fn write(&self, name: &str, data: &[u8]) -> Result<()> {
+        let path = self.directory.join(name);
+        let temp_path = self.directory.join(format!(".{}.tmp", name));
+
+        std::fs::write(&temp_path, data)?;
+        std::fs::rename(&temp_path, &path)?;
+
+        Ok(())
+    }
+}
+
+/// Generate a new key pair for signing dumps. Returns the base64 encoded secret and public key.
+pub fn generate_signing_key() -> (String, String) {
+    let (public_key, secret_key) = sign::gen_keypair();
+    (base64::encode(secret_key), base64::encode(public_key))
+}
+
+/// Read a published dump after verifying the signature of its checksum file and its checksum.
+/// The checksum and signature files are expected next to the dump. `public_key` is the base64
+/// encoded public key of the server that published the dump.
+pub fn read_published_dump(path: &str, public_key: &str) -> Result<Dump> {
+    let public_key = sign::PublicKey::from_slice(&base64::decode(public_key.trim())?)
+        .ok_or_else(|| anyhow!("Invalid public key!"))?;
+
+    let checksum = std::fs::read_to_string(checksum_name(path))?;
+    let signature = base64::decode(std::fs::read_to_string(signature_name(path))?.trim())?;
+    let signature = sign::Signature::try_from(&signature[..])
+        .map_err(|_| anyhow!("Invalid signature file!"))?;
+
+    if !sign::verify_detached(&signature, checksum.as_bytes(), &public_key) {
+        return Err(anyhow!("The signature of the checksum file is invalid!"));
+    }
+
+    // The checksum file has to refer to this dump, so that it can't be swapped with another one.
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut parts = checksum.split_whitespace();
+    let (expected, checksum_name) = (parts.next(), parts.next());
+
+    if checksum_name != Some(name.as_str()) {
+        return Err(anyhow!("The checksum file belongs to another dump!"));
+    }
+
+    let data = std::fs::read(path)?;
+
+    if expected != Some(format!("{:x}", Sha256::digest(&data)).as_str()) {
+        return Err(anyhow!("The checksum of the dump doesn't match!"));
+    }
+
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Get the time of creation from the name of a published dump.
+fn parse_time(name: &str) -> Option<NaiveDateTime> {
+    let time = name.strip_prefix(PREFIX)?.strip_suffix(".json")?;
+    NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok()
+}
+
+/// Get the name of the checksum file of a dump.
+fn checksum_name(name: &str) -> String {
+    format!("{}.sha256", name)
+}
+
+/// Get the name of the signature file of a dump.
+fn signature_name(name: &str) -> String {
+    format!("{}.sig", name)
+}
@@ -0,0 +1,315 @@
+use crate::cache::ResponseCache;
+use crate::database;
+use crate::database::{generate_id, DbPool, User};
+use crate::error::ServerError;
+use crate::routes::StatisticsCache;
+use crate::search;
+use crate::search::SearchIndex;
+use crate::shutdown::Shutdown;
+use actix_web::web;
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// The maximum number of jobs that are kept for reporting their progress.
+const MAX_JOBS: usize = 20;
+
+/// Data that is derived from the actual tables and can be rebuilt. The targets are processed in
+/// the order of this declaration, so that later targets already use the results of earlier ones.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum RebuildTarget {
+    /// The normalized names and titles of all entities.
+    Names,
+
+    /// The fully resolved representations of recordings and mediums.
+    ReadModels,
+
+    /// The precomputed number of entities of each type.
+    Statistics,
+
+    /// The documents within the search index.
+    SearchIndex,
+}
+
+/// The state of a rebuild job or of one of its targets.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RebuildStatus {
+    Pending,
+    Running,
+    Finished,
+    Failed,
+}
+
+/// The progress of rebuilding one target.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetProgress {
+    pub target: RebuildTarget,
+    pub status: RebuildStatus,
+
+    /// The number of finished steps, e.g. entities.
+    pub done: usize,
+
+    /// The number of all steps, if it is known already.
+    pub total: usize,
+
+    /// What went wrong, if the target failed.
+    pub error: Option<String>,
+}
+
+/// A background job rebuilding derived data.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuildJob {
+    pub id: String,
+
+    /// The job has failed, if any of its targets failed.
+    pub status: RebuildStatus,
+
+    pub targets: Vec<TargetProgress>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+/// Everything a rebuild job needs access to.
+#[derive(Clone)]
+struct Context {
+    jobs: Arc<Mutex<Vec<RebuildJob>>>,
+    pool: DbPool,
+    index: Option<SearchIndex>,
+    statistics: web::Data<StatisticsCache>,
+    cache: web::Data<ResponseCache>,
+}
+
+/// Runs rebuild jobs one at a time in a background thread and keeps track of their progress.
+pub struct Rebuilds {
+    context: Context,
+    shutdown: Shutdown,
+}
+
+impl Rebuilds {
+    /// Create a new handle without any jobs.
+    pub fn new(
+        pool: DbPool,
+        index: Option<SearchIndex>,
+        statistics: web::Data<StatisticsCache>,
+        cache: web::Data<ResponseCache>,
+        shutdown: Shutdown,
+    ) -> Self {
+        Self {
+            context: Context {
+                jobs: Arc::new(Mutex::new(Vec::new())),
+                pool,
+                index,
+                statistics,
+                cache,
+            },
+            shutdown,
+        }
+    }
+
+    /// Get all known jobs, the most recent one first.
+    pub fn get_jobs(&self) -> Vec<RebuildJob> {
+        let jobs = self.context.jobs();
+        jobs.iter().rev().cloned().collect()
+    }
+
+    /// Get a job by its ID.
+    pub fn get_job(&self, id: &str) -> Option<RebuildJob> {
+        let jobs = self.context.jobs();
+        jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    /// Start a job rebuilding the provided targets in the background and return its initial
+    /// state. This fails with "409 Conflict", if another job is still running or the server is
+    /// shutting down, and with "400 Bad Request", if the search index should be rebuilt without
+    /// being configured. Changes to entities are made on behalf of the user.
+    pub fn start(
+        &self,
+        mut targets: Vec<RebuildTarget>,
+        user: User,
+    ) -> Result<RebuildJob, ServerError> {
+        targets.sort();
+        targets.dedup();
+
+        if targets.is_empty()
+            || (targets.contains(&RebuildTarget::SearchIndex) && self.context.index.is_none())
+        {
+            return Err(ServerError::BadRequest);
+        }
+
+        let mut jobs = self.context.jobs();
+
+        if jobs.iter().any(|job| job.finished_at.is_none()) {
+            return Err(ServerError::Conflict);
+        }
+
+        let guard = self.shutdown.start_job().ok_or(ServerError::Conflict)?;
+
+        let job = RebuildJob {
+            id: generate_id(),
+            status: RebuildStatus::Running,
+            targets: targets
+                .iter()
+                .map(|target| TargetProgress {
+                    target: *target,
+                    status: RebuildStatus::Pending,
+                    done: 0,
+                    total: 0,
+                    error: None,
+                })
+                .collect(),
+            started_at: Utc::now().naive_utc(),
+            finished_at: None,
+        };
+
+        jobs.push(job.clone());
+        if jobs.len() > MAX_JOBS {
+            jobs.remove(0);
+        }
+
+        let context = self.context.clone();
+        let id = job.id.clone();
+
+        std::thread::spawn(move || {
+            let running = Running {
+                context: context.clone(),
+                id: id.clone(),
+            };
+
+            context.run(&id, &targets, &user);
+            drop(running);
+            drop(guard);
+        });
+
+        Ok(job)
+    }
+}
+
+/// Marks a job as failed when it is dropped without the job being finished, i.e. if the thread
+/// running it panicked. Otherwise, no other job could be started afterwards.
+struct Running {
+    context: Context,
+    id: String,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.context.update(&self.id, |job| {
+            if job.finished_at.is_none() {
+                for progress in &mut job.targets {
+                    if progress.status == RebuildStatus::Running {
+                        progress.status = RebuildStatus::Failed;
+                        progress.error = Some(String::from("The rebuild was aborted"));
+                    }
+                }
+
+                job.status = RebuildStatus::Failed;
+                job.finished_at = Some(Utc::now().naive_utc());
+            }
+        });
+    }
+}
+
+impl Context {
+    /// Get access to the jobs. Their states stay valid even if a thread panicked while holding
+    /// the lock, so a poisoned lock is just taken over.
+    fn jobs(&self) -> MutexGuard<'_, Vec<RebuildJob>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Rebuild all targets of a job one after another. Failing targets don't prevent the
+    /// following ones from being rebuilt.
+    fn run(&self, id: &str, targets: &[RebuildTarget], user: &User) {
+        let mut failed = false;
+
+        for (position, target) in targets.iter().enumerate() {
+            self.update(id, |job| {
+                job.targets[position].status = RebuildStatus::Running
+            });
+
+            let result = self
+                .rebuild(*target, user, &mut |done, total| {
+                    self.update(id, |job| {
+                        job.targets[position].done = done;
+                        job.targets[position].total = total;
+                    })
+                })
+                .and_then(|_| self.cache.invalidate());
+
+            self.update(id, |job| {
+                let progress = &mut job.targets[position];
+
+                match &result {
+                    Ok(()) => progress.status = RebuildStatus::Finished,
+                    Err(error) => {
+                        progress.status = RebuildStatus::Failed;
+                        progress.error = Some(error.to_string());
+                    }
+                }
+            });
+
+            if let Err(error) = result {
+                println!("{:?}", error);
+                failed = true;
+            }
+        }
+
+        self.update(id, |job| {
+            job.status = if failed {
+                RebuildStatus::Failed
+            } else {
+                RebuildStatus::Finished
+            };
+
+            job.finished_at = Some(Utc::now().naive_utc());
+        });
+    }
+
+    /// Rebuild one target and report the progress.
+    fn rebuild(
+        &self,
+        target: RebuildTarget,
+        user: &User,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<()> {
+        let conn = self.pool.get()?;
+
+        match target {
+            RebuildTarget::Names => {
+                database::normalize_names(&conn, user, progress)?;
+            }
+            RebuildTarget::ReadModels => {
+                database::rebuild_read_models(&conn, progress)?;
+            }
+            RebuildTarget::Statistics => {
+                progress(0, 1);
+                let data = database::compute_statistics(&conn)?;
+                *self.statistics.write().unwrap() = Some(data);
+                progress(1, 1);
+            }
+            RebuildTarget::SearchIndex => {
+                let index = self
+                    .index
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("There is no search index!"))?;
+
+                search::rebuild(index, &conn, progress)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Change the state of a job.
+    fn update<F: FnOnce(&mut RebuildJob)>(&self, id: &str, f: F) {
+        let mut jobs = self.jobs();
+
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            f(job);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use crate::cache::ResponseCache;
+use crate::database;
+use crate::database::{ChangeFeed, ChangeRecord, DbConn, DbPool, Ensemble, EntityType};
+use crate::database::{ExternalId, Instrument, Label, Medium, Person, Recording, User, Work};
+use crate::error::ServerError;
+use crate::shutdown::Shutdown;
+use crate::validation::MAX_ID_LENGTH;
+use actix_web::web;
+use anyhow::{anyhow, bail, Result};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// The time to wait between pulling changes from the upstream server.
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// The timeout for a single request to the upstream server.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum length of ID prefixes. Longer upstream IDs are replaced with a hash, so that the
+/// prefixed ID stays within the maximum length of IDs. At least 32 hex digits are left for that.
+const MAX_PREFIX_LENGTH: usize = MAX_ID_LENGTH - 33;
+
+/// How to handle upstream changes to entities that were changed locally as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Overwrite local changes with the upstream data.
+    Upstream,
+
+    /// Keep local changes and ignore further upstream changes to the entity.
+    Local,
+}
+
+impl ConflictPolicy {
+    /// Get a policy from its string representation.
+    pub fn parse(policy: &str) -> Option<ConflictPolicy> {
+        match policy {
+            "upstream" => Some(ConflictPolicy::Upstream),
+            "local" => Some(ConflictPolicy::Local),
+            _ => None,
+        }
+    }
+}
+
+/// The configuration for pulling changes from another wolfgang server.
+#[derive(Clone)]
+pub struct Replication {
+    upstream: String,
+    username: String,
+    token: Option<String>,
+    prefix: Option<String>,
+    conflicts: ConflictPolicy,
+    agent: ureq::Agent,
+}
+
+impl Replication {
+    /// Create the configuration, if the environment variable "WOLFGANG_REPLICATION_UPSTREAM" is
+    /// set to the URL of the upstream server. Changes are applied on behalf of the user named in
+    /// "WOLFGANG_REPLICATION_USER". If the upstream server requires authentication, a token can
+    /// be provided using "WOLFGANG_REPLICATION_TOKEN". Upstream IDs are prefixed with the value of
+    /// "WOLFGANG_REPLICATION_PREFIX", if it is set. "WOLFGANG_REPLICATION_CONFLICTS" selects the
+    /// [`ConflictPolicy`] and defaults to "upstream".
+    pub fn from_env() -> Result<Option<Self>> {
+        let upstream = match std::env::var("WOLFGANG_REPLICATION_UPSTREAM") {
+            Ok(upstream) => upstream,
+            Err(_) => return Ok(None),
+        };
+
+        let username = std::env::var("WOLFGANG_REPLICATION_USER")
+            .map_err(|_| anyhow!("WOLFGANG_REPLICATION_USER is required for replication!"))?;
+
+        let prefix = std::env::var("WOLFGANG_REPLICATION_PREFIX").ok();
+        if let Some(prefix) = &prefix {
+            if prefix.is_empty()
+                || prefix.len() > MAX_PREFIX_LENGTH
+                || !prefix.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                bail!("Invalid replication prefix: {}", prefix);
+            }
+        }
+
+        let conflicts = match std::env::var("WOLFGANG_REPLICATION_CONFLICTS") {
+            Ok(policy) => ConflictPolicy::parse(&policy)
+                .ok_or_else(|| anyhow!("Invalid conflict policy: {}", policy))?,
+            Err(_) => ConflictPolicy::Upstream,
+        };
+
+        Ok(Some(Self {
+            upstream: upstream.trim_end_matches('/').to_string(),
+            username,
+            token: std::env::var("WOLFGANG_REPLICATION_TOKEN").ok(),
+            prefix,
+            conflicts,
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+        }))
+    }
+
+    /// Check that replication can continue after importing a dump of the upstream server. The
+    /// dump keeps the upstream IDs, so this fails if a prefix is configured. Otherwise, each
+    /// entity would be replicated a second time using its prefixed ID.
+    pub fn check_dump_import(&self) -> Result<()> {
+        if self.prefix.is_some() {
+            bail!("Dumps can't be continued using replication with a prefix!");
+        }
+
+        Ok(())
+    }
+
+    /// Continue replication after the provided revision, e.g. after importing a dump of the
+    /// upstream server. See [`Replication::check_dump_import`].
+    pub fn start_after(&self, conn: &DbConn, revision: i64) -> Result<()> {
+        self.check_dump_import()?;
+        database::set_replication_revision(conn, &self.upstream, revision)
+    }
+
+    /// Get the local ID for an ID of the upstream server. If the prefixed ID would be too long,
+    /// the upstream ID is replaced with as much of its hash as fits.
+    fn local_id(&self, id: &str) -> String {
+        match &self.prefix {
+            Some(prefix) if prefix.len() + 1 + id.len() > MAX_ID_LENGTH => {
+                let mut hash = format!("{:x}", Sha256::digest(id.as_bytes()));
+                hash.truncate(MAX_ID_LENGTH - prefix.len() - 1);
+                format!("{}-{}", prefix, hash)
+            }
+            Some(prefix) => format!("{}-{}", prefix, id),
+            None => id.to_string(),
+        }
+    }
+
+    /// Create a request to the upstream server.
+    fn request(&self, path: &str) -> ureq::Request {
+        let request = self.agent.get(&format!("{}{}", self.upstream, path));
+
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+}
+
+/// Start pulling changes from the upstream server in a background thread. The thread stops once
+/// the server shuts down.
+pub fn spawn(
+    replication: Replication,
+    pool: DbPool,
+    cache: web::Data<ResponseCache>,
+    shutdown: Shutdown,
+) {
+    std::thread::spawn(move || {
+        while let Some(job) = shutdown.start_job() {
+            if let Err(error) = pull(&replication, &pool, &cache) {
+                println!("{:?}", error);
+            }
+
+            drop(job);
+            shutdown.sleep(INTERVAL);
+        }
+    });
+}
+
+/// Apply all changes of the upstream server since the last known revision. The revision is
+/// remembered after each part of the changefeed, so a failed change will be retried next time.
+/// Cached responses are invalidated after each part that contained changes.
+fn pull(replication: &Replication, pool: &DbPool, cache: &ResponseCache) -> Result<()> {
+    let conn = pool.get()?;
+
+    let user = database::get_user(&conn, &replication.username)?
+        .ok_or_else(|| anyhow!("Unknown replication user: {}", replication.username))?;
+
+    let mut since = database::get_replication_revision(&conn, &replication.upstream)?;
+
+    loop {
+        let feed: ChangeFeed = replication
+            .request("/export/changes")
+            .query("since", &since.to_string())
+            .call()?
+            .into_json()?;
+
+        let mut changes = feed.changes;
+
+        // Referenced entities have to exist before the entities referring to them and have to be
+        // deleted after them.
+        changes.sort_by_key(|change| match change.payload {
+            Some(_) => (0, get_rank(change.entity_type)),
+            None => (1, EntityType::ALL.len() - get_rank(change.entity_type)),
+        });
+
+        // Changes that were applied before a failing one have to be visible as well.
+        let result = changes
+            .iter()
+            .try_for_each(|change| apply(replication, &conn, &user, change));
+
+        if !changes.is_empty() {
+            cache.invalidate()?;
+        }
+
+        result?;
+
+        database::set_replication_revision(&conn, &replication.upstream, feed.revision)?;
+        since = feed.revision;
+
+        if !feed.more {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply one change of the upstream server locally.
+fn apply(
+    replication: &Replication,
+    conn: &DbConn,
+    user: &User,
+    change: &ChangeRecord,
+) -> Result<()> {
+    let id = replication.local_id(&change.entity_id);
+
+    if replication.conflicts == ConflictPolicy::Local {
+        let author = database::get_last_event_author(conn, change.entity_type, &id)?;
+
+        if matches!(author, Some(author) if author != user.username) {
+            println!(
+                "Keeping local changes to {} {}",
+                change.entity_type.as_str(),
+                id
+            );
+
+            return Ok(());
+        }
+    }
+
+    let payload = match &change.payload {
+        Some(payload) => payload.clone(),
+        None => return delete(conn, user, change.entity_type, &id),
+    };
+
+    match change.entity_type {
+        EntityType::Person => {
+            let mut person: Person = serde_json::from_value(payload)?;
+            localize_person(replication, conn, &mut person)?;
+            database::update_person(conn, &person, user)
+        }
+        EntityType::Ensemble => {
+            let mut ensemble: Ensemble = serde_json::from_value(payload)?;
+            localize_ensemble(replication, &mut ensemble);
+            database::update_ensemble(conn, &ensemble, user)
+        }
+        EntityType::Instrument => {
+            let mut instrument: Instrument = serde_json::from_value(payload)?;
+            localize_instrument(replication, &mut instrument);
+            database::update_instrument(conn, &instrument, user)
+        }
+        EntityType::Label => {
+            let mut label: Label = serde_json::from_value(payload)?;
+            localize_label(replication, &mut label);
+            database::update_label(conn, &label, user)
+        }
+        EntityType::Work => {
+            let mut work: Work = serde_json::from_value(payload)?;
+            localize_work(replication, conn, &mut work)?;
+            database::update_work(conn, &work, user)
+        }
+        EntityType::Recording => {
+            let mut recording: Recording = serde_json::from_value(payload)?;
+            localize_recording(replication, conn, &mut recording)?;
+            database::update_recording(conn, &recording, user)
+        }
+        EntityType::Medium => {
+            let mut medium: Medium = serde_json::from_value(payload)?;
+            localize_medium(replication, conn, &mut medium)?;
+            database::update_medium(conn, &medium, user)
+        }
+    }
+}
+
+/// Delete an entity that was deleted upstream. Entities that are still referenced by local
+/// entities are kept.
+fn delete(conn: &DbConn, user: &User, entity_type: EntityType, id: &str) -> Result<()> {
+    if !database::entity_exists(conn, entity_type, id)? {
+        return Ok(());
+    }
+
+    let result = match entity_type {
+        EntityType::Person => database::delete_person(conn, id, user),
+        EntityType::Ensemble => database::delete_ensemble(conn, id, user),
+        EntityType::Instrument => database::delete_instrument(conn, id, user),
+        EntityType::Label => database::delete_label(conn, id, user),
+        EntityType::Work => database::delete_work(conn, id, user),
+        EntityType::Recording => database::delete_recording(conn, id, user),
+        EntityType::Medium => database::delete_medium(conn, id, user),
+    };
+
+    match result {
+        Err(error) if matches!(error.downcast_ref(), Some(ServerError::Referenced(_))) => {
+            println!("Keeping referenced {} {}", entity_type.as_str(), id);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Get the position of an entity type within the order in which changes are applied.
+fn get_rank(entity_type: EntityType) -> usize {
+    match entity_type {
+        EntityType::Person => 0,
+        EntityType::Ensemble => 1,
+        EntityType::Instrument => 2,
+        EntityType::Label => 3,
+        EntityType::Work => 4,
+        EntityType::Recording => 5,
+        EntityType::Medium => 6,
+    }
+}
+
+/// Use local IDs within a person. Periods are defined per server, so unknown ones are dropped.
+fn localize_person(replication: &Replication, conn: &DbConn, person: &mut Person) -> Result<()> {
+    person.id = replication.local_id(&person.id);
+    person.period = localize_period(conn, person.period.take())?;
+
+    Ok(())
+}
+
+/// Use local IDs within an ensemble.
+fn localize_ensemble(replication: &Replication, ensemble: &mut Ensemble) {
+    ensemble.id = replication.local_id(&ensemble.id);
+}
+
+/// Use local IDs within an instrument.
+fn localize_instrument(replication: &Replication, instrument: &mut Instrument) {
+    instrument.id = replication.local_id(&instrument.id);
+}
+
+/// Use local IDs within a label.
+fn localize_label(replication: &Replication, label: &mut Label) {
+    label.id = replication.local_id(&label.id);
+}
+
+/// Use local IDs within a work and all entities it refers to.
+fn localize_work(replication: &Replication, conn: &DbConn, work: &mut Work) -> Result<()> {
+    work.id = replication.local_id(&work.id);
+    work.period = localize_period(conn, work.period.take())?;
+
+    localize_person(replication, conn, &mut work.composer)?;
+
+    for author in &mut work.authors {
+        localize_person(replication, conn, author)?;
+    }
+
+    for part in &mut work.parts {
+        for author in &mut part.authors {
+            localize_person(replication, conn, author)?;
+        }
+    }
+
+    for instrument in &mut work.instruments {
+        localize_instrument(replication, instrument);
+    }
+
+    Ok(())
+}
+
+/// Use local IDs within a recording and all entities it refers to.
+fn localize_recording(
+    replication: &Replication,
+    conn: &DbConn,
+    recording: &mut Recording,
+) -> Result<()> {
+    recording.id = replication.local_id(&recording.id);
+
+    localize_work(replication, conn, &mut recording.work)?;
+
+    for work in &mut recording.additional_works {
+        localize_work(replication, conn, work)?;
+    }
+
+    for performance in &mut recording.performances {
+        if let Some(person) = &mut performance.person {
+            localize_person(replication, conn, person)?;
+        }
+
+        if let Some(ensemble) = &mut performance.ensemble {
+            localize_ensemble(replication, ensemble);
+        }
+
+        if let Some(role) = &mut performance.role {
+            localize_instrument(replication, role);
+        }
+    }
+
+    recording.external_ids = localize_external_ids(
+        conn,
+        EntityType::Recording,
+        &recording.id,
+        &recording.external_ids,
+    )?;
+
+    Ok(())
+}
+
+/// Use local IDs within a medium and all entities it refers to.
+fn localize_medium(replication: &Replication, conn: &DbConn, medium: &mut Medium) -> Result<()> {
+    medium.id = replication.local_id(&medium.id);
+
+    if let Some(label) = &mut medium.label {
+        localize_label(replication, label);
+    }
+
+    for track_set in &mut medium.tracks {
+        localize_recording(replication, conn, &mut track_set.recording)?;
+    }
+
+    medium.external_ids =
+        localize_external_ids(conn, EntityType::Medium, &medium.id, &medium.external_ids)?;
+
+    Ok(())
+}
+
+/// Keep a period only if it exists locally.
+fn localize_period(conn: &DbConn, period: Option<String>) -> Result<Option<String>> {
+    match period {
+        Some(id) if database::get_period(conn, &id)?.is_some() => Ok(Some(id)),
+        _ => Ok(None),
+    }
+}
+
+/// Drop external IDs that already belong to another local entity.
+fn localize_external_ids(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    external_ids: &[ExternalId],
+) -> Result<Vec<ExternalId>> {
+    let mut result = Vec::new();
+
+    for external_id in external_ids {
+        let available =
+            match database::lookup_external_id(conn, external_id.source, &external_id.id)? {
+                Some(entity) => entity.entity_type == entity_type && entity.entity_id == id,
+                None => true,
+            };
+
+        if available {
+            result.push(external_id.clone());
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,135 @@
+use super::Json;
+use super::{authenticate_login, hash_password, verify_password, PasswordParams};
+use crate::database;
+use crate::database::{DbConn, DbPool};
+use crate::error::ServerError;
+use crate::mail;
+use crate::validation::Validate;
+use actix_web::{get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use anyhow::Result;
+use serde::Deserialize;
+
+/// Request body data for changing the password.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordChange {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Request body data for changing the email address.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailChange {
+    pub password: String,
+
+    /// The new email address. If this is not provided, the email address will be removed.
+    pub email: Option<String>,
+}
+
+/// Query parameters for confirming an email change.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EmailConfirmation {
+    pub token: String,
+}
+
+/// Change the password of the current user. This requires a login token and the current password.
+#[post("/account/password")]
+pub async fn change_password(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    password_params: web::Data<PasswordParams>,
+    data: Json<PasswordChange>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let (user, _) =
+            authenticate_login(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+
+        if !verify_password(&data.current_password, &user) {
+            return Err(ServerError::Forbidden);
+        }
+
+        let password_hash = hash_password(&data.new_password, &password_params)?;
+        database::set_password_hash(&conn, &user.username, &password_hash)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Change the email address of the current user. This requires a login token and the current
+/// password. The new address will only be used after it has been confirmed using the token that
+/// is sent to it. Removing the email address takes effect immediately.
+#[post("/account/email")]
+pub async fn change_email(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<EmailChange>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let (user, _) =
+            authenticate_login(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+
+        if !verify_password(&data.password, &user) {
+            return Err(ServerError::Forbidden);
+        }
+
+        match &data.email {
+            Some(email) => request_email_change(&conn, &user.username, email)?,
+            None => database::set_email(&conn, &user.username, None)?,
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Confirm a requested email change using the token that was sent to the new address.
+#[get("/account/email/confirm")]
+pub async fn confirm_email(
+    db: web::Data<DbPool>,
+    query: web::Query<EmailConfirmation>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        database::confirm_email_change(&conn, &query.token)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().body("The email address has been confirmed."))
+}
+
+/// Store a requested email change and send the confirmation token to the new address. If the
+/// environment variable "WOLFGANG_PUBLIC_URL" is set, the mail will contain a link for confirming
+/// the change.
+pub fn request_email_change(conn: &DbConn, username: &str, email: &str) -> Result<()> {
+    let token = database::insert_email_change(conn, username, email)?;
+
+    let confirmation = match std::env::var("WOLFGANG_PUBLIC_URL") {
+        Ok(url) => format!(
+            "{}/account/email/confirm?token={}",
+            url.trim_end_matches('/'),
+            token
+        ),
+        Err(_) => format!("Confirmation token: {}", token),
+    };
+
+    let body = format!(
+        "Hello {},\n\nplease confirm that this is your new email address:\n\n{}\n\nIf you didn't \
+        request this change, you can ignore this message.\n",
+        username, confirmation
+    );
+
+    mail::send_mail(email, "Confirm your email address", &body)
+}
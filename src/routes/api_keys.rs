@@ -0,0 +1,95 @@
+use super::authenticate_login;
+use super::Json;
+use crate::database;
+use crate::database::{ApiKey, DbPool};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// Request body data for creating an API key.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyCreation {
+    /// A name to recognize the key later.
+    pub name: String,
+
+    /// The scopes that the key should grant.
+    pub scopes: Vec<String>,
+}
+
+/// Response body data for a newly created API key.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+
+    /// The actual key. It is only available within this response.
+    pub key: String,
+}
+
+/// Create a new API key for the current user. This requires a login token that has all of the
+/// requested scopes itself.
+#[post("/account/api-keys")]
+pub async fn create_api_key(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<ApiKeyCreation>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let (user, granted) =
+            authenticate_login(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+
+        let (api_key, key) =
+            database::insert_api_key(&conn, &user, &granted, &data.name, &data.scopes)?;
+
+        Ok(CreatedApiKey { api_key, key })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get all API keys of the current user. This requires a login token.
+#[get("/account/api-keys")]
+pub async fn get_api_keys(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let (user, _) =
+            authenticate_login(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+
+        Ok(database::get_api_keys(&conn, &user.username)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Revoke an API key of the current user. This requires a login token.
+#[delete("/account/api-keys/{id}")]
+pub async fn delete_api_key(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let (user, _) =
+            authenticate_login(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+
+        database::delete_api_key(&conn, &id.into_inner(), &user.username)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
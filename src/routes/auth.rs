@@ -1,12 +1,22 @@
-use super::CaptchaManager;
+use super::request_email_change;
+use super::Json;
+use crate::captcha::CaptchaBackend;
 use crate::database;
-use crate::database::{DbConn, DbPool, User, UserInsertion};
+use crate::database::{
+    DbConn, DbPool, NestedEntities, PasswordScheme, Scope, User, UserInsertion, API_KEY_PREFIX,
+};
 use crate::error::ServerError;
+use crate::validation::Validate;
 use actix_web::{get, post, put, web, HttpResponse};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use anyhow::{anyhow, Result};
+use argon2::password_hash::{PasswordHash, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, PasswordVerifier, Version};
+use diesel::Connection;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sodiumoxide::crypto::pwhash::argon2id13;
+use std::convert::TryFrom;
 
 /// Request body data for user registration.
 #[derive(Deserialize, Debug, Clone)]
@@ -17,6 +27,7 @@ pub struct UserRegistration {
     pub email: Option<String>,
     pub captcha_id: String,
     pub answer: String,
+    pub invitation: Option<String>,
 }
 
 /// Request body data for user login.
@@ -25,6 +36,10 @@ pub struct UserRegistration {
 pub struct Login {
     pub username: String,
     pub password: String,
+
+    /// The scopes that the issued token should have. If this is not provided, the token will have
+    /// all scopes that the user may grant.
+    pub scopes: Option<Vec<String>>,
 }
 
 /// Request body data for changing user details.
@@ -36,6 +51,13 @@ pub struct PutUser {
     pub email: Option<String>,
 }
 
+/// Request body data for renaming a user.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Rename {
+    pub username: String,
+}
+
 /// Response body data for getting a user.
 #[derive(Serialize, Debug, Clone)]
 pub struct GetUser {
@@ -43,75 +65,169 @@ pub struct GetUser {
     pub email: Option<String>,
 }
 
+/// Who is allowed to register new users.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RegistrationPolicy {
+    /// Everybody can register.
+    Open,
+
+    /// Registration requires an invitation code created by an administrator.
+    Invitation,
+
+    /// Nobody can register.
+    Closed,
+}
+
+impl RegistrationPolicy {
+    /// Get the registration policy from the environment variable "WOLFGANG_REGISTRATION". Possible
+    /// values are "open" (the default), "invitation" and "closed".
+    pub fn from_env() -> Result<Self> {
+        let policy = match std::env::var("WOLFGANG_REGISTRATION") {
+            Ok(policy) => match policy.as_str() {
+                "open" => RegistrationPolicy::Open,
+                "invitation" => RegistrationPolicy::Invitation,
+                "closed" => RegistrationPolicy::Closed,
+                _ => return Err(anyhow!("Unknown registration policy: {}", policy)),
+            },
+            Err(_) => RegistrationPolicy::Open,
+        };
+
+        Ok(policy)
+    }
+}
+
+/// The parameters for hashing new passwords using Argon2id.
+#[derive(Debug, Clone)]
+pub struct PasswordParams(Params);
+
+impl PasswordParams {
+    /// The default memory cost in KiB.
+    const DEFAULT_M_COST: u32 = 65536;
+
+    /// The default number of iterations.
+    const DEFAULT_T_COST: u32 = 2;
+
+    /// The default degree of parallelism.
+    const DEFAULT_P_COST: u32 = 1;
+
+    /// Get the parameters from the environment variables "WOLFGANG_ARGON2_MEMORY" (in KiB),
+    /// "WOLFGANG_ARGON2_ITERATIONS" and "WOLFGANG_ARGON2_PARALLELISM". They default to 64 MiB, 2
+    /// iterations and no parallelism. This fails, if any of the values is invalid.
+    pub fn from_env() -> Result<Self> {
+        let params = Params::new(
+            argon2_param("WOLFGANG_ARGON2_MEMORY", Self::DEFAULT_M_COST)?,
+            argon2_param("WOLFGANG_ARGON2_ITERATIONS", Self::DEFAULT_T_COST)?,
+            argon2_param("WOLFGANG_ARGON2_PARALLELISM", Self::DEFAULT_P_COST)?,
+            None,
+        )
+        .map_err(|error| anyhow!("Invalid Argon2 parameters: {}", error))?;
+
+        Ok(Self(params))
+    }
+}
+
+/// Read one of the Argon2id parameters from an environment variable.
+fn argon2_param(name: &str, default: u32) -> Result<u32> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| anyhow!("Invalid value for {}: {}", name, value)),
+        Err(_) => Ok(default),
+    }
+}
+
 /// Claims for issued JWTs.
 #[derive(Deserialize, Serialize, Debug, Clone)]
 struct Claims {
     pub iat: u64,
     pub exp: u64,
     pub username: String,
+    pub scopes: Vec<String>,
 }
 
-/// Register a new user.
+/// Register a new user. Depending on the registration policy, this requires a valid invitation
+/// code or is not possible at all.
 #[post("/users")]
 pub async fn register_user(
     db: web::Data<DbPool>,
-    captcha_manager: web::Data<CaptchaManager>,
-    data: web::Json<UserRegistration>,
+    captchas: web::Data<dyn CaptchaBackend>,
+    policy: web::Data<RegistrationPolicy>,
+    password_params: web::Data<PasswordParams>,
+    data: Json<UserRegistration>,
 ) -> Result<HttpResponse, ServerError> {
-    let captcha_manager = captcha_manager.into_inner();
+    data.validate()?;
 
-    if captcha_manager.check_captcha(&data.captcha_id, &data.answer)? {
-        web::block(move || {
-            let conn = db.into_inner().get().or(Err(ServerError::Internal))?;
+    if *policy.get_ref() == RegistrationPolicy::Closed {
+        return Err(ServerError::Forbidden);
+    }
+
+    database::block(move || {
+        if !captchas.check_captcha(&data.captcha_id, &data.answer)? {
+            return Err(ServerError::Forbidden);
+        }
 
+        let conn = db.into_inner().get().or(Err(ServerError::Internal))?;
+        let password_hash =
+            hash_password(&data.password, &password_params).or(Err(ServerError::Internal))?;
+
+        conn.transaction::<(), anyhow::Error, _>(|| {
             database::insert_user(
                 &conn,
                 &data.username,
                 &UserInsertion {
-                    password_hash: hash_password(&data.password).or(Err(ServerError::Internal))?,
+                    password_hash,
                     email: data.email.clone(),
                 },
-            )
-            .or(Err(ServerError::Internal))
-        })
-        .await?;
+            )?;
 
-        Ok(HttpResponse::Ok().finish())
-    } else {
-        Err(ServerError::Forbidden)
-    }
+            if *policy.get_ref() == RegistrationPolicy::Invitation {
+                let code = data.invitation.as_ref().ok_or(ServerError::Forbidden)?;
+                database::use_invitation(&conn, code, &data.username)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
 }
 
 /// Update an existing user. This doesn't use a JWT for authentication but requires the client to
-/// resent the old password.
+/// resent the old password. A changed email address has to be confirmed like when using the
+/// "/account/email" route.
 #[put("/users/{username}")]
 pub async fn put_user(
     db: web::Data<DbPool>,
+    password_params: web::Data<PasswordParams>,
     username: web::Path<String>,
-    data: web::Json<PutUser>,
+    data: Json<PutUser>,
 ) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
     let conn = db.into_inner().get().or(Err(ServerError::Internal))?;
 
-    web::block(move || {
+    database::block(move || {
         let user = database::get_user(&conn, &username)
             .or(Err(ServerError::Internal))?
             .ok_or(ServerError::Unauthorized)?;
 
-        if verify_password(&data.old_password, &user.password_hash) {
-            let password_hash = match &data.new_password {
-                Some(password) => hash_password(password).or(Err(ServerError::Unauthorized))?,
-                None => user.password_hash.clone(),
-            };
+        if verify_password(&data.old_password, &user) {
+            if let Some(password) = &data.new_password {
+                let password_hash =
+                    hash_password(password, &password_params).or(Err(ServerError::Unauthorized))?;
+                database::set_password_hash(&conn, &username, &password_hash)
+                    .or(Err(ServerError::Internal))?;
+            }
 
-            database::update_user(
-                &conn,
-                &username,
-                &UserInsertion {
-                    email: data.email.clone(),
-                    password_hash,
-                },
-            )
-            .or(Err(ServerError::Internal))?;
+            if let Some(email) = &data.email {
+                if user.email.as_ref() != Some(email) {
+                    request_email_change(&conn, &username, email).or(Err(ServerError::Internal))?;
+                }
+            }
 
             Ok(())
         } else {
@@ -130,9 +246,9 @@ pub async fn get_user(
     username: web::Path<String>,
     auth: BearerAuth,
 ) -> Result<HttpResponse, ServerError> {
-    let user = web::block(move || {
+    let user = database::block(move || {
         let conn = db.into_inner().get().or(Err(ServerError::Internal))?;
-        authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))
+        authenticate(&conn, auth.token(), Scope::Read)
     })
     .await?;
 
@@ -146,21 +262,66 @@ pub async fn get_user(
     }))
 }
 
+/// Rename an existing user. This requires an administrator. All references to the user are
+/// updated, but previously issued login tokens of the user will no longer be valid. The old name
+/// can't be registered again, so that these tokens never refer to another user.
+#[post("/users/{username}/rename")]
+pub async fn rename_user(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    username: web::Path<String>,
+    data: Json<Rename>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        database::rename_user(&conn, &username, &data.username, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
 /// Login an already existing user. This will respond with a newly issued JWT.
 #[post("/login")]
 pub async fn login_user(
     db: web::Data<DbPool>,
-    data: web::Json<Login>,
+    password_params: web::Data<PasswordParams>,
+    data: Json<Login>,
 ) -> Result<HttpResponse, ServerError> {
-    let token = web::block(move || {
+    let token = database::block(move || {
         let conn = db.into_inner().get().or(Err(ServerError::Internal))?;
 
         let user = database::get_user(&conn, &data.username)
             .or(Err(ServerError::Internal))?
             .ok_or(ServerError::Unauthorized)?;
 
-        if verify_password(&data.password, &user.password_hash) {
-            issue_jwt(&user.username).or(Err(ServerError::Internal))
+        if verify_password(&data.password, &user) {
+            // Failing to upgrade the hash shouldn't prevent the login.
+            if let Err(error) =
+                upgrade_password_hash(&conn, &user, &data.password, &password_params)
+            {
+                println!("{:?}", error);
+            }
+
+            let scopes = match &data.scopes {
+                Some(scopes) => scopes
+                    .iter()
+                    .map(|scope| Scope::parse(scope).ok_or(ServerError::BadRequest))
+                    .collect::<Result<Vec<Scope>, ServerError>>()?,
+                None => user.grantable_scopes(),
+            };
+
+            if !scopes.iter().all(|scope| user.may_grant(*scope)) {
+                return Err(ServerError::Forbidden);
+            }
+
+            issue_jwt(&user.username, &scopes).or(Err(ServerError::Internal))
         } else {
             Err(ServerError::Unauthorized)
         }
@@ -170,30 +331,90 @@ pub async fn login_user(
     Ok(HttpResponse::Ok().body(token))
 }
 
-/// Authenticate a user by verifying the provided token. The environemtn variable "WOLFGANG_SECRET"
-/// will be used as the secret key and has to be set.
-pub fn authenticate(conn: &DbConn, token: &str) -> Result<User> {
-    let username = verify_jwt(token)?.username;
-    database::get_user(conn, &username)?.ok_or(anyhow!("User doesn't exist: {}", &username))
+/// Authenticate a user by verifying the provided token and check whether it has the required
+/// scope. The token may either be a JWT issued on login or an API key. The environment variable
+/// "WOLFGANG_SECRET" will be used as the secret key for JWTs and has to be set. Invalid tokens
+/// result in [`ServerError::Unauthorized`], valid ones without the scope in
+/// [`ServerError::Forbidden`].
+pub fn authenticate(conn: &DbConn, token: &str, scope: Scope) -> Result<User, ServerError> {
+    let (user, scopes) = identify(conn, token)?;
+
+    // The scope has to be checked against the user again, because the user's role may have
+    // changed since the token was issued.
+    if scopes.iter().any(|s| s == scope.as_str()) && user.may_grant(scope) {
+        Ok(user)
+    } else {
+        Err(ServerError::Forbidden)
+    }
 }
 
-/// Return a hash for a password that can be stored in the database.
-fn hash_password(password: &str) -> Result<String> {
-    let hash = argon2id13::pwhash(
-        password.as_bytes(),
-        argon2id13::OPSLIMIT_INTERACTIVE,
-        argon2id13::MEMLIMIT_INTERACTIVE,
-    )
-    .or(Err(anyhow!("Failed to hash password!")))?;
+/// Get the user that a token belongs to along with the scopes of the token, without requiring any
+/// of them. Invalid tokens result in [`ServerError::Unauthorized`].
+pub fn identify(conn: &DbConn, token: &str) -> Result<(User, Vec<String>), ServerError> {
+    if token.starts_with(API_KEY_PREFIX) {
+        Ok(database::get_api_key_user(conn, token)?.ok_or(ServerError::Unauthorized)?)
+    } else {
+        let claims = verify_jwt(token).or(Err(ServerError::Unauthorized))?;
+        let user = database::get_user(conn, &claims.username)?.ok_or(ServerError::Unauthorized)?;
+
+        Ok((user, claims.scopes))
+    }
+}
+
+/// Check that a token may also create the entities that are created along with an entity, e.g. the
+/// composer of a new work. Each of them requires the write scope of its own type.
+pub fn authorize_nested<T: NestedEntities>(
+    conn: &DbConn,
+    token: &str,
+    entity: &T,
+) -> Result<(), ServerError> {
+    for entity_type in database::get_new_nested_types(conn, entity)? {
+        authenticate(conn, token, entity_type.write_scope())?;
+    }
+
+    Ok(())
+}
+
+/// Authenticate a user using a JWT issued on login and return the user along with the scopes of
+/// the token. API keys are not accepted.
+pub fn authenticate_login(conn: &DbConn, token: &str) -> Result<(User, Vec<String>)> {
+    let claims = verify_jwt(token)?;
+    let user = database::get_user(conn, &claims.username)?
+        .ok_or(anyhow!("User doesn't exist: {}", &claims.username))?;
+
+    Ok((user, claims.scopes))
+}
+
+/// Return a hash for a password that can be stored in the database. This uses the current
+/// password scheme, i.e. Argon2id with the configured parameters.
+pub fn hash_password(password: &str, params: &PasswordParams) -> Result<String> {
+    let salt: [u8; 16] = rand::thread_rng().gen();
+    let salt = SaltString::encode_b64(&salt).or(Err(anyhow!("Failed to encode salt!")))?;
+
+    let hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.0.clone())
+        .hash_password(password.as_bytes(), &salt)
+        .or(Err(anyhow!("Failed to hash password!")))?;
+
+    Ok(hash.to_string())
+}
 
-    // Strip trailing null bytes to facilitate database storage.
-    Ok(std::str::from_utf8(&hash.0)?
-        .trim_end_matches('\u{0}')
-        .to_string())
+/// Verify whether the password hash of a user is valid for a password.
+pub fn verify_password(password: &str, user: &User) -> bool {
+    match PasswordScheme::parse(&user.password_scheme) {
+        Some(PasswordScheme::Argon2id) => match PasswordHash::new(&user.password_hash) {
+            // The parameters are taken from the hash itself.
+            Ok(hash) => Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok(),
+            Err(_) => false,
+        },
+        Some(PasswordScheme::Sodiumoxide) => verify_legacy_password(password, &user.password_hash),
+        None => false,
+    }
 }
 
-/// Verify whether a hash is valid for a password.
-fn verify_password(password: &str, hash: &str) -> bool {
+/// Verify a password hash that was created using sodiumoxide.
+fn verify_legacy_password(password: &str, hash: &str) -> bool {
     // Readd the trailing null bytes padding.
     let mut bytes = [0u8; 128];
     for (index, byte) in hash.as_bytes().iter().enumerate() {
@@ -206,9 +427,44 @@ fn verify_password(password: &str, hash: &str) -> bool {
     )
 }
 
-/// Issue a JWT that allows to claim to be a user. This uses the value of the environment variable
-/// "WOLFGANG_SECRET" as the secret key. This needs to be set.
-fn issue_jwt(username: &str) -> Result<String> {
+/// Check whether the password hash of a user should be replaced, because it was created using an
+/// older scheme or different parameters.
+fn needs_rehash(user: &User, params: &PasswordParams) -> bool {
+    match PasswordScheme::parse(&user.password_scheme) {
+        Some(PasswordScheme::Argon2id) => match PasswordHash::new(&user.password_hash) {
+            Ok(hash) => match Params::try_from(&hash) {
+                Ok(hash_params) => {
+                    hash_params.m_cost() != params.0.m_cost()
+                        || hash_params.t_cost() != params.0.t_cost()
+                        || hash_params.p_cost() != params.0.p_cost()
+                }
+                Err(_) => true,
+            },
+            Err(_) => true,
+        },
+        _ => true,
+    }
+}
+
+/// Replace the password hash of a user after the password was verified, if it was created using
+/// an older scheme or different parameters. This allows upgrading hashes without downtime.
+fn upgrade_password_hash(
+    conn: &DbConn,
+    user: &User,
+    password: &str,
+    params: &PasswordParams,
+) -> Result<()> {
+    if needs_rehash(user, params) {
+        let password_hash = hash_password(password, params)?;
+        database::set_password_hash(conn, &user.username, &password_hash)?;
+    }
+
+    Ok(())
+}
+
+/// Issue a JWT that allows to claim to be a user with the provided scopes. This uses the value of
+/// the environment variable "WOLFGANG_SECRET" as the secret key. This needs to be set.
+fn issue_jwt(username: &str, scopes: &[Scope]) -> Result<String> {
     let now = std::time::SystemTime::now();
     let expiry = now + std::time::Duration::new(86400, 0);
 
@@ -223,6 +479,10 @@ fn issue_jwt(username: &str) -> Result<String> {
             iat,
             exp,
             username: username.to_string(),
+            scopes: scopes
+                .iter()
+                .map(|scope| scope.as_str().to_string())
+                .collect(),
         },
         &jsonwebtoken::EncodingKey::from_secret(&secret.as_bytes()),
     )?;
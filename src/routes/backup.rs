@@ -0,0 +1,58 @@
+use super::authenticate;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use crate::tasks::write_backup_into;
+use actix_web::{post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Serialize;
+
+/// The directory that backups triggered using the API are written to.
+#[derive(Debug, Clone)]
+pub struct BackupLocation(Option<String>);
+
+impl BackupLocation {
+    /// Read the directory from the environment variable "WOLFGANG_BACKUP_PATH". If it is not
+    /// set, backups can't be triggered using the API.
+    pub fn from_env() -> Self {
+        Self(std::env::var("WOLFGANG_BACKUP_PATH").ok())
+    }
+}
+
+/// Information on a backup that was written.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub path: String,
+}
+
+/// Write a complete backup of the database to the configured location. The user must be an
+/// administrator.
+#[post("/admin/backup")]
+pub async fn create_backup(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    location: web::Data<BackupLocation>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let pool = db.into_inner();
+        let conn = pool.get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        let directory = location.0.as_ref().ok_or(ServerError::NotFound)?;
+
+        // The connection is not needed for writing the backup.
+        drop(conn);
+
+        let path = write_backup_into(&pool, directory)?;
+
+        Ok(BackupInfo { path })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
@@ -0,0 +1,121 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{BulkChange, DbPool, EntityType, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// The number of bulk edits that are listed by default.
+const DEFAULT_LIMIT: i64 = 100;
+
+/// Request body data for changing fields of many entities at once.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkEditSubmission {
+    /// Why the changes are made, e.g. "Fix composer attribution of the Haydn quartets".
+    pub description: String,
+
+    pub changes: Vec<BulkChange>,
+}
+
+/// Query parameters for listing bulk edits.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BulkEditsQuery {
+    /// The maximum number of bulk edits to list.
+    pub limit: Option<i64>,
+}
+
+/// Change fields of many entities within a single transaction. The token needs the write scopes
+/// of all affected entity types and the user must be an editor. The response contains the record
+/// of the bulk edit.
+#[post("/bulk-edit")]
+pub async fn create_bulk_edit(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<BulkEditSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+
+        let mut entity_types: Vec<EntityType> = data
+            .changes
+            .iter()
+            .map(|change| change.entity_type)
+            .collect();
+
+        entity_types.sort();
+        entity_types.dedup();
+
+        let mut user = None;
+        for entity_type in entity_types {
+            user = Some(authenticate(
+                &conn,
+                auth.token(),
+                entity_type.write_scope(),
+            )?);
+        }
+
+        let user = user.ok_or(ServerError::BadRequest)?;
+
+        Ok(database::apply_bulk_edit(
+            &conn,
+            &data.description,
+            &data.changes,
+            &user,
+        )?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// List the most recent bulk edits. The user must be an editor.
+#[get("/bulk-edits")]
+pub async fn get_bulk_edits(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    query: web::Query<BulkEditsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        if !user.may_bulk_edit() {
+            return Err(ServerError::Forbidden);
+        }
+
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, DEFAULT_LIMIT);
+
+        Ok(database::get_bulk_edits(&conn, limit)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get a single bulk edit including all of its changes. The user must be an editor.
+#[get("/bulk-edits/{id}")]
+pub async fn get_bulk_edit(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        if !user.may_bulk_edit() {
+            return Err(ServerError::Forbidden);
+        }
+
+        database::get_bulk_edit(&conn, id.into_inner())?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
@@ -0,0 +1,81 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, MediumCondition, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, put, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for adding a medium to the collection of the current user.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItemSubmission {
+    #[serde(default)]
+    pub notes: String,
+    pub condition: Option<MediumCondition>,
+}
+
+/// Add a medium to the collection of the current user or update its notes and condition. The
+/// request body is optional.
+#[put("/account/collection/mediums/{id}")]
+pub async fn put_collection_item(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+    data: Option<Json<CollectionItemSubmission>>,
+) -> Result<HttpResponse, ServerError> {
+    let data = data.map(|data| data.into_inner()).unwrap_or_default();
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        let id = id.into_inner();
+        database::set_collection_item(&conn, &id, &data.notes, data.condition, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Remove a medium from the collection of the current user.
+#[delete("/account/collection/mediums/{id}")]
+pub async fn delete_collection_item(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::delete_collection_item(&conn, &id.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Get all mediums within the collection of the current user.
+#[get("/account/collection")]
+pub async fn get_collection(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(database::get_collection(&conn, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
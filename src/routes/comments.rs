@@ -0,0 +1,96 @@
+use super::authenticate;
+use super::watches::parse_entity_type;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// Request body data for commenting on an entity.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentSubmission {
+    pub text: String,
+}
+
+/// Response body data for a newly created comment.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentCreated {
+    pub id: String,
+}
+
+/// Get the discussion about an entity, e.g. "/works/{id}/comments". Comments are only visible
+/// to logged in users.
+#[get("/{entity_type}/{id}/comments")]
+pub async fn get_comments(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(database::get_comments(&conn, entity_type, &id, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Comment on an entity. Users watching the entity and users mentioned using "@username" are
+/// notified.
+#[post("/{entity_type}/{id}/comments")]
+pub async fn create_comment(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    data: Json<CommentSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        let id = database::insert_comment(&conn, entity_type, &id, &data.text, &user)?;
+
+        Ok(CommentCreated { id })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Delete a comment. Only its author and editors may do that.
+#[delete("/{entity_type}/{id}/comments/{comment_id}")]
+pub async fn delete_comment(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id, comment_id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::delete_comment(&conn, entity_type, &id, &comment_id, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
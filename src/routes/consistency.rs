@@ -0,0 +1,49 @@
+use super::authenticate;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use actix_web::{get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+
+/// Search for dangling references within the database. The user must be an administrator.
+#[get("/admin/consistency")]
+pub async fn check_consistency(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        Ok(database::check_consistency(&conn, false)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Search for dangling references and repair them. This returns the problems that were fixed.
+/// The user must be an administrator.
+#[post("/admin/consistency/repair")]
+pub async fn repair_consistency(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        Ok(database::check_consistency(&conn, true)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
@@ -0,0 +1,34 @@
+use crate::database::{generate_id, EntityType};
+use actix_web::http::header;
+use actix_web::HttpResponse;
+use serde::Serialize;
+
+/// Response body data for a new entity whose ID was generated by the server.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Created {
+    pub id: String,
+}
+
+/// Generate an ID for a submitted entity, if the client left it out. Returns whether this
+/// happened. IDs that were provided are kept as they are and validated later on.
+pub fn assign_id(id: &mut String) -> bool {
+    if id.is_empty() {
+        *id = generate_id();
+        true
+    } else {
+        false
+    }
+}
+
+/// Respond to a successful update of an entity. If its ID was generated by the server, the
+/// response is "201 Created" containing the ID and the location of the new entity.
+pub fn updated_response(entity_type: EntityType, id: String, generated: bool) -> HttpResponse {
+    if generated {
+        HttpResponse::Created()
+            .header(header::LOCATION, format!("/{}/{}", entity_type.path(), id))
+            .json(Created { id })
+    } else {
+        HttpResponse::Ok().finish()
+    }
+}
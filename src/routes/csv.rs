@@ -0,0 +1,234 @@
+use crate::error::ServerError;
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpRequest, HttpResponse};
+use futures::future::{ready, Ready};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The separator for multiple values within one cell.
+const LIST_SEPARATOR: &str = "; ";
+
+/// The characters that make spreadsheet applications treat a cell as a formula.
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+/// A column of a CSV export consisting of the header and a JSON pointer to the value. A "*"
+/// segment within the pointer selects all items of an array. Their values are joined.
+pub type CsvColumn = (&'static str, &'static str);
+
+/// The columns of persons within CSV exports.
+pub const PERSON_COLUMNS: &[CsvColumn] = &[
+    ("id", "/id"),
+    ("firstName", "/firstName"),
+    ("lastName", "/lastName"),
+    ("period", "/period"),
+    ("locked", "/locked"),
+    ("private", "/private"),
+];
+
+/// The columns of ensembles within CSV exports.
+pub const ENSEMBLE_COLUMNS: &[CsvColumn] =
+    &[("id", "/id"), ("name", "/name"), ("private", "/private")];
+
+/// The columns of instruments within CSV exports.
+pub const INSTRUMENT_COLUMNS: &[CsvColumn] =
+    &[("id", "/id"), ("name", "/name"), ("private", "/private")];
+
+/// The columns of works within CSV exports.
+pub const WORK_COLUMNS: &[CsvColumn] = &[
+    ("id", "/id"),
+    ("title", "/title"),
+    ("nickname", "/nickname"),
+    ("composer", "/composer/id"),
+    ("composerFirstName", "/composer/firstName"),
+    ("composerLastName", "/composer/lastName"),
+    ("authors", "/authors/*/id"),
+    ("instruments", "/instruments/*/id"),
+    ("parts", "/parts/*/title"),
+    ("period", "/period"),
+    ("dedication", "/dedication"),
+    ("premiereDate", "/premiere/date"),
+    ("premierePlace", "/premiere/place"),
+    ("imslp", "/imslp"),
+    ("locked", "/locked"),
+    ("private", "/private"),
+];
+
+/// Query parameters for choosing the format of a list.
+#[derive(Deserialize, Debug, Clone)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+/// The format a list should be returned in. This is chosen using the "format" query parameter,
+/// which can be "json" or "csv", or the "Accept" header. JSON is the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    Json,
+    Csv,
+}
+
+impl FromRequest for ListFormat {
+    type Error = ServerError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let format = actix_web::web::Query::<FormatQuery>::from_query(req.query_string())
+            .ok()
+            .and_then(|query| query.into_inner().format);
+
+        let result = match format.as_deref() {
+            Some("json") => Ok(ListFormat::Json),
+            Some("csv") => Ok(ListFormat::Csv),
+            Some(_) => Err(ServerError::BadRequest),
+            None => {
+                let accepts_csv = req
+                    .headers()
+                    .get(header::ACCEPT)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|value| value.contains("text/csv"));
+
+                if accepts_csv {
+                    Ok(ListFormat::Csv)
+                } else {
+                    Ok(ListFormat::Json)
+                }
+            }
+        };
+
+        ready(result)
+    }
+}
+
+/// Create a response containing a list of entities as CSV.
+pub fn csv_response(data: &Value, columns: &[CsvColumn]) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .body(to_csv(data, columns))
+}
+
+/// Convert a list of entities to CSV according to RFC 4180. The first line contains the headers
+/// of the columns.
+pub fn to_csv(data: &Value, columns: &[CsvColumn]) -> String {
+    let mut csv = String::new();
+
+    let headers: Vec<String> = columns.iter().map(|(name, _)| escape(name)).collect();
+    csv.push_str(&headers.join(","));
+    csv.push_str("\r\n");
+
+    if let Value::Array(items) = data {
+        for item in items {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|(_, pointer)| escape(&get_cell(item, pointer)))
+                .collect();
+
+            csv.push_str(&cells.join(","));
+            csv.push_str("\r\n");
+        }
+    }
+
+    csv
+}
+
+/// Get the content of one cell.
+fn get_cell(item: &Value, pointer: &str) -> String {
+    match pointer.split_once("/*") {
+        Some((array, rest)) => match item.pointer(array) {
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|value| get_cell(value, rest))
+                .filter(|cell| !cell.is_empty())
+                .collect::<Vec<String>>()
+                .join(LIST_SEPARATOR),
+            _ => String::new(),
+        },
+        None => match item.pointer(pointer) {
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        },
+    }
+}
+
+/// Quote a cell, if it contains characters with a special meaning. Cells that spreadsheet
+/// applications would treat as formulas are prefixed with an apostrophe, so that names and
+/// titles entered by users can't run formulas.
+fn escape(cell: &str) -> String {
+    let cell = if cell.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", cell)
+    } else {
+        cell.to_string()
+    };
+
+    if cell.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell
+    }
+}
+
+/// Parse CSV or TSV according to RFC 4180 using the provided delimiter. Fields may be quoted
+/// and quoted fields may contain delimiters, line breaks and doubled quotes. Empty lines are
+/// skipped. The apostrophes that [`escape`] adds in front of formulas are removed again. This
+/// fails for unterminated quotes.
+pub fn parse_csv(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, ServerError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+        } else {
+            match c {
+                '"' if field.is_empty() => quoted = true,
+                '\r' if chars.peek() == Some(&'\n') => (),
+                '\r' | '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    push_record(&mut records, std::mem::take(&mut record));
+                }
+                c if c == delimiter => record.push(std::mem::take(&mut field)),
+                c => field.push(c),
+            }
+        }
+    }
+
+    if quoted {
+        return Err(ServerError::BadRequest);
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        push_record(&mut records, record);
+    }
+
+    Ok(records)
+}
+
+/// Add a record for [`parse_csv`], unless it stems from an empty line.
+fn push_record(records: &mut Vec<Vec<String>>, record: Vec<String>) {
+    if !(record.len() == 1 && record[0].is_empty()) {
+        records.push(record.into_iter().map(unescape_formula).collect());
+    }
+}
+
+/// Remove the apostrophe in front of a field that would otherwise be treated as a formula.
+fn unescape_formula(field: String) -> String {
+    match field.strip_prefix('\'') {
+        Some(rest) if rest.starts_with(FORMULA_PREFIXES) => rest.to_string(),
+        _ => field,
+    }
+}
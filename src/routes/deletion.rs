@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+/// Query parameters for deleting entities.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DeleteQuery {
+    /// Also delete everything that refers to the entity. This is only allowed for
+    /// administrators.
+    #[serde(default)]
+    pub cascade: bool,
+}
@@ -0,0 +1,193 @@
+use super::{
+    authenticate, authorize_nested, read_json, DuplicateQuery, Duplicates, JSON_LIMIT,
+    MEDIUM_JSON_LIMIT,
+};
+use crate::database;
+use crate::database::{DbPool, EntityType, Medium, Scope, Work};
+use crate::error::ServerError;
+use crate::validation::{FieldError, Validate, ValidationErrors};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Get the entity type from a path segment. Only works and mediums can be saved as drafts.
+fn parse_draft_type(path: &str) -> Result<EntityType, ServerError> {
+    match EntityType::from_path(path) {
+        Some(entity_type @ EntityType::Work) | Some(entity_type @ EntityType::Medium) => {
+            Ok(entity_type)
+        }
+        _ => Err(ServerError::NotFound),
+    }
+}
+
+/// Get the scope that is needed for changing entities of a type that can be saved as a draft.
+fn draft_scope(entity_type: EntityType) -> Scope {
+    match entity_type {
+        EntityType::Work => Scope::WriteWorks,
+        _ => Scope::WriteMediums,
+    }
+}
+
+/// Convert the data of a draft into the actual entity. Problems are reported like validation
+/// errors, because drafts may be missing any field.
+fn parse_draft_data<T: DeserializeOwned>(data: Value) -> Result<T, ServerError> {
+    serde_json::from_value(data).map_err(|error| {
+        ServerError::Invalid(ValidationErrors {
+            errors: vec![FieldError {
+                field: String::new(),
+                message: error.to_string(),
+            }],
+        })
+    })
+}
+
+/// Get all drafts of the current user.
+#[get("/account/drafts")]
+pub async fn get_drafts(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(database::get_drafts(&conn, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get a draft of the current user, e.g. "/drafts/works/{id}".
+#[get("/drafts/{entity_type}/{id}")]
+pub async fn get_draft(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_draft_type(&entity_type)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::get_draft(&conn, entity_type, &id, &user)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Save a work or medium as a draft or replace an existing draft of the current user. The request
+/// body has the same format as for the entity itself, but may be incomplete. The ID within the
+/// data is always set to the one from the path.
+#[put("/drafts/{entity_type}/{id}")]
+pub async fn update_draft(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_draft_type(&entity_type)?;
+
+    let limit = match entity_type {
+        EntityType::Medium => MEDIUM_JSON_LIMIT,
+        _ => JSON_LIMIT,
+    };
+
+    let mut data: Value = read_json(&req, payload, limit).await?;
+    let object = data.as_object_mut().ok_or(ServerError::BadRequest)?;
+    object.insert("id".to_string(), Value::String(id.clone()));
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), draft_scope(entity_type))?;
+
+        database::update_draft(&conn, entity_type, &id, &data, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Delete a draft of the current user without publishing it.
+#[delete("/drafts/{entity_type}/{id}")]
+pub async fn delete_draft(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_draft_type(&entity_type)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), draft_scope(entity_type))?;
+
+        database::delete_draft(&conn, entity_type, &id, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Publish a draft of the current user. The data is validated like for adding the entity directly
+/// and new works that look like duplicates of existing ones are rejected, unless the "force" query
+/// parameter is set. The draft is removed afterwards.
+#[post("/drafts/{entity_type}/{id}/publish")]
+pub async fn publish_draft(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<DuplicateQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_draft_type(&entity_type)?;
+
+    let candidates = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), draft_scope(entity_type))?;
+
+        let draft =
+            database::get_draft(&conn, entity_type, &id, &user)?.ok_or(ServerError::NotFound)?;
+
+        if entity_type == EntityType::Work {
+            let work: Work = parse_draft_data(draft.data)?;
+            work.validate()?;
+            authorize_nested(&conn, auth.token(), &work)?;
+
+            if !query.force && database::get_work(&conn, &work.id)?.is_none() {
+                let candidates = database::find_similar_works(&conn, &work)?;
+                if !candidates.is_empty() {
+                    return Ok(Some(candidates));
+                }
+            }
+
+            database::update_work(&conn, &work, &user)?;
+        } else {
+            let medium: Medium = parse_draft_data(draft.data)?;
+            medium.validate()?;
+            authorize_nested(&conn, auth.token(), &medium)?;
+
+            database::update_medium(&conn, &medium, &user)?;
+        }
+
+        database::delete_draft(&conn, entity_type, &id, &user)?;
+
+        Ok(None)
+    })
+    .await?;
+
+    match candidates {
+        Some(candidates) => Ok(HttpResponse::Conflict().json(Duplicates { candidates })),
+        None => Ok(HttpResponse::Ok().finish()),
+    }
+}
@@ -0,0 +1,42 @@
+use crate::database;
+use crate::error::ServerError;
+use crate::publishing::DumpPublisher;
+use actix_web::{get, web, HttpResponse};
+
+/// List the published dumps together with the public key for verifying their signatures. This
+/// responds with 404, if dumps are not published.
+#[get("/dumps")]
+pub async fn get_dumps(
+    publisher: web::Data<Option<DumpPublisher>>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let publisher = publisher.as_ref().as_ref().ok_or(ServerError::NotFound)?;
+        Ok(publisher.list()?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Download a published dump, its checksum or its signature, e.g.
+/// "/dumps/wolfgang-dump-20260101-033000.json.sig".
+#[get("/dumps/{file}")]
+pub async fn get_dump_file(
+    publisher: web::Data<Option<DumpPublisher>>,
+    file: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let file = file.into_inner();
+    let content_type = if file.ends_with(".json") {
+        "application/json"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+
+    let data = database::block(move || {
+        let publisher = publisher.as_ref().as_ref().ok_or(ServerError::NotFound)?;
+        publisher.load(&file)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().content_type(content_type).body(data))
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for adding new entities.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DuplicateQuery {
+    /// Add the entity even if there are existing ones that look like duplicates.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Response body data for a new entity that wasn't added, because it looks like a duplicate.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Duplicates<T> {
+    /// The existing entities that are nearly identical to the new one.
+    pub candidates: Vec<T>,
+}
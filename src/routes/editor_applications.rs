@@ -0,0 +1,116 @@
+use super::Json;
+use super::{authenticate, authenticate_login};
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// Request body data for applying for becoming an editor.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorApplicationSubmission {
+    pub motivation: String,
+}
+
+/// Response body data for a newly submitted application.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorApplicationCreated {
+    pub id: String,
+}
+
+/// Query parameters for listing applications.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EditorApplicationsQuery {
+    /// Only list pending or decided applications.
+    pub pending: Option<bool>,
+}
+
+/// Apply for becoming an editor. The application will be reviewed by an administrator.
+#[post("/account/apply-editor")]
+pub async fn apply_editor(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<EditorApplicationSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let (user, _) =
+            authenticate_login(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+
+        let id = database::insert_editor_application(&conn, &data.motivation, &user)?;
+
+        Ok(EditorApplicationCreated { id })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get all applications for becoming an editor. The user must be an administrator.
+#[get("/admin/editor-applications")]
+pub async fn get_editor_applications(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    query: web::Query<EditorApplicationsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        Ok(database::get_editor_applications(
+            &conn,
+            query.pending,
+            &user,
+        )?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Approve a pending application, which makes the applicant an editor. The user must be an
+/// administrator.
+#[post("/admin/editor-applications/{id}/approve")]
+pub async fn approve_editor_application(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    decide(auth, db, id.into_inner(), true).await
+}
+
+/// Reject a pending application. The user must be an administrator.
+#[post("/admin/editor-applications/{id}/reject")]
+pub async fn reject_editor_application(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    decide(auth, db, id.into_inner(), false).await
+}
+
+/// Approve or reject a pending application.
+async fn decide(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: String,
+    approved: bool,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        database::decide_editor_application(&conn, &id, approved, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
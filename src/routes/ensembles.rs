@@ -1,23 +1,35 @@
-use super::authenticate;
+use super::Json;
+use super::{assign_id, check_redirect, updated_response, viewer_key, CreateQuery, FieldsQuery};
+use super::{authenticate, authenticate_viewer, check_visible, csv_response, get_viewer};
+use super::{ListFormat, QualityQuery, ENSEMBLE_COLUMNS};
+use crate::cache::{cached, ResponseCache};
 use crate::database;
-use crate::database::{DbPool, Ensemble};
+use crate::database::{DbPool, Ensemble, EntityType, ReadDbPool, Scope};
 use crate::error::ServerError;
+use crate::validation::Validate;
 use actix_web::{delete, get, post, web, HttpResponse};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 
 /// Get an existing ensemble.
 #[get("/ensembles/{id}")]
 pub async fn get_ensemble(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        database::get_ensemble(&conn, &id.into_inner())?.ok_or(ServerError::NotFound)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_redirect(&conn, EntityType::Ensemble, &id, viewer.as_ref())?;
+        check_visible(&conn, EntityType::Ensemble, &id, viewer.as_ref())?;
+        database::get_ensemble(&conn, &id)?.ok_or(ServerError::NotFound)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
 }
 
 /// Add a new ensemble or update an existin one. The user must be authorized to do that.
@@ -25,30 +37,54 @@ pub async fn get_ensemble(
 pub async fn update_ensemble(
     auth: BearerAuth,
     db: web::Data<DbPool>,
-    data: web::Json<Ensemble>,
+    data: Json<Ensemble>,
+    create: web::Query<CreateQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    let mut data = data.into_inner();
+    let generated = assign_id(&mut data.id);
+    data.validate()?;
+
+    let id = data.id.clone();
+
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteEnsembles)?;
 
-        database::update_ensemble(&conn, &data.into_inner(), &user)?;
+        database::with_transaction(&conn, |tx| {
+            create.check(tx, EntityType::Ensemble, &data.id)?;
+            database::update_ensemble_in(tx, &data, &user)
+        })?;
 
         Ok(())
     })
     .await?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(updated_response(EntityType::Ensemble, id, generated))
 }
 
 #[get("/ensembles")]
-pub async fn get_ensembles(db: web::Data<DbPool>) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+pub async fn get_ensembles(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+    query: web::Query<FieldsQuery>,
+    quality: web::Query<QualityQuery>,
+    format: ListFormat,
+) -> Result<HttpResponse, ServerError> {
+    let viewer = get_viewer(&db, auth).await?;
+    let key = viewer_key(quality.key("/ensembles"), viewer.as_ref());
+
+    let data = cached(&cache, key, move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_ensembles(&conn)?)
+        let ensembles = database::get_ensembles(&conn, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Ensemble, &ensembles)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    match format {
+        ListFormat::Json => Ok(HttpResponse::Ok().json(query.apply(&*data)?)),
+        ListFormat::Csv => Ok(csv_response(&data, ENSEMBLE_COLUMNS)),
+    }
 }
 
 #[delete("/ensembles/{id}")]
@@ -57,9 +93,9 @@ pub async fn delete_ensemble(
     db: web::Data<DbPool>,
     id: web::Path<String>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteEnsembles)?;
 
         database::delete_ensemble(&conn, &id.into_inner(), &user)?;
 
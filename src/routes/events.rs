@@ -0,0 +1,134 @@
+use crate::database;
+use crate::database::{Change, DbPool};
+use crate::error::ServerError;
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// The time to wait before checking for new events again.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The number of polls without new events after which a comment is sent to keep the connection
+/// alive.
+const KEEP_ALIVE_POLLS: u32 = 15;
+
+/// The maximum number of events that are sent at once.
+const BATCH_SIZE: i64 = 100;
+
+/// Query parameters for the event stream.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EventsQuery {
+    /// Start with the events after this revision. By default, only new events are sent.
+    pub after: Option<i64>,
+}
+
+/// The state of one event stream.
+struct EventStream {
+    db: web::Data<DbPool>,
+    last: i64,
+    idle_polls: u32,
+}
+
+/// Stream notifications on created, updated and deleted entities using Server-Sent Events. The
+/// revision of each change is used as the event ID, so clients can resume after reconnecting
+/// using the "Last-Event-ID" header. Changes to private entities are left out.
+#[get("/events")]
+pub async fn get_events(
+    req: HttpRequest,
+    db: web::Data<DbPool>,
+    query: web::Query<EventsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let last_event_id = match req.headers().get("Last-Event-ID") {
+        Some(value) => Some(
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .ok_or(ServerError::BadRequest)?,
+        ),
+        None => query.after,
+    };
+
+    let last = match last_event_id {
+        Some(last) => last,
+        None => {
+            let db = db.clone();
+            database::block(move || {
+                let conn = db.into_inner().get()?;
+                Ok(database::get_last_event_id(&conn)?)
+            })
+            .await?
+        }
+    };
+
+    let state = EventStream {
+        db,
+        last,
+        idle_polls: 0,
+    };
+
+    let stream = futures::stream::unfold(state, |mut state| async move {
+        loop {
+            let db = state.db.clone();
+            let last = state.last;
+
+            // End the stream on errors. The client will reconnect and resume.
+            let (newest, events) = database::block(move || {
+                let conn = db.into_inner().get()?;
+                let events = database::get_events_after(&conn, last, BATCH_SIZE)?;
+                let newest = events.last().map(|event| event.id);
+
+                let mut visible = Vec::new();
+                for event in events {
+                    if database::is_change_visible(
+                        &conn,
+                        event.entity_type,
+                        &event.entity_id,
+                        None,
+                    )? {
+                        visible.push(event);
+                    }
+                }
+
+                Ok::<_, ServerError>((newest, visible))
+            })
+            .await
+            .ok()?;
+
+            // Left out events don't count as activity, but the stream moves past them as well.
+            if let Some(newest) = newest {
+                state.last = newest;
+            }
+
+            if !events.is_empty() {
+                state.idle_polls = 0;
+
+                let mut message = String::new();
+                for event in events {
+                    let id = event.id;
+                    let kind = event.kind.as_str();
+                    let data = serde_json::to_string(&Change::from(event)).ok()?;
+
+                    message.push_str(&format!("id: {}\nevent: {}\ndata: {}\n\n", id, kind, data));
+                }
+
+                return Some((Ok::<_, ServerError>(Bytes::from(message)), state));
+            }
+
+            state.idle_polls += 1;
+            if state.idle_polls >= KEEP_ALIVE_POLLS {
+                state.idle_polls = 0;
+                return Some((Ok(Bytes::from_static(b": keep-alive\n\n")), state));
+            }
+
+            actix_web::rt::time::delay_for(POLL_INTERVAL).await;
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .header("X-Accel-Buffering", "no")
+        .streaming(Box::pin(stream)))
+}
@@ -0,0 +1,103 @@
+use super::authenticate_viewer;
+use crate::database;
+use crate::database::{DbTransaction, EntityType, ReadDbPool};
+use crate::error::ServerError;
+use actix_web::{get, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of IDs that can be checked at once.
+const MAX_IDS: usize = 100;
+
+/// Query parameters for checking whether entities exist.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExistsQuery {
+    /// The entity type, e.g. "work".
+    #[serde(rename = "type")]
+    pub entity_type: String,
+
+    /// A comma separated list of entity IDs.
+    pub id: String,
+}
+
+/// Response body data for existence checks.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Existence {
+    /// The IDs of entities that exist and are visible to the user, if any.
+    pub existing: Vec<String>,
+
+    /// The IDs of entities that don't exist or are not visible to the user.
+    pub missing: Vec<String>,
+}
+
+/// Check which of up to 100 entities of the same type exist, e.g.
+/// "/exists?type=work&id={id1},{id2}". Both lists keep the order of the query.
+#[get("/exists")]
+pub async fn get_existence(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    query: web::Query<ExistsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let entity_type = EntityType::parse(&query.entity_type).ok_or(ServerError::BadRequest)?;
+
+    let mut ids: Vec<String> = Vec::new();
+    for id in query
+        .id
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+    {
+        if !ids.iter().any(|other| other == id) {
+            ids.push(id.to_string());
+        }
+    }
+
+    if ids.is_empty() || ids.len() > MAX_IDS {
+        return Err(ServerError::BadRequest);
+    }
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+
+        let visible = database::get_visible_ids(&conn, entity_type, &ids, viewer.as_ref())?;
+        let (existing, missing) = ids.into_iter().partition(|id| visible.contains(id));
+
+        Ok(Existence { existing, missing })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Query parameters for adding entities.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateQuery {
+    /// Only add the entity, if there is none with the same ID yet, instead of overwriting it.
+    #[serde(default)]
+    pub if_absent: bool,
+}
+
+impl CreateQuery {
+    /// Fail with "409 Conflict", if only new entities should be added and the entity exists
+    /// already. Private entities of other users count as well. This has to happen within the
+    /// transaction that adds the entity, because the ID stays locked until it ends. Concurrent
+    /// requests for the same ID will wait for that and only one of them will succeed.
+    pub fn check(
+        &self,
+        tx: &DbTransaction,
+        entity_type: EntityType,
+        id: &str,
+    ) -> Result<(), ServerError> {
+        if self.if_absent {
+            database::lock_entity_id(tx, entity_type, id)?;
+
+            if database::entity_exists(tx.conn(), entity_type, id)? {
+                return Err(ServerError::Conflict);
+            }
+        }
+
+        Ok(())
+    }
+}
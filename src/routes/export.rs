@@ -0,0 +1,42 @@
+use crate::database;
+use crate::database::ReadDbPool;
+use crate::error::ServerError;
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+
+/// The maximum number of events that are combined into one part of the changefeed.
+const MAX_CHANGES: i64 = 1000;
+
+/// Query parameters for the changefeed.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ChangesQuery {
+    /// Only include changes after this revision. By default, all changes are included.
+    pub since: Option<i64>,
+
+    /// The maximum number of events to combine. This defaults to and is limited by
+    /// [`MAX_CHANGES`].
+    pub limit: Option<i64>,
+}
+
+/// Get the changes to all public entities after a revision together with their current data, so
+/// that mirrors can stay in sync without downloading full dumps.
+#[get("/export/changes")]
+pub async fn get_changes(
+    db: web::Data<ReadDbPool>,
+    query: web::Query<ChangesQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let since = query.since.unwrap_or(0);
+    let limit = query.limit.unwrap_or(MAX_CHANGES);
+
+    if since < 0 || !(1..=MAX_CHANGES).contains(&limit) {
+        return Err(ServerError::BadRequest);
+    }
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        Ok(database::get_changes_since(&conn, since, limit)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
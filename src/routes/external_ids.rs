@@ -0,0 +1,60 @@
+use super::{authenticate_viewer, check_visible};
+use crate::database;
+use crate::database::{EntityType, ExternalSource, ReadDbPool};
+use crate::error::ServerError;
+use actix_web::{get, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+
+/// Find the medium, recording or person with an ID within an external service, e.g.
+/// "/external/spotify/spotify:album:4uLU6hMCjMI75M1A2tKUQC". This returns the type and ID of the
+/// entity.
+#[get("/external/{source}/{id}")]
+pub async fn lookup_external_id(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (source, id) = path.into_inner();
+    let source = ExternalSource::parse(&source).ok_or(ServerError::NotFound)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+
+        let entity =
+            database::lookup_external_id(&conn, source, &id)?.ok_or(ServerError::NotFound)?;
+
+        check_visible(
+            &conn,
+            entity.entity_type,
+            &entity.entity_id,
+            viewer.as_ref(),
+        )?;
+
+        Ok(entity)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get the external IDs of a person. Unlike mediums and recordings, persons don't contain them,
+/// because they are only added by confirming candidates found on Wikidata.
+#[get("/persons/{id}/external-ids")]
+pub async fn get_person_external_ids(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Person, &id, viewer.as_ref())?;
+        Ok(database::get_external_ids(&conn, EntityType::Person, &id)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
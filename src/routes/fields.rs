@@ -0,0 +1,113 @@
+use crate::error::ServerError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Query parameters for choosing which parts of entities to return.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct FieldsQuery {
+    /// A comma separated list of the top level fields to include. The ID is always included. If
+    /// this is not set, all fields are included.
+    pub fields: Option<String>,
+
+    /// A comma separated list of references to embed, e.g. "work,performances.person". Nested
+    /// references are separated by dots. All other references are replaced by the IDs of the
+    /// referenced entities. If this is not set, all references are embedded.
+    pub embed: Option<String>,
+}
+
+impl FieldsQuery {
+    /// Convert an entity or a list of entities to JSON and remove everything that wasn't
+    /// requested.
+    pub fn apply<T: Serialize>(&self, data: &T) -> Result<Value, ServerError> {
+        let value = serde_json::to_value(data).or(Err(ServerError::Internal))?;
+
+        let fields = split(&self.fields);
+        let embed = split(&self.embed);
+
+        Ok(match value {
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| shape(item, fields.as_deref(), embed.as_deref()))
+                    .collect(),
+            ),
+            value => shape(value, fields.as_deref(), embed.as_deref()),
+        })
+    }
+}
+
+/// Split a comma separated list.
+fn split(list: &Option<String>) -> Option<Vec<String>> {
+    list.as_ref().map(|list| {
+        list.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+/// Apply the requested fields and embeddings to one entity.
+fn shape(value: Value, fields: Option<&[String]>, embed: Option<&[String]>) -> Value {
+    let value = match embed {
+        Some(embed) => collapse(value, "", embed),
+        None => value,
+    };
+
+    match (value, fields) {
+        (Value::Object(object), Some(fields)) => Value::Object(
+            object
+                .into_iter()
+                .filter(|(key, _)| key == "id" || fields.contains(key))
+                .collect::<Map<String, Value>>(),
+        ),
+        (value, _) => value,
+    }
+}
+
+/// Replace references to other entities by their IDs, unless their path is listed in `embed`.
+/// Objects without an ID, like performances, are not entities themselves, so only the references
+/// within them are handled.
+fn collapse(value: Value, path: &str, embed: &[String]) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| {
+                    let path = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+
+                    (key, collapse_field(value, &path, embed))
+                })
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Handle one field of an object for [`collapse`].
+fn collapse_field(value: Value, path: &str, embed: &[String]) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| collapse_field(item, path, embed))
+                .collect(),
+        ),
+        Value::Object(object) => match object.get("id") {
+            Some(id) if !is_embedded(path, embed) => id.clone(),
+            _ => collapse(Value::Object(object), path, embed),
+        },
+        value => value,
+    }
+}
+
+/// Check whether a reference or one of the references within it should be embedded.
+fn is_embedded(path: &str, embed: &[String]) -> bool {
+    embed.iter().any(|embedded| {
+        embedded == path || (embedded.starts_with(path) && embedded[path.len()..].starts_with('.'))
+    })
+}
@@ -0,0 +1,184 @@
+use super::{authenticate, authenticate_viewer, check_visible, read_json, IMAGE_JSON_LIMIT};
+use crate::database;
+use crate::database::{generate_id, DbPool, EntityType, Image, ReadDbPool};
+use crate::error::ServerError;
+use crate::images::{ImageStore, IMAGE_SIZES};
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use chrono::Utc;
+use serde::Deserialize;
+
+/// Request body data for uploading the image of a person or an ensemble.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageSubmission {
+    /// The contents of a JPEG or PNG file encoded using Base64.
+    pub data: String,
+
+    /// The license under which the image may be used, e.g. "CC BY-SA 4.0".
+    pub license: String,
+
+    /// Who has to be credited when showing the image.
+    pub attribution: String,
+
+    /// Where the image was published before, if anywhere.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// Query parameters for getting images.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImageQuery {
+    /// The size to scale the image down to. This has to be one of the supported sizes. By
+    /// default, the image is returned in its stored size.
+    pub size: Option<u32>,
+}
+
+/// Get the entity type from a path segment, if entities of that type can have images.
+fn parse_image_entity_type(path: &str) -> Result<EntityType, ServerError> {
+    match EntityType::from_path(path) {
+        Some(entity_type @ (EntityType::Person | EntityType::Ensemble)) => Ok(entity_type),
+        _ => Err(ServerError::NotFound),
+    }
+}
+
+/// Get the image of a person or an ensemble as JPEG file, e.g. "/persons/{id}/image?size=256".
+#[get("/{entity_type}/{id}/image")]
+pub async fn get_image(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    store: web::Data<Option<ImageStore>>,
+    path: web::Path<(String, String)>,
+    query: web::Query<ImageQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_image_entity_type(&entity_type)?;
+
+    if matches!(query.size, Some(size) if !IMAGE_SIZES.contains(&size)) {
+        return Err(ServerError::BadRequest);
+    }
+
+    let data = database::block(move || {
+        let store = store.as_ref().as_ref().ok_or(ServerError::NotFound)?;
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+
+        check_visible(&conn, entity_type, &id, viewer.as_ref())?;
+
+        let image = database::get_image(&conn, entity_type, &id)?.ok_or(ServerError::NotFound)?;
+
+        Ok(store.load(&image.id, query.size)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().content_type("image/jpeg").body(data))
+}
+
+/// Get the license, attribution and size of the image of a person or an ensemble.
+#[get("/{entity_type}/{id}/image/metadata")]
+pub async fn get_image_metadata(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_image_entity_type(&entity_type)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+
+        check_visible(&conn, entity_type, &id, viewer.as_ref())?;
+        database::get_image(&conn, entity_type, &id)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Upload a new image for a person or an ensemble replacing the previous one. The user must be
+/// allowed to edit the entity. Large images are scaled down. This returns the metadata of the
+/// stored image.
+#[post("/{entity_type}/{id}/image")]
+pub async fn update_image(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    store: web::Data<Option<ImageStore>>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_image_entity_type(&entity_type)?;
+
+    let submission: ImageSubmission = read_json(&req, payload, IMAGE_JSON_LIMIT).await?;
+    submission.validate()?;
+
+    let data = base64::decode(&submission.data).or(Err(ServerError::BadRequest))?;
+
+    let image = database::block(move || {
+        let store = store.as_ref().as_ref().ok_or(ServerError::NotFound)?;
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), entity_type.write_scope())?;
+
+        // Uploads are checked before decoding them, which is the expensive part.
+        if !database::may_edit_image(&conn, entity_type, &id, &user)? {
+            return Err(ServerError::Forbidden);
+        }
+
+        let image_id = generate_id();
+        let (width, height) = store.save(&image_id, &data)?;
+
+        let image = Image {
+            id: image_id,
+            license: submission.license,
+            attribution: submission.attribution,
+            source: submission.source,
+            width: width as i32,
+            height: height as i32,
+            created_by: user.username.clone(),
+            created_at: Utc::now().naive_utc(),
+        };
+
+        match database::update_image(&conn, entity_type, &id, &image, &user) {
+            Ok(Some(old_id)) => store.remove(&old_id)?,
+            Ok(None) => (),
+            Err(error) => {
+                store.remove(&image.id)?;
+                return Err(error.into());
+            }
+        }
+
+        Ok(image)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(image))
+}
+
+/// Remove the image of a person or an ensemble. The user must be allowed to edit the entity.
+#[delete("/{entity_type}/{id}/image")]
+pub async fn delete_image(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    store: web::Data<Option<ImageStore>>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_image_entity_type(&entity_type)?;
+
+    database::block(move || {
+        let store = store.as_ref().as_ref().ok_or(ServerError::NotFound)?;
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), entity_type.write_scope())?;
+
+        let image_id = database::delete_image(&conn, entity_type, &id, &user)?;
+        store.remove(&image_id)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,547 @@
+use super::{authenticate, authorize_nested, parse_csv, read_text, CSV_LIMIT};
+use crate::database;
+use crate::database::{DbConn, DbPool, EntityType, Person, Scope, User, Work, WorkPart};
+use crate::error::ServerError;
+use crate::validation::{FieldError, Validate};
+use actix_web::http::header;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The maximum number of rows within one import.
+const MAX_ROWS: usize = 5000;
+
+/// Query parameters for importing entities.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImportQuery {
+    /// The kind of entities within the file, either "persons" or "works".
+    #[serde(rename = "type")]
+    pub entity_type: String,
+
+    /// Only check the rows and report what would happen without changing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Import new entities even if there are existing ones that look like duplicates.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// What happens to the entity described by one row.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportAction {
+    /// A new entity is created.
+    Create,
+
+    /// An existing entity is updated.
+    Update,
+
+    /// The row looks like a duplicate of existing entities or repeats the ID of another row.
+    Conflict,
+
+    /// The row contains invalid data.
+    Invalid,
+}
+
+/// The result for one row of an imported file.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRow {
+    /// The line of the row within the file, starting with 1 for the header.
+    pub line: usize,
+
+    /// The ID of the entity the row is imported as.
+    pub id: String,
+
+    pub action: ImportAction,
+
+    /// The problems with the data of an invalid row.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+
+    /// The IDs of existing entities that a new entity looks like.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
+}
+
+/// Response body data for an import.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    /// Whether the changes were actually made.
+    pub committed: bool,
+
+    pub rows: Vec<ImportRow>,
+}
+
+/// An entity read from one row.
+enum ImportEntity {
+    Person(Person),
+    Work(Box<Work>),
+}
+
+/// One row of a file together with the names of its columns.
+struct Record<'a> {
+    columns: &'a HashMap<String, usize>,
+    values: &'a [String],
+}
+
+impl<'a> Record<'a> {
+    /// Get the trimmed value of a column, if the file contains that column.
+    fn get(&self, column: &str) -> Option<&'a str> {
+        self.columns
+            .get(column)
+            .map(|index| self.values.get(*index).map_or("", |value| value.trim()))
+    }
+
+    /// Get the value of a column that is absent for empty cells.
+    fn get_optional(&self, column: &str) -> Option<Option<String>> {
+        self.get(column)
+            .map(|value| Some(value.to_string()).filter(|value| !value.is_empty()))
+    }
+
+    /// Get a list of values that are separated by semicolons.
+    fn get_list(&self, column: &str) -> Option<Vec<String>> {
+        self.get(column).map(|value| {
+            value
+                .split(';')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+    }
+
+    /// Get a boolean value. Empty cells are false.
+    fn get_bool(&self, column: &str) -> Result<Option<bool>, String> {
+        match self.get(column) {
+            Some("") | Some("false") => Ok(Some(false)),
+            Some("true") => Ok(Some(true)),
+            Some(_) => Err("Must be true or false.".to_string()),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Import persons or works from a CSV file, or a TSV file if the content type is
+/// "text/tab-separated-values". The columns are the same as for CSV exports. Cells of existing
+/// entities are only changed for the columns within the file. With "dry_run", the result of each
+/// row is reported without changing anything. Otherwise, all rows are imported within one
+/// transaction, unless there are invalid or conflicting rows. In that case, nothing is changed
+/// and the response has the status "409 Conflict".
+#[post("/import/csv")]
+pub async fn import_csv(
+    req: HttpRequest,
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    query: web::Query<ImportQuery>,
+    payload: web::Payload,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, scope) = match query.entity_type.as_str() {
+        "persons" => (EntityType::Person, Scope::WritePersons),
+        "works" => (EntityType::Work, Scope::WriteWorks),
+        _ => return Err(ServerError::BadRequest),
+    };
+
+    let delimiter = match req.headers().get(header::CONTENT_TYPE) {
+        Some(value) if value.as_bytes().starts_with(b"text/tab-separated-values") => '\t',
+        _ => ',',
+    };
+
+    let text = read_text(payload, CSV_LIMIT).await?;
+    let records = parse_csv(&text, delimiter)?;
+
+    if records.is_empty() || records.len() > MAX_ROWS + 1 {
+        return Err(ServerError::BadRequest);
+    }
+
+    let query = query.into_inner();
+    let dry_run = query.dry_run;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), scope)?;
+
+        let (rows, entities) = plan_import(&conn, entity_type, &records, &user, query.force)?;
+
+        let valid = rows
+            .iter()
+            .all(|row| row.action == ImportAction::Create || row.action == ImportAction::Update);
+
+        let committed = valid && !dry_run;
+
+        if committed {
+            for entity in &entities {
+                if let ImportEntity::Work(work) = entity {
+                    authorize_nested(&conn, auth.token(), work.as_ref())?;
+                }
+            }
+
+            database::with_transaction(&conn, |tx| {
+                for entity in &entities {
+                    match entity {
+                        ImportEntity::Person(person) => {
+                            database::update_person_in(tx, person, &user)?
+                        }
+                        ImportEntity::Work(work) => database::update_work_in(tx, work, &user)?,
+                    }
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(ImportResult { committed, rows })
+    })
+    .await?;
+
+    if data.committed || dry_run {
+        Ok(HttpResponse::Ok().json(data))
+    } else {
+        Ok(HttpResponse::Conflict().json(data))
+    }
+}
+
+/// Read the entities from all rows and find out what would happen to them.
+fn plan_import(
+    conn: &DbConn,
+    entity_type: EntityType,
+    records: &[Vec<String>],
+    user: &User,
+    force: bool,
+) -> Result<(Vec<ImportRow>, Vec<ImportEntity>), ServerError> {
+    let columns: HashMap<String, usize> = records[0]
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.trim().to_string(), index))
+        .collect();
+
+    let mut rows = Vec::new();
+    let mut entities = Vec::new();
+    let mut ids = HashSet::new();
+
+    for (index, values) in records.iter().enumerate().skip(1) {
+        let record = Record {
+            columns: &columns,
+            values,
+        };
+
+        let id = match record.get("id") {
+            Some(id) if !id.is_empty() => id.to_string(),
+            _ => database::generate_id(),
+        };
+
+        let result = match entity_type {
+            EntityType::Person => read_person(conn, &record, &id, user)?
+                .map(|(person, exists)| (ImportEntity::Person(person), exists)),
+            _ => read_work(conn, &record, &id, user)?
+                .map(|(work, exists)| (ImportEntity::Work(Box::new(work)), exists)),
+        };
+
+        let mut row = ImportRow {
+            line: index + 1,
+            id: id.clone(),
+            action: ImportAction::Invalid,
+            errors: Vec::new(),
+            candidates: Vec::new(),
+        };
+
+        match result {
+            Ok((entity, exists)) => {
+                if !exists && !force {
+                    row.candidates = match &entity {
+                        ImportEntity::Person(person) => {
+                            database::find_similar_persons(conn, person)?
+                                .into_iter()
+                                .map(|candidate| candidate.id)
+                                .collect()
+                        }
+                        ImportEntity::Work(work) => database::find_similar_works(conn, work)?
+                            .into_iter()
+                            .map(|candidate| candidate.id)
+                            .collect(),
+                    };
+                }
+
+                row.action = if !ids.insert(id) || !row.candidates.is_empty() {
+                    ImportAction::Conflict
+                } else if exists {
+                    ImportAction::Update
+                } else {
+                    ImportAction::Create
+                };
+
+                entities.push(entity);
+            }
+            Err(errors) => row.errors = errors,
+        }
+
+        rows.push(row);
+    }
+
+    Ok((rows, entities))
+}
+
+/// Read a person from a row. Existing persons are only changed for the columns within the file.
+/// Returns the person and whether it exists already or the problems with the row.
+fn read_person(
+    conn: &DbConn,
+    record: &Record,
+    id: &str,
+    user: &User,
+) -> Result<Result<(Person, bool), Vec<FieldError>>, ServerError> {
+    let existing = get_visible(conn, EntityType::Person, id, user, database::get_person)?;
+    let exists = existing.is_some();
+
+    let mut person = existing.unwrap_or_else(|| Person {
+        id: id.to_string(),
+        first_name: String::new(),
+        last_name: String::new(),
+        period: None,
+        locked: false,
+        private: false,
+    });
+
+    let mut errors = Vec::new();
+
+    if let Some(first_name) = record.get("firstName") {
+        person.first_name = first_name.to_string();
+    }
+
+    if let Some(last_name) = record.get("lastName") {
+        person.last_name = last_name.to_string();
+    }
+
+    if let Some(period) = record.get_optional("period") {
+        person.period = period;
+    }
+
+    match record.get_bool("private") {
+        Ok(Some(private)) => person.private = private,
+        Ok(None) => (),
+        Err(message) => errors.push(field_error("private", message)),
+    }
+
+    errors.extend(validation_errors(&person));
+
+    if errors.is_empty() {
+        Ok(Ok((person, exists)))
+    } else {
+        Ok(Err(errors))
+    }
+}
+
+/// Read a work from a row. Persons and instruments are referenced by their IDs. Parts are listed
+/// by their titles, so existing parts keep their other properties. Existing works are only
+/// changed for the columns within the file. Returns the work and whether it exists already or
+/// the problems with the row.
+fn read_work(
+    conn: &DbConn,
+    record: &Record,
+    id: &str,
+    user: &User,
+) -> Result<Result<(Work, bool), Vec<FieldError>>, ServerError> {
+    let existing = get_visible(conn, EntityType::Work, id, user, database::get_work)?;
+    let exists = existing.is_some();
+    let mut errors = Vec::new();
+
+    let composer = match record.get("composer") {
+        Some(id) => {
+            let composer = get_visible(conn, EntityType::Person, id, user, database::get_person)?;
+
+            if composer.is_none() {
+                errors.push(field_error("composer", "Unknown person.".to_string()));
+            }
+
+            composer
+        }
+        None => None,
+    };
+
+    let mut work = match (existing, composer) {
+        (Some(mut work), composer) => {
+            if let Some(composer) = composer {
+                work.composer = composer;
+            }
+
+            work
+        }
+        (None, Some(composer)) => Work {
+            id: id.to_string(),
+            title: String::new(),
+            nickname: None,
+            titles: Vec::new(),
+            composer,
+            authors: Vec::new(),
+            instruments: Vec::new(),
+            parts: Vec::new(),
+            sections: Vec::new(),
+            premiere: None,
+            dedication: None,
+            period: None,
+            imslp: None,
+            locked: false,
+            private: false,
+        },
+        (None, None) => {
+            if errors.is_empty() {
+                errors.push(field_error(
+                    "composer",
+                    "Required for new works.".to_string(),
+                ));
+            }
+
+            return Ok(Err(errors));
+        }
+    };
+
+    if let Some(title) = record.get("title") {
+        work.title = title.to_string();
+    }
+
+    if let Some(authors) = record.get_list("authors") {
+        work.authors = Vec::new();
+
+        for author in authors {
+            match get_visible(
+                conn,
+                EntityType::Person,
+                &author,
+                user,
+                database::get_person,
+            )? {
+                Some(author) => work.authors.push(author),
+                None => errors.push(field_error(
+                    "authors",
+                    format!("Unknown person: {}", author),
+                )),
+            }
+        }
+    }
+
+    if let Some(instruments) = record.get_list("instruments") {
+        work.instruments = Vec::new();
+
+        for instrument in instruments {
+            let found = get_visible(
+                conn,
+                EntityType::Instrument,
+                &instrument,
+                user,
+                database::get_instrument,
+            )?;
+
+            match found {
+                Some(instrument) => work.instruments.push(instrument),
+                None => errors.push(field_error(
+                    "instruments",
+                    format!("Unknown instrument: {}", instrument),
+                )),
+            }
+        }
+    }
+
+    if let Some(titles) = record.get_list("parts") {
+        work.parts.truncate(titles.len());
+
+        for (index, title) in titles.into_iter().enumerate() {
+            match work.parts.get_mut(index) {
+                Some(part) => part.title = title,
+                None => work.parts.push(WorkPart {
+                    title,
+                    titles: Vec::new(),
+                    key: None,
+                    tempo: None,
+                    duration: None,
+                    authors: Vec::new(),
+                }),
+            }
+        }
+
+        let count = work.parts.len() as i64;
+        work.sections
+            .retain(|section| section.before_index <= count);
+    }
+
+    if let Some(period) = record.get_optional("period") {
+        work.period = period;
+    }
+
+    if let Some(dedication) = record.get_optional("dedication") {
+        work.dedication = dedication;
+    }
+
+    if let Some(imslp) = record.get_optional("imslp") {
+        work.imslp = imslp;
+    }
+
+    if let Some(nickname) = record.get_optional("nickname") {
+        work.nickname = nickname;
+    }
+
+    let date = record.get_optional("premiereDate");
+    let place = record.get_optional("premierePlace");
+
+    if date.is_some() || place.is_some() {
+        let mut premiere = work.premiere.take().unwrap_or_default();
+
+        if let Some(date) = date {
+            premiere.date = date;
+        }
+
+        if let Some(place) = place {
+            premiere.place = place;
+        }
+
+        if premiere.date.is_some() || premiere.place.is_some() || premiere.performers.is_some() {
+            work.premiere = Some(premiere);
+        }
+    }
+
+    match record.get_bool("private") {
+        Ok(Some(private)) => work.private = private,
+        Ok(None) => (),
+        Err(message) => errors.push(field_error("private", message)),
+    }
+
+    errors.extend(validation_errors(&work));
+
+    if errors.is_empty() {
+        Ok(Ok((work, exists)))
+    } else {
+        Ok(Err(errors))
+    }
+}
+
+/// Get an entity, if it exists and the user may see it.
+fn get_visible<T>(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    user: &User,
+    get: fn(&DbConn, &str) -> anyhow::Result<Option<T>>,
+) -> Result<Option<T>, ServerError> {
+    if database::is_entity_visible(conn, entity_type, id, Some(user))? {
+        Ok(get(conn, id)?)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Get the problems with an entity that were found by validating it.
+fn validation_errors<T: Validate>(entity: &T) -> Vec<FieldError> {
+    match entity.validate() {
+        Err(ServerError::Invalid(errors)) => errors.errors,
+        _ => Vec::new(),
+    }
+}
+
+/// Create a problem with a column.
+fn field_error(field: &str, message: String) -> FieldError {
+    FieldError {
+        field: field.to_string(),
+        message,
+    }
+}
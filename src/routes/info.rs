@@ -0,0 +1,86 @@
+use super::RegistrationPolicy;
+use crate::access::ReadAccess;
+use crate::captcha::CaptchaBackend;
+use crate::database;
+use crate::error::ServerError;
+use crate::maintenance::MaintenanceMode;
+use actix_web::{get, web, HttpResponse};
+use serde::Serialize;
+
+/// The version of the API. This is increased for changes that are not backwards compatible.
+pub const API_VERSION: u32 = 1;
+
+/// Formats that mediums can be exported to in addition to JSON.
+const MEDIUM_FORMATS: [&str; 4] = ["tags", "cue", "m3u", "musicbrainz"];
+
+/// Response body data describing the server and its configuration, so that clients can adapt to
+/// differently configured instances.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    /// The version of the server software.
+    pub version: &'static str,
+    pub api_version: u32,
+    pub registration: RegistrationPolicy,
+
+    /// Whether reading requires authentication.
+    pub read_access: ReadAccess,
+
+    /// The type of captchas that have to be solved, e.g. "questions".
+    pub captcha: &'static str,
+
+    /// Where search requests are handled, either "meilisearch" or "database".
+    pub search: &'static str,
+
+    pub medium_formats: Vec<&'static str>,
+
+    /// How to contact the operators of the server, if they provided that.
+    pub contact: Option<String>,
+
+    /// Whether the server currently rejects changes.
+    pub maintenance: bool,
+}
+
+impl ServerInfo {
+    /// Collect the information on the server from its configuration. The contact information is
+    /// read from the environment variable "WOLFGANG_CONTACT".
+    pub fn from_env(
+        registration: RegistrationPolicy,
+        read_access: ReadAccess,
+        captchas: &dyn CaptchaBackend,
+        has_search_index: bool,
+    ) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            api_version: API_VERSION,
+            registration,
+            read_access,
+            captcha: captchas.name(),
+            search: if has_search_index {
+                "meilisearch"
+            } else {
+                "database"
+            },
+            medium_formats: MEDIUM_FORMATS.to_vec(),
+            contact: std::env::var("WOLFGANG_CONTACT").ok(),
+            maintenance: false,
+        }
+    }
+}
+
+/// Get information on the server and its capabilities.
+#[get("/info")]
+pub async fn get_info(
+    info: web::Data<ServerInfo>,
+    maintenance: web::Data<MaintenanceMode>,
+) -> Result<HttpResponse, ServerError> {
+    let maintenance =
+        database::block(move || Ok::<_, ServerError>(maintenance.is_enabled()?)).await?;
+
+    let data = ServerInfo {
+        maintenance,
+        ..info.get_ref().clone()
+    };
+
+    Ok(HttpResponse::Ok().json(data))
+}
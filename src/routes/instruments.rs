@@ -1,23 +1,35 @@
-use super::authenticate;
+use super::Json;
+use super::{assign_id, check_redirect, updated_response, viewer_key, CreateQuery, FieldsQuery};
+use super::{authenticate, authenticate_viewer, check_visible, csv_response, get_viewer};
+use super::{ListFormat, QualityQuery, INSTRUMENT_COLUMNS};
+use crate::cache::{cached, ResponseCache};
 use crate::database;
-use crate::database::{DbPool, Instrument};
+use crate::database::{DbPool, EntityType, Instrument, ReadDbPool, Scope};
 use crate::error::ServerError;
+use crate::validation::Validate;
 use actix_web::{delete, get, post, web, HttpResponse};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 
 /// Get an existing instrument.
 #[get("/instruments/{id}")]
 pub async fn get_instrument(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        database::get_instrument(&conn, &id.into_inner())?.ok_or(ServerError::NotFound)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_redirect(&conn, EntityType::Instrument, &id, viewer.as_ref())?;
+        check_visible(&conn, EntityType::Instrument, &id, viewer.as_ref())?;
+        database::get_instrument(&conn, &id)?.ok_or(ServerError::NotFound)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
 }
 
 /// Add a new instrument or update an existin one. The user must be authorized to do that.
@@ -25,30 +37,54 @@ pub async fn get_instrument(
 pub async fn update_instrument(
     auth: BearerAuth,
     db: web::Data<DbPool>,
-    data: web::Json<Instrument>,
+    data: Json<Instrument>,
+    create: web::Query<CreateQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    let mut data = data.into_inner();
+    let generated = assign_id(&mut data.id);
+    data.validate()?;
+
+    let id = data.id.clone();
+
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteInstruments)?;
 
-        database::update_instrument(&conn, &data.into_inner(), &user)?;
+        database::with_transaction(&conn, |tx| {
+            create.check(tx, EntityType::Instrument, &data.id)?;
+            database::update_instrument_in(tx, &data, &user)
+        })?;
 
         Ok(())
     })
     .await?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(updated_response(EntityType::Instrument, id, generated))
 }
 
 #[get("/instruments")]
-pub async fn get_instruments(db: web::Data<DbPool>) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+pub async fn get_instruments(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+    query: web::Query<FieldsQuery>,
+    quality: web::Query<QualityQuery>,
+    format: ListFormat,
+) -> Result<HttpResponse, ServerError> {
+    let viewer = get_viewer(&db, auth).await?;
+    let key = viewer_key(quality.key("/instruments"), viewer.as_ref());
+
+    let data = cached(&cache, key, move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_instruments(&conn)?)
+        let instruments = database::get_instruments(&conn, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Instrument, &instruments)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    match format {
+        ListFormat::Json => Ok(HttpResponse::Ok().json(query.apply(&*data)?)),
+        ListFormat::Csv => Ok(csv_response(&data, INSTRUMENT_COLUMNS)),
+    }
 }
 
 #[delete("/instruments/{id}")]
@@ -57,9 +93,9 @@ pub async fn delete_instrument(
     db: web::Data<DbPool>,
     id: web::Path<String>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteInstruments)?;
 
         database::delete_instrument(&conn, &id.into_inner(), &user)?;
 
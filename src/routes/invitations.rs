@@ -0,0 +1,60 @@
+use super::authenticate;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+
+/// Create a new invitation code. The user must be an administrator.
+#[post("/invitations")]
+pub async fn create_invitation(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        Ok(database::insert_invitation(&conn, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get all invitations including already used ones. The user must be an administrator.
+#[get("/invitations")]
+pub async fn get_invitations(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        Ok(database::get_invitations(&conn, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Delete an invitation, so that it can't be used anymore. The user must be an administrator.
+#[delete("/invitations/{code}")]
+pub async fn delete_invitation(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    code: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        database::delete_invitation(&conn, &code.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
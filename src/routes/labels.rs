@@ -0,0 +1,129 @@
+use super::{assign_id, check_redirect, updated_response, CreateQuery, FieldsQuery, QualityQuery};
+use super::{authenticate, authenticate_viewer, check_visible, get_viewer, viewer_key};
+use super::{DeleteQuery, Json};
+use crate::cache::{cached, ResponseCache};
+use crate::database;
+use crate::database::{DbPool, EntityType, Label, ReadDbPool, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+
+/// Get an existing label.
+#[get("/labels/{id}")]
+pub async fn get_label(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_redirect(&conn, EntityType::Label, &id, viewer.as_ref())?;
+        check_visible(&conn, EntityType::Label, &id, viewer.as_ref())?;
+        database::get_label(&conn, &id)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
+}
+
+/// Add a new label or update an existing one. The user must be authorized to do that.
+#[post("/labels")]
+pub async fn update_label(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<Label>,
+    create: web::Query<CreateQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let mut data = data.into_inner();
+    let generated = assign_id(&mut data.id);
+    data.validate()?;
+
+    let id = data.id.clone();
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteMediums)?;
+
+        database::with_transaction(&conn, |tx| {
+            create.check(tx, EntityType::Label, &data.id)?;
+            database::update_label_in(tx, &data, &user)
+        })?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(updated_response(EntityType::Label, id, generated))
+}
+
+#[get("/labels")]
+pub async fn get_labels(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+    query: web::Query<FieldsQuery>,
+    quality: web::Query<QualityQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let viewer = get_viewer(&db, auth).await?;
+    let key = viewer_key(quality.key("/labels"), viewer.as_ref());
+
+    let data = cached(&cache, key, move || {
+        let conn = db.into_inner().get()?;
+        let labels = database::get_labels(&conn, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Label, &labels)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(query.apply(&*data)?))
+}
+
+/// Get everything that would be affected by deleting a label using the "cascade" option.
+#[get("/labels/{id}/delete-preview")]
+pub async fn get_label_deletion_preview(
+    auth: Option<BearerAuth>,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Label, &id, viewer.as_ref())?;
+        database::get_deletion_preview(&conn, EntityType::Label, &id)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Delete an existing label. If the "cascade" query parameter is set, the mediums of the label
+/// lose their reference to it instead of preventing the deletion. Only administrators may do that.
+#[delete("/labels/{id}")]
+pub async fn delete_label(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+    query: web::Query<DeleteQuery>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteMediums)?;
+
+        if query.cascade {
+            database::delete_cascading(&conn, EntityType::Label, &id, &user)?;
+        } else {
+            database::delete_label(&conn, &id, &user)?;
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
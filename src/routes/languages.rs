@@ -0,0 +1,167 @@
+use crate::error::ServerError;
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The maximum number of languages that are taken into account.
+const MAX_LANGUAGES: usize = 10;
+
+/// Query parameters for choosing the language of titles.
+#[derive(Deserialize, Debug, Clone)]
+struct LanguageQuery {
+    lang: Option<String>,
+}
+
+/// The languages a client prefers, most preferred first. They are taken from the "lang" query
+/// parameter, which may contain a comma separated list, or the "Accept-Language" header.
+#[derive(Debug, Clone, Default)]
+pub struct Languages(Vec<String>);
+
+impl FromRequest for Languages {
+    type Error = ServerError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let lang = actix_web::web::Query::<LanguageQuery>::from_query(req.query_string())
+            .ok()
+            .and_then(|query| query.into_inner().lang);
+
+        let languages = match lang {
+            Some(lang) => lang
+                .split(',')
+                .map(str::trim)
+                .filter(|language| !language.is_empty())
+                .map(str::to_string)
+                .take(MAX_LANGUAGES)
+                .collect(),
+            None => req
+                .headers()
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+                .map(parse_accept_language)
+                .unwrap_or_default(),
+        };
+
+        ready(Ok(Languages(languages)))
+    }
+}
+
+impl Languages {
+    /// Add the titles in the most preferred language that is available to a work or to each work
+    /// within a list, including their parts and sections. See [`Languages::localize_titled`].
+    pub fn localize(&self, data: &Value) -> Value {
+        let mut data = data.clone();
+
+        match &mut data {
+            Value::Array(items) => items.iter_mut().for_each(|item| self.localize_work(item)),
+            work => self.localize_work(work),
+        }
+
+        data
+    }
+
+    /// Localize the titles of one work and of its parts and sections.
+    fn localize_work(&self, work: &mut Value) {
+        self.localize_titled(work);
+
+        for key in &["parts", "sections"] {
+            if let Some(Value::Array(items)) = work.get_mut(*key) {
+                items.iter_mut().for_each(|item| self.localize_titled(item));
+            }
+        }
+    }
+
+    /// Add the title in the most preferred language as "localizedTitle" to an object that has
+    /// titles in different languages. Objects without a title in one of the preferred languages
+    /// get their canonical title. The title in the original language is added as "originalTitle".
+    /// That is the title marked as original or otherwise the canonical one. The canonical "title"
+    /// itself is left untouched, so that clients can still rely on it.
+    fn localize_titled(&self, object: &mut Value) {
+        let titles = match object.get("titles") {
+            Some(Value::Array(titles)) => titles,
+            _ => return,
+        };
+
+        let localized = self
+            .find_title(titles)
+            .map(Value::String)
+            .unwrap_or_else(|| object["title"].clone());
+
+        let original = titles
+            .iter()
+            .find(|title| title["original"].as_bool() == Some(true))
+            .map(|title| title["title"].clone())
+            .unwrap_or_else(|| object["title"].clone());
+
+        if let Value::Object(object) = object {
+            object.insert(String::from("localizedTitle"), localized);
+            object.insert(String::from("originalTitle"), original);
+        }
+    }
+
+    /// Find the title in the most preferred language. Titles in a regional variant of a language
+    /// are used, if there is no title in the language itself, and vice versa.
+    fn find_title(&self, titles: &[Value]) -> Option<String> {
+        for language in &self.0 {
+            let exact = titles.iter().find(|title| {
+                title["language"]
+                    .as_str()
+                    .is_some_and(|l| l.eq_ignore_ascii_case(language))
+            });
+
+            let title = exact.or_else(|| {
+                titles.iter().find(|title| {
+                    title["language"].as_str().is_some_and(|l| {
+                        primary_subtag(l).eq_ignore_ascii_case(primary_subtag(language))
+                    })
+                })
+            });
+
+            if let Some(title) = title.and_then(|title| title["title"].as_str()) {
+                return Some(title.to_string());
+            }
+        }
+
+        None
+    }
+}
+
+/// Get the languages from an "Accept-Language" header like "de-AT, de;q=0.9, en;q=0.5" ordered
+/// by their quality. Wildcards and languages with a quality of zero are ignored.
+fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut languages: Vec<(f32, String)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let language = parts.next()?;
+
+            let quality = parts
+                .find_map(|part| part.strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.parse::<f32>().ok())?;
+
+            if language.is_empty() || language == "*" || quality <= 0.0 {
+                None
+            } else {
+                Some((quality, language.to_string()))
+            }
+        })
+        .collect();
+
+    // The sort is stable, so languages with the same quality keep their order.
+    languages.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    languages
+        .into_iter()
+        .map(|(_, language)| language)
+        .take(MAX_LANGUAGES)
+        .collect()
+}
+
+/// Get the primary subtag of a language code, e.g. "de" for "de-AT".
+fn primary_subtag(language: &str) -> &str {
+    language.split('-').next().unwrap_or(language)
+}
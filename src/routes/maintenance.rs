@@ -0,0 +1,59 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use crate::maintenance::MaintenanceMode;
+use actix_web::{get, put, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// Request and response body data for the maintenance mode.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceStatus {
+    /// Whether changes are currently rejected.
+    pub enabled: bool,
+}
+
+/// Check whether the server is in maintenance mode.
+#[get("/admin/maintenance")]
+pub async fn get_maintenance(
+    maintenance: web::Data<MaintenanceMode>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        Ok::<_, ServerError>(MaintenanceStatus {
+            enabled: maintenance.is_enabled()?,
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Enter or leave maintenance mode. While the server is in maintenance mode, all requests that
+/// could change anything are rejected, except for logging in and administration requests. The
+/// user must be an administrator.
+#[put("/admin/maintenance")]
+pub async fn set_maintenance(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    maintenance: web::Data<MaintenanceMode>,
+    data: Json<MaintenanceStatus>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        maintenance.set_enabled(data.enabled)?;
+
+        Ok(data.into_inner())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
@@ -0,0 +1,107 @@
+use super::Json;
+use super::{authenticate, authenticate_viewer, check_visible};
+use crate::database;
+use crate::database::{DbPool, EntityType, MediumRelationKind, ReadDbPool, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for relating another medium to a medium.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MediumRelationSubmission {
+    /// How the medium is related to the other medium.
+    pub kind: MediumRelationKind,
+
+    /// The ID of the other medium.
+    pub medium: String,
+}
+
+/// Get all mediums directly related to a medium, e.g. its reissues or the box set it is part of.
+#[get("/mediums/{id}/relations")]
+pub async fn get_medium_relations(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Medium, &id, viewer.as_ref())?;
+        Ok(database::get_medium_relations(&conn, &id, viewer.as_ref())?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get all mediums that are connected to a medium through any chain of relations, like all
+/// releases of the same recordings.
+#[get("/mediums/{id}/related")]
+pub async fn get_related_mediums(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Medium, &id, viewer.as_ref())?;
+        Ok(database::get_related_mediums(&conn, &id, viewer.as_ref())?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Relate another medium to a medium. The user must be allowed to edit the medium.
+#[post("/mediums/{id}/relations")]
+pub async fn add_medium_relation(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+    data: Json<MediumRelationSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteMediums)?;
+
+        database::insert_medium_relation(&conn, &id.into_inner(), data.kind, &data.medium, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Remove a relation, e.g. "/mediums/{id}/relations/reissueOf/{related}".
+#[delete("/mediums/{id}/relations/{kind}/{related}")]
+pub async fn delete_medium_relation(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (id, kind, related) = path.into_inner();
+    let kind = MediumRelationKind::parse(&kind).ok_or(ServerError::NotFound)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteMediums)?;
+
+        database::delete_medium_relation(&conn, &id, kind, &related, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
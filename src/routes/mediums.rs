@@ -1,23 +1,84 @@
-use super::authenticate;
+use super::viewer_key;
+use super::{assign_id, check_redirect, read_json, updated_response, CreateQuery, FieldsQuery};
+use super::{authenticate, authenticate_viewer, authorize_nested, check_visible, get_viewer};
+use super::{MusicBrainzRelease, QualityQuery, MEDIUM_JSON_LIMIT};
+use crate::cache::{cached, ResponseCache};
 use crate::database;
-use crate::database::{DbPool, Medium};
+use crate::database::{ChecksumKind, DbPool, EntityType, Medium, ReadDbPool, Scope};
 use crate::error::ServerError;
-use actix_web::{delete, get, post, web, HttpResponse};
+use crate::validation::{FieldError, Validate, ValidationErrors};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for generated playlists and CUE sheets.
+#[derive(Deserialize, Debug, Clone)]
+pub struct FileQuery {
+    /// The file extension of the audio files to reference. This defaults to "flac".
+    pub extension: Option<String>,
+}
+
+/// The maximum length of file extensions within generated playlists and CUE sheets.
+const MAX_EXTENSION_LENGTH: usize = 8;
+
+impl FileQuery {
+    /// Get the file extension to use. It is written into the output as it is, so it may only
+    /// consist of up to 8 letters and digits. Otherwise, this fails with [`ServerError::Invalid`].
+    fn extension(&self) -> Result<&str, ServerError> {
+        let extension = self.extension.as_deref().unwrap_or("flac");
+
+        if extension.is_empty()
+            || extension.len() > MAX_EXTENSION_LENGTH
+            || !extension.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(ServerError::Invalid(ValidationErrors {
+                errors: vec![FieldError {
+                    field: "extension".to_string(),
+                    message: format!(
+                        "Must consist of 1 to {} letters or digits.",
+                        MAX_EXTENSION_LENGTH
+                    ),
+                }],
+            }));
+        }
+
+        Ok(extension)
+    }
+}
+
+/// Query parameters for exporting mediums.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExportQuery {
+    /// The format to export to. Currently, this has to be "musicbrainz".
+    pub format: String,
+
+    /// The encoding of the export, either "json" (the default) or "xml". This is named after the
+    /// corresponding parameter of the MusicBrainz web service.
+    pub fmt: Option<String>,
+}
 
 /// Get an existing medium by ID.
 #[get("/mediums/{id}")]
 pub async fn get_medium(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
     id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let viewer = get_viewer(&db, auth).await?;
+    let id = id.into_inner();
+    let key = viewer_key(format!("/mediums/{}", id), viewer.as_ref());
+
+    let data = cached(&cache, key, move || {
         let conn = db.into_inner().get()?;
-        database::get_medium(&conn, &id.into_inner())?.ok_or(ServerError::NotFound)
+        check_redirect(&conn, EntityType::Medium, &id, viewer.as_ref())?;
+        check_visible(&conn, EntityType::Medium, &id, viewer.as_ref())?;
+        database::get_medium(&conn, &id)?.ok_or(ServerError::NotFound)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    Ok(HttpResponse::Ok().json(query.apply(&*data)?))
 }
 
 /// Add a new medium or update an existing one. The user must be authorized to do that.
@@ -25,43 +86,118 @@ pub async fn get_medium(
 pub async fn update_medium(
     auth: BearerAuth,
     db: web::Data<DbPool>,
-    data: web::Json<Medium>,
+    req: HttpRequest,
+    payload: web::Payload,
+    create: web::Query<CreateQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    let mut data: Medium = read_json(&req, payload, MEDIUM_JSON_LIMIT).await?;
+    let generated = assign_id(&mut data.id);
+    data.validate()?;
+
+    let id = data.id.clone();
+
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteMediums)?;
+        authorize_nested(&conn, auth.token(), &data)?;
 
-        database::update_medium(&conn, &data.into_inner(), &user)?;
+        database::with_transaction(&conn, |tx| {
+            create.check(tx, EntityType::Medium, &data.id)?;
+            database::update_medium_in(tx, &data, &user)
+        })?;
 
         Ok(())
     })
     .await?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(updated_response(EntityType::Medium, id, generated))
 }
 
 #[get("/recordings/{id}/mediums")]
 pub async fn get_mediums_for_recording(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     recording_id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+    quality: web::Query<QualityQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_mediums_for_recording(&conn, &recording_id.into_inner())?)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let recording_id = recording_id.into_inner();
+
+        let mediums = database::get_mediums_for_recording(&conn, &recording_id, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Medium, &mediums)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
+}
+
+#[get("/labels/{id}/mediums")]
+pub async fn get_mediums_for_label(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    label_id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+    quality: web::Query<QualityQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let label_id = label_id.into_inner();
+
+        check_visible(&conn, EntityType::Label, &label_id, viewer.as_ref())?;
+        let mediums = database::get_mediums_for_label(&conn, &label_id, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Medium, &mediums)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
 }
 
 #[get("/discids/{id}/mediums")]
 pub async fn get_mediums_by_discid(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     discid: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+    quality: web::Query<QualityQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_mediums_by_discid(&conn, &discid.into_inner())?)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let discid = discid.into_inner();
+
+        let mediums = database::get_mediums_by_discid(&conn, &discid, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Medium, &mediums)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
+}
+
+/// Find the tracks that have an audio checksum, e.g. "/checksums/flacMd5/{checksum}". This
+/// allows to check whether ripped files correspond to a medium.
+#[get("/checksums/{kind}/{checksum}")]
+pub async fn get_tracks_by_checksum(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (kind, checksum) = path.into_inner();
+    let kind = ChecksumKind::parse(&kind).ok_or(ServerError::NotFound)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+
+        Ok(database::get_tracks_by_checksum(
+            &conn,
+            kind,
+            &checksum,
+            viewer.as_ref(),
+        )?)
     })
     .await?;
 
@@ -74,9 +210,9 @@ pub async fn delete_medium(
     db: web::Data<DbPool>,
     id: web::Path<String>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteMediums)?;
 
         database::delete_medium(&conn, &id.into_inner(), &user)?;
 
@@ -86,3 +222,458 @@ pub async fn delete_medium(
 
     Ok(HttpResponse::Ok().finish())
 }
+
+/// Two values of the same property of two compared mediums.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Comparison<T> {
+    pub a: T,
+    pub b: T,
+    pub equal: bool,
+}
+
+impl<T: PartialEq> Comparison<T> {
+    fn new(a: T, b: T) -> Self {
+        let equal = a == b;
+        Self { a, b, equal }
+    }
+}
+
+/// The essential data of a track for comparing mediums.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackSummary {
+    pub recording: String,
+    pub work_index: usize,
+    pub work_parts: Vec<usize>,
+    pub duration: Option<i32>,
+}
+
+/// Two tracks at the same position within two compared mediums. If one of the mediums has less
+/// tracks, the respective side is missing.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackComparison {
+    pub a: Option<TrackSummary>,
+    pub b: Option<TrackSummary>,
+
+    /// Whether both tracks contain the same parts of the same recording.
+    pub same_content: bool,
+
+    /// The duration of the second track minus the duration of the first one, if both are known.
+    pub duration_difference: Option<i64>,
+}
+
+/// A structural comparison of two mediums.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MediumDiff {
+    pub discid: Comparison<Option<String>>,
+    pub track_count: Comparison<usize>,
+
+    /// The total durations of the mediums, if the durations of all of their tracks are known.
+    pub duration: Comparison<Option<i64>>,
+
+    /// Recordings contained in both mediums.
+    pub common_recordings: Vec<String>,
+
+    /// Recordings that are only contained in the first medium.
+    pub only_a: Vec<String>,
+
+    /// Recordings that are only contained in the second medium.
+    pub only_b: Vec<String>,
+
+    /// The tracks of both mediums compared by their position.
+    pub tracks: Vec<TrackComparison>,
+}
+
+/// Compare two mediums, e.g. to decide whether they describe the same release.
+#[get("/mediums/{a}/diff/{b}")]
+pub async fn get_medium_diff(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (a, b) = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let (a, b) = path.into_inner();
+
+        check_visible(&conn, EntityType::Medium, &a, viewer.as_ref())?;
+        check_visible(&conn, EntityType::Medium, &b, viewer.as_ref())?;
+
+        let a = database::get_medium(&conn, &a)?.ok_or(ServerError::NotFound)?;
+        let b = database::get_medium(&conn, &b)?.ok_or(ServerError::NotFound)?;
+
+        Ok((a, b))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(diff_mediums(&a, &b)))
+}
+
+/// Tag values for one track of a medium, named after the common Vorbis comment fields.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackTags {
+    pub title: String,
+    pub album: String,
+    pub artist: String,
+    pub performers: Vec<String>,
+    pub composer: String,
+    pub work: String,
+    pub movement: Option<String>,
+    pub movement_number: Option<usize>,
+    pub movement_total: usize,
+    pub track_number: usize,
+    pub track_total: usize,
+    pub disc_number: usize,
+    pub disc_total: usize,
+    pub musicbrainz_discid: Option<String>,
+}
+
+/// Get tag values for all tracks of a medium that can be written into audio file metadata.
+#[get("/mediums/{id}/tags")]
+pub async fn get_medium_tags(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let medium = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Medium, &id, viewer.as_ref())?;
+        database::get_medium(&conn, &id)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(get_tags(&medium)))
+}
+
+/// Get a CUE sheet for a medium. This references one audio file containing the whole medium and
+/// requires the durations of all tracks to be known. Otherwise, this fails with 422 and names the
+/// first track without a duration.
+#[get("/mediums/{id}/cue")]
+pub async fn get_medium_cue(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+    query: web::Query<FileQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let medium = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Medium, &id, viewer.as_ref())?;
+        database::get_medium(&conn, &id)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    let cue = render_cue(&medium, query.extension()?)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-cue; charset=utf-8")
+        .body(cue))
+}
+
+/// Get an M3U playlist for a medium. The tracks are expected to be stored in one file per track
+/// named after the track number, e.g. "01.flac".
+#[get("/mediums/{id}/m3u")]
+pub async fn get_medium_m3u(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+    query: web::Query<FileQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let medium = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Medium, &id, viewer.as_ref())?;
+        database::get_medium(&conn, &id)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    let m3u = render_m3u(&medium, query.extension()?);
+
+    Ok(HttpResponse::Ok()
+        .content_type("audio/x-mpegurl; charset=utf-8")
+        .body(m3u))
+}
+
+/// Export a medium in the format of another database. With the format "musicbrainz", the medium
+/// is returned as release like the MusicBrainz web service would return it, so that it can be
+/// used as a starting point for adding the release to MusicBrainz.
+#[get("/mediums/{id}/export")]
+pub async fn get_medium_export(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ServerError> {
+    if query.format != "musicbrainz" {
+        return Err(ServerError::BadRequest);
+    }
+
+    let xml = match query.fmt.as_deref() {
+        None | Some("json") => false,
+        Some("xml") => true,
+        Some(_) => return Err(ServerError::BadRequest),
+    };
+
+    let medium = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Medium, &id, viewer.as_ref())?;
+        database::get_medium(&conn, &id)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    let release = MusicBrainzRelease::from_medium(&medium);
+
+    if xml {
+        Ok(HttpResponse::Ok()
+            .content_type("application/xml; charset=utf-8")
+            .body(release.to_xml()))
+    } else {
+        Ok(HttpResponse::Ok().json(release))
+    }
+}
+
+/// Compute the tag values for all tracks of a medium.
+fn get_tags(medium: &Medium) -> Vec<TrackTags> {
+    let mut tags = Vec::new();
+
+    let track_total = medium
+        .tracks
+        .iter()
+        .map(|track_set| track_set.tracks.len())
+        .sum();
+
+    for track_set in &medium.tracks {
+        let recording = &track_set.recording;
+
+        for track in &track_set.tracks {
+            let work = track.work(recording);
+            tags.push(TrackTags {
+                title: track.title(work),
+                album: medium.name.clone(),
+                artist: recording.performers(),
+                performers: recording
+                    .performances
+                    .iter()
+                    .map(|performance| performance.label())
+                    .collect(),
+                composer: work.composer.name_fl(),
+                work: work.title.clone(),
+                movement: track.movement(work),
+                movement_number: track.work_parts.first().map(|index| index + 1),
+                movement_total: work.parts.len(),
+                track_number: tags.len() + 1,
+                track_total,
+                disc_number: 1,
+                disc_total: 1,
+                musicbrainz_discid: medium.discid.clone(),
+            });
+        }
+    }
+
+    tags
+}
+
+/// Render a CUE sheet for a medium. This fails with [`ServerError::Invalid`], if the duration of
+/// any track is unknown, because the track positions can't be computed in that case.
+fn render_cue(medium: &Medium, extension: &str) -> Result<String, ServerError> {
+    let mut cue = String::new();
+
+    if let Some(discid) = &medium.discid {
+        cue.push_str(&format!("REM DISCID {}\n", discid));
+    }
+
+    let name = cue_escape(&medium.name);
+    cue.push_str(&format!("TITLE \"{}\"\n", name));
+    cue.push_str(&format!("FILE \"{}.{}\" WAVE\n", name, extension));
+
+    let mut number = 1;
+    // The position in milliseconds. The durations are summed up as 64 bit integers, so that
+    // this doesn't overflow even for very long mediums.
+    let mut position: i64 = 0;
+
+    for (set_index, track_set) in medium.tracks.iter().enumerate() {
+        let recording = &track_set.recording;
+
+        for (track_index, track) in track_set.tracks.iter().enumerate() {
+            let work = track.work(recording);
+            // CUE sheets use frames of 1/75 seconds.
+            let frames = position * 75 / 1000;
+
+            let title = cue_escape(&track.title(work));
+            let performers = cue_escape(&recording.performers());
+            let composer = cue_escape(&work.composer.name_fl());
+
+            cue.push_str(&format!("  TRACK {:02} AUDIO\n", number));
+            cue.push_str(&format!("    TITLE \"{}\"\n", title));
+            cue.push_str(&format!("    PERFORMER \"{}\"\n", performers));
+            cue.push_str(&format!("    SONGWRITER \"{}\"\n", composer));
+            cue.push_str(&format!(
+                "    INDEX 01 {:02}:{:02}:{:02}\n",
+                frames / (75 * 60),
+                frames / 75 % 60,
+                frames % 75
+            ));
+
+            let duration = track.duration.ok_or_else(|| {
+                ServerError::Invalid(ValidationErrors {
+                    errors: vec![FieldError {
+                        field: format!("tracks[{}].tracks[{}].duration", set_index, track_index),
+                        message: "Must be known for creating a CUE sheet.".to_string(),
+                    }],
+                })
+            })?;
+
+            position += i64::from(duration);
+            number += 1;
+        }
+    }
+
+    Ok(cue)
+}
+
+/// Render an extended M3U playlist for a medium.
+fn render_m3u(medium: &Medium, extension: &str) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    let mut number = 1;
+
+    for track_set in &medium.tracks {
+        for track in &track_set.tracks {
+            let work = track.work(&track_set.recording);
+            let seconds = match track.duration {
+                Some(duration) => duration / 1000,
+                None => -1,
+            };
+
+            m3u.push_str(&format!(
+                "#EXTINF:{},{} - {}\n{:02}.{}\n",
+                seconds,
+                work.composer.name_fl(),
+                track.title(work),
+                number,
+                extension
+            ));
+
+            number += 1;
+        }
+    }
+
+    m3u
+}
+
+/// Get the essential data of all tracks of a medium in order.
+fn summarize_tracks(medium: &Medium) -> Vec<TrackSummary> {
+    medium
+        .tracks
+        .iter()
+        .flat_map(|track_set| {
+            track_set.tracks.iter().map(move |track| TrackSummary {
+                recording: track_set.recording.id.clone(),
+                work_index: track.work_index,
+                work_parts: track.work_parts.clone(),
+                duration: track.duration,
+            })
+        })
+        .collect()
+}
+
+/// Get the IDs of all recordings of a medium without duplicates.
+fn get_recording_ids(medium: &Medium) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+
+    for track_set in &medium.tracks {
+        if !ids.contains(&track_set.recording.id) {
+            ids.push(track_set.recording.id.clone());
+        }
+    }
+
+    ids
+}
+
+/// Compare two mediums.
+fn diff_mediums(a: &Medium, b: &Medium) -> MediumDiff {
+    let tracks_a = summarize_tracks(a);
+    let tracks_b = summarize_tracks(b);
+
+    // Durations are summed up and subtracted as 64 bit integers to avoid overflows.
+    let total = |tracks: &[TrackSummary]| -> Option<i64> {
+        tracks
+            .iter()
+            .map(|track| track.duration.map(i64::from))
+            .sum()
+    };
+
+    let recordings_a = get_recording_ids(a);
+    let recordings_b = get_recording_ids(b);
+
+    let tracks = (0..tracks_a.len().max(tracks_b.len()))
+        .map(|index| {
+            let a = tracks_a.get(index).cloned();
+            let b = tracks_b.get(index).cloned();
+
+            let same_content = match (&a, &b) {
+                (Some(a), Some(b)) => {
+                    a.recording == b.recording
+                        && a.work_index == b.work_index
+                        && a.work_parts == b.work_parts
+                }
+                _ => false,
+            };
+
+            let duration_difference = match (&a, &b) {
+                (Some(a), Some(b)) => match (a.duration, b.duration) {
+                    (Some(a), Some(b)) => Some(i64::from(b) - i64::from(a)),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            TrackComparison {
+                a,
+                b,
+                same_content,
+                duration_difference,
+            }
+        })
+        .collect();
+
+    MediumDiff {
+        discid: Comparison::new(a.discid.clone(), b.discid.clone()),
+        track_count: Comparison::new(tracks_a.len(), tracks_b.len()),
+        duration: Comparison::new(total(&tracks_a), total(&tracks_b)),
+        common_recordings: recordings_a
+            .iter()
+            .filter(|id| recordings_b.contains(id))
+            .cloned()
+            .collect(),
+        only_a: recordings_a
+            .iter()
+            .filter(|id| !recordings_b.contains(id))
+            .cloned()
+            .collect(),
+        only_b: recordings_b
+            .iter()
+            .filter(|id| !recordings_a.contains(id))
+            .cloned()
+            .collect(),
+        tracks,
+    }
+}
+
+/// Make a string safe for use within quotes in a CUE sheet.
+fn cue_escape(value: &str) -> String {
+    value.replace('"', "'").replace('\n', " ")
+}
@@ -0,0 +1,41 @@
+use super::{authenticate, Json};
+use crate::database;
+use crate::database::{DbPool, EntityType};
+use crate::error::ServerError;
+use actix_web::{post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for merging an entity into another one.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeSubmission {
+    /// The ID of the entity of the same type that replaces the merged one.
+    pub into: String,
+}
+
+/// Merge a person, an ensemble, an instrument or a label into another one, e.g.
+/// "/persons/{id}/merge". Everything that referred to the entity refers to the other one
+/// afterwards and requests for its ID are redirected there. The user must be an editor.
+#[post("/{entity_type}/{id}/merge")]
+pub async fn merge_entity(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    data: Json<MergeSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = EntityType::from_path(&entity_type).ok_or(ServerError::NotFound)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), entity_type.write_scope())?;
+
+        database::merge_entities(&conn, entity_type, &id, &data.into, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,28 @@
+use super::authenticate;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use actix_web::{get, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+
+/// List all database migrations and whether they were applied. The user must be an
+/// administrator.
+#[get("/admin/migrations")]
+pub async fn get_migrations(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        Ok(database::get_migrations(&conn)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
@@ -1,23 +1,185 @@
+pub mod account;
+pub use account::*;
+
+pub mod api_keys;
+pub use api_keys::*;
+
 pub mod auth;
 pub use auth::*;
 
+pub mod backup;
+pub use backup::*;
+
+pub mod bulk_edits;
+pub use bulk_edits::*;
+
 pub mod captcha;
 pub use captcha::*;
 
+pub mod collections;
+pub use collections::*;
+
+pub mod comments;
+pub use comments::*;
+
+pub mod consistency;
+pub use consistency::*;
+
+pub mod created;
+pub use created::*;
+
+pub mod csv;
+pub use csv::*;
+
+pub mod deletion;
+pub use deletion::*;
+
+pub mod drafts;
+pub use drafts::*;
+
+pub mod dumps;
+pub use dumps::*;
+
+pub mod duplicates;
+pub use duplicates::*;
+
+pub mod editor_applications;
+pub use editor_applications::*;
+
 pub mod ensembles;
 pub use ensembles::*;
 
+pub mod events;
+pub use events::*;
+
+pub mod exists;
+pub use exists::*;
+
+pub mod export;
+pub use export::*;
+
+pub mod external_ids;
+pub use external_ids::*;
+
+pub mod fields;
+pub use fields::*;
+
+pub mod images;
+pub use images::*;
+
+pub mod import;
+pub use import::*;
+
+pub mod info;
+pub use info::*;
+
 pub mod instruments;
 pub use instruments::*;
 
+pub mod invitations;
+pub use invitations::*;
+
+pub mod labels;
+pub use labels::*;
+
+pub mod languages;
+pub use languages::*;
+
+pub mod maintenance;
+pub use maintenance::*;
+
+pub mod medium_relations;
+pub use medium_relations::*;
+
+pub mod merging;
+pub use merging::*;
+
 pub mod mediums;
 pub use mediums::*;
 
+pub mod migrations;
+pub use migrations::*;
+
+pub mod musicbrainz;
+pub use musicbrainz::*;
+
+pub mod notifications;
+pub use notifications::*;
+
+pub mod payload;
+pub use payload::*;
+
+pub mod periods;
+pub use periods::*;
+
+pub mod person_relations;
+pub use person_relations::*;
+
 pub mod persons;
 pub use persons::*;
 
+pub mod playlists;
+pub use playlists::*;
+
+pub mod plays;
+pub use plays::*;
+
+pub mod quality;
+pub use quality::*;
+
+pub mod ratings;
+pub use ratings::*;
+
+pub mod rebuild;
+pub use rebuild::*;
+
 pub mod recordings;
 pub use recordings::*;
 
+pub mod reports;
+pub use reports::*;
+
+pub mod revisions;
+pub use revisions::*;
+
+pub mod schemas;
+pub use schemas::*;
+
+pub mod search;
+pub use search::*;
+
+pub mod setup;
+pub use setup::*;
+
+pub mod sources;
+pub use sources::*;
+
+pub mod statistics;
+pub use statistics::*;
+
+pub mod toc;
+pub use toc::*;
+
+pub mod trash;
+pub use trash::*;
+
+pub mod visibility;
+pub use visibility::*;
+
+pub mod watches;
+pub use watches::*;
+
+pub mod webhooks;
+pub use webhooks::*;
+
+pub mod wikidata;
+pub use wikidata::*;
+
+pub mod work_texts;
+pub use work_texts::*;
+
 pub mod works;
 pub use works::*;
+
+pub mod ws;
+pub use ws::*;
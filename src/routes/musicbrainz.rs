@@ -0,0 +1,496 @@
+use crate::database::{Ensemble, Medium, Performance, Person, Recording, Track, Work};
+use serde::Serialize;
+
+/// The XML namespace of the MusicBrainz web service.
+const XML_NAMESPACE: &str = "http://musicbrainz.org/ns/mmd-2.0#";
+
+/// The join phrase between multiple artists within one artist credit.
+const JOIN_PHRASE: &str = "; ";
+
+/// A medium as release in the format of the MusicBrainz web service. Each medium becomes a
+/// release with one medium, as it would be returned by MusicBrainz for a release including
+/// recordings, artist credits, labels and relationships. Tracks are credited to the composers of
+/// the works, as recommended by the MusicBrainz style guidelines for classical music, while the
+/// performers are listed as relationships of the recordings.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct MusicBrainzRelease {
+    title: String,
+    artist_credit: Vec<NameCredit>,
+    label_info: Vec<LabelInfo>,
+    media: Vec<ReleaseMedium>,
+}
+
+/// One artist of an artist credit.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct NameCredit {
+    name: String,
+    joinphrase: String,
+    artist: Artist,
+}
+
+/// A person or an ensemble.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct Artist {
+    name: String,
+    sort_name: String,
+
+    /// Either "Person" or "Group".
+    #[serde(rename = "type")]
+    artist_type: &'static str,
+}
+
+/// The label that released a release.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct LabelInfo {
+    catalog_number: Option<String>,
+    label: ReleaseLabel,
+}
+
+/// A label by name.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct ReleaseLabel {
+    name: String,
+}
+
+/// A medium within a release.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct ReleaseMedium {
+    position: usize,
+
+    /// "CD" for mediums with a DiscID. The format of other mediums is unknown.
+    format: Option<&'static str>,
+
+    discs: Vec<Disc>,
+    track_count: usize,
+    tracks: Vec<ReleaseTrack>,
+}
+
+/// A MusicBrainz DiscID.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct Disc {
+    id: String,
+}
+
+/// A track of a medium.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct ReleaseTrack {
+    position: usize,
+    number: String,
+    title: String,
+
+    /// The length in milliseconds.
+    length: Option<i32>,
+
+    artist_credit: Vec<NameCredit>,
+    recording: ReleaseRecording,
+}
+
+/// The recording that is played on a track.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct ReleaseRecording {
+    title: String,
+    length: Option<i32>,
+    artist_credit: Vec<NameCredit>,
+    relations: Vec<Relation>,
+}
+
+/// A relationship of a recording to a performer or to the work it is a performance of.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct Relation {
+    #[serde(rename = "type")]
+    relation_type: &'static str,
+    target_type: &'static str,
+    direction: &'static str,
+    attributes: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artist: Option<Artist>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    work: Option<RelatedWork>,
+}
+
+/// A work by title.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct RelatedWork {
+    title: String,
+}
+
+impl MusicBrainzRelease {
+    /// Convert a medium to a release.
+    pub fn from_medium(medium: &Medium) -> Self {
+        let mut tracks = Vec::new();
+        let mut composers: Vec<&Person> = Vec::new();
+
+        for track_set in &medium.tracks {
+            for track in &track_set.tracks {
+                let work = track.work(&track_set.recording);
+
+                if !composers.iter().any(|person| person.id == work.composer.id) {
+                    composers.push(&work.composer);
+                }
+
+                let position = tracks.len() + 1;
+                tracks.push(get_track(position, track, &track_set.recording, work));
+            }
+        }
+
+        let label_info = medium
+            .label
+            .iter()
+            .map(|label| LabelInfo {
+                catalog_number: None,
+                label: ReleaseLabel {
+                    name: label.name.clone(),
+                },
+            })
+            .collect();
+
+        Self {
+            title: medium.name.clone(),
+            artist_credit: get_artist_credit(composers.into_iter().map(person_artist).collect()),
+            label_info,
+            media: vec![ReleaseMedium {
+                position: 1,
+                format: medium.discid.as_ref().map(|_| "CD"),
+                discs: medium
+                    .discid
+                    .iter()
+                    .map(|discid| Disc { id: discid.clone() })
+                    .collect(),
+                track_count: tracks.len(),
+                tracks,
+            }],
+        }
+    }
+
+    /// Render the release as XML document like the ones returned by the MusicBrainz web service.
+    pub fn to_xml(&self) -> String {
+        let mut xml = XmlWriter::new();
+
+        xml.open("metadata", &[("xmlns", XML_NAMESPACE)]);
+        xml.open("release", &[]);
+        xml.text("title", &self.title);
+        write_artist_credit(&mut xml, &self.artist_credit);
+
+        let count = self.label_info.len().to_string();
+        xml.open("label-info-list", &[("count", &count)]);
+        for label_info in &self.label_info {
+            xml.open("label-info", &[]);
+            if let Some(catalog_number) = &label_info.catalog_number {
+                xml.text("catalog-number", catalog_number);
+            }
+            xml.open("label", &[]);
+            xml.text("name", &label_info.label.name);
+            xml.close("label");
+            xml.close("label-info");
+        }
+        xml.close("label-info-list");
+
+        let count = self.media.len().to_string();
+        xml.open("medium-list", &[("count", &count)]);
+        for medium in &self.media {
+            write_medium(&mut xml, medium);
+        }
+        xml.close("medium-list");
+
+        xml.close("release");
+        xml.close("metadata");
+
+        xml.finish()
+    }
+}
+
+/// Convert a track of a medium.
+fn get_track(position: usize, track: &Track, recording: &Recording, work: &Work) -> ReleaseTrack {
+    let title = track.title(work);
+    let artist_credit = get_artist_credit(vec![person_artist(&work.composer)]);
+
+    let mut relations: Vec<Relation> = recording
+        .performances
+        .iter()
+        .filter_map(performance_relation)
+        .collect();
+
+    relations.push(Relation {
+        relation_type: "performance",
+        target_type: "work",
+        direction: "forward",
+        attributes: Vec::new(),
+        artist: None,
+        work: Some(RelatedWork {
+            title: title.clone(),
+        }),
+    });
+
+    ReleaseTrack {
+        position,
+        number: position.to_string(),
+        title: title.clone(),
+        length: track.duration,
+        artist_credit: artist_credit.clone(),
+        recording: ReleaseRecording {
+            title,
+            length: track.duration,
+            artist_credit,
+            relations,
+        },
+    }
+}
+
+/// Create an artist credit joining all provided artists.
+fn get_artist_credit(artists: Vec<Artist>) -> Vec<NameCredit> {
+    let count = artists.len();
+
+    artists
+        .into_iter()
+        .enumerate()
+        .map(|(index, artist)| NameCredit {
+            name: artist.name.clone(),
+            joinphrase: if index + 1 < count {
+                JOIN_PHRASE.to_string()
+            } else {
+                String::new()
+            },
+            artist,
+        })
+        .collect()
+}
+
+/// Convert a performance to a relationship. Performers with a role become instrument
+/// relationships. This will return [`None`], if the performance has no performer.
+fn performance_relation(performance: &Performance) -> Option<Relation> {
+    let artist = match (&performance.person, &performance.ensemble) {
+        (Some(person), _) => person_artist(person),
+        (None, Some(ensemble)) => ensemble_artist(ensemble),
+        (None, None) => return None,
+    };
+
+    let (relation_type, attributes) = match &performance.role {
+        Some(role) => ("instrument", vec![role.name.clone()]),
+        None => ("performer", Vec::new()),
+    };
+
+    Some(Relation {
+        relation_type,
+        target_type: "artist",
+        direction: "backward",
+        attributes,
+        artist: Some(artist),
+        work: None,
+    })
+}
+
+/// Convert a person to an artist.
+fn person_artist(person: &Person) -> Artist {
+    Artist {
+        name: person.name_fl(),
+        sort_name: format!("{}, {}", person.last_name, person.first_name),
+        artist_type: "Person",
+    }
+}
+
+/// Convert an ensemble to an artist.
+fn ensemble_artist(ensemble: &Ensemble) -> Artist {
+    Artist {
+        name: ensemble.name.clone(),
+        sort_name: ensemble.name.clone(),
+        artist_type: "Group",
+    }
+}
+
+/// Write an artist credit as XML.
+fn write_artist_credit(xml: &mut XmlWriter, artist_credit: &[NameCredit]) {
+    xml.open("artist-credit", &[]);
+    for name_credit in artist_credit {
+        if name_credit.joinphrase.is_empty() {
+            xml.open("name-credit", &[]);
+        } else {
+            xml.open("name-credit", &[("joinphrase", &name_credit.joinphrase)]);
+        }
+        write_artist(xml, &name_credit.artist);
+        xml.close("name-credit");
+    }
+    xml.close("artist-credit");
+}
+
+/// Write an artist as XML.
+fn write_artist(xml: &mut XmlWriter, artist: &Artist) {
+    xml.open("artist", &[("type", artist.artist_type)]);
+    xml.text("name", &artist.name);
+    xml.text("sort-name", &artist.sort_name);
+    xml.close("artist");
+}
+
+/// Write a medium including all of its tracks as XML.
+fn write_medium(xml: &mut XmlWriter, medium: &ReleaseMedium) {
+    xml.open("medium", &[]);
+    xml.text("position", &medium.position.to_string());
+    if let Some(format) = medium.format {
+        xml.text("format", format);
+    }
+
+    let count = medium.discs.len().to_string();
+    xml.open("disc-list", &[("count", &count)]);
+    for disc in &medium.discs {
+        xml.empty("disc", &[("id", &disc.id)]);
+    }
+    xml.close("disc-list");
+
+    let count = medium.track_count.to_string();
+    xml.open("track-list", &[("count", &count), ("offset", "0")]);
+    for track in &medium.tracks {
+        xml.open("track", &[]);
+        xml.text("position", &track.position.to_string());
+        xml.text("number", &track.number);
+        xml.text("title", &track.title);
+        if let Some(length) = track.length {
+            xml.text("length", &length.to_string());
+        }
+        write_artist_credit(xml, &track.artist_credit);
+        write_recording(xml, &track.recording);
+        xml.close("track");
+    }
+    xml.close("track-list");
+
+    xml.close("medium");
+}
+
+/// Write a recording including its relationships as XML. Relationships are grouped by their
+/// target type.
+fn write_recording(xml: &mut XmlWriter, recording: &ReleaseRecording) {
+    xml.open("recording", &[]);
+    xml.text("title", &recording.title);
+    if let Some(length) = recording.length {
+        xml.text("length", &length.to_string());
+    }
+    write_artist_credit(xml, &recording.artist_credit);
+
+    for target_type in &["artist", "work"] {
+        let relations: Vec<&Relation> = recording
+            .relations
+            .iter()
+            .filter(|relation| relation.target_type == *target_type)
+            .collect();
+
+        if relations.is_empty() {
+            continue;
+        }
+
+        xml.open("relation-list", &[("target-type", target_type)]);
+        for relation in relations {
+            xml.open("relation", &[("type", relation.relation_type)]);
+            xml.text("direction", relation.direction);
+
+            if !relation.attributes.is_empty() {
+                xml.open("attribute-list", &[]);
+                for attribute in &relation.attributes {
+                    xml.text("attribute", attribute);
+                }
+                xml.close("attribute-list");
+            }
+
+            if let Some(artist) = &relation.artist {
+                write_artist(xml, artist);
+            }
+
+            if let Some(work) = &relation.work {
+                xml.open("work", &[]);
+                xml.text("title", &work.title);
+                xml.close("work");
+            }
+
+            xml.close("relation");
+        }
+        xml.close("relation-list");
+    }
+
+    xml.close("recording");
+}
+
+/// A minimal writer for indented XML documents.
+struct XmlWriter {
+    xml: String,
+    depth: usize,
+}
+
+impl XmlWriter {
+    /// Start a new document.
+    fn new() -> Self {
+        Self {
+            xml: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"),
+            depth: 0,
+        }
+    }
+
+    /// Open an element. It has to be closed using [`XmlWriter::close`].
+    fn open(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.start_tag(name, attributes);
+        self.xml.push_str(">\n");
+        self.depth += 1;
+    }
+
+    /// Close the last opened element.
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        self.xml.push_str(&format!("</{}>\n", name));
+    }
+
+    /// Write an element without content.
+    fn empty(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.start_tag(name, attributes);
+        self.xml.push_str("/>\n");
+    }
+
+    /// Write an element containing only text.
+    fn text(&mut self, name: &str, value: &str) {
+        self.start_tag(name, &[]);
+        self.xml
+            .push_str(&format!(">{}</{}>\n", xml_escape(value), name));
+    }
+
+    /// Get the document.
+    fn finish(self) -> String {
+        self.xml
+    }
+
+    /// Write the beginning of a start tag up to the closing angle bracket.
+    fn start_tag(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.indent();
+        self.xml.push('<');
+        self.xml.push_str(name);
+
+        for (key, value) in attributes {
+            self.xml
+                .push_str(&format!(" {}=\"{}\"", key, xml_escape(value)));
+        }
+    }
+
+    /// Indent the next line according to the current depth.
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.xml.push_str("  ");
+        }
+    }
+}
+
+/// Make a string safe for use within XML text and attribute values.
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
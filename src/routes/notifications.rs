@@ -0,0 +1,62 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use actix_web::{get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Query parameters for listing notifications.
+#[derive(Deserialize, Debug, Clone)]
+pub struct NotificationsQuery {
+    /// Only list notifications that haven't been read yet.
+    #[serde(default)]
+    pub unread: bool,
+}
+
+/// Request body data for marking notifications as read.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationsRead {
+    /// The notifications to mark. If this is missing, all notifications are marked.
+    pub ids: Option<Vec<i64>>,
+}
+
+/// Get the latest notifications of the current user.
+#[get("/account/notifications")]
+pub async fn get_notifications(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    query: web::Query<NotificationsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(database::get_notifications(&conn, &user, query.unread)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Mark notifications of the current user as read.
+#[post("/account/notifications/read")]
+pub async fn read_notifications(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<NotificationsRead>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::mark_notifications_read(&conn, &user, data.ids.as_deref())?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
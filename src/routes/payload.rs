@@ -0,0 +1,242 @@
+use crate::error::ServerError;
+use crate::validation::{FieldError, ValidationErrors};
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use anyhow::{anyhow, Result};
+use futures::future::LocalBoxFuture;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use std::ops::{Deref, DerefMut};
+
+/// The maximum size of JSON request bodies in bytes, unless a route allows more.
+pub const JSON_LIMIT: usize = 256 * 1024;
+
+/// The maximum size of a medium in bytes. Mediums include all of their tracks together with the
+/// recordings and works they belong to, so they are a lot larger than other entities.
+pub const MEDIUM_JSON_LIMIT: usize = 8 * 1024 * 1024;
+
+/// The maximum size of the texts of a work in bytes. Librettos of operas together with their
+/// translations easily exceed the default limit.
+pub const WORK_TEXTS_JSON_LIMIT: usize = 4 * 1024 * 1024;
+
+/// The maximum size of uploaded images in bytes. Images are sent as Base64 encoded JSON strings,
+/// so the files themselves may be about three quarters of this.
+pub const IMAGE_JSON_LIMIT: usize = 16 * 1024 * 1024;
+
+/// The maximum size of CSV or TSV files to import in bytes.
+pub const CSV_LIMIT: usize = 4 * 1024 * 1024;
+
+/// What happens to fields within JSON request bodies that the server doesn't know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Unknown fields are left out silently.
+    Ignore,
+
+    /// Requests containing unknown fields fail with "400 Bad Request" listing the fields.
+    Reject,
+}
+
+impl UnknownFields {
+    /// Get the default handling of unknown fields from the environment variable
+    /// "WOLFGANG_UNKNOWN_FIELDS". Possible values are "ignore" (the default) and "reject".
+    pub fn from_env() -> Result<Self> {
+        let unknown_fields = match std::env::var("WOLFGANG_UNKNOWN_FIELDS") {
+            Ok(unknown_fields) => match unknown_fields.as_str() {
+                "ignore" => UnknownFields::Ignore,
+                "reject" => UnknownFields::Reject,
+                _ => return Err(anyhow!("Unknown handling of fields: {}", unknown_fields)),
+            },
+            Err(_) => UnknownFields::Ignore,
+        };
+
+        Ok(unknown_fields)
+    }
+
+    /// Get the handling of unknown fields for a request. Clients can choose it using the
+    /// "Prefer" header with "handling=strict" or "handling=lenient" as defined in RFC 7240.
+    /// Otherwise, the configured default is used.
+    fn of_request(req: &HttpRequest) -> Self {
+        let handling = req
+            .headers()
+            .get_all("Prefer")
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|preference| preference.trim().strip_prefix("handling="))
+            .last();
+
+        match handling {
+            Some("strict") => UnknownFields::Reject,
+            Some("lenient") => UnknownFields::Ignore,
+            _ => req
+                .app_data::<web::Data<UnknownFields>>()
+                .map(|unknown_fields| *unknown_fields.get_ref())
+                .unwrap_or(UnknownFields::Ignore),
+        }
+    }
+}
+
+/// A JSON request body of up to [`JSON_LIMIT`] bytes. Unlike [`web::Json`], this reports where
+/// the body doesn't match the expected data and may reject unknown fields, see [`UnknownFields`].
+#[derive(Debug, Clone)]
+pub struct Json<T>(pub T);
+
+impl<T> Json<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Json<T> {
+    type Error = ServerError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let payload = web::Payload(payload.take());
+        let content_type = req.content_type().to_lowercase();
+
+        Box::pin(async move {
+            if content_type != "application/json" && !content_type.ends_with("+json") {
+                return Err(ServerError::BadRequest);
+            }
+
+            Ok(Json(read_json(&req, payload, JSON_LIMIT).await?))
+        })
+    }
+}
+
+/// Read and parse a JSON request body that may be up to `limit` bytes long. This is meant for
+/// routes that need a different limit than the default one.
+pub async fn read_json<T: DeserializeOwned>(
+    req: &HttpRequest,
+    payload: web::Payload,
+    limit: usize,
+) -> Result<T, ServerError> {
+    let body = read_body(payload, limit).await?;
+    parse_json(&body, UnknownFields::of_request(req))
+}
+
+/// Parse a JSON request body. If it doesn't match the expected data, the error lists the path
+/// to the problem, e.g. "parts[2].duration".
+pub fn parse_json<T: DeserializeOwned>(
+    body: &[u8],
+    unknown_fields: UnknownFields,
+) -> Result<T, ServerError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let mut ignored = Vec::new();
+
+    let mut record_ignored = |path: serde_ignored::Path| ignored.push(ignored_path(&path));
+    let ignoring = serde_ignored::Deserializer::new(&mut deserializer, &mut record_ignored);
+
+    let data = serde_path_to_error::deserialize(ignoring)
+        .map_err(|error| malformed(error_path(error.path()), error.inner().to_string()))?;
+
+    // Anything except whitespace after the data is an error as well.
+    deserializer
+        .end()
+        .map_err(|error| malformed(String::new(), error.to_string()))?;
+
+    if unknown_fields == UnknownFields::Reject && !ignored.is_empty() {
+        let errors = ignored
+            .into_iter()
+            .map(|field| FieldError {
+                field,
+                message: "Unknown field".to_string(),
+            })
+            .collect();
+
+        return Err(ServerError::Malformed(ValidationErrors { errors }));
+    }
+
+    Ok(data)
+}
+
+/// Create an error for a request body that doesn't match the expected data.
+fn malformed(field: String, message: String) -> ServerError {
+    ServerError::Malformed(ValidationErrors {
+        errors: vec![FieldError { field, message }],
+    })
+}
+
+/// Format the path of a field that failed to parse like the paths of validation errors.
+fn error_path(path: &serde_path_to_error::Path) -> String {
+    let mut field = String::new();
+
+    for segment in path.iter() {
+        match segment {
+            serde_path_to_error::Segment::Seq { index } => {
+                field.push_str(&format!("[{}]", index));
+            }
+            serde_path_to_error::Segment::Map { key } => push_key(&mut field, key),
+            serde_path_to_error::Segment::Enum { variant } => push_key(&mut field, variant),
+            serde_path_to_error::Segment::Unknown => push_key(&mut field, "?"),
+        }
+    }
+
+    field
+}
+
+/// Format the path of an unknown field like the paths of validation errors.
+fn ignored_path(path: &serde_ignored::Path) -> String {
+    match path {
+        serde_ignored::Path::Root => String::new(),
+        serde_ignored::Path::Seq { parent, index } => {
+            format!("{}[{}]", ignored_path(parent), index)
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            let mut field = ignored_path(parent);
+            push_key(&mut field, key);
+            field
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_path(parent),
+    }
+}
+
+/// Append the key of an object to a path.
+fn push_key(field: &mut String, key: &str) {
+    if !field.is_empty() {
+        field.push('.');
+    }
+
+    field.push_str(key);
+}
+
+/// Read a UTF-8 encoded text request body that may be up to `limit` bytes long.
+pub async fn read_text(payload: web::Payload, limit: usize) -> Result<String, ServerError> {
+    let body = read_body(payload, limit).await?;
+    String::from_utf8(body.to_vec()).or(Err(ServerError::BadRequest))
+}
+
+/// Read a request body that may be up to `limit` bytes long.
+async fn read_body(mut payload: web::Payload, limit: usize) -> Result<web::BytesMut, ServerError> {
+    let mut body = web::BytesMut::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.or(Err(ServerError::BadRequest))?;
+
+        if body.len() + chunk.len() > limit {
+            return Err(ServerError::PayloadTooLarge);
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
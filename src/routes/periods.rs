@@ -0,0 +1,116 @@
+use super::authenticate;
+use super::Json;
+use crate::cache::{cached, ResponseCache};
+use crate::database;
+use crate::database::{DbPool, Period, ReadDbPool, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Query parameters for limiting lists and search results to one musical period.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PeriodQuery {
+    /// The ID of the period.
+    pub period: Option<String>,
+}
+
+impl PeriodQuery {
+    /// Remove all persons or works from a list that don't belong to the requested period. Works
+    /// without a period of their own belong to the period of their composer.
+    pub fn apply(&self, data: &Value) -> Value {
+        match (&self.period, data) {
+            (Some(period), Value::Array(items)) => Value::Array(
+                items
+                    .iter()
+                    .filter(|item| {
+                        let own = &item["period"];
+                        let effective = if own.is_null() {
+                            &item["composer"]["period"]
+                        } else {
+                            own
+                        };
+
+                        effective.as_str() == Some(period.as_str())
+                    })
+                    .cloned()
+                    .collect(),
+            ),
+            _ => data.clone(),
+        }
+    }
+}
+
+/// Get an existing period.
+#[get("/periods/{id}")]
+pub async fn get_period(
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        database::get_period(&conn, &id.into_inner())?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get all periods in chronological order.
+#[get("/periods")]
+pub async fn get_periods(
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+) -> Result<HttpResponse, ServerError> {
+    let data = cached(&cache, "/periods".to_string(), move || {
+        let conn = db.into_inner().get()?;
+        Ok(database::get_periods(&conn)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(&*data))
+}
+
+/// Add a new period or update an existing one. Only editors may do that.
+#[post("/periods")]
+pub async fn update_period(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<Period>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePeriods)?;
+
+        database::update_period(&conn, &data.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Delete a period that isn't used anymore. Only editors may do that.
+#[delete("/periods/{id}")]
+pub async fn delete_period(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePeriods)?;
+
+        database::delete_period(&conn, &id.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
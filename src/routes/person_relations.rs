@@ -0,0 +1,86 @@
+use super::Json;
+use super::{authenticate, authenticate_viewer, check_visible};
+use crate::database;
+use crate::database::{DbPool, EntityType, ReadDbPool, RelationKind, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for relating another person to a person.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelationSubmission {
+    /// How the other person is related from the perspective of the person.
+    pub kind: RelationKind,
+
+    /// The ID of the other person.
+    pub person: String,
+}
+
+/// Get all persons related to a person, e.g. teachers, students and family members.
+#[get("/persons/{id}/relations")]
+pub async fn get_person_relations(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Person, &id, viewer.as_ref())?;
+        Ok(database::get_person_relations(&conn, &id, viewer.as_ref())?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Relate another person to a person. The user must be allowed to edit the person.
+#[post("/persons/{id}/relations")]
+pub async fn add_person_relation(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+    data: Json<RelationSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePersons)?;
+
+        database::insert_person_relation(&conn, &id.into_inner(), data.kind, &data.person, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Remove a relation, e.g. "/persons/{id}/relations/teacher/{related}".
+#[delete("/persons/{id}/relations/{kind}/{related}")]
+pub async fn delete_person_relation(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (id, kind, related) = path.into_inner();
+    let kind = RelationKind::parse(&kind).ok_or(ServerError::NotFound)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePersons)?;
+
+        database::delete_person_relation(&conn, &id, kind, &related, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -1,67 +1,215 @@
-use super::authenticate;
+use super::check_redirect;
+use super::Json;
+use super::{assign_id, updated_response, CreateQuery, PeriodQuery, QualityQuery, PERSON_COLUMNS};
+use super::{authenticate, authenticate_viewer, check_visible, get_viewer, viewer_key};
+use super::{csv_response, DeleteQuery, DuplicateQuery, Duplicates, FieldsQuery, ListFormat};
+use crate::cache::{cached, ResponseCache};
 use crate::database;
-use crate::database::{DbPool, Person};
+use crate::database::{DbPool, EntityType, Person, ReadDbPool, Scope};
 use crate::error::ServerError;
+use crate::validation::Validate;
 use actix_web::{delete, get, post, web, HttpResponse};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 
 /// Get an existing person.
 #[get("/persons/{id}")]
 pub async fn get_person(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        database::get_person(&conn, &id.into_inner())?.ok_or(ServerError::NotFound)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_redirect(&conn, EntityType::Person, &id, viewer.as_ref())?;
+        check_visible(&conn, EntityType::Person, &id, viewer.as_ref())?;
+        database::get_person(&conn, &id)?.ok_or(ServerError::NotFound)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
 }
 
-/// Add a new person or update an existin one. The user must be authorized to do that.
+/// Get the works and recordings of a person, the ensembles they performed with and how many of
+/// each there are.
+#[get("/persons/{id}/overview")]
+pub async fn get_person_overview(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+    id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let viewer = get_viewer(&db, auth).await?;
+    let id = id.into_inner();
+    let key = viewer_key(format!("/persons/{}/overview", id), viewer.as_ref());
+
+    let data = cached(&cache, key, move || {
+        let conn = db.into_inner().get()?;
+        check_visible(&conn, EntityType::Person, &id, viewer.as_ref())?;
+        database::get_person_overview(&conn, &id, viewer.as_ref())?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(query.apply(&*data)?))
+}
+
+/// Add a new person or update an existin one. The user must be authorized to do that. New
+/// persons that look like duplicates of existing ones are rejected with a list of candidates,
+/// unless the "force" query parameter is set.
 #[post("/persons")]
 pub async fn update_person(
     auth: BearerAuth,
     db: web::Data<DbPool>,
-    data: web::Json<Person>,
+    data: Json<Person>,
+    query: web::Query<DuplicateQuery>,
+    create: web::Query<CreateQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    let mut person = data.into_inner();
+    let generated = assign_id(&mut person.id);
+    person.validate()?;
+
+    let id = person.id.clone();
+
+    let candidates = database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePersons)?;
 
-        database::update_person(&conn, &data.into_inner(), &user)?;
+        let candidates = database::with_transaction(&conn, |tx| {
+            let conn = tx.conn();
+            create.check(tx, EntityType::Person, &person.id)?;
 
-        Ok(())
+            if !query.force && database::get_person(conn, &person.id)?.is_none() {
+                let candidates = database::find_similar_persons(conn, &person)?;
+                if !candidates.is_empty() {
+                    return Ok(Some(candidates));
+                }
+            }
+
+            database::update_person_in(tx, &person, &user)?;
+
+            Ok(None)
+        })?;
+
+        Ok(candidates)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().finish())
+    match candidates {
+        Some(candidates) => Ok(HttpResponse::Conflict().json(Duplicates { candidates })),
+        None => Ok(updated_response(EntityType::Person, id, generated)),
+    }
 }
 
 #[get("/persons")]
-pub async fn get_persons(db: web::Data<DbPool>) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+pub async fn get_persons(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+    query: web::Query<FieldsQuery>,
+    period: web::Query<PeriodQuery>,
+    quality: web::Query<QualityQuery>,
+    format: ListFormat,
+) -> Result<HttpResponse, ServerError> {
+    let viewer = get_viewer(&db, auth).await?;
+    let key = viewer_key(quality.key("/persons"), viewer.as_ref());
+
+    let data = cached(&cache, key, move || {
+        let conn = db.into_inner().get()?;
+        let persons = database::get_persons(&conn, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Person, &persons)
+    })
+    .await?;
+
+    let data = period.apply(&data);
+
+    match format {
+        ListFormat::Json => Ok(HttpResponse::Ok().json(query.apply(&data)?)),
+        ListFormat::Csv => Ok(csv_response(&data, PERSON_COLUMNS)),
+    }
+}
+
+/// Get everything that would be affected by deleting a person using the "cascade" option.
+#[get("/persons/{id}/delete-preview")]
+pub async fn get_person_deletion_preview(
+    auth: Option<BearerAuth>,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_persons(&conn)?)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Person, &id, viewer.as_ref())?;
+        database::get_deletion_preview(&conn, EntityType::Person, &id)?.ok_or(ServerError::NotFound)
     })
     .await?;
 
     Ok(HttpResponse::Ok().json(data))
 }
 
+/// Delete an existing person. If the "cascade" query parameter is set, everything that refers to the
+/// person is deleted as well. Only administrators may do that.
 #[delete("/persons/{id}")]
 pub async fn delete_person(
     auth: BearerAuth,
     db: web::Data<DbPool>,
     id: web::Path<String>,
+    query: web::Query<DeleteQuery>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePersons)?;
+
+        if query.cascade {
+            database::delete_cascading(&conn, EntityType::Person, &id, &user)?;
+        } else {
+            database::delete_person(&conn, &id, &user)?;
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Lock an existing person, so that only editors can change it. The user must be an editor.
+#[post("/persons/{id}/lock")]
+pub async fn lock_person(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePersons)?;
+
+        database::set_person_locked(&conn, &id.into_inner(), true, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Unlock an existing person. The user must be an editor.
+#[delete("/persons/{id}/lock")]
+pub async fn unlock_person(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePersons)?;
 
-        database::delete_person(&conn, &id.into_inner(), &user)?;
+        database::set_person_locked(&conn, &id.into_inner(), false, &user)?;
 
         Ok(())
     })
@@ -0,0 +1,98 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, PlaylistItem, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for creating or replacing a playlist.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistSubmission {
+    /// An unique ID for the playlist chosen by the client.
+    pub id: String,
+
+    pub name: String,
+    pub items: Vec<PlaylistItem>,
+}
+
+/// Get all playlists of the current user.
+#[get("/account/playlists")]
+pub async fn get_playlists(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(database::get_playlists(&conn, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get a playlist of the current user.
+#[get("/account/playlists/{id}")]
+pub async fn get_playlist(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::get_playlist(&conn, &id.into_inner(), &user)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Create a new playlist or replace an existing playlist of the current user.
+#[post("/account/playlists")]
+pub async fn update_playlist(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<PlaylistSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        let data = data.into_inner();
+        database::update_playlist(&conn, &data.id, &data.name, &data.items, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Delete a playlist of the current user.
+#[delete("/account/playlists/{id}")]
+pub async fn delete_playlist(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::delete_playlist(&conn, &id.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,130 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, Play, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Request body data for submitting plays.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaysSubmission {
+    pub plays: Vec<Play>,
+}
+
+/// Query parameters for getting the listening history.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaysQuery {
+    /// Only consider plays after this time.
+    pub since: Option<NaiveDateTime>,
+}
+
+/// Whether plays of the current user are stored.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaySettings {
+    pub enabled: bool,
+}
+
+/// Store plays of the current user. The user has to opt in first.
+#[post("/account/plays")]
+pub async fn submit_plays(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<PlaysSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::insert_plays(&conn, &data.plays, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Get the listening history of the current user including the most played composers and works.
+#[get("/account/plays")]
+pub async fn get_plays(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    query: web::Query<PlaysQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(database::get_play_history(&conn, query.since, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Delete the whole listening history of the current user.
+#[delete("/account/plays")]
+pub async fn delete_plays(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::delete_plays(&conn, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Get whether plays of the current user are stored.
+#[get("/account/plays/settings")]
+pub async fn get_play_settings(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(PlaySettings {
+            enabled: user.record_plays,
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Opt in to or out of storing plays of the current user. Opting out deletes the existing history.
+#[put("/account/plays/settings")]
+pub async fn set_play_settings(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<PlaySettings>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::set_record_plays(&conn, data.enabled, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,153 @@
+use super::watches::parse_entity_type;
+use super::Json;
+use super::{authenticate, authenticate_viewer};
+use crate::database;
+use crate::database::{DbConn, DbPool, EntityType, QualityLevel, ReadDbPool};
+use crate::error::ServerError;
+use actix_web::{delete, get, put, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Request body data for setting the quality level of an entity.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QualitySubmission {
+    pub level: QualityLevel,
+}
+
+/// Query parameters for limiting lists to entities with certain quality levels.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct QualityQuery {
+    /// A comma separated list of quality levels, e.g. "verified,needsReview".
+    pub quality: Option<String>,
+}
+
+impl QualityQuery {
+    /// Get the requested quality levels, if the list should be limited at all.
+    fn levels(&self) -> Result<Option<Vec<QualityLevel>>, ServerError> {
+        match &self.quality {
+            Some(quality) => {
+                let levels = quality
+                    .split(',')
+                    .map(|level| QualityLevel::parse(level.trim()).ok_or(ServerError::BadRequest))
+                    .collect::<Result<Vec<QualityLevel>, ServerError>>()?;
+
+                Ok(Some(levels))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Convert a list of entities to JSON and remove all entities that don't have one of the
+    /// requested quality levels.
+    pub fn filter<T: Serialize>(
+        &self,
+        conn: &DbConn,
+        entity_type: EntityType,
+        data: &T,
+    ) -> Result<Value, ServerError> {
+        let value = serde_json::to_value(data).or(Err(ServerError::Internal))?;
+
+        match (self.levels()?, value) {
+            (Some(levels), Value::Array(items)) => {
+                let ids: HashSet<String> =
+                    database::get_ids_with_quality(conn, entity_type, &levels)?
+                        .into_iter()
+                        .collect();
+
+                Ok(Value::Array(
+                    items
+                        .into_iter()
+                        .filter(|item| {
+                            item["id"]
+                                .as_str()
+                                .map(|id| ids.contains(id))
+                                .unwrap_or(false)
+                        })
+                        .collect(),
+                ))
+            }
+            (_, value) => Ok(value),
+        }
+    }
+
+    /// Get the key for caching a list at the provided path limited by this query.
+    pub fn key(&self, path: &str) -> String {
+        match &self.quality {
+            Some(quality) => format!("{}?quality={}", path, quality),
+            None => path.to_string(),
+        }
+    }
+}
+
+/// Get the quality level of an entity, e.g. "/works/{id}/quality". This responds with 404, if no
+/// level was set yet.
+#[get("/{entity_type}/{id}/quality")]
+pub async fn get_quality(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+
+        database::get_quality(&conn, entity_type, &id, viewer.as_ref())?
+            .ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Set the quality level of an entity. The user must be an editor.
+#[put("/{entity_type}/{id}/quality")]
+pub async fn set_quality(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    data: Json<QualitySubmission>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), entity_type.write_scope())?;
+
+        database::set_quality(&conn, entity_type, &id, data.level, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Remove the quality level of an entity. The user must be an editor.
+#[delete("/{entity_type}/{id}/quality")]
+pub async fn delete_quality(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), entity_type.write_scope())?;
+
+        database::delete_quality(&conn, entity_type, &id, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,143 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, ReadDbPool, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for rating a recording.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingSubmission {
+    /// The number of stars from 1 to 5.
+    pub stars: i32,
+
+    pub review: Option<String>,
+}
+
+/// Rate a recording or change the existing rating of the current user.
+#[post("/recordings/{id}/rating")]
+pub async fn rate_recording(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+    data: Json<RatingSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::set_rating(
+            &conn,
+            &id.into_inner(),
+            data.stars,
+            data.review.as_deref(),
+            &user,
+        )?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Get the rating of a recording by the current user.
+#[get("/recordings/{id}/rating")]
+pub async fn get_rating(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::get_rating(&conn, &id.into_inner(), &user.username)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Remove the rating of a recording by the current user.
+#[delete("/recordings/{id}/rating")]
+pub async fn delete_rating(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::delete_rating(&conn, &id.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Get all reviews of a recording that weren't hidden.
+#[get("/recordings/{id}/reviews")]
+pub async fn get_reviews(
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        Ok(database::get_reviews(&conn, &id.into_inner())?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Hide an abusive review of another user. The user must be an editor.
+#[post("/recordings/{id}/reviews/{username}/hide")]
+pub async fn hide_review(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        let (id, username) = path.into_inner();
+        database::set_review_hidden(&conn, &id, &username, true, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Show a previously hidden review again. The user must be an editor.
+#[delete("/recordings/{id}/reviews/{username}/hide")]
+pub async fn unhide_review(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        let (id, username) = path.into_inner();
+        database::set_review_hidden(&conn, &id, &username, false, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
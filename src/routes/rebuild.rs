@@ -0,0 +1,78 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, Scope, User};
+use crate::error::ServerError;
+use crate::rebuild::{RebuildTarget, Rebuilds};
+use actix_web::http::header;
+use actix_web::{get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for rebuilding derived data.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuildRequest {
+    /// What to rebuild, e.g. "searchIndex", "readModels", "statistics" or "names".
+    pub targets: Vec<RebuildTarget>,
+}
+
+/// Check that the user is an administrator and return them.
+async fn authenticate_admin(db: web::Data<DbPool>, auth: BearerAuth) -> Result<User, ServerError> {
+    let user = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        Ok(user)
+    })
+    .await?;
+
+    Ok(user)
+}
+
+/// Start rebuilding derived data in the background. The response contains the new job, whose
+/// progress can be followed at the provided location. Only one job may run at a time. The user
+/// must be an administrator.
+#[post("/admin/rebuild")]
+pub async fn start_rebuild(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    rebuilds: web::Data<Rebuilds>,
+    data: Json<RebuildRequest>,
+) -> Result<HttpResponse, ServerError> {
+    let user = authenticate_admin(db, auth).await?;
+    let job = rebuilds.start(data.into_inner().targets, user)?;
+
+    Ok(HttpResponse::Accepted()
+        .header(header::LOCATION, format!("/admin/rebuild/{}", job.id))
+        .json(job))
+}
+
+/// Get the recent rebuild jobs, the most recent one first. The user must be an administrator.
+#[get("/admin/rebuild")]
+pub async fn get_rebuilds(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    rebuilds: web::Data<Rebuilds>,
+) -> Result<HttpResponse, ServerError> {
+    authenticate_admin(db, auth).await?;
+    Ok(HttpResponse::Ok().json(rebuilds.get_jobs()))
+}
+
+/// Get the progress of a rebuild job. The user must be an administrator.
+#[get("/admin/rebuild/{id}")]
+pub async fn get_rebuild(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    rebuilds: web::Data<Rebuilds>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    authenticate_admin(db, auth).await?;
+
+    let job = rebuilds.get_job(&id).ok_or(ServerError::NotFound)?;
+    Ok(HttpResponse::Ok().json(job))
+}
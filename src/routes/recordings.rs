@@ -1,23 +1,67 @@
-use super::authenticate;
+use super::check_redirect;
+use super::viewer_key;
+use super::Json;
+use super::{assign_id, updated_response, CreateQuery, DeleteQuery, FieldsQuery, QualityQuery};
+use super::{authenticate, authenticate_viewer, authorize_nested, check_visible, get_viewer};
+use crate::cache::{cached, ResponseCache};
 use crate::database;
-use crate::database::{DbPool, Recording};
+use crate::database::{DbPool, EntityType, ReadDbPool, Recording, Scope};
 use crate::error::ServerError;
+use crate::validation::Validate;
 use actix_web::{delete, get, post, web, HttpResponse};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Query parameters for telling complete recordings of a work apart from excerpts.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ExcerptQuery {
+    /// If this is set, only excerpts or only complete recordings are included.
+    pub excerpt: Option<bool>,
+}
+
+impl ExcerptQuery {
+    /// Remove all recordings from a list that don't match the requested kind. Recordings that
+    /// contain the work as an additional work always count as complete recordings of it.
+    pub fn apply(&self, data: &Value, work_id: &str) -> Value {
+        match (self.excerpt, data) {
+            (Some(excerpt), Value::Array(items)) => Value::Array(
+                items
+                    .iter()
+                    .filter(|item| {
+                        let is_excerpt = item["work"]["id"].as_str() == Some(work_id)
+                            && !item["parts"].is_null();
+
+                        is_excerpt == excerpt
+                    })
+                    .cloned()
+                    .collect(),
+            ),
+            _ => data.clone(),
+        }
+    }
+}
 
 /// Get an existing recording.
 #[get("/recordings/{id}")]
 pub async fn get_recording(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        database::get_recording(&conn, &id.into_inner())?.ok_or(ServerError::NotFound)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_redirect(&conn, EntityType::Recording, &id, viewer.as_ref())?;
+        check_visible(&conn, EntityType::Recording, &id, viewer.as_ref())?;
+        database::get_recording(&conn, &id)?.ok_or(ServerError::NotFound)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
 }
 
 /// Add a new recording or update an existin one. The user must be authorized to do that.
@@ -25,74 +69,187 @@ pub async fn get_recording(
 pub async fn update_recording(
     auth: BearerAuth,
     db: web::Data<DbPool>,
-    data: web::Json<Recording>,
+    data: Json<Recording>,
+    create: web::Query<CreateQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    let mut data = data.into_inner();
+    let generated = assign_id(&mut data.id);
+    data.validate()?;
+
+    let id = data.id.clone();
+
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteRecordings)?;
+        authorize_nested(&conn, auth.token(), &data)?;
 
-        database::update_recording(&conn, &data.into_inner(), &user)?;
+        database::with_transaction(&conn, |tx| {
+            create.check(tx, EntityType::Recording, &data.id)?;
+            database::update_recording_in(tx, &data, &user)
+        })?;
 
         Ok(())
     })
     .await?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(updated_response(EntityType::Recording, id, generated))
 }
 
+/// Get all recordings of a work. Excerpts of the work can be told apart from complete recordings by
+/// their parts and the list can be limited to one of both kinds.
 #[get("/works/{id}/recordings")]
 pub async fn get_recordings_for_work(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
     work_id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+    excerpt: web::Query<ExcerptQuery>,
+    quality: web::Query<QualityQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let viewer = get_viewer(&db, auth).await?;
+    let work_id = work_id.into_inner();
+    let key = viewer_key(
+        quality.key(&format!("/works/{}/recordings", work_id)),
+        viewer.as_ref(),
+    );
+
+    let id = work_id.clone();
+    let data = cached(&cache, key, move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_recordings_for_work(&conn, &work_id.into_inner())?)
+        let recordings = database::get_recordings_for_work(&conn, &id, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Recording, &recordings)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    Ok(HttpResponse::Ok().json(query.apply(&excerpt.apply(&data, &work_id))?))
 }
 
 #[get("/persons/{id}/recordings")]
 pub async fn get_recordings_for_person(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     person_id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_recordings_for_person(&conn, &person_id.into_inner())?)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let person_id = person_id.into_inner();
+
+        Ok(database::get_recordings_for_person(
+            &conn,
+            &person_id,
+            viewer.as_ref(),
+        )?)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
 }
 
 #[get("/ensembles/{id}/recordings")]
 pub async fn get_recordings_for_ensemble(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     ensemble_id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let ensemble_id = ensemble_id.into_inner();
+
+        Ok(database::get_recordings_for_ensemble(
+            &conn,
+            &ensemble_id,
+            viewer.as_ref(),
+        )?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(query.apply(&data)?))
+}
+
+/// Get everything that would be affected by deleting a recording using the "cascade" option.
+#[get("/recordings/{id}/delete-preview")]
+pub async fn get_recording_deletion_preview(
+    auth: Option<BearerAuth>,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_recordings_for_ensemble(&conn, &ensemble_id.into_inner())?)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Recording, &id, viewer.as_ref())?;
+        database::get_deletion_preview(&conn, EntityType::Recording, &id)?
+            .ok_or(ServerError::NotFound)
     })
     .await?;
 
     Ok(HttpResponse::Ok().json(data))
 }
 
+/// Delete an existing recording. If the "cascade" query parameter is set, everything that refers to the
+/// recording is deleted as well. Only administrators may do that.
 #[delete("/recordings/{id}")]
 pub async fn delete_recording(
     auth: BearerAuth,
     db: web::Data<DbPool>,
     id: web::Path<String>,
+    query: web::Query<DeleteQuery>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteRecordings)?;
+
+        if query.cascade {
+            database::delete_cascading(&conn, EntityType::Recording, &id, &user)?;
+        } else {
+            database::delete_recording(&conn, &id, &user)?;
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Lock an existing recording, so that only editors can change it. The user must be an editor.
+#[post("/recordings/{id}/lock")]
+pub async fn lock_recording(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteRecordings)?;
+
+        database::set_recording_locked(&conn, &id.into_inner(), true, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Unlock an existing recording. The user must be an editor.
+#[delete("/recordings/{id}/lock")]
+pub async fn unlock_recording(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteRecordings)?;
 
-        database::delete_recording(&conn, &id.into_inner(), &user)?;
+        database::set_recording_locked(&conn, &id.into_inner(), false, &user)?;
 
         Ok(())
     })
@@ -0,0 +1,159 @@
+use super::Json;
+use super::{authenticate, authenticate_login};
+use crate::database;
+use crate::database::{DbPool, EntityType, ReportKind, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// Request body data for reporting an entity.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportSubmission {
+    pub entity_type: EntityType,
+    pub entity_id: String,
+    pub kind: ReportKind,
+    pub reason: String,
+}
+
+/// Response body data for a newly created report.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportCreated {
+    pub id: String,
+}
+
+/// Query parameters for listing reports.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReportsQuery {
+    /// Only list resolved or unresolved reports.
+    pub resolved: Option<bool>,
+}
+
+/// Request body data for commenting on a report.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportCommentSubmission {
+    pub text: String,
+}
+
+/// Request body data for resolving a report.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportResolution {
+    pub resolution: String,
+}
+
+/// Report a problem with an entity. Every logged in user may do that.
+#[post("/reports")]
+pub async fn create_report(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<ReportSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let (user, _) =
+            authenticate_login(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+
+        let id = database::insert_report(
+            &conn,
+            data.entity_type,
+            &data.entity_id,
+            data.kind,
+            &data.reason,
+            &user,
+        )?;
+
+        Ok(ReportCreated { id })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get all reports. The user must be an administrator.
+#[get("/reports")]
+pub async fn get_reports(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    query: web::Query<ReportsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        Ok(database::get_reports(&conn, query.resolved, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get an existing report including all comments. The user must be an administrator.
+#[get("/reports/{id}")]
+pub async fn get_report(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        database::get_report(&conn, &id.into_inner(), &user)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Comment on a report. The user must be an administrator.
+#[post("/reports/{id}/comments")]
+pub async fn comment_report(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+    data: Json<ReportCommentSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        database::insert_report_comment(&conn, &id.into_inner(), &data.text, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Mark a report as resolved. The user must be an administrator.
+#[post("/reports/{id}/resolve")]
+pub async fn resolve_report(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+    data: Json<ReportResolution>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        database::resolve_report(&conn, &id.into_inner(), &data.resolution, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,49 @@
+use super::authenticate;
+use crate::database;
+use crate::database::DbPool;
+use crate::error::ServerError;
+use actix_web::{post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for reverting a change.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RevertQuery {
+    /// Revert the change even if the entity was changed again afterwards.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// The result of reverting a change.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RevertResult {
+    /// The revision of the new change that restored the previous version.
+    pub revision: i64,
+}
+
+/// Undo a change by restoring the version of the entity from before it. This responds with "409
+/// Conflict", if there are later changes to the entity, unless the "force" query parameter is
+/// set. The user must be an editor.
+#[post("/revisions/{id}/revert")]
+pub async fn revert_revision(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<i64>,
+    query: web::Query<RevertQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let id = id.into_inner();
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let event = database::get_event(&conn, id)?.ok_or(ServerError::NotFound)?;
+        let user = authenticate(&conn, auth.token(), event.entity_type.write_scope())?;
+
+        let revision = database::revert_revision(&conn, id, query.force, &user)?;
+
+        Ok(RevertResult { revision })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
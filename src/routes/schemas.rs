@@ -0,0 +1,81 @@
+use super::API_VERSION;
+use crate::database::{Ensemble, EntityType, Instrument, Label, Medium, Person, Recording, Work};
+use crate::error::ServerError;
+use actix_web::{get, web, HttpResponse};
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use serde::Serialize;
+
+/// Response body data listing the available JSON Schemas.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaIndex {
+    /// The version of the API the schemas belong to.
+    pub api_version: u32,
+
+    /// The locations of the schemas by entity type.
+    pub schemas: Vec<SchemaLocation>,
+}
+
+/// Where to find the schema of an entity type.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaLocation {
+    pub entity_type: EntityType,
+    pub location: String,
+}
+
+/// List the JSON Schemas of all entity types.
+#[get("/schemas")]
+pub async fn get_schemas() -> HttpResponse {
+    let schemas = EntityType::ALL
+        .iter()
+        .map(|entity_type| SchemaLocation {
+            entity_type: *entity_type,
+            location: schema_location(*entity_type),
+        })
+        .collect();
+
+    HttpResponse::Ok().json(SchemaIndex {
+        api_version: API_VERSION,
+        schemas,
+    })
+}
+
+/// Get the JSON Schema for the data of an entity type as it is sent and received by the API,
+/// e.g. "/schemas/person.json". The schemas are derived from the same types that the server uses,
+/// so they always match the current API version.
+#[get("/schemas/{entity_type}.json")]
+pub async fn get_schema(entity_type: web::Path<String>) -> Result<HttpResponse, ServerError> {
+    let entity_type = EntityType::parse(&entity_type).ok_or(ServerError::NotFound)?;
+
+    let mut schema = match entity_type {
+        EntityType::Person => schema_for!(Person),
+        EntityType::Ensemble => schema_for!(Ensemble),
+        EntityType::Instrument => schema_for!(Instrument),
+        EntityType::Work => schema_for!(Work),
+        EntityType::Recording => schema_for!(Recording),
+        EntityType::Medium => schema_for!(Medium),
+        EntityType::Label => schema_for!(Label),
+    };
+
+    add_metadata(&mut schema, entity_type);
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/schema+json")
+        .json(schema))
+}
+
+/// Identify the schema by its location and the API version.
+fn add_metadata(schema: &mut RootSchema, entity_type: EntityType) {
+    schema.schema.metadata().id = Some(schema_location(entity_type));
+    schema
+        .schema
+        .extensions
+        .insert("x-api-version".to_string(), API_VERSION.into());
+}
+
+/// Get the location of the schema of an entity type.
+fn schema_location(entity_type: EntityType) -> String {
+    format!("/schemas/{}.json", entity_type.as_str())
+}
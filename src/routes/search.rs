@@ -0,0 +1,108 @@
+use crate::database;
+use crate::database::{DbConn, ReadDbPool, Recording, Work};
+use crate::error::ServerError;
+use crate::search::SearchIndex;
+use actix_web::{get, web, HttpResponse};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// The default number of results of each type.
+const DEFAULT_LIMIT: i64 = 20;
+
+/// The maximum number of results of each type.
+const MAX_LIMIT: i64 = 100;
+
+/// Query parameters for searching.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SearchQuery {
+    /// The text to search for.
+    pub q: String,
+
+    /// The maximum number of results of each type.
+    pub limit: Option<i64>,
+
+    /// If this is set, only works and recordings of works from this musical period are included.
+    pub period: Option<String>,
+}
+
+/// Works and recordings matching a search query.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    pub works: Vec<Work>,
+    pub recordings: Vec<Recording>,
+}
+
+/// Search for works and recordings. This uses the search index, if one is configured, and falls
+/// back to searching the database otherwise. Filtering by period always uses the database.
+#[get("/search")]
+pub async fn get_search_results(
+    db: web::Data<ReadDbPool>,
+    index: web::Data<Option<SearchIndex>>,
+    query: web::Query<SearchQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ServerError::BadRequest);
+    }
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+
+        let period = query.period.as_deref();
+
+        let results = match (index.get_ref(), period) {
+            (Some(index), None) => match search_index(&conn, index, &query.q, limit) {
+                Ok(results) => results,
+                Err(error) => {
+                    println!("{:?}", error);
+                    search_database(&conn, &query.q, None, limit)?
+                }
+            },
+            _ => search_database(&conn, &query.q, period, limit)?,
+        };
+
+        Ok(results)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Search using the search index and get the matching entities from the database.
+fn search_index(
+    conn: &DbConn,
+    index: &SearchIndex,
+    query: &str,
+    limit: i64,
+) -> Result<SearchResults> {
+    let mut works = Vec::new();
+    for id in index.search_works(query, limit)? {
+        // The index may not have caught up with works that became private.
+        if let Some(work) = database::get_work(conn, &id)?.filter(|work| !work.private) {
+            works.push(work);
+        }
+    }
+
+    let mut recordings = Vec::new();
+    for id in index.search_recordings(query, limit)? {
+        if let Some(recording) = database::get_recording(conn, &id)?.filter(|r| !r.private) {
+            recordings.push(recording);
+        }
+    }
+
+    Ok(SearchResults { works, recordings })
+}
+
+/// Search within the database, optionally limited to one period.
+fn search_database(
+    conn: &DbConn,
+    query: &str,
+    period: Option<&str>,
+    limit: i64,
+) -> Result<SearchResults> {
+    Ok(SearchResults {
+        works: database::search_works(conn, query, period, limit)?,
+        recordings: database::search_recordings(conn, query, period, limit)?,
+    })
+}
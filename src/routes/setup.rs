@@ -0,0 +1,46 @@
+use super::{Json, PasswordParams};
+use crate::bootstrap::{create_first_admin, AdminCreation, SetupToken};
+use crate::database::{self, DbPool};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{post, web, HttpResponse};
+use serde::Deserialize;
+
+/// Request body data for creating the first administrator of a new server.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminSetup {
+    /// The one-time setup token that was logged on startup.
+    pub token: String,
+
+    pub username: String,
+    pub password: String,
+}
+
+/// Create the first administrator using the setup token. This only works as long as there are no
+/// users at all.
+#[post("/setup")]
+pub async fn setup_admin(
+    db: web::Data<DbPool>,
+    setup_token: web::Data<SetupToken>,
+    password_params: web::Data<PasswordParams>,
+    data: Json<AdminSetup>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+
+        setup_token.redeem(&data.token, || {
+            let admin = AdminCreation {
+                username: data.username.clone(),
+                password: data.password.clone(),
+            };
+
+            Ok(create_first_admin(&conn, &admin, &password_params)?)
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,106 @@
+use super::watches::parse_entity_type;
+use super::Json;
+use super::{authenticate, authenticate_viewer};
+use crate::database;
+use crate::database::{DbPool, ReadDbPool};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for adding a source to an entity.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceSubmission {
+    /// What the data is based on, e.g. "Liner notes" or "Data derived from MusicBrainz".
+    pub description: String,
+
+    #[serde(default)]
+    pub license: Option<String>,
+
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// Get where the data of an entity comes from, e.g. "/works/{id}/sources".
+#[get("/{entity_type}/{id}/sources")]
+pub async fn get_sources(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+
+        Ok(database::get_sources(
+            &conn,
+            entity_type,
+            &id,
+            viewer.as_ref(),
+        )?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Add a source to an entity. The user must be allowed to edit the entity. This returns the new
+/// source including the revision it belongs to.
+#[post("/{entity_type}/{id}/sources")]
+pub async fn create_source(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    data: Json<SourceSubmission>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), entity_type.write_scope())?;
+
+        Ok(database::insert_source(
+            &conn,
+            entity_type,
+            &id,
+            &data.description,
+            data.license.as_deref(),
+            data.url.as_deref(),
+            &user,
+        )?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Remove a source from an entity. Only its author and editors may do that.
+#[delete("/{entity_type}/{id}/sources/{source_id}")]
+pub async fn delete_source(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id, source_id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), entity_type.write_scope())?;
+
+        database::delete_source(&conn, entity_type, &id, &source_id, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,66 @@
+use crate::cache::{cached, ResponseCache};
+use crate::database;
+use crate::database::{ReadDbPool, Statistics};
+use crate::error::ServerError;
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+use std::sync::RwLock;
+
+/// The most recently computed statistics. They are updated by a scheduled task.
+pub type StatisticsCache = RwLock<Option<Statistics>>;
+
+/// Get the number of stored entities of each type. This uses the precomputed statistics, if they
+/// are available.
+#[get("/statistics")]
+pub async fn get_statistics(
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<StatisticsCache>,
+) -> Result<HttpResponse, ServerError> {
+    let cached = cache.read().unwrap().clone();
+
+    let data = match cached {
+        Some(data) => data,
+        None => {
+            let data = database::block(move || {
+                let conn = db.into_inner().get()?;
+                Ok(database::compute_statistics(&conn)?)
+            })
+            .await?;
+
+            *cache.write().unwrap() = Some(data.clone());
+            data
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Query parameters for completeness metrics.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CompletenessQuery {
+    /// Only count entities related to the works of this composer.
+    pub composer: Option<String>,
+}
+
+/// Get how many public works, recordings and mediums lack information like instrumentations,
+/// performances or DiscIDs.
+#[get("/statistics/completeness")]
+pub async fn get_completeness(
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+    query: web::Query<CompletenessQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let composer = query.into_inner().composer;
+    let key = format!(
+        "/statistics/completeness?composer={}",
+        composer.as_deref().unwrap_or_default()
+    );
+
+    let data = cached(&cache, key, move || {
+        let conn = db.into_inner().get()?;
+        Ok(database::compute_completeness(&conn, composer.as_deref())?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(&*data))
+}
@@ -0,0 +1,275 @@
+use super::Json;
+use super::{authenticate, authenticate_viewer};
+use crate::database;
+use crate::database::{generate_id, DbPool, EntityType, Medium, Scope};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::http::header;
+use actix_web::{post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// The number of CD sectors per second.
+const SECTORS_PER_SECOND: u64 = 75;
+
+/// The largest possible offset of the lead-out, which is at 99:59:74.
+const MAX_LEADOUT: u32 = 449_999;
+
+/// Request body data for a CD table of contents. All offsets are given in sectors (1/75 s) and
+/// include the 150 sectors lead-in, like they are reported by the drive.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Toc {
+    /// The number of the first audio track, usually 1.
+    pub first_track: u8,
+
+    /// The number of the last audio track.
+    pub last_track: u8,
+
+    /// The offset of the lead-out.
+    pub leadout: u32,
+
+    /// The offsets of all tracks from the first to the last one.
+    pub offsets: Vec<u32>,
+}
+
+/// Response body data for a TOC lookup.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TocLookup {
+    /// The MusicBrainz DiscID computed from the TOC.
+    pub discid: String,
+
+    /// All mediums that are known to have this DiscID.
+    pub mediums: Vec<Medium>,
+}
+
+/// Request body data for a CD that was ripped, as reported by the ripping software.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RipReport {
+    pub toc: Toc,
+
+    /// The durations of the tracks in milliseconds as measured while ripping. If they are left
+    /// out, they are computed from the TOC.
+    #[serde(default)]
+    pub durations: Option<Vec<i32>>,
+
+    /// The CD-Text of the disc, if there is any.
+    #[serde(default)]
+    pub cd_text: Option<CdText>,
+}
+
+/// The CD-Text of a disc.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CdText {
+    /// The title of the album.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// The performer of the whole album. This is used for tracks without their own performer.
+    #[serde(default)]
+    pub performer: Option<String>,
+
+    /// The CD-Text of the tracks from the first to the last one.
+    #[serde(default)]
+    pub tracks: Vec<CdTextTrack>,
+}
+
+/// The CD-Text of a single track.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CdTextTrack {
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub performer: Option<String>,
+}
+
+impl Toc {
+    /// Check whether the TOC is plausible. The first track has to be between 1 and 99, there has
+    /// to be an offset for each track, the offsets have to be increasing and the lead-out has to
+    /// come after the last track, but not after the end of the longest possible CD.
+    pub fn validate(&self) -> Result<()> {
+        if self.first_track < 1 || self.last_track > 99 || self.first_track > self.last_track {
+            return Err(anyhow!("Invalid track numbers!"));
+        }
+
+        if self.leadout > MAX_LEADOUT {
+            return Err(anyhow!("The lead-out is too late!"));
+        }
+
+        let count = (self.last_track - self.first_track + 1) as usize;
+        if self.offsets.len() != count {
+            return Err(anyhow!(
+                "Expected {} offsets, got {}!",
+                count,
+                self.offsets.len()
+            ));
+        }
+
+        let mut previous = 0;
+        for offset in self.offsets.iter().chain(std::iter::once(&self.leadout)) {
+            if *offset <= previous {
+                return Err(anyhow!("Offsets are not increasing!"));
+            }
+
+            previous = *offset;
+        }
+
+        Ok(())
+    }
+
+    /// Compute the MusicBrainz DiscID for this TOC. This is the SHA-1 hash of the hexadecimal
+    /// representation of the track numbers and all 100 offsets (lead-out first and missing tracks
+    /// as zero), encoded using a variant of Base64 that is safe for URLs.
+    pub fn discid(&self) -> String {
+        let mut input = format!(
+            "{:02X}{:02X}{:08X}",
+            self.first_track, self.last_track, self.leadout
+        );
+
+        // The offset of track n goes into slot n, so tracks before the first one are left empty.
+        for track in 1..=99u8 {
+            let offset = track
+                .checked_sub(self.first_track)
+                .and_then(|index| self.offsets.get(index as usize))
+                .cloned()
+                .unwrap_or(0);
+
+            input.push_str(&format!("{:08X}", offset));
+        }
+
+        let hash = sha1::Sha1::from(input.as_bytes()).digest().bytes();
+
+        base64::encode(hash)
+            .replace('+', ".")
+            .replace('/', "_")
+            .replace('=', "-")
+    }
+
+    /// Get the durations of the tracks in milliseconds computed from their offsets.
+    pub fn durations(&self) -> Vec<i32> {
+        self.offsets
+            .iter()
+            .zip(
+                self.offsets
+                    .iter()
+                    .skip(1)
+                    .chain(std::iter::once(&self.leadout)),
+            )
+            .map(|(start, end)| (u64::from(end - start) * 1000 / SECTORS_PER_SECOND) as i32)
+            .collect()
+    }
+}
+
+impl RipReport {
+    /// Create the data of a medium draft for the ripped CD. Each track gets its own placeholder
+    /// recording without a work. The CD-Text, if any, is kept within the name of the medium and
+    /// the comments of the recordings, so that the user can fill in the actual works and
+    /// performers later on.
+    fn to_draft_data(&self, id: &str) -> Value {
+        let durations = self
+            .durations
+            .clone()
+            .unwrap_or_else(|| self.toc.durations());
+
+        let cd_text = self.cd_text.as_ref();
+
+        let track_sets: Vec<Value> = durations
+            .iter()
+            .enumerate()
+            .map(|(index, duration)| {
+                let text = cd_text.and_then(|cd_text| cd_text.tracks.get(index));
+                let performer = text
+                    .and_then(|text| text.performer.as_deref())
+                    .or_else(|| cd_text.and_then(|cd_text| cd_text.performer.as_deref()));
+
+                let comment = [text.and_then(|text| text.title.as_deref()), performer]
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<&str>>()
+                    .join(" – ");
+
+                json!({
+                    "recording": {
+                        "id": generate_id(),
+                        "comment": comment,
+                        "performances": [],
+                    },
+                    "tracks": [{
+                        "workParts": [],
+                        "duration": duration,
+                    }],
+                })
+            })
+            .collect();
+
+        let name = cd_text
+            .and_then(|cd_text| cd_text.title.clone())
+            .unwrap_or_default();
+
+        json!({
+            "id": id,
+            "name": name,
+            "discid": self.toc.discid(),
+            "tracks": track_sets,
+        })
+    }
+}
+
+/// Compute the DiscID for a table of contents and look up mediums that match it.
+#[post("/toc/lookup")]
+pub async fn lookup_toc(
+    auth: Option<BearerAuth>,
+    db: web::Data<DbPool>,
+    data: Json<Toc>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate().or(Err(ServerError::BadRequest))?;
+    let discid = data.discid();
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let mediums = database::get_mediums_by_discid(&conn, &discid, viewer.as_ref())?;
+
+        Ok(TocLookup { discid, mediums })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Start adding a CD that was just ripped. This creates a draft of a medium with the DiscID, the
+/// durations of the tracks and placeholder recordings for each track that can be completed and
+/// published later. The response contains the new draft.
+#[post("/mediums/from-rip")]
+pub async fn create_medium_from_rip(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<RipReport>,
+) -> Result<HttpResponse, ServerError> {
+    data.toc.validate().or(Err(ServerError::BadRequest))?;
+    data.validate()?;
+
+    let id = generate_id();
+    let draft_data = data.to_draft_data(&id);
+
+    let draft = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteMediums)?;
+
+        database::update_draft(&conn, EntityType::Medium, &id, &draft_data, &user)?;
+        database::get_draft(&conn, EntityType::Medium, &id, &user)?.ok_or(ServerError::Internal)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created()
+        .header(header::LOCATION, format!("/drafts/mediums/{}", draft.id))
+        .json(draft))
+}
@@ -0,0 +1,114 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use actix_web::{get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of days that can be used for purging the trash.
+const MAX_PURGE_DAYS: i64 = 36500;
+
+/// Request body data for restoring items from the trash.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashRestoration {
+    /// The IDs of the trash items to restore.
+    pub ids: Vec<i64>,
+}
+
+/// Query parameters for purging the trash.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PurgeQuery {
+    /// Remove items that were deleted more than this number of days ago. If this is not set, the
+    /// configured retention period is used.
+    pub days: Option<i64>,
+}
+
+/// The number of items that were restored or purged.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashResult {
+    pub count: usize,
+}
+
+/// List all deleted entities across all types. The user must be an administrator.
+#[get("/admin/trash")]
+pub async fn get_trash(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        Ok(database::get_trash(&conn)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Restore deleted entities. Either all of them are restored or none. The user must be an
+/// administrator.
+#[post("/admin/trash/restore")]
+pub async fn restore_trash(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<TrashRestoration>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        let count = database::restore_from_trash(&conn, &data.ids, &user)?;
+
+        Ok(TrashResult { count })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Permanently remove old items from the trash. The user must be an administrator.
+#[post("/admin/trash/purge")]
+pub async fn purge_trash(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    query: web::Query<PurgeQuery>,
+) -> Result<HttpResponse, ServerError> {
+    if matches!(query.days, Some(days) if !(0..=MAX_PURGE_DAYS).contains(&days)) {
+        return Err(ServerError::BadRequest);
+    }
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        let count = match query.days {
+            Some(days) => {
+                database::purge_trash(&conn, Utc::now().naive_utc() - Duration::days(days))?
+            }
+            None => database::purge_expired_trash(&conn)?,
+        };
+
+        Ok(TrashResult { count })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
@@ -0,0 +1,91 @@
+use super::authenticate;
+use crate::database;
+use crate::database::{DbConn, EntityType, ReadDbPool, Scope, User};
+use crate::error::ServerError;
+use actix_web::web;
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+
+/// Authenticate the user of a public request, if it carries a token. Responses to authenticated
+/// requests include the private entities of the user.
+pub fn authenticate_viewer(
+    conn: &DbConn,
+    auth: Option<&BearerAuth>,
+) -> Result<Option<User>, ServerError> {
+    match auth {
+        Some(auth) => {
+            let user = authenticate(conn, auth.token(), Scope::Read)?;
+
+            Ok(Some(user))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Authenticate the user of a public request like [`authenticate_viewer`], but within its own
+/// blocking call. This is needed before looking up cached responses.
+pub async fn get_viewer(
+    db: &web::Data<ReadDbPool>,
+    auth: Option<BearerAuth>,
+) -> Result<Option<User>, ServerError> {
+    match auth {
+        Some(auth) => {
+            let db = db.clone();
+
+            let viewer = database::block(move || {
+                let conn = db.into_inner().get()?;
+                authenticate_viewer(&conn, Some(&auth))
+            })
+            .await?;
+
+            Ok(viewer)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Get the key for caching a response that includes the private entities of the viewer.
+pub fn viewer_key(path: String, viewer: Option<&User>) -> String {
+    match viewer {
+        Some(user) => format!("{}#{}", path, user.username),
+        None => path,
+    }
+}
+
+/// Fail with "not found", if an entity doesn't exist or the viewer isn't allowed to see it.
+pub fn check_visible(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    viewer: Option<&User>,
+) -> Result<(), ServerError> {
+    if database::is_entity_visible(conn, entity_type, id, viewer)? {
+        Ok(())
+    } else {
+        Err(ServerError::NotFound)
+    }
+}
+
+/// Fail with "308 Permanent Redirect" to the entity that replaced a merged one, if an entity with
+/// the ID doesn't exist anymore. Entities that the viewer may not see are not redirected to.
+pub fn check_redirect(
+    conn: &DbConn,
+    entity_type: EntityType,
+    id: &str,
+    viewer: Option<&User>,
+) -> Result<(), ServerError> {
+    if database::entity_exists(conn, entity_type, id)? {
+        return Ok(());
+    }
+
+    match database::get_redirect(conn, entity_type, id)? {
+        Some(new_id) => {
+            check_visible(conn, entity_type, &new_id, viewer)?;
+            Err(ServerError::Moved(format!(
+                "/{}/{}",
+                entity_type.path(),
+                new_id
+            )))
+        }
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,88 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, EntityType, Scope};
+use crate::error::ServerError;
+use actix_web::{delete, get, put, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for watching an entity.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchSettings {
+    /// Whether notifications on changes should also be sent by mail.
+    #[serde(default)]
+    pub email: bool,
+}
+
+/// Get the entity type from a path segment like "works".
+pub(super) fn parse_entity_type(path: &str) -> Result<EntityType, ServerError> {
+    EntityType::from_path(path).ok_or(ServerError::NotFound)
+}
+
+/// Watch an entity, e.g. "/watch/works/{id}". The current user will be notified when somebody
+/// else changes it. The request body is optional.
+#[put("/watch/{entity_type}/{id}")]
+pub async fn put_watch(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    data: Option<Json<WatchSettings>>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+    let settings = data.map(|data| data.into_inner()).unwrap_or_default();
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::set_watch(&conn, entity_type, &id, settings.email, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Stop watching an entity.
+#[delete("/watch/{entity_type}/{id}")]
+pub async fn delete_watch(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = parse_entity_type(&entity_type)?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        database::delete_watch(&conn, entity_type, &id, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Get all entities the current user is watching.
+#[get("/account/watchlist")]
+pub async fn get_watchlist(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(database::get_watches(&conn, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
@@ -0,0 +1,96 @@
+use super::authenticate;
+use super::Json;
+use crate::database;
+use crate::database::{DbPool, EntityType, EventKind, Scope, Webhook};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::{Deserialize, Serialize};
+
+/// Request body data for registering a webhook.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookCreation {
+    /// The URL that will receive the events.
+    pub url: String,
+
+    /// Only notify about changes to these entity types. All types are included, if this is empty.
+    #[serde(default)]
+    pub entity_types: Vec<EntityType>,
+
+    /// Only notify about these kinds of changes. All kinds are included, if this is empty.
+    #[serde(default)]
+    pub kinds: Vec<EventKind>,
+}
+
+/// Response body data for a newly registered webhook.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+
+    /// The secret used for signing the payloads. It is only available within this response.
+    pub secret: String,
+}
+
+/// Register a new webhook. The user must be an administrator.
+#[post("/webhooks")]
+pub async fn create_webhook(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    data: Json<WebhookCreation>,
+) -> Result<HttpResponse, ServerError> {
+    data.validate()?;
+
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        let (webhook, secret) =
+            database::insert_webhook(&conn, &data.url, &data.entity_types, &data.kinds, &user)?;
+
+        Ok(CreatedWebhook { webhook, secret })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Get all registered webhooks. The user must be an administrator.
+#[get("/webhooks")]
+pub async fn get_webhooks(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        Ok(database::get_webhooks(&conn, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Delete a webhook. The user must be an administrator.
+#[delete("/webhooks/{id}")]
+pub async fn delete_webhook(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        database::delete_webhook(&conn, &id.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
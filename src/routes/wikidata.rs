@@ -0,0 +1,105 @@
+use super::authenticate;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use crate::wikidata;
+use actix_web::{delete, get, post, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Query parameters for searching persons on Wikidata.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReconcileQuery {
+    /// The maximum number of persons to search.
+    pub limit: Option<i64>,
+}
+
+/// Get all Wikidata items that might describe persons and are waiting for confirmation. The
+/// user must be an editor.
+#[get("/wikidata/candidates")]
+pub async fn get_wikidata_candidates(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Read)?;
+
+        Ok(database::get_wikidata_candidates(&conn, &user)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Link a person to the Wikidata item of a candidate. The user must be an editor.
+#[post("/wikidata/candidates/{id}/confirm")]
+pub async fn confirm_wikidata_candidate(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePersons)?;
+
+        database::confirm_wikidata_candidate(&conn, id.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Discard a candidate that describes somebody else. The user must be an editor.
+#[delete("/wikidata/candidates/{id}")]
+pub async fn reject_wikidata_candidate(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WritePersons)?;
+
+        database::reject_wikidata_candidate(&conn, id.into_inner(), &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Search persons without external IDs on Wikidata now instead of waiting for the scheduled
+/// task. This returns how many persons were searched and how many candidates were found. The
+/// user must be an administrator.
+#[post("/admin/wikidata/reconcile")]
+pub async fn reconcile_wikidata(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    query: web::Query<ReconcileQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let limit = query.limit.unwrap_or(wikidata::BATCH_SIZE);
+    if !(1..=wikidata::BATCH_SIZE).contains(&limit) {
+        return Err(ServerError::BadRequest);
+    }
+
+    let data = database::block(move || {
+        let pool = db.into_inner();
+        let conn = pool.get()?;
+        let user = authenticate(&conn, auth.token(), Scope::Admin)?;
+
+        if !user.may_administrate() {
+            return Err(ServerError::Forbidden);
+        }
+
+        drop(conn);
+
+        Ok(wikidata::reconcile(&pool, limit)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
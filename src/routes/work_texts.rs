@@ -0,0 +1,60 @@
+use super::{authenticate, authenticate_viewer, check_visible, read_json, WORK_TEXTS_JSON_LIMIT};
+use crate::database;
+use crate::database::{DbPool, EntityType, ReadDbPool, Scope, WorkText};
+use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::{get, put, web, HttpRequest, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use serde::Deserialize;
+
+/// Request body data for replacing the texts of a work.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkTextsSubmission {
+    pub texts: Vec<WorkText>,
+}
+
+/// Get the sung texts of a work together with their translations.
+#[get("/works/{id}/texts")]
+pub async fn get_work_texts(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Work, &id, viewer.as_ref())?;
+        Ok(database::get_work_texts(&conn, &id)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// Replace the texts of a work. The user must be allowed to edit the work.
+#[put("/works/{id}/texts")]
+pub async fn update_work_texts(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, ServerError> {
+    let data: WorkTextsSubmission = read_json(&req, payload, WORK_TEXTS_JSON_LIMIT).await?;
+    data.validate()?;
+
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteWorks)?;
+
+        database::update_work_texts(&conn, &id.into_inner(), &data.texts, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
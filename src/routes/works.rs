@@ -1,70 +1,299 @@
-use super::authenticate;
+use super::check_redirect;
+use super::Json;
+use super::{assign_id, updated_response, CreateQuery, PeriodQuery, QualityQuery, WORK_COLUMNS};
+use super::{authenticate, authenticate_viewer, authorize_nested, check_visible, get_viewer};
+use super::{csv_response, DeleteQuery, DuplicateQuery, Duplicates, FieldsQuery, ListFormat};
+use super::{viewer_key, Languages};
+use crate::cache::{cached, ResponseCache};
 use crate::database;
-use crate::database::{DbPool, Work};
+use crate::database::{DbPool, EntityType, ReadDbPool, Scope, Work};
 use crate::error::ServerError;
+use crate::validation::Validate;
+use actix_web::http::header;
 use actix_web::{delete, get, post, web, HttpResponse};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 
 /// Get an existing work.
 #[get("/works/{id}")]
 pub async fn get_work(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
     id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+    languages: Languages,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let data = database::block(move || {
         let conn = db.into_inner().get()?;
-        database::get_work(&conn, &id.into_inner())?.ok_or(ServerError::NotFound)
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_redirect(&conn, EntityType::Work, &id, viewer.as_ref())?;
+        check_visible(&conn, EntityType::Work, &id, viewer.as_ref())?;
+        database::get_work(&conn, &id)?.ok_or(ServerError::NotFound)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().json(data))
+    let data = serde_json::to_value(&data).or(Err(ServerError::Internal))?;
+    let data = languages.localize(&data);
+
+    Ok(HttpResponse::Ok()
+        .header(header::VARY, "Accept-Language")
+        .json(query.apply(&data)?))
 }
 
-/// Add a new work or update an existin one. The user must be authorized to do that.
+/// Get a work together with all of its recordings, their performers and the mediums containing
+/// them, so that a complete page on the work can be shown using a single request.
+#[get("/works/{id}/full")]
+pub async fn get_full_work(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+    id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+    languages: Languages,
+) -> Result<HttpResponse, ServerError> {
+    let viewer = get_viewer(&db, auth).await?;
+    let id = id.into_inner();
+    let key = viewer_key(format!("/works/{}/full", id), viewer.as_ref());
+
+    let data = cached(&cache, key, move || {
+        let conn = db.into_inner().get()?;
+        check_visible(&conn, EntityType::Work, &id, viewer.as_ref())?;
+        database::get_full_work(&conn, &id, viewer.as_ref())?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    let data = languages.localize(&data);
+
+    Ok(HttpResponse::Ok()
+        .header(header::VARY, "Accept-Language")
+        .json(query.apply(&data)?))
+}
+
+/// Redirect to the page of a work on IMSLP, where its scores can be found. This responds with
+/// "404 Not Found", if the page of the work is unknown.
+#[get("/works/{id}/score")]
+pub async fn get_work_score(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let work = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Work, &id, viewer.as_ref())?;
+        database::get_work(&conn, &id)?.ok_or(ServerError::NotFound)
+    })
+    .await?;
+
+    let url = work.imslp_url().ok_or(ServerError::NotFound)?;
+
+    Ok(HttpResponse::Found().header(header::LOCATION, url).finish())
+}
+
+/// Add a new work or update an existin one. The user must be authorized to do that. New
+/// works that look like duplicates of existing ones are rejected with a list of candidates,
+/// unless the "force" query parameter is set.
 #[post("/works")]
 pub async fn update_work(
     auth: BearerAuth,
     db: web::Data<DbPool>,
-    data: web::Json<Work>,
+    data: Json<Work>,
+    query: web::Query<DuplicateQuery>,
+    create: web::Query<CreateQuery>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    let mut work = data.into_inner();
+    let generated = assign_id(&mut work.id);
+    work.validate()?;
+
+    let id = work.id.clone();
+
+    let candidates = database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteWorks)?;
+        authorize_nested(&conn, auth.token(), &work)?;
 
-        database::update_work(&conn, &data.into_inner(), &user)?;
+        let candidates = database::with_transaction(&conn, |tx| {
+            let conn = tx.conn();
+            create.check(tx, EntityType::Work, &work.id)?;
 
-        Ok(())
+            if !query.force && database::get_work(conn, &work.id)?.is_none() {
+                let candidates = database::find_similar_works(conn, &work)?;
+                if !candidates.is_empty() {
+                    return Ok(Some(candidates));
+                }
+            }
+
+            database::update_work_in(tx, &work, &user)?;
+
+            Ok(None)
+        })?;
+
+        Ok(candidates)
     })
     .await?;
 
-    Ok(HttpResponse::Ok().finish())
+    match candidates {
+        Some(candidates) => Ok(HttpResponse::Conflict().json(Duplicates { candidates })),
+        None => Ok(updated_response(EntityType::Work, id, generated)),
+    }
 }
 
 #[get("/persons/{id}/works")]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_works(
-    db: web::Data<DbPool>,
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
     composer_id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+    period: web::Query<PeriodQuery>,
+    quality: web::Query<QualityQuery>,
+    format: ListFormat,
+    languages: Languages,
 ) -> Result<HttpResponse, ServerError> {
-    let data = web::block(move || {
+    let viewer = get_viewer(&db, auth).await?;
+    let composer_id = composer_id.into_inner();
+    let key = viewer_key(
+        quality.key(&format!("/persons/{}/works", composer_id)),
+        viewer.as_ref(),
+    );
+
+    let data = cached(&cache, key, move || {
         let conn = db.into_inner().get()?;
-        Ok(database::get_works(&conn, &composer_id.into_inner())?)
+        let works = database::get_works(&conn, &composer_id, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Work, &works)
+    })
+    .await?;
+
+    let data = languages.localize(&period.apply(&data));
+
+    match format {
+        ListFormat::Json => Ok(HttpResponse::Ok()
+            .header(header::VARY, "Accept-Language")
+            .json(query.apply(&data)?)),
+        ListFormat::Csv => Ok(csv_response(&data, WORK_COLUMNS)),
+    }
+}
+
+/// Get all works with texts written by a person, e.g. operas by a librettist or songs by a poet.
+#[get("/persons/{id}/authored-works")]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_works_by_author(
+    auth: Option<BearerAuth>,
+    db: web::Data<ReadDbPool>,
+    cache: web::Data<ResponseCache>,
+    author_id: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+    period: web::Query<PeriodQuery>,
+    quality: web::Query<QualityQuery>,
+    format: ListFormat,
+    languages: Languages,
+) -> Result<HttpResponse, ServerError> {
+    let viewer = get_viewer(&db, auth).await?;
+    let author_id = author_id.into_inner();
+    let key = viewer_key(
+        quality.key(&format!("/persons/{}/authored-works", author_id)),
+        viewer.as_ref(),
+    );
+
+    let data = cached(&cache, key, move || {
+        let conn = db.into_inner().get()?;
+        let works = database::get_works_by_author(&conn, &author_id, viewer.as_ref())?;
+        quality.filter(&conn, EntityType::Work, &works)
+    })
+    .await?;
+
+    let data = languages.localize(&period.apply(&data));
+
+    match format {
+        ListFormat::Json => Ok(HttpResponse::Ok()
+            .header(header::VARY, "Accept-Language")
+            .json(query.apply(&data)?)),
+        ListFormat::Csv => Ok(csv_response(&data, WORK_COLUMNS)),
+    }
+}
+
+/// Get everything that would be affected by deleting a work using the "cascade" option.
+#[get("/works/{id}/delete-preview")]
+pub async fn get_work_deletion_preview(
+    auth: Option<BearerAuth>,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let data = database::block(move || {
+        let conn = db.into_inner().get()?;
+        let viewer = authenticate_viewer(&conn, auth.as_ref())?;
+        let id = id.into_inner();
+
+        check_visible(&conn, EntityType::Work, &id, viewer.as_ref())?;
+        database::get_deletion_preview(&conn, EntityType::Work, &id)?.ok_or(ServerError::NotFound)
     })
     .await?;
 
     Ok(HttpResponse::Ok().json(data))
 }
 
+/// Delete an existing work. If the "cascade" query parameter is set, everything that refers to the
+/// work is deleted as well. Only administrators may do that.
 #[delete("/works/{id}")]
 pub async fn delete_work(
     auth: BearerAuth,
     db: web::Data<DbPool>,
     id: web::Path<String>,
+    query: web::Query<DeleteQuery>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteWorks)?;
+
+        if query.cascade {
+            database::delete_cascading(&conn, EntityType::Work, &id, &user)?;
+        } else {
+            database::delete_work(&conn, &id, &user)?;
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Lock an existing work, so that only editors can change it. The user must be an editor.
+#[post("/works/{id}/lock")]
+pub async fn lock_work(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    database::block(move || {
+        let conn = db.into_inner().get()?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteWorks)?;
+
+        database::set_work_locked(&conn, &id.into_inner(), true, &user)?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Unlock an existing work. The user must be an editor.
+#[delete("/works/{id}/lock")]
+pub async fn unlock_work(
+    auth: BearerAuth,
+    db: web::Data<DbPool>,
+    id: web::Path<String>,
 ) -> Result<HttpResponse, ServerError> {
-    web::block(move || {
+    database::block(move || {
         let conn = db.into_inner().get()?;
-        let user = authenticate(&conn, auth.token()).or(Err(ServerError::Unauthorized))?;
+        let user = authenticate(&conn, auth.token(), Scope::WriteWorks)?;
 
-        database::delete_work(&conn, &id.into_inner(), &user)?;
+        database::set_work_locked(&conn, &id.into_inner(), false, &user)?;
 
         Ok(())
     })
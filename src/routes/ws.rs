@@ -0,0 +1,130 @@
+use super::authenticate;
+use crate::database;
+use crate::database::{DbPool, Scope};
+use crate::error::ServerError;
+use crate::presence::{ClientMessage, Hub};
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{handshake, Codec, Frame, Message};
+use actix_web::web::BytesMut;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use futures::channel::mpsc;
+use futures::StreamExt;
+use serde::Deserialize;
+
+/// Query parameters for opening a WebSocket connection.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WsQuery {
+    /// A token for announcing which entity the user is editing. Browsers can't set headers for
+    /// WebSocket connections, so it has to be passed here. Without a token, the client will only
+    /// receive messages.
+    pub token: Option<String>,
+}
+
+/// Open a WebSocket connection that receives presence information ("user X is editing work Y")
+/// and change notifications. Authenticated clients can announce what they are editing. Clients
+/// are only notified about changes to entities they may see and editing private entities isn't
+/// announced.
+#[get("/ws")]
+pub async fn connect_ws(
+    req: HttpRequest,
+    mut payload: web::Payload,
+    db: web::Data<DbPool>,
+    hub: web::Data<Hub>,
+    query: web::Query<WsQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let mut response = handshake(req.head()).or(Err(ServerError::BadRequest))?;
+
+    let pool = db.get_ref().clone();
+
+    let username = match query.into_inner().token {
+        Some(token) => Some(
+            database::block(move || {
+                let conn = db.into_inner().get()?;
+                let user = authenticate(&conn, &token, Scope::Read)?;
+
+                Ok(user.username)
+            })
+            .await?,
+        ),
+        None => None,
+    };
+
+    let (sender, receiver) = mpsc::unbounded::<Message>();
+    let id = hub.connect(username, sender.clone());
+
+    // Handle incoming frames until the connection is closed.
+    actix_web::rt::spawn(async move {
+        let mut codec = Codec::new();
+        let mut buffer = BytesMut::new();
+
+        'connection: while let Some(Ok(chunk)) = payload.next().await {
+            buffer.extend_from_slice(&chunk);
+
+            loop {
+                match codec.decode(&mut buffer) {
+                    Ok(Some(Frame::Text(text))) => {
+                        if let Ok(message) = serde_json::from_slice::<ClientMessage>(&text) {
+                            match announced(&pool, message).await {
+                                Ok(message) => hub.handle(id, message),
+                                Err(error) => println!("{:?}", error),
+                            }
+                        }
+                    }
+                    Ok(Some(Frame::Ping(message))) => {
+                        let _ = sender.unbounded_send(Message::Pong(message));
+                    }
+                    Ok(Some(Frame::Close(reason))) => {
+                        let _ = sender.unbounded_send(Message::Close(reason));
+                        break 'connection;
+                    }
+                    Ok(Some(_)) => (),
+                    Ok(None) => break,
+                    Err(_) => break 'connection,
+                }
+            }
+        }
+
+        hub.disconnect(id);
+        sender.close_channel();
+    });
+
+    // Encode outgoing messages as WebSocket frames.
+    let mut codec = Codec::new();
+    let stream = receiver.map(move |message| {
+        let mut buffer = BytesMut::new();
+        codec
+            .encode(message, &mut buffer)
+            .or(Err(ServerError::Internal))?;
+
+        Ok::<_, ServerError>(buffer.freeze())
+    });
+
+    Ok(response.streaming(stream))
+}
+
+/// Get the message to pass on for a message by a client. Editing a private entity is handled like
+/// stopping to edit, so that other users don't learn about the entity.
+async fn announced(pool: &DbPool, message: ClientMessage) -> Result<ClientMessage, ServerError> {
+    let entity = match message {
+        ClientMessage::Editing(entity) => entity,
+        ClientMessage::Stopped => return Ok(message),
+    };
+
+    let pool = pool.clone();
+    let entity_type = entity.entity_type;
+    let entity_id = entity.entity_id.clone();
+
+    let private = database::block(move || {
+        let conn = pool.get()?;
+        let visibility = database::get_visibility(&conn, entity_type, &entity_id)?;
+
+        Ok::<_, ServerError>(visibility.is_some_and(|visibility| visibility.private))
+    })
+    .await?;
+
+    if private {
+        Ok(ClientMessage::Stopped)
+    } else {
+        Ok(ClientMessage::Editing(entity))
+    }
+}
@@ -0,0 +1,178 @@
+use crate::shutdown::Shutdown;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use std::time::Duration;
+
+/// A cron-like schedule consisting of five fields: minute, hour, day of month, month and day of
+/// week (0 or 7 is Sunday). Each field can be "*", a number, a range like "1-5", a list like
+/// "1,15" or any of these with a step like "*/10". All times are in UTC.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    /// Parse a cron expression.
+    pub fn parse(expression: &str) -> Result<Schedule> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(anyhow!("Expected five fields in \"{}\"!", expression));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+
+        Ok(Schedule {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    /// Read a schedule from an environment variable. If it is not set, the default expression is
+    /// used. The value "off" disables the task, in which case this returns [`None`].
+    pub fn from_env(name: &str, default: &str) -> Result<Option<Schedule>> {
+        let expression = std::env::var(name).unwrap_or_else(|_| default.to_string());
+
+        if expression == "off" {
+            Ok(None)
+        } else {
+            Schedule::parse(&expression)
+                .map(Some)
+                .map_err(|error| anyhow!("Invalid value for {}: {}", name, error))
+        }
+    }
+
+    /// Check whether the schedule matches the minute of a point in time. Like in cron, a day
+    /// matches if either the day of month or the day of week matches, when both are restricted.
+    pub fn matches(&self, time: &DateTime<Utc>) -> bool {
+        let day = self.days[time.day() as usize];
+        let weekday = self.weekdays[time.weekday().num_days_from_sunday() as usize];
+
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+
+        self.minutes[time.minute() as usize]
+            && self.hours[time.hour() as usize]
+            && self.months[time.month() as usize]
+            && day_matches
+    }
+}
+
+/// Parse one field of a cron expression. The result contains a flag for each value from zero to
+/// `max`.
+fn parse_field(field: &str, min: usize, max: usize) -> Result<Vec<bool>> {
+    let mut values = vec![false; max + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<usize>()?),
+            None => (part, 1),
+        };
+
+        if step == 0 {
+            return Err(anyhow!("Invalid step in \"{}\"!", part));
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else {
+            match range.split_once('-') {
+                Some((start, end)) => (start.parse()?, end.parse()?),
+                None => {
+                    let value = range.parse()?;
+                    (value, if step > 1 { max } else { value })
+                }
+            }
+        };
+
+        if start < min || end > max || start > end {
+            return Err(anyhow!("Value out of range in \"{}\"!", part));
+        }
+
+        for value in (start..=end).step_by(step) {
+            values[value] = true;
+        }
+    }
+
+    Ok(values)
+}
+
+/// A task that is run according to a schedule.
+struct Task {
+    name: String,
+    schedule: Schedule,
+    run: Box<dyn Fn() -> Result<()> + Send>,
+}
+
+/// Runs periodic maintenance tasks in a background thread. The tasks are checked once a minute
+/// and run one after another.
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Vec<Task>,
+}
+
+impl Scheduler {
+    /// Create a new scheduler without any tasks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a task. If no schedule is provided, the task is disabled and won't be added.
+    pub fn add<F>(&mut self, name: &str, schedule: Option<Schedule>, run: F)
+    where
+        F: Fn() -> Result<()> + Send + 'static,
+    {
+        if let Some(schedule) = schedule {
+            self.tasks.push(Task {
+                name: name.to_string(),
+                schedule,
+                run: Box::new(run),
+            });
+        }
+    }
+
+    /// Start running the tasks in a background thread. The thread stops once the server shuts
+    /// down. A task that is running at that time will be finished first.
+    pub fn spawn(self, shutdown: Shutdown) {
+        std::thread::spawn(move || {
+            let mut last_minute = None;
+
+            while let Some(job) = shutdown.start_job() {
+                let now = Utc::now();
+                let minute = now.with_second(0).and_then(|now| now.with_nanosecond(0));
+
+                if minute.is_some() && minute != last_minute {
+                    last_minute = minute;
+
+                    for task in &self.tasks {
+                        if task.schedule.matches(&now) {
+                            if let Err(error) = (task.run)() {
+                                println!("Scheduled task {} failed: {:?}", task.name, error);
+                            }
+                        }
+                    }
+                }
+
+                drop(job);
+
+                let seconds = 60 - Utc::now().second().min(59);
+                shutdown.sleep(Duration::from_secs(seconds as u64));
+            }
+        });
+    }
+}
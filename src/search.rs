@@ -0,0 +1,371 @@
+use crate::database;
+use crate::database::{DbConn, DbPool, EntityType, Recording, Work};
+use crate::shutdown::Shutdown;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// The maximum number of events that are processed at once.
+const BATCH_SIZE: i64 = 100;
+
+/// The maximum number of documents that are sent to the search index at once.
+const DOCUMENT_BATCH_SIZE: usize = 500;
+
+/// The time to wait between checking for new events.
+const INTERVAL: Duration = Duration::from_secs(5);
+
+/// The timeout for a single request to the search index.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The name of the index containing works.
+const WORKS_INDEX: &str = "works";
+
+/// The name of the index containing recordings.
+const RECORDINGS_INDEX: &str = "recordings";
+
+/// The searchable attributes of works, most important first. Matches of popular names like
+/// "Moonlight Sonata" are ranked above matches of the formal titles.
+const WORKS_ATTRIBUTES: &[&str] = &[
+    "nickname", "title", "titles", "composer", "parts", "authors",
+];
+
+/// The searchable attributes of recordings, most important first.
+const RECORDINGS_ATTRIBUTES: &[&str] = &[
+    "nickname",
+    "work",
+    "composer",
+    "performers",
+    "additionalWorks",
+    "comment",
+];
+
+/// The searchable representation of a work.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct WorkDocument {
+    id: String,
+    title: String,
+    nickname: Option<String>,
+    titles: Vec<String>,
+    composer: String,
+    authors: Vec<String>,
+    parts: Vec<String>,
+}
+
+impl From<&Work> for WorkDocument {
+    fn from(work: &Work) -> Self {
+        Self {
+            id: work.id.clone(),
+            title: work.title.clone(),
+            nickname: work.nickname.clone(),
+            titles: work
+                .titles
+                .iter()
+                .map(|title| title.title.clone())
+                .collect(),
+            composer: work.composer.name_fl(),
+            authors: work
+                .authors
+                .iter()
+                .chain(work.parts.iter().flat_map(|part| &part.authors))
+                .map(|author| author.name_fl())
+                .collect(),
+            parts: work.parts.iter().map(|part| part.title.clone()).collect(),
+        }
+    }
+}
+
+/// The searchable representation of a recording.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RecordingDocument {
+    id: String,
+    work: String,
+    nickname: Option<String>,
+    composer: String,
+    additional_works: Vec<String>,
+    comment: String,
+    performers: Vec<String>,
+}
+
+impl From<&Recording> for RecordingDocument {
+    fn from(recording: &Recording) -> Self {
+        Self {
+            id: recording.id.clone(),
+            work: recording.work.title.clone(),
+            nickname: recording.work.nickname.clone(),
+            composer: recording.work.composer.name_fl(),
+            additional_works: recording
+                .additional_works
+                .iter()
+                .map(|work| format!("{} {}", work.composer.name_fl(), work.title))
+                .collect(),
+            comment: recording.comment.clone(),
+            performers: recording
+                .performances
+                .iter()
+                .map(|performance| performance.label())
+                .collect(),
+        }
+    }
+}
+
+/// A search result as returned by the search index. Only the ID is used.
+#[derive(Deserialize, Debug, Clone)]
+struct Hit {
+    id: String,
+}
+
+/// The response to a search request.
+#[derive(Deserialize, Debug, Clone)]
+struct SearchResponse {
+    hits: Vec<Hit>,
+}
+
+/// A Meilisearch server that contains searchable representations of works and recordings.
+#[derive(Clone)]
+pub struct SearchIndex {
+    url: String,
+    key: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl SearchIndex {
+    /// Create a search index, if the environment variable "WOLFGANG_MEILISEARCH_URL" is set.
+    /// The API key can be provided using "WOLFGANG_MEILISEARCH_KEY".
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("WOLFGANG_MEILISEARCH_URL").ok()?;
+
+        Some(Self {
+            url: url.trim_end_matches('/').to_string(),
+            key: std::env::var("WOLFGANG_MEILISEARCH_KEY").ok(),
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+        })
+    }
+
+    /// Get the IDs of the works matching the query, best matches first.
+    pub fn search_works(&self, query: &str, limit: i64) -> Result<Vec<String>> {
+        self.search(WORKS_INDEX, query, limit)
+    }
+
+    /// Get the IDs of the recordings matching the query, best matches first.
+    pub fn search_recordings(&self, query: &str, limit: i64) -> Result<Vec<String>> {
+        self.search(RECORDINGS_INDEX, query, limit)
+    }
+
+    /// Get the IDs of the documents within an index matching the query.
+    fn search(&self, index: &str, query: &str, limit: i64) -> Result<Vec<String>> {
+        let response: SearchResponse = self
+            .request("POST", &format!("/indexes/{}/search", index))
+            .send_json(json!({
+                "q": query,
+                "limit": limit,
+                "attributesToRetrieve": ["id"],
+            }))?
+            .into_json()?;
+
+        Ok(response.hits.into_iter().map(|hit| hit.id).collect())
+    }
+
+    /// Add documents to an index or replace existing ones with the same ID.
+    fn add_documents<T: Serialize>(&self, index: &str, documents: &[T]) -> Result<()> {
+        for chunk in documents.chunks(DOCUMENT_BATCH_SIZE) {
+            self.request(
+                "POST",
+                &format!("/indexes/{}/documents?primaryKey=id", index),
+            )
+            .send_json(serde_json::to_value(chunk)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set which attributes of the documents within an index are searched and how they are
+    /// ranked.
+    fn set_searchable_attributes(&self, index: &str, attributes: &[&str]) -> Result<()> {
+        self.request(
+            "PUT",
+            &format!("/indexes/{}/settings/searchable-attributes", index),
+        )
+        .send_json(json!(attributes))?;
+
+        Ok(())
+    }
+
+    /// Remove documents from an index.
+    fn delete_documents(&self, index: &str, ids: &[String]) -> Result<()> {
+        for chunk in ids.chunks(DOCUMENT_BATCH_SIZE) {
+            self.request(
+                "POST",
+                &format!("/indexes/{}/documents/delete-batch", index),
+            )
+            .send_json(serde_json::to_value(chunk)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove all documents from an index.
+    fn clear_documents(&self, index: &str) -> Result<()> {
+        self.request("DELETE", &format!("/indexes/{}/documents", index))
+            .call()?;
+
+        Ok(())
+    }
+
+    /// Create a request to the search server.
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self.agent.request(method, &format!("{}{}", self.url, path));
+
+        match &self.key {
+            Some(key) => request.set("Authorization", &format!("Bearer {}", key)),
+            None => request,
+        }
+    }
+}
+
+/// Start keeping the search index up to date in a background thread. At first, all works and
+/// recordings are added. Afterwards, the documents that are affected by new events are updated.
+/// The thread stops once the server shuts down.
+pub fn spawn(index: SearchIndex, pool: DbPool, shutdown: Shutdown) {
+    std::thread::spawn(move || {
+        let mut last = None;
+
+        while let Some(job) = shutdown.start_job() {
+            if let Err(error) = update_index(&index, &pool, &mut last) {
+                println!("{:?}", error);
+            }
+
+            drop(job);
+            shutdown.sleep(INTERVAL);
+        }
+    });
+}
+
+/// Configure the search index and replace all documents with the ones for the current works and
+/// recordings. `progress` is called with the number of finished and of all steps.
+pub fn rebuild(
+    index: &SearchIndex,
+    conn: &DbConn,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<()> {
+    index.set_searchable_attributes(WORKS_INDEX, WORKS_ATTRIBUTES)?;
+    index.set_searchable_attributes(RECORDINGS_INDEX, RECORDINGS_ATTRIBUTES)?;
+    progress(1, 3);
+
+    // The search server processes the changes to an index in order, so the old documents are
+    // removed before the new ones are added.
+    let works = database::get_all_works(conn, None)?;
+    let documents: Vec<WorkDocument> = works.iter().map(WorkDocument::from).collect();
+    index.clear_documents(WORKS_INDEX)?;
+    index.add_documents(WORKS_INDEX, &documents)?;
+    progress(2, 3);
+
+    let recordings = database::get_all_recordings(conn, None)?;
+    let documents: Vec<RecordingDocument> =
+        recordings.iter().map(RecordingDocument::from).collect();
+    index.clear_documents(RECORDINGS_INDEX)?;
+    index.add_documents(RECORDINGS_INDEX, &documents)?;
+    progress(3, 3);
+
+    Ok(())
+}
+
+/// Update all documents that are affected by events after the last known one. If there is no
+/// known event yet, all documents will be added.
+fn update_index(index: &SearchIndex, pool: &DbPool, last: &mut Option<i64>) -> Result<()> {
+    let conn = pool.get()?;
+
+    let after = match last {
+        Some(after) => *after,
+        None => {
+            // Changes that happen while adding the documents will be handled afterwards.
+            let after = database::get_last_event_id(&conn)?;
+
+            rebuild(index, &conn, &mut |_, _| ())?;
+
+            *last = Some(after);
+            return Ok(());
+        }
+    };
+
+    let events = database::get_events_after(&conn, after, BATCH_SIZE)?;
+
+    let mut works = BTreeSet::new();
+    let mut recordings = BTreeSet::new();
+
+    for event in &events {
+        match event.entity_type {
+            EntityType::Person => {
+                for work in database::get_works(&conn, &event.entity_id, None)? {
+                    works.insert(work.id);
+                }
+
+                for work in database::get_works_by_author(&conn, &event.entity_id, None)? {
+                    works.insert(work.id);
+                }
+            }
+            EntityType::Work => {
+                works.insert(event.entity_id.clone());
+            }
+            _ => (),
+        }
+
+        recordings.extend(database::get_dependent_recordings(
+            &conn,
+            event.entity_type,
+            &event.entity_id,
+        )?);
+    }
+
+    update_works(index, &conn, works)?;
+    update_recordings(index, &conn, recordings)?;
+
+    if let Some(event) = events.last() {
+        *last = Some(event.id);
+    }
+
+    Ok(())
+}
+
+/// Update the documents for the provided works. Works that don't exist anymore or are private are
+/// removed.
+fn update_works(index: &SearchIndex, conn: &DbConn, ids: BTreeSet<String>) -> Result<()> {
+    let mut documents = Vec::new();
+    let mut deleted = Vec::new();
+
+    for id in ids {
+        match database::get_work(conn, &id)? {
+            Some(work) if !work.private => documents.push(WorkDocument::from(&work)),
+            _ => deleted.push(id),
+        }
+    }
+
+    index.add_documents(WORKS_INDEX, &documents)?;
+    index.delete_documents(WORKS_INDEX, &deleted)?;
+
+    Ok(())
+}
+
+/// Update the documents for the provided recordings. Recordings that don't exist anymore or are
+/// private are removed.
+fn update_recordings(index: &SearchIndex, conn: &DbConn, ids: BTreeSet<String>) -> Result<()> {
+    let mut documents = Vec::new();
+    let mut deleted = Vec::new();
+
+    for id in ids {
+        match database::get_recording(conn, &id)? {
+            Some(recording) if !recording.private => {
+                documents.push(RecordingDocument::from(&recording))
+            }
+            _ => deleted.push(id),
+        }
+    }
+
+    index.add_documents(RECORDINGS_INDEX, &documents)?;
+    index.delete_documents(RECORDINGS_INDEX, &deleted)?;
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anyhow::Result;
+
+/// A pool of connections to a Redis server.
+pub type RedisPool = r2d2::Pool<redis::Client>;
+
+/// Where state that is not stored in the database is kept.
+#[derive(Clone)]
+pub enum SharedState {
+    /// Keep the state in the memory of this process. This only works, if there is exactly one
+    /// instance of the server.
+    Local,
+
+    /// Keep the state in a Redis server, so that it is shared between multiple instances of the
+    /// server.
+    Redis(RedisPool),
+}
+
+impl SharedState {
+    /// Select the backend for shared state. If the environment variable "WOLFGANG_REDIS_URL" is
+    /// set, the Redis server at that URL will be used. Otherwise, the state is kept locally.
+    pub fn from_env() -> Result<Self> {
+        let state = match std::env::var("WOLFGANG_REDIS_URL") {
+            Ok(url) => {
+                let client = redis::Client::open(url)?;
+                SharedState::Redis(r2d2::Pool::new(client)?)
+            }
+            Err(_) => SharedState::Local,
+        };
+
+        Ok(state)
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// The default time to wait for running work when shutting down.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Read the time to wait for running requests and background jobs when shutting down from the
+/// environment variable "WOLFGANG_SHUTDOWN_TIMEOUT" in seconds.
+pub fn timeout_from_env() -> Result<Duration> {
+    let timeout = match std::env::var("WOLFGANG_SHUTDOWN_TIMEOUT") {
+        Ok(seconds) => Duration::from_secs(seconds.parse()?),
+        Err(_) => DEFAULT_TIMEOUT,
+    };
+
+    Ok(timeout)
+}
+
+/// The state shared between all handles.
+#[derive(Default)]
+struct State {
+    /// Whether the server is shutting down.
+    stopping: bool,
+
+    /// The number of jobs that are currently running.
+    jobs: usize,
+}
+
+/// A handle for coordinating the shutdown of background threads. Threads register each run of
+/// their job, so that the shutdown can wait for it to finish instead of interrupting it.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl Shutdown {
+    /// Create a new handle for a server that is running.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a job that is about to be run. The job is considered finished once the returned
+    /// guard is dropped. If the server is shutting down, no new job may be started and `None` is
+    /// returned.
+    pub fn start_job(&self) -> Option<Job> {
+        let (state, _) = &*self.inner;
+        let mut state = state.lock().unwrap();
+
+        if state.stopping {
+            None
+        } else {
+            state.jobs += 1;
+
+            Some(Job {
+                shutdown: self.clone(),
+            })
+        }
+    }
+
+    /// Wait for the provided duration. This returns early, if the server starts shutting down.
+    pub fn sleep(&self, duration: Duration) {
+        let (state, condvar) = &*self.inner;
+        let state = state.lock().unwrap();
+
+        let _ = condvar
+            .wait_timeout_while(state, duration, |state| !state.stopping)
+            .unwrap();
+    }
+
+    /// Prevent new jobs from starting and wait for running jobs to finish, but no longer than the
+    /// provided timeout. Returns the number of jobs that are still running.
+    pub fn stop(&self, timeout: Duration) -> usize {
+        let (state, condvar) = &*self.inner;
+        let mut state = state.lock().unwrap();
+
+        state.stopping = true;
+        condvar.notify_all();
+
+        let deadline = Instant::now() + timeout;
+
+        while state.jobs > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            state = condvar.wait_timeout(state, deadline - now).unwrap().0;
+        }
+
+        state.jobs
+    }
+}
+
+/// A running job, see [`Shutdown::start_job`].
+pub struct Job {
+    shutdown: Shutdown,
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        let (state, condvar) = &*self.shutdown.inner;
+        state.lock().unwrap().jobs -= 1;
+        condvar.notify_all();
+    }
+}
@@ -0,0 +1,229 @@
+use crate::captcha::CaptchaBackend;
+use crate::database;
+use crate::database::{DbPool, NotificationKind};
+use crate::images::ImageStore;
+use crate::mail;
+use crate::publishing::DumpPublisher;
+use crate::routes::StatisticsCache;
+use crate::scheduler::{Schedule, Scheduler};
+use crate::wikidata;
+use actix_web::web;
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Set up all periodic maintenance tasks. Their schedules can be configured using environment
+/// variables containing cron-like expressions or "off".
+pub fn schedule(
+    pool: DbPool,
+    captchas: web::Data<dyn CaptchaBackend>,
+    statistics: web::Data<StatisticsCache>,
+) -> Result<Scheduler> {
+    let mut scheduler = Scheduler::new();
+
+    // Purge expired captchas, so that unanswered ones don't pile up.
+    scheduler.add(
+        "captchas",
+        Schedule::from_env("WOLFGANG_SCHEDULE_CAPTCHAS", "* * * * *")?,
+        move || captchas.purge(),
+    );
+
+    let cleanup_pool = pool.clone();
+    scheduler.add(
+        "cleanup",
+        Schedule::from_env("WOLFGANG_SCHEDULE_CLEANUP", "0 * * * *")?,
+        move || {
+            let conn = cleanup_pool.get()?;
+            database::delete_expired_email_changes(&conn)?;
+            database::delete_expired_idempotency_keys(&conn)?;
+            database::purge_expired_trash(&conn)?;
+            database::delete_orphans(&conn)?;
+            Ok(())
+        },
+    );
+
+    let statistics_pool = pool.clone();
+    scheduler.add(
+        "statistics",
+        Schedule::from_env("WOLFGANG_SCHEDULE_STATISTICS", "*/10 * * * *")?,
+        move || {
+            let conn = statistics_pool.get()?;
+            let data = database::compute_statistics(&conn)?;
+            *statistics.write().unwrap() = Some(data);
+            Ok(())
+        },
+    );
+
+    // Searching persons on Wikidata sends their names to a third party, so it is off by default.
+    let wikidata_pool = pool.clone();
+    scheduler.add(
+        "wikidata",
+        Schedule::from_env("WOLFGANG_SCHEDULE_WIKIDATA", "off")?,
+        move || {
+            wikidata::reconcile(&wikidata_pool, wikidata::BATCH_SIZE)?;
+            Ok(())
+        },
+    );
+
+    // Mails are only sent if there is a sender address.
+    if std::env::var("WOLFGANG_MAIL_FROM").is_ok() {
+        let mails_pool = pool.clone();
+        scheduler.add(
+            "mails",
+            Schedule::from_env("WOLFGANG_SCHEDULE_MAILS", "* * * * *")?,
+            move || send_notification_mails(&mails_pool),
+        );
+    }
+
+    // Files of replaced or deleted images are only left behind if a request failed halfway.
+    if let Some(store) = ImageStore::from_env() {
+        let images_pool = pool.clone();
+        scheduler.add(
+            "images",
+            Schedule::from_env("WOLFGANG_SCHEDULE_IMAGES", "30 4 * * *")?,
+            move || {
+                let conn = images_pool.get()?;
+                store.purge(&database::get_image_ids(&conn)?)
+            },
+        );
+    }
+
+    // Signed dumps are only published if there is a directory and a key for them.
+    if let Some(publisher) = DumpPublisher::from_env()? {
+        let publish_pool = pool.clone();
+        scheduler.add(
+            "publish",
+            Schedule::from_env("WOLFGANG_SCHEDULE_PUBLISH", "30 3 * * *")?,
+            move || {
+                publisher.publish(&publish_pool)?;
+                Ok(())
+            },
+        );
+    }
+
+    // Dumps are only created if there is a place to store them.
+    if let Ok(path) = std::env::var("WOLFGANG_DUMP_PATH") {
+        scheduler.add(
+            "dump",
+            Schedule::from_env("WOLFGANG_SCHEDULE_DUMP", "0 3 * * *")?,
+            move || write_dump(&pool, &path),
+        );
+    }
+
+    Ok(scheduler)
+}
+
+/// Write a JSON dump of all public data to a file.
+pub fn write_dump(pool: &DbPool, path: &str) -> Result<()> {
+    let conn = pool.get()?;
+    let dump = database::get_dump(&conn)?;
+
+    write_json(path, &dump)
+}
+
+/// Write a complete backup of the database to a file.
+pub fn write_backup(pool: &DbPool, path: &str) -> Result<()> {
+    let conn = pool.get()?;
+    let backup = database::get_backup(&conn)?;
+
+    write_json(path, &backup)
+}
+
+/// Write a complete backup of the database into a directory. Each backup gets its own file named
+/// after the current time. Returns the path of the new file.
+pub fn write_backup_into(pool: &DbPool, directory: &str) -> Result<String> {
+    let name = format!("wolfgang-{}.json", Utc::now().format("%Y%m%d-%H%M%S"));
+    let path = Path::new(directory)
+        .join(name)
+        .to_string_lossy()
+        .to_string();
+
+    write_backup(pool, &path)?;
+
+    Ok(path)
+}
+
+/// Write data as JSON to a file. The data is written to a temporary file first, so that the
+/// previous file stays available until the new one is complete.
+fn write_json<T: Serialize>(path: &str, data: &T) -> Result<()> {
+    let temp_path = format!("{}.tmp", path);
+    let file = File::create(&temp_path)?;
+    serde_json::to_writer(BufWriter::new(file), data)?;
+    std::fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Read a JSON dump that was written using [`write_dump`].
+pub fn read_dump(path: &str) -> Result<database::Dump> {
+    let file = File::open(path)?;
+    let dump = serde_json::from_reader(BufReader::new(file))?;
+
+    Ok(dump)
+}
+
+/// Read a backup that was written using [`write_backup`].
+pub fn read_backup(path: &str) -> Result<database::Backup> {
+    let file = File::open(path)?;
+    let backup = serde_json::from_reader(BufReader::new(file))?;
+
+    Ok(backup)
+}
+
+/// Send all notifications that should be sent by mail. Each notification is only tried once, so
+/// that a failing address doesn't result in repeated mails to others.
+fn send_notification_mails(pool: &DbPool) -> Result<()> {
+    let conn = pool.get()?;
+    let mails = database::get_pending_mails(&conn)?;
+
+    if mails.is_empty() {
+        return Ok(());
+    }
+
+    let ids: Vec<i64> = mails.iter().map(|mail| mail.notification.id).collect();
+    database::clear_pending_mails(&conn, &ids)?;
+
+    for mail in mails {
+        if let Some(email) = &mail.email {
+            let notification = &mail.notification;
+
+            let (entity_type, id) = match (notification.entity_type, &notification.entity_id) {
+                (Some(entity_type), Some(id)) => (entity_type, id),
+                _ => continue,
+            };
+
+            let entity = match std::env::var("WOLFGANG_PUBLIC_URL") {
+                Ok(url) => format!(
+                    "{}/{}/{}",
+                    url.trim_end_matches('/'),
+                    entity_type.path(),
+                    id
+                ),
+                Err(_) => format!("{} {}", entity_type.as_str(), id),
+            };
+
+            let action = match notification.kind {
+                NotificationKind::EntityDeleted => "deleted",
+                NotificationKind::EntityMerged => "merged",
+                _ => "changed",
+            };
+
+            let body = format!(
+                "Hello {},\n\n{} {} an entity that you are watching:\n\n{}\n",
+                mail.username, notification.created_by, action, entity
+            );
+
+            if let Err(error) = mail::send_mail(email, "A watched entity has changed", &body) {
+                println!(
+                    "Failed to send notification {}: {:?}",
+                    notification.id, error
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::database::{with_query_context, QueryContext};
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures::future::{ok, poll_fn, LocalBoxFuture, Ready};
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    /// Requests taking longer than this are logged with the time spent within the database. This
+    /// is read from the environment variable "WOLFGANG_SLOW_REQUEST_MS". If it is not set, no
+    /// requests are logged.
+    static ref SLOW_REQUEST_THRESHOLD: Option<Duration> = std::env::var("WOLFGANG_SLOW_REQUEST_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .map(Duration::from_millis);
+}
+
+/// Middleware that provides a [`QueryContext`] describing the route and entity ID of each
+/// request, so that slow queries can be attributed to it. Slow requests are logged together with
+/// the number of queries and the time spent running them.
+pub struct Timing;
+
+impl<S> Transform<S> for Timing
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TimingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TimingMiddleware { service })
+    }
+}
+
+/// The service created by [`Timing`].
+pub struct TimingMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service for TimingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let context = Arc::new(QueryContext::new(describe(&req)));
+        let start = Instant::now();
+
+        let service = &mut self.service;
+        let mut future = with_query_context(Some(context.clone()), || Box::pin(service.call(req)));
+
+        // The context has to be provided each time the handler is polled, because other
+        // requests are handled on the same thread in the meantime.
+        let scoped = {
+            let context = context.clone();
+            poll_fn(move |cx| {
+                with_query_context(Some(context.clone()), || future.as_mut().poll(cx))
+            })
+        };
+
+        Box::pin(async move {
+            let res = scoped.await?;
+
+            if let Some(threshold) = *SLOW_REQUEST_THRESHOLD {
+                let duration = start.elapsed();
+
+                if duration >= threshold {
+                    println!(
+                        "Slow request ({} ms, {} ms in {} queries): {}",
+                        duration.as_millis(),
+                        context.duration().as_millis(),
+                        context.queries(),
+                        context.label
+                    );
+                }
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+/// Describe a request using its method, the matched route and the values of its parameters,
+/// e.g. the entity ID.
+fn describe(req: &ServiceRequest) -> String {
+    let route = match req.match_pattern() {
+        Some(route) => route,
+        None => return format!("{} {}", req.method(), req.path()),
+    };
+
+    // Routing happens after this middleware, so the parameters are taken from the path.
+    let parameters: Vec<String> = route
+        .split('/')
+        .zip(req.path().split('/'))
+        .filter(|(segment, _)| segment.starts_with('{'))
+        .map(|(segment, value)| {
+            format!(
+                "{}={}",
+                segment.trim_matches(|c| c == '{' || c == '}'),
+                value
+            )
+        })
+        .collect();
+
+    if parameters.is_empty() {
+        format!("{} {}", req.method(), route)
+    } else {
+        format!("{} {} ({})", req.method(), route, parameters.join(", "))
+    }
+}
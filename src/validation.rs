@@ -0,0 +1,940 @@
+use crate::bootstrap::AdminCreation;
+use crate::database::{
+    BulkChange, Ensemble, ExternalId, ExternalSource, Instrument, Label, Medium, Performance,
+    Period, Person, Play, PlaylistItem, Premiere, Recording, Track, TrackReference, TrackSet, Work,
+    WorkPart, WorkSection, WorkText, WorkTitle,
+};
+use crate::error::ServerError;
+use crate::routes::{
+    AdminSetup, ApiKeyCreation, BulkEditSubmission, CdText, CdTextTrack, CollectionItemSubmission,
+    CommentSubmission, EditorApplicationSubmission, EmailChange, ImageSubmission,
+    MediumRelationSubmission, PasswordChange, PlaylistSubmission, PlaysSubmission, PutUser,
+    RatingSubmission, RelationSubmission, Rename, ReportCommentSubmission, ReportResolution,
+    ReportSubmission, RipReport, SourceSubmission, UserRegistration, WebhookCreation,
+    WorkTextsSubmission,
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// The maximum length of IDs.
+pub const MAX_ID_LENGTH: usize = 64;
+
+/// The maximum length of usernames.
+const MAX_USERNAME_LENGTH: usize = 64;
+
+/// The minimum length of new passwords.
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// The maximum length of names and titles.
+const MAX_NAME_LENGTH: usize = 256;
+
+/// The maximum length of longer texts like comments.
+const MAX_TEXT_LENGTH: usize = 4096;
+
+/// The maximum length of sung texts like librettos.
+const MAX_WORK_TEXT_LENGTH: usize = 1_000_000;
+
+/// The maximum number of items within a list.
+const MAX_ITEMS: usize = 1000;
+
+/// The maximum length of IMSLP page titles in bytes, which is the limit of MediaWiki.
+const MAX_IMSLP_PAGE_LENGTH: usize = 255;
+
+/// A problem with one field of a request body.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    /// The path to the field, e.g. "parts[2].title".
+    pub field: String,
+
+    /// A description of the problem.
+    pub message: String,
+}
+
+/// All problems that were found within a request body.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+/// Collects problems while validating a value.
+#[derive(Default)]
+pub struct Validator {
+    path: String,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    /// Get the full path to a field.
+    fn field(&self, field: &str) -> String {
+        if self.path.is_empty() {
+            field.to_string()
+        } else if field.starts_with('[') {
+            format!("{}{}", self.path, field)
+        } else {
+            format!("{}.{}", self.path, field)
+        }
+    }
+
+    /// Record a problem with a field.
+    pub fn error(&mut self, field: &str, message: &str) {
+        self.errors.push(FieldError {
+            field: self.field(field),
+            message: message.to_string(),
+        });
+    }
+
+    /// Check that an ID only contains letters, digits, dashes and underscores.
+    pub fn check_id(&mut self, field: &str, id: &str) {
+        let valid = !id.is_empty()
+            && id.len() <= MAX_ID_LENGTH
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if !valid {
+            self.error(
+                field,
+                &format!(
+                    "Must consist of 1 to {} letters, digits, dashes or underscores.",
+                    MAX_ID_LENGTH
+                ),
+            );
+        }
+    }
+
+    /// Check that a name is not empty and not too long.
+    pub fn check_name(&mut self, field: &str, name: &str) {
+        if name.trim().is_empty() {
+            self.error(field, "Must not be empty.");
+        } else {
+            self.check_length(field, name, MAX_NAME_LENGTH);
+        }
+    }
+
+    /// Check that a text is not longer than `max` characters.
+    pub fn check_length(&mut self, field: &str, text: &str, max: usize) {
+        if text.chars().count() > max {
+            self.error(
+                field,
+                &format!("Must not be longer than {} characters.", max),
+            );
+        }
+    }
+
+    /// Check that a list doesn't contain too many items.
+    pub fn check_items<T>(&mut self, field: &str, items: &[T]) {
+        if items.len() > MAX_ITEMS {
+            self.error(
+                field,
+                &format!("Must not contain more than {} items.", MAX_ITEMS),
+            );
+        }
+    }
+
+    /// Validate a nested value.
+    pub fn nested<T: Validate>(&mut self, field: &str, value: &T) {
+        let nested_path = self.field(field);
+        let path = std::mem::replace(&mut self.path, nested_path);
+
+        value.validate_with(self);
+        self.path = path;
+    }
+
+    /// Validate all items of a list.
+    pub fn list<T: Validate>(&mut self, field: &str, items: &[T]) {
+        self.check_items(field, items);
+
+        for (index, item) in items.iter().enumerate() {
+            self.nested(&format!("{}[{}]", field, index), item);
+        }
+    }
+}
+
+/// A request body that can be checked before it is used.
+pub trait Validate {
+    /// Check the value and record all problems using the validator.
+    fn validate_with(&self, validator: &mut Validator);
+
+    /// Check the value. If there are problems, this returns an error that results in a response
+    /// listing all of them.
+    fn validate(&self) -> Result<(), ServerError> {
+        let mut validator = Validator::default();
+        self.validate_with(&mut validator);
+
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ServerError::Invalid(ValidationErrors {
+                errors: validator.errors,
+            }))
+        }
+    }
+}
+
+impl Validate for Person {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.check_name("firstName", &self.first_name);
+        v.check_name("lastName", &self.last_name);
+
+        if let Some(period) = &self.period {
+            v.check_id("period", period);
+        }
+    }
+}
+
+impl Validate for Period {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.check_name("name", &self.name);
+
+        if let (Some(start), Some(end)) = (self.start_year, self.end_year) {
+            if end < start {
+                v.error("endYear", "Must not be before the start year.");
+            }
+        }
+    }
+}
+
+impl Validate for Ensemble {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.check_name("name", &self.name);
+    }
+}
+
+impl Validate for Instrument {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.check_name("name", &self.name);
+    }
+}
+
+impl Validate for Label {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.check_name("name", &self.name);
+    }
+}
+
+impl Validate for Work {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.check_name("title", &self.title);
+        check_titles(v, &self.titles);
+
+        if let Some(nickname) = &self.nickname {
+            v.check_name("nickname", nickname);
+        }
+
+        v.nested("composer", &self.composer);
+        v.list("authors", &self.authors);
+        v.list("instruments", &self.instruments);
+        v.list("parts", &self.parts);
+        v.list("sections", &self.sections);
+
+        if let Some(premiere) = &self.premiere {
+            v.nested("premiere", premiere);
+        }
+
+        if let Some(dedication) = &self.dedication {
+            v.check_name("dedication", dedication);
+        }
+
+        if let Some(period) = &self.period {
+            v.check_id("period", period);
+        }
+
+        if let Some(imslp) = &self.imslp {
+            check_imslp_page(v, "imslp", imslp);
+        }
+
+        for (index, section) in self.sections.iter().enumerate() {
+            if section.before_index < 0 || section.before_index as usize > self.parts.len() {
+                v.error(
+                    &format!("sections[{}].beforeIndex", index),
+                    "Must refer to an existing part.",
+                );
+            }
+        }
+    }
+}
+
+impl Validate for WorkTitle {
+    fn validate_with(&self, v: &mut Validator) {
+        check_language(v, "language", &self.language);
+        v.check_name("title", &self.title);
+    }
+}
+
+impl Validate for Premiere {
+    fn validate_with(&self, v: &mut Validator) {
+        if let Some(date) = &self.date {
+            check_partial_date(v, "date", date);
+        }
+
+        if let Some(place) = &self.place {
+            v.check_name("place", place);
+        }
+
+        if let Some(performers) = &self.performers {
+            v.check_name("performers", performers);
+        }
+    }
+}
+
+impl Validate for WorkPart {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_name("title", &self.title);
+        check_titles(v, &self.titles);
+
+        if let Some(key) = &self.key {
+            v.check_name("key", key);
+        }
+
+        if let Some(tempo) = &self.tempo {
+            v.check_name("tempo", tempo);
+        }
+
+        if matches!(self.duration, Some(duration) if duration < 0) {
+            v.error("duration", "Must not be negative.");
+        }
+
+        v.list("authors", &self.authors);
+    }
+}
+
+impl Validate for WorkSection {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_name("title", &self.title);
+        check_titles(v, &self.titles);
+    }
+}
+
+impl Validate for RelationSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("person", &self.person);
+    }
+}
+
+impl Validate for MediumRelationSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("medium", &self.medium);
+    }
+}
+
+impl Validate for WorkTextsSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.list("texts", &self.texts);
+    }
+}
+
+impl Validate for WorkText {
+    fn validate_with(&self, v: &mut Validator) {
+        check_language(v, "language", &self.language);
+        v.check_length("text", &self.text, MAX_WORK_TEXT_LENGTH);
+    }
+}
+
+impl Validate for Recording {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.nested("work", &self.work);
+        v.list("additionalWorks", &self.additional_works);
+
+        if let Some(parts) = &self.parts {
+            if parts.is_empty() {
+                v.error(
+                    "parts",
+                    "Must not be empty. Leave it out for complete recordings.",
+                );
+            } else if parts.iter().any(|part| *part >= self.work.parts.len()) {
+                v.error(
+                    "parts",
+                    "Must refer to existing parts of the recorded work.",
+                );
+            }
+        }
+
+        v.check_length("comment", &self.comment, MAX_TEXT_LENGTH);
+        v.list("performances", &self.performances);
+        check_external_ids(v, "externalIds", &self.external_ids);
+    }
+}
+
+impl Validate for Performance {
+    fn validate_with(&self, v: &mut Validator) {
+        match (&self.person, &self.ensemble) {
+            (Some(person), None) => v.nested("person", person),
+            (None, Some(ensemble)) => v.nested("ensemble", ensemble),
+            _ => v.error("person", "Exactly one of person and ensemble must be set."),
+        }
+
+        if let Some(role) = &self.role {
+            v.nested("role", role);
+        }
+    }
+}
+
+impl Validate for Medium {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.check_name("name", &self.name);
+
+        if let Some(discid) = &self.discid {
+            let valid = discid.len() == 28
+                && discid
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-');
+
+            if !valid {
+                v.error("discid", "Must be a valid MusicBrainz DiscID.");
+            }
+        }
+
+        if let Some(label) = &self.label {
+            v.nested("label", label);
+        }
+
+        check_external_ids(v, "externalIds", &self.external_ids);
+
+        v.list("tracks", &self.tracks);
+    }
+}
+
+impl Validate for TrackSet {
+    fn validate_with(&self, v: &mut Validator) {
+        v.nested("recording", &self.recording);
+        v.list("tracks", &self.tracks);
+
+        for (index, track) in self.tracks.iter().enumerate() {
+            let work = match self.recording.work_at(track.work_index) {
+                Some(work) => work,
+                None => {
+                    v.error(
+                        &format!("tracks[{}].workIndex", index),
+                        "Must refer to a work of the recording.",
+                    );
+                    continue;
+                }
+            };
+
+            let parts = work.parts.len();
+            if track.work_parts.iter().any(|part| *part >= parts) {
+                v.error(
+                    &format!("tracks[{}].workParts", index),
+                    "Must refer to existing parts of the recorded work.",
+                );
+            }
+
+            // Tracks of an excerpt may only contain the parts that were actually recorded.
+            if let (0, Some(recorded)) = (track.work_index, &self.recording.parts) {
+                if track.work_parts.iter().any(|part| !recorded.contains(part)) {
+                    v.error(
+                        &format!("tracks[{}].workParts", index),
+                        "Must refer to parts that are covered by the recording.",
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Validate for ExternalId {
+    fn validate_with(&self, v: &mut Validator) {
+        let (valid, message) = match self.source {
+            ExternalSource::Spotify => {
+                let id = self
+                    .id
+                    .strip_prefix("spotify:album:")
+                    .or_else(|| self.id.strip_prefix("spotify:track:"));
+
+                (
+                    matches!(id, Some(id) if id.len() == 22
+                        && id.chars().all(|c| c.is_ascii_alphanumeric())),
+                    "Must be a Spotify album or track URI.",
+                )
+            }
+            ExternalSource::AppleMusic => (
+                !self.id.is_empty()
+                    && self.id.len() <= 20
+                    && self.id.chars().all(|c| c.is_ascii_digit()),
+                "Must be a numeric Apple Music ID.",
+            ),
+            ExternalSource::Qobuz => (
+                !self.id.is_empty()
+                    && self.id.len() <= MAX_ID_LENGTH
+                    && self
+                        .id
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()),
+                "Must be a Qobuz ID consisting of lowercase letters and digits.",
+            ),
+            ExternalSource::Wikidata => (
+                matches!(self.id.strip_prefix('Q'), Some(number) if !number.is_empty()
+                    && number.len() <= 20
+                    && !number.starts_with('0')
+                    && number.chars().all(|c| c.is_ascii_digit())),
+                "Must be a Wikidata item ID like \"Q254\".",
+            ),
+        };
+
+        if !valid {
+            v.error("id", message);
+        }
+    }
+}
+
+impl Validate for Track {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_items("workParts", &self.work_parts);
+
+        if matches!(self.duration, Some(duration) if duration < 0) {
+            v.error("duration", "Must not be negative.");
+        }
+
+        if let Some(checksum) = &self.flac_md5 {
+            check_checksum(v, "flacMd5", checksum, 32);
+        }
+
+        if let Some(checksum) = &self.accuraterip_v1 {
+            check_checksum(v, "accurateripV1", checksum, 8);
+        }
+
+        if let Some(checksum) = &self.accuraterip_v2 {
+            check_checksum(v, "accurateripV2", checksum, 8);
+        }
+    }
+}
+
+/// Check a list of external IDs for valid and unique items.
+fn check_external_ids(v: &mut Validator, field: &str, external_ids: &[ExternalId]) {
+    v.list(field, external_ids);
+
+    for (index, external_id) in external_ids.iter().enumerate() {
+        if external_ids[..index].contains(external_id) {
+            v.error(
+                &format!("{}[{}]", field, index),
+                "Must not be listed twice.",
+            );
+        }
+    }
+}
+
+/// Check that a checksum consists of the expected number of lowercase hexadecimal digits.
+fn check_checksum(v: &mut Validator, field: &str, checksum: &str, length: usize) {
+    let valid = checksum.len() == length
+        && checksum
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+
+    if !valid {
+        v.error(
+            field,
+            &format!("Must consist of {} lowercase hexadecimal digits.", length),
+        );
+    }
+}
+
+/// Check that a username consists of letters, digits, dots, dashes and underscores.
+fn check_username(v: &mut Validator, field: &str, username: &str) {
+    let valid = !username.is_empty()
+        && username.len() <= MAX_USERNAME_LENGTH
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_');
+
+    if !valid {
+        v.error(
+            field,
+            &format!(
+                "Must consist of 1 to {} letters, digits, dots, dashes or underscores.",
+                MAX_USERNAME_LENGTH
+            ),
+        );
+    }
+}
+
+/// Check that a language code looks like "de" or "en-GB".
+fn check_language(v: &mut Validator, field: &str, language: &str) {
+    let primary = language.split('-').next().unwrap_or_default();
+
+    let valid = (2..=3).contains(&primary.len())
+        && language.split('-').all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+
+    if !valid {
+        v.error(field, "Must be a language code like \"de\" or \"en-GB\".");
+    }
+}
+
+/// Check the titles of a work, a part or a section in different languages. Each language may only
+/// be used once and only one title may be the original one.
+fn check_titles(v: &mut Validator, titles: &[WorkTitle]) {
+    v.list("titles", titles);
+
+    for (index, title) in titles.iter().enumerate() {
+        let duplicate = titles[..index]
+            .iter()
+            .any(|other| other.language.eq_ignore_ascii_case(&title.language));
+
+        if duplicate {
+            v.error(
+                &format!("titles[{}].language", index),
+                "Must not be used for another title.",
+            );
+        }
+    }
+
+    if titles.iter().filter(|title| title.original).count() > 1 {
+        v.error("titles", "Must not contain more than one original title.");
+    }
+}
+
+/// Check that a date looks like "1808-12-22", "1808-12" or "1808" and that it exists.
+fn check_partial_date(v: &mut Validator, field: &str, date: &str) {
+    let valid = match date.len() {
+        4 => date.chars().all(|c| c.is_ascii_digit()),
+        7 => NaiveDate::parse_from_str(&format!("{}-01", date), "%Y-%m-%d").is_ok(),
+        10 => NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok(),
+        _ => false,
+    };
+
+    if !valid {
+        v.error(
+            field,
+            "Must be a date like \"1808-12-22\". The month and day may be left out.",
+        );
+    }
+}
+
+/// Check that a text can be the title of a page on IMSLP. Full URLs are rejected, so that only
+/// links to IMSLP can be created from it.
+fn check_imslp_page(v: &mut Validator, field: &str, page: &str) {
+    let valid = !page.trim().is_empty()
+        && page.len() <= MAX_IMSLP_PAGE_LENGTH
+        && !page.contains("://")
+        && !page
+            .chars()
+            .any(|c| c.is_control() || ['#', '<', '>', '[', ']', '{', '}', '|'].contains(&c));
+
+    if !valid {
+        v.error(field, "Must be the title of an IMSLP page without the URL.");
+    }
+}
+
+/// Check that a new password is long enough.
+fn check_password(v: &mut Validator, field: &str, password: &str) {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        v.error(
+            field,
+            &format!("Must be at least {} characters long.", MIN_PASSWORD_LENGTH),
+        );
+    } else {
+        v.check_length(field, password, MAX_NAME_LENGTH);
+    }
+}
+
+/// Check that an email address looks plausible.
+fn check_email(v: &mut Validator, field: &str, email: &str) {
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => !local.is_empty() && !domain.is_empty(),
+        None => false,
+    };
+
+    if !valid || email.chars().any(char::is_whitespace) {
+        v.error(field, "Must be a valid email address.");
+    } else {
+        v.check_length(field, email, MAX_NAME_LENGTH);
+    }
+}
+
+impl Validate for UserRegistration {
+    fn validate_with(&self, v: &mut Validator) {
+        check_username(v, "username", &self.username);
+        check_password(v, "password", &self.password);
+
+        if let Some(email) = &self.email {
+            check_email(v, "email", email);
+        }
+    }
+}
+
+impl Validate for AdminCreation {
+    fn validate_with(&self, v: &mut Validator) {
+        check_username(v, "username", &self.username);
+        check_password(v, "password", &self.password);
+    }
+}
+
+impl Validate for AdminSetup {
+    fn validate_with(&self, v: &mut Validator) {
+        check_username(v, "username", &self.username);
+        check_password(v, "password", &self.password);
+    }
+}
+
+impl Validate for PutUser {
+    fn validate_with(&self, v: &mut Validator) {
+        if let Some(password) = &self.new_password {
+            check_password(v, "newPassword", password);
+        }
+
+        if let Some(email) = &self.email {
+            check_email(v, "email", email);
+        }
+    }
+}
+
+impl Validate for Rename {
+    fn validate_with(&self, v: &mut Validator) {
+        check_username(v, "username", &self.username);
+    }
+}
+
+impl Validate for PasswordChange {
+    fn validate_with(&self, v: &mut Validator) {
+        check_password(v, "newPassword", &self.new_password);
+    }
+}
+
+impl Validate for EmailChange {
+    fn validate_with(&self, v: &mut Validator) {
+        if let Some(email) = &self.email {
+            check_email(v, "email", email);
+        }
+    }
+}
+
+impl Validate for ApiKeyCreation {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_name("name", &self.name);
+        v.check_items("scopes", &self.scopes);
+    }
+}
+
+impl Validate for ReportSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("entityId", &self.entity_id);
+        v.check_length("reason", &self.reason, MAX_TEXT_LENGTH);
+    }
+}
+
+impl Validate for ReportCommentSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        if self.text.trim().is_empty() {
+            v.error("text", "Must not be empty.");
+        }
+
+        v.check_length("text", &self.text, MAX_TEXT_LENGTH);
+    }
+}
+
+impl Validate for CommentSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        if self.text.trim().is_empty() {
+            v.error("text", "Must not be empty.");
+        }
+
+        v.check_length("text", &self.text, MAX_TEXT_LENGTH);
+    }
+}
+
+impl Validate for ReportResolution {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_length("resolution", &self.resolution, MAX_TEXT_LENGTH);
+    }
+}
+
+impl Validate for CollectionItemSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_length("notes", &self.notes, MAX_TEXT_LENGTH);
+    }
+}
+
+impl Validate for PlaylistSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+        v.check_name("name", &self.name);
+        v.list("items", &self.items);
+    }
+}
+
+impl Validate for PlaylistItem {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("recording", &self.recording);
+
+        if let Some(track) = &self.track {
+            v.nested("track", track);
+        }
+    }
+}
+
+impl Validate for PlaysSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.list("plays", &self.plays);
+    }
+}
+
+impl Validate for Play {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("recording", &self.recording);
+
+        if let Some(track) = &self.track {
+            v.nested("track", track);
+        }
+    }
+}
+
+impl Validate for TrackReference {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("medium", &self.medium);
+
+        if self.index > i32::MAX as usize {
+            v.error("index", "Must refer to an existing track of the medium.");
+        }
+    }
+}
+
+impl Validate for RatingSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        if !(1..=5).contains(&self.stars) {
+            v.error("stars", "Must be between 1 and 5.");
+        }
+
+        if let Some(review) = &self.review {
+            v.check_length("review", review, MAX_TEXT_LENGTH);
+        }
+    }
+}
+
+impl Validate for EditorApplicationSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        if self.motivation.trim().is_empty() {
+            v.error("motivation", "Must not be empty.");
+        }
+
+        v.check_length("motivation", &self.motivation, MAX_TEXT_LENGTH);
+    }
+}
+
+impl Validate for WebhookCreation {
+    fn validate_with(&self, v: &mut Validator) {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            v.error("url", "Must be an HTTP or HTTPS URL.");
+        }
+
+        v.check_length("url", &self.url, MAX_TEXT_LENGTH);
+    }
+}
+
+impl Validate for ImageSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        if self.data.is_empty() {
+            v.error("data", "Must not be empty.");
+        }
+
+        v.check_name("license", &self.license);
+        v.check_name("attribution", &self.attribution);
+
+        if let Some(source) = &self.source {
+            v.check_name("source", source);
+        }
+    }
+}
+
+impl Validate for RipReport {
+    fn validate_with(&self, v: &mut Validator) {
+        let count = self.toc.offsets.len();
+
+        if let Some(durations) = &self.durations {
+            if durations.len() != count {
+                v.error("durations", "Must contain one duration for each track.");
+            } else if durations.iter().any(|duration| *duration <= 0) {
+                v.error("durations", "Must be positive.");
+            }
+        }
+
+        if let Some(cd_text) = &self.cd_text {
+            if cd_text.tracks.len() > count {
+                v.error(
+                    "cdText.tracks",
+                    "Must not contain more tracks than the TOC.",
+                );
+            }
+
+            v.nested("cdText", cd_text);
+        }
+    }
+}
+
+impl Validate for CdText {
+    fn validate_with(&self, v: &mut Validator) {
+        if let Some(title) = &self.title {
+            v.check_length("title", title, MAX_NAME_LENGTH);
+        }
+
+        if let Some(performer) = &self.performer {
+            v.check_length("performer", performer, MAX_NAME_LENGTH);
+        }
+
+        v.list("tracks", &self.tracks);
+    }
+}
+
+impl Validate for CdTextTrack {
+    fn validate_with(&self, v: &mut Validator) {
+        if let Some(title) = &self.title {
+            v.check_length("title", title, MAX_NAME_LENGTH);
+        }
+
+        if let Some(performer) = &self.performer {
+            v.check_length("performer", performer, MAX_NAME_LENGTH);
+        }
+    }
+}
+
+impl Validate for SourceSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_name("description", &self.description);
+
+        if let Some(license) = &self.license {
+            v.check_name("license", license);
+        }
+
+        if let Some(url) = &self.url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                v.error("url", "Must be an HTTP or HTTPS URL.");
+            }
+
+            v.check_length("url", url, MAX_TEXT_LENGTH);
+        }
+    }
+}
+
+impl Validate for BulkEditSubmission {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_name("description", &self.description);
+
+        if self.changes.is_empty() {
+            v.error("changes", "Must not be empty.");
+        }
+
+        v.list("changes", &self.changes);
+    }
+}
+
+impl Validate for BulkChange {
+    fn validate_with(&self, v: &mut Validator) {
+        v.check_id("id", &self.id);
+
+        if !self.field.starts_with('/') {
+            v.error("field", "Must be a JSON pointer to a field of the entity.");
+        } else if self.field == "/id" {
+            v.error("field", "The ID of an entity can't be changed.");
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::database;
+use crate::database::{DbPool, Event, Webhook};
+use crate::shutdown::Shutdown;
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+use std::time::Duration;
+
+/// The maximum number of events that are delivered to a webhook in one go.
+const BATCH_SIZE: i64 = 100;
+
+/// The time to wait between checking for new events.
+const INTERVAL: Duration = Duration::from_secs(5);
+
+/// The timeout for a single delivery.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start delivering events to registered webhooks in a background thread. Each webhook receives
+/// the events in order. Events about private entities are skipped. If a delivery fails, it will be retried later and newer events will be
+/// held back until then. The thread stops once the server shuts down.
+pub fn spawn(pool: DbPool, shutdown: Shutdown) {
+    std::thread::spawn(move || {
+        let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+
+        while let Some(job) = shutdown.start_job() {
+            if let Err(error) = deliver_due(&pool, &agent) {
+                println!("{:?}", error);
+            }
+
+            drop(job);
+            shutdown.sleep(INTERVAL);
+        }
+    });
+}
+
+/// Deliver new events to all webhooks that are due.
+fn deliver_due(pool: &DbPool, agent: &ureq::Agent) -> Result<()> {
+    let conn = pool.get()?;
+
+    for (webhook, secret) in database::get_due_webhooks(&conn)? {
+        let mut last_event = webhook.last_event;
+        let mut failed = false;
+
+        for event in database::get_events_after(&conn, last_event, BATCH_SIZE)? {
+            // Webhooks are not bound to a user, so they only get changes to public entities.
+            if webhook.matches(&event)
+                && database::is_change_visible(&conn, event.entity_type, &event.entity_id, None)?
+            {
+                if let Err(error) = deliver(agent, &webhook, &secret, &event) {
+                    println!(
+                        "Failed to deliver event {} to webhook {}: {:?}",
+                        event.id, webhook.id, error
+                    );
+
+                    failed = true;
+                    break;
+                }
+            }
+
+            last_event = event.id;
+        }
+
+        let progressed = last_event != webhook.last_event;
+        if progressed {
+            database::set_webhook_delivered(&conn, &webhook.id, last_event)?;
+        }
+
+        if failed {
+            let failures = if progressed { 1 } else { webhook.failures + 1 };
+            database::set_webhook_failed(&conn, &webhook.id, failures)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Send one event to a webhook. The JSON payload is signed using HMAC-SHA256 with the webhook's
+/// secret. The hexadecimal signature is sent within the "X-Wolfgang-Signature" header.
+fn deliver(agent: &ureq::Agent, webhook: &Webhook, secret: &str, event: &Event) -> Result<()> {
+    let payload = serde_json::to_string(event)?;
+    let signature = sign(secret, &payload)?;
+
+    agent
+        .post(&webhook.url)
+        .set("Content-Type", "application/json")
+        .set("X-Wolfgang-Event", &event.id.to_string())
+        .set("X-Wolfgang-Signature", &format!("sha256={}", signature))
+        .send_string(&payload)?;
+
+    Ok(())
+}
+
+/// Compute the hexadecimal HMAC-SHA256 of a payload.
+fn sign(secret: &str, payload: &str) -> Result<String> {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).map_err(|_| anyhow!("Invalid key!"))?;
+    mac.update(payload.as_bytes());
+
+    Ok(format!("{:x}", mac.finalize().into_bytes()))
+}
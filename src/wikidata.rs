@@ -0,0 +1,210 @@
+use crate::database;
+use crate::database::{DbConn, DbPool, Period, Person, WikidataItem};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// The endpoint of the Wikidata API.
+const API_URL: &str = "https://www.wikidata.org/w/api.php";
+
+/// The maximum number of persons that are searched at once by default.
+pub const BATCH_SIZE: i64 = 50;
+
+/// The maximum number of items to consider for each person.
+const SEARCH_LIMIT: usize = 10;
+
+/// The timeout for a single request to Wikidata.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The item for humans, which all candidates have to be an instance of.
+const HUMAN: &str = "Q5";
+
+/// The property linking an item to the class it is an instance of.
+const INSTANCE_OF: &str = "P31";
+
+/// The property for the date of birth.
+const DATE_OF_BIRTH: &str = "P569";
+
+/// The property for the date of death.
+const DATE_OF_DEATH: &str = "P570";
+
+/// The lifespan in years that is assumed, if only one of the life dates is known.
+const MAX_AGE: i32 = 100;
+
+/// Response body data summarizing a run of the reconciliation.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciliationSummary {
+    /// The number of persons that were searched.
+    pub persons: usize,
+
+    /// The number of candidates that were found for them.
+    pub candidates: usize,
+}
+
+/// One result of searching items by name.
+#[derive(Deserialize, Debug, Clone)]
+struct SearchResult {
+    id: String,
+    #[serde(default)]
+    label: String,
+    description: Option<String>,
+}
+
+/// Response body data of the search.
+#[derive(Deserialize, Debug, Clone)]
+struct SearchResponse {
+    #[serde(default)]
+    search: Vec<SearchResult>,
+}
+
+/// Search Wikidata for up to `limit` persons that don't have external IDs yet and store the
+/// humans with matching names and plausible life dates as candidates. Nothing is linked
+/// automatically, an editor has to confirm each candidate.
+pub fn reconcile(pool: &DbPool, limit: i64) -> Result<ReconciliationSummary> {
+    let conn = pool.get()?;
+    let agent = ureq::AgentBuilder::new()
+        .timeout(TIMEOUT)
+        .user_agent(&format!("wolfgang/{}", env!("CARGO_PKG_VERSION")))
+        .build();
+
+    let mut summary = ReconciliationSummary {
+        persons: 0,
+        candidates: 0,
+    };
+
+    for person in database::get_unreconciled_persons(&conn, limit)? {
+        let items = find_candidates(&agent, &conn, &person)?;
+        database::save_wikidata_candidates(&conn, &person.id, &items)?;
+
+        summary.persons += 1;
+        summary.candidates += items.len();
+    }
+
+    Ok(summary)
+}
+
+/// Find all humans on Wikidata that might be the person.
+fn find_candidates(
+    agent: &ureq::Agent,
+    conn: &DbConn,
+    person: &Person,
+) -> Result<Vec<WikidataItem>> {
+    let response: SearchResponse = agent
+        .get(API_URL)
+        .query("action", "wbsearchentities")
+        .query("search", &person.name_fl())
+        .query("language", "en")
+        .query("type", "item")
+        .query("limit", &SEARCH_LIMIT.to_string())
+        .query("format", "json")
+        .call()?
+        .into_json()?;
+
+    if response.search.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let ids: Vec<&str> = response
+        .search
+        .iter()
+        .map(|result| result.id.as_str())
+        .collect();
+
+    let entities: Value = agent
+        .get(API_URL)
+        .query("action", "wbgetentities")
+        .query("ids", &ids.join("|"))
+        .query("props", "claims")
+        .query("format", "json")
+        .call()?
+        .into_json()?;
+
+    let period = match &person.period {
+        Some(period) => database::get_period(conn, period)?,
+        None => None,
+    };
+
+    let mut items = Vec::new();
+
+    for result in response.search {
+        let entity = match entities.get("entities").and_then(|e| e.get(&result.id)) {
+            Some(entity) => entity,
+            None => continue,
+        };
+
+        let is_human = get_claims(entity, INSTANCE_OF)
+            .iter()
+            .any(|value| value.get("id").and_then(Value::as_str) == Some(HUMAN));
+
+        if !is_human {
+            continue;
+        }
+
+        let item = WikidataItem {
+            label: if result.label.is_empty() {
+                result.id.clone()
+            } else {
+                result.label
+            },
+            id: result.id,
+            description: result.description,
+            birth_year: get_year(entity, DATE_OF_BIRTH),
+            death_year: get_year(entity, DATE_OF_DEATH),
+        };
+
+        if is_plausible(&item, period.as_ref()) {
+            items.push(item);
+        }
+    }
+
+    Ok(items)
+}
+
+/// Check whether somebody with the life dates of an item could belong to a period. Items
+/// without life dates and persons without a period are always plausible.
+fn is_plausible(item: &WikidataItem, period: Option<&Period>) -> bool {
+    let period = match period {
+        Some(period) => period,
+        None => return true,
+    };
+
+    let (birth, death) = match (item.birth_year, item.death_year) {
+        (Some(birth), Some(death)) => (birth, death),
+        (Some(birth), None) => (birth, birth + MAX_AGE),
+        (None, Some(death)) => (death - MAX_AGE, death),
+        (None, None) => return true,
+    };
+
+    period.start_year.is_none_or(|start| death >= start)
+        && period.end_year.is_none_or(|end| birth <= end)
+}
+
+/// Get the values of all statements of an entity for a property.
+fn get_claims<'a>(entity: &'a Value, property: &str) -> Vec<&'a Value> {
+    match entity.pointer(&format!("/claims/{}", property)) {
+        Some(Value::Array(claims)) => claims
+            .iter()
+            .filter_map(|claim| claim.pointer("/mainsnak/datavalue/value"))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Get the year of the first date of an entity for a property. Wikidata stores dates like
+/// "+1756-01-27T00:00:00Z" with negative years before the common era.
+fn get_year(entity: &Value, property: &str) -> Option<i32> {
+    let time = get_claims(entity, property)
+        .into_iter()
+        .find_map(|value| value.get("time").and_then(Value::as_str))?;
+
+    let (sign, date) = match time.strip_prefix('-') {
+        Some(date) => (-1, date),
+        None => (1, time.strip_prefix('+').unwrap_or(time)),
+    };
+
+    let year: i32 = date.split('-').next()?.parse().ok()?;
+
+    Some(sign * year)
+}